@@ -14,7 +14,9 @@ use super::parsing::{parse_command_with_quotes, find_last_redirect, split_on_cha
 
 #[path = "execution/streaming.rs"]
 mod streaming;
-pub use streaming::{execute_command_streaming, execute_command_streaming_with_timeout};
+pub use streaming::{
+    execute_command_streaming, execute_command_streaming_with_timeout, run_command_streaming, CommandOutcome,
+};
 
 #[path = "execution/pty.rs"]
 mod pty;
@@ -15,16 +15,48 @@ pub fn execute_command_streaming_with_timeout(
     timeout_override: Option<u64>,
     on_line: &mut dyn FnMut(&str),
 ) -> String {
+    run_command_streaming(cmd, cancel, timeout_override, on_line).output
+}
+
+/// Output of a streamed command, plus whether it ran to a zero exit status.
+pub struct CommandOutcome {
+    pub output: String,
+    /// False when the command exited non-zero, was cancelled, was killed by a
+    /// timeout, or could not be spawned.
+    pub success: bool,
+}
+
+impl CommandOutcome {
+    fn failed(output: String) -> Self {
+        Self { output, success: false }
+    }
+
+    /// `execute_command` and the native echo shortcut report failures as an
+    /// "Error:" line rather than an exit status.
+    fn from_direct(output: String) -> Self {
+        let success = !output.starts_with("Error");
+        Self { output, success }
+    }
+}
+
+/// Like [`execute_command_streaming_with_timeout`], but also reports whether
+/// the command succeeded.
+pub fn run_command_streaming(
+    cmd: &str,
+    cancel: Option<Arc<AtomicBool>>,
+    timeout_override: Option<u64>,
+    on_line: &mut dyn FnMut(&str),
+) -> CommandOutcome {
     let trimmed = cmd.trim();
 
     let parts = parse_command_with_quotes(trimmed);
     if parts.is_empty() {
-        return "Error: Empty command".to_string();
+        return CommandOutcome::failed("Error: Empty command".to_string());
     }
 
     let command_name = &parts[0];
     if command_name == "cd" {
-        return execute_command(cmd);
+        return CommandOutcome::from_direct(execute_command(cmd));
     }
 
     let original_cwd = std::env::current_dir().unwrap_or_default();
@@ -37,7 +69,7 @@ pub fn execute_command_streaming_with_timeout(
 
     if has_shell_ops && !cfg!(target_os = "windows") {
         if let Some(result) = try_native_echo_redirect(trimmed) {
-            return result;
+            return CommandOutcome::from_direct(result);
         }
     }
 
@@ -171,7 +203,7 @@ pub fn execute_command_streaming_with_timeout(
                         ));
                         kill_process_tree(pid);
                         unregister_streaming_process(pid);
-                        return output;
+                        return CommandOutcome::failed(output);
                     }
 
                     match rx.recv_timeout(std::time::Duration::from_millis(POLL_INTERVAL_MS)) {
@@ -235,7 +267,7 @@ pub fn execute_command_streaming_with_timeout(
                         "\n[Command killed after {elapsed}s wall-clock limit]\n"
                     ));
                 }
-                return output;
+                return CommandOutcome::failed(output);
             }
 
             let mut exit_code = -1i32;
@@ -291,8 +323,9 @@ pub fn execute_command_streaming_with_timeout(
             track_cwd_change(trimmed);
             capture_env_from_command(trimmed);
             let annotation = cwd_annotation(&original_cwd).unwrap_or_default();
+            let success = success && !was_cancelled && !inactivity_killed;
 
-            if output.trim().is_empty() {
+            let output = if output.trim().is_empty() {
                 if success {
                     format!("Command executed successfully (no output){annotation}")
                 } else {
@@ -307,16 +340,17 @@ pub fn execute_command_streaming_with_timeout(
                 }
             } else {
                 format!("{output}{annotation}")
-            }
+            };
+            CommandOutcome { output, success }
         }
         Err(e) => {
             if cfg!(target_os = "windows")
                 && !has_shell_ops
                 && e.kind() == std::io::ErrorKind::NotFound
             {
-                execute_command(cmd)
+                CommandOutcome::from_direct(execute_command(cmd))
             } else {
-                format!("Failed to execute command: {e}")
+                CommandOutcome::failed(format!("Failed to execute command: {e}"))
             }
         }
     }
@@ -87,6 +87,17 @@ fn test_streaming_cmd_quoted_paths_raw_arg() {
     assert!(!lines.is_empty(), "Expected streaming lines, got none");
 }
 
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_streaming_reports_the_exit_status() {
+    let ok = run_command_streaming("echo fine", None, None, &mut |_| {});
+    assert!(ok.success, "{}", ok.output);
+    let failed = run_command_streaming("echo Error: not really && exit 3", None, None, &mut |_| {});
+    assert!(!failed.success, "{}", failed.output);
+    let quiet_ok = run_command_streaming("echo Error: only text", None, None, &mut |_| {});
+    assert!(quiet_ok.success, "output text must not decide success");
+}
+
 // All CWD-mutating tests are merged into one sequential test to avoid
 // parallel races on the process-global `env::current_dir()`.
 #[test]
@@ -20,6 +20,8 @@ pub use execution::{
     execute_command_streaming_with_timeout,
     execute_command_pty,
     kill_process_tree,
+    run_command_streaming,
+    CommandOutcome,
 };
 #[cfg(windows)]
 pub use execution::enriched_windows_path;
@@ -5,9 +5,13 @@ extern crate llama_chat_types;
 
 use llama_chat_db::config::DbSamplerConfig;
 use llama_chat_db::Database;
-use llama_chat_types::SamplerConfig;
+use llama_chat_types::models::RecommendedSampling;
+use llama_chat_types::{sampler_preset, unknown_sampler_preset, GenerationOverrides, SamplerConfig};
 use llama_chat_types::TagPair;
 
+mod model_path;
+pub use model_path::{resolve_model_path, ModelTarget, MODEL_NOT_FOUND};
+
 /// Convert DbSamplerConfig to the JSON-serializable SamplerConfig
 pub fn db_config_to_sampler_config(db_config: &DbSamplerConfig) -> SamplerConfig {
     let tag_pairs: Option<Vec<TagPair>> = db_config
@@ -69,7 +73,33 @@ pub fn db_config_to_sampler_config(db_config: &DbSamplerConfig) -> SamplerConfig
         provider_api_keys: db_config.provider_api_keys.clone(),
         max_tool_calls: db_config.max_tool_calls,
         loop_detection_limit: db_config.loop_detection_limit,
+        compress_messages: db_config.compress_messages,
+        allow_external_model_paths: db_config.allow_external_model_paths,
         thinking_mode: db_config.thinking_mode,
+        auto_execute_tools: db_config.auto_execute_tools,
+        temperature_min: db_config.temperature_min,
+        temperature_max: db_config.temperature_max,
+        generation_timeout_secs: db_config.generation_timeout_secs,
+        enabled_tools: db_config.enabled_tools.clone(),
+        normalize_tool_output: db_config.normalize_tool_output,
+        default_context_cap: db_config.default_context_cap,
+        tools_enabled: db_config.tools_enabled,
+        reasoning_tag_open: db_config.reasoning_tag_open.clone(),
+        reasoning_tag_close: db_config.reasoning_tag_close.clone(),
+        require_confirmation_for: db_config.require_confirmation_for.clone(),
+        max_parallel_generations: db_config.max_parallel_generations,
+        sampler_preset: db_config.sampler_preset.clone(),
+        sampler_presets: db_config.sampler_presets.clone(),
+        autosave_interval_ms: db_config.autosave_interval_ms,
+        python_isolated: db_config.python_isolated,
+        python_venv: db_config.python_venv.clone(),
+        max_tool_iterations: db_config.max_tool_iterations,
+        dedupe_tool_calls: db_config.dedupe_tool_calls,
+        max_completions: db_config.max_completions,
+        read_file_max_bytes: db_config.read_file_max_bytes,
+        list_directory_max_entries: db_config.list_directory_max_entries,
+        preload_model: db_config.preload_model.clone(),
+        preload_gpu_layers: db_config.preload_gpu_layers,
     }
 }
 
@@ -135,7 +165,33 @@ pub fn sampler_config_to_db(config: &SamplerConfig) -> DbSamplerConfig {
         provider_api_keys: config.provider_api_keys.clone(),
         max_tool_calls: config.max_tool_calls,
         loop_detection_limit: config.loop_detection_limit,
+        compress_messages: config.compress_messages,
+        allow_external_model_paths: config.allow_external_model_paths,
         thinking_mode: config.thinking_mode,
+        auto_execute_tools: config.auto_execute_tools,
+        temperature_min: config.temperature_min,
+        temperature_max: config.temperature_max,
+        generation_timeout_secs: config.generation_timeout_secs,
+        enabled_tools: config.enabled_tools.clone(),
+        normalize_tool_output: config.normalize_tool_output,
+        default_context_cap: config.default_context_cap,
+        tools_enabled: config.tools_enabled,
+        reasoning_tag_open: config.reasoning_tag_open.clone(),
+        reasoning_tag_close: config.reasoning_tag_close.clone(),
+        require_confirmation_for: config.require_confirmation_for.clone(),
+        max_parallel_generations: config.max_parallel_generations,
+        sampler_preset: config.sampler_preset.clone(),
+        sampler_presets: config.sampler_presets.clone(),
+        autosave_interval_ms: config.autosave_interval_ms,
+        python_isolated: config.python_isolated,
+        python_venv: config.python_venv.clone(),
+        max_tool_iterations: config.max_tool_iterations,
+        dedupe_tool_calls: config.dedupe_tool_calls,
+        max_completions: config.max_completions,
+        read_file_max_bytes: config.read_file_max_bytes,
+        list_directory_max_entries: config.list_directory_max_entries,
+        preload_model: config.preload_model.clone(),
+        preload_gpu_layers: config.preload_gpu_layers,
     }
 }
 
@@ -145,11 +201,107 @@ pub fn load_config(db: &Database) -> SamplerConfig {
     db_config_to_sampler_config(&db_config)
 }
 
-/// Load configuration for a specific conversation.
-/// Resolves agent config + sparse overrides, then falls back to app-level defaults.
+/// Load configuration for a specific conversation, without per-request
+/// overrides or a model's recommended sampling (see `resolve_generation_config`).
 pub fn load_config_for_conversation(db: &Database, conversation_id: &str) -> SamplerConfig {
+    resolve_generation_config(db, conversation_id, None, &GenerationOverrides::default())
+}
+
+/// Config for one generation, each layer winning over the ones before it:
+/// 1. the agent's config, else the app-level one
+/// 2. the model's recommended sampling, unless an agent's saved sampler fields apply
+/// 3. the configured `sampler_preset`, likewise
+/// 4. the sampler settings stored on the conversation
+/// 5. the per-request overrides
+///
+/// Nothing is written back to the database.
+pub fn resolve_generation_config(
+    db: &Database,
+    conversation_id: &str,
+    recommended: Option<&RecommendedSampling>,
+    overrides: &GenerationOverrides,
+) -> SamplerConfig {
     let db_config = db.load_effective_config(conversation_id);
-    db_config_to_sampler_config(&db_config)
+    let mut config = db_config_to_sampler_config(&db_config);
+    // The app-level config stores no sampler fields, so only an agent's are the user's
+    let agent = has_agent(db, conversation_id);
+    if let Some(recommended) = recommended.filter(|_| !agent) {
+        let applied = recommended.apply_to(&mut config);
+        if !applied.is_empty() {
+            log_info!(
+                conversation_id,
+                "Using model-recommended sampling for: {}",
+                applied.join(", ")
+            );
+        }
+    }
+    if let Some(name) = config.sampler_preset.clone() {
+        match sampler_preset(&name, &config.sampler_presets) {
+            Some(_) if agent => {}
+            Some(preset) => preset.params.apply_to(&mut config),
+            None => sys_warn!(
+                "Ignoring configured sampler preset: {}",
+                unknown_sampler_preset(&name, &config.sampler_presets)
+            ),
+        }
+    }
+    if let Ok(Some(stored)) = db.get_conversation_overrides(conversation_id) {
+        stored.apply_to(&mut config);
+    }
+    overrides.apply_to(&mut config);
+    config
+}
+
+/// Whether the conversation runs under an agent, whose sampler fields are
+/// the user's saved choice (the app-level config stores none).
+fn has_agent(db: &Database, conversation_id: &str) -> bool {
+    match db.get_conversation_agent_id(conversation_id) {
+        Ok(Some(agent_id)) => matches!(db.get_agent(&agent_id), Ok(Some(_))),
+        _ => false,
+    }
+}
+
+/// Error for a generation with no model loaded and none configured to load.
+pub const NO_MODEL_CONFIGURED: &str =
+    "No model loaded and no model configured for this conversation";
+
+/// Model a generation would load on its own: the agent's, else the
+/// conversation's, else the app-level one. Blank paths count as none.
+pub fn configured_model_path(
+    db: &Database,
+    conversation_id: Option<&str>,
+    agent_id: Option<&str>,
+) -> Option<String> {
+    let db_config = match (agent_id, conversation_id) {
+        (Some(agent_id), _) => db.load_config_for_agent(agent_id),
+        (None, Some(conversation_id)) => db.load_effective_config(conversation_id),
+        (None, None) => db.load_config(),
+    };
+    db_config.model_path.filter(|p| !p.trim().is_empty())
+}
+
+/// A generation needs a loaded model or one it can load from config; checked
+/// before a conversation is created or the message logged.
+pub fn require_model(
+    model_loaded: bool,
+    db: &Database,
+    conversation_id: Option<&str>,
+    agent_id: Option<&str>,
+) -> Result<(), &'static str> {
+    if model_loaded || configured_model_path(db, conversation_id, agent_id).is_some() {
+        return Ok(());
+    }
+    Err(NO_MODEL_CONFIGURED)
+}
+
+/// Config for one generation: the conversation's config with per-request
+/// overrides merged in. Nothing is written back to the database.
+pub fn load_config_with_overrides(
+    db: &Database,
+    conversation_id: &str,
+    overrides: &GenerationOverrides,
+) -> SamplerConfig {
+    resolve_generation_config(db, conversation_id, None, overrides)
 }
 
 // Helper function to add a model path to history
@@ -158,3 +310,239 @@ pub fn add_to_model_history(db: &Database, model_path: &str) {
         sys_warn!("Failed to add to model history: {}", e);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_request_temperature_does_not_touch_stored_config() {
+        let db = Database::new(":memory:").unwrap();
+        let mut stored = db.load_config();
+        stored.temperature = 0.7;
+        stored.top_k = 40;
+        db.save_config(&stored).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+
+        let overrides = GenerationOverrides {
+            temperature: Some(0.1),
+            seed: Some(42),
+            ..Default::default()
+        };
+        let config = load_config_with_overrides(&db, &conversation_id, &overrides);
+        assert_eq!(config.temperature, 0.1);
+        assert_eq!(config.seed, 42);
+        // Fields not overridden come from the stored config
+        assert_eq!(config.top_k, 40);
+
+        // A later request without overrides sees the stored values again
+        assert_eq!(load_config_for_conversation(&db, &conversation_id).temperature, 0.7);
+        assert_eq!(db.load_config().temperature, 0.7);
+    }
+
+    #[test]
+    fn test_configured_model_path_ignores_blank_paths() {
+        let db = Database::new(":memory:").unwrap();
+        let mut stored = db.load_config();
+        stored.model_path = Some("  ".to_string());
+        db.save_config(&stored).unwrap();
+        assert_eq!(configured_model_path(&db, None, None), None);
+
+        stored.model_path = Some("/models/a.gguf".to_string());
+        db.save_config(&stored).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        assert_eq!(
+            configured_model_path(&db, Some(&conversation_id), None).as_deref(),
+            Some("/models/a.gguf")
+        );
+    }
+
+    #[test]
+    fn test_generation_requires_a_loaded_or_configured_model() {
+        let db = Database::new(":memory:").unwrap();
+        assert_eq!(require_model(false, &db, None, None), Err(NO_MODEL_CONFIGURED));
+        assert_eq!(require_model(true, &db, None, None), Ok(()));
+
+        // A configured model is loaded by the generation itself
+        let mut config = db.load_config();
+        config.model_path = Some("/models/a.gguf".to_string());
+        db.save_config(&config).unwrap();
+        assert_eq!(require_model(false, &db, None, None), Ok(()));
+    }
+
+    #[test]
+    fn test_conversations_generate_with_their_own_stored_temperature() {
+        let db = Database::new(":memory:").unwrap();
+        let mut stored = db.load_config();
+        stored.temperature = 0.7;
+        db.save_config(&stored).unwrap();
+
+        let cold = db.create_conversation().unwrap();
+        let hot = db.create_conversation().unwrap();
+        for (id, temperature) in [(&cold, 0.2), (&hot, 1.3)] {
+            let overrides = GenerationOverrides {
+                temperature: Some(temperature),
+                ..Default::default()
+            };
+            db.set_conversation_overrides(id, Some(&overrides)).unwrap();
+        }
+
+        assert_eq!(load_config_for_conversation(&db, &cold).temperature, 0.2);
+        assert_eq!(load_config_for_conversation(&db, &hot).temperature, 1.3);
+        assert_eq!(db.load_config().temperature, 0.7);
+
+        // A per-request override still beats the conversation's setting
+        let request = GenerationOverrides {
+            temperature: Some(0.5),
+            ..Default::default()
+        };
+        assert_eq!(load_config_with_overrides(&db, &hot, &request).temperature, 0.5);
+
+        db.set_conversation_overrides(&hot, None).unwrap();
+        assert_eq!(load_config_for_conversation(&db, &hot).temperature, 0.7);
+    }
+
+    #[test]
+    fn test_configured_sampler_preset_yields_to_request_params() {
+        let db = Database::new(":memory:").unwrap();
+        let mut stored = db.load_config();
+        stored.sampler_preset = Some("deterministic".to_string());
+        db.save_config(&stored).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+
+        let config = load_config_for_conversation(&db, &conversation_id);
+        assert_eq!((config.sampler_type.as_str(), config.temperature), ("Greedy", 0.0));
+
+        // A request preset replaces the configured one; explicit params beat both
+        let request = GenerationOverrides {
+            sampler_preset: Some("creative".to_string()),
+            top_p: Some(0.5),
+            ..Default::default()
+        };
+        let config = load_config_with_overrides(&db, &conversation_id, &request);
+        assert_eq!(config.temperature, 1.0);
+        assert_eq!(config.top_p, 0.5);
+    }
+
+    #[test]
+    fn test_recommendation_sits_below_preset_conversation_and_request() {
+        let db = Database::new(":memory:").unwrap();
+        let recommended = RecommendedSampling {
+            temperature: Some(0.6),
+            top_p: Some(0.8),
+            top_k: Some(64),
+            ..Default::default()
+        };
+        let conversation_id = db.create_conversation().unwrap();
+        let config = resolve_generation_config(
+            &db,
+            &conversation_id,
+            Some(&recommended),
+            &GenerationOverrides::default(),
+        );
+        assert_eq!((config.temperature, config.top_p, config.top_k), (0.6, 0.8, 64));
+
+        // "balanced" is 0.7/0.95, the app defaults, and still beats the recommendation
+        let mut stored = db.load_config();
+        stored.sampler_preset = Some("balanced".to_string());
+        db.save_config(&stored).unwrap();
+        let config = resolve_generation_config(
+            &db,
+            &conversation_id,
+            Some(&recommended),
+            &GenerationOverrides::default(),
+        );
+        assert_eq!((config.temperature, config.top_p, config.top_k), (0.7, 0.95, 40));
+
+        // A conversation value equal to the default is kept, and the request wins over it
+        let pinned = GenerationOverrides {
+            temperature: Some(0.7),
+            top_k: Some(20),
+            ..Default::default()
+        };
+        db.set_conversation_overrides(&conversation_id, Some(&pinned)).unwrap();
+        stored.sampler_preset = None;
+        db.save_config(&stored).unwrap();
+        let request = GenerationOverrides {
+            top_k: Some(5),
+            ..Default::default()
+        };
+        let config = resolve_generation_config(&db, &conversation_id, Some(&recommended), &request);
+        assert_eq!((config.temperature, config.top_p, config.top_k), (0.7, 0.8, 5));
+    }
+
+    #[test]
+    fn test_configured_preset_leaves_an_agents_saved_sampler_alone() {
+        let db = Database::new(":memory:").unwrap();
+        let mut stored = db.load_config();
+        stored.sampler_preset = Some("creative".to_string());
+        db.save_config(&stored).unwrap();
+
+        let mut agent = llama_chat_db::agents::AgentRecord::from_db_sampler_config(
+            "Reviewer",
+            &stored,
+        );
+        agent.temperature = 0.2;
+        agent.top_p = 0.5;
+        let agent_id = db.create_agent(&agent).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        db.set_conversation_agent_id(&conversation_id, Some(&agent_id)).unwrap();
+
+        let config = load_config_for_conversation(&db, &conversation_id);
+        assert_eq!((config.temperature, config.top_p), (0.2, 0.5));
+
+        // Without the agent the preset applies
+        let other = db.create_conversation().unwrap();
+        assert_eq!(load_config_for_conversation(&db, &other).temperature, 1.0);
+    }
+
+    #[test]
+    fn test_recommendation_leaves_an_agents_default_valued_sampler_alone() {
+        let db = Database::new(":memory:").unwrap();
+        let stored = db.load_config();
+        // Deliberately the app defaults
+        let mut agent =
+            llama_chat_db::agents::AgentRecord::from_db_sampler_config("Tutor", &stored);
+        agent.temperature = 0.7;
+        agent.top_p = 0.95;
+        agent.top_k = 20;
+        let agent_id = db.create_agent(&agent).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+        db.set_conversation_agent_id(&conversation_id, Some(&agent_id)).unwrap();
+        let recommended = RecommendedSampling {
+            temperature: Some(0.6),
+            top_p: Some(0.8),
+            top_k: Some(64),
+            ..Default::default()
+        };
+
+        let config = resolve_generation_config(
+            &db,
+            &conversation_id,
+            Some(&recommended),
+            &GenerationOverrides::default(),
+        );
+        assert_eq!((config.temperature, config.top_p, config.top_k), (0.7, 0.95, 20));
+    }
+
+    #[test]
+    fn test_sampler_preset_defined_in_config_is_applied() {
+        let db = Database::new(":memory:").unwrap();
+        let mut stored = db.load_config();
+        stored.sampler_preset = Some("house".to_string());
+        stored.sampler_presets = vec![llama_chat_types::models::SamplerPreset {
+            name: "house".to_string(),
+            description: String::new(),
+            params: GenerationOverrides {
+                temperature: Some(0.45),
+                top_k: Some(30),
+                ..Default::default()
+            },
+        }];
+        db.save_config(&stored).unwrap();
+        let conversation_id = db.create_conversation().unwrap();
+
+        let config = load_config_for_conversation(&db, &conversation_id);
+        assert_eq!((config.temperature, config.top_k), (0.45, 30));
+    }
+}
@@ -0,0 +1,300 @@
+// Resolve client-supplied model paths against the configured models directory.
+//
+// Every route that takes a model path (info, validate, load) goes through
+// `resolve_model_path`, so symlinks, directories and non-GGUF files are
+// handled the same way everywhere.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Start of the error for a path that doesn't exist (or is a broken symlink).
+pub const MODEL_NOT_FOUND: &str = "Model file not found";
+
+/// How deep a directory is searched for `.gguf` files.
+const MAX_SCAN_DEPTH: usize = 4;
+
+/// What a model path points at, with symlinks followed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelTarget {
+    /// A `.gguf` file.
+    File(String),
+    /// A directory and the `.gguf` files under it (nested directories
+    /// included), relative to it and sorted.
+    Directory { path: String, gguf_files: Vec<String> },
+}
+
+impl ModelTarget {
+    /// The file to load; a directory is an error naming what it contains.
+    pub fn into_file(self) -> Result<String, String> {
+        match self {
+            Self::File(path) => Ok(path),
+            Self::Directory { path, gguf_files } if gguf_files.is_empty() => {
+                Err(format!("'{path}' is a directory with no .gguf files"))
+            }
+            Self::Directory { path, gguf_files } => Err(format!(
+                "'{path}' is a directory; pick one of its .gguf files: {}",
+                gguf_files.join(", ")
+            )),
+        }
+    }
+}
+
+/// Resolve `requested` to the model file (or directory) it names.
+///
+/// Symlinks are followed to the real file. With a `models_directory`, relative
+/// paths resolve under it and must stay inside it after that (so `..` and
+/// symlinks can't escape); absolute paths must also be inside it unless
+/// `allow_external` is set. Files must be `.gguf`; a symlink counts if either
+/// its own name or its target's has the extension, since download caches link
+/// readable names to extensionless blobs.
+pub fn resolve_model_path(
+    requested: &str,
+    models_directory: Option<&str>,
+    allow_external: bool,
+) -> Result<ModelTarget, String> {
+    let requested = requested.trim();
+    if requested.is_empty() {
+        return Err("Model path is required".to_string());
+    }
+    let root = match models_directory.map(str::trim).filter(|r| !r.is_empty()) {
+        Some(root) => Some(
+            std::fs::canonicalize(root)
+                .map_err(|e| format!("Models directory '{root}' is not accessible: {e}"))?,
+        ),
+        None => None,
+    };
+    let path = Path::new(requested);
+    let is_relative = path.is_relative();
+    let candidate = match &root {
+        Some(root) if is_relative => root.join(path),
+        _ => path.to_path_buf(),
+    };
+    let resolved = std::fs::canonicalize(&candidate)
+        .map_err(|e| format!("{MODEL_NOT_FOUND}: {requested} ({e})"))?;
+
+    let inside = |p: &Path| match &root {
+        Some(root) => p.starts_with(root) || (allow_external && !is_relative),
+        None => true,
+    };
+    if !inside(&resolved) {
+        return Err(format!(
+            "Model path '{requested}' is outside the configured models directory"
+        ));
+    }
+
+    if resolved.is_dir() {
+        let mut gguf_files = Vec::new();
+        let mut visited = HashSet::new();
+        scan_gguf_files(&resolved, Path::new(""), 0, &inside, &mut visited, &mut gguf_files);
+        gguf_files.sort();
+        return Ok(ModelTarget::Directory {
+            path: path_to_string(resolved),
+            gguf_files,
+        });
+    }
+    if !is_gguf(&candidate) && !is_gguf(&resolved) {
+        return Err(format!("'{requested}' is not a .gguf model file"));
+    }
+    Ok(ModelTarget::File(path_to_string(resolved)))
+}
+
+fn is_gguf(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("gguf"))
+}
+
+/// Collect `.gguf` files under `dir` as paths relative to the scanned root.
+/// Each real directory is entered once, so symlink cycles end.
+fn scan_gguf_files(
+    dir: &Path,
+    relative: &Path,
+    depth: usize,
+    inside: &dyn Fn(&Path) -> bool,
+    visited: &mut HashSet<PathBuf>,
+    found: &mut Vec<String>,
+) {
+    if depth > MAX_SCAN_DEPTH || !visited.insert(dir.to_path_buf()) {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Ok(target) = std::fs::canonicalize(entry.path()) else {
+            continue; // Broken symlink
+        };
+        if !inside(&target) {
+            continue;
+        }
+        let relative = relative.join(&name);
+        if target.is_dir() {
+            scan_gguf_files(&target, &relative, depth + 1, inside, visited, found);
+        } else if is_gguf(Path::new(&name)) || is_gguf(&target) {
+            found.push(relative.to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// Stringify a canonical path, dropping the `\\?\` verbatim prefix Windows adds.
+fn path_to_string(path: PathBuf) -> String {
+    let s = path.to_string_lossy().into_owned();
+    match s.strip_prefix(r"\\?\") {
+        Some(stripped) if !stripped.starts_with("UNC") => stripped.to_string(),
+        _ => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create `<tmp>/<name>-<pid>/models/sub/model.gguf` plus a sibling
+    /// `<tmp>/<name>-<pid>/outside.gguf`; returns the base dir.
+    fn setup(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("models").join("sub")).unwrap();
+        std::fs::write(base.join("models").join("sub").join("model.gguf"), b"GGUF").unwrap();
+        std::fs::write(base.join("outside.gguf"), b"GGUF").unwrap();
+        base
+    }
+
+    #[test]
+    fn test_resolves_relative_path_under_root() {
+        let base = setup("models-root-relative");
+        let root = base.join("models");
+        let root_str = root.to_string_lossy().to_string();
+
+        let resolved = resolve_model_path("sub/model.gguf", Some(&root_str), false).unwrap();
+        let expected = std::fs::canonicalize(root.join("sub").join("model.gguf")).unwrap();
+        assert_eq!(resolved, ModelTarget::File(path_to_string(expected.clone())));
+
+        // Absolute paths inside the root are still allowed
+        let absolute = expected.to_string_lossy().to_string();
+        assert!(resolve_model_path(&absolute, Some(&root_str), false).is_ok());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_blocks_traversal_outside_root() {
+        let base = setup("models-root-traversal");
+        let root_str = base.join("models").to_string_lossy().to_string();
+
+        let err = resolve_model_path("../outside.gguf", Some(&root_str), false).unwrap_err();
+        assert!(err.contains("outside the configured models directory"), "{err}");
+        // The external-paths flag only covers absolute paths, never relative escapes
+        assert!(resolve_model_path("../outside.gguf", Some(&root_str), true).is_err());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_absolute_path_outside_root_needs_flag() {
+        let base = setup("models-root-absolute");
+        let root_str = base.join("models").to_string_lossy().to_string();
+        let outside = base.join("outside.gguf").to_string_lossy().to_string();
+
+        assert!(resolve_model_path(&outside, Some(&root_str), false).is_err());
+        assert!(resolve_model_path(&outside, Some(&root_str), true).is_ok());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_without_root_path_resolves_as_given() {
+        let base = setup("models-no-root");
+        let model = base.join("outside.gguf");
+        let expected = path_to_string(std::fs::canonicalize(&model).unwrap());
+        let padded = format!(" {} ", model.to_string_lossy());
+        assert_eq!(
+            resolve_model_path(&padded, None, false).unwrap(),
+            ModelTarget::File(expected)
+        );
+        assert!(resolve_model_path("  ", None, false).is_err());
+        let missing = base.join("missing.gguf").to_string_lossy().to_string();
+        let err = resolve_model_path(&missing, None, false).unwrap_err();
+        assert!(err.starts_with(MODEL_NOT_FOUND), "{err}");
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_gguf_resolves_to_its_target() {
+        let base = setup("models-symlink");
+        let root = base.join("models");
+        let root_str = root.to_string_lossy().to_string();
+        let target = std::fs::canonicalize(root.join("sub").join("model.gguf")).unwrap();
+
+        std::os::unix::fs::symlink(&target, root.join("link.gguf")).unwrap();
+        assert_eq!(
+            resolve_model_path("link.gguf", Some(&root_str), false).unwrap(),
+            ModelTarget::File(path_to_string(target))
+        );
+
+        // Download caches link a readable name to an extensionless blob
+        let blob = root.join("blob-1234");
+        std::fs::write(&blob, b"GGUF").unwrap();
+        std::os::unix::fs::symlink(&blob, root.join("cached.gguf")).unwrap();
+        assert!(resolve_model_path("cached.gguf", Some(&root_str), false).is_ok());
+
+        // A link pointing out of the root is an escape like `..`
+        std::os::unix::fs::symlink(base.join("outside.gguf"), root.join("escape.gguf")).unwrap();
+        assert!(resolve_model_path("escape.gguf", Some(&root_str), true).is_err());
+
+        // Broken links read as missing files
+        std::os::unix::fs::symlink(root.join("gone.gguf"), root.join("broken.gguf")).unwrap();
+        let err = resolve_model_path("broken.gguf", Some(&root_str), false).unwrap_err();
+        assert!(err.starts_with(MODEL_NOT_FOUND), "{err}");
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_directory_lists_its_gguf_files() {
+        let base = setup("models-directory");
+        let root = base.join("models");
+        let root_str = root.to_string_lossy().to_string();
+        std::fs::write(root.join("a.GGUF"), b"GGUF").unwrap();
+        std::fs::write(root.join("notes.txt"), b"").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("sub").join("loop")).unwrap();
+
+        let target = resolve_model_path(".", Some(&root_str), false).unwrap();
+        let sub_model = Path::new("sub").join("model.gguf").to_string_lossy().into_owned();
+        match &target {
+            ModelTarget::Directory { gguf_files, .. } => {
+                // The symlink back to the root is not followed again
+                assert_eq!(gguf_files, &vec!["a.GGUF".to_string(), sub_model]);
+            }
+            other => panic!("expected a directory, got {other:?}"),
+        }
+        let err = target.into_file().unwrap_err();
+        assert!(err.contains("a.GGUF"), "{err}");
+
+        std::fs::create_dir_all(root.join("empty")).unwrap();
+        let err = resolve_model_path("empty", Some(&root_str), false)
+            .and_then(ModelTarget::into_file)
+            .unwrap_err();
+        assert!(err.contains("no .gguf files"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_non_gguf_file_is_rejected() {
+        let base = setup("models-not-gguf");
+        let root = base.join("models");
+        let root_str = root.to_string_lossy().to_string();
+        std::fs::write(root.join("model.bin"), b"").unwrap();
+        std::fs::write(root.join("README"), b"").unwrap();
+
+        for name in ["model.bin", "README"] {
+            let err = resolve_model_path(name, Some(&root_str), false).unwrap_err();
+            assert!(err.contains("is not a .gguf model file"), "{err}");
+        }
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
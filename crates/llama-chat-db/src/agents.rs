@@ -143,6 +143,32 @@ impl AgentRecord {
             provider_api_keys: global.provider_api_keys.clone(),
             max_tool_calls: global.max_tool_calls,
             loop_detection_limit: global.loop_detection_limit,
+            compress_messages: global.compress_messages,
+            allow_external_model_paths: global.allow_external_model_paths,
+            auto_execute_tools: global.auto_execute_tools,
+            temperature_min: global.temperature_min,
+            temperature_max: global.temperature_max,
+            generation_timeout_secs: global.generation_timeout_secs,
+            enabled_tools: global.enabled_tools.clone(),
+            normalize_tool_output: global.normalize_tool_output,
+            default_context_cap: global.default_context_cap,
+            tools_enabled: global.tools_enabled,
+            reasoning_tag_open: global.reasoning_tag_open.clone(),
+            reasoning_tag_close: global.reasoning_tag_close.clone(),
+            require_confirmation_for: global.require_confirmation_for.clone(),
+            max_parallel_generations: global.max_parallel_generations,
+            sampler_preset: global.sampler_preset.clone(),
+            sampler_presets: global.sampler_presets.clone(),
+            autosave_interval_ms: global.autosave_interval_ms,
+            python_isolated: global.python_isolated,
+            python_venv: global.python_venv.clone(),
+            max_tool_iterations: global.max_tool_iterations,
+            dedupe_tool_calls: global.dedupe_tool_calls,
+            max_completions: global.max_completions,
+            read_file_max_bytes: global.read_file_max_bytes,
+            list_directory_max_entries: global.list_directory_max_entries,
+            preload_model: global.preload_model.clone(),
+            preload_gpu_layers: global.preload_gpu_layers,
             model_history: Vec::new(),
         }
     }
@@ -549,7 +575,18 @@ impl Database {
     /// Resolution order:
     /// 1. If conversation has an `agent_id`, use that agent's config.
     /// 2. Final fallback: global config.
+    ///
+    /// A per-conversation `system_prompt` (when set) replaces the resolved prompt.
     pub fn load_effective_config(&self, conversation_id: &str) -> DbSamplerConfig {
+        let mut config = self.load_agent_or_global_config(conversation_id);
+        if let Ok(Some(system_prompt)) = self.get_conversation_system_prompt(conversation_id) {
+            config.system_prompt = Some(system_prompt);
+            config.system_prompt_type = SystemPromptType::Custom;
+        }
+        config
+    }
+
+    fn load_agent_or_global_config(&self, conversation_id: &str) -> DbSamplerConfig {
         if let Ok(Some(agent_id)) = self.get_conversation_agent_id(conversation_id) {
             if let Ok(Some(agent)) = self.get_agent(&agent_id) {
                 let global = self.load_config();
@@ -42,6 +42,7 @@ pub struct DbSamplerConfig {
     pub tool_tag_output_close: Option<String>,
     // App settings
     pub web_browser_backend: Option<String>,
+    // Also the root that relative LoadModel paths resolve against
     pub models_directory: Option<String>,
     // Hardware / context / sampler params
     pub seed: i32,
@@ -69,11 +70,60 @@ pub struct DbSamplerConfig {
     pub telegram_chat_id: Option<String>,
     // Provider API keys (JSON blob for OpenAI-compatible providers)
     pub provider_api_keys: Option<String>,
-    // Max tool calls per remote provider turn (safety limit)
+    // Max tool calls per remote provider turn (safety limit); local models use max_tool_iterations
     pub max_tool_calls: i32,
     pub loop_detection_limit: i32,
+    // zstd compression of large message content at rest
+    pub compress_messages: bool,
+    // Allow absolute model paths outside models_directory
+    pub allow_external_model_paths: bool,
     // Thinking mode: None = use model default, Some(true/false) = explicit override
     pub thinking_mode: Option<bool>,
+    // Execute tool calls inline; false = stop and return them to the caller
+    pub auto_execute_tools: bool,
+    // Sampler clamps temperature into [temperature_min, temperature_max]
+    pub temperature_min: f64,
+    pub temperature_max: f64,
+    // Wall-clock cap per generation in seconds; None = no limit
+    pub generation_timeout_secs: Option<u32>,
+    // Native tools advertised and dispatchable; None = all (stored as a JSON array)
+    pub enabled_tools: Option<Vec<String>>,
+    // Collapse blank-line runs and trailing whitespace in tool output sent to the model
+    pub normalize_tool_output: bool,
+    // Context size cap applied when context_size is unset; 0 = model's full context
+    pub default_context_cap: u32,
+    // Advertise tools and dispatch tool calls; false = plain chat
+    pub tools_enabled: bool,
+    // Reasoning block tags stripped from stored answers; blank = disabled
+    pub reasoning_tag_open: String,
+    pub reasoning_tag_close: String,
+    // Tools whose calls wait for user confirmation (stored as a JSON array)
+    pub require_confirmation_for: Vec<String>,
+    // Generations the worker runs at once, each on its own context; 1 = serialized
+    pub max_parallel_generations: u32,
+    // Named sampler preset expanded at generation time; None = use the fields as stored
+    pub sampler_preset: Option<String>,
+    // Presets defined in config, next to the built-in ones (stored as a JSON array)
+    pub sampler_presets: Vec<llama_chat_types::models::SamplerPreset>,
+    // Milliseconds between saves of a streaming reply (crash recovery); 0 = every sync
+    pub autosave_interval_ms: u32,
+    // Run execute_python scripts in a per-conversation directory without user site-packages
+    pub python_isolated: bool,
+    // Virtualenv whose interpreter isolated scripts use; None = python on PATH
+    pub python_venv: Option<String>,
+    // Tool/command executions per local-model turn before the wrap-up notice; 0 = no limit
+    pub max_tool_iterations: u32,
+    // Answer an identical repeated tool call from its earlier result instead of re-running it
+    pub dedupe_tool_calls: bool,
+    // Largest `n` (completions per request) accepted
+    pub max_completions: u32,
+    // Most bytes `read_file` returns inline
+    pub read_file_max_bytes: u32,
+    // Most entries `list_directory` lists before the "...and N more" footer
+    pub list_directory_max_entries: u32,
+    // Model loaded (and warmed up) when the server starts
+    pub preload_model: Option<String>,
+    pub preload_gpu_layers: Option<u32>,
 }
 
 impl Default for DbSamplerConfig {
@@ -133,7 +183,33 @@ impl Default for DbSamplerConfig {
             provider_api_keys: None,
             max_tool_calls: 2000,
             loop_detection_limit: 15,
+            compress_messages: false,
+            allow_external_model_paths: true,
             thinking_mode: None,
+            auto_execute_tools: true,
+            temperature_min: 0.0,
+            temperature_max: 2.0,
+            generation_timeout_secs: None,
+            enabled_tools: None,
+            normalize_tool_output: true,
+            default_context_cap: llama_chat_types::models::DEFAULT_CONTEXT_CAP,
+            tools_enabled: true,
+            reasoning_tag_open: "<think>".to_string(),
+            reasoning_tag_close: "</think>".to_string(),
+            require_confirmation_for: Vec::new(),
+            max_parallel_generations: 1,
+            sampler_preset: None,
+            sampler_presets: Vec::new(),
+            autosave_interval_ms: llama_chat_types::models::DEFAULT_AUTOSAVE_INTERVAL_MS,
+            python_isolated: false,
+            python_venv: None,
+            max_tool_iterations: llama_chat_types::models::DEFAULT_MAX_TOOL_ITERATIONS,
+            dedupe_tool_calls: true,
+            max_completions: llama_chat_types::models::DEFAULT_MAX_COMPLETIONS,
+            read_file_max_bytes: llama_chat_types::models::DEFAULT_READ_FILE_MAX_BYTES,
+            list_directory_max_entries: llama_chat_types::models::DEFAULT_LIST_DIRECTORY_MAX_ENTRIES,
+            preload_model: None,
+            preload_gpu_layers: None,
         }
     }
 }
@@ -153,7 +229,33 @@ impl Database {
                         telegram_chat_id,
                         provider_api_keys,
                         max_tool_calls,
-                        loop_detection_limit
+                        loop_detection_limit,
+                        compress_messages,
+                        allow_external_model_paths,
+                        auto_execute_tools,
+                        temperature_min,
+                        temperature_max,
+                        generation_timeout_secs,
+                        enabled_tools,
+                        normalize_tool_output,
+                        default_context_cap,
+                        tools_enabled,
+                        reasoning_tag_open,
+                        reasoning_tag_close,
+                        require_confirmation_for,
+                        max_parallel_generations,
+                        sampler_preset,
+                        autosave_interval_ms,
+                        python_isolated,
+                        python_venv,
+                        max_tool_iterations,
+                        dedupe_tool_calls,
+                        max_completions,
+                        read_file_max_bytes,
+                        list_directory_max_entries,
+                        preload_model,
+                        preload_gpu_layers,
+                        sampler_presets
                  FROM config WHERE id = 1",
                 [],
                 |row| {
@@ -168,6 +270,62 @@ impl Database {
                         provider_api_keys: row.get(7)?,
                         max_tool_calls: row.get::<_, Option<i32>>(8)?.unwrap_or(2000),
                         loop_detection_limit: row.get::<_, Option<i32>>(9)?.unwrap_or(15),
+                        compress_messages: row.get::<_, Option<i32>>(10)?.unwrap_or(0) != 0,
+                        allow_external_model_paths: row.get::<_, Option<i32>>(11)?.unwrap_or(1) != 0,
+                        auto_execute_tools: row.get::<_, Option<i32>>(12)?.unwrap_or(1) != 0,
+                        temperature_min: row.get::<_, Option<f64>>(13)?.unwrap_or(0.0),
+                        temperature_max: row.get::<_, Option<f64>>(14)?.unwrap_or(2.0),
+                        generation_timeout_secs: row.get(15)?,
+                        enabled_tools: row
+                            .get::<_, Option<String>>(16)?
+                            .and_then(|j| serde_json::from_str(&j).ok()),
+                        normalize_tool_output: row.get::<_, Option<i32>>(17)?.unwrap_or(1) != 0,
+                        default_context_cap: row
+                            .get::<_, Option<u32>>(18)?
+                            .unwrap_or(llama_chat_types::models::DEFAULT_CONTEXT_CAP),
+                        tools_enabled: row.get::<_, Option<i32>>(19)?.unwrap_or(1) != 0,
+                        reasoning_tag_open: row
+                            .get::<_, Option<String>>(20)?
+                            .unwrap_or_else(|| "<think>".to_string()),
+                        reasoning_tag_close: row
+                            .get::<_, Option<String>>(21)?
+                            .unwrap_or_else(|| "</think>".to_string()),
+                        require_confirmation_for: row
+                            .get::<_, Option<String>>(22)?
+                            .and_then(|j| serde_json::from_str(&j).ok())
+                            .unwrap_or_default(),
+                        max_parallel_generations: row
+                            .get::<_, Option<u32>>(23)?
+                            .unwrap_or(1)
+                            .max(1),
+                        sampler_preset: row.get(24)?,
+                        autosave_interval_ms: row
+                            .get::<_, Option<u32>>(25)?
+                            .unwrap_or(llama_chat_types::models::DEFAULT_AUTOSAVE_INTERVAL_MS),
+                        python_isolated: row.get::<_, Option<i32>>(26)?.unwrap_or(0) != 0,
+                        python_venv: row.get(27)?,
+                        max_tool_iterations: row
+                            .get::<_, Option<u32>>(28)?
+                            .unwrap_or(llama_chat_types::models::DEFAULT_MAX_TOOL_ITERATIONS),
+                        dedupe_tool_calls: row.get::<_, Option<i32>>(29)?.unwrap_or(1) != 0,
+                        max_completions: row
+                            .get::<_, Option<u32>>(30)?
+                            .unwrap_or(llama_chat_types::models::DEFAULT_MAX_COMPLETIONS)
+                            .max(1),
+                        read_file_max_bytes: row
+                            .get::<_, Option<u32>>(31)?
+                            .unwrap_or(llama_chat_types::models::DEFAULT_READ_FILE_MAX_BYTES)
+                            .max(1),
+                        list_directory_max_entries: row
+                            .get::<_, Option<u32>>(32)?
+                            .unwrap_or(llama_chat_types::models::DEFAULT_LIST_DIRECTORY_MAX_ENTRIES)
+                            .max(1),
+                        preload_model: row.get(33)?,
+                        preload_gpu_layers: row.get(34)?,
+                        sampler_presets: row
+                            .get::<_, Option<String>>(35)?
+                            .and_then(|j| serde_json::from_str(&j).ok())
+                            .unwrap_or_default(),
                         ..Default::default()
                     })
                 },
@@ -184,14 +342,31 @@ impl Database {
 
     /// Save configuration to database
     pub fn save_config(&self, config: &DbSamplerConfig) -> Result<(), String> {
+        let enabled_tools_json = config
+            .enabled_tools
+            .as_ref()
+            .and_then(|names| serde_json::to_string(names).ok());
+        let require_confirmation_json =
+            serde_json::to_string(&config.require_confirmation_for).unwrap_or_default();
+        let sampler_presets_json = serde_json::to_string(&config.sampler_presets).unwrap_or_default();
         let conn = self.connection();
 
         conn.execute(
             "INSERT INTO config
              (id, disable_file_logging, web_browser_backend, models_directory,
               use_rtk, use_htmd, telegram_bot_token, telegram_chat_id,
-              provider_api_keys, max_tool_calls, loop_detection_limit, updated_at)
-             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+              provider_api_keys, max_tool_calls, loop_detection_limit, compress_messages,
+              allow_external_model_paths, auto_execute_tools,
+              temperature_min, temperature_max, generation_timeout_secs, enabled_tools,
+              normalize_tool_output, default_context_cap, tools_enabled, reasoning_tag_open,
+              reasoning_tag_close, require_confirmation_for, max_parallel_generations,
+              sampler_preset, autosave_interval_ms, python_isolated, python_venv,
+              max_tool_iterations, dedupe_tool_calls, max_completions, read_file_max_bytes,
+              list_directory_max_entries, preload_model, preload_gpu_layers, sampler_presets,
+              updated_at)
+             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+                     ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35,
+                     ?36, ?37)",
             params![
                 config.disable_file_logging as i32,
                 config.web_browser_backend,
@@ -203,6 +378,32 @@ impl Database {
                 config.provider_api_keys,
                 config.max_tool_calls,
                 config.loop_detection_limit,
+                config.compress_messages as i32,
+                config.allow_external_model_paths as i32,
+                config.auto_execute_tools as i32,
+                config.temperature_min,
+                config.temperature_max,
+                config.generation_timeout_secs,
+                enabled_tools_json,
+                config.normalize_tool_output as i32,
+                config.default_context_cap,
+                config.tools_enabled as i32,
+                config.reasoning_tag_open,
+                config.reasoning_tag_close,
+                require_confirmation_json,
+                config.max_parallel_generations,
+                config.sampler_preset,
+                config.autosave_interval_ms,
+                config.python_isolated as i32,
+                config.python_venv,
+                config.max_tool_iterations,
+                config.dedupe_tool_calls as i32,
+                config.max_completions,
+                config.read_file_max_bytes,
+                config.list_directory_max_entries,
+                config.preload_model,
+                config.preload_gpu_layers,
+                sampler_presets_json,
                 current_timestamp_millis(),
             ],
         )
@@ -219,7 +420,33 @@ impl Database {
                  provider_api_keys = ?8,
                  max_tool_calls = ?9,
                  loop_detection_limit = ?10,
-                 updated_at = ?11
+                 compress_messages = ?11,
+                 allow_external_model_paths = ?12,
+                 auto_execute_tools = ?13,
+                 temperature_min = ?14,
+                 temperature_max = ?15,
+                 generation_timeout_secs = ?16,
+                 enabled_tools = ?17,
+                 normalize_tool_output = ?18,
+                 default_context_cap = ?19,
+                 tools_enabled = ?20,
+                 reasoning_tag_open = ?21,
+                 reasoning_tag_close = ?22,
+                 require_confirmation_for = ?23,
+                 max_parallel_generations = ?24,
+                 sampler_preset = ?25,
+                 autosave_interval_ms = ?26,
+                 python_isolated = ?27,
+                 python_venv = ?28,
+                 max_tool_iterations = ?29,
+                 dedupe_tool_calls = ?30,
+                 max_completions = ?31,
+                 read_file_max_bytes = ?32,
+                 list_directory_max_entries = ?33,
+                 preload_model = ?34,
+                 preload_gpu_layers = ?35,
+                 sampler_presets = ?36,
+                 updated_at = ?37
                  WHERE id = 1",
                 params![
                     config.disable_file_logging as i32,
@@ -232,12 +459,39 @@ impl Database {
                     config.provider_api_keys,
                     config.max_tool_calls,
                     config.loop_detection_limit,
+                    config.compress_messages as i32,
+                    config.allow_external_model_paths as i32,
+                    config.auto_execute_tools as i32,
+                    config.temperature_min,
+                    config.temperature_max,
+                    config.generation_timeout_secs,
+                    enabled_tools_json,
+                    config.normalize_tool_output as i32,
+                    config.default_context_cap,
+                    config.tools_enabled as i32,
+                    config.reasoning_tag_open,
+                    config.reasoning_tag_close,
+                    require_confirmation_json,
+                    config.max_parallel_generations,
+                    config.sampler_preset,
+                    config.autosave_interval_ms,
+                    config.python_isolated as i32,
+                    config.python_venv,
+                    config.max_tool_iterations,
+                    config.dedupe_tool_calls as i32,
+                    config.max_completions,
+                    config.read_file_max_bytes,
+                    config.list_directory_max_entries,
+                    config.preload_model,
+                    config.preload_gpu_layers,
+                    sampler_presets_json,
                     current_timestamp_millis(),
                 ],
             )
         })
         .map_err(db_error("save config"))?;
 
+        self.set_message_compression(config.compress_messages);
         Ok(())
     }
 
@@ -269,8 +523,15 @@ impl Database {
         Ok(())
     }
 
-    /// Add model to history (MRU list)
+    /// Add model to history (MRU list). Re-adding an existing path moves it to the front.
     pub fn add_to_model_history(&self, model_path: &str) -> Result<(), String> {
+        let model_path = model_path.trim();
+        if model_path.is_empty() {
+            return Err("Model path is empty".to_string());
+        }
+        let display_name = model_display_name(model_path);
+        let size_bytes = std::fs::metadata(model_path).ok().map(|m| m.len() as i64);
+
         let conn = self.connection();
         let now = current_timestamp_millis();
 
@@ -290,14 +551,17 @@ impl Database {
 
         // Insert at position 0
         conn.execute(
-            "INSERT INTO model_history (model_path, last_used, display_order) VALUES (?1, ?2, 0)",
-            params![model_path, now],
+            "INSERT INTO model_history (model_path, last_used, display_order, display_name, size_bytes)
+             VALUES (?1, ?2, 0, ?3, ?4)",
+            params![model_path, now, display_name, size_bytes],
         )
         .map_err(db_error("insert into model history"))?;
 
-        // Keep only top 10
-        conn.execute("DELETE FROM model_history WHERE display_order >= 10", [])
-            .map_err(db_error("trim model history"))?;
+        conn.execute(
+            "DELETE FROM model_history WHERE display_order >= ?1",
+            [MODEL_HISTORY_LIMIT as i64],
+        )
+        .map_err(db_error("trim model history"))?;
 
         Ok(())
     }
@@ -318,6 +582,35 @@ impl Database {
         Ok(paths)
     }
 
+    /// Get model history with per-entry metadata (ordered most recent first)
+    pub fn get_model_history_entries(&self) -> Result<Vec<ModelHistoryEntry>, String> {
+        let conn = self.connection();
+        let mut stmt = conn
+            .prepare(
+                "SELECT model_path, last_used, display_name, size_bytes FROM model_history ORDER BY display_order ASC",
+            )
+            .map_err(db_error("prepare model history query"))?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                let model_path: String = row.get(0)?;
+                let display_name = row
+                    .get::<_, Option<String>>(2)?
+                    .unwrap_or_else(|| model_display_name(&model_path));
+                Ok(ModelHistoryEntry {
+                    last_loaded_at: row.get(1)?,
+                    display_name,
+                    size_bytes: row.get::<_, Option<i64>>(3)?.map(|s| s as u64),
+                    model_path,
+                })
+            })
+            .map_err(db_error("query model history"))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(entries)
+    }
+
     /// Insert log entry
     pub fn insert_log(
         &self,
@@ -366,6 +659,32 @@ impl Database {
     }
 }
 
+/// Maximum number of entries kept in the model history.
+pub const MODEL_HISTORY_LIMIT: usize = 10;
+
+/// A recently loaded model, as shown in the frontend's recent-models list.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelHistoryEntry {
+    pub model_path: String,
+    pub display_name: String,
+    /// Milliseconds since Unix epoch.
+    pub last_loaded_at: i64,
+    /// File size at load time. None for legacy rows or files that were unreadable.
+    pub size_bytes: Option<u64>,
+}
+
+/// Derive a display name from a model path: the file name without `.gguf`.
+fn model_display_name(model_path: &str) -> String {
+    let file_name = model_path
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(model_path);
+    file_name
+        .strip_suffix(".gguf")
+        .unwrap_or(file_name)
+        .to_string()
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct LogEntry {
     pub level: String,
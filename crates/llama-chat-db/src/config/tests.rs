@@ -15,6 +15,30 @@ fn test_load_default_config() {
     assert_eq!(config.top_p, 0.95);
     assert!(config.disable_file_logging);
     assert_eq!(config.max_tool_calls, 2000);
+    assert!(config.auto_execute_tools);
+    assert_eq!((config.temperature_min, config.temperature_max), (0.0, 2.0));
+    assert_eq!(config.generation_timeout_secs, None);
+    assert_eq!(config.enabled_tools, None);
+    assert!(config.normalize_tool_output);
+    assert_eq!(config.context_size, Some(32768));
+    assert_eq!(config.default_context_cap, 8192);
+    assert!(config.tools_enabled);
+    assert_eq!(config.reasoning_tag_open, "<think>");
+    assert_eq!(config.reasoning_tag_close, "</think>");
+    assert!(config.require_confirmation_for.is_empty());
+    assert_eq!(config.max_parallel_generations, 1);
+    assert_eq!(config.sampler_preset, None);
+    assert!(config.sampler_presets.is_empty());
+    assert_eq!(config.autosave_interval_ms, 5000);
+    assert!(!config.python_isolated);
+    assert_eq!(config.python_venv, None);
+    assert_eq!(config.max_tool_iterations, 200);
+    assert!(config.dedupe_tool_calls);
+    assert_eq!(config.max_completions, 4);
+    assert_eq!(config.read_file_max_bytes, 100 * 1024);
+    assert_eq!(config.list_directory_max_entries, 500);
+    assert_eq!(config.preload_model, None);
+    assert_eq!(config.preload_gpu_layers, None);
 }
 
 #[test]
@@ -55,7 +79,7 @@ fn test_save_and_load_config() {
         tool_tag_output_open: None,
         tool_tag_output_close: None,
         web_browser_backend: None,
-        models_directory: None,
+        models_directory: Some("/srv/models".to_string()),
         seed: -1,
         n_ubatch: 512,
         n_threads: 0,
@@ -76,7 +100,40 @@ fn test_save_and_load_config() {
         provider_api_keys: None,
         max_tool_calls: 123,
         loop_detection_limit: 15,
+        compress_messages: false,
+        allow_external_model_paths: false,
         thinking_mode: None,
+        auto_execute_tools: false,
+        temperature_min: 0.1,
+        temperature_max: 1.5,
+        generation_timeout_secs: Some(90),
+        enabled_tools: Some(vec!["read_file".to_string(), "list_directory".to_string()]),
+        normalize_tool_output: false,
+        default_context_cap: 0,
+        tools_enabled: false,
+        reasoning_tag_open: "<reasoning>".to_string(),
+        reasoning_tag_close: String::new(),
+        require_confirmation_for: vec!["write_file".to_string()],
+        max_parallel_generations: 4,
+        sampler_preset: Some("creative".to_string()),
+        sampler_presets: vec![llama_chat_types::models::SamplerPreset {
+            name: "house".to_string(),
+            description: String::new(),
+            params: llama_chat_types::models::GenerationOverrides {
+                temperature: Some(0.4),
+                ..Default::default()
+            },
+        }],
+        autosave_interval_ms: 250,
+        python_isolated: true,
+        python_venv: Some("/srv/venvs/tools".to_string()),
+        max_tool_iterations: 3,
+        dedupe_tool_calls: false,
+        max_completions: 2,
+        read_file_max_bytes: 4096,
+        list_directory_max_entries: 50,
+        preload_model: Some("/models/preload.gguf".to_string()),
+        preload_gpu_layers: Some(20),
     };
 
     db.save_config(&config).unwrap();
@@ -88,6 +145,37 @@ fn test_save_and_load_config() {
     assert_eq!(loaded.stop_tokens, None);
     assert!(loaded.proactive_compaction);
     assert_eq!(loaded.max_tool_calls, 123);
+    assert_eq!(loaded.models_directory.as_deref(), Some("/srv/models"));
+    assert!(!loaded.allow_external_model_paths);
+    assert!(!loaded.auto_execute_tools);
+    assert_eq!((loaded.temperature_min, loaded.temperature_max), (0.1, 1.5));
+    assert_eq!(loaded.generation_timeout_secs, Some(90));
+    assert_eq!(
+        loaded.enabled_tools,
+        Some(vec!["read_file".to_string(), "list_directory".to_string()])
+    );
+    assert!(!loaded.normalize_tool_output);
+    assert_eq!(loaded.default_context_cap, 0);
+    assert!(!loaded.tools_enabled);
+    assert_eq!(loaded.reasoning_tag_open, "<reasoning>");
+    assert_eq!(loaded.reasoning_tag_close, "");
+    assert_eq!(
+        loaded.require_confirmation_for,
+        vec!["write_file".to_string()]
+    );
+    assert_eq!(loaded.max_parallel_generations, 4);
+    assert_eq!(loaded.sampler_preset.as_deref(), Some("creative"));
+    assert_eq!(loaded.sampler_presets, config.sampler_presets);
+    assert_eq!(loaded.autosave_interval_ms, 250);
+    assert!(loaded.python_isolated);
+    assert_eq!(loaded.python_venv.as_deref(), Some("/srv/venvs/tools"));
+    assert_eq!(loaded.max_tool_iterations, 3);
+    assert!(!loaded.dedupe_tool_calls);
+    assert_eq!(loaded.max_completions, 2);
+    assert_eq!(loaded.read_file_max_bytes, 4096);
+    assert_eq!(loaded.list_directory_max_entries, 50);
+    assert_eq!(loaded.preload_model.as_deref(), Some("/models/preload.gguf"));
+    assert_eq!(loaded.preload_gpu_layers, Some(20));
 }
 
 #[test]
@@ -122,6 +210,66 @@ fn test_model_history_limit() {
     assert_eq!(history[0], "/model14.gguf");
 }
 
+#[test]
+fn test_model_history_readd_moves_to_front_without_duplicates() {
+    let db = create_test_db();
+
+    db.add_to_model_history("/models/a.gguf").unwrap();
+    db.add_to_model_history("/models/b.gguf").unwrap();
+    db.add_to_model_history(" /models/a.gguf ").unwrap();
+
+    let history = db.get_model_history().unwrap();
+    assert_eq!(history, vec!["/models/a.gguf", "/models/b.gguf"]);
+}
+
+#[test]
+fn test_model_history_limit_keeps_most_recent() {
+    let db = create_test_db();
+
+    for i in 0..(MODEL_HISTORY_LIMIT + 3) {
+        db.add_to_model_history(&format!("/model{i}.gguf")).unwrap();
+    }
+
+    let entries = db.get_model_history_entries().unwrap();
+    assert_eq!(entries.len(), MODEL_HISTORY_LIMIT);
+    assert_eq!(entries[0].model_path, format!("/model{}.gguf", MODEL_HISTORY_LIMIT + 2));
+    assert!(!entries.iter().any(|e| e.model_path == "/model0.gguf"));
+}
+
+#[test]
+fn test_model_history_entry_metadata() {
+    let db = create_test_db();
+    let path = std::env::temp_dir().join(format!("history-{}.gguf", uuid::Uuid::new_v4()));
+    std::fs::write(&path, [0u8; 64]).unwrap();
+    let path_str = path.to_string_lossy().to_string();
+
+    db.add_to_model_history(&path_str).unwrap();
+    db.add_to_model_history("C:\\models\\Qwen3-8B-Q4_K_M.gguf").unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let entries = db.get_model_history_entries().unwrap();
+    assert_eq!(entries[0].display_name, "Qwen3-8B-Q4_K_M");
+    assert_eq!(entries[0].size_bytes, None);
+    assert_eq!(entries[1].size_bytes, Some(64));
+    assert!(entries[1].display_name.starts_with("history-"));
+    assert!(entries[0].last_loaded_at >= entries[1].last_loaded_at);
+}
+
+#[test]
+fn test_model_history_legacy_rows_derive_display_name() {
+    let db = create_test_db();
+    db.connection()
+        .execute(
+            "INSERT INTO model_history (model_path, last_used, display_order) VALUES ('/m/legacy.gguf', 1, 0)",
+            [],
+        )
+        .unwrap();
+
+    let entries = db.get_model_history_entries().unwrap();
+    assert_eq!(entries[0].display_name, "legacy");
+    assert_eq!(entries[0].size_bytes, None);
+}
+
 #[test]
 fn test_logs() {
     let db = create_test_db();
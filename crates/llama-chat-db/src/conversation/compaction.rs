@@ -1,6 +1,31 @@
 use super::*;
 
 impl Database {
+    /// Sequence number ending the oldest `turns` live turns, to pass to
+    /// `compact_messages`. A turn starts at a user message; messages already
+    /// under a summary don't count. None when that would leave no live turn.
+    pub fn turn_compaction_cutoff(
+        &self,
+        conversation_id: &str,
+        turns: usize,
+    ) -> Result<Option<i32>, String> {
+        let live: Vec<MessageRecord> = self
+            .get_messages(conversation_id)?
+            .into_iter()
+            .filter(|m| !m.compacted && m.role != "system")
+            .collect();
+        let turn_starts: Vec<usize> = live
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == "user")
+            .map(|(i, _)| i)
+            .collect();
+        Ok(match turn_starts.get(turns) {
+            Some(&next_turn) if turns > 0 && next_turn > 0 => Some(live[next_turn - 1].sequence_order),
+            _ => None,
+        })
+    }
+
     /// Record a compaction summary covering all messages up to `covers_to_sequence`.
     ///
     /// Any previous summary is replaced — the new summary is produced by merging the
@@ -39,7 +64,7 @@ impl Database {
         conn.execute(
             "INSERT INTO compaction_summaries \
              (id, conversation_id, covers_from_sequence, covers_to_sequence, message_count, summary_text, created_at) \
-             VALUES (?1, ?2, 1, ?3, ?4, ?5, ?6)",
+             VALUES (?1, ?2, 0, ?3, ?4, ?5, ?6)",
             params![
                 uuid::Uuid::new_v4().to_string(),
                 conversation_id,
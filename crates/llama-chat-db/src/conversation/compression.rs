@@ -0,0 +1,70 @@
+// Transparent zstd compression of large message content at rest.
+//
+// Compressed rows store the zstd frame as a BLOB in `messages.content` with
+// `compressed = 1`. Rows written before compression existed (or below the
+// threshold) keep plain TEXT content and `compressed = 0`, so reads handle both.
+
+use rusqlite::types::{ToSqlOutput, Type, Value, ValueRef};
+
+/// Messages shorter than this are always stored as plain text.
+pub(crate) const COMPRESSION_THRESHOLD_BYTES: usize = 4096;
+const ZSTD_LEVEL: i32 = 3;
+/// Leading bytes of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Content ready to bind into an INSERT/UPDATE, plus the matching `compressed` flag.
+pub(crate) struct EncodedContent<'a> {
+    pub value: ToSqlOutput<'a>,
+    pub compressed: bool,
+}
+
+/// Encode message content for storage, compressing when enabled and worthwhile.
+pub(crate) fn encode_content(content: &str, enabled: bool) -> EncodedContent<'_> {
+    if enabled && content.len() >= COMPRESSION_THRESHOLD_BYTES {
+        if let Ok(bytes) = zstd::encode_all(content.as_bytes(), ZSTD_LEVEL) {
+            if bytes.len() < content.len() {
+                return EncodedContent {
+                    value: ToSqlOutput::Owned(Value::Blob(bytes)),
+                    compressed: true,
+                };
+            }
+        }
+    }
+    EncodedContent {
+        value: ToSqlOutput::Borrowed(ValueRef::Text(content.as_bytes())),
+        compressed: false,
+    }
+}
+
+/// Read message content from a row, decompressing when the row's flag is set.
+pub(crate) fn read_content(
+    row: &rusqlite::Row<'_>,
+    content_idx: usize,
+    compressed_idx: usize,
+) -> rusqlite::Result<String> {
+    let compressed = row
+        .get::<_, Option<i32>>(compressed_idx)
+        .unwrap_or(None)
+        .unwrap_or(0)
+        != 0;
+    if !compressed {
+        return row.get(content_idx);
+    }
+
+    let bytes: Vec<u8> = row.get(content_idx)?;
+    zstd::decode_all(bytes.as_slice())
+        .map_err(|e| e.to_string())
+        .and_then(|raw| String::from_utf8(raw).map_err(|e| e.to_string()))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(content_idx, Type::Blob, e.into()))
+}
+
+/// Decode a compressed `messages.content` BLOB seen without its `compressed`
+/// flag (e.g. in ad-hoc SQL). Returns `None` for BLOBs that aren't zstd text.
+pub(crate) fn decode_blob(bytes: &[u8]) -> Option<String> {
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return None;
+    }
+    zstd::decode_all(bytes)
+        .ok()
+        .and_then(|raw| String::from_utf8(raw).ok())
+}
@@ -1,5 +1,6 @@
 // Message insert, streaming, and timing operations
 
+use super::compression::encode_content;
 use crate::{current_timestamp_millis, db_error, Database};
 use rusqlite::params;
 
@@ -27,12 +28,13 @@ impl Database {
         token_count: Option<i32>,
     ) -> Result<String, String> {
         let message_id = uuid::Uuid::new_v4().to_string();
+        let encoded = encode_content(content, self.message_compression_enabled());
         let conn = self.connection();
 
         conn.execute(
-            "INSERT INTO messages (id, conversation_id, role, content, timestamp, sequence_order, is_streaming, token_count)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7)",
-            params![message_id, conversation_id, role, content, timestamp as i64, sequence_order, token_count],
+            "INSERT INTO messages (id, conversation_id, role, content, timestamp, sequence_order, is_streaming, token_count, created_at_millis, compressed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, ?8, ?9)",
+            params![message_id, conversation_id, role, encoded.value, timestamp as i64, sequence_order, token_count, current_timestamp_millis(), encoded.compressed as i32],
         )
         .map_err(db_error("insert message"))?;
 
@@ -50,9 +52,9 @@ impl Database {
         let conn = self.connection();
 
         conn.execute(
-            "INSERT INTO messages (id, conversation_id, role, content, timestamp, sequence_order, is_streaming)
-             VALUES (?1, ?2, 'assistant', '', ?3, ?4, 1)",
-            params![message_id, conversation_id, timestamp as i64, sequence_order],
+            "INSERT INTO messages (id, conversation_id, role, content, timestamp, sequence_order, is_streaming, created_at_millis)
+             VALUES (?1, ?2, 'assistant', '', ?3, ?4, 1, ?5)",
+            params![message_id, conversation_id, timestamp as i64, sequence_order, current_timestamp_millis()],
         )
         .map_err(db_error("insert streaming message"))?;
 
@@ -108,23 +110,36 @@ impl Database {
         Ok(())
     }
 
-    /// Finalize a streaming message (copy content, clear is_streaming flag)
+    /// Finalize a streaming message (copy content, store token count, clear is_streaming flag)
     pub fn finalize_streaming_message(
         &self,
         message_id: &str,
         content: &str,
+        token_count: Option<i32>,
     ) -> Result<(), String> {
+        let encoded = encode_content(content, self.message_compression_enabled());
         let conn = self.connection();
 
         conn.execute(
-            "UPDATE messages SET content = ?1, is_streaming = 0 WHERE id = ?2",
-            params![content, message_id],
+            "UPDATE messages SET content = ?1, compressed = ?2, token_count = COALESCE(?3, token_count), is_streaming = 0 WHERE id = ?4",
+            params![encoded.value, encoded.compressed as i32, token_count, message_id],
         )
         .map_err(db_error("finalize streaming message"))?;
 
         Ok(())
     }
 
+    /// Store the reasoning split off an assistant reply (kept out of `content`).
+    pub fn set_message_reasoning(&self, message_id: &str, reasoning: &str) -> Result<(), String> {
+        let conn = self.connection();
+        conn.execute(
+            "UPDATE messages SET reasoning = ?1 WHERE id = ?2",
+            params![reasoning, message_id],
+        )
+        .map_err(db_error("set message reasoning"))?;
+        Ok(())
+    }
+
     /// Store generation timing metrics and token count on a message row.
     pub fn update_message_timings(
         &self,
@@ -145,6 +160,46 @@ impl Database {
         Ok(())
     }
 
+    /// Settle replies left streaming by a crashed generation: partial ones are
+    /// kept and flagged `interrupted`, empty placeholders are removed. Only
+    /// safe while nothing is generating, i.e. at startup. Returns how many
+    /// partial replies were flagged.
+    pub fn recover_interrupted_messages(&self) -> Result<usize, String> {
+        self.recover_streaming_messages(None)
+    }
+
+    /// [`Self::recover_interrupted_messages`] for one conversation, e.g. the
+    /// ones a crashed worker was generating for.
+    pub fn recover_interrupted_messages_in(&self, conversation_id: &str) -> Result<usize, String> {
+        self.recover_streaming_messages(Some(conversation_id))
+    }
+
+    fn recover_streaming_messages(&self, conversation_id: Option<&str>) -> Result<usize, String> {
+        let conn = self.connection();
+        // Only the buffers of the replies settled here: remote providers keep
+        // their partial replies in streaming_buffer alone, without a row
+        conn.execute(
+            "DELETE FROM streaming_buffer WHERE message_id IN \
+             (SELECT id FROM messages WHERE is_streaming = 1 AND (?1 IS NULL OR conversation_id = ?1))",
+            [conversation_id],
+        )
+        .map_err(db_error("clear streaming buffers"))?;
+        // NULL matches every conversation
+        let flagged = conn
+            .execute(
+                "UPDATE messages SET is_streaming = 0, interrupted = 1 \
+                 WHERE is_streaming = 1 AND content != '' AND (?1 IS NULL OR conversation_id = ?1)",
+                [conversation_id],
+            )
+            .map_err(db_error("flag interrupted messages"))?;
+        conn.execute(
+            "DELETE FROM messages WHERE is_streaming = 1 AND (?1 IS NULL OR conversation_id = ?1)",
+            [conversation_id],
+        )
+        .map_err(db_error("delete empty streaming messages"))?;
+        Ok(flagged)
+    }
+
     /// Delete streaming buffer for a conversation
     pub fn delete_streaming_buffer(&self, conversation_id: &str) -> Result<(), String> {
         let conn = self.connection();
@@ -2,12 +2,19 @@
 
 pub use crate::logger::ConversationLogger;
 
-use super::{current_timestamp_millis, db_error, generate_conversation_id, Database};
+use super::{
+    current_timestamp_millis, db_error, generate_conversation_id, ConversationEvent, Database,
+};
+use llama_chat_types::GenerationOverrides;
 use rusqlite::params;
 
 mod compaction;
+pub(crate) mod compression;
 mod messages;
 mod queries;
+mod transcript;
+
+pub use transcript::{transcript_blocks, TranscriptBlock, TranscriptEntry};
 
 /// Conversation metadata
 #[derive(Debug, Clone)]
@@ -19,6 +26,23 @@ pub struct ConversationRecord {
     pub provider_session_id: Option<String>,
 }
 
+/// Lightweight conversation listing entry for sidebars — no message bodies.
+#[derive(Debug, Clone)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    /// First `PREVIEW_CHARS` characters of the most recent message, if any.
+    pub last_message_preview: Option<String>,
+    pub message_count: i64,
+    /// Last activity time in milliseconds since Unix epoch.
+    pub updated_at: i64,
+    pub worker_id: Option<String>,
+    pub provider_id: Option<String>,
+}
+
+/// Maximum characters of the last message included in a `ConversationSummary`.
+pub const PREVIEW_CHARS: usize = 120;
+
 /// Message record from database
 #[derive(Debug, Clone)]
 pub struct MessageRecord {
@@ -39,6 +63,14 @@ pub struct MessageRecord {
     pub parts: Option<String>,
     /// LLM-generated short title (≤50 chars). Set by background title gen; user messages only.
     pub title: Option<String>,
+    /// Token count (exact for generated messages, estimated for logged ones). None on legacy rows.
+    pub token_count: Option<i32>,
+    /// Creation time in milliseconds since Unix epoch. None on legacy rows.
+    pub created_at_millis: Option<i64>,
+    /// Reasoning block split off an assistant reply. Never part of the prompt.
+    pub reasoning: Option<String>,
+    /// Reply cut off by a crash mid-generation; `content` is what was saved before it.
+    pub interrupted: bool,
 }
 
 /// A compaction summary — records which message range has been summarized.
@@ -73,6 +105,10 @@ impl CompactionSummaryRecord {
             sequence_order: self.covers_to_sequence,
             parts: None,
             title: None,
+            token_count: None,
+            created_at_millis: None,
+            reasoning: None,
+            interrupted: false,
         }
     }
 }
@@ -135,7 +171,7 @@ impl Database {
     }
 
     /// List all conversations (newest first)
-    pub fn list_conversations(&self) -> Result<Vec<ConversationRecord>, String> {
+    pub fn list_all_conversations(&self) -> Result<Vec<ConversationRecord>, String> {
         let conn = self.connection();
         let mut stmt = conn
             .prepare(
@@ -160,8 +196,55 @@ impl Database {
         Ok(records)
     }
 
-    /// Delete a conversation (cascades to messages)
-    pub fn delete_conversation(&self, id: &str) -> Result<(), String> {
+    /// List one page of conversation summaries, most recently updated first.
+    pub fn list_conversations(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<ConversationSummary>, String> {
+        let conn = self.connection();
+        let mut stmt = conn
+            .prepare(
+                "SELECT c.id, COALESCE(c.title, ''), c.updated_at, c.worker_id, c.provider_id,
+                        (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id),
+                        last.content, last.compressed
+                 FROM conversations c
+                 LEFT JOIN messages last ON last.id = (
+                     SELECT m.id FROM messages m WHERE m.conversation_id = c.id
+                     ORDER BY m.sequence_order DESC LIMIT 1
+                 )
+                 ORDER BY c.updated_at DESC, c.id DESC
+                 LIMIT ?1 OFFSET ?2",
+            )
+            .map_err(db_error("prepare statement"))?;
+
+        let summaries = stmt
+            .query_map(params![limit as i64, offset as i64], |row| {
+                let last_content = match row.get_ref(6)? {
+                    rusqlite::types::ValueRef::Null => None,
+                    _ => Some(compression::read_content(row, 6, 7)?),
+                };
+                Ok(ConversationSummary {
+                    id: row.get(0)?,
+                    title: row.get::<_, String>(1).unwrap_or_default(),
+                    updated_at: row.get(2)?,
+                    worker_id: row.get(3)?,
+                    provider_id: row.get(4)?,
+                    message_count: row.get(5)?,
+                    last_message_preview: last_content
+                        .map(|c| c.chars().take(PREVIEW_CHARS).collect()),
+                })
+            })
+            .map_err(db_error("query conversation summaries"))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(summaries)
+    }
+
+    /// Delete a conversation (cascades to messages). Returns false for unknown ids.
+    /// Broadcasts a `deleted` streaming update so other clients refresh their lists.
+    pub fn delete_conversation(&self, id: &str) -> Result<bool, String> {
         let conn = self.connection();
 
         // Delete streaming buffer first
@@ -176,10 +259,56 @@ impl Database {
             .map_err(db_error("delete messages"))?;
 
         // Delete conversation
-        conn.execute("DELETE FROM conversations WHERE id = ?1", [id])
+        let removed = conn
+            .execute("DELETE FROM conversations WHERE id = ?1", [id])
             .map_err(db_error("delete conversation"))?;
+        drop(conn);
 
-        Ok(())
+        if removed == 0 {
+            return Ok(false);
+        }
+        self.broadcast_conversation_event(ConversationEvent::Deleted {
+            conversation_id: id.to_string(),
+        });
+        Ok(true)
+    }
+
+    /// Delete every conversation and its messages in one transaction. Returns how
+    /// many conversations were removed; each gets a `Deleted` event.
+    pub fn clear_all_conversations(&self) -> Result<usize, String> {
+        let conn = self.connection();
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(db_error("begin clear conversations"))?;
+
+        let ids: Vec<String> = {
+            let mut stmt = tx
+                .prepare("SELECT id FROM conversations")
+                .map_err(db_error("prepare statement"))?;
+            let ids = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(db_error("query conversation ids"))?
+                .filter_map(|r| r.ok())
+                .collect();
+            ids
+        };
+
+        tx.execute("DELETE FROM streaming_buffer", [])
+            .map_err(db_error("clear streaming buffer"))?;
+        tx.execute("DELETE FROM messages", [])
+            .map_err(db_error("clear messages"))?;
+        // Remaining per-conversation rows cascade from this delete
+        tx.execute("DELETE FROM conversations", [])
+            .map_err(db_error("clear conversations"))?;
+        tx.commit().map_err(db_error("commit clear conversations"))?;
+        drop(conn);
+
+        for id in &ids {
+            self.broadcast_conversation_event(ConversationEvent::Deleted {
+                conversation_id: id.clone(),
+            });
+        }
+        Ok(ids.len())
     }
 
     /// Update conversation timestamp
@@ -205,6 +334,28 @@ impl Database {
         Ok(())
     }
 
+    /// Rename a conversation and announce it to event subscribers. Returns
+    /// `false` if no conversation has this id.
+    pub fn set_conversation_title(&self, id: &str, title: &str) -> Result<bool, String> {
+        let conn = self.connection();
+        let updated = conn
+            .execute(
+                "UPDATE conversations SET title = ?1, updated_at = ?2 WHERE id = ?3",
+                params![title, current_timestamp_millis(), id],
+            )
+            .map_err(db_error("rename conversation"))?;
+        drop(conn);
+
+        if updated == 0 {
+            return Ok(false);
+        }
+        self.broadcast_conversation_event(ConversationEvent::Renamed {
+            conversation_id: id.to_string(),
+            title: title.to_string(),
+        });
+        Ok(true)
+    }
+
     /// Get conversation title (returns None if not set)
     pub fn get_conversation_title(&self, id: &str) -> Result<Option<String>, String> {
         let conn = self.connection();
@@ -291,6 +442,70 @@ impl Database {
         Ok(())
     }
 
+    /// Get the per-conversation system prompt. NULL means "use the agent / global prompt".
+    pub fn get_conversation_system_prompt(&self, id: &str) -> Result<Option<String>, String> {
+        let conn = self.connection();
+        let result = conn.query_row(
+            "SELECT system_prompt FROM conversations WHERE id = ?1",
+            [id],
+            |row| row.get::<_, Option<String>>(0),
+        );
+        match result {
+            Ok(system_prompt) => Ok(system_prompt),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(format!("Failed to get conversation system prompt: {e}")),
+        }
+    }
+
+    /// Set or clear the per-conversation system prompt.
+    pub fn set_conversation_system_prompt(
+        &self,
+        id: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<(), String> {
+        let conn = self.connection();
+        conn.execute(
+            "UPDATE conversations SET system_prompt = ?1 WHERE id = ?2",
+            params![system_prompt, id],
+        )
+        .map_err(db_error("update conversation system prompt"))?;
+        Ok(())
+    }
+
+    /// Get the per-conversation sampler overrides. NULL (or unparsable JSON) means none.
+    pub fn get_conversation_overrides(&self, id: &str) -> Result<Option<GenerationOverrides>, String> {
+        let conn = self.connection();
+        let result = conn.query_row(
+            "SELECT overrides FROM conversations WHERE id = ?1",
+            [id],
+            |row| row.get::<_, Option<String>>(0),
+        );
+        match result {
+            Ok(json) => Ok(json.and_then(|j| serde_json::from_str(&j).ok())),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(format!("Failed to get conversation overrides: {e}")),
+        }
+    }
+
+    /// Set or clear the per-conversation sampler overrides, stored as sparse JSON.
+    pub fn set_conversation_overrides(
+        &self,
+        id: &str,
+        overrides: Option<&GenerationOverrides>,
+    ) -> Result<(), String> {
+        let json = overrides
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| format!("Failed to serialize conversation overrides: {e}"))?;
+        let conn = self.connection();
+        conn.execute(
+            "UPDATE conversations SET overrides = ?1 WHERE id = ?2",
+            params![json, id],
+        )
+        .map_err(db_error("update conversation overrides"))?;
+        Ok(())
+    }
+
     /// Clear worker binding for all conversations bound to the given worker.
     pub fn clear_worker_id_for_worker(&self, worker_id: &str) -> Result<usize, String> {
         let conn = self.connection();
@@ -343,6 +558,14 @@ impl Database {
         }
         rows.into_iter().map(|(_, content)| content).collect()
     }
+
+    /// Drop a conversation's queued messages, e.g. when its generation is
+    /// cancelled. Returns how many were dropped.
+    pub fn clear_queued_messages(&self, conversation_id: &str) -> Result<usize, String> {
+        let conn = self.connection();
+        conn.execute("DELETE FROM message_queue WHERE conversation_id = ?1", [conversation_id])
+            .map_err(db_error("clear queued messages"))
+    }
 }
 
 #[cfg(test)]
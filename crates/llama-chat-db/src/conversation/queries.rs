@@ -3,6 +3,7 @@
 use crate::{db_error, Database};
 use rusqlite::{params, OptionalExtension};
 
+use super::compression::read_content;
 use super::{CompactionSummaryRecord, MessageRecord};
 
 impl Database {
@@ -38,6 +39,18 @@ impl Database {
         Ok(records)
     }
 
+    /// Like `get_messages`, but distinguishes an unknown conversation (`None`)
+    /// from an existing one with no messages (`Some(vec![])`).
+    pub fn get_messages_if_exists(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<Vec<MessageRecord>>, String> {
+        if !self.conversation_exists(conversation_id)? {
+            return Ok(None);
+        }
+        self.get_messages(conversation_id).map(Some)
+    }
+
     /// Get all messages for a conversation (in order), with compaction metadata applied.
     ///
     /// Messages that fall within a compaction summary's range have `compacted=true`.
@@ -50,7 +63,7 @@ impl Database {
         let mut stmt = conn
             .prepare(
                 "SELECT role, content, timestamp, prompt_tok_per_sec, gen_tok_per_sec, gen_eval_ms, gen_tokens, \
-                 prompt_eval_ms, prompt_tokens, sequence_order, parts, title \
+                 prompt_eval_ms, prompt_tokens, sequence_order, parts, title, token_count, created_at_millis, compressed, reasoning, interrupted \
                  FROM messages WHERE conversation_id = ?1 ORDER BY sequence_order ASC",
             )
             .map_err(db_error("prepare messages"))?;
@@ -59,7 +72,7 @@ impl Database {
             .query_map([conversation_id], |row| {
                 Ok(MessageRecord {
                     role: row.get(0)?,
-                    content: row.get(1)?,
+                    content: read_content(row, 1, 14)?,
                     timestamp: row.get::<_, i64>(2).unwrap_or(0) as u64,
                     prompt_tok_per_sec: row.get(3)?,
                     gen_tok_per_sec: row.get(4)?,
@@ -71,6 +84,10 @@ impl Database {
                     sequence_order: row.get(9).unwrap_or(0),
                     parts: row.get(10).unwrap_or(None),
                     title: row.get(11).unwrap_or(None),
+                    token_count: row.get(12).unwrap_or(None),
+                    created_at_millis: row.get(13).unwrap_or(None),
+                    reasoning: row.get(15).unwrap_or(None),
+                    interrupted: row.get::<_, Option<i32>>(16).unwrap_or(None).unwrap_or(0) != 0,
                 })
             })
             .map_err(db_error("query messages"))?
@@ -164,7 +181,7 @@ impl Database {
         // Emit user/assistant messages that come after the summarized range.
         let mut stmt = conn
             .prepare(
-                "SELECT role, content FROM messages \
+                "SELECT role, content, compressed FROM messages \
                  WHERE conversation_id = ?1 AND sequence_order > ?2 AND role != 'system' \
                  ORDER BY sequence_order ASC",
             )
@@ -172,7 +189,7 @@ impl Database {
 
         let rows = stmt
             .query_map(params![conversation_id, start_after], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                Ok((row.get::<_, String>(0)?, read_content(row, 1, 2)?))
             })
             .map_err(db_error("query conversation text"))?;
 
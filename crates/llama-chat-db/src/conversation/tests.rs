@@ -58,6 +58,634 @@ fn test_delete_conversation() {
     db.insert_message(&id, "user", "Test", 0, 0).unwrap();
 
     assert!(db.conversation_exists(&id).unwrap());
-    db.delete_conversation(&id).unwrap();
+    assert!(db.delete_conversation(&id).unwrap());
     assert!(!db.conversation_exists(&id).unwrap());
+    assert_eq!(count_messages(&db, &id), 0);
+}
+
+fn count_messages(db: &Database, conversation_id: &str) -> i64 {
+    db.connection()
+        .query_row(
+            "SELECT COUNT(*) FROM messages WHERE conversation_id = ?1",
+            [conversation_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_delete_unknown_conversation_returns_false() {
+    let db = create_test_db();
+    let mut events = db.subscribe_conversation_events();
+    assert!(!db.delete_conversation("chat_does-not-exist").unwrap());
+    // Nothing was deleted, so nothing is announced
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn test_delete_conversation_leaves_others_intact() {
+    let db = create_test_db();
+    let doomed = db.create_conversation().unwrap();
+    let kept = db.create_conversation().unwrap();
+    db.insert_message(&doomed, "user", "bye", 0, 0).unwrap();
+    db.insert_message(&kept, "user", "stay", 0, 0).unwrap();
+    db.insert_message(&kept, "assistant", "staying", 0, 0).unwrap();
+
+    let mut events = db.subscribe_conversation_events();
+    let mut tokens = db.subscribe_streaming();
+    // More token updates than the streaming channel buffers
+    for _ in 0..1500 {
+        db.broadcast_streaming_update(crate::StreamingUpdate {
+            conversation_id: kept.clone(),
+            partial_content: String::new(),
+            tokens_used: 0,
+            max_tokens: 0,
+            is_complete: false,
+        });
+    }
+    assert!(db.delete_conversation(&doomed).unwrap());
+
+    assert!(db.conversation_exists(&kept).unwrap());
+    assert_eq!(count_messages(&db, &kept), 2);
+    assert!(matches!(
+        tokens.try_recv(),
+        Err(tokio::sync::broadcast::error::TryRecvError::Lagged(_))
+    ));
+    // The token flood overran the streaming channel; the deletion still arrives
+    assert_eq!(
+        events.try_recv().unwrap(),
+        crate::ConversationEvent::Deleted { conversation_id: doomed }
+    );
+}
+
+#[test]
+fn test_clear_all_conversations() {
+    let db = create_test_db();
+    let first = db.create_conversation().unwrap();
+    let second = db.create_conversation().unwrap();
+    db.insert_message(&first, "user", "one", 0, 0).unwrap();
+    db.insert_message(&second, "user", "two", 0, 0).unwrap();
+
+    let mut events = db.subscribe_conversation_events();
+    assert_eq!(db.clear_all_conversations().unwrap(), 2);
+
+    assert!(db.list_conversations(50, 0).unwrap().is_empty());
+    assert_eq!(count_messages(&db, &first), 0);
+    assert_eq!(count_messages(&db, &second), 0);
+    let mut deleted: Vec<String> = std::iter::from_fn(|| events.try_recv().ok())
+        .inspect(|e| assert!(matches!(e, crate::ConversationEvent::Deleted { .. })))
+        .map(|e| e.conversation_id().to_string())
+        .collect();
+    deleted.sort();
+    let mut expected = vec![first, second];
+    expected.sort();
+    assert_eq!(deleted, expected);
+
+    assert_eq!(db.clear_all_conversations().unwrap(), 0);
+}
+
+#[test]
+fn test_set_conversation_title() {
+    let db = create_test_db();
+    let id = db.create_conversation().unwrap();
+
+    let mut events = db.subscribe_conversation_events();
+    assert!(db.set_conversation_title(&id, "Trip planning").unwrap());
+    assert_eq!(
+        db.get_conversation_title(&id).unwrap().as_deref(),
+        Some("Trip planning")
+    );
+    assert_eq!(
+        events.try_recv().unwrap(),
+        crate::ConversationEvent::Renamed {
+            conversation_id: id,
+            title: "Trip planning".to_string(),
+        }
+    );
+
+    assert!(!db
+        .set_conversation_title("chat_does-not-exist", "x")
+        .unwrap());
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn test_conversation_system_prompts_are_independent() {
+    let db = create_test_db();
+    let pirate = ConversationLogger::new_with_system_prompt(db.clone(), "You are a pirate.")
+        .unwrap()
+        .get_conversation_id();
+    let poet = ConversationLogger::new_with_system_prompt(db.clone(), "You are a poet.")
+        .unwrap()
+        .get_conversation_id();
+
+    let pirate_config = db.load_effective_config(&pirate);
+    let poet_config = db.load_effective_config(&poet);
+    assert_eq!(pirate_config.system_prompt.as_deref(), Some("You are a pirate."));
+    assert_eq!(poet_config.system_prompt.as_deref(), Some("You are a poet."));
+    assert_ne!(pirate_config.system_prompt, poet_config.system_prompt);
+}
+
+#[test]
+fn test_global_system_prompt_used_when_conversation_has_none() {
+    let db = create_test_db();
+    let conv_id = ConversationLogger::new(db.clone(), Some("Resolved global prompt"))
+        .unwrap()
+        .get_conversation_id();
+
+    assert_eq!(db.get_conversation_system_prompt(&conv_id).unwrap(), None);
+    assert_eq!(
+        db.load_effective_config(&conv_id).system_prompt,
+        db.load_config().system_prompt
+    );
+}
+
+#[test]
+fn test_assistant_message_stores_token_count_and_timestamp() {
+    let db = create_test_db();
+    let before = crate::current_timestamp_millis();
+    let mut logger = ConversationLogger::new(db.clone(), None).unwrap();
+    let conv_id = logger.get_conversation_id();
+
+    logger.log_message("USER", "How many tokens is this?");
+    logger.start_assistant_message();
+    logger.log_token("About ");
+    logger.log_token("seven.");
+    logger.set_message_token_count(2);
+    logger.finish_assistant_message();
+    let after = crate::current_timestamp_millis();
+
+    let messages = db.get_messages(&conv_id).unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].token_count.unwrap_or(0) > 0);
+
+    let assistant = &messages[1];
+    assert_eq!(assistant.role, "assistant");
+    assert_eq!(assistant.token_count, Some(2));
+    let created = assistant.created_at_millis.expect("created_at_millis set");
+    assert!(created >= before && created <= after);
+}
+
+#[test]
+fn test_reasoning_is_stored_apart_and_left_out_of_next_prompt() {
+    let db = create_test_db();
+    let mut logger = ConversationLogger::new(db.clone(), None).unwrap();
+    logger.set_reasoning_tags(Some(llama_chat_types::ReasoningTags {
+        open: "<think>".to_string(),
+        close: "</think>".to_string(),
+    }));
+    let conv_id = logger.get_conversation_id();
+
+    logger.log_message("USER", "What is 2+2?");
+    logger.start_assistant_message();
+    logger.log_token("<think>Simple arithmetic.</think>");
+    logger.log_token("\n\nIt's 4.");
+    logger.finish_assistant_message();
+
+    let messages = db.get_messages(&conv_id).unwrap();
+    assert_eq!(messages[1].content, "It's 4.");
+    assert_eq!(messages[1].reasoning.as_deref(), Some("Simple arithmetic."));
+    assert_eq!(messages[0].reasoning, None);
+
+    let next_prompt = db.get_conversation_as_text(&conv_id).unwrap();
+    assert!(next_prompt.contains("ASSISTANT:\nIt's 4."), "{next_prompt}");
+    assert!(!next_prompt.contains("arithmetic"), "{next_prompt}");
+    assert!(!next_prompt.contains("<think>"), "{next_prompt}");
+}
+
+fn stored_compressed_flag(db: &Database, conv_id: &str, role: &str) -> i32 {
+    db.connection()
+        .query_row(
+            "SELECT compressed FROM messages WHERE conversation_id = ?1 AND role = ?2",
+            [conv_id, role],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_large_message_compressed_round_trip() {
+    let db = create_test_db();
+    db.set_message_compression(true);
+    let mut logger = ConversationLogger::new(db.clone(), None).unwrap();
+    let conv_id = logger.get_conversation_id();
+
+    let big_output = "drwxr-xr-x  2 user user 4096 src/\n".repeat(500);
+    logger.log_message("USER", "list it");
+    logger.start_assistant_message();
+    logger.log_token_bulk(&big_output);
+    logger.finish_assistant_message();
+
+    assert_eq!(stored_compressed_flag(&db, &conv_id, "assistant"), 1);
+    let messages = db.get_messages(&conv_id).unwrap();
+    assert_eq!(messages[1].content, big_output);
+    assert!(db
+        .get_conversation_as_text(&conv_id)
+        .unwrap()
+        .contains(&big_output));
+}
+
+#[test]
+fn test_small_message_stays_uncompressed() {
+    let db = create_test_db();
+    db.set_message_compression(true);
+    let conv_id = db.create_conversation().unwrap();
+    db.insert_message(&conv_id, "user", "Hello", 0, 0).unwrap();
+
+    assert_eq!(stored_compressed_flag(&db, &conv_id, "user"), 0);
+    assert_eq!(db.get_messages(&conv_id).unwrap()[0].content, "Hello");
+}
+
+fn set_updated_at(db: &Database, id: &str, updated_at: i64) {
+    db.connection()
+        .execute(
+            "UPDATE conversations SET updated_at = ?1 WHERE id = ?2",
+            rusqlite::params![updated_at, id],
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_list_conversations_orders_by_recency() {
+    let db = create_test_db();
+    let old = db.create_conversation().unwrap();
+    let newest = db.create_conversation().unwrap();
+    let middle = db.create_conversation().unwrap();
+    set_updated_at(&db, &old, 1_000);
+    set_updated_at(&db, &newest, 3_000);
+    set_updated_at(&db, &middle, 2_000);
+
+    db.insert_message(&middle, "user", "first", 1, 0).unwrap();
+    db.insert_message(&middle, "assistant", "latest reply", 2, 1).unwrap();
+
+    let page = db.list_conversations(10, 0).unwrap();
+    let ids: Vec<&str> = page.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec![newest.as_str(), middle.as_str(), old.as_str()]);
+
+    assert_eq!(page[1].message_count, 2);
+    assert_eq!(page[1].last_message_preview.as_deref(), Some("latest reply"));
+    assert_eq!(page[0].message_count, 0);
+    assert!(page[0].last_message_preview.is_none());
+}
+
+#[test]
+fn test_list_conversations_pages_are_disjoint() {
+    let db = create_test_db();
+    for i in 0..5 {
+        let id = db.create_conversation().unwrap();
+        set_updated_at(&db, &id, 1_000 + i);
+    }
+
+    let first = db.list_conversations(2, 0).unwrap();
+    let second = db.list_conversations(2, 2).unwrap();
+    let third = db.list_conversations(2, 4).unwrap();
+    assert_eq!((first.len(), second.len(), third.len()), (2, 2, 1));
+
+    let mut ids: Vec<String> = first
+        .into_iter()
+        .chain(second)
+        .chain(third)
+        .map(|s| s.id)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 5);
+}
+
+#[test]
+fn test_list_conversations_preview_is_truncated() {
+    let db = create_test_db();
+    let id = db.create_conversation().unwrap();
+    db.insert_message(&id, "user", &"x".repeat(PREVIEW_CHARS * 2), 1, 0)
+        .unwrap();
+
+    let page = db.list_conversations(1, 0).unwrap();
+    assert_eq!(
+        page[0].last_message_preview.as_ref().map(|p| p.chars().count()),
+        Some(PREVIEW_CHARS)
+    );
+}
+
+#[test]
+fn test_get_messages_if_exists_unknown_id() {
+    let db = create_test_db();
+    assert!(db.get_messages_if_exists("chat_does-not-exist").unwrap().is_none());
+}
+
+#[test]
+fn test_get_messages_if_exists_empty_conversation() {
+    let db = create_test_db();
+    let id = db.create_conversation().unwrap();
+    assert_eq!(db.get_messages_if_exists(&id).unwrap().map(|m| m.len()), Some(0));
+}
+
+/// A CLI session as written by `src/test_support/logger.rs`: the command runs
+/// mid-generation, so its block lands before the final ASSISTANT reply.
+const LEGACY_CHAT: &str = "SYSTEM:\nYou are a helpful assistant.\n\n\
+USER:\nhello\n\n\
+ASSISTANT:\nHello! How can I help?\n\n\
+USER:\ncheck the file notes.txt\n\n\
+[COMMAND: cat notes.txt]\nfirst line\n\nsecond paragraph\n\n\
+ASSISTANT:\n<COMMAND>cat notes.txt</COMMAND>\nThe file has two paragraphs.\n\n\
+USER:\nthanks\n\n\
+ERROR:\nGeneration failed: context full\n\n";
+
+#[test]
+fn test_parse_legacy_conversation_folds_commands() {
+    let messages = crate::logger::parse_legacy_conversation(LEGACY_CHAT);
+    let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(
+        roles,
+        ["system", "user", "assistant", "user", "assistant", "user"]
+    );
+    assert_eq!(messages[0].content, "You are a helpful assistant.");
+    assert_eq!(messages[2].content, "Hello! How can I help?");
+    assert_eq!(
+        messages[4].content,
+        "[COMMAND: cat notes.txt]\nfirst line\n\nsecond paragraph\n\n\
+         <COMMAND>cat notes.txt</COMMAND>\nThe file has two paragraphs."
+    );
+    assert_eq!(messages[5].content, "thanks");
+}
+
+#[test]
+fn test_parse_legacy_command_after_assistant() {
+    let text = "USER:\nlist files\n\nASSISTANT:\nRunning ls.\n\n[COMMAND: ls]\na.txt\nb.txt\n\n";
+    let messages = crate::logger::parse_legacy_conversation(text);
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1].role, "assistant");
+    assert_eq!(messages[1].content, "Running ls.\n\n[COMMAND: ls]\na.txt\nb.txt");
+}
+
+#[test]
+fn test_import_legacy_conversation_file() {
+    let db = create_test_db();
+    let path = std::env::temp_dir().join(format!(
+        "chat_2025-01-01-12-00-00-{}.txt",
+        std::process::id()
+    ));
+    std::fs::write(&path, LEGACY_CHAT).unwrap();
+
+    let conv_id =
+        ConversationLogger::import_from_text(db.clone(), path.to_str().unwrap()).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    let messages = db.get_messages(&conv_id).unwrap();
+    let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(
+        roles,
+        ["system", "user", "assistant", "user", "assistant", "user"]
+    );
+    assert!(messages[4].content.starts_with("[COMMAND: cat notes.txt]\nfirst line"));
+    assert!(messages[4].content.ends_with("The file has two paragraphs."));
+    let sequences: Vec<i32> = messages.iter().map(|m| m.sequence_order).collect();
+    assert_eq!(sequences, [0, 1, 2, 3, 4, 5]);
+    assert_eq!(
+        db.get_conversation_title(&conv_id).unwrap().as_deref(),
+        path.file_stem().and_then(|s| s.to_str())
+    );
+}
+
+#[test]
+fn test_import_empty_legacy_file_fails() {
+    let db = create_test_db();
+    let path = std::env::temp_dir().join(format!("chat_empty-{}.txt", std::process::id()));
+    std::fs::write(&path, "SYSTEM:\nonly a prompt\n\n").unwrap();
+
+    let result = ConversationLogger::import_from_text(db.clone(), path.to_str().unwrap());
+    let _ = std::fs::remove_file(&path);
+    assert!(result.unwrap_err().contains("No messages found"));
+    assert!(ConversationLogger::import_from_text(db, "/nonexistent/chat_x.txt").is_err());
+}
+
+#[test]
+fn test_transcript_blocks_split_tools_and_commands() {
+    let content = "Checking.\n[COMMAND: ls -a]\na.txt\nb.txt\n\n\
+<tool_call>{\"name\": \"read_file\"}</tool_call>\n\
+<tool_response>hello</tool_response>\nDone.";
+    assert_eq!(
+        transcript_blocks(content),
+        vec![
+            TranscriptBlock::Text {
+                text: "Checking.".to_string()
+            },
+            TranscriptBlock::Command {
+                command: "ls -a".to_string(),
+                output: "a.txt\nb.txt".to_string()
+            },
+            TranscriptBlock::ToolCall {
+                text: "{\"name\": \"read_file\"}".to_string()
+            },
+            TranscriptBlock::ToolResponse {
+                text: "hello".to_string()
+            },
+            TranscriptBlock::Text {
+                text: "Done.".to_string()
+            },
+        ]
+    );
+    // Only a line-start header opens a command block
+    assert_eq!(
+        transcript_blocks("see [COMMAND: x] inline"),
+        vec![TranscriptBlock::Text {
+            text: "see [COMMAND: x] inline".to_string()
+        }]
+    );
+}
+
+#[test]
+fn test_transcript_keeps_system_notices_and_commands_in_order() {
+    let db = create_test_db();
+    let id = db.create_conversation().unwrap();
+    db.insert_message(&id, "user", "list files", 100, 0)
+        .unwrap();
+    db.insert_message(&id, "system", "[WARNING] Context is 90% full", 101, 1)
+        .unwrap();
+    db.insert_message(
+        &id,
+        "assistant",
+        "[COMMAND: ls]\na.txt\n\nThere is one file.",
+        102,
+        2,
+    )
+    .unwrap();
+
+    let entries = db.get_transcript(&id).unwrap().unwrap();
+    let roles: Vec<&str> = entries.iter().map(|e| e.role.as_str()).collect();
+    assert_eq!(roles, ["user", "system", "assistant"]);
+    assert_eq!(
+        entries.iter().map(|e| e.timestamp).collect::<Vec<_>>(),
+        [100, 101, 102]
+    );
+    assert_eq!(
+        entries[1].blocks,
+        vec![TranscriptBlock::Text {
+            text: "[WARNING] Context is 90% full".to_string()
+        }]
+    );
+    assert_eq!(
+        entries[2].blocks,
+        vec![
+            TranscriptBlock::Command {
+                command: "ls".to_string(),
+                output: "a.txt".to_string()
+            },
+            TranscriptBlock::Text {
+                text: "There is one file.".to_string()
+            },
+        ]
+    );
+
+    assert!(db.get_transcript("chat_missing").unwrap().is_none());
+}
+
+fn buffered_content(db: &Database, conv_id: &str) -> Option<String> {
+    db.connection()
+        .query_row(
+            "SELECT partial_content FROM streaming_buffer WHERE conversation_id = ?1",
+            [conv_id],
+            |row| row.get(0),
+        )
+        .ok()
+}
+
+#[test]
+fn test_recovery_settles_local_replies_and_keeps_provider_buffers() {
+    let db = create_test_db();
+    let mut logger = ConversationLogger::new(db.clone(), None).unwrap();
+    let conv_id = logger.get_conversation_id();
+    logger.set_autosave_interval(std::time::Duration::ZERO);
+
+    logger.log_message("USER", "Write a long story");
+    logger.start_assistant_message();
+    logger.log_token_bulk("Once upon ");
+    logger.log_token_bulk("a time");
+    // The worker dies here: no finish_assistant_message
+    drop(logger);
+    assert!(buffered_content(&db, &conv_id).is_some());
+
+    // A second conversation, cut off before anything was saved
+    let mut other = ConversationLogger::new(db.clone(), None).unwrap();
+    let other_id = other.get_conversation_id();
+    other.log_message("USER", "Hi");
+    other.start_assistant_message();
+    drop(other);
+
+    // A remote provider reply, which lives in the buffer alone
+    let provider_id = db.create_conversation().unwrap();
+    db.init_streaming_buffer(&provider_id, "provider_msg").unwrap();
+    db.update_streaming_buffer(&provider_id, "Partial remote reply", 12, 0).unwrap();
+
+    assert_eq!(db.recover_interrupted_messages().unwrap(), 1);
+    let messages = db.get_messages(&conv_id).unwrap();
+    assert!(messages[1].interrupted);
+    assert_eq!(messages[1].content, "Once upon a time");
+    assert!(buffered_content(&db, &conv_id).is_none());
+    // The empty placeholder is gone rather than flagged
+    assert_eq!(db.get_messages(&other_id).unwrap().len(), 1);
+    // The partial reply stays in the history the next turn continues from
+    assert!(db
+        .get_conversation_as_text(&conv_id)
+        .unwrap()
+        .contains("ASSISTANT:\nOnce upon a time"));
+    assert_eq!(buffered_content(&db, &provider_id).as_deref(), Some("Partial remote reply"));
+
+    // Nothing left to recover, and the provider buffer still waits for its reload
+    assert_eq!(db.recover_interrupted_messages().unwrap(), 0);
+    assert!(buffered_content(&db, &provider_id).is_some());
+}
+
+#[test]
+fn test_autosave_interval_throttles_partial_saves() {
+    let db = create_test_db();
+    let mut logger = ConversationLogger::new(db.clone(), None).unwrap();
+    let conv_id = logger.get_conversation_id();
+    logger.set_autosave_interval(std::time::Duration::from_secs(3600));
+
+    logger.log_message("USER", "Go");
+    logger.start_assistant_message();
+    logger.log_token_bulk("first ");
+    logger.log_token_bulk("second");
+    drop(logger);
+
+    // Only the first chunk was saved before the interval
+    db.recover_interrupted_messages_in(&conv_id).unwrap();
+    let messages = db.get_messages(&conv_id).unwrap();
+    assert_eq!(messages[1].content, "first ");
+    assert!(messages[1].interrupted);
+}
+
+fn insert_turns(db: &Database, conv_id: &str, turns: i32) {
+    for turn in 0..turns {
+        db.insert_message(conv_id, "user", &format!("question {turn}"), 0, turn * 2).unwrap();
+        db.insert_message(conv_id, "assistant", &format!("answer {turn}"), 0, turn * 2 + 1).unwrap();
+    }
+}
+
+fn live_messages(db: &Database, conv_id: &str) -> Vec<MessageRecord> {
+    db.get_messages(conv_id).unwrap().into_iter().filter(|m| !m.compacted).collect()
+}
+
+#[test]
+fn test_compacting_oldest_turns_archives_them_behind_a_summary() {
+    let db = create_test_db();
+    let conv_id = db.create_conversation().unwrap();
+    insert_turns(&db, &conv_id, 4);
+    assert_eq!(live_messages(&db, &conv_id).len(), 8);
+
+    let cutoff = db.turn_compaction_cutoff(&conv_id, 3).unwrap().expect("cutoff");
+    assert_eq!(db.compact_messages(&conv_id, cutoff, "Asked three questions.").unwrap(), 6);
+
+    // Live: the summary plus the last turn
+    let live = live_messages(&db, &conv_id);
+    assert_eq!(live.len(), 3);
+    assert_eq!(live[0].role, "system");
+    assert!(live[0].content.contains("Asked three questions."));
+    assert_eq!(live[1].content, "question 3");
+
+    // The originals are kept, flagged as compacted
+    let archived: Vec<_> = db
+        .get_messages(&conv_id)
+        .unwrap()
+        .into_iter()
+        .filter(|m| m.compacted)
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(archived.len(), 6);
+    assert_eq!(archived[0], "question 0");
+    assert_eq!(archived[5], "answer 2");
+
+    let text = db.get_conversation_as_text(&conv_id).unwrap();
+    assert!(text.contains("Asked three questions."));
+    assert!(!text.contains("question 0"));
+    assert!(text.contains("question 3"));
+}
+
+#[test]
+fn test_turn_compaction_cutoff_keeps_a_live_turn() {
+    let db = create_test_db();
+    let conv_id = db.create_conversation().unwrap();
+    insert_turns(&db, &conv_id, 2);
+
+    assert_eq!(db.turn_compaction_cutoff(&conv_id, 2).unwrap(), None);
+    assert_eq!(db.turn_compaction_cutoff(&conv_id, 0).unwrap(), None);
+    let cutoff = db.turn_compaction_cutoff(&conv_id, 1).unwrap().expect("cutoff");
+    db.compact_messages(&conv_id, cutoff, "First turn.").unwrap();
+
+    // Compacted turns no longer count
+    assert_eq!(db.turn_compaction_cutoff(&conv_id, 1).unwrap(), None);
+}
+
+#[test]
+fn test_clearing_the_queue_only_drops_that_conversation() {
+    let db = create_test_db();
+    let a = db.create_conversation().unwrap();
+    let b = db.create_conversation().unwrap();
+    db.queue_message(&a, "first").unwrap();
+    db.queue_message(&a, "second").unwrap();
+    db.queue_message(&b, "other").unwrap();
+
+    assert_eq!(db.clear_queued_messages(&a).unwrap(), 2);
+    assert!(db.pop_queued_messages(&a).is_empty());
+    assert_eq!(db.pop_queued_messages(&b), ["other"]);
 }
@@ -0,0 +1,140 @@
+// Debug transcript: every stored message in order, split into text/tool/command blocks
+
+use serde::Serialize;
+
+use crate::Database;
+
+/// One piece of a stored message, in the order it appeared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TranscriptBlock {
+    Text {
+        text: String,
+    },
+    /// `[COMMAND: cmd]` header and the output logged after it.
+    Command {
+        command: String,
+        output: String,
+    },
+    /// Model tool call between `<tool_call>` tags.
+    ToolCall {
+        text: String,
+    },
+    /// Tool result between `<tool_response>` tags.
+    ToolResponse {
+        text: String,
+    },
+}
+
+/// A stored message (including SYSTEM notices, compacted messages and compaction
+/// summaries) with its content split into blocks.
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptEntry {
+    pub sequence_order: i32,
+    pub role: String,
+    /// Seconds since Unix epoch.
+    pub timestamp: u64,
+    pub created_at_millis: Option<i64>,
+    pub compacted: bool,
+    pub blocks: Vec<TranscriptBlock>,
+}
+
+const TOOL_TAGS: &[(&str, &str)] = &[
+    ("<tool_call>", "</tool_call>"),
+    ("<tool_response>", "</tool_response>"),
+];
+const COMMAND_HEADER: &str = "[COMMAND: ";
+
+/// Split message content into blocks. Command output runs to the first blank
+/// line, as the logger writes it; an unterminated tool tag runs to the end.
+/// Tool formats other than `<tool_call>`/`<tool_response>` stay in text blocks.
+pub fn transcript_blocks(content: &str) -> Vec<TranscriptBlock> {
+    let mut blocks = Vec::new();
+    let mut rest = content;
+    loop {
+        let tag = TOOL_TAGS
+            .iter()
+            .filter_map(|&(open, close)| rest.find(open).map(|pos| (pos, open, close)))
+            .min_by_key(|&(pos, _, _)| pos);
+        let command = find_command_header(rest);
+        let tag = tag.filter(|&(pos, _, _)| command.is_none_or(|c| pos < c));
+
+        if let Some((pos, open, close)) = tag {
+            push_text(&mut blocks, &rest[..pos]);
+            let body_start = pos + open.len();
+            let (body, after) = match rest[body_start..].find(close) {
+                Some(end) => (
+                    &rest[body_start..body_start + end],
+                    &rest[body_start + end + close.len()..],
+                ),
+                None => (&rest[body_start..], ""),
+            };
+            let text = body.trim().to_string();
+            blocks.push(if open == "<tool_call>" {
+                TranscriptBlock::ToolCall { text }
+            } else {
+                TranscriptBlock::ToolResponse { text }
+            });
+            rest = after;
+        } else if let Some(pos) = command {
+            push_text(&mut blocks, &rest[..pos]);
+            let header_end = rest[pos..].find('\n').map_or(rest.len(), |i| pos + i);
+            let command = rest[pos + COMMAND_HEADER.len()..header_end]
+                .trim_end()
+                .trim_end_matches(']')
+                .to_string();
+            let body = rest.get(header_end + 1..).unwrap_or("");
+            let (output, after) = body.split_once("\n\n").unwrap_or((body, ""));
+            blocks.push(TranscriptBlock::Command {
+                command,
+                output: output.to_string(),
+            });
+            rest = after;
+        } else {
+            push_text(&mut blocks, rest);
+            return blocks;
+        }
+    }
+}
+
+/// Byte offset of the first `[COMMAND: ` that starts a line.
+fn find_command_header(text: &str) -> Option<usize> {
+    text.match_indices(COMMAND_HEADER)
+        .map(|(pos, _)| pos)
+        .find(|&pos| pos == 0 || text.as_bytes()[pos - 1] == b'\n')
+}
+
+fn push_text(blocks: &mut Vec<TranscriptBlock>, text: &str) {
+    let text = text.trim_matches('\n');
+    if !text.trim().is_empty() {
+        blocks.push(TranscriptBlock::Text {
+            text: text.to_string(),
+        });
+    }
+}
+
+impl Database {
+    /// Full ordered transcript of a conversation for debugging, or `None` if the
+    /// conversation doesn't exist. Unlike export, nothing is filtered out.
+    pub fn get_transcript(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<Vec<TranscriptEntry>>, String> {
+        let Some(messages) = self.get_messages_if_exists(conversation_id)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            messages
+                .into_iter()
+                .map(|m| TranscriptEntry {
+                    blocks: transcript_blocks(&m.content),
+                    sequence_order: m.sequence_order,
+                    role: m.role,
+                    timestamp: m.timestamp,
+                    created_at_millis: m.created_at_millis,
+                    compacted: m.compacted,
+                })
+                .collect(),
+        ))
+    }
+}
@@ -17,11 +17,14 @@ pub mod logger;
 #[allow(dead_code)]
 pub mod mcp;
 pub mod pending_approvals;
+pub mod read_only_query;
 pub mod schema;
+pub mod tool_calls;
 
 use rusqlite::Connection;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
 
 /// Streaming update sent via broadcast channel for real-time WebSocket updates
 #[derive(Clone, Debug)]
@@ -34,11 +37,37 @@ pub struct StreamingUpdate {
     pub is_complete: bool,
 }
 
+/// A change to the conversation list rather than to a conversation's content.
+///
+/// These go out on a channel of their own with an unbounded queue per
+/// subscriber, so a burst of token updates can't make a client miss one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversationEvent {
+    /// The conversation was deleted; clients should drop it from their lists.
+    Deleted { conversation_id: String },
+    /// The conversation was renamed to `title`.
+    Renamed { conversation_id: String, title: String },
+}
+
+impl ConversationEvent {
+    pub fn conversation_id(&self) -> &str {
+        match self {
+            Self::Deleted { conversation_id } | Self::Renamed { conversation_id, .. } => {
+                conversation_id
+            }
+        }
+    }
+}
+
 /// Main database wrapper with connection pool and streaming broadcast
 pub struct Database {
     conn: Mutex<Connection>,
     /// Broadcast channel for real-time streaming updates
     streaming_tx: broadcast::Sender<StreamingUpdate>,
+    /// One sender per `subscribe_conversation_events` caller still listening
+    conversation_event_txs: Mutex<Vec<mpsc::UnboundedSender<ConversationEvent>>>,
+    /// Mirrors `config.compress_messages`; read on every message write
+    compress_messages: AtomicBool,
 }
 
 /// Shared database type for passing across async boundaries
@@ -66,10 +95,15 @@ impl Database {
         // Create broadcast channel with buffer for 1000 messages
         let (streaming_tx, _) = broadcast::channel(1000);
 
-        Ok(Self {
+        let db = Self {
             conn: Mutex::new(conn),
             streaming_tx,
-        })
+            conversation_event_txs: Mutex::new(Vec::new()),
+            compress_messages: AtomicBool::new(false),
+        };
+        db.set_message_compression(db.load_config().compress_messages);
+
+        Ok(db)
     }
 
     /// Get a reference to the connection (locked)
@@ -77,11 +111,49 @@ impl Database {
         self.conn.lock().expect("Database lock poisoned")
     }
 
+    /// Checkpoint pending writes to the main database file (called on shutdown).
+    ///
+    /// Taking the connection lock also waits out any in-flight statement.
+    pub fn flush(&self) -> Result<(), String> {
+        let conn = self.connection();
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE); PRAGMA optimize;")
+            .map_err(db_error("flush database"))
+    }
+
     /// Subscribe to streaming updates for WebSocket handlers
     pub fn subscribe_streaming(&self) -> broadcast::Receiver<StreamingUpdate> {
         self.streaming_tx.subscribe()
     }
 
+    /// Subscribe to conversation deletions and renames. Unlike streaming
+    /// updates, none are dropped for a slow subscriber.
+    pub fn subscribe_conversation_events(&self) -> mpsc::UnboundedReceiver<ConversationEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.conversation_event_txs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tx);
+        rx
+    }
+
+    /// Send a conversation event to every subscriber, forgetting closed ones.
+    pub fn broadcast_conversation_event(&self, event: ConversationEvent) {
+        self.conversation_event_txs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|tx| tx.send(event.clone()).is_ok());
+    }
+
+    /// Enable or disable zstd compression for newly written message content.
+    pub fn set_message_compression(&self, enabled: bool) {
+        self.compress_messages.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether newly written message content should be compressed.
+    pub fn message_compression_enabled(&self) -> bool {
+        self.compress_messages.load(Ordering::Relaxed)
+    }
+
     /// Broadcast a streaming update to all WebSocket subscribers
     pub fn broadcast_streaming_update(&self, update: StreamingUpdate) {
         // Ignore send errors (no subscribers)
@@ -107,7 +179,14 @@ pub fn current_timestamp_secs() -> u64 {
         .as_secs()
 }
 
-/// Generate a new conversation ID with timestamp format: chat_YYYY-MM-DD-HH-mm-ss-SSS
+/// Per-process counter for the id suffix; ids minted in the same millisecond
+/// by one process differ here and keep their creation order.
+static CONVERSATION_ID_SEQ: AtomicU32 = AtomicU32::new(0);
+
+/// Generate a new conversation ID: chat_YYYY-MM-DD-HH-mm-ss-SSS-QQQQRRRR
+///
+/// The suffix is a 4-hex per-process sequence (QQQQ) and 4 random hex digits
+/// (RRRR, for ids minted by other processes in the same millisecond).
 pub fn generate_conversation_id() -> String {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -154,7 +233,19 @@ pub fn generate_conversation_id() -> String {
 
     let day = remaining_days + 1;
 
-    format!("chat_{year:04}-{month:02}-{day:02}-{hours:02}-{minutes:02}-{seconds:02}-{millis:03}")
+    let seq = CONVERSATION_ID_SEQ.fetch_add(1, Ordering::Relaxed) & 0xffff;
+    let random = uuid::Uuid::new_v4().as_u128() & 0xffff;
+    format!(
+        "chat_{year:04}-{month:02}-{day:02}-{hours:02}-{minutes:02}-{seconds:02}-{millis:03}-{seq:04x}{random:04x}"
+    )
+}
+
+/// The `YYYY-MM-DD-HH-mm-ss-SSS` part of a conversation ID, without the
+/// `chat_` prefix or uniqueness suffix. IDs in another format are returned whole.
+pub fn conversation_id_timestamp(id: &str) -> &str {
+    const TIMESTAMP_LEN: usize = "YYYY-MM-DD-HH-mm-ss-SSS".len();
+    let rest = id.strip_prefix("chat_").unwrap_or(id);
+    rest.get(..TIMESTAMP_LEN).unwrap_or(rest)
 }
 
 fn is_leap_year(year: i64) -> bool {
@@ -169,7 +260,28 @@ mod tests {
     fn test_generate_conversation_id() {
         let id = generate_conversation_id();
         assert!(id.starts_with("chat_"));
-        assert_eq!(id.len(), 28); // chat_YYYY-MM-DD-HH-mm-ss-SSS
+        assert_eq!(id.len(), 37); // chat_YYYY-MM-DD-HH-mm-ss-SSS-QQQQRRRR
+        assert_eq!(conversation_id_timestamp(&id).len(), 23);
+        assert_eq!(
+            conversation_id_timestamp("chat_2026-01-02-03-04-05-678-00a1beef"),
+            "2026-01-02-03-04-05-678"
+        );
+        // Pre-suffix ids still parse
+        assert_eq!(
+            conversation_id_timestamp("chat_2026-01-02-03-04-05-678"),
+            "2026-01-02-03-04-05-678"
+        );
+    }
+
+    #[test]
+    fn test_conversation_ids_unique_in_tight_loop() {
+        let ids: Vec<String> = (0..10_000).map(|_| generate_conversation_id()).collect();
+        let unique: std::collections::HashSet<&String> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+        // Ordering by id follows creation order at millisecond resolution
+        for pair in ids.windows(2) {
+            assert!(conversation_id_timestamp(&pair[0]) <= conversation_id_timestamp(&pair[1]));
+        }
     }
 
     #[test]
@@ -4,15 +4,86 @@
 //! accumulated content, WebSocket broadcast throttling, and periodic DB flushing
 //! for crash recovery. Database CRUD lives in `conversation.rs`.
 
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use llama_chat_types::{split_reasoning, ReasoningTags};
+
 use super::{current_timestamp_secs, Database, StreamingUpdate};
 
 const STREAM_BROADCAST_MIN_INTERVAL: Duration = Duration::from_millis(200);
 const STREAM_BROADCAST_MIN_CHARS: usize = 64;
-/// How often to persist streaming content to DB for crash recovery.
-const DB_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+/// How often to persist streaming content to DB for crash recovery, unless
+/// `set_autosave_interval` says otherwise.
+const DB_FLUSH_INTERVAL: Duration =
+    Duration::from_millis(llama_chat_types::models::DEFAULT_AUTOSAVE_INTERVAL_MS as u64);
+
+/// Rough token estimate (~4 chars/token) for messages logged without a tokenizer.
+fn estimate_token_count(text: &str) -> i32 {
+    (text.len() / 4).max(1) as i32
+}
+
+/// Role headers written by the legacy CLI logger (`src/test_support/logger.rs`).
+const LEGACY_ROLE_HEADERS: [&str; 4] = ["SYSTEM:", "USER:", "ASSISTANT:", "ERROR:"];
+
+/// One message recovered from a legacy CLI conversation file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// Parse a legacy CLI log (`assets/conversations/chat_*.txt`).
+///
+/// The file is a sequence of `ROLE:\n<text>\n\n` blocks. `[COMMAND: cmd]\n<output>\n\n`
+/// blocks are folded into the assistant turn they belong to (the CLI writes them
+/// before the final reply), and `ERROR:` blocks are dropped.
+pub fn parse_legacy_conversation(text: &str) -> Vec<LegacyMessage> {
+    let mut messages: Vec<LegacyMessage> = Vec::new();
+    let mut header: Option<&str> = None;
+    let mut body = String::new();
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        let is_header = LEGACY_ROLE_HEADERS.contains(&trimmed)
+            || (trimmed.starts_with("[COMMAND: ") && trimmed.ends_with(']'));
+        if is_header {
+            if let Some(header) = header {
+                push_legacy_block(&mut messages, header, &body);
+            }
+            header = Some(trimmed);
+            body.clear();
+        } else if header.is_some() {
+            body.push_str(line);
+        }
+    }
+    if let Some(header) = header {
+        push_legacy_block(&mut messages, header, &body);
+    }
+    messages
+}
+
+fn push_legacy_block(messages: &mut Vec<LegacyMessage>, header: &str, body: &str) {
+    let body = body.trim_end_matches('\n');
+    let (role, content) = match header {
+        "SYSTEM:" => ("system", body.to_string()),
+        "USER:" => ("user", body.to_string()),
+        "ASSISTANT:" => ("assistant", body.to_string()),
+        "ERROR:" => return,
+        command => ("assistant", format!("{command}\n{body}")),
+    };
+    match messages.last_mut() {
+        Some(last) if role == "assistant" && last.role == "assistant" => {
+            last.content.push_str("\n\n");
+            last.content.push_str(&content);
+        }
+        _ => messages.push(LegacyMessage {
+            role: role.to_string(),
+            content,
+        }),
+    }
+}
 
 /// SQLite-backed conversation logger.
 ///
@@ -36,6 +107,12 @@ pub struct ConversationLogger {
     last_db_flush: Option<Instant>,
     /// Length of accumulated_content at last DB flush
     last_db_flush_len: usize,
+    /// Minimum time between those flushes
+    autosave_interval: Duration,
+    /// Exact token count of the streaming message, reported by the generation loop
+    message_token_count: Option<i32>,
+    /// When set, reasoning blocks are split off the finished message into their own column
+    reasoning_tags: Option<ReasoningTags>,
 }
 
 impl ConversationLogger {
@@ -65,9 +142,24 @@ impl ConversationLogger {
             current_max_tokens: 0,
             last_db_flush: None,
             last_db_flush_len: 0,
+            autosave_interval: DB_FLUSH_INTERVAL,
+            message_token_count: None,
+            reasoning_tags: None,
         })
     }
 
+    /// Create a new conversation pinned to its own system prompt.
+    ///
+    /// The prompt is stored on the conversation row and takes precedence over the
+    /// agent / global prompt on every turn (see `Database::load_effective_config`).
+    pub fn new_with_system_prompt(db: Arc<Database>, system_prompt: &str) -> Result<Self, String> {
+        let logger = Self::new(db, Some(system_prompt))?;
+        logger
+            .db
+            .set_conversation_system_prompt(&logger.conversation_id, Some(system_prompt))?;
+        Ok(logger)
+    }
+
     /// Load an existing conversation.
     pub fn from_existing(db: Arc<Database>, conversation_id: &str) -> Result<Self, String> {
         if !db.conversation_exists(conversation_id)? {
@@ -89,12 +181,55 @@ impl ConversationLogger {
             current_max_tokens: 0,
             last_db_flush: None,
             last_db_flush_len: 0,
+            autosave_interval: DB_FLUSH_INTERVAL,
+            message_token_count: None,
+            reasoning_tags: None,
         })
     }
 
-    /// Log a complete message (typically a user message).
+    /// Import a legacy CLI conversation file as a new conversation.
+    ///
+    /// Returns the new conversation ID. A leading `SYSTEM:` block becomes the
+    /// system prompt; see `parse_legacy_conversation` for the rest of the format.
+    pub fn import_from_text(db: Arc<Database>, path: &str) -> Result<String, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+        let mut messages = parse_legacy_conversation(&text);
+        let system_prompt = match messages.first() {
+            Some(first) if first.role == "system" => Some(messages.remove(0).content),
+            _ => None,
+        };
+        if messages.is_empty() {
+            return Err(format!("No messages found in {path}"));
+        }
+
+        let mut logger = Self::new(db, system_prompt.as_deref())?;
+        for message in &messages {
+            if let Err(e) = logger.db.insert_message_with_tokens(
+                &logger.conversation_id,
+                &message.role,
+                &message.content,
+                current_timestamp_secs(),
+                logger.sequence_counter,
+                Some(estimate_token_count(&message.content)),
+            ) {
+                // Don't leave a half-imported conversation behind
+                let _ = logger.db.delete_conversation(&logger.conversation_id);
+                return Err(e);
+            }
+            logger.sequence_counter += 1;
+        }
+        if let Some(stem) = Path::new(path).file_stem().and_then(|s| s.to_str()) {
+            let _ = logger
+                .db
+                .update_conversation_title(&logger.conversation_id, stem);
+        }
+        Ok(logger.conversation_id)
+    }
+
+    /// Log a complete message (typically a user message) with an estimated token count.
     pub fn log_message(&mut self, role: &str, message: &str) {
-        self.log_message_with_tokens(role, message, None);
+        self.log_message_with_tokens(role, message, Some(estimate_token_count(message)));
     }
 
     /// Log a message with an optional pre-computed token count.
@@ -125,6 +260,11 @@ impl ConversationLogger {
         let _ = self.db.update_conversation_timestamp(&self.conversation_id);
     }
 
+    /// How often the streaming message is saved to the DB (`autosave_interval_ms`).
+    pub fn set_autosave_interval(&mut self, interval: Duration) {
+        self.autosave_interval = interval;
+    }
+
     /// Start streaming an assistant message.
     pub fn start_assistant_message(&mut self) {
         let message_id = uuid::Uuid::new_v4().to_string();
@@ -151,9 +291,12 @@ impl ConversationLogger {
 
         self.current_message_id = Some(message_id);
         self.accumulated_content.clear();
+        self.message_token_count = None;
         self.sequence_counter += 1;
         self.last_broadcast_at = None;
         self.last_broadcast_len = 0;
+        self.last_db_flush = None;
+        self.last_db_flush_len = 0;
     }
 
     /// Update token counts from the generation loop (call before log_token).
@@ -162,6 +305,12 @@ impl ConversationLogger {
         self.current_max_tokens = max_tokens;
     }
 
+    /// Record the exact number of tokens generated for the current streaming message
+    /// (call before `finish_assistant_message`). Without it the count is estimated.
+    pub fn set_message_token_count(&mut self, token_count: i32) {
+        self.message_token_count = Some(token_count);
+    }
+
     /// Append a token to the current streaming message.
     ///
     /// Only accumulates in memory + broadcasts via WebSocket (throttled).
@@ -232,7 +381,7 @@ impl ConversationLogger {
             let should_flush = match self.last_db_flush {
                 None => true,
                 Some(last) => {
-                    now.duration_since(last) >= DB_FLUSH_INTERVAL && len > self.last_db_flush_len
+                    now.duration_since(last) >= self.autosave_interval && len > self.last_db_flush_len
                 }
             };
             if should_flush {
@@ -247,16 +396,37 @@ impl ConversationLogger {
         }
     }
 
+    /// Split reasoning blocks delimited by `tags` off messages finished from now on.
+    pub fn set_reasoning_tags(&mut self, tags: Option<ReasoningTags>) {
+        self.reasoning_tags = tags;
+    }
+
     /// Finish the current streaming message.
+    ///
+    /// With reasoning tags set, only the answer is stored as content (and so fed
+    /// back into later prompts); the reasoning goes to its own column.
     pub fn finish_assistant_message(&mut self) {
         if let Some(ref msg_id) = self.current_message_id {
-            // Update the message with final content
+            let (content, reasoning) = match &self.reasoning_tags {
+                Some(tags) => split_reasoning(&self.accumulated_content, tags),
+                None => (self.accumulated_content.clone(), None),
+            };
+            // Update the message with final content and token count
+            let token_count = self
+                .message_token_count
+                .filter(|n| *n > 0)
+                .unwrap_or_else(|| estimate_token_count(&self.accumulated_content));
             if let Err(e) = self
                 .db
-                .finalize_streaming_message(msg_id, &self.accumulated_content)
+                .finalize_streaming_message(msg_id, &content, Some(token_count))
             {
                 sys_error!("Failed to finalize streaming message: {}", e);
             }
+            if let Some(reasoning) = reasoning {
+                if let Err(e) = self.db.set_message_reasoning(msg_id, &reasoning) {
+                    sys_error!("Failed to store message reasoning: {}", e);
+                }
+            }
 
             // Clean up streaming buffer
             if let Err(e) = self.db.delete_streaming_buffer(&self.conversation_id) {
@@ -277,6 +447,7 @@ impl ConversationLogger {
 
         self.last_finished_message_id = self.current_message_id.take();
         self.accumulated_content.clear();
+        self.message_token_count = None;
 
         // Update conversation timestamp
         let _ = self.db.update_conversation_timestamp(&self.conversation_id);
@@ -0,0 +1,263 @@
+// Read-only SQL over the app database, for the `sql_query` tool.
+//
+// Only a single SELECT (optionally behind a WITH) is accepted. File-backed
+// databases are queried through a separate read-only connection; in-memory
+// ones (tests) through the shared connection with `query_only` set for the
+// duration of the statement. An authorizer keeps the settings table and the
+// secret columns out of reach.
+//
+// Compressed message content (see `conversation::compression`) is decoded in
+// results, and `message_text(content)` decodes it inside WHERE/LIKE clauses.
+
+use super::conversation::compression::decode_blob;
+use super::{db_error, Database};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, OpenFlags};
+
+/// Tables the query tool may not read at all: `config` holds the bot token,
+/// provider API keys and the remote access token.
+const HIDDEN_TABLES: &[&str] = &["config"];
+
+/// Secret columns, denied wherever they appear.
+const SECRET_COLUMNS: &[&str] = &[
+    "telegram_bot_token",
+    "provider_api_keys",
+    "remote_access_token",
+    "env_vars",
+];
+
+/// Rows returned by [`Database::read_only_query`], values rendered as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// More rows matched than `max_rows`.
+    pub truncated: bool,
+}
+
+/// Reject anything that doesn't start as a query (`SELECT` or `WITH`); a
+/// trailing `;` is allowed. Further statements are caught when preparing,
+/// so semicolons inside string literals are fine.
+pub fn validate_select(sql: &str) -> Result<&str, String> {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let first_word: String = sql
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    if !first_word.eq_ignore_ascii_case("select") && !first_word.eq_ignore_ascii_case("with") {
+        return Err("Only SELECT statements are allowed".to_string());
+    }
+    Ok(sql)
+}
+
+fn authorize(ctx: AuthContext<'_>) -> Authorization {
+    match ctx.action {
+        AuthAction::Read { table_name, column_name }
+            if HIDDEN_TABLES.iter().any(|t| t.eq_ignore_ascii_case(table_name))
+                || SECRET_COLUMNS.iter().any(|c| c.eq_ignore_ascii_case(column_name)) =>
+        {
+            Authorization::Deny
+        }
+        _ => Authorization::Allow,
+    }
+}
+
+impl Database {
+    /// Run one SELECT and return up to `max_rows` rows.
+    pub fn read_only_query(&self, sql: &str, max_rows: usize) -> Result<QueryRows, String> {
+        let sql = validate_select(sql)?;
+        let shared = self.connection();
+        let path = shared.path().filter(|p| !p.is_empty()).map(str::to_string);
+        match path {
+            Some(path) => {
+                let conn = Connection::open_with_flags(
+                    &path,
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                )
+                .map_err(db_error("open read-only connection"))?;
+                drop(shared);
+                run_select(&conn, sql, max_rows)
+            }
+            None => {
+                shared
+                    .pragma_update(None, "query_only", true)
+                    .map_err(db_error("enable query_only"))?;
+                let result = run_select(&shared, sql, max_rows);
+                shared
+                    .pragma_update(None, "query_only", false)
+                    .map_err(db_error("disable query_only"))?;
+                result
+            }
+        }
+    }
+}
+
+/// Register `message_text(x)`: `x` with compressed content BLOBs decoded to text.
+fn register_message_text(conn: &Connection) -> Result<(), String> {
+    conn.create_scalar_function(
+        "message_text",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let raw = ctx.get_raw(0);
+            Ok(match raw {
+                ValueRef::Blob(b) => decode_blob(b).map_or_else(|| Value::from(raw), Value::Text),
+                _ => Value::from(raw),
+            })
+        },
+    )
+    .map_err(db_error("register message_text"))
+}
+
+fn run_select(conn: &Connection, sql: &str, max_rows: usize) -> Result<QueryRows, String> {
+    register_message_text(conn)?;
+    conn.authorizer(Some(authorize));
+    let result = query_rows(conn, sql, max_rows);
+    conn.authorizer(None::<fn(AuthContext<'_>) -> Authorization>);
+    result
+}
+
+fn query_rows(conn: &Connection, sql: &str, max_rows: usize) -> Result<QueryRows, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| match e {
+        rusqlite::Error::MultipleStatement => "Only a single statement is allowed".to_string(),
+        e => db_error("prepare query")(e),
+    })?;
+    // Catches writes hidden from the keyword check (e.g. a SELECT calling a mutating function)
+    if !stmt.readonly() {
+        return Err("Only read-only statements are allowed".to_string());
+    }
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows_iter = stmt.query([]).map_err(db_error("run query"))?;
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    while let Some(row) = rows_iter.next().map_err(db_error("read row"))? {
+        if rows.len() == max_rows {
+            truncated = true;
+            break;
+        }
+        let values = (0..columns.len())
+            .map(|i| row.get_ref(i).map(render_value))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error("read column"))?;
+        rows.push(values);
+    }
+    Ok(QueryRows { columns, rows, truncated })
+}
+
+fn render_value(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_string(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).replace('\n', "\\n"),
+        ValueRef::Blob(b) => match decode_blob(b) {
+            Some(text) => text.replace('\n', "\\n"),
+            None => format!("<blob {} bytes>", b.len()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_returns_rows() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "hello\nthere", 10, 0).unwrap();
+
+        let result = db
+            .read_only_query("SELECT role, content FROM messages;", 10)
+            .unwrap();
+        assert_eq!(result.columns, vec!["role", "content"]);
+        assert_eq!(result.rows, vec![vec!["user".to_string(), "hello\\nthere".to_string()]]);
+        assert!(!result.truncated);
+
+        db.insert_message(&id, "assistant", "hi", 11, 1).unwrap();
+        let result = db.read_only_query("select id from messages", 1).unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn test_compressed_content_is_decoded_and_searchable() {
+        let db = Database::new(":memory:").unwrap();
+        db.set_message_compression(true);
+        let id = db.create_conversation().unwrap();
+        let big = "needle in a log line\n".repeat(400);
+        db.insert_message(&id, "assistant", &big, 10, 0).unwrap();
+
+        let result = db.read_only_query("SELECT content FROM messages", 10).unwrap();
+        assert_eq!(result.rows[0][0], big.replace('\n', "\\n"));
+
+        let decoded = db
+            .read_only_query(
+                "SELECT id FROM messages WHERE message_text(content) LIKE '%needle%'",
+                10,
+            )
+            .unwrap();
+        assert_eq!(decoded.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_non_select_statements_are_rejected() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        for sql in [
+            "INSERT INTO conversations (id, created_at, updated_at) VALUES ('x', 0, 0)",
+            "UPDATE conversations SET title = 'x'",
+            "DELETE FROM conversations",
+            "PRAGMA query_only = OFF",
+            "SELECT 1; DELETE FROM conversations",
+            "WITH gone AS (SELECT id FROM conversations) DELETE FROM conversations",
+        ] {
+            assert!(db.read_only_query(sql, 10).is_err(), "{sql}");
+        }
+        assert!(db.conversation_exists(&id).unwrap());
+
+        // The shared connection is writable again afterwards
+        assert!(db.create_conversation().is_ok());
+    }
+
+    #[test]
+    fn test_ctes_and_semicolons_in_strings_are_accepted() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "a; b", 10, 0).unwrap();
+
+        let result = db
+            .read_only_query(
+                "WITH recent AS (SELECT content FROM messages) SELECT content FROM recent WHERE content LIKE '%;%';",
+                10,
+            )
+            .unwrap();
+        assert_eq!(result.rows, vec![vec!["a; b".to_string()]]);
+    }
+
+    #[test]
+    fn test_settings_and_secrets_are_unreadable() {
+        let db = Database::new(":memory:").unwrap();
+        let mut config = db.load_config();
+        config.telegram_bot_token = Some("bot-secret".to_string());
+        db.save_config(&config).unwrap();
+
+        for sql in [
+            "SELECT telegram_bot_token, provider_api_keys, remote_access_token FROM config",
+            "SELECT * FROM config",
+            "SELECT model_path FROM config",
+            "WITH c AS (SELECT * FROM config) SELECT * FROM c",
+            "SELECT (SELECT telegram_bot_token FROM config) AS t FROM conversations",
+            "SELECT env_vars FROM mcp_servers",
+        ] {
+            let err = db.read_only_query(sql, 10).unwrap_err();
+            assert!(err.contains("not authorized") || err.contains("prohibited"), "{sql}: {err}");
+        }
+
+        // Other tables stay queryable afterwards, and the app can still read its settings
+        assert!(db.read_only_query("SELECT id FROM conversations", 10).is_ok());
+        assert_eq!(db.load_config().telegram_bot_token.as_deref(), Some("bot-secret"));
+    }
+}
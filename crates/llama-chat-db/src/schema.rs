@@ -40,6 +40,11 @@ pub fn initialize(conn: &Connection) -> Result<(), String> {
             CREATE_COMPACTION_SUMMARIES_INDEX,
         ),
         ("agents", CREATE_AGENTS_TABLE),
+        ("tool_calls", CREATE_TOOL_CALLS_TABLE),
+        (
+            "tool_calls_conversation_index",
+            CREATE_TOOL_CALLS_CONVERSATION_INDEX,
+        ),
     ];
 
     for (name, sql) in statements.iter() {
@@ -65,6 +70,8 @@ pub fn initialize(conn: &Connection) -> Result<(), String> {
     // overrides = sparse JSON of per-conversation param deltas from the agent baseline.
     let _ = conn.execute("ALTER TABLE conversations ADD COLUMN agent_id TEXT", []);
     let _ = conn.execute("ALTER TABLE conversations ADD COLUMN overrides TEXT", []);
+    // Per-conversation system prompt. NULL = resolve from the agent / global config.
+    let _ = conn.execute("ALTER TABLE conversations ADD COLUMN system_prompt TEXT", []);
     let _ = conn.execute(
         "ALTER TABLE conversations ADD COLUMN heartbeat_enabled INTEGER DEFAULT 0",
         [],
@@ -176,10 +183,6 @@ pub fn initialize(conn: &Connection) -> Result<(), String> {
         [],
     );
 
-    // Drop old per-conversation prompt column; effective prompts now resolve from agents plus
-    // sparse conversation overrides. Requires SQLite 3.35+; errors ignored.
-    let _ = conn.execute("ALTER TABLE conversations DROP COLUMN system_prompt", []);
-
     // Drop orphaned agent_heartbeat table (migrated to per-conversation heartbeat_* columns
     // on conversations). Errors ignored — table may already be absent on fresh DBs.
     let _ = conn.execute("DROP TABLE IF EXISTS agent_heartbeat", []);
@@ -198,6 +201,147 @@ pub fn initialize(conn: &Connection) -> Result<(), String> {
     // Remote access token (generated once, used for Bearer auth from non-localhost clients)
     let _ = conn.execute("ALTER TABLE config ADD COLUMN remote_access_token TEXT", []);
 
+    // Millisecond-precision creation time (legacy `timestamp` column is in seconds)
+    let _ = conn.execute("ALTER TABLE messages ADD COLUMN created_at_millis INTEGER", []);
+
+    // zstd compression of large message content (content holds a BLOB when compressed = 1)
+    let _ = conn.execute("ALTER TABLE messages ADD COLUMN compressed INTEGER DEFAULT 0", []);
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN compress_messages INTEGER DEFAULT 0",
+        [],
+    );
+
+    // Model history metadata (legacy rows have NULLs; display name is derived on read)
+    let _ = conn.execute("ALTER TABLE model_history ADD COLUMN display_name TEXT", []);
+    let _ = conn.execute("ALTER TABLE model_history ADD COLUMN size_bytes INTEGER", []);
+
+    // Opt-out for absolute model paths outside models_directory
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN allow_external_model_paths INTEGER DEFAULT 1",
+        [],
+    );
+
+    // Tool-call handling: 1 = execute inline, 0 = stop and return calls to the caller
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN auto_execute_tools INTEGER DEFAULT 1",
+        [],
+    );
+
+    // Temperature guardrails applied at sampler construction
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN temperature_min REAL DEFAULT 0.0",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN temperature_max REAL DEFAULT 2.0",
+        [],
+    );
+
+    // Wall-clock cap per generation (seconds); NULL = off
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN generation_timeout_secs INTEGER",
+        [],
+    );
+
+    // JSON array of enabled native tool names; NULL = all tools
+    let _ = conn.execute("ALTER TABLE config ADD COLUMN enabled_tools TEXT", []);
+
+    // Whitespace normalization of tool output before it reaches the model
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN normalize_tool_output INTEGER DEFAULT 1",
+        [],
+    );
+
+    // Context size cap used when no context_size is set; 0 = model's full context
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN default_context_cap INTEGER DEFAULT 8192",
+        [],
+    );
+
+    // Tool definitions and dispatch on/off (off = plain chat)
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN tools_enabled INTEGER DEFAULT 1",
+        [],
+    );
+
+    // Reasoning block tags (blank = no stripping) and the reasoning split off each reply
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN reasoning_tag_open TEXT DEFAULT '<think>'",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN reasoning_tag_close TEXT DEFAULT '</think>'",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE messages ADD COLUMN reasoning TEXT", []);
+
+    // JSON array of tool names whose calls wait for user confirmation
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN require_confirmation_for TEXT",
+        [],
+    );
+
+    // Generations the worker runs concurrently on one loaded model
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN max_parallel_generations INTEGER DEFAULT 1",
+        [],
+    );
+
+    // Named sampler preset (precise/balanced/creative/deterministic)
+    let _ = conn.execute("ALTER TABLE config ADD COLUMN sampler_preset TEXT", []);
+
+    // How often a streaming reply is saved, and replies cut off by a crash
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN autosave_interval_ms INTEGER DEFAULT 5000",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE messages ADD COLUMN interrupted INTEGER DEFAULT 0",
+        [],
+    );
+
+    // Isolated execute_python: per-conversation directory and an optional virtualenv
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN python_isolated INTEGER DEFAULT 0",
+        [],
+    );
+    let _ = conn.execute("ALTER TABLE config ADD COLUMN python_venv TEXT", []);
+
+    // Tool executions per user turn before the wrap-up notice
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN max_tool_iterations INTEGER DEFAULT 200",
+        [],
+    );
+
+    // Repeated identical tool calls reuse the earlier result
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN dedupe_tool_calls INTEGER DEFAULT 1",
+        [],
+    );
+
+    // Cap on completions per request (`n`)
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN max_completions INTEGER DEFAULT 4",
+        [],
+    );
+
+    // File tool output caps (read_file bytes, list_directory entries)
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN read_file_max_bytes INTEGER DEFAULT 102400",
+        [],
+    );
+    let _ = conn.execute(
+        "ALTER TABLE config ADD COLUMN list_directory_max_entries INTEGER DEFAULT 500",
+        [],
+    );
+
+    // Model to load at startup (None = start unloaded)
+    let _ = conn.execute("ALTER TABLE config ADD COLUMN preload_model TEXT", []);
+    let _ = conn.execute("ALTER TABLE config ADD COLUMN preload_gpu_layers INTEGER", []);
+
+    // Sampler presets defined in config (JSON array)
+    let _ = conn.execute("ALTER TABLE config ADD COLUMN sampler_presets TEXT", []);
+
     // Per-message LLM-generated title (≤50 chars, user messages only, set by background title gen)
     let _ = conn.execute("ALTER TABLE messages ADD COLUMN title TEXT", []);
 
@@ -215,7 +359,7 @@ pub fn initialize(conn: &Connection) -> Result<(), String> {
            SELECT
                m.id,
                m.conversation_id,
-               1 AS covers_from_sequence,
+               0 AS covers_from_sequence,
                m.sequence_order - 1 AS covers_to_sequence,
                0 AS message_count,
                CASE
@@ -572,4 +716,33 @@ mod tests {
         assert!(!columns.contains(&"temperature".to_string()));
         assert!(!columns.contains(&"context_size".to_string()));
     }
+
+    #[test]
+    fn test_upgrade_adds_conversation_system_prompt() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("PRAGMA foreign_keys = ON", []).unwrap();
+        // An older database whose conversations table predates system_prompt
+        conn.execute(
+            "CREATE TABLE conversations (id TEXT PRIMARY KEY, created_at INTEGER NOT NULL, \
+             updated_at INTEGER NOT NULL, title TEXT)",
+            [],
+        )
+        .unwrap();
+        initialize(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO conversations (id, title, created_at, updated_at, worker_id, system_prompt, overrides) \
+             VALUES ('chat_1', 'Chat', 1, 1, NULL, 'Be terse', NULL)",
+            [],
+        )
+        .unwrap();
+        let system_prompt: Option<String> = conn
+            .query_row(
+                "SELECT system_prompt FROM conversations WHERE id = 'chat_1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(system_prompt.as_deref(), Some("Be terse"));
+    }
 }
@@ -4,6 +4,7 @@ CREATE TABLE IF NOT EXISTS conversations (
     created_at INTEGER NOT NULL,
     updated_at INTEGER NOT NULL,
     title TEXT,
+    system_prompt TEXT,
     worker_id TEXT,
     provider_id TEXT,
     provider_session_id TEXT,
@@ -34,6 +35,7 @@ CREATE TABLE IF NOT EXISTS messages (
     timestamp INTEGER NOT NULL,
     sequence_order INTEGER NOT NULL,
     is_streaming INTEGER DEFAULT 0,
+    compressed INTEGER DEFAULT 0,
     FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
 )
 "#;
@@ -68,6 +70,32 @@ CREATE TABLE IF NOT EXISTS config (
     provider_api_keys TEXT,
     max_tool_calls INTEGER DEFAULT 2000,
     loop_detection_limit INTEGER DEFAULT 15,
+    compress_messages INTEGER DEFAULT 0,
+    allow_external_model_paths INTEGER DEFAULT 1,
+    auto_execute_tools INTEGER DEFAULT 1,
+    temperature_min REAL DEFAULT 0.0,
+    temperature_max REAL DEFAULT 2.0,
+    generation_timeout_secs INTEGER,
+    enabled_tools TEXT,
+    normalize_tool_output INTEGER DEFAULT 1,
+    default_context_cap INTEGER DEFAULT 8192,
+    tools_enabled INTEGER DEFAULT 1,
+    reasoning_tag_open TEXT DEFAULT '<think>',
+    reasoning_tag_close TEXT DEFAULT '</think>',
+    require_confirmation_for TEXT,
+    max_parallel_generations INTEGER DEFAULT 1,
+    sampler_preset TEXT,
+    autosave_interval_ms INTEGER DEFAULT 5000,
+    python_isolated INTEGER DEFAULT 0,
+    python_venv TEXT,
+    max_tool_iterations INTEGER DEFAULT 200,
+    dedupe_tool_calls INTEGER DEFAULT 1,
+    max_completions INTEGER DEFAULT 4,
+    read_file_max_bytes INTEGER DEFAULT 102400,
+    list_directory_max_entries INTEGER DEFAULT 500,
+    preload_model TEXT,
+    preload_gpu_layers INTEGER,
+    sampler_presets TEXT,
     active_provider TEXT DEFAULT 'local',
     active_provider_model TEXT,
     updated_at INTEGER NOT NULL
@@ -79,7 +107,9 @@ CREATE TABLE IF NOT EXISTS model_history (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     model_path TEXT UNIQUE NOT NULL,
     last_used INTEGER NOT NULL,
-    display_order INTEGER NOT NULL
+    display_order INTEGER NOT NULL,
+    display_name TEXT,
+    size_bytes INTEGER
 )
 "#;
 
@@ -206,6 +236,26 @@ CREATE INDEX IF NOT EXISTS idx_compaction_summaries_conversation
 ON compaction_summaries(conversation_id, covers_to_sequence)
 "#;
 
+pub(super) const CREATE_TOOL_CALLS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS tool_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    conversation_id TEXT NOT NULL,
+    turn INTEGER NOT NULL DEFAULT 0,
+    tool_name TEXT NOT NULL,
+    arguments_json TEXT NOT NULL,
+    result TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL DEFAULT 0,
+    success INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+)
+"#;
+
+pub(super) const CREATE_TOOL_CALLS_CONVERSATION_INDEX: &str = r#"
+CREATE INDEX IF NOT EXISTS idx_tool_calls_conversation
+ON tool_calls(conversation_id)
+"#;
+
 // agent_heartbeat table removed — migrated to heartbeat_* columns on conversations
 
 pub(super) const CREATE_AGENTS_TABLE: &str = r#"
@@ -0,0 +1,150 @@
+// Structured record of each tool call and its result, for evaluating agent runs.
+//
+// Separate from the `[COMMAND: ...]` text folded into assistant messages: one
+// row per executed call, with the arguments as JSON and the raw (unsummarized)
+// output.
+
+use rusqlite::params;
+use serde::Serialize;
+
+use super::{current_timestamp_millis, db_error, Database};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ToolCallRecord {
+    pub id: i64,
+    pub conversation_id: String,
+    /// User messages in the conversation when the call ran (1 = first turn).
+    pub turn: i64,
+    pub tool_name: String,
+    pub arguments_json: String,
+    pub result: String,
+    pub duration_ms: i64,
+    pub success: bool,
+    pub created_at: i64,
+}
+
+impl Database {
+    /// Append one executed tool call to `conversation_id`'s log.
+    pub fn record_tool_call(
+        &self,
+        conversation_id: &str,
+        tool_name: &str,
+        arguments_json: &str,
+        result: &str,
+        duration_ms: u64,
+        success: bool,
+    ) -> Result<i64, String> {
+        let conn = self.connection();
+        conn.execute(
+            "INSERT INTO tool_calls (conversation_id, turn, tool_name, arguments_json, result, duration_ms, success, created_at)
+             VALUES (?1, (SELECT COUNT(*) FROM messages WHERE conversation_id = ?1 AND role = 'user'), ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                conversation_id,
+                tool_name,
+                arguments_json,
+                result,
+                duration_ms as i64,
+                success,
+                current_timestamp_millis(),
+            ],
+        )
+        .map_err(db_error("insert tool call"))?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// The conversation's tool calls in execution order, or None if it doesn't exist.
+    pub fn get_tool_calls(&self, conversation_id: &str) -> Result<Option<Vec<ToolCallRecord>>, String> {
+        if !self.conversation_exists(conversation_id)? {
+            return Ok(None);
+        }
+        let conn = self.connection();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, conversation_id, turn, tool_name, arguments_json, result, duration_ms, success, created_at
+                 FROM tool_calls
+                 WHERE conversation_id = ?1
+                 ORDER BY id",
+            )
+            .map_err(db_error("prepare tool calls query"))?;
+
+        let rows = stmt
+            .query_map([conversation_id], |row| {
+                Ok(ToolCallRecord {
+                    id: row.get(0)?,
+                    conversation_id: row.get(1)?,
+                    turn: row.get(2)?,
+                    tool_name: row.get(3)?,
+                    arguments_json: row.get(4)?,
+                    result: row.get(5)?,
+                    duration_ms: row.get(6)?,
+                    success: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            })
+            .map_err(db_error("query tool calls"))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map(Some)
+            .map_err(db_error("collect tool calls"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_tool_calls_produce_two_rows() {
+        let db = Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "list then read", 10, 0).unwrap();
+
+        db.record_tool_call(&id, "list_directory", r#"{"path":"."}"#, "a.txt\nb.txt", 12, true)
+            .unwrap();
+        db.insert_message(&id, "user", "now the missing one", 20, 1).unwrap();
+        db.record_tool_call(
+            &id,
+            "read_file",
+            r#"{"path":"missing.txt"}"#,
+            "Error: file not found",
+            3,
+            false,
+        )
+        .unwrap();
+
+        let calls = db.get_tool_calls(&id).unwrap().unwrap();
+        assert_eq!(calls.len(), 2);
+        let (list, read) = (&calls[0], &calls[1]);
+        assert_eq!(
+            (list.turn, list.tool_name.as_str(), list.arguments_json.as_str()),
+            (1, "list_directory", r#"{"path":"."}"#)
+        );
+        assert_eq!((list.result.as_str(), list.duration_ms, list.success), ("a.txt\nb.txt", 12, true));
+        assert_eq!(
+            (read.turn, read.tool_name.as_str(), read.result.as_str()),
+            (2, "read_file", "Error: file not found")
+        );
+        assert_eq!((read.duration_ms, read.success), (3, false));
+        assert!(calls.iter().all(|c| c.conversation_id == id));
+    }
+
+    #[test]
+    fn test_tool_calls_are_per_conversation() {
+        let db = Database::new(":memory:").unwrap();
+        let a = db.create_conversation().unwrap();
+        let b = db.create_conversation().unwrap();
+        db.record_tool_call(&a, "web_search", "{}", "results", 100, true).unwrap();
+
+        assert_eq!(db.get_tool_calls(&a).unwrap().unwrap().len(), 1);
+        assert!(db.get_tool_calls(&b).unwrap().unwrap().is_empty());
+        assert!(db.get_tool_calls("missing").unwrap().is_none());
+
+        // Deleting the conversation drops its log
+        db.delete_conversation(&a).unwrap();
+        let conn = db.connection();
+        let left: i64 = conn
+            .query_row("SELECT COUNT(*) FROM tool_calls", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+}
@@ -75,6 +75,7 @@ pub fn tool_annotate_screenshot(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: format!("Drew {drawn} shape(s) on screenshot"),
         images: vec![buf],
+        success: true,
     }
 }
 
@@ -135,6 +136,7 @@ pub fn tool_ocr_region(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: format!("OCR region ({x},{y} {width}x{height}):\n{rtext}"),
         images: vec![png_buf],
+        success: true,
     }
 }
 
@@ -117,11 +117,13 @@ pub fn tool_smart_wait(args: &Value) -> NativeToolResult {
         NativeToolResult {
             text: format!("Timeout after {elapsed}ms: no conditions met (mode={mode})"),
             images: screenshot.images,
+            success: false,
         }
     } else {
         NativeToolResult {
             text: format!("Wait complete after {elapsed}ms: {} (mode={mode})", result_parts.join(" + ")),
             images: screenshot.images,
+            success: true,
         }
     }
 }
@@ -153,7 +155,7 @@ pub fn tool_click_and_verify(args: &Value) -> NativeToolResult {
         "delay_ms": 500,
         "screenshot": false
     }));
-    if click_result.text.starts_with("Error") {
+    if !click_result.success {
         return click_result;
     }
 
@@ -174,6 +176,7 @@ pub fn tool_click_and_verify(args: &Value) -> NativeToolResult {
             wait_result.text
         ),
         images: screenshot.images,
+        success: verified,
     }
 }
 
@@ -87,6 +87,7 @@ pub fn tool_handle_dialog(args: &Value) -> NativeToolResult {
             return NativeToolResult {
                 text: output,
                 images: result.images,
+                success: true,
             };
         } else {
             output.push_str(&format!("Button '{btn_name}' not found in dialog"));
@@ -98,6 +99,7 @@ pub fn tool_handle_dialog(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: output,
         images: screenshot.images,
+        success: true,
     }
 }
 
@@ -184,6 +186,7 @@ pub fn tool_wait_for_element_state(args: &Value) -> NativeToolResult {
                     "Element '{name_desc}' reached state '{target_state}' after {elapsed}ms"
                 ),
                 images: screenshot.images,
+                success: true,
             };
         }
 
@@ -58,6 +58,7 @@ pub fn tool_move_to_monitor(args: &Value) -> NativeToolResult {
             info.title, monitor_idx, mon_x, mon_y
         ),
         images: screenshot.images,
+        success: true,
     }
 }
 
@@ -188,5 +189,6 @@ pub fn tool_highlight_point(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: format!("Highlighted point ({x}, {y}) with {color_name} crosshair (size {size})"),
         images: vec![buf],
+        success: true,
     }
 }
@@ -140,6 +140,7 @@ pub fn tool_fill_form(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: output,
         images: screenshot.images,
+        success: true,
     }
 }
 
@@ -410,6 +411,7 @@ pub fn tool_run_action_sequence(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: format!("Executed {action_count} action(s):\n{results_joined}"),
         images: screenshot.images,
+        success: true,
     }
 }
 
@@ -35,13 +35,13 @@ const MONITOR_CACHE_TTL_MS: u128 = 1000;
 /// Format a desktop tool error consistently.
 #[allow(dead_code)]
 pub fn tool_error(tool: &str, msg: impl std::fmt::Display) -> NativeToolResult {
-    NativeToolResult::text_only(format!("Error [{tool}]: {msg}"))
+    NativeToolResult::failure(format!("Error [{tool}]: {msg}"))
 }
 
 /// Format a platform-not-supported error.
 #[allow(dead_code)]
 pub fn tool_not_supported(tool: &str) -> NativeToolResult {
-    NativeToolResult::text_only(format!("Error [{tool}]: not available on this platform"))
+    NativeToolResult::failure(format!("Error [{tool}]: not available on this platform"))
 }
 
 /// Return cached monitors or re-enumerate if stale/empty.
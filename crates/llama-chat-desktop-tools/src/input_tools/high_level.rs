@@ -32,6 +32,7 @@ pub fn tool_paste(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: format!("Pasted from clipboard. {rtext}"),
         images: result.images,
+        success: true,
     }
 }
 
@@ -62,6 +63,7 @@ pub fn tool_clear_field(args: &Value) -> NativeToolResult {
         NativeToolResult {
             text: format!("Cleared field and typed '{text}'. {rtext}"),
             images: result.images,
+            success: true,
         }
     } else {
         // Take final screenshot (selection already deleted above, just capture)
@@ -70,6 +72,7 @@ pub fn tool_clear_field(args: &Value) -> NativeToolResult {
         NativeToolResult {
             text: format!("Cleared field. {rtext}"),
             images: result.images,
+            success: true,
         }
     }
 }
@@ -151,6 +154,7 @@ pub fn tool_hover_element(args: &Value) -> NativeToolResult {
             element.name, element.control_type, element.cx, element.cy, tooltip_text
         ),
         images: screenshot.images,
+        success: true,
     }
 }
 
@@ -45,6 +45,7 @@ pub(super) fn scroll_to_text(args: &Value) -> NativeToolResult {
             return NativeToolResult {
                 text: format!("Found '{target_text}' after {i} scroll(s)"),
                 images: screenshot.images,
+                success: true,
             };
         }
         // Scroll
@@ -64,6 +65,7 @@ pub(super) fn scroll_to_text(args: &Value) -> NativeToolResult {
     NativeToolResult {
         text: format!("Text '{target_text}' not found after {max_scrolls} scrolls {direction}"),
         images: screenshot.images,
+        success: false,
     }
 }
 
@@ -100,6 +100,7 @@ pub fn tool_click_tray_icon(args: &Value) -> NativeToolResult {
                     "Clicked tray icon '{}' at ({}, {})", el.name, el.cx, el.cy
                 ),
                 images: click_result.images,
+                success: true,
             }
         }
         Ok(_) => {
@@ -118,6 +119,7 @@ pub fn tool_click_tray_icon(args: &Value) -> NativeToolResult {
                     NativeToolResult {
                         text: format!("Clicked tray element '{}' at ({}, {})", el.name, el.cx, el.cy),
                         images: click_result.images,
+                        success: true,
                     }
                 }
                 Ok(_) => super::tool_error("click_tray_icon", format!("tray icon matching '{name}' not found")),
@@ -217,6 +219,7 @@ pub fn tool_watch_window(args: &Value) -> NativeToolResult {
                     changes.join("\n")
                 ),
                 images: screenshot.images,
+                success: true,
             };
         }
     }
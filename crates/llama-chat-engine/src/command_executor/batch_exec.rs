@@ -8,12 +8,13 @@ use crate::tool_dispatch::{
     run_native_tool_with_timeout,
     execute_single_tool,
     is_read_only_tool,
+    requires_confirmation,
     MAX_PARALLEL_TOOLS,
 };
 use super::output_assembly::maybe_summarize_or_truncate;
 
-/// Result of executing one tool within a batch: (text_output, image_bytes)
-pub type ToolResult = (String, Vec<Vec<u8>>);
+/// Result of executing one tool within a batch: (text_output, image_bytes, succeeded)
+pub type ToolResult = (String, Vec<Vec<u8>>, bool);
 
 /// Execute a batch of tool calls (len > 1).
 ///
@@ -47,11 +48,14 @@ pub(crate) fn execute_batch_tools(
     // Group consecutive tool calls by parallelizability.
     // With force_parallel, all tools are treated as a single parallel group.
     let mut groups: Vec<(bool, Vec<usize>)> = Vec::new();
+    // Gated tools wait for confirmation one at a time, so they always run serially
+    let force_parallel = force_parallel
+        && !all_calls.iter().any(|(name, _)| requires_confirmation(name, &db));
     if force_parallel {
         groups.push((true, (0..all_calls.len()).collect()));
     } else {
         for (i, (name, _args)) in all_calls.iter().enumerate() {
-            let is_ro = is_read_only_tool(name);
+            let is_ro = is_read_only_tool(name) && !requires_confirmation(name, &db);
             if let Some(last) = groups.last_mut() {
                 if last.0 == is_ro {
                     last.1.push(i);
@@ -117,19 +121,19 @@ pub(crate) fn execute_batch_tools(
                             );
                             let duration_ms = tool_start.elapsed().as_millis() as u64;
                             let native_result = result.unwrap_or_else(|| {
-                                llama_chat_tools::NativeToolResult::text_only(
+                                llama_chat_tools::NativeToolResult::failure(
                                     format!("Error: Tool '{tool_name}' returned no output")
                                 )
                             });
-                            (idx, native_result.text, native_result.images, duration_ms)
+                            (idx, native_result, duration_ms)
                         })
                     })
                     .collect();
 
                 for handle in handles {
-                    if let Ok((idx, text, images, duration_ms)) = handle.join() {
+                    if let Ok((idx, r, duration_ms)) = handle.join() {
                         all_durations[idx] = duration_ms;
-                        results[idx] = Some((text, images));
+                        results[idx] = Some((r.text, r.images, r.success));
                     }
                 }
             });
@@ -138,7 +142,7 @@ pub(crate) fn execute_batch_tools(
             for &i in indices.iter().skip(MAX_PARALLEL_TOOLS) {
                 let (name, args) = &all_calls[i];
                 let single_json = serde_json::json!({"name": name, "arguments": args}).to_string();
-                let (tool_output, tool_images, dur) = execute_single_tool(
+                let (r, dur) = execute_single_tool(
                     name, args, &single_json,
                     conversation_id,
                     token_sender,
@@ -152,14 +156,14 @@ pub(crate) fn execute_batch_tools(
                     model, backend, chat_template_string, tags,
                 );
                 all_durations[i] = dur;
-                results[i] = Some((tool_output, tool_images));
+                results[i] = Some((r.text, r.images, r.success));
             }
         } else {
             // Execute serially
             for &i in indices {
                 let (name, args) = &all_calls[i];
                 let single_json = serde_json::json!({"name": name, "arguments": args}).to_string();
-                let (tool_output, tool_images, dur) = execute_single_tool(
+                let (r, dur) = execute_single_tool(
                     name, args, &single_json,
                     conversation_id,
                     token_sender,
@@ -173,13 +177,13 @@ pub(crate) fn execute_batch_tools(
                     model, backend, chat_template_string, tags,
                 );
                 all_durations[i] = dur;
-                results[i] = Some((tool_output, tool_images));
+                results[i] = Some((r.text, r.images, r.success));
             }
         }
     }
 
     // Merge results in original order, streaming to frontend
-    for (i, (name, _args)) in all_calls.iter().enumerate() {
+    for (i, (name, args)) in all_calls.iter().enumerate() {
         let dur = all_durations[i];
         llama_chat_db::event_log::log_event(
             conversation_id,
@@ -204,15 +208,16 @@ pub(crate) fn execute_batch_tools(
         }
         combined_output.push_str(&header);
 
-        let (tool_output, tool_images) = results[i].take().unwrap_or_default();
+        let (tool_output, tool_images, succeeded) = results[i].take().unwrap_or_default();
+        super::record_tool_call(&db, conversation_id, name, args, &tool_output, dur, succeeded);
         if !tool_images.is_empty() && image_summary_prompt.is_none() {
             // Build a raw JSON snippet for this tool call to reuse extract_image_summary_prompt
-            let tool_json = serde_json::to_string(&_args).unwrap_or_default();
+            let tool_json = serde_json::to_string(args).unwrap_or_default();
             image_summary_prompt = super::output_assembly::extract_image_summary_prompt(&tool_json);
         }
         all_response_images.extend(tool_images);
 
-        let summary_val = _args.get("summary");
+        let summary_val = args.get("summary");
         let summary_opt_out = summary_val
             .map(|v| v.as_bool() == Some(false) || v.as_str() == Some("false"))
             .unwrap_or(false);
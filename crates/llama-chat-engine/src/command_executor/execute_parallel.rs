@@ -103,6 +103,7 @@ pub fn execute_parallel_block(
         model,
         backend,
         chat_template_string,
+        normalize_whitespace: db.load_config().normalize_tool_output,
     };
 
     let (display_text, model_text) = output_assembly::sanitize_and_summarize(&ap);
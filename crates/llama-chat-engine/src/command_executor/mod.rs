@@ -4,6 +4,7 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 
 use llama_chat_types::*;
+use super::duplicate_calls::{duplicate_call_reply, ExecutedCalls};
 use super::loop_detection::{self, LoopCheckResult};
 use super::tool_parser::FORMAT_PRIORITY;
 use super::tool_tags::ToolTags;
@@ -52,6 +53,7 @@ pub fn check_and_execute_command_with_tags(
     template_type: Option<&str>,
     recent_commands: &mut Vec<String>,
     consecutive_loop_blocks: &mut usize,
+    executed_calls: &mut ExecutedCalls,
     token_sender: &Option<mpsc::UnboundedSender<TokenData>>,
     token_pos: i32,
     context_size: u32,
@@ -133,7 +135,10 @@ pub fn check_and_execute_command_with_tags(
             Ok(Some(result))
         }
         LoopCheckResult::Continue(fuzzy_warning) => {
-            let all_calls = llama_chat_tools::try_parse_all_from_raw(&command_text);
+            let all_calls = llama_chat_tools::try_parse_all_from_raw_for_format(
+                &command_text,
+                crate::model_manager::loaded_tool_format(),
+            );
             let is_batch = all_calls.len() > 1;
 
             if is_batch {
@@ -153,6 +158,11 @@ pub fn check_and_execute_command_with_tags(
                 });
             }
 
+            let previous = all_calls
+                .first()
+                .filter(|_| !is_batch)
+                .and_then(|(name, args)| executed_calls.previous_result(name, args));
+
             let (output, all_response_images, image_summary_prompt) = if is_batch {
                 batch_exec::execute_batch_tools(
                     &all_calls,
@@ -168,8 +178,23 @@ pub fn check_and_execute_command_with_tags(
                     model, backend, chat_template_string, tags,
                     false, // force_parallel: use normal read/write classification
                 )
+            } else if let Some(previous) = previous {
+                // Identical to a call already run this turn: hand back its result
+                llama_chat_db::event_log::log_event(conversation_id, "tool_deduped", &tool_name_for_log);
+                let reply = duplicate_call_reply(previous);
+                if let Some(ref sender) = token_sender {
+                    let _ = sender.send(TokenData {
+                        token: reply.clone(),
+                        tokens_used: token_pos,
+                        max_tokens: context_size as i32,
+                        status: None,
+                        ..Default::default()
+                    });
+                }
+                (reply, Vec::new(), None)
             } else {
-                single_exec::execute_single_call(
+                let started = std::time::Instant::now();
+                let (single, succeeded) = single_exec::execute_single_call(
                     &command_text,
                     &tool_name_for_log,
                     conversation_id,
@@ -183,7 +208,21 @@ pub fn check_and_execute_command_with_tags(
                     db.clone(),
                     model, backend, chat_template_string, tags,
                     recent_commands,
-                )
+                );
+                let args = all_calls.first().map(|(_, args)| args.clone()).unwrap_or_default();
+                record_tool_call(
+                    &db,
+                    conversation_id,
+                    &tool_name_for_log,
+                    &args,
+                    &single.0,
+                    started.elapsed().as_millis() as u64,
+                    succeeded,
+                );
+                if let Some((name, _)) = all_calls.first() {
+                    executed_calls.record(name, &args, &single.0, succeeded);
+                }
+                single
             };
 
             log_info!(conversation_id, "📤 Command output length: {} chars", output.len());
@@ -203,6 +242,7 @@ pub fn check_and_execute_command_with_tags(
                 model,
                 backend,
                 chat_template_string,
+                normalize_whitespace: db.load_config().normalize_tool_output,
             };
 
             let (display_text, model_text) = output_assembly::sanitize_and_summarize(&ap);
@@ -269,3 +309,152 @@ fn extract_tool_name(command_text: &str) -> String {
     }
     "unknown".to_string()
 }
+
+/// Add an executed call to the conversation's `tool_calls` log (see
+/// `GET /api/conversation/{id}/tools`). Logging failures don't affect the run.
+fn record_tool_call(
+    db: &llama_chat_db::SharedDatabase,
+    conversation_id: &str,
+    name: &str,
+    args: &serde_json::Value,
+    output: &str,
+    duration_ms: u64,
+    success: bool,
+) {
+    if let Err(e) = db.record_tool_call(conversation_id, name, &args.to_string(), output, duration_ms, success) {
+        log_warn!(conversation_id, "Failed to record tool call {}: {}", name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// MCP tools with canned replies, so dispatch runs without a server.
+    struct StubTools;
+
+    impl llama_chat_tools::McpManagerOps for StubTools {
+        fn is_mcp_tool(&self, name: &str) -> bool {
+            name.starts_with("mcp__stub__")
+        }
+        fn call_tool(&self, name: &str, _args: serde_json::Value) -> Result<String, String> {
+            match name {
+                // Reads like a failure but the tool reported success
+                "mcp__stub__list" => Ok("Error.log\nnotes.txt".to_string()),
+                _ => Err("file not found".to_string()),
+            }
+        }
+        fn get_server_statuses(&self) -> Vec<McpServerStatus> {
+            Vec::new()
+        }
+        fn get_tool_definitions(&self) -> Vec<llama_chat_tools::McpToolDefInfo> {
+            Vec::new()
+        }
+        fn get_connected_server_names(&self) -> Vec<String> {
+            Vec::new()
+        }
+        fn refresh_connections(&self, _db: &llama_chat_db::SharedDatabase) -> Result<(), String> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dispatched_calls_are_logged_with_the_tool_outcome() {
+        let db: llama_chat_db::SharedDatabase =
+            Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "list then read", 10, 0).unwrap();
+        let stub: Arc<dyn llama_chat_tools::McpManagerOps> = Arc::new(StubTools);
+
+        let calls = [
+            ("mcp__stub__list", serde_json::json!({"path": "."})),
+            ("mcp__stub__read", serde_json::json!({"path": "missing.txt"})),
+        ];
+        for (turn, (name, args)) in calls.iter().enumerate() {
+            if turn > 0 {
+                db.insert_message(&id, "user", "now the missing one", 20, 1).unwrap();
+            }
+            let call = serde_json::json!({"name": name, "arguments": args}).to_string();
+            let result = crate::tool_dispatch::run_native_tool_with_timeout(
+                &call,
+                &id,
+                false,
+                crate::browser::BrowserBackend::None,
+                Some(stub.clone()),
+                db.clone(),
+            )
+            .expect("stub tool should be dispatched");
+            record_tool_call(&db, &id, name, args, &result.text, 5, result.success);
+        }
+
+        let logged = db.get_tool_calls(&id).unwrap().unwrap();
+        assert_eq!(logged.len(), 2);
+        let (list, read) = (&logged[0], &logged[1]);
+        assert_eq!((list.turn, list.tool_name.as_str(), list.success), (1, "mcp__stub__list", true));
+        assert_eq!(list.result, "Error.log\nnotes.txt");
+        assert_eq!((read.turn, read.tool_name.as_str(), read.success), (2, "mcp__stub__read", false));
+        assert!(read.result.contains("file not found"), "{}", read.result);
+        assert_eq!(read.arguments_json, r#"{"path":"missing.txt"}"#);
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_executed_calls_are_logged_with_the_dispatcher_outcome() {
+        use llama_cpp_2::llama_backend::LlamaBackend;
+        use llama_cpp_2::model::params::LlamaModelParams;
+        use llama_cpp_2::model::LlamaModel;
+
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let backend = LlamaBackend::init().unwrap();
+        let model =
+            LlamaModel::load_from_file(&backend, &model_path, &LlamaModelParams::default()).unwrap();
+        let db: llama_chat_db::SharedDatabase =
+            Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "run two tools", 10, 0).unwrap();
+
+        let tags = crate::tool_tags::default_tags();
+        let missing = std::env::temp_dir().join("tool_calls_log_missing.txt");
+        std::fs::remove_file(&missing).ok();
+        let calls = [
+            // "Error" output from a zero exit still counts as a success
+            r#"{"name": "execute_command", "arguments": {"command": "echo Error: only text"}}"#.to_string(),
+            format!(
+                r#"{{"name": "read_file", "arguments": {{"path": "{}"}}}}"#,
+                missing.display().to_string().replace('\\', "\\\\")
+            ),
+        ];
+        let mut response = String::new();
+        let (mut recent, mut blocks) = (Vec::new(), 0);
+        let mut executed = ExecutedCalls::new(false);
+        for (turn, call) in calls.iter().enumerate() {
+            if turn > 0 {
+                db.insert_message(&id, "user", "and the next one", 20, 1).unwrap();
+            }
+            let scan_from = response.len();
+            response.push_str(&format!("{}{}{}", tags.exec_open, call, tags.exec_close));
+            let result = check_and_execute_command_with_tags(
+                &response, scan_from, &id, &model, &tags, None,
+                &mut recent, &mut blocks, &mut executed,
+                &None, 0, 4096, None, false,
+                &crate::browser::BrowserBackend::None, None, db.clone(),
+                &backend, None,
+            )
+            .unwrap()
+            .expect("tool call should run");
+            response.push_str(&result.output_block);
+        }
+
+        let logged = db.get_tool_calls(&id).unwrap().unwrap();
+        assert_eq!(logged.len(), 2);
+        assert_eq!(
+            (logged[0].tool_name.as_str(), logged[0].success),
+            ("execute_command", true)
+        );
+        assert!(logged[0].result.contains("Error: only text"), "{}", logged[0].result);
+        assert_eq!((logged[1].tool_name.as_str(), logged[1].success), ("read_file", false));
+        assert_eq!((logged[0].turn, logged[1].turn), (1, 2));
+        assert!(logged[1].arguments_json.contains("tool_calls_log_missing.txt"));
+    }
+}
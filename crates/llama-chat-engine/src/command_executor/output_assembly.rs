@@ -6,6 +6,7 @@ use crate::tool_output::{
     tool_use_one_liner,
     maybe_truncate_tool_output,
     maybe_summarize_tool_output,
+    normalize_tool_whitespace,
     summarize_tool_output,
     summarize_tool_output_with_prompt,
     wrap_output_for_model,
@@ -78,6 +79,8 @@ pub(crate) struct AssemblyParams<'a> {
     pub model: &'a llama_cpp_2::model::LlamaModel,
     pub backend: &'a llama_cpp_2::llama_backend::LlamaBackend,
     pub chat_template_string: Option<&'a str>,
+    /// `normalize_tool_output` config: tidy whitespace in the model's copy.
+    pub normalize_whitespace: bool,
 }
 
 /// Assembled output ready for frontend display and model injection.
@@ -120,6 +123,20 @@ fn extract_summary_param(command_text: &str) -> Option<String> {
     None
 }
 
+/// The model's copy of a tool output: whitespace-normalized when enabled, except
+/// when file tools are involved (their exact content matters, e.g. for edits).
+/// The display copy, which is what gets stored, is never touched.
+fn model_copy_of_output(output: &str, tool_name: &str, normalize: bool) -> String {
+    let exact_content = ["read_file", "write_file", "edit_file"]
+        .iter()
+        .any(|t| tool_name.contains(t));
+    if normalize && !exact_content {
+        normalize_tool_whitespace(output)
+    } else {
+        output.to_string()
+    }
+}
+
 /// Sanitize, (optionally) summarize, and wrap output text for model injection.
 ///
 /// Returns `(display_text, model_text)`.
@@ -134,6 +151,7 @@ pub(crate) fn sanitize_and_summarize(
     };
     let sanitized = sanitize_command_output(&output_for_model);
     let sanitized = maybe_truncate_tool_output(&sanitized, p.tool_name_for_log, p.conversation_id);
+    let for_model = model_copy_of_output(&sanitized, p.tool_name_for_log, p.normalize_whitespace);
 
     let summary_value = extract_summary_param(p.command_text);
     let cmd_lower = p.command_text.to_lowercase();
@@ -149,7 +167,7 @@ pub(crate) fn sanitize_and_summarize(
     };
 
     if summary_disabled {
-        return (sanitized.clone(), sanitized);
+        return (sanitized, for_model);
     }
 
     // Tools that need exact content — summarizing destroys their usefulness.
@@ -159,14 +177,14 @@ pub(crate) fn sanitize_and_summarize(
         || tool_lower.contains("write_file")
         || tool_lower.contains("edit_file");
     if no_summarize {
-        return (sanitized.clone(), sanitized);
+        return (sanitized, for_model);
     }
 
     if p.raw_output.len() > SUMMARIZE_THRESHOLD || sanitized.len() > SUMMARIZE_THRESHOLD {
         let summarize_result = if let Some(ref prompt) = custom_summary_prompt {
-            summarize_tool_output_with_prompt(p.model, p.backend, &sanitized, p.chat_template_string, p.conversation_id, Some(prompt))
+            summarize_tool_output_with_prompt(p.model, p.backend, &for_model, p.chat_template_string, p.conversation_id, Some(prompt))
         } else {
-            summarize_tool_output(p.model, p.backend, &sanitized, p.chat_template_string, p.conversation_id)
+            summarize_tool_output(p.model, p.backend, &for_model, p.chat_template_string, p.conversation_id)
         };
         match summarize_result {
             Ok(summary) => {
@@ -193,11 +211,11 @@ pub(crate) fn sanitize_and_summarize(
             }
             Err(e) => {
                 log_warn!(p.conversation_id, "Summarization failed ({}), using raw output", e);
-                (sanitized.clone(), sanitized)
+                (sanitized, for_model)
             }
         }
     } else {
-        (sanitized.clone(), sanitized)
+        (sanitized, for_model)
     }
 }
 
@@ -255,3 +273,33 @@ pub(crate) fn append_image_links(output_block: &mut String, images: &[Vec<u8>],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LISTING: &str = "src/   \n\n\n\n\nmain.rs\t\n  lib.rs\n\n\n\n";
+
+    #[test]
+    fn test_model_copy_collapses_blank_lines() {
+        let for_model = model_copy_of_output(LISTING, "list_directory", true);
+        assert_eq!(for_model, "src/\n\nmain.rs\n  lib.rs");
+    }
+
+    #[test]
+    fn test_normalize_tool_whitespace() {
+        assert_eq!(normalize_tool_whitespace("\n\n  a  \r\n\r\n\r\nb\n"), "  a\n\nb");
+        assert_eq!(normalize_tool_whitespace("one\ntwo"), "one\ntwo");
+        assert_eq!(normalize_tool_whitespace(" \n\t\n"), "");
+    }
+
+    #[test]
+    fn test_model_copy_kept_raw_when_disabled_or_exact() {
+        assert_eq!(model_copy_of_output(LISTING, "list_directory", false), LISTING);
+        assert_eq!(model_copy_of_output(LISTING, "read_file", true), LISTING);
+        assert_eq!(
+            model_copy_of_output(LISTING, "parallel[read_file,git_status]", true),
+            LISTING
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 
-use llama_chat_command::{execute_command_streaming, execute_command_streaming_with_timeout, strip_ansi_codes};
+use llama_chat_command::{run_command_streaming, strip_ansi_codes};
 use llama_chat_command::background::execute_command_background;
 use llama_chat_types::*;
 
@@ -13,6 +13,8 @@ use crate::tool_dispatch::{
     detect_destructive_command,
     detect_command_injection,
     run_native_tool_with_timeout,
+    requires_confirmation,
+    await_tool_confirmation,
 };
 use crate::tool_tags::ToolTags;
 use super::output_assembly::tool_use_one_liner_pub;
@@ -20,7 +22,40 @@ use super::output_assembly::tool_use_one_liner_pub;
 /// Result of single tool execution: (text_output, image_bytes, image_summary_prompt)
 pub type SingleToolResult = (String, Vec<Vec<u8>>, Option<String>);
 
-/// Execute a single tool call and return (output_text, images).
+/// Refusal for a disabled tool this module runs itself (spawn_agent, execute_command);
+/// everything else is checked inside `dispatch_native_tool`.
+fn direct_tool_refusal(command_text: &str, db: &llama_chat_db::SharedDatabase) -> Option<String> {
+    let name = if try_extract_spawn_agent(command_text).is_some() {
+        "spawn_agent"
+    } else if llama_chat_tools::extract_execute_command_with_opts(command_text).is_some() {
+        "execute_command"
+    } else {
+        return None;
+    };
+    llama_chat_tools::disabled_tool_refusal(name, Some(db))
+}
+
+/// Waits for confirmation when the call is to a `require_confirmation_for` tool;
+/// returns the reply for the model if the user declines.
+fn declined_confirmation(
+    command_text: &str,
+    conversation_id: &str,
+    token_sender: &Option<mpsc::UnboundedSender<TokenData>>,
+    cancel: Option<&AtomicBool>,
+    db: &llama_chat_db::SharedDatabase,
+) -> Option<String> {
+    let tool_format = crate::model_manager::loaded_tool_format();
+    let (name, args) = llama_chat_tools::try_parse_all_from_raw_for_format(command_text, tool_format)
+        .into_iter()
+        .next()?;
+    if !requires_confirmation(&name, db) {
+        return None;
+    }
+    await_tool_confirmation(&name, &args, conversation_id, token_sender, cancel, db)
+}
+
+/// Execute a single tool call and return its output, images and image summary
+/// prompt, plus whether the call succeeded.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn execute_single_call(
     command_text: &str,
@@ -39,14 +74,18 @@ pub(crate) fn execute_single_call(
     chat_template_string: Option<&str>,
     tags: &ToolTags,
     recent_commands: &mut Vec<String>,
-) -> SingleToolResult {
+) -> (SingleToolResult, bool) {
     let mut all_images: Vec<Vec<u8>> = Vec::new();
 
     // Check for spawn_agent first — needs model/backend access, can't go through native tool path
-    let output = if let Some(agent_result) = try_extract_spawn_agent(command_text) {
+    let (output, succeeded) = if let Some(refusal) = direct_tool_refusal(command_text, &db) {
+        (refusal, false)
+    } else if let Some(declined) = declined_confirmation(command_text, conversation_id, token_sender, cancel.as_deref(), &db) {
+        (declined, false)
+    } else if let Some(agent_result) = try_extract_spawn_agent(command_text) {
         let (task, extra_context) = agent_result;
         if task.is_empty() {
-            "Error: 'task' argument is required for spawn_agent".to_string()
+            ("Error: 'task' argument is required for spawn_agent".to_string(), false)
         } else {
             match run_sub_agent(
                 model, backend, &task, extra_context.as_deref(), chat_template_string,
@@ -55,13 +94,14 @@ pub(crate) fn execute_single_call(
                 token_sender,
             ) {
                 Ok((result, child_conv_id)) => {
-                    if child_conv_id.is_empty() {
+                    let output = if child_conv_id.is_empty() {
                         result
                     } else {
                         format!("{result}\n[sub_agent_session:{child_conv_id}]")
-                    }
+                    };
+                    (output, true)
                 }
-                Err(e) => format!("Sub-agent error: {e}"),
+                Err(e) => (format!("Sub-agent error: {e}"), false),
             }
         }
     }
@@ -69,7 +109,7 @@ pub(crate) fn execute_single_call(
     else if let Some(opts) = llama_chat_tools::extract_execute_command_with_opts(command_text) {
         // Security checks
         if let Some(injection_msg) = detect_command_injection(&opts.command) {
-            injection_msg
+            (injection_msg, false)
         } else {
             if let Some(warning) = detect_destructive_command(&opts.command) {
                 eprintln!("[SECURITY] {}: {}", warning, &opts.command[..opts.command.len().min(100)]);
@@ -89,7 +129,7 @@ pub(crate) fn execute_single_call(
             if opts.background {
                 log_info!(conversation_id, "🐚 Background execute_command: {}", rtk_cmd);
                 let sender_clone = token_sender.clone();
-                execute_command_background(&rtk_cmd, |line| {
+                let output = execute_command_background(&rtk_cmd, |line| {
                     if let Some(ref sender) = sender_clone {
                         let _ = sender.send(TokenData {
                             token: format!("{}\n", strip_ansi_codes(line)),
@@ -99,13 +139,14 @@ pub(crate) fn execute_single_call(
                             ..Default::default()
                         });
                     }
-                })
+                });
+                (output, true)
             } else {
                 log_info!(conversation_id, "🐚 Streaming execute_command (timeout={:?}s): {}", opts.timeout, rtk_cmd);
                 llama_chat_db::event_log::log_event(conversation_id, "tool_exec", &format!("execute_command: {}", &rtk_cmd[..rtk_cmd.len().min(100)]));
                 let exec_start = std::time::Instant::now();
                 let sender_clone = token_sender.clone();
-                let result = execute_command_streaming_with_timeout(&rtk_cmd, cancel.clone(), opts.timeout, &mut |line| {
+                let outcome = run_command_streaming(&rtk_cmd, cancel.clone(), opts.timeout, &mut |line| {
                     if let Some(ref sender) = sender_clone {
                         let _ = sender.send(TokenData {
                             token: format!("{}\n", strip_ansi_codes(line)),
@@ -117,7 +158,7 @@ pub(crate) fn execute_single_call(
                     }
                 });
                 let elapsed_ms = exec_start.elapsed().as_millis();
-                let one_liner = tool_use_one_liner_pub("execute_command", &rtk_cmd[..rtk_cmd.len().min(60)], &result, elapsed_ms as u64);
+                let one_liner = tool_use_one_liner_pub("execute_command", &rtk_cmd[..rtk_cmd.len().min(60)], &outcome.output, elapsed_ms as u64);
                 llama_chat_db::event_log::log_event(conversation_id, "tool_done", &one_liner);
                 llama_chat_db::event_log::log_event(
                     conversation_id,
@@ -133,7 +174,7 @@ pub(crate) fn execute_single_call(
                         ..Default::default()
                     });
                 }
-                result
+                (outcome.output, outcome.success)
             }
         }
     } else {
@@ -209,10 +250,11 @@ pub(crate) fn execute_single_call(
                 });
             }
             all_images.extend(native_result.images);
+            let succeeded = native_result.success && result_status != "error";
             if result_status.is_empty() {
-                native_result.text
+                (native_result.text, succeeded)
             } else {
-                format!("[TOOL_RESULT:{}]{}", result_status, native_result.text)
+                (format!("[TOOL_RESULT:{}]{}", result_status, native_result.text), succeeded)
             }
         } else {
             let trimmed_cmd = command_text.trim();
@@ -228,12 +270,12 @@ pub(crate) fn execute_single_call(
                         ..Default::default()
                     });
                 }
-                err_msg
+                (err_msg, false)
             } else {
                 log_info!(conversation_id, "🐚 Falling back to streaming shell execution");
                 let rtk_cmd = command_text.strip_prefix("rtk ").unwrap_or(command_text).to_string();
                 let sender_clone = token_sender.clone();
-                execute_command_streaming(&rtk_cmd, cancel.clone(), |line| {
+                let outcome = run_command_streaming(&rtk_cmd, cancel.clone(), None, &mut |line| {
                     if let Some(ref sender) = sender_clone {
                         let _ = sender.send(TokenData {
                             token: format!("{}\n", strip_ansi_codes(line)),
@@ -243,7 +285,8 @@ pub(crate) fn execute_single_call(
                             ..Default::default()
                         });
                     }
-                })
+                });
+                (outcome.output, outcome.success)
             }
         }
     };
@@ -253,5 +296,5 @@ pub(crate) fn execute_single_call(
     } else {
         super::output_assembly::extract_image_summary_prompt(command_text)
     };
-    (output, all_images, image_summary_prompt)
+    ((output, all_images, image_summary_prompt), succeeded)
 }
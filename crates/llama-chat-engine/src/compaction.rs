@@ -586,3 +586,53 @@ pub fn force_compact_conversation(
 
     Ok(())
 }
+
+/// Summarize the oldest `turns` live turns and replace them with one summary
+/// (merged with any earlier one). The originals stay in the DB, flagged as
+/// compacted. Returns how many messages the summary now covers.
+pub fn compact_oldest_turns(
+    conversation_id: &str,
+    turns: usize,
+    db: &SharedDatabase,
+    llama_state: &llama_chat_types::models::SharedLlamaState,
+    status_sender: Option<&tokio::sync::mpsc::UnboundedSender<TokenData>>,
+) -> Result<usize, String> {
+    let up_to_sequence = db
+        .turn_compaction_cutoff(conversation_id, turns)?
+        .ok_or("Not enough turns to compact; the latest turn stays live")?;
+
+    let state_guard = llama_state.lock().map_err(|_| "Failed to lock LLaMA state")?;
+    let state = state_guard.as_ref().ok_or("No model loaded")?;
+    let model = state.model.as_ref().ok_or("No model loaded")?;
+    let real_ctx = state.inference_cache.as_ref()
+        .map(|c| c.context_size)
+        .or(state.model_context_length)
+        .unwrap_or(4096);
+
+    let messages = db.get_messages(conversation_id)?;
+    let old_text: String = messages.iter()
+        .filter(|m| !m.compacted && m.role != "system" && m.sequence_order <= up_to_sequence)
+        .map(|m| format!("{}:\n{}", m.role.to_uppercase(), m.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let previous_summary: Option<String> = db
+        .get_compaction_summaries(conversation_id)
+        .ok()
+        .and_then(|mut v| v.pop())
+        .map(|r| r.summary_text);
+
+    eprintln!(
+        "[COMPACTION] Compacting oldest {turns} turn(s) up to seq {up_to_sequence} ({} chars), conv={conversation_id}",
+        old_text.len()
+    );
+    send_status(status_sender, "Compacting conversation...");
+    let summary = summarize_conversation(
+        model, &state.backend, &old_text, state.chat_template_string.as_deref(), conversation_id,
+        real_ctx, previous_summary.as_deref(), status_sender,
+    );
+    llama_chat_db::event_log::clear_global_status();
+
+    let covered = db.compact_messages(conversation_id, up_to_sequence, &summary?)?;
+    log_info!(conversation_id, "📦 Compacted oldest {} turn(s): {} messages now summarized", turns, covered);
+    Ok(covered)
+}
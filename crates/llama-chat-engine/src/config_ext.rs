@@ -45,15 +45,7 @@ pub fn get_resolved_system_prompt(
     }
 
     // Cache miss: resolve
-    let resolved = match config.system_prompt.as_deref() {
-        Some("__AGENTIC__") | None => {
-            // Both explicit agentic marker and no prompt default to agentic mode
-            let general_name = current_key.1.as_deref();
-            let tags = get_tool_tags_for_model(general_name);
-            Some(get_universal_system_prompt_with_tags(&tags))
-        }
-        Some(custom) => Some(custom.to_string()),
-    };
+    let resolved = resolve_system_prompt(config.system_prompt.as_deref(), current_key.1.as_deref());
 
     // Store in cache
     if let Some(ref state_arc) = llama_state {
@@ -67,3 +59,110 @@ pub fn get_resolved_system_prompt(
 
     resolved
 }
+
+/// Swap the system prompt without reloading the model.
+///
+/// Saves `prompt` to the config and stores its resolution under the matching
+/// cache key, so the warmup that follows (and the next generation) hit the
+/// cache instead of resolving the old prompt again.
+pub fn set_system_prompt(
+    db: &Database,
+    llama_state: &SharedLlamaState,
+    prompt: Option<String>,
+) -> Result<(), String> {
+    let mut db_config = db.load_config();
+    if db_config.system_prompt != prompt {
+        db_config.system_prompt = prompt.clone();
+        db.save_config(&db_config)?;
+    }
+
+    let mut guard = llama_state
+        .lock()
+        .map_err(|_| "Failed to lock LLaMA state")?;
+    let state = guard
+        .as_mut()
+        .filter(|s| s.model.is_some())
+        .ok_or("No model loaded")?;
+    state.cached_system_prompt =
+        resolve_system_prompt(prompt.as_deref(), state.general_name.as_deref());
+    state.cached_prompt_key = Some((prompt, state.general_name.clone()));
+    Ok(())
+}
+
+/// Resolve a configured prompt, in the priority order of `get_resolved_system_prompt`.
+fn resolve_system_prompt(
+    system_prompt: Option<&str>,
+    general_name: Option<&str>,
+) -> Option<String> {
+    match system_prompt {
+        Some("__AGENTIC__") | None => {
+            // Both explicit agentic marker and no prompt default to agentic mode
+            let tags = get_tool_tags_for_model(general_name);
+            Some(get_universal_system_prompt_with_tags(&tags))
+        }
+        Some(custom) => Some(custom.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_set_system_prompt_without_model_still_saves_config() {
+        let db = Database::new(":memory:").unwrap();
+        let state: SharedLlamaState = Arc::new(Mutex::new(None));
+        let prompt = Some("You are terse.".to_string());
+        assert_eq!(
+            set_system_prompt(&db, &state, prompt.clone()),
+            Err("No model loaded".to_string())
+        );
+        assert_eq!(db.load_config().system_prompt, prompt);
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_set_system_prompt_updates_key_and_rewarms() {
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let db = Database::new(":memory:").unwrap();
+        let state: SharedLlamaState = Arc::new(Mutex::new(None));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(crate::model_manager::load_model(
+            state.clone(),
+            &model_path,
+            Some(0),
+            None,
+            None,
+            None,
+            None,
+        ))
+        .unwrap();
+
+        let prompt = "You are a pirate.".to_string();
+        set_system_prompt(&db, &state, Some(prompt.clone())).unwrap();
+        let general_name = state.lock().unwrap().as_ref().unwrap().general_name.clone();
+        assert_eq!(
+            state.lock().unwrap().as_ref().unwrap().cached_prompt_key,
+            Some((Some(prompt.clone()), general_name))
+        );
+        assert_eq!(
+            get_resolved_system_prompt(&db, &Some(state.clone())),
+            Some(prompt.clone())
+        );
+
+        crate::warmup_system_prompt(state.clone(), &db, None).unwrap();
+        let guard = state.lock().unwrap();
+        let s = guard.as_ref().unwrap();
+        let cache = s
+            .inference_cache
+            .as_ref()
+            .expect("warmup stores the system prompt prefix");
+        assert_eq!(
+            cache.conversation_id,
+            crate::prompt_builder::WARMUP_CONVERSATION_ID
+        );
+        assert_eq!(s.cached_system_prompt.as_deref(), Some(prompt.as_str()));
+    }
+}
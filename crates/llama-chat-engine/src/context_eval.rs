@@ -12,6 +12,24 @@ use llama_chat_types::*;
 // Constants for LLaMA configuration
 pub(crate) const CONTEXT_SIZE: u32 = 32768;
 
+/// Context size to allocate: an explicit `requested` size wins; otherwise the
+/// model's advertised context (or [`CONTEXT_SIZE`] if unknown), bounded by
+/// `cap` unless it is 0. The second value is the advertised size when capped.
+pub(crate) fn resolve_context_size(
+    requested: Option<u32>,
+    model_context_length: Option<u32>,
+    cap: u32,
+) -> (u32, Option<u32>) {
+    if let Some(size) = requested {
+        return (size, None);
+    }
+    let advertised = model_context_length.unwrap_or(CONTEXT_SIZE);
+    if cap > 0 && advertised > cap {
+        (cap, Some(advertised))
+    } else {
+        (advertised, None)
+    }
+}
 
 /// Parse a KV cache type string (from config) into the llama-cpp-2 enum.
 pub(crate) fn parse_kv_cache_type(s: &str) -> KvCacheType {
@@ -35,6 +53,20 @@ pub(crate) fn parse_kv_cache_type(s: &str) -> KvCacheType {
     }
 }
 
+/// Apply the load-time KV cache types (from the load request) over the config's
+/// cache_type_k/cache_type_v, so contexts use the same types the VRAM estimate assumed.
+pub(crate) fn apply_kv_cache_override(
+    config: &mut SamplerConfig,
+    kv_cache_type: llama_chat_types::KvCacheTypes,
+) {
+    if let Some(k) = kv_cache_type.k {
+        config.cache_type_k = k.as_str().to_string();
+    }
+    if let Some(v) = kv_cache_type.v {
+        config.cache_type_v = v.as_str().to_string();
+    }
+}
+
 /// Build LlamaContextParams from config, applying all context-level settings.
 pub(crate) fn build_context_params(
     n_ctx: NonZeroU32,
@@ -77,7 +109,8 @@ pub(crate) fn build_context_params(
 /// Evaluate tokenized prompt through the model, reusing KV cache when possible.
 ///
 /// Returns `(context, skip_count)` where `skip_count` is how many tokens were
-/// already in the cache and didn't need re-evaluation.
+/// already in the cache and didn't need re-evaluation. New tokens are decoded in
+/// `batch_cap`-sized chunks; `on_progress(done, total)` reports after each one.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn evaluate_text_prompt(
     inference_cache: &mut Option<InferenceCache>,
@@ -93,6 +126,7 @@ pub(crate) fn evaluate_text_prompt(
     config: &SamplerConfig,
     batch_cap: usize,
     cancel: Option<&std::sync::Arc<std::sync::atomic::AtomicBool>>,
+    on_progress: &mut dyn FnMut(usize, usize),
 ) -> Result<(LlamaContext<'static>, usize), String> {
     let n_ctx = NonZeroU32::new(context_size).expect("Context size must be non-zero");
 
@@ -151,39 +185,34 @@ pub(crate) fn evaluate_text_prompt(
             new_tokens.len(), n_chunks, skip_tokens);
 
         let mut batch = LlamaBatch::new(batch_cap, 1);
-        for chunk_idx in 0..n_chunks {
-            if let Some(cf) = cancel {
-                if cf.load(std::sync::atomic::Ordering::Relaxed) {
-                    unsafe { ctx.set_abort_callback(None, std::ptr::null_mut()); }
-                    return Err("Cancelled".to_string());
-                }
+        let result = decode_in_chunks(new_tokens.len(), batch_cap, |chunk_idx, range| {
+            if cancel.is_some_and(|c| c.load(std::sync::atomic::Ordering::Relaxed)) {
+                return Err("Cancelled".to_string());
             }
 
-            let start = chunk_idx * batch_cap;
-            let end = std::cmp::min(start + batch_cap, new_tokens.len());
-
             batch.clear();
-            for (offset, &token) in new_tokens[start..end].iter().enumerate() {
-                let pos = skip_tokens + start + offset;
+            for (offset, &token) in new_tokens[range.clone()].iter().enumerate() {
+                let pos = skip_tokens + range.start + offset;
                 let is_last = pos == tokens.len() - 1;
                 batch.add(token, pos as i32, &[0], is_last)
                     .map_err(|e| format!("Batch add failed at prompt token {pos}: {e}"))?;
             }
 
-            if let Err(e) = ctx.decode(&mut batch) {
+            ctx.decode(&mut batch).map_err(|e| {
                 let err_str = format!("{e}");
                 if err_str.contains("NoKvCacheSlot") {
-                    unsafe { ctx.set_abort_callback(None, std::ptr::null_mut()); }
-                    return Err("Context too small for conversation — try increasing context size or starting a new conversation".to_string());
+                    return "Context too small for conversation — try increasing context size or starting a new conversation".to_string();
                 }
                 // Abort callback triggered — treat as cancellation
                 if err_str.contains("Unknown(2)") || cancel.is_some_and(|c| c.load(std::sync::atomic::Ordering::Relaxed)) {
-                    unsafe { ctx.set_abort_callback(None, std::ptr::null_mut()); }
-                    return Err("Cancelled".to_string());
+                    return "Cancelled".to_string();
                 }
-                unsafe { ctx.set_abort_callback(None, std::ptr::null_mut()); }
-                return Err(format!("Prompt decode failed (chunk {}/{}): {e}", chunk_idx + 1, n_chunks));
-            }
+                format!("Prompt decode failed (chunk {}/{}): {e}", chunk_idx + 1, n_chunks)
+            })
+        }, on_progress);
+        if let Err(e) = result {
+            unsafe { ctx.set_abort_callback(None, std::ptr::null_mut()); }
+            return Err(e);
         }
     } else {
         log_info!(conversation_id, "All {} prompt tokens already in KV cache, skipping decode", tokens.len());
@@ -195,6 +224,30 @@ pub(crate) fn evaluate_text_prompt(
     Ok((ctx, skip_tokens))
 }
 
+/// Run `decode(chunk_idx, range)` over `n_tokens` prompt tokens in `batch_cap`-sized
+/// ranges, stopping at the first error.
+///
+/// `on_progress(done, total)` fires after each chunk, but only when the prompt spans
+/// more than one chunk — single-batch prompts finish too fast to be worth reporting.
+pub(crate) fn decode_in_chunks(
+    n_tokens: usize,
+    batch_cap: usize,
+    mut decode: impl FnMut(usize, std::ops::Range<usize>) -> Result<(), String>,
+    on_progress: &mut dyn FnMut(usize, usize),
+) -> Result<(), String> {
+    let batch_cap = batch_cap.max(1);
+    let n_chunks = n_tokens.div_ceil(batch_cap);
+    for chunk_idx in 0..n_chunks {
+        let start = chunk_idx * batch_cap;
+        let end = std::cmp::min(start + batch_cap, n_tokens);
+        decode(chunk_idx, start..end)?;
+        if n_chunks > 1 {
+            on_progress(end, n_tokens);
+        }
+    }
+    Ok(())
+}
+
 /// Create a fresh LlamaContext with transmuted 'static lifetime for cache storage.
 pub(crate) fn create_fresh_context(
     model: &LlamaModel,
@@ -211,3 +264,116 @@ pub(crate) fn create_fresh_context(
         Ok(std::mem::transmute::<LlamaContext<'_>, LlamaContext<'static>>(real_ctx))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kv_cache_type_maps_to_llama_type() {
+        use llama_chat_types::KvCacheType as Requested;
+        assert!(matches!(parse_kv_cache_type(Requested::F16.as_str()), KvCacheType::F16));
+        assert!(matches!(parse_kv_cache_type(Requested::Q8_0.as_str()), KvCacheType::Q8_0));
+        assert!(matches!(parse_kv_cache_type(Requested::Q4_0.as_str()), KvCacheType::Q4_0));
+        assert!(matches!(parse_kv_cache_type(Requested::F32.as_str()), KvCacheType::F32));
+        assert!(matches!(parse_kv_cache_type(Requested::Q4_1.as_str()), KvCacheType::Q4_1));
+        assert!(matches!(parse_kv_cache_type(Requested::Q5_0.as_str()), KvCacheType::Q5_0));
+        assert!(matches!(parse_kv_cache_type(Requested::Q5_1.as_str()), KvCacheType::Q5_1));
+        for turbo in [Requested::Turbo2, Requested::Turbo3, Requested::Turbo4] {
+            assert!(matches!(parse_kv_cache_type(turbo.as_str()), KvCacheType::Q4_0));
+        }
+    }
+
+    #[test]
+    fn test_apply_kv_cache_override() {
+        use llama_chat_types::{KvCacheType as Requested, KvCacheTypes};
+        let mut config = SamplerConfig::default();
+        apply_kv_cache_override(&mut config, Default::default());
+        assert_eq!((config.cache_type_k.as_str(), config.cache_type_v.as_str()), ("f16", "f16"));
+
+        apply_kv_cache_override(&mut config, KvCacheTypes::both(Requested::Q4_0));
+        assert_eq!((config.cache_type_k.as_str(), config.cache_type_v.as_str()), ("q4_0", "q4_0"));
+
+        // K and V can differ; an unset side keeps the config's type
+        apply_kv_cache_override(&mut config, KvCacheTypes { k: Some(Requested::Turbo2), v: None });
+        assert_eq!((config.cache_type_k.as_str(), config.cache_type_v.as_str()), ("turbo2", "q4_0"));
+    }
+
+    #[test]
+    fn test_default_context_is_capped() {
+        let cap = DEFAULT_CONTEXT_CAP;
+        // 128K model without an explicit size gets the cap
+        assert_eq!(
+            resolve_context_size(None, Some(131072), cap),
+            (8192, Some(131072))
+        );
+        // Models within the cap keep their own context
+        assert_eq!(resolve_context_size(None, Some(4096), cap), (4096, None));
+        // An explicit larger request overrides the cap
+        assert_eq!(
+            resolve_context_size(Some(131072), Some(131072), cap),
+            (131072, None)
+        );
+        // A cap of 0 uses the full advertised context
+        assert_eq!(resolve_context_size(None, Some(131072), 0), (131072, None));
+        assert_eq!(resolve_context_size(None, None, 0), (CONTEXT_SIZE, None));
+    }
+
+    #[test]
+    fn test_prompt_larger_than_batch_decodes_in_chunks() {
+        let mut ranges = Vec::new();
+        let mut progress = Vec::new();
+        decode_in_chunks(
+            5000,
+            2048,
+            |_, range| {
+                assert!(range.len() <= 2048);
+                ranges.push(range);
+                Ok(())
+            },
+            &mut |done, total| progress.push((done, total)),
+        )
+        .unwrap();
+
+        assert_eq!(ranges, vec![0..2048, 2048..4096, 4096..5000]);
+        assert_eq!(progress, vec![(2048, 5000), (4096, 5000), (5000, 5000)]);
+    }
+
+    #[test]
+    fn test_single_batch_prompt_reports_no_progress() {
+        let mut calls = 0;
+        let mut progress = Vec::new();
+        decode_in_chunks(
+            512,
+            2048,
+            |_, _| {
+                calls += 1;
+                Ok(())
+            },
+            &mut |done, total| progress.push((done, total)),
+        )
+        .unwrap();
+
+        assert_eq!(calls, 1);
+        assert!(progress.is_empty());
+    }
+
+    #[test]
+    fn test_decode_in_chunks_stops_on_error() {
+        let mut calls = 0;
+        let mut progress = Vec::new();
+        let result = decode_in_chunks(
+            300,
+            100,
+            |chunk_idx, _| {
+                calls += 1;
+                if chunk_idx == 1 { Err("Cancelled".to_string()) } else { Ok(()) }
+            },
+            &mut |done, total| progress.push((done, total)),
+        );
+
+        assert_eq!(result.unwrap_err(), "Cancelled");
+        assert_eq!(calls, 2);
+        assert_eq!(progress, vec![(100, 300)]);
+    }
+}
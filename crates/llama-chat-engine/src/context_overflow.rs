@@ -0,0 +1,241 @@
+// Context-overflow handling for the Generate command's `on_overflow` policy.
+//
+// AutoReduceContext is the compaction path in generation setup; this module
+// covers the alternatives: trimming the oldest messages out of the prompt
+// (the DB keeps them) or failing fast.
+
+use llama_chat_types::{OverflowPolicy, OverflowReport};
+
+/// Prompts above 95% of the context leave no room to generate.
+pub(crate) fn prompt_fits(n_tokens: usize, context_size: u32) -> bool {
+    n_tokens <= context_size.saturating_sub(context_size / 20) as usize
+}
+
+/// Token budget the overflow policy fits the prompt into. TrimOldest also
+/// trims down to the model's trained context when `n_ctx` is larger than it;
+/// the other policies only warn (see `trained_context_warning`).
+pub(crate) fn overflow_budget(
+    policy: OverflowPolicy,
+    context_size: u32,
+    trained_context: Option<u32>,
+) -> u32 {
+    match (policy, trained_context) {
+        (OverflowPolicy::TrimOldest, Some(trained)) if trained > 0 => context_size.min(trained),
+        _ => context_size,
+    }
+}
+
+/// Warning for a prompt longer than the context the model was trained on.
+/// Many models produce garbage past it even when `n_ctx` has room.
+pub(crate) fn trained_context_warning(n_tokens: usize, trained_context: Option<u32>) -> Option<String> {
+    let trained = trained_context.filter(|&t| t > 0)?;
+    (n_tokens > trained as usize).then(|| {
+        format!(
+            "Prompt is {n_tokens} tokens, longer than the {trained}-token context this model was \
+             trained on — output may degrade. Start a new conversation or use on_overflow=trim_oldest."
+        )
+    })
+}
+
+/// Apply a non-compacting overflow policy to `conversation` (the "ROLE:\n..."
+/// text from the DB). `fits` renders and measures a candidate conversation.
+///
+/// Returns the conversation to build the prompt from plus the report for the
+/// completion payload. AutoReduceContext passes through unchanged, since
+/// compaction already ran.
+pub(crate) fn apply_overflow_policy(
+    policy: OverflowPolicy,
+    conversation: &str,
+    mut fits: impl FnMut(&str) -> Result<bool, String>,
+) -> Result<(String, OverflowReport), String> {
+    let report = |trimmed_messages| OverflowReport {
+        policy,
+        trimmed_messages,
+    };
+    match policy {
+        OverflowPolicy::AutoReduceContext => Ok((conversation.to_string(), report(0))),
+        OverflowPolicy::Error => {
+            if fits(conversation)? {
+                Ok((conversation.to_string(), report(0)))
+            } else {
+                Err(
+                    "Context too small for conversation (on_overflow=error) — try increasing \
+                     context size or starting a new conversation"
+                        .to_string(),
+                )
+            }
+        }
+        OverflowPolicy::TrimOldest => {
+            let (trimmed, count) = trim_oldest_messages(conversation, fits)?;
+            Ok((trimmed, report(count)))
+        }
+    }
+}
+
+/// Drop the fewest oldest user/assistant messages that make `fits` pass.
+/// SYSTEM blocks (compaction summaries) and the newest message are never dropped.
+fn trim_oldest_messages(
+    conversation: &str,
+    mut fits: impl FnMut(&str) -> Result<bool, String>,
+) -> Result<(String, usize), String> {
+    if fits(conversation)? {
+        return Ok((conversation.to_string(), 0));
+    }
+    let blocks = split_messages(conversation);
+    // Oldest first; the last block is the message being answered
+    let removable: Vec<usize> = blocks
+        .iter()
+        .enumerate()
+        .take(blocks.len().saturating_sub(1))
+        .filter(|(_, b)| !b.starts_with("SYSTEM:"))
+        .map(|(i, _)| i)
+        .collect();
+    let without_oldest = |n: usize| -> String {
+        blocks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !removable[..n].contains(i))
+            .map(|(_, b)| *b)
+            .collect()
+    };
+
+    // Fit is monotonic in the number dropped, so binary search the smallest count
+    let (mut lo, mut hi) = (1, removable.len());
+    if hi == 0 || !fits(&without_oldest(hi))? {
+        return Err(
+            "Context too small for conversation even after trimming all earlier messages \
+             (on_overflow=trim_oldest) — try increasing context size"
+                .to_string(),
+        );
+    }
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if fits(&without_oldest(mid))? {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Ok((without_oldest(lo), lo))
+}
+
+/// Split conversation text into whole blocks, each starting at a role header.
+/// Text before the first header stays attached to the first block.
+fn split_messages(conversation: &str) -> Vec<&str> {
+    let mut starts = vec![0];
+    let mut offset = 0;
+    for line in conversation.split_inclusive('\n') {
+        if offset > 0 && matches!(line.trim_end(), "SYSTEM:" | "USER:" | "ASSISTANT:") {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    starts.push(conversation.len());
+    starts
+        .windows(2)
+        .map(|w| &conversation[w[0]..w[1]])
+        .filter(|b| !b.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Summary + 10 turns of ~400 chars each (~100 "tokens" at chars/4).
+    fn oversized_conversation() -> String {
+        let mut text = String::from(
+            "SYSTEM:\n[Conversation summary — 4 earlier messages compacted]\nearlier work\n\n",
+        );
+        for i in 0..10 {
+            let role = if i % 2 == 0 { "USER" } else { "ASSISTANT" };
+            text.push_str(&format!("{role}:\nmessage {i} {}\n\n", "x".repeat(400)));
+        }
+        text
+    }
+
+    /// chars/4 token estimate against a 1024-token context.
+    fn fits_in_1024(text: &str) -> Result<bool, String> {
+        Ok(prompt_fits(text.len() / 4, 1024))
+    }
+
+    #[test]
+    fn test_auto_reduce_passes_through() {
+        let conv = oversized_conversation();
+        let (out, report) = apply_overflow_policy(OverflowPolicy::AutoReduceContext, &conv, |_| {
+            panic!("auto-reduce must not re-measure")
+        })
+        .unwrap();
+        assert_eq!(out, conv);
+        assert_eq!(report, OverflowReport::default());
+    }
+
+    #[test]
+    fn test_error_policy_rejects_oversized() {
+        let conv = oversized_conversation();
+        let err = apply_overflow_policy(OverflowPolicy::Error, &conv, fits_in_1024).unwrap_err();
+        assert!(err.contains("on_overflow=error"), "{err}");
+
+        let (out, report) =
+            apply_overflow_policy(OverflowPolicy::Error, "USER:\nhi\n\n", fits_in_1024).unwrap();
+        assert_eq!(out, "USER:\nhi\n\n");
+        assert_eq!(report.policy, OverflowPolicy::Error);
+    }
+
+    #[test]
+    fn test_trim_oldest_drops_minimum_and_keeps_protected_blocks() {
+        let conv = oversized_conversation();
+        let (out, report) =
+            apply_overflow_policy(OverflowPolicy::TrimOldest, &conv, fits_in_1024).unwrap();
+
+        // ~1070 estimated tokens vs a 972 budget: dropping one ~104-token turn is enough
+        assert_eq!(report.policy, OverflowPolicy::TrimOldest);
+        assert_eq!(report.trimmed_messages, 1);
+        assert!(fits_in_1024(&out).unwrap());
+        // Summary and newest message survive; the oldest turn is gone
+        assert!(out.starts_with("SYSTEM:\n[Conversation summary"));
+        assert!(out.contains("message 9 "));
+        assert!(!out.contains("message 0 "));
+        assert_eq!(split_messages(&out).len(), 11 - report.trimmed_messages);
+    }
+
+    #[test]
+    fn test_trim_oldest_fails_when_newest_alone_is_too_big() {
+        let conv = format!("USER:\nold\n\nUSER:\n{}\n\n", "y".repeat(8000));
+        let err =
+            apply_overflow_policy(OverflowPolicy::TrimOldest, &conv, fits_in_1024).unwrap_err();
+        assert!(err.contains("on_overflow=trim_oldest"), "{err}");
+    }
+
+    #[test]
+    fn test_prompt_beyond_trained_context_warns() {
+        let warning = trained_context_warning(5000, Some(4096)).unwrap();
+        assert!(warning.contains("5000 tokens"), "{warning}");
+        assert!(warning.contains("4096-token context"), "{warning}");
+
+        assert_eq!(trained_context_warning(4096, Some(4096)), None);
+        assert_eq!(trained_context_warning(100, Some(4096)), None);
+        // Unknown trained context: nothing to compare against
+        assert_eq!(trained_context_warning(5000, None), None);
+        assert_eq!(trained_context_warning(5000, Some(0)), None);
+    }
+
+    #[test]
+    fn test_trim_oldest_budget_respects_trained_context() {
+        assert_eq!(overflow_budget(OverflowPolicy::TrimOldest, 32768, Some(4096)), 4096);
+        assert_eq!(overflow_budget(OverflowPolicy::TrimOldest, 2048, Some(4096)), 2048);
+        assert_eq!(overflow_budget(OverflowPolicy::TrimOldest, 32768, None), 32768);
+        // The other policies keep the allocated context and only warn
+        assert_eq!(overflow_budget(OverflowPolicy::AutoReduceContext, 32768, Some(4096)), 32768);
+        assert_eq!(overflow_budget(OverflowPolicy::Error, 32768, Some(4096)), 32768);
+    }
+
+    #[test]
+    fn test_split_messages_keeps_inline_role_words() {
+        let conv = "USER:\nwhat does USER: mean?\n\nASSISTANT:\nA label.\n";
+        assert_eq!(
+            split_messages(conv),
+            vec!["USER:\nwhat does USER: mean?\n\n", "ASSISTANT:\nA label.\n"]
+        );
+    }
+}
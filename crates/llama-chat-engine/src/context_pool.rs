@@ -0,0 +1,365 @@
+// Concurrent generations on one loaded model.
+//
+// Every generation holds a slot from `generation_slots()` while it runs; with
+// all `max_parallel_generations` slots taken, further generations wait in
+// arrival order. Each running generation decodes on its own LlamaContext:
+// finished contexts are parked in `LlamaState` and handed to the next
+// generation of the same conversation, so its KV cache is reused. Parked
+// contexts are capped in number and, before a fresh GPU context is created,
+// by free VRAM (`make_vram_room`), oldest first. The state lock is released
+// while decoding, so `share_model()` keeps the model itself from being
+// reloaded or unloaded under a running generation: whoever drops or replaces
+// the model must hold `exclusive_model()` while doing so.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use llama_chat_types::{InferenceCache, KvCacheType, LlamaState};
+
+use super::gpu_devices::enumerate_gpus;
+use super::prompt_builder::WARMUP_CONVERSATION_ID;
+use super::vram_calculator::kv_cache_bytes_per_element;
+
+/// How often a queued generation re-checks its cancel flag.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+static SLOTS: GenerationSlots = GenerationSlots::new(1);
+static MODEL_USE: RwLock<()> = RwLock::new(());
+
+/// The worker-wide generation slots.
+pub fn generation_slots() -> &'static GenerationSlots {
+    &SLOTS
+}
+
+/// Held by each generation while it uses the loaded model.
+pub fn share_model() -> RwLockReadGuard<'static, ()> {
+    MODEL_USE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Held while reloading or unloading the model; waits for running generations.
+pub fn exclusive_model() -> RwLockWriteGuard<'static, ()> {
+    MODEL_USE.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A counting gate with a FIFO queue: at most `capacity` generations run at once.
+pub struct GenerationSlots {
+    state: Mutex<SlotState>,
+    freed: Condvar,
+}
+
+struct SlotState {
+    capacity: usize,
+    in_use: usize,
+    next_ticket: u64,
+    /// Tickets of queued generations, oldest first.
+    waiting: VecDeque<u64>,
+}
+
+impl GenerationSlots {
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(SlotState {
+                capacity,
+                in_use: 0,
+                next_ticket: 0,
+                waiting: VecDeque::new(),
+            }),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Change how many generations may run at once (at least 1). Running
+    /// generations are unaffected; a raised limit admits queued ones now.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.lock().capacity = capacity.max(1);
+        self.freed.notify_all();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    /// Generations currently holding a slot.
+    pub fn in_use(&self) -> usize {
+        self.lock().in_use
+    }
+
+    /// Wait for a free slot. `on_queued(position)` is called once if the
+    /// generation has to wait (1 = next in line). Cancelling while queued
+    /// returns `Err("Cancelled")`.
+    pub fn acquire(
+        &self,
+        cancel: &AtomicBool,
+        on_queued: impl FnOnce(usize),
+    ) -> Result<GenerationSlot<'_>, String> {
+        let mut state = self.lock();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.waiting.push_back(ticket);
+        let mut on_queued = Some(on_queued);
+        loop {
+            if state.in_use < state.capacity && state.waiting.front() == Some(&ticket) {
+                state.waiting.pop_front();
+                state.in_use += 1;
+                drop(state);
+                // The next in line may fit too
+                self.freed.notify_all();
+                return Ok(GenerationSlot { slots: self });
+            }
+            if cancel.load(Ordering::Relaxed) {
+                state.waiting.retain(|&t| t != ticket);
+                drop(state);
+                self.freed.notify_all();
+                return Err("Cancelled".to_string());
+            }
+            if let Some(notify) = on_queued.take() {
+                let position = state.waiting.iter().position(|&t| t == ticket).unwrap_or(0);
+                // Unlocked, so the callback may query the slots
+                drop(state);
+                notify(position + 1);
+                state = self.lock();
+                continue;
+            }
+            state = self
+                .freed
+                .wait_timeout(state, CANCEL_POLL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SlotState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A held generation slot; released on drop.
+pub struct GenerationSlot<'a> {
+    slots: &'a GenerationSlots,
+}
+
+impl Drop for GenerationSlot<'_> {
+    fn drop(&mut self) {
+        self.slots.lock().in_use -= 1;
+        self.slots.freed.notify_all();
+    }
+}
+
+/// Take the parked context holding `conversation_id`'s KV cache (or the
+/// warmup one), if any. Other parked contexts beyond what the running
+/// generations leave room for are dropped first, so a new context never
+/// pushes the total past `capacity`. None = create a fresh context.
+pub(crate) fn checkout_context(
+    state: &mut LlamaState,
+    conversation_id: &str,
+    capacity: usize,
+    in_use: usize,
+) -> Option<InferenceCache> {
+    let claims = |c: &InferenceCache, id: &str| c.conversation_id == id;
+    let found = [conversation_id, WARMUP_CONVERSATION_ID].iter().find_map(|id| {
+        if state.inference_cache.as_ref().is_some_and(|c| claims(c, id)) {
+            return state.inference_cache.take();
+        }
+        let i = state.idle_contexts.iter().position(|c| claims(c, id))?;
+        Some(state.idle_contexts.remove(i))
+    });
+    if found.is_none() {
+        // Room for this generation's new context: parked + running <= capacity
+        let room = capacity.saturating_sub(in_use);
+        while parked_count(state) > room {
+            if state.idle_contexts.is_empty() {
+                state.inference_cache = None;
+            } else {
+                state.idle_contexts.remove(0);
+            }
+        }
+    }
+    found
+}
+
+/// Park a finished generation's context as the primary cache. The previous
+/// primary joins the idle list; the oldest idle ones are dropped to keep at
+/// most `capacity` contexts parked.
+pub(crate) fn checkin_context(state: &mut LlamaState, cache: InferenceCache, capacity: usize) {
+    if let Some(previous) = state.inference_cache.replace(cache) {
+        state.idle_contexts.push(previous);
+    }
+    while parked_count(state) > capacity.max(1) {
+        state.idle_contexts.remove(0);
+    }
+}
+
+fn parked_count(state: &LlamaState) -> usize {
+    state.inference_cache.is_some() as usize + state.idle_contexts.len()
+}
+
+/// Bytes of GPU KV cache a context of `context_size` tokens takes with the
+/// given cache types (unknown types count as f16).
+pub(crate) fn kv_cache_bytes(
+    elements_per_token: f64,
+    context_size: u32,
+    cache_type_k: &str,
+    cache_type_v: &str,
+) -> u64 {
+    (kv_bytes_per_token(elements_per_token, cache_type_k, cache_type_v) * context_size as f64) as u64
+}
+
+/// Bytes of KV cache one context token takes with the given cache types.
+pub(crate) fn kv_bytes_per_token(elements_per_token: f64, cache_type_k: &str, cache_type_v: &str) -> f64 {
+    let bytes = |t: &str| kv_cache_bytes_per_element(KvCacheType::parse(t).unwrap_or(KvCacheType::F16));
+    // Half of the elements are K, half V
+    elements_per_token / 2.0 * (bytes(cache_type_k) + bytes(cache_type_v))
+}
+
+/// How many of the oldest parked contexts (`parked_bytes`, oldest first, each
+/// its GPU KV size) must go before a new context of `needed_bytes` fits in
+/// `free_bytes` of VRAM. Everything goes when even that isn't enough.
+fn contexts_to_evict(parked_bytes: &[u64], needed_bytes: u64, free_bytes: u64) -> usize {
+    let mut free = free_bytes;
+    let mut evicted = 0;
+    for bytes in parked_bytes {
+        if free >= needed_bytes {
+            break;
+        }
+        free = free.saturating_add(*bytes);
+        evicted += 1;
+    }
+    evicted
+}
+
+/// Before creating a GPU context of `needed_bytes`, drop the oldest parked
+/// contexts whose KV caches keep it from fitting in the free VRAM. Only a
+/// budget: `free_vram_bytes` None (CPU, or no driver to ask) drops nothing.
+pub(crate) fn make_vram_room(state: &mut LlamaState, needed_bytes: u64, free_vram_bytes: Option<u64>) {
+    let (Some(free), Some(elements)) = (free_vram_bytes, state.kv_elements_per_token) else {
+        return;
+    };
+    // Same order as the count cap: idle contexts oldest first, the primary last
+    let parked_bytes: Vec<u64> = state
+        .idle_contexts
+        .iter()
+        .chain(state.inference_cache.as_ref())
+        .map(|c| {
+            if c.offload_kqv {
+                kv_cache_bytes(elements, c.context_size, &c.cache_type_k, &c.cache_type_v)
+            } else {
+                0
+            }
+        })
+        .collect();
+    for _ in 0..contexts_to_evict(&parked_bytes, needed_bytes, free) {
+        if state.idle_contexts.is_empty() {
+            state.inference_cache = None;
+        } else {
+            state.idle_contexts.remove(0);
+        }
+    }
+}
+
+/// Free VRAM summed over the visible GPUs; None when none can be queried.
+pub(crate) fn free_vram_bytes() -> Option<u64> {
+    let gpus = enumerate_gpus();
+    (!gpus.is_empty()).then(|| gpus.iter().map(|g| g.free_vram_mb * 1024 * 1024).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    /// Stand-in for a decode loop: records how many run at the same time.
+    fn mocked_decode(running: &AtomicUsize, peak: &AtomicUsize) {
+        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(200));
+        running.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_two_slots_run_generations_concurrently() {
+        let slots = Arc::new(GenerationSlots::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let (slots, running, peak) = (slots.clone(), running.clone(), peak.clone());
+                std::thread::spawn(move || {
+                    let cancel = AtomicBool::new(false);
+                    let _slot = slots.acquire(&cancel, |_| panic!("should not queue")).unwrap();
+                    mocked_decode(&running, &peak);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(slots.in_use(), 0);
+    }
+
+    #[test]
+    fn test_third_generation_queues_until_a_slot_frees() {
+        let slots = Arc::new(GenerationSlots::new(2));
+        let cancel = AtomicBool::new(false);
+        let first = slots.acquire(&cancel, |_| {}).unwrap();
+        let _second = slots.acquire(&cancel, |_| {}).unwrap();
+
+        let started = Arc::new(AtomicBool::new(false));
+        let queued_at = Arc::new(AtomicUsize::new(0));
+        let third = {
+            let (slots, started, queued_at) = (slots.clone(), started.clone(), queued_at.clone());
+            std::thread::spawn(move || {
+                let cancel = AtomicBool::new(false);
+                let _slot = slots
+                    .acquire(&cancel, |pos| queued_at.store(pos, Ordering::SeqCst))
+                    .unwrap();
+                started.store(true, Ordering::SeqCst);
+            })
+        };
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(!started.load(Ordering::SeqCst));
+        assert_eq!(queued_at.load(Ordering::SeqCst), 1);
+
+        drop(first);
+        third.join().unwrap();
+        assert!(started.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_cancelled_while_queued_leaves_the_line() {
+        let slots = GenerationSlots::new(1);
+        let running = slots.acquire(&AtomicBool::new(false), |_| {}).unwrap();
+        let cancel = AtomicBool::new(true);
+        assert_eq!(slots.acquire(&cancel, |_| {}).err().as_deref(), Some("Cancelled"));
+        drop(running);
+        // The cancelled ticket doesn't block whoever comes next
+        assert!(slots.acquire(&AtomicBool::new(false), |_| {}).is_ok());
+    }
+
+    #[test]
+    fn test_parked_contexts_are_evicted_until_a_new_one_fits() {
+        const GB: u64 = 1 << 30;
+        // Enough VRAM already: nothing goes
+        assert_eq!(contexts_to_evict(&[2 * GB, 2 * GB], GB, 3 * GB), 0);
+        // Oldest first, only as many as needed
+        assert_eq!(contexts_to_evict(&[2 * GB, 2 * GB, 2 * GB], 3 * GB, GB), 1);
+        // CPU-resident contexts free nothing, so eviction moves past them
+        assert_eq!(contexts_to_evict(&[0, 2 * GB], 2 * GB, GB), 2);
+        // Never more than what's parked
+        assert_eq!(contexts_to_evict(&[GB], 8 * GB, 0), 1);
+    }
+
+    #[test]
+    fn test_kv_cache_bytes_follow_context_size_and_cache_type() {
+        // 32 layers x 8 KV heads x 128 dims, K and V
+        let elements = 2.0 * 32.0 * 8.0 * 128.0;
+        let f16 = kv_cache_bytes(elements, 8192, "f16", "f16");
+        assert_eq!(f16, 1 << 30);
+        assert_eq!(kv_cache_bytes(elements, 16384, "f16", "f16"), 2 * f16);
+        assert!(kv_cache_bytes(elements, 8192, "q8_0", "q8_0") < f16 * 3 / 5);
+        assert_eq!(kv_cache_bytes(elements, 8192, "bogus", "f16"), f16);
+    }
+}
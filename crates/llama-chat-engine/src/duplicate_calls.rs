@@ -0,0 +1,179 @@
+//! Per-turn memo of executed tool calls (`dedupe_tool_calls`).
+//!
+//! Models often re-issue the exact call they just made. An identical repeat
+//! (same name, same arguments up to key order) gets the earlier result back
+//! instead of running again, which matters most for side-effectful tools.
+//! Argument values are compared as-is: content differing only in whitespace
+//! is a different call. Any call that may change state (not in
+//! `READ_ONLY_TOOLS`) forgets the earlier entries, so re-reading a file after
+//! an edit still happens. Failed calls are never memoized, so they can be
+//! retried.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::tool_dispatch::is_read_only_tool;
+
+/// Longest earlier result repeated back to the model.
+const MAX_REPLAYED_CHARS: usize = 1500;
+
+/// Tools whose output changes without the model doing anything; never memoized.
+/// Shell commands are here because polling (`ls build/`, `tail log.txt`) is
+/// how the model watches background work.
+const VOLATILE_TOOLS: &[&str] = &[
+    "execute_command",
+    "check_background_process", "list_background_processes",
+    "take_screenshot", "list_windows", "get_cursor_position",
+    "get_active_window", "ocr_screen", "wait", "sleep",
+];
+
+/// Results of the calls executed so far in this generation.
+pub(crate) struct ExecutedCalls {
+    enabled: bool,
+    results: HashMap<String, String>,
+}
+
+impl ExecutedCalls {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self { enabled, results: HashMap::new() }
+    }
+
+    /// The earlier result of an identical call, if one ran this turn.
+    pub(crate) fn previous_result(&self, name: &str, args: &Value) -> Option<&str> {
+        if !self.enabled || is_volatile(name) {
+            return None;
+        }
+        self.results.get(&call_key(name, args)).map(String::as_str)
+    }
+
+    /// Remember a call that was actually executed. A failed one (`succeeded`
+    /// false) isn't kept, so a transient error can be retried.
+    pub(crate) fn record(&mut self, name: &str, args: &Value, result: &str, succeeded: bool) {
+        if !self.enabled {
+            return;
+        }
+        if !is_read_only_tool(name) {
+            // It may have changed what earlier calls would return now
+            self.results.clear();
+        }
+        if succeeded && !is_volatile(name) {
+            self.results.insert(call_key(name, args), result.to_string());
+        }
+    }
+}
+
+/// What the model gets instead of a second run.
+pub(crate) fn duplicate_call_reply(previous: &str) -> String {
+    let previous = previous.trim();
+    let shown = match previous.char_indices().nth(MAX_REPLAYED_CHARS) {
+        Some((end, _)) => format!("{}\n... (truncated)", &previous[..end]),
+        None => previous.to_string(),
+    };
+    format!(
+        "Already executed with these exact arguments earlier in this turn; not run again. The result was:\n{shown}"
+    )
+}
+
+fn is_volatile(name: &str) -> bool {
+    VOLATILE_TOOLS.contains(&name) || name.starts_with("browser_")
+}
+
+fn call_key(name: &str, args: &Value) -> String {
+    format!("{}\u{0}{}", name, normalize(args))
+}
+
+/// Arguments with object keys sorted; values are left untouched.
+fn normalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(entries.into_iter().map(|(k, v)| (k.clone(), normalize(v))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(normalize).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_repeated_read_file_is_not_re_executed() {
+        let mut calls = ExecutedCalls::new(true);
+        let args = json!({ "path": "src/main.rs", "offset": 0 });
+        assert!(calls.previous_result("read_file", &args).is_none());
+        calls.record("read_file", &args, "fn main() {}", true);
+
+        // Same call with keys reordered
+        let repeat = json!({ "offset": 0, "path": "src/main.rs" });
+        assert_eq!(calls.previous_result("read_file", &repeat), Some("fn main() {}"));
+        let reply = duplicate_call_reply(calls.previous_result("read_file", &repeat).unwrap());
+        assert!(reply.starts_with("Already executed"), "{reply}");
+        assert!(reply.ends_with("fn main() {}"), "{reply}");
+    }
+
+    #[test]
+    fn test_different_args_are_executed() {
+        let mut calls = ExecutedCalls::new(true);
+        calls.record("read_file", &json!({ "path": "a.txt" }), "A", true);
+        assert!(calls.previous_result("read_file", &json!({ "path": "b.txt" })).is_none());
+        assert!(calls.previous_result("list_directory", &json!({ "path": "a.txt" })).is_none());
+    }
+
+    #[test]
+    fn test_state_changing_call_forgets_earlier_results() {
+        let mut calls = ExecutedCalls::new(true);
+        let read = json!({ "path": "src/lib.rs" });
+        calls.record("read_file", &read, "pub fn a() {}", true);
+        assert!(calls.previous_result("read_file", &read).is_some());
+
+        calls.record("edit_file", &json!({ "path": "src/lib.rs" }), "ok", true);
+        assert!(calls.previous_result("read_file", &read).is_none());
+    }
+
+    #[test]
+    fn test_whitespace_in_values_makes_a_different_call() {
+        let mut calls = ExecutedCalls::new(true);
+        calls.record("write_file", &json!({ "path": "a.py", "content": "x = 1" }), "ok", true);
+        let padded = json!({ "path": "a.py", "content": "x = 1\n" });
+        assert!(calls.previous_result("write_file", &padded).is_none());
+    }
+
+    #[test]
+    fn test_failed_calls_can_be_retried() {
+        let mut calls = ExecutedCalls::new(true);
+        let fetch = json!({ "url": "https://example.com" });
+        calls.record("web_fetch", &fetch, "Error: connection reset", false);
+        assert!(calls.previous_result("web_fetch", &fetch).is_none());
+    }
+
+    #[test]
+    fn test_polling_shell_commands_always_run() {
+        let mut calls = ExecutedCalls::new(true);
+        let poll = json!({ "command": "ls build/" });
+        calls.record("execute_command", &poll, "(empty)", true);
+        assert!(calls.previous_result("execute_command", &poll).is_none());
+    }
+
+    #[test]
+    fn test_disabled_or_volatile_calls_always_run() {
+        let mut off = ExecutedCalls::new(false);
+        off.record("read_file", &json!({ "path": "a" }), "A", true);
+        assert!(off.previous_result("read_file", &json!({ "path": "a" })).is_none());
+
+        let mut on = ExecutedCalls::new(true);
+        on.record("check_background_process", &json!({ "pid": 7 }), "running", true);
+        assert!(on.previous_result("check_background_process", &json!({ "pid": 7 })).is_none());
+    }
+
+    #[test]
+    fn test_long_results_are_shortened() {
+        let reply = duplicate_call_reply(&"x".repeat(MAX_REPLAYED_CHARS * 2));
+        assert!(reply.ends_with("(truncated)"));
+        assert!(reply.len() < MAX_REPLAYED_CHARS + 200);
+    }
+}
@@ -0,0 +1,169 @@
+//! `n` > 1: extra completions for the same prompt.
+//!
+//! The first completion is the normal one: streamed, stored and kept in the
+//! conversation. Each further one branches off the prompt already in the KV
+//! cache: the cache is cut back to the end of the prompt, the last prompt
+//! token is decoded again for fresh logits, and the loop samples with its own
+//! seed. Alternatives are neither streamed nor stored and never run tools.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use llama_cpp_2::context::LlamaContext;
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::LlamaModel;
+use llama_cpp_2::token::LlamaToken;
+use tokio::sync::mpsc;
+
+use llama_chat_db::conversation::ConversationLogger;
+use llama_chat_types::{split_reasoning, AlternativeCompletion, ReasoningTags, SamplerConfig, TokenData};
+
+use crate::duplicate_calls::ExecutedCalls;
+use crate::sampler::create_sampler;
+use crate::token_loop::{run_generation_loop, TokenGenConfig, TokenGenState, VisionCtxRef};
+use crate::SharedConversationLogger;
+
+/// Completions a request asks for: `n` (default 1), which must be within
+/// `1..=max_completions`.
+pub fn completion_count(n: Option<u32>, max_completions: u32) -> Result<usize, String> {
+    let max = max_completions.max(1);
+    match n.unwrap_or(1) {
+        0 => Err("n must be at least 1".to_string()),
+        n if n > max => Err(format!("n must be at most {max} (max_completions)")),
+        n => Ok(n as usize),
+    }
+}
+
+/// Seed for completion `index`: a fixed seed is offset so every completion
+/// gets its own; a random one (< 0) stays random.
+fn completion_seed(seed: i32, index: usize) -> i32 {
+    if seed < 0 {
+        return seed;
+    }
+    ((seed as i64 + index as i64) % (i32::MAX as i64 + 1)) as i32
+}
+
+/// Generate completions 2..=`count` after the first has finished on `context`.
+/// Leaves just the prompt in the KV cache.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn generate_alternatives(
+    count: usize,
+    cfg: &TokenGenConfig<'_>,
+    context: &mut LlamaContext<'static>,
+    model: &LlamaModel,
+    batch: &mut LlamaBatch,
+    prompt_tokens: &[LlamaToken],
+    prefill: &str,
+    config: &SamplerConfig,
+    reasoning_tags: Option<&ReasoningTags>,
+    token_sender: &Option<mpsc::UnboundedSender<TokenData>>,
+    cancel: &Arc<AtomicBool>,
+    vision_ctx: VisionCtxRef<'_>,
+) -> Result<Vec<AlternativeCompletion>, String> {
+    // The loop syncs its text to a logger; give it one that isn't the conversation's
+    let scratch_db = Arc::new(llama_chat_db::Database::new(":memory:")?);
+    let scratch_logger: SharedConversationLogger =
+        Arc::new(Mutex::new(ConversationLogger::new(scratch_db, None)?));
+    let branch_cfg = TokenGenConfig {
+        mcp_manager: None,
+        db: cfg.db.clone(),
+        tools_enabled: false,
+        proactive_compaction: false,
+        logprobs: None,
+        stream_token_ids: false,
+        ..*cfg
+    };
+
+    let mut alternatives = Vec::with_capacity(count.saturating_sub(1));
+    for index in 1..count {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        if let Some(sender) = token_sender {
+            let _ = sender.send(TokenData {
+                status: Some(format!("Generating completion {} of {count}", index + 1)),
+                ..Default::default()
+            });
+        }
+        rewind_to_prompt(context, batch, prompt_tokens)?;
+
+        let seeded = SamplerConfig { seed: completion_seed(config.seed, index), ..config.clone() };
+        let mut sampler = create_sampler(&seeded, cfg.conversation_id, Some(model));
+        let mut gen = TokenGenState::new(
+            prefill.to_string(),
+            prompt_tokens.len() as i32,
+            ExecutedCalls::new(false),
+            None,
+        );
+        run_generation_loop(
+            &mut gen, &branch_cfg, context, model, &mut sampler,
+            batch, &None, &scratch_logger, cancel, vision_ctx,
+        )?;
+
+        let (content, reasoning) = match reasoning_tags {
+            Some(tags) => split_reasoning(&gen.response, tags),
+            None => (gen.response.clone(), None),
+        };
+        log_info!(
+            cfg.conversation_id,
+            "Completion {} of {}: {} tokens, finish={}",
+            index + 1, count, gen.total_tokens_generated, gen.finish_reason
+        );
+        alternatives.push(AlternativeCompletion {
+            content: content.trim().to_string(),
+            reasoning,
+            finish_reason: gen.finish_reason,
+            gen_tokens: gen.total_tokens_generated,
+        });
+    }
+
+    context
+        .clear_kv_cache_seq(Some(0), Some(prompt_tokens.len() as u32), None)
+        .map_err(|e| format!("KV cache rewind failed: {e}"))?;
+    Ok(alternatives)
+}
+
+/// Cut the KV cache back to the prompt minus its last token, then decode that
+/// token again so the next sample starts right after the prompt.
+fn rewind_to_prompt(
+    context: &mut LlamaContext<'static>,
+    batch: &mut LlamaBatch,
+    prompt_tokens: &[LlamaToken],
+) -> Result<(), String> {
+    let last = prompt_tokens.len().checked_sub(1).ok_or("Empty prompt")?;
+    context
+        .clear_kv_cache_seq(Some(0), Some(last as u32), None)
+        .map_err(|e| format!("KV cache rewind failed: {e}"))?;
+    batch.clear();
+    batch
+        .add(prompt_tokens[last], last as i32, &[0], true)
+        .map_err(|e| format!("Batch add failed at prompt token {last}: {e}"))?;
+    context
+        .decode(batch)
+        .map_err(|e| format!("Prompt decode failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_n_beyond_max_completions_is_rejected() {
+        assert_eq!(completion_count(None, 4), Ok(1));
+        assert_eq!(completion_count(Some(3), 4), Ok(3));
+        assert_eq!(completion_count(Some(4), 4), Ok(4));
+        assert_eq!(
+            completion_count(Some(5), 4),
+            Err("n must be at most 4 (max_completions)".to_string())
+        );
+        assert!(completion_count(Some(0), 4).is_err());
+    }
+
+    #[test]
+    fn test_each_completion_gets_its_own_seed() {
+        let seeds: Vec<i32> = (0..3).map(|i| completion_seed(42, i)).collect();
+        assert_eq!(seeds, [42, 43, 44]);
+        assert_eq!(completion_seed(-1, 2), -1);
+        assert!(completion_seed(i32::MAX, 1) >= 0);
+    }
+}
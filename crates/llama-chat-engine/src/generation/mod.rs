@@ -1,8 +1,5 @@
 
-use llama_cpp_2::{
-    llama_batch::LlamaBatch,
-    model::AddBos,
-};
+use llama_cpp_2::llama_batch::LlamaBatch;
 #[cfg(feature = "vision")]
 use llama_cpp_2::{context::LlamaContext, token::LlamaToken};
 use std::num::NonZeroU32;
@@ -11,11 +8,13 @@ use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::mpsc;
 
-use llama_chat_config::load_config_for_conversation;
+use llama_chat_config::{load_config_with_overrides, resolve_generation_config};
 use super::model_manager::load_model;
 use llama_chat_types::*;
 use crate::SharedConversationLogger;
-use super::templates::{apply_system_prompt_by_type_with_tags, get_behavioral_system_prompt};
+use super::templates::{assemble_prompt, raw_completion_add_bos, resolve_template, resolved_system_prompt, templated_add_bos, validate_assistant_prefill, PromptTemplate};
+use super::prompt_echo::prompt_echo_event;
+use llama_chat_types::debug::debug_enabled;
 use super::jinja_templates::get_available_tools_openai_with_mcp;
 use super::sampler::create_sampler;
 use llama_chat_db::event_log::log_event;
@@ -23,15 +22,24 @@ use llama_chat_db::event_log::log_event;
 // Re-export submodule items used by sibling modules
 pub(crate) use super::context_eval::create_fresh_context;
 
-use super::context_eval::{evaluate_text_prompt, CONTEXT_SIZE};
+use super::context_eval::{apply_kv_cache_override, evaluate_text_prompt, resolve_context_size};
+use super::context_overflow::{
+    overflow_budget, prompt_fits, trained_context_warning,
+};
+use super::image_input::prepare_image_inputs;
 #[cfg(feature = "vision")]
 use super::context_eval::build_context_params;
 use super::prompt_builder::{resolve_tool_tags, snapshot_context_overhead};
 #[cfg(feature = "vision")]
 use super::prompt_builder::inject_media_markers;
-use super::token_loop::{TokenGenState, TokenGenConfig, VisionCtxRef, run_generation_loop};
-use super::stop_conditions::ExecBlockTracker;
+use super::token_loop::{TokenGenState, TokenGenConfig, VisionCtxRef, run_generation_loop, tool_dispatch_enabled};
+use super::tokenize_guard::validate_tokenizable;
+use super::stop_conditions::request_stop_sequences;
+use super::duplicate_calls::ExecutedCalls;
+mod alternatives;
 mod output;
+use alternatives::generate_alternatives;
+pub use alternatives::completion_count;
 use output::{build_generation_output, strip_incomplete_tool_call_on_cancel};
 
 pub use output::GenerationOutput;
@@ -49,12 +57,31 @@ pub async fn generate_llama_response(
     image_data: Option<&[String]>,
     mcp_manager: Option<Arc<dyn llama_chat_tools::McpManagerOps>>,
     agent_id: Option<&str>,
+    on_overflow: OverflowPolicy,
+    overrides: &GenerationOverrides,
 ) -> Result<GenerationOutput, String> {
     sys_debug!(
         "[GENERATION] generate_llama_response called, token_sender is {}",
         if token_sender.is_some() { "Some" } else { "None" }
     );
 
+    let assistant_prefill = overrides.assistant_prefill.as_deref().filter(|p| !p.is_empty());
+    if let Some(prefill) = assistant_prefill {
+        validate_assistant_prefill(prefill)?;
+    }
+    // raw_completion: the user text is the whole prompt — no template, system
+    // prompt, history or tools
+    let raw_completion = overrides.raw_completion.unwrap_or(false);
+    if raw_completion && assistant_prefill.is_some() {
+        return Err("assistant_prefill cannot be combined with raw_completion".to_string());
+    }
+    if let Some(name) = overrides.sampler_preset.as_deref() {
+        let configured = db.load_config().sampler_presets;
+        if sampler_preset(name, &configured).is_none() {
+            return Err(unknown_sampler_preset(name, &configured));
+        }
+    }
+
     let conversation_id = {
         let logger = conversation_logger
             .lock()
@@ -78,7 +105,13 @@ pub async fn generate_llama_response(
         logger.log_message_with_tokens("USER", user_message, Some(estimated_tokens));
     }
 
-    let config = load_config_for_conversation(&db, &conversation_id);
+    // Re-resolved once the model is known, with its recommended sampling
+    // below the preset, conversation and request layers
+    let mut config = load_config_with_overrides(&db, &conversation_id, overrides);
+    let completions = completion_count(overrides.n, config.max_completions)?;
+    if completions > 1 && image_data.is_some_and(|images| !images.is_empty()) {
+        return Err("n > 1 is not supported with image input".to_string());
+    }
     let stop_tokens = config
         .stop_tokens
         .clone()
@@ -96,31 +129,102 @@ pub async fn generate_llama_response(
             let current = state_guard.as_ref().and_then(|s| s.current_model_path.clone());
             drop(state_guard);
             model_path_owned = current
-                .ok_or_else(|| "No model loaded and no model configured for this conversation".to_string())?;
+                .ok_or_else(|| llama_chat_config::NO_MODEL_CONFIGURED.to_string())?;
             (&model_path_owned, false)
         }
     };
-    if need_load {
-        load_model(llama_state.clone(), model_path, None, None, None, None).await?;
-    }
+
+    // With every slot (max_parallel_generations) busy, wait for one to free up
+    let slots = super::context_pool::generation_slots();
+    let _slot = slots.acquire(&cancel, |position| {
+        log_info!(&conversation_id, "All {} generation slots busy; queued at position {}", slots.capacity(), position);
+        if let Some(ref sender) = token_sender {
+            let _ = sender.send(TokenData {
+                status: Some(format!("Waiting for a free generation slot (position {position})")),
+                ..Default::default()
+            });
+        }
+    })?;
+    let _model_in_use = if need_load {
+        let loaded = llama_state
+            .lock()
+            .map_err(|_| "Failed to lock LLaMA state")?
+            .as_ref()
+            .and_then(|s| s.current_model_path.clone());
+        if loaded.as_deref() == Some(model_path) && slots.in_use() > 1 {
+            // Already on the configured model; a context rebuild would only
+            // evict the contexts parked by the other running generations
+            super::context_pool::share_model()
+        } else {
+            // load_model waits for the other running generations to release the model
+            load_model(llama_state.clone(), model_path, None, None, None, None, None).await?;
+            super::context_pool::share_model()
+        }
+    } else {
+        super::context_pool::share_model()
+    };
 
     let mut state_guard = llama_state
         .lock()
         .map_err(|_| "Failed to lock LLaMA state")?;
     let state = state_guard.as_mut().ok_or("LLaMA state not initialized")?;
-    let model = state.model.as_ref().ok_or("No model loaded")?;
+    // SAFETY: `_model_in_use` is held until this function returns, and every
+    // path that drops or replaces the model or vision context (`load_model`,
+    // the worker's UnloadModel) first takes `exclusive_model()`, so these
+    // references outlive their use. The `LlamaState` holding the backend is
+    // created once and never replaced. That lets the state lock go before
+    // prompt eval, so parallel generations decode at the same time.
+    let model: &llama_cpp_2::model::LlamaModel =
+        unsafe { &*(state.model.as_ref().ok_or("No model loaded")? as *const _) };
+    let backend: &llama_cpp_2::llama_backend::LlamaBackend =
+        unsafe { &*(&state.backend as *const _) };
+    #[cfg(feature = "vision")]
+    let vision_state: Option<&VisionState> =
+        state.vision_state.as_ref().map(|v| unsafe { &*(v as *const VisionState) });
+    if state.recommended_sampling.is_some() {
+        config = resolve_generation_config(
+            &db,
+            &conversation_id,
+            state.recommended_sampling.as_ref(),
+            overrides,
+        );
+    }
+    apply_kv_cache_override(&mut config, state.kv_cache_type);
 
-    let context_size = config.context_size.unwrap_or_else(|| {
-        state
-            .model_context_length
-            .map(|ctx| ctx.min(CONTEXT_SIZE))
-            .unwrap_or(CONTEXT_SIZE)
-    });
+    let (context_size, capped_from) = resolve_context_size(
+        config.context_size,
+        state.model_context_length,
+        config.default_context_cap,
+    );
+    if let Some(advertised) = capped_from {
+        log_info!(
+            &conversation_id,
+            "Model advertises {} tokens of context; capped to {} (set context_size, or default_context_cap = 0, for more)",
+            advertised, context_size
+        );
+    }
+
+    // The KV cache has to fit the VRAM the offloaded layers left at load
+    let context_size = match (state.kv_cache_vram_budget, state.kv_elements_per_token) {
+        (Some(budget), Some(elements)) => {
+            let per_token = super::context_pool::kv_bytes_per_token(elements, &config.cache_type_k, &config.cache_type_v);
+            let (safe_ctx, reduced) = super::vram_calculator::safe_context_for_bytes(context_size, per_token, budget);
+            if reduced {
+                log_warn!(
+                    &conversation_id,
+                    "⚠️  Context {} ({}/{} KV cache) exceeds the VRAM left after layer offload, auto-reducing to {} tokens",
+                    context_size, config.cache_type_k, config.cache_type_v, safe_ctx
+                );
+            }
+            safe_ctx
+        }
+        _ => context_size,
+    };
 
     log_info!(
         &conversation_id,
         "Using context size: {} (model max: {:?}, default cap: {})",
-        context_size, state.model_context_length, CONTEXT_SIZE
+        context_size, state.model_context_length, config.default_context_cap
     );
 
     let mut sampler = create_sampler(&config, &conversation_id, Some(model));
@@ -137,33 +241,55 @@ pub async fn generate_llama_response(
     let cached_overhead = db.get_context_overhead_tokens(&conversation_id);
     let last_token_pos = db.get_last_generation_token_pos(&conversation_id);
 
-    let conversation_content = super::compaction::maybe_compact_conversation(
-        &raw_conversation_content,
-        context_size,
-        &conversation_id,
-        &db,
-        model,
-        &state.backend,
-        state.chat_template_string.as_deref(),
-        if cached_overhead > 0 { Some(cached_overhead) } else { None },
-        last_token_pos,
-        false,
-        token_sender.as_ref(),
-    );
+    // Only auto-reduce compacts; the other policies work on the raw conversation below
+    let conversation_content = if on_overflow == OverflowPolicy::AutoReduceContext && !raw_completion {
+        super::compaction::maybe_compact_conversation(
+            &raw_conversation_content,
+            context_size,
+            &conversation_id,
+            &db,
+            model,
+            backend,
+            state.chat_template_string.as_deref(),
+            if cached_overhead > 0 { Some(cached_overhead) } else { None },
+            last_token_pos,
+            false,
+            token_sender.as_ref(),
+        )
+    } else {
+        raw_conversation_content.clone()
+    };
 
     if conversation_content != raw_conversation_content {
         eprintln!("[COMPACTION] Conversation compacted — cache was dropped");
     }
 
-    let template_type = state.chat_template_type.clone();
-    let chat_template_string = state.chat_template_string.clone();
+    let (template_type, chat_template_string) = resolve_template(
+        state.chat_template_type.as_deref(),
+        state.chat_template_string.as_deref(),
+        state.template_override.as_deref(),
+    );
+    let template_type = template_type.map(str::to_string);
+    let chat_template_string = chat_template_string.map(str::to_string);
     let general_name = state.general_name.clone();
+    let add_bos = if raw_completion {
+        raw_completion_add_bos(overrides.add_bos)
+    } else {
+        templated_add_bos(
+            overrides.add_bos,
+            template_type.as_deref(),
+            chat_template_string.as_deref(),
+            state.model_add_bos,
+        )
+    };
 
     let stop_tokens = if template_type.as_deref() == Some("Harmony") {
         stop_tokens.into_iter().filter(|t| t != "<|end|>").collect()
     } else {
         stop_tokens
     };
+    // Per-request stops apply to this generation only; `stop_tokens` is left as configured
+    let request_stops = request_stop_sequences(overrides.stop.as_deref());
 
     let tags = resolve_tool_tags(&config, general_name.as_deref());
     #[allow(deprecated)]
@@ -199,25 +325,62 @@ pub async fn generate_llama_response(
         Some("__AGENTIC__") | None => None,
         Some(custom) => Some(custom),
     };
-    let prompt = apply_system_prompt_by_type_with_tags(
-        &conversation_content,
-        template_type.as_deref(),
-        chat_template_string.as_deref(),
-        &tags,
-        &bos_text,
-        &eos_text,
-        mcp_tools_ref,
+    // Raw completions are plain text; otherwise thinking is streamed and stored apart
+    let reasoning_tags = ReasoningTags::from_config(&config).filter(|_| !raw_completion);
+    let prompt_template = PromptTemplate {
+        template_type: template_type.as_deref(),
+        chat_template_string: chat_template_string.as_deref(),
+        tags: &tags,
+        bos_text: &bos_text,
+        eos_text: &eos_text,
+        mcp_tools: mcp_tools_ref,
+        enabled_tools: config.enabled_tools.as_deref(),
+        tools_enabled: config.tools_enabled,
         enable_thinking,
         custom_system_prompt,
+        assistant_prefill,
+        reasoning_tags: reasoning_tags.as_ref(),
+    };
+    let budget = overflow_budget(on_overflow, context_size, state.model_context_length);
+    let (prompt, overflow) = assemble_prompt(
+        raw_completion, user_message, &conversation_content, on_overflow, &prompt_template,
+        |prompt| Ok(prompt_fits(validate_tokenizable(model, prompt, add_bos)?.len(), budget)),
     )?;
+    if overflow.trimmed_messages > 0 {
+        log_event(&conversation_id, "context_overflow", &format!(
+            "Trimmed {} oldest message(s) from the prompt to fit context {} (on_overflow=trim_oldest)",
+            overflow.trimmed_messages, budget
+        ));
+    }
     log_info!(&conversation_id, "=== FINAL PROMPT BEING SENT TO MODEL ===");
     log_info!(&conversation_id, "{}", prompt);
     log_info!(&conversation_id, "=== END PROMPT (length: {} chars) ===", prompt.len());
 
-    let system_prompt_text = get_behavioral_system_prompt();
-    let tools_json = serde_json::to_string(
-        &get_available_tools_openai_with_mcp(mcp_tools_ref)
-    ).unwrap_or_default();
+    // The prompt `prompt_template` rendered with: reported by echo_prompt and counted as overhead
+    let system_prompt_text = resolved_system_prompt(
+        chat_template_string.is_some(), &tags, config.tools_enabled, custom_system_prompt,
+    );
+    let tools_json = if config.tools_enabled {
+        serde_json::to_string(
+            &get_available_tools_openai_with_mcp(mcp_tools_ref, config.enabled_tools.as_deref())
+        ).unwrap_or_default()
+    } else {
+        String::new()
+    };
+
+    // echo_prompt (debug only); a raw_completion has no system prompt to report
+    if let Some(sender) = token_sender.as_ref().filter(|_| !raw_completion) {
+        let echo = prompt_echo_event(
+            overrides.echo_prompt,
+            debug_enabled(),
+            system_prompt_text.clone(),
+            template_type.as_deref(),
+            &tools_json,
+        );
+        if let Some(echo) = echo {
+            let _ = sender.send(echo);
+        }
+    }
 
     let (system_prompt_token_count, tool_def_token_count) = snapshot_context_overhead(
         &db, &conversation_id, model, &system_prompt_text, &tools_json, &conversation_id,
@@ -244,7 +407,6 @@ pub async fn generate_llama_response(
     };
     let cache_type_k = config.cache_type_k.clone();
     let cache_type_v = config.cache_type_v.clone();
-    #[allow(unused_variables)]
     let n_batch = config.n_batch;
     if offload_kqv {
         log_info!(&conversation_id, "⚡ KV cache on GPU ({} layers offloaded)", state.gpu_layers.unwrap_or(0));
@@ -256,39 +418,63 @@ pub async fn generate_llama_response(
         log_info!(&conversation_id, "KV cache quantization: K={}, V={}", cache_type_k, cache_type_v);
     }
 
+    // Separate from the n_ctx checks: a prompt can fit the allocated context
+    // and still run past what the model was trained on
+    let trained_context = state.model_context_length;
+    let warn_if_beyond_trained_context = |n_tokens: usize| {
+        let Some(warning) = trained_context_warning(n_tokens, trained_context) else {
+            return;
+        };
+        log_warn!(&conversation_id, "{}", warning);
+        log_event(&conversation_id, "context_overflow", &warning);
+        // Streamed only: a persisted copy would be fed back into every later
+        // prompt, and this fires again on each over-budget turn
+        if let Some(ref sender) = token_sender {
+            let _ = sender.send(TokenData {
+                token: format!("*⚠️ {warning}*\n\n"),
+                tokens_used: 0,
+                max_tokens: 0,
+                ..Default::default()
+            });
+        }
+    };
+
+    // This generation's context: the one parked with its KV cache, else a fresh one
+    let mut slot_cache = super::context_pool::checkout_context(
+        state, &conversation_id, slots.capacity(), slots.in_use(),
+    );
+    // A fresh GPU context also has to fit next to the parked ones' KV caches
+    if slot_cache.is_none() && offload_kqv {
+        if let Some(elements) = state.kv_elements_per_token {
+            let needed = super::context_pool::kv_cache_bytes(elements, context_size, &cache_type_k, &cache_type_v);
+            super::context_pool::make_vram_room(state, needed, super::context_pool::free_vram_bytes());
+        }
+    }
+    drop(state_guard);
+
+    // Never decode more tokens per call than the context's n_batch allows
     const PROMPT_BATCH_CAP: usize = 2048;
-    let batch_cap = PROMPT_BATCH_CAP;
+    let batch_cap = (n_batch as usize).clamp(1, PROMPT_BATCH_CAP);
 
     if cancel.load(Ordering::Relaxed) {
         return Err("Cancelled".to_string());
     }
 
-    // Decode base64 image data if present
-    let image_bytes_vec: Vec<Vec<u8>> = if let Some(images) = image_data {
-        use base64::Engine;
-        images.iter().filter_map(|data_str| {
-            let b64 = if let Some(comma_pos) = data_str.find(',') {
-                &data_str[comma_pos + 1..]
-            } else {
-                data_str.as_str()
-            };
-            match base64::engine::general_purpose::STANDARD.decode(b64) {
-                Ok(bytes) => {
-                    log_info!(&conversation_id, "Decoded image: {} bytes", bytes.len());
-                    Some(bytes)
-                }
-                Err(e) => {
-                    log_warn!(&conversation_id, "Failed to decode image base64: {}", e);
-                    None
-                }
+    // Decode and validate image data up front; a bad image fails the request
+    // with a per-image error instead of being silently dropped
+    let image_bytes_vec: Vec<Vec<u8>> = match image_data {
+        Some(images) if !images.is_empty() => {
+            let decoded = prepare_image_inputs(images)?;
+            for image in &decoded {
+                log_info!(&conversation_id, "Decoded {:?} image: {}x{}, {} bytes", image.format, image.width, image.height, image.bytes.len());
             }
-        }).collect()
-    } else {
-        Vec::new()
+            decoded.into_iter().map(|image| image.bytes).collect()
+        }
+        _ => Vec::new(),
     };
 
     #[cfg(feature = "vision")]
-    let use_vision = !image_bytes_vec.is_empty() && state.vision_state.is_some();
+    let use_vision = !image_bytes_vec.is_empty() && vision_state.is_some();
     #[cfg(not(feature = "vision"))]
     let use_vision = false;
     if use_vision {
@@ -316,7 +502,7 @@ pub async fn generate_llama_response(
         {
         use llama_cpp_2::mtmd::{MtmdBitmap, MtmdInputText};
 
-        let vision = state.vision_state.as_ref().unwrap();
+        let vision = vision_state.unwrap();
 
         let vision_prompt = inject_media_markers(&prompt, user_message, image_bytes_vec.len());
         log_debug!(&conversation_id, "Vision prompt with {} markers, len={}", image_bytes_vec.len(), vision_prompt.len());
@@ -340,8 +526,9 @@ pub async fn generate_llama_response(
             .map_err(|e| format!("Vision tokenization failed: {e}"))?;
         let n_prompt_tokens = chunks.total_tokens();
         log_info!(&conversation_id, "Vision tokenized: {} chunks, {} total tokens ({} images)", chunks.len(), n_prompt_tokens, bitmaps.len());
+        warn_if_beyond_trained_context(n_prompt_tokens);
 
-        drop(state.inference_cache.take());
+        drop(slot_cache.take());
         let mut ctx_params = build_context_params(n_ctx, offload_kqv, &config);
         if vision.context.decode_use_non_causal() {
             ctx_params = ctx_params.with_flash_attention_policy(0);
@@ -350,7 +537,7 @@ pub async fn generate_llama_response(
         log_debug!(&conversation_id, "Creating vision context...");
         let ctx = unsafe {
             let real_ctx = model
-                .new_context_safe(&state.backend, ctx_params)
+                .new_context_safe(backend, ctx_params)
                 .map_err(|e| format!("Context creation failed: {e}"))?;
             std::mem::transmute::<LlamaContext<'_>, LlamaContext<'static>>(real_ctx)
         };
@@ -366,12 +553,10 @@ pub async fn generate_llama_response(
         #[cfg(not(feature = "vision"))]
         unreachable!("Vision feature not enabled")
     } else {
-        let tokens = model
-            .str_to_token(&prompt, AddBos::Never)
-            .map_err(|e| format!("Tokenization failed: {e}"))?;
+        let tokens = validate_tokenizable(model, &prompt, add_bos)?;
         log_debug!(&conversation_id, "Tokenized to {} tokens", tokens.len());
 
-        if tokens.len() as u32 > context_size.saturating_sub(context_size / 20) {
+        if !prompt_fits(tokens.len(), context_size) {
             log_event(&conversation_id, "context_overflow", &format!(
                 "Prompt {} tokens > 95% of context {} — conversation too large even after compaction",
                 tokens.len(), context_size
@@ -381,6 +566,7 @@ pub async fn generate_llama_response(
                 tokens.len(), context_size
             ));
         }
+        warn_if_beyond_trained_context(tokens.len());
 
         if let Ok(dump_dir) = std::env::var("LLAMA_CHAT_DATA_DIR") {
             let _ = std::fs::write(format!("{dump_dir}/logs/last_prompt_dump.txt"), &prompt);
@@ -389,22 +575,35 @@ pub async fn generate_llama_response(
             eprintln!("[GEN] Dump files reset ({} tokens, {} chars)", tokens.len(), prompt.len());
         }
 
+        // Long prompts take several chunks — let the UI show "reading context N%".
+        // tokens_used is the context filled so far, so usage meters don't drop to 0.
+        let mut report_progress = |done: usize, total: usize| {
+            if let Some(ref sender) = token_sender {
+                let _ = sender.send(TokenData {
+                    token: String::new(),
+                    tokens_used: done as i32,
+                    max_tokens: context_size as i32,
+                    prompt_eval_progress: Some(PromptEvalProgress { done, total }),
+                    ..Default::default()
+                });
+            }
+        };
         let (ctx, _skip_tokens) = match evaluate_text_prompt(
-            &mut state.inference_cache, model, &state.backend,
+            &mut slot_cache, model, backend,
             &tokens, &conversation_id, context_size,
             offload_kqv, flash_attention, &cache_type_k, &cache_type_v,
-            &config, batch_cap, Some(&cancel),
+            &config, batch_cap, Some(&cancel), &mut report_progress,
         ) {
             Ok(result) => result,
             Err(e) if e.contains("Context too small") => {
                 eprintln!("[GENERATION] Prompt decode failed, retrying in 2s...");
-                state.inference_cache = None;
+                slot_cache = None;
                 std::thread::sleep(std::time::Duration::from_secs(2));
                 evaluate_text_prompt(
-                    &mut state.inference_cache, model, &state.backend,
+                    &mut slot_cache, model, backend,
                     &tokens, &conversation_id, context_size,
                     offload_kqv, flash_attention, &cache_type_k, &cache_type_v,
-                    &config, batch_cap, Some(&cancel),
+                    &config, batch_cap, Some(&cancel), &mut report_progress,
                 )?
             },
             Err(e) => return Err(e),
@@ -420,12 +619,18 @@ pub async fn generate_llama_response(
         let mut logger = conversation_logger
             .lock()
             .map_err(|_| "Failed to lock conversation logger")?;
+        logger.set_reasoning_tags(reasoning_tags.clone());
+        logger.set_autosave_interval(std::time::Duration::from_millis(config.autosave_interval_ms as u64));
         logger.start_assistant_message();
     }
 
     let token_pos = tokens.len() as i32;
     let remaining_context = (context_size as i32) - token_pos - 128;
     let max_total_tokens = remaining_context.max(512);
+    let max_total_tokens = match overrides.max_tokens {
+        Some(cap) => max_total_tokens.min(cap.min(i32::MAX as u32) as i32),
+        None => max_total_tokens,
+    };
 
     log_event(&conversation_id, "gen_start", &format!(
         "ctx={}, prompt_tokens={}, remaining={}, flash_attn={}, kv_cache={}",
@@ -438,23 +643,26 @@ pub async fn generate_llama_response(
         "Context size: {context_size}, Prompt tokens: {token_pos}, Max tokens to generate: {max_total_tokens}"
     );
 
-    let mut gen = TokenGenState {
-        response: String::new(),
+    // The prefill is already in the prompt; seed the response so it's part of the reply
+    if let (Some(prefill), Some(sender)) = (assistant_prefill, token_sender.as_ref()) {
+        let _ = sender.send(TokenData {
+            token: prefill.to_string(),
+            tokens_used: token_pos,
+            max_tokens: context_size as i32,
+            ..Default::default()
+        });
+    }
+
+    let mut gen = TokenGenState::new(
+        assistant_prefill.unwrap_or_default().to_string(),
         token_pos,
-        total_tokens_generated: 0,
-        generated_token_ids: Vec::new(),
-        logger_synced_len: 0,
-        last_logger_sync: Instant::now(),
-        exec_tracker: ExecBlockTracker::new(),
-        recent_commands: Vec::new(),
-        consecutive_loop_blocks: 0,
-        last_exec_scan_pos: 0,
-        finish_reason: "stop".to_string(),
-        tool_response_tokens: 0,
-        loop_recoveries: 0,
-        eos_continue_count: 0,
-        tool_call_count: 0,
-    };
+        ExecutedCalls::new(config.dedupe_tool_calls),
+        // Templates that open the thinking block themselves leave the prompt ending in it
+        reasoning_tags.clone().map(|tags| {
+            let starts_in_reasoning = prompt.trim_end().ends_with(&tags.open);
+            ReasoningSplitter::new(tags, starts_in_reasoning)
+        }),
+    );
 
     // Snapshot the first ~300 chars of user message for the EOS continuation check.
     let user_message_snapshot: String = if user_message.len() > 300 {
@@ -470,6 +678,7 @@ pub async fn generate_llama_response(
         tags: &tags,
         template_type: template_type.as_deref(),
         stop_tokens: &stop_tokens,
+        request_stops: &request_stops,
         context_size,
         max_total_tokens,
         use_htmd: config.use_htmd,
@@ -477,15 +686,24 @@ pub async fn generate_llama_response(
         n_batch,
         mcp_manager: mcp_manager.clone(),
         db: db.clone(),
-        backend: &state.backend,
+        backend,
         chat_template_string: chat_template_string.as_deref(),
         proactive_compaction: config.proactive_compaction,
         safe_tool_injection: config.safe_tool_injection,
         user_message: &user_message_snapshot,
+        logprobs: overrides.logprobs,
+        stream_token_ids: overrides.stream_token_ids.unwrap_or(false),
+        auto_execute_tools: config.auto_execute_tools,
+        max_tool_iterations: config.max_tool_iterations,
+        tools_enabled: tool_dispatch_enabled(&config, raw_completion),
+        generation_timeout: config
+            .generation_timeout_secs
+            .filter(|&secs| secs > 0)
+            .map(|secs| std::time::Duration::from_secs(secs.into())),
     };
 
     #[cfg(feature = "vision")]
-    let vision_ctx_ref: VisionCtxRef<'_> = state.vision_state.as_ref().map(|v| &v.context);
+    let vision_ctx_ref: VisionCtxRef<'_> = vision_state.map(|v| &v.context);
     #[cfg(not(feature = "vision"))]
     let vision_ctx_ref: VisionCtxRef<'_> = ();
 
@@ -502,13 +720,43 @@ pub async fn generate_llama_response(
         vision_ctx_ref,
     );
 
+    // Timings of the first completion only; alternatives decode on the same context
+    let timings = context.timings();
+    // A compaction ends the turn on a prompt the conversation no longer has
+    let branch = completions > 1 && !gen.compacted;
+    if completions > 1 && gen.compacted {
+        log_info!(&conversation_id, "Skipping {} alternative completion(s) after a compaction", completions - 1);
+    }
+    let alternatives = match loop_result {
+        Ok(()) if branch => generate_alternatives(
+            completions, &cfg, &mut context, model, &mut batch,
+            &tokens, assistant_prefill.unwrap_or_default(), &config,
+            reasoning_tags.as_ref(), &token_sender, &cancel, vision_ctx_ref,
+        ),
+        Ok(()) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    };
+
     unsafe { context.set_abort_callback(None, std::ptr::null_mut()); }
 
-    loop_result?;
+    let alternatives = alternatives?;
+
+    // Text held back as a possible partial reasoning tag
+    if let (Some(splitter), Some(sender)) = (gen.reasoning.as_mut(), token_sender.as_ref()) {
+        let (token, reasoning) = splitter.finish();
+        if !token.is_empty() || !reasoning.is_empty() {
+            let _ = sender.send(TokenData {
+                token,
+                tokens_used: gen.token_pos,
+                max_tokens: context_size as i32,
+                reasoning: Some(reasoning).filter(|r| !r.is_empty()),
+                ..Default::default()
+            });
+        }
+    }
 
     let token_pos = gen.token_pos;
 
-    let timings = context.timings();
     let gen_eval_ms = timings.t_eval_ms();
     let prompt_eval_ms_internal = timings.t_p_eval_ms();
     let n_eval = timings.n_eval() as usize;
@@ -554,6 +802,9 @@ pub async fn generate_llama_response(
             logger.log_token_bulk(remaining);
         }
         logger.set_token_counts(token_pos, context_size as i32);
+        if n_eval > 0 {
+            logger.set_message_token_count(n_eval as i32);
+        }
         logger.finish_assistant_message();
         if was_cancelled {
             logger.log_message("system", "[Generation stopped by user]");
@@ -569,18 +820,35 @@ pub async fn generate_llama_response(
         );
     }
 
-    let output = build_generation_output(
-        &gen, token_pos, context_size,
-        prompt_tok_per_sec, gen_tok_per_sec,
-        gen_eval_ms, n_eval, prompt_eval_ms_internal, n_p_eval,
-        prompt_tokens, system_prompt_token_count, tool_def_token_count,
-    );
+    // Executed calls are reported too, so clients never re-parse the response
+    if gen.tool_calls.is_empty() {
+        gen.tool_calls = crate::tool_return::extract_tool_calls(&gen.response, &tags);
+    }
 
+    let output = GenerationOutput {
+        overflow,
+        alternatives,
+        ..build_generation_output(
+            &gen, token_pos, context_size,
+            prompt_tok_per_sec, gen_tok_per_sec,
+            gen_eval_ms, n_eval, prompt_eval_ms_internal, n_p_eval,
+            prompt_tokens, system_prompt_token_count, tool_def_token_count,
+        )
+    };
+
+    // After alternatives only the prompt is left in the KV cache
+    if branch {
+        gen.generated_token_ids.clear();
+    }
     let total_cached = tokens.len() + gen.generated_token_ids.len();
     let gen_count = gen.generated_token_ids.len();
     let mut all_evaluated = tokens;
     all_evaluated.extend(gen.generated_token_ids);
-    state.inference_cache = Some(InferenceCache {
+    let mut state_guard = llama_state
+        .lock()
+        .map_err(|_| "Failed to lock LLaMA state")?;
+    let state = state_guard.as_mut().ok_or("LLaMA state not initialized")?;
+    super::context_pool::checkin_context(state, InferenceCache {
         context,
         conversation_id: conversation_id.clone(),
         evaluated_tokens: all_evaluated,
@@ -589,7 +857,8 @@ pub async fn generate_llama_response(
         flash_attention,
         cache_type_k,
         cache_type_v,
-    });
+    }, slots.capacity());
+    drop(state_guard);
     log_info!(
         &conversation_id,
         "Stored KV cache: {} total tokens ({} generated this turn)",
@@ -605,3 +874,60 @@ pub async fn generate_llama_response(
 
     Ok(output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_streamed_tokens_carry_ids_when_requested() {
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let state: SharedLlamaState = Arc::new(std::sync::Mutex::new(None));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(load_model(state.clone(), &model_path, Some(0), None, None, None, None))
+            .unwrap();
+
+        let db: llama_chat_db::SharedDatabase =
+            Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let generate = |stream_token_ids: bool| {
+            let logger = llama_chat_db::conversation::ConversationLogger::new(db.clone(), None).unwrap();
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let overrides = GenerationOverrides {
+                max_tokens: Some(8),
+                raw_completion: Some(true),
+                stream_token_ids: Some(stream_token_ids),
+                ..Default::default()
+            };
+            rt.block_on(generate_llama_response(
+                "The capital of France is",
+                state.clone(),
+                Arc::new(std::sync::Mutex::new(logger)),
+                Some(tx),
+                false,
+                db.clone(),
+                Arc::new(AtomicBool::new(false)),
+                None,
+                None,
+                None,
+                OverflowPolicy::default(),
+                &overrides,
+            ))
+            .unwrap();
+            let mut tokens = Vec::new();
+            while let Ok(token) = rx.try_recv() {
+                if !token.token.is_empty() {
+                    tokens.push(token);
+                }
+            }
+            tokens
+        };
+
+        let with_ids = generate(true);
+        assert!(!with_ids.is_empty());
+        assert!(with_ids.iter().all(|t| t.token_id.is_some()), "{with_ids:?}");
+
+        assert!(generate(false).iter().all(|t| t.token_id.is_none()));
+    }
+}
@@ -36,6 +36,17 @@ pub struct GenerationOutput {
     pub prompt_eval_ms: Option<f64>,
     pub prompt_tokens: Option<i32>,
     pub token_breakdown: Option<llama_chat_types::TokenBreakdown>,
+    /// Overflow policy applied and how many messages it trimmed from the prompt.
+    pub overflow: llama_chat_types::OverflowReport,
+    /// Per-token logprobs (empty unless the request asked for them).
+    pub logprobs: Vec<llama_chat_types::TokenLogprob>,
+    /// Unexecuted tool calls when `auto_execute_tools` is off (finish_reason "tool_calls"),
+    /// otherwise every call found in the final response.
+    pub tool_calls: Vec<llama_chat_types::ToolCallRequest>,
+    /// The request `stop` sequence that ended generation, if one did.
+    pub stop_sequence: Option<String>,
+    /// Completions 2..=n of an `n` > 1 request.
+    pub alternatives: Vec<llama_chat_types::AlternativeCompletion>,
 }
 
 pub(super) fn strip_incomplete_tool_call_on_cancel(gen: &mut TokenGenState) {
@@ -54,7 +65,7 @@ pub(super) fn strip_incomplete_tool_call_on_cancel(gen: &mut TokenGenState) {
         if is_unclosed || is_fn_unclosed {
             eprintln!("[CANCEL] Stripping incomplete tool call at pos {open_pos}");
             gen.response.truncate(open_pos);
-            gen.logger_synced_len = gen.logger_synced_len.min(open_pos);
+            gen.clamp_offsets();
         }
     }
 }
@@ -79,6 +90,7 @@ pub(super) fn build_generation_output(
         tokens_used: token_pos,
         max_tokens: context_size as i32,
         finish_reason: gen.finish_reason.clone(),
+        stop_sequence: gen.stop_sequence.clone(),
         prompt_tok_per_sec,
         gen_tok_per_sec,
         gen_eval_ms: if gen_eval_ms > 0.0 {
@@ -107,5 +119,9 @@ pub(super) fn build_generation_output(
             tool_calls_and_results: gen.tool_response_tokens,
             model_response: n_eval as i32,
         }),
+        overflow: Default::default(),
+        logprobs: gen.logprobs.clone(),
+        tool_calls: gen.tool_calls.clone(),
+        alternatives: Vec::new(),
     }
 }
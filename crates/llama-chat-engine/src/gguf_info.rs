@@ -159,10 +159,10 @@ pub fn extract_model_info(decoded_path: &str) -> Result<serde_json::Value, Strin
 
                 // Block count (also used for layer estimation)
                 if let Some(val) = extractor.get_arch_field(&arch, "block_count") {
-                    model_info["block_count"] = serde_json::json!(val.clone());
-                    if let Ok(count) = val.parse::<u32>() {
-                        model_info["estimated_layers"] = serde_json::json!(count);
-                    }
+                    model_info["block_count"] = serde_json::json!(val);
+                }
+                if let Some(count) = extractor.get_u32(&format!("{arch}.block_count")) {
+                    model_info["estimated_layers"] = serde_json::json!(count);
                 }
 
                 // Tokenizer
@@ -0,0 +1,91 @@
+// Test-only helpers for writing small synthetic GGUF files (header, metadata
+// and optional tensor infos; no tensor data).
+//
+// Layout follows GGUF v3: magic, version, tensor count, kv count, then each
+// key as a length-prefixed string followed by a type tag and the value, then
+// each tensor's name, dims, ggml type and data offset.
+
+use std::io::Write;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+const GGUF_VERSION: u32 = 3;
+
+const TYPE_UINT32: u32 = 4;
+const TYPE_STRING: u32 = 8;
+const TYPE_UINT64: u32 = 10;
+
+/// A metadata value to write into a synthetic GGUF file.
+pub(crate) enum TestValue<'a> {
+    U32(u32),
+    U64(u64),
+    Str(&'a str),
+}
+
+/// A tensor info entry to write into a synthetic GGUF file.
+pub(crate) struct TestTensor<'a> {
+    pub name: &'a str,
+    pub dims: &'a [u64],
+    pub ggml_type: u32,
+    pub offset: u64,
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u64).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Encode a GGUF file containing only the given metadata.
+pub(crate) fn gguf_bytes(metadata: &[(&str, TestValue<'_>)]) -> Vec<u8> {
+    gguf_bytes_with_tensors(metadata, &[])
+}
+
+/// Encode a GGUF file with metadata and tensor infos, ending where the
+/// (unpadded) tensor data section would begin.
+pub(crate) fn gguf_bytes_with_tensors(
+    metadata: &[(&str, TestValue<'_>)],
+    tensors: &[TestTensor<'_>],
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(GGUF_MAGIC);
+    out.extend_from_slice(&GGUF_VERSION.to_le_bytes());
+    out.extend_from_slice(&(tensors.len() as u64).to_le_bytes());
+    out.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+
+    for (key, value) in metadata {
+        write_string(&mut out, key);
+        match value {
+            TestValue::U32(n) => {
+                out.extend_from_slice(&TYPE_UINT32.to_le_bytes());
+                out.extend_from_slice(&n.to_le_bytes());
+            }
+            TestValue::U64(n) => {
+                out.extend_from_slice(&TYPE_UINT64.to_le_bytes());
+                out.extend_from_slice(&n.to_le_bytes());
+            }
+            TestValue::Str(s) => {
+                out.extend_from_slice(&TYPE_STRING.to_le_bytes());
+                write_string(&mut out, s);
+            }
+        }
+    }
+
+    for tensor in tensors {
+        write_string(&mut out, tensor.name);
+        out.extend_from_slice(&(tensor.dims.len() as u32).to_le_bytes());
+        for dim in tensor.dims {
+            out.extend_from_slice(&dim.to_le_bytes());
+        }
+        out.extend_from_slice(&tensor.ggml_type.to_le_bytes());
+        out.extend_from_slice(&tensor.offset.to_le_bytes());
+    }
+    out
+}
+
+/// Write a synthetic GGUF file to a unique temp path and return the path.
+pub(crate) fn write_test_gguf(name: &str, metadata: &[(&str, TestValue<'_>)]) -> String {
+    let path = std::env::temp_dir().join(format!("{name}-{}.gguf", std::process::id()));
+    let mut file = std::fs::File::create(&path).expect("create test gguf");
+    file.write_all(&gguf_bytes(metadata))
+        .expect("write test gguf");
+    path.to_string_lossy().to_string()
+}
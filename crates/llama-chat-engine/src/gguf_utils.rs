@@ -6,7 +6,20 @@
 use gguf_llms::{GgufHeader, GgufReader, Value};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Read, Seek};
+use std::time::Duration;
+
+/// Tensor data alignment when `general.alignment` is absent (the GGUF default).
+pub const GGUF_DEFAULT_ALIGNMENT: u64 = 32;
+/// ggml tensors have at most 4 dimensions; anything larger is a corrupt file.
+const GGUF_MAX_TENSOR_DIMS: u32 = 4;
+/// Sanity cap on tensor name length (ggml itself allows 64 bytes).
+const GGUF_MAX_TENSOR_NAME_LEN: u64 = 4096;
+
+/// First ranged GET for remote metadata; later requests double in size.
+const REMOTE_INITIAL_CHUNK: u64 = 256 * 1024;
+/// Give up if the metadata section is larger than this (large vocabularies are ~10 MB).
+const REMOTE_MAX_METADATA_BYTES: u64 = 256 * 1024 * 1024;
 
 /// Basic model metadata extracted from GGUF file
 /// TODO: Use for caching model metadata to avoid repeated GGUF file reads
@@ -19,6 +32,12 @@ pub struct GgufBasicMetadata {
     pub context_length: String,
 }
 
+/// Metadata value types the reader decodes, in GGUF type-id order.
+pub const GGUF_VALUE_TYPES: &[&str] = &[
+    "uint8", "int8", "uint16", "int16", "uint32", "int32", "float32", "bool",
+    "string", "array", "uint64", "int64", "float64",
+];
+
 /// Convert a GGUF Value to an Option<String>
 pub fn value_to_string(value: &Value) -> Option<String> {
     match value {
@@ -38,6 +57,36 @@ pub fn value_to_string(value: &Value) -> Option<String> {
     }
 }
 
+/// Read an integer GGUF Value (or a numeric string) as u64
+pub fn value_to_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Uint8(n) => Some(u64::from(*n)),
+        Value::Uint16(n) => Some(u64::from(*n)),
+        Value::Uint32(n) => Some(u64::from(*n)),
+        Value::Uint64(n) => Some(*n),
+        Value::Int8(n) => u64::try_from(*n).ok(),
+        Value::Int16(n) => u64::try_from(*n).ok(),
+        Value::Int32(n) => u64::try_from(*n).ok(),
+        Value::Int64(n) => u64::try_from(*n).ok(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Read a numeric GGUF Value (or a numeric string) as f32
+pub fn value_to_f32(value: &Value) -> Option<f32> {
+    match value {
+        Value::Float32(f) => Some(*f),
+        Value::Float64(f) => Some(*f as f32),
+        Value::Int8(n) => Some(f32::from(*n)),
+        Value::Int16(n) => Some(f32::from(*n)),
+        Value::Int32(n) => Some(*n as f32),
+        Value::Int64(n) => Some(*n as f32),
+        Value::String(s) => s.trim().parse().ok(),
+        other => value_to_u64(other).map(|n| n as f32),
+    }
+}
+
 /// Convert a GGUF Value to a serde_json::Value
 pub fn value_to_json(value: &Value) -> serde_json::Value {
     match value {
@@ -102,18 +151,252 @@ pub fn format_parameter_count(param_str: &str) -> String {
 #[allow(dead_code)]
 pub fn read_gguf_metadata_raw(file_path: &str) -> Result<HashMap<String, Value>, String> {
     let file = File::open(file_path).map_err(|e| format!("Failed to open file: {e}"))?;
+    parse_gguf_metadata(&mut BufReader::new(file))
+}
 
-    let mut reader = BufReader::new(file);
-
-    let header = GgufHeader::parse(&mut reader)
-        .map_err(|e| format!("Failed to parse GGUF header: {e}"))?;
+/// Parse the GGUF header and metadata section from the start of `reader`.
+fn parse_gguf_metadata<R: Read>(
+    reader: &mut BufReader<R>,
+) -> Result<HashMap<String, Value>, String> {
+    let header =
+        GgufHeader::parse(reader).map_err(|e| format!("Failed to parse GGUF header: {e}"))?;
 
-    let metadata = GgufReader::read_metadata(&mut reader, header.n_kv)
+    let metadata = GgufReader::read_metadata(reader, header.n_kv)
         .map_err(|e| format!("Failed to read GGUF metadata: {e}"))?;
 
     Ok(metadata)
 }
 
+/// One entry of the GGUF tensor info section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgufTensorInfo {
+    pub name: String,
+    pub dims: Vec<u64>,
+    /// ggml type id (0 = F32, 1 = F16, ...).
+    pub ggml_type: u32,
+    /// Offset relative to `GgufLayout::tensor_data_start`.
+    pub offset: u64,
+}
+
+/// Where the tensor data of a GGUF file lives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GgufLayout {
+    /// `general.alignment`, or [`GGUF_DEFAULT_ALIGNMENT`].
+    pub alignment: u64,
+    /// Absolute file offset of the tensor data: the end of the tensor infos,
+    /// padded up to `alignment`.
+    pub tensor_data_start: u64,
+    pub tensors: Vec<GgufTensorInfo>,
+}
+
+impl GgufLayout {
+    /// Size in bytes of each tensor, measured to the next tensor's offset (the
+    /// last one runs to the end of the file), so it includes alignment padding.
+    pub fn tensor_sizes(&self, file_size: u64) -> Vec<(&str, u64)> {
+        let mut offsets: Vec<u64> = self.tensors.iter().map(|t| t.offset).collect();
+        offsets.sort_unstable();
+        let data_len = file_size.saturating_sub(self.tensor_data_start);
+        self.tensors
+            .iter()
+            .map(|t| {
+                let end = offsets
+                    .iter()
+                    .copied()
+                    .find(|&o| o > t.offset)
+                    .unwrap_or(data_len);
+                (t.name.as_str(), end.saturating_sub(t.offset))
+            })
+            .collect()
+    }
+
+    /// Total size of the repeating `blk.N.*` tensors, the part of the weights
+    /// that is split across GPU layers.
+    pub fn block_bytes(&self, file_size: u64) -> u64 {
+        self.tensor_sizes(file_size)
+            .into_iter()
+            .filter(|(name, _)| name.starts_with("blk."))
+            .map(|(_, size)| size)
+            .sum()
+    }
+}
+
+/// `general.alignment` from the metadata; the default when absent or not a power of two.
+pub fn gguf_alignment(metadata: &HashMap<String, Value>) -> u64 {
+    metadata
+        .get("general.alignment")
+        .and_then(value_to_u64)
+        .filter(|a| a.is_power_of_two())
+        .unwrap_or(GGUF_DEFAULT_ALIGNMENT)
+}
+
+/// Round `offset` up to the next multiple of `alignment`.
+pub fn align_offset(offset: u64, alignment: u64) -> u64 {
+    offset.div_ceil(alignment) * alignment
+}
+
+/// Read the tensor layout (alignment, tensor infos, data start) of a GGUF file.
+pub fn read_gguf_layout(file_path: &str) -> Result<GgufLayout, String> {
+    let file = File::open(file_path).map_err(|e| format!("Failed to open file: {e}"))?;
+    parse_gguf_layout(&mut BufReader::new(file))
+}
+
+/// Parse header, metadata and tensor infos, tracking where the tensor data starts.
+fn parse_gguf_layout<R: Read + Seek>(reader: &mut BufReader<R>) -> Result<GgufLayout, String> {
+    let header =
+        GgufHeader::parse(reader).map_err(|e| format!("Failed to parse GGUF header: {e}"))?;
+    let metadata = GgufReader::read_metadata(reader, header.n_kv)
+        .map_err(|e| format!("Failed to read GGUF metadata: {e}"))?;
+    let alignment = gguf_alignment(&metadata);
+
+    let tensors = (0..header.n_tensors)
+        .map(|_| read_tensor_info(reader))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|e| format!("Failed to read GGUF tensor info: {e}"))?;
+    let infos_end = reader
+        .stream_position()
+        .map_err(|e| format!("Failed to read GGUF tensor info: {e}"))?;
+
+    Ok(GgufLayout {
+        alignment,
+        tensor_data_start: align_offset(infos_end, alignment),
+        tensors,
+    })
+}
+
+fn read_tensor_info(reader: &mut impl Read) -> std::io::Result<GgufTensorInfo> {
+    let name_len = read_u64(reader)?;
+    if name_len > GGUF_MAX_TENSOR_NAME_LEN {
+        return Err(std::io::Error::other(format!(
+            "tensor name is {name_len} bytes"
+        )));
+    }
+    let mut name = vec![0u8; name_len as usize];
+    reader.read_exact(&mut name)?;
+    let n_dims = read_u32(reader)?;
+    if n_dims > GGUF_MAX_TENSOR_DIMS {
+        return Err(std::io::Error::other(format!(
+            "tensor has {n_dims} dimensions"
+        )));
+    }
+    let dims = (0..n_dims)
+        .map(|_| read_u64(reader))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    Ok(GgufTensorInfo {
+        name: String::from_utf8_lossy(&name).into_owned(),
+        dims,
+        ggml_type: read_u32(reader)?,
+        offset: read_u64(reader)?,
+    })
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Read GGUF metadata from a remote file without downloading it.
+///
+/// Uses HTTP range requests, fetching only as many bytes as the header and
+/// metadata need. If the server ignores `Range`, the body is streamed and the
+/// connection dropped once the metadata has been read.
+pub fn read_gguf_metadata_from_url(url: &str) -> Result<HashMap<String, Value>, String> {
+    let agent = ureq::AgentBuilder::new()
+        .redirects(10)
+        .timeout_connect(Duration::from_secs(10))
+        .timeout_read(Duration::from_secs(30))
+        .build();
+
+    let resp = agent
+        .get(url)
+        .set("Range", &format!("bytes=0-{}", REMOTE_INITIAL_CHUNK - 1))
+        .call()
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+
+    if resp.status() != 206 {
+        return parse_gguf_metadata(&mut BufReader::new(resp.into_reader()));
+    }
+    let mut reader = HttpRangeReader {
+        agent,
+        url: url.to_string(),
+        buf: Vec::new(),
+        pos: 0,
+        next_chunk: REMOTE_INITIAL_CHUNK * 2,
+        eof: false,
+    };
+    reader.append_body(resp, REMOTE_INITIAL_CHUNK)?;
+    parse_gguf_metadata(&mut BufReader::new(reader))
+}
+
+/// `Read` over a remote file that fetches the next byte range on demand.
+struct HttpRangeReader {
+    agent: ureq::Agent,
+    url: String,
+    /// Bytes fetched so far, always a prefix of the file
+    buf: Vec<u8>,
+    pos: usize,
+    next_chunk: u64,
+    eof: bool,
+}
+
+impl HttpRangeReader {
+    fn append_body(&mut self, resp: ureq::Response, requested: u64) -> std::io::Result<()> {
+        let read = resp
+            .into_reader()
+            .take(requested)
+            .read_to_end(&mut self.buf)?;
+        // A short range means we reached the end of the file
+        self.eof = (read as u64) < requested;
+        Ok(())
+    }
+
+    fn fetch_more(&mut self) -> std::io::Result<()> {
+        let start = self.buf.len() as u64;
+        if start >= REMOTE_MAX_METADATA_BYTES {
+            return Err(std::io::Error::other(format!(
+                "GGUF metadata exceeds {} MB",
+                REMOTE_MAX_METADATA_BYTES / (1024 * 1024)
+            )));
+        }
+        let requested = self.next_chunk.min(REMOTE_MAX_METADATA_BYTES - start);
+        self.next_chunk = self.next_chunk.saturating_mul(2);
+
+        let range = format!("bytes={start}-{}", start + requested - 1);
+        match self.agent.get(&self.url).set("Range", &range).call() {
+            Ok(resp) if resp.status() == 206 => self.append_body(resp, requested),
+            Ok(resp) => Err(std::io::Error::other(format!(
+                "server stopped honouring range requests (HTTP {})",
+                resp.status()
+            ))),
+            // Range starts past the end of the file
+            Err(ureq::Error::Status(416, _)) => {
+                self.eof = true;
+                Ok(())
+            }
+            Err(e) => Err(std::io::Error::other(e.to_string())),
+        }
+    }
+}
+
+impl Read for HttpRangeReader {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.buf.len() && !self.eof {
+            self.fetch_more()?;
+        }
+        let available = &self.buf[self.pos..];
+        let n = available.len().min(out.len());
+        out[..n].copy_from_slice(&available[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
 /// Read basic metadata from GGUF file (architecture, parameters, quantization, context_length).
 /// This is a convenience function for simple use cases.
 /// TODO: Use for quick model info display without full metadata extraction
@@ -183,6 +466,9 @@ pub fn detect_tool_format(architecture: &str, model_name: &str) -> &'static str
         "llama3"
     } else if arch_lower.contains("qwen") || name_lower.contains("qwen") {
         "qwen"
+    } else if arch_lower.contains("granite") || name_lower.contains("granite") {
+        // Granite emits `name{json}`
+        "granite"
     } else if arch_lower.contains("llama") {
         // Older llama models don't support tools
         "unknown"
@@ -281,6 +567,21 @@ impl<'a> MetadataExtractor<'a> {
         self.metadata.get(key).and_then(value_to_string)
     }
 
+    /// Get an integer metadata value; None if missing, negative or out of range
+    pub fn get_u32(&self, key: &str) -> Option<u32> {
+        self.get_u64(key).and_then(|n| u32::try_from(n).ok())
+    }
+
+    /// Get an integer metadata value as u64
+    pub fn get_u64(&self, key: &str) -> Option<u64> {
+        self.metadata.get(key).and_then(value_to_u64)
+    }
+
+    /// Get a numeric metadata value as f32
+    pub fn get_f32(&self, key: &str) -> Option<f32> {
+        self.metadata.get(key).and_then(value_to_f32)
+    }
+
     /// Get a metadata value as serde_json::Value
     pub fn get_json(&self, key: &str) -> Option<serde_json::Value> {
         self.metadata.get(key).map(value_to_json)
@@ -304,6 +605,127 @@ impl<'a> MetadataExtractor<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gguf_test_support::{gguf_bytes, gguf_bytes_with_tensors, TestTensor, TestValue};
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Serve `file` over HTTP on localhost. Returns the URL and a counter of body
+    /// bytes sent; `ranges` controls whether `Range` headers are honoured.
+    fn serve_file(file: Vec<u8>, ranges: bool) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/model.gguf", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut request = Vec::new();
+                let mut byte = [0u8; 1];
+                while !request.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) == 1 {
+                    request.push(byte[0]);
+                }
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let range = request
+                    .lines()
+                    .find_map(|l| l.strip_prefix("range: bytes="))
+                    .and_then(|r| r.trim().split_once('-'))
+                    .and_then(|(a, b)| Some((a.parse::<usize>().ok()?, b.parse::<usize>().ok()?)));
+
+                let (head, body) = match range {
+                    Some((start, _)) if ranges && start >= file.len() => (
+                        "HTTP/1.1 416 Range Not Satisfiable\r\n".to_string(),
+                        &file[..0],
+                    ),
+                    Some((start, end)) if ranges => {
+                        let end = end.min(file.len() - 1);
+                        (
+                            format!(
+                                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{end}/{}\r\n",
+                                file.len()
+                            ),
+                            &file[start..=end],
+                        )
+                    }
+                    _ => ("HTTP/1.1 200 OK\r\n".to_string(), &file[..]),
+                };
+                let _ = write!(
+                    stream,
+                    "{head}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                // The client hangs up once it has the metadata; count what got through
+                for chunk in body.chunks(16 * 1024) {
+                    if stream.write_all(chunk).is_err() {
+                        break;
+                    }
+                    counter.fetch_add(chunk.len(), Ordering::SeqCst);
+                }
+            }
+        });
+        (url, served)
+    }
+
+    /// Metadata larger than the first range request, followed by fake tensor data.
+    fn remote_test_gguf() -> (Vec<u8>, usize) {
+        let template = "{{ message }}".repeat(40_000);
+        let mut file = gguf_bytes(&[
+            ("general.architecture", TestValue::Str("llama")),
+            ("general.name", TestValue::Str("Remote Test")),
+            ("llama.block_count", TestValue::U32(32)),
+            ("llama.context_length", TestValue::U64(8192)),
+            ("tokenizer.chat_template", TestValue::Str(&template)),
+        ]);
+        let metadata_len = file.len();
+        file.extend(std::iter::repeat(0xAB).take(8 * 1024 * 1024));
+        (file, metadata_len)
+    }
+
+    fn assert_remote_metadata(metadata: &HashMap<String, Value>) {
+        let extractor = MetadataExtractor::new(metadata);
+        assert_eq!(
+            extractor.get_string("general.name").as_deref(),
+            Some("Remote Test")
+        );
+        assert_eq!(extractor.get_u32("llama.block_count"), Some(32));
+        assert_eq!(extractor.get_u64("llama.context_length"), Some(8192));
+        assert_eq!(
+            extractor
+                .get_string("tokenizer.chat_template")
+                .map(|t| t.len()),
+            Some(13 * 40_000)
+        );
+    }
+
+    #[test]
+    fn test_read_metadata_from_url_with_ranges() {
+        let (file, metadata_len) = remote_test_gguf();
+        assert!(metadata_len as u64 > REMOTE_INITIAL_CHUNK);
+        let file_len = file.len();
+        let (url, served) = serve_file(file, true);
+
+        let metadata = read_gguf_metadata_from_url(&url).unwrap();
+        assert_remote_metadata(&metadata);
+        // Grew past the first chunk but never touched the tensor data
+        let served = served.load(Ordering::SeqCst);
+        assert!(served >= metadata_len, "served {served}");
+        assert!(served < 2 * metadata_len, "served {served} of {file_len}");
+    }
+
+    #[test]
+    fn test_read_metadata_from_url_without_range_support() {
+        let (file, _) = remote_test_gguf();
+        let (url, _) = serve_file(file, false);
+        assert_remote_metadata(&read_gguf_metadata_from_url(&url).unwrap());
+    }
+
+    #[test]
+    fn test_read_metadata_from_url_truncated_file() {
+        let (file, metadata_len) = remote_test_gguf();
+        let (url, _) = serve_file(file[..metadata_len / 2].to_vec(), true);
+        assert!(read_gguf_metadata_from_url(&url).is_err());
+    }
 
     #[test]
     fn test_format_parameter_count_billions() {
@@ -360,6 +782,10 @@ mod tests {
         assert_eq!(detect_tool_format("llama", "llama-3-8b"), "llama3");
         assert_eq!(detect_tool_format("qwen2", "qwen2-7b"), "qwen");
         assert_eq!(detect_tool_format("llama", "llama-2-7b"), "unknown");
+        assert_eq!(
+            detect_tool_format("granitehybrid", "Granite 4.0 H Tiny"),
+            "granite"
+        );
     }
 
     #[test]
@@ -374,4 +800,131 @@ mod tests {
         let template_no_prompt = "no system prompt here";
         assert_eq!(extract_default_system_prompt(template_no_prompt), None);
     }
+
+    fn mixed_metadata() -> HashMap<String, Value> {
+        HashMap::from([
+            ("llama.block_count".to_string(), Value::Uint32(32)),
+            ("llama.context_length".to_string(), Value::Uint64(131_072)),
+            ("llama.attention.head_count".to_string(), Value::Int32(32)),
+            (
+                "llama.attention.head_count_kv".to_string(),
+                Value::String("8".to_string()),
+            ),
+            (
+                "llama.rope.freq_base".to_string(),
+                Value::Float32(500_000.0),
+            ),
+            (
+                "llama.attention.layer_norm_rms_epsilon".to_string(),
+                Value::String("1e-5".to_string()),
+            ),
+            (
+                "general.name".to_string(),
+                Value::String("Llama 3".to_string()),
+            ),
+            ("bad.negative".to_string(), Value::Int32(-1)),
+            ("bad.huge".to_string(), Value::Uint64(u64::MAX)),
+        ])
+    }
+
+    #[test]
+    fn test_metadata_extractor_integers() {
+        let metadata = mixed_metadata();
+        let extractor = MetadataExtractor::new(&metadata);
+        assert_eq!(extractor.get_u32("llama.block_count"), Some(32));
+        assert_eq!(extractor.get_u32("llama.attention.head_count"), Some(32));
+        assert_eq!(extractor.get_u32("llama.attention.head_count_kv"), Some(8));
+        assert_eq!(extractor.get_u64("llama.context_length"), Some(131_072));
+        assert_eq!(extractor.get_u64("bad.huge"), Some(u64::MAX));
+        assert_eq!(extractor.get_u32("bad.huge"), None);
+        assert_eq!(extractor.get_u32("bad.negative"), None);
+        assert_eq!(extractor.get_u32("general.name"), None);
+        assert_eq!(extractor.get_u32("llama.rope.freq_base"), None);
+        assert_eq!(extractor.get_u32("missing"), None);
+    }
+
+    #[test]
+    fn test_metadata_extractor_floats() {
+        let metadata = mixed_metadata();
+        let extractor = MetadataExtractor::new(&metadata);
+        assert_eq!(extractor.get_f32("llama.rope.freq_base"), Some(500_000.0));
+        assert_eq!(
+            extractor.get_f32("llama.attention.layer_norm_rms_epsilon"),
+            Some(1e-5)
+        );
+        assert_eq!(extractor.get_f32("llama.block_count"), Some(32.0));
+        assert_eq!(extractor.get_f32("bad.negative"), Some(-1.0));
+        assert_eq!(extractor.get_f32("general.name"), None);
+    }
+
+    fn parse_layout(file: &[u8]) -> GgufLayout {
+        parse_gguf_layout(&mut BufReader::new(std::io::Cursor::new(file.to_vec()))).unwrap()
+    }
+
+    #[test]
+    fn test_tensor_data_start_honours_alignment() {
+        let tensors = [
+            TestTensor {
+                name: "token_embd.weight",
+                dims: &[8, 4],
+                ggml_type: 0,
+                offset: 0,
+            },
+            TestTensor {
+                name: "output.weight",
+                dims: &[8],
+                ggml_type: 0,
+                offset: 128,
+            },
+        ];
+        let infos = gguf_bytes_with_tensors(&[("general.alignment", TestValue::U32(64))], &tensors);
+        assert_ne!(infos.len() % 64, 0, "fixture must need padding");
+        let data_start = infos.len().next_multiple_of(64);
+        let mut file = infos.clone();
+        file.resize(data_start + 128 + 32, 0);
+
+        let layout = parse_layout(&file);
+        assert_eq!(layout.alignment, 64);
+        assert_eq!(layout.tensor_data_start, data_start as u64);
+        assert_eq!(layout.tensors[0].dims, vec![8, 4]);
+        assert_eq!(layout.tensors[1].offset, 128);
+        assert_eq!(
+            layout.tensor_sizes(file.len() as u64),
+            vec![("token_embd.weight", 128), ("output.weight", 32)]
+        );
+
+        // Without the key the GGUF default of 32 applies
+        let infos = gguf_bytes_with_tensors(&[], &tensors);
+        assert_ne!(infos.len() % 32, 0, "fixture must need padding");
+        let layout = parse_layout(&infos);
+        assert_eq!(layout.alignment, GGUF_DEFAULT_ALIGNMENT);
+        assert_eq!(
+            layout.tensor_data_start,
+            infos.len().next_multiple_of(32) as u64
+        );
+    }
+
+    #[test]
+    fn test_block_bytes_leave_out_non_repeating_tensors() {
+        let tensors = [
+            TestTensor { name: "token_embd.weight", dims: &[16], ggml_type: 0, offset: 0 },
+            TestTensor { name: "blk.0.attn_q.weight", dims: &[8], ggml_type: 0, offset: 64 },
+            TestTensor { name: "blk.1.attn_q.weight", dims: &[8], ggml_type: 0, offset: 96 },
+            TestTensor { name: "output.weight", dims: &[8], ggml_type: 0, offset: 128 },
+        ];
+        let mut file = gguf_bytes_with_tensors(&[], &tensors);
+        let data_start = file.len().next_multiple_of(32);
+        file.resize(data_start + 160, 0);
+
+        let layout = parse_layout(&file);
+        assert_eq!(layout.block_bytes(file.len() as u64), 64);
+    }
+
+    #[test]
+    fn test_align_offset() {
+        assert_eq!(align_offset(0, 32), 0);
+        assert_eq!(align_offset(1, 32), 32);
+        assert_eq!(align_offset(64, 32), 64);
+        assert_eq!(align_offset(65, 64), 128);
+    }
 }
@@ -0,0 +1,394 @@
+// Cross-backend GPU enumeration for VRAM planning and the system info routes.
+//
+// CUDA builds query nvidia-smi (same source as get_available_vram_gb) and fall
+// back to the ggml backend registry; Vulkan builds ask the registry. CPU-only
+// builds, missing drivers and unparsable output all yield an empty list rather
+// than an error.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// One GPU visible to the compiled backend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GpuInfo {
+    pub index: u32,
+    pub name: String,
+    pub total_vram_mb: u64,
+    pub free_vram_mb: u64,
+}
+
+/// Static device info (index, name, total VRAM), kept once a probe finds a device.
+static GPU_CACHE: OnceLock<Vec<GpuInfo>> = OnceLock::new();
+
+/// List GPUs with current free VRAM.
+///
+/// Names and totals are cached after the first probe that finds a device;
+/// free VRAM is re-queried every time. Returns an empty vec on CPU-only builds
+/// or when no driver responds.
+pub fn enumerate_gpus() -> Vec<GpuInfo> {
+    enumerate_with(&GPU_CACHE, query_gpus, query_free_vram_mb)
+}
+
+fn enumerate_with(
+    cache: &OnceLock<Vec<GpuInfo>>,
+    query: impl FnOnce() -> Vec<GpuInfo>,
+    query_free: impl FnOnce() -> Vec<(u32, u64)>,
+) -> Vec<GpuInfo> {
+    let Some(cached) = cache.get() else {
+        // An empty probe isn't cached: the driver may just not be up yet
+        let gpus = query();
+        if !gpus.is_empty() {
+            let _ = cache.set(gpus.clone());
+        }
+        return gpus;
+    };
+    let mut gpus = cached.clone();
+    for (index, free_mb) in query_free() {
+        if let Some(gpu) = gpus.iter_mut().find(|g| g.index == index) {
+            gpu.free_vram_mb = free_mb;
+        }
+    }
+    gpus
+}
+
+/// Name of the GPU backend this build can probe ("cuda" or "vulkan"), if any.
+pub fn compiled_gpu_backend() -> Option<&'static str> {
+    if cfg!(feature = "cuda") {
+        Some("cuda")
+    } else if cfg!(feature = "vulkan") {
+        Some("vulkan")
+    } else {
+        None
+    }
+}
+
+/// Layers to offload and the backend they run on, for a load asking for
+/// `requested_layers`.
+///
+/// A CUDA/Vulkan build whose probe finds no usable device loads on the CPU
+/// (zero layers) instead of failing inside llama.cpp. Builds without either
+/// backend can't probe (Metal isn't visible here), so the request is kept and
+/// the backend is only known when nothing is offloaded.
+pub fn resolve_load_backend(
+    requested_layers: u32,
+    compiled: Option<&'static str>,
+    has_usable_device: impl FnOnce() -> bool,
+) -> (u32, Option<&'static str>) {
+    if requested_layers == 0 {
+        return (0, Some("cpu"));
+    }
+    match compiled {
+        Some(backend) if has_usable_device() => (requested_layers, Some(backend)),
+        Some(_) => (0, Some("cpu")),
+        None => (requested_layers, None),
+    }
+}
+
+/// Text the CUDA runtime and ggml-cuda emit when the installed driver is older
+/// than the runtime this build links against.
+const CUDA_DRIVER_MISMATCH_SIGNATURES: &[&str] = &[
+    "cuda driver version is insufficient",
+    "cudaerrorinsufficientdriver",
+    "forward compatibility was attempted",
+    "ggml_cuda_init: failed to initialize cuda",
+];
+
+/// Shown with a driver mismatch so the user knows what to change.
+pub const CUDA_DRIVER_REMEDIATION: &str = "Update the NVIDIA driver to one that supports the CUDA \
+     version this build was compiled with, or run the CPU/Vulkan build instead.";
+
+/// True if `message` matches a known CUDA driver/runtime mismatch.
+pub fn is_cuda_driver_mismatch(message: &str) -> bool {
+    let lower = message.to_ascii_lowercase();
+    CUDA_DRIVER_MISMATCH_SIGNATURES.iter().any(|sig| lower.contains(sig))
+}
+
+/// Why the llama.cpp backend failed to initialize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendInitError {
+    /// The GPU driver can't run the compiled backend's runtime.
+    DriverMismatch { backend: &'static str, detail: String },
+    /// Any other failure, reported as-is.
+    Other(String),
+}
+
+impl std::fmt::Display for BackendInitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DriverMismatch { backend, detail } => write!(
+                f,
+                "Failed to init backend: {backend} driver/runtime mismatch ({detail}). {CUDA_DRIVER_REMEDIATION}"
+            ),
+            Self::Other(detail) => write!(f, "Failed to init backend: {detail}"),
+        }
+    }
+}
+
+/// Run `init` (normally `LlamaBackend::init`) and classify its failure.
+///
+/// A panic inside the init is caught and treated like an error, so the worker
+/// answers the load request instead of dying. A native abort can't be caught
+/// here; the bridge flags those from the worker's stderr instead.
+pub fn init_backend<B, E: std::fmt::Display>(
+    compiled: Option<&'static str>,
+    init: impl FnOnce() -> Result<B, E>,
+) -> Result<B, BackendInitError> {
+    let detail = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(init)) {
+        Ok(Ok(backend)) => return Ok(backend),
+        Ok(Err(e)) => e.to_string(),
+        Err(payload) => crate::tokenize_guard::panic_message(payload.as_ref())
+            .unwrap_or_else(|| "backend init panicked".to_string()),
+    };
+    match compiled {
+        Some(backend @ "cuda") if is_cuda_driver_mismatch(&detail) => {
+            Err(BackendInitError::DriverMismatch { backend, detail })
+        }
+        _ => Err(BackendInitError::Other(detail)),
+    }
+}
+
+/// Set in the environment of a worker respawned after a driver mismatch. The
+/// GPUs are hidden from that process (`CUDA_VISIBLE_DEVICES=""`) before it
+/// starts, so its backend init sees no CUDA device.
+pub const CPU_FALLBACK_ENV: &str = "LLAMA_CHAT_CPU_FALLBACK";
+
+/// Shown while a worker runs on the CPU after a driver mismatch.
+pub const CPU_FALLBACK_NOTICE: &str = "CUDA driver/runtime mismatch: running on the CPU without \
+     GPU offload. Update the NVIDIA driver to use the GPU again.";
+
+/// Set once this process runs without the GPU runtime. Loads then stay on the
+/// CPU instead of offloading to a dead device.
+static CPU_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+/// True if this process runs on the CPU after a driver mismatch.
+pub fn cpu_fallback_active() -> bool {
+    CPU_FALLBACK.load(Ordering::Relaxed)
+}
+
+/// Record that the backend is running without the GPU runtime.
+pub fn set_cpu_fallback() {
+    CPU_FALLBACK.store(true, Ordering::Relaxed);
+}
+
+#[cfg(not(any(feature = "cuda", feature = "vulkan")))]
+fn query_gpus() -> Vec<GpuInfo> {
+    Vec::new()
+}
+
+#[cfg(not(any(feature = "cuda", feature = "vulkan")))]
+fn query_free_vram_mb() -> Vec<(u32, u64)> {
+    Vec::new()
+}
+
+#[cfg(any(feature = "cuda", feature = "vulkan"))]
+fn query_gpus() -> Vec<GpuInfo> {
+    #[cfg(feature = "cuda")]
+    {
+        let gpus = query_nvidia_smi();
+        if !gpus.is_empty() {
+            return gpus;
+        }
+        // No nvidia-smi on PATH doesn't mean CUDA sees no device
+        let gpus = query_ggml_devices("cuda");
+        if !gpus.is_empty() {
+            return gpus;
+        }
+    }
+    #[cfg(feature = "vulkan")]
+    {
+        let gpus = query_ggml_devices("vulkan");
+        if !gpus.is_empty() {
+            return gpus;
+        }
+    }
+    Vec::new()
+}
+
+#[cfg(any(feature = "cuda", feature = "vulkan"))]
+fn query_free_vram_mb() -> Vec<(u32, u64)> {
+    query_gpus()
+        .into_iter()
+        .map(|g| (g.index, g.free_vram_mb))
+        .collect()
+}
+
+#[cfg(feature = "cuda")]
+fn query_nvidia_smi() -> Vec<GpuInfo> {
+    let output = super::utils::silent_command("nvidia-smi")
+        .args([
+            "--query-gpu=index,name,memory.total,memory.free",
+            "--format=csv,noheader,nounits",
+        ])
+        .output();
+    match output {
+        Ok(out) if out.status.success() => {
+            parse_nvidia_smi_gpus(&String::from_utf8_lossy(&out.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Devices the ggml backend registry lists for `backend` ("cuda", "vulkan").
+#[cfg(any(feature = "cuda", feature = "vulkan"))]
+fn query_ggml_devices(backend: &str) -> Vec<GpuInfo> {
+    const BYTES_PER_MB: usize = 1024 * 1024;
+    llama_cpp_2::list_llama_ggml_backend_devices()
+        .into_iter()
+        .filter(|dev| dev.backend.eq_ignore_ascii_case(backend))
+        .enumerate()
+        .map(|(index, dev)| GpuInfo {
+            index: index as u32,
+            name: dev.description.clone(),
+            total_vram_mb: (dev.memory_total / BYTES_PER_MB) as u64,
+            free_vram_mb: (dev.memory_free / BYTES_PER_MB) as u64,
+        })
+        .collect()
+}
+
+/// Parse `nvidia-smi --query-gpu=index,name,memory.total,memory.free
+/// --format=csv,noheader,nounits` output. Malformed lines are skipped.
+#[cfg(any(test, feature = "cuda"))]
+fn parse_nvidia_smi_gpus(output: &str) -> Vec<GpuInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            // Names may contain commas; index is first, totals are the last two fields
+            let n = fields.len();
+            Some(GpuInfo {
+                index: fields[0].parse().ok()?,
+                name: fields[1..n - 2].join(", "),
+                total_vram_mb: fields[n - 2].parse().ok()?,
+                free_vram_mb: fields[n - 1].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(any(feature = "cuda", feature = "vulkan")))]
+    #[test]
+    fn test_cpu_only_build_has_no_gpus() {
+        assert!(enumerate_gpus().is_empty());
+        // Cache stays empty, so a second call is still empty rather than stale
+        assert!(enumerate_gpus().is_empty());
+    }
+
+    #[test]
+    fn test_failed_probe_is_retried_and_a_found_device_is_cached() {
+        let cache = OnceLock::new();
+        let gpu = GpuInfo { index: 0, name: "Radeon RX 7900".to_string(), total_vram_mb: 24560, free_vram_mb: 20000 };
+
+        // Driver not up yet: nothing cached, so the next call probes again
+        assert!(enumerate_with(&cache, Vec::new, Vec::new).is_empty());
+        assert!(cache.get().is_none());
+        assert_eq!(enumerate_with(&cache, || vec![gpu.clone()], Vec::new), [gpu.clone()]);
+
+        // Found devices stay; only free VRAM is refreshed
+        let gpus = enumerate_with(&cache, || unreachable!(), || vec![(0, 512)]);
+        assert_eq!(gpus[0].name, "Radeon RX 7900");
+        assert_eq!(gpus[0].free_vram_mb, 512);
+    }
+
+    #[test]
+    fn test_gpu_build_without_device_falls_back_to_cpu() {
+        let mut probed = false;
+        let resolved = resolve_load_backend(99, Some("cuda"), || {
+            probed = true;
+            false
+        });
+        assert!(probed);
+        assert_eq!(resolved, (0, Some("cpu")));
+        assert_eq!(
+            resolve_load_backend(99, Some("vulkan"), || true),
+            (99, Some("vulkan"))
+        );
+    }
+
+    #[test]
+    fn test_unprobed_and_cpu_requests_keep_layers() {
+        // Zero layers never needs the probe
+        assert_eq!(
+            resolve_load_backend(0, Some("cuda"), || unreachable!()),
+            (0, Some("cpu"))
+        );
+        assert_eq!(
+            resolve_load_backend(32, None, || unreachable!()),
+            (32, None)
+        );
+        assert_eq!(
+            compiled_gpu_backend().is_some(),
+            cfg!(any(feature = "cuda", feature = "vulkan"))
+        );
+    }
+
+    #[test]
+    fn test_gpu_info_serializes_expected_fields() {
+        let gpu = GpuInfo {
+            index: 0,
+            name: "NVIDIA GeForce RTX 4090".to_string(),
+            total_vram_mb: 24564,
+            free_vram_mb: 23012,
+        };
+        let json = serde_json::to_value(&gpu).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "index": 0,
+                "name": "NVIDIA GeForce RTX 4090",
+                "total_vram_mb": 24564,
+                "free_vram_mb": 23012,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi_gpus() {
+        let output = "0, NVIDIA GeForce RTX 4090, 24564, 23012\n\
+                      1, NVIDIA RTX A6000, 49140, 48000\n\
+                      garbage line\n";
+        let gpus = parse_nvidia_smi_gpus(output);
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].name, "NVIDIA GeForce RTX 4090");
+        assert_eq!(gpus[1].index, 1);
+        assert_eq!(gpus[1].total_vram_mb, 49140);
+        assert_eq!(gpus[1].free_vram_mb, 48000);
+    }
+
+    #[test]
+    fn test_parse_nvidia_smi_empty_output() {
+        assert!(parse_nvidia_smi_gpus("").is_empty());
+        assert!(parse_nvidia_smi_gpus("No devices were found\n").is_empty());
+    }
+
+    #[test]
+    fn test_backend_init_failure_yields_structured_error() {
+        let err = init_backend::<(), _>(Some("cuda"), || {
+            Err("CUDA error: CUDA driver version is insufficient for CUDA runtime version")
+        })
+        .unwrap_err();
+        assert!(matches!(err, BackendInitError::DriverMismatch { backend: "cuda", .. }));
+        assert!(err.to_string().contains(CUDA_DRIVER_REMEDIATION));
+
+        // A panicking init is reported rather than unwinding through the worker
+        let err = init_backend::<(), &str>(Some("cuda"), || {
+            panic!("ggml_cuda_init: failed to initialize CUDA: forward compatibility was attempted")
+        })
+        .unwrap_err();
+        assert!(matches!(err, BackendInitError::DriverMismatch { .. }));
+
+        // Same text on a non-CUDA build isn't a driver mismatch
+        assert_eq!(
+            init_backend::<(), _>(None, || Err("cudaErrorInsufficientDriver")).unwrap_err(),
+            BackendInitError::Other("cudaErrorInsufficientDriver".to_string())
+        );
+        assert_eq!(init_backend::<_, &str>(Some("cuda"), || Ok(7)), Ok(7));
+    }
+}
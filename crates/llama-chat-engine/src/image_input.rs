@@ -0,0 +1,252 @@
+//! Decode and validate `image_data` entries before they reach the vision encoder.
+//!
+//! A malformed data URL or an oversized image used to crash or hang mtmd, so
+//! every entry is checked up front: optional `data:image/...;base64,` prefix,
+//! base64 payload, PNG/JPEG magic, and byte/pixel budgets.
+
+use base64::Engine;
+
+/// Largest decoded image accepted (bytes).
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+/// Largest image accepted (width × height).
+pub const MAX_IMAGE_PIXELS: u64 = 4096 * 4096;
+
+const PNG_MAGIC: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const JPEG_MAGIC: &[u8] = &[0xFF, 0xD8, 0xFF];
+
+/// Supported image container formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+/// A decoded image that passed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedImage {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// Decode one `image_data` entry (raw base64 or a `data:image/...;base64,` URL).
+pub fn decode_image_input(data: &str) -> Result<ValidatedImage, String> {
+    let payload = strip_data_url(data.trim())?;
+    // Budget check before decoding: base64 is 4 chars per 3 bytes
+    if payload.len() / 4 * 3 > MAX_IMAGE_BYTES + 3 {
+        return Err(format!(
+            "image exceeds the {} MB limit",
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+    let compact: String = payload
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(compact.as_bytes())
+        .map_err(|e| format!("invalid base64 ({e})"))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "image exceeds the {} MB limit",
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let (format, (width, height)) = if bytes.starts_with(PNG_MAGIC) {
+        (ImageFormat::Png, png_dimensions(&bytes)?)
+    } else if bytes.starts_with(JPEG_MAGIC) {
+        (ImageFormat::Jpeg, jpeg_dimensions(&bytes)?)
+    } else {
+        return Err("unsupported image format (expected PNG or JPEG)".to_string());
+    };
+    if width == 0 || height == 0 {
+        return Err("image has zero width or height".to_string());
+    }
+    if u64::from(width) * u64::from(height) > MAX_IMAGE_PIXELS {
+        return Err(format!(
+            "image is {width}x{height}, over the {MAX_IMAGE_PIXELS} pixel limit"
+        ));
+    }
+    Ok(ValidatedImage {
+        format,
+        width,
+        height,
+        bytes,
+    })
+}
+
+/// Validate every entry, reporting each bad one by position. In builds without
+/// the `vision` feature, valid input still fails with "vision not supported".
+pub fn prepare_image_inputs(images: &[String]) -> Result<Vec<ValidatedImage>, String> {
+    let mut decoded = Vec::with_capacity(images.len());
+    let mut errors = Vec::new();
+    for (i, data) in images.iter().enumerate() {
+        match decode_image_input(data) {
+            Ok(image) => decoded.push(image),
+            Err(e) => errors.push(format!("image {}: {e}", i + 1)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!("Invalid image input — {}", errors.join("; ")));
+    }
+    if !decoded.is_empty() && !cfg!(feature = "vision") {
+        return Err(
+            "Vision not supported: this build was compiled without the `vision` feature"
+                .to_string(),
+        );
+    }
+    Ok(decoded)
+}
+
+/// Accept raw base64 or `data:image/<type>;base64,<payload>`.
+fn strip_data_url(data: &str) -> Result<&str, String> {
+    let Some(rest) = data.strip_prefix("data:") else {
+        return Ok(data);
+    };
+    let (header, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| "malformed data URL (missing ',')".to_string())?;
+    if !header.starts_with("image/") {
+        return Err(format!("data URL is not an image ({header})"));
+    }
+    if !header.ends_with(";base64") {
+        return Err("data URL is not base64-encoded".to_string());
+    }
+    Ok(payload)
+}
+
+/// Width and height from the IHDR chunk, which PNG requires to come first.
+fn png_dimensions(bytes: &[u8]) -> Result<(u32, u32), String> {
+    if bytes.len() < 24 || &bytes[12..16] != b"IHDR" {
+        return Err("truncated PNG header".to_string());
+    }
+    let width = u32::from_be_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]);
+    let height = u32::from_be_bytes([bytes[20], bytes[21], bytes[22], bytes[23]]);
+    Ok((width, height))
+}
+
+/// Width and height from the first SOFn segment.
+fn jpeg_dimensions(bytes: &[u8]) -> Result<(u32, u32), String> {
+    let mut i = 2;
+    while i + 4 <= bytes.len() {
+        if bytes[i] != 0xFF {
+            return Err("corrupt JPEG segment".to_string());
+        }
+        let marker = bytes[i + 1];
+        // Fill bytes and standalone markers carry no length
+        if marker == 0xFF {
+            i += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            i += 2;
+            continue;
+        }
+        let len = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_sof {
+            if i + 9 > bytes.len() {
+                break;
+            }
+            let height = u16::from_be_bytes([bytes[i + 5], bytes[i + 6]]) as u32;
+            let width = u16::from_be_bytes([bytes[i + 7], bytes[i + 8]]) as u32;
+            return Ok((width, height));
+        }
+        if len < 2 {
+            return Err("corrupt JPEG segment".to_string());
+        }
+        i += 2 + len;
+    }
+    Err("truncated JPEG (no frame header)".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// PNG signature + IHDR for a `width`×`height` RGBA image (enough for validation).
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = PNG_MAGIC.to_vec();
+        bytes.extend_from_slice(&13u32.to_be_bytes());
+        bytes.extend_from_slice(b"IHDR");
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+        bytes.extend_from_slice(&[0, 0, 0, 0]); // CRC (not checked)
+        bytes
+    }
+
+    fn b64(bytes: &[u8]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    #[test]
+    fn test_valid_base64_png() {
+        let image = decode_image_input(&b64(&png_header(640, 480))).unwrap();
+        assert_eq!(image.format, ImageFormat::Png);
+        assert_eq!((image.width, image.height), (640, 480));
+    }
+
+    #[test]
+    fn test_data_url_prefixed_png() {
+        let url = format!("data:image/png;base64,{}", b64(&png_header(2, 3)));
+        let image = decode_image_input(&url).unwrap();
+        assert_eq!((image.width, image.height), (2, 3));
+
+        let not_image = format!("data:text/plain;base64,{}", b64(b"hello"));
+        assert!(decode_image_input(&not_image)
+            .unwrap_err()
+            .contains("not an image"));
+    }
+
+    #[test]
+    fn test_valid_jpeg_dimensions() {
+        // SOI, APP0 (len 16), SOF0 with height 100 width 200
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        jpeg.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x64, 0x00, 0xC8, 0x03]);
+        let image = decode_image_input(&b64(&jpeg)).unwrap();
+        assert_eq!(image.format, ImageFormat::Jpeg);
+        assert_eq!((image.width, image.height), (200, 100));
+    }
+
+    #[test]
+    fn test_garbage_input_is_rejected() {
+        assert!(decode_image_input("not base64 at all!!")
+            .unwrap_err()
+            .contains("invalid base64"));
+        let err = decode_image_input(&b64(b"GIF89a fake")).unwrap_err();
+        assert!(err.contains("unsupported image format"), "{err}");
+        assert!(decode_image_input("data:image/png;base64")
+            .unwrap_err()
+            .contains("malformed"));
+    }
+
+    #[test]
+    fn test_pixel_budget() {
+        let err = decode_image_input(&b64(&png_header(100_000, 100_000))).unwrap_err();
+        assert!(err.contains("pixel limit"), "{err}");
+    }
+
+    #[test]
+    fn test_prepare_reports_each_bad_image() {
+        let images = vec![b64(&png_header(1, 1)), "???".to_string(), b64(b"nope")];
+        let err = prepare_image_inputs(&images).unwrap_err();
+        assert!(err.contains("image 2:"), "{err}");
+        assert!(err.contains("image 3:"), "{err}");
+        assert!(!err.contains("image 1:"), "{err}");
+    }
+
+    #[test]
+    fn test_prepare_without_vision_feature() {
+        let result = prepare_image_inputs(&[b64(&png_header(1, 1))]);
+        if cfg!(feature = "vision") {
+            assert_eq!(result.unwrap().len(), 1);
+        } else {
+            assert!(result.unwrap_err().contains("Vision not supported"));
+        }
+        assert!(prepare_image_inputs(&[]).unwrap().is_empty());
+    }
+}
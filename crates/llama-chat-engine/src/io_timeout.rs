@@ -0,0 +1,94 @@
+//! Time-limited filesystem calls for the models directory.
+//!
+//! A models directory on an unresponsive network mount can block `read_dir`
+//! or `metadata` indefinitely. These helpers run the call on a separate thread
+//! and give up after a short timeout; a hung call keeps its thread, but the
+//! caller gets an error instead of hanging.
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Error returned when a models directory call times out.
+pub const MODELS_DIR_UNRESPONSIVE: &str = "Models directory unresponsive";
+/// Timeout used when `LLAMA_CHAT_MODELS_IO_TIMEOUT_MS` is unset or invalid.
+pub const DEFAULT_MODELS_IO_TIMEOUT_MS: u64 = 5000;
+
+/// Timeout for models directory calls, from `LLAMA_CHAT_MODELS_IO_TIMEOUT_MS`.
+pub fn models_io_timeout() -> Duration {
+    let ms = std::env::var("LLAMA_CHAT_MODELS_IO_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&ms| ms > 0)
+        .unwrap_or(DEFAULT_MODELS_IO_TIMEOUT_MS);
+    Duration::from_millis(ms)
+}
+
+/// Run `op` on its own thread, failing with [`MODELS_DIR_UNRESPONSIVE`] if it
+/// hasn't finished within `timeout`.
+pub fn with_io_timeout<T, F>(timeout: Duration, op: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("models-io".to_string())
+        .spawn(move || {
+            let _ = tx.send(op());
+        })
+        .map_err(|e| format!("Failed to spawn I/O thread: {e}"))?;
+    match rx.recv_timeout(timeout) {
+        Ok(value) => Ok(value),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            log_warn!(
+                "system",
+                "Models directory call timed out after {} ms",
+                timeout.as_millis()
+            );
+            Err(MODELS_DIR_UNRESPONSIVE.to_string())
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Err("Models directory call failed".to_string())
+        }
+    }
+}
+
+/// [`with_io_timeout`] with the configured [`models_io_timeout`].
+pub fn with_models_io_timeout<T, F>(op: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    with_io_timeout(models_io_timeout(), op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_fast_call_returns_its_value() {
+        let entries = with_io_timeout(Duration::from_secs(5), || vec!["a.gguf", "b.gguf"]);
+        assert_eq!(entries.unwrap(), ["a.gguf", "b.gguf"]);
+    }
+
+    #[test]
+    fn test_slow_readdir_times_out() {
+        // Stands in for read_dir on a hung network mount
+        let slow_readdir = || {
+            std::thread::sleep(Duration::from_secs(2));
+            Vec::<String>::new()
+        };
+        let started = Instant::now();
+        let err = with_io_timeout(Duration::from_millis(50), slow_readdir).unwrap_err();
+        assert_eq!(err, MODELS_DIR_UNRESPONSIVE);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_panicking_call_is_an_error() {
+        let err = with_io_timeout(Duration::from_secs(5), || -> u8 { panic!("boom") }).unwrap_err();
+        assert_eq!(err, "Models directory call failed");
+    }
+}
@@ -11,6 +11,7 @@ pub use tool_catalog::{
     get_all_tools,
     get_available_tools,
     get_desktop_tool_definitions,
+    get_enabled_tools,
     get_tool_catalog,
     get_tool_schema,
 };
@@ -158,12 +159,16 @@ pub struct ToolFunction {
 /// Get available tools in OpenAI function-calling format for Jinja templates.
 #[allow(dead_code)]
 pub fn get_available_tools_openai() -> Vec<Value> {
-    get_available_tools_openai_with_mcp(None)
+    get_available_tools_openai_with_mcp(None, None)
 }
 
 /// Get available tools in OpenAI format, optionally including MCP tools.
-pub fn get_available_tools_openai_with_mcp(mcp_tools: Option<&[llama_chat_tools::McpToolDefInfo]>) -> Vec<Value> {
-    let mut tools: Vec<Value> = get_available_tools()
+/// Native tools outside `enabled_tools` are left out (None = all).
+pub fn get_available_tools_openai_with_mcp(
+    mcp_tools: Option<&[llama_chat_tools::McpToolDefInfo]>,
+    enabled_tools: Option<&[String]>,
+) -> Vec<Value> {
+    let mut tools: Vec<Value> = get_enabled_tools(enabled_tools)
         .into_iter()
         .map(|tool| {
             json!({
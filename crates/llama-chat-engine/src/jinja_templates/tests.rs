@@ -2,7 +2,7 @@
 mod tests {
     use super::super::{
         apply_native_chat_template, parse_conversation_for_jinja,
-        get_available_tools_openai,
+        get_available_tools_openai, get_available_tools_openai_with_mcp,
         ChatMessage,
     };
     // preprocess_template and epoch_days_to_ymd are pub(crate), access via parent
@@ -40,6 +40,22 @@ Hi there!"#;
         }
     }
 
+    #[test]
+    fn test_disabled_tools_are_not_advertised() {
+        let names = |tools: &[serde_json::Value]| -> Vec<String> {
+            tools
+                .iter()
+                .filter_map(|t| t["function"]["name"].as_str().map(str::to_string))
+                .collect()
+        };
+        let all = names(&get_available_tools_openai_with_mcp(None, None));
+        assert!(all.iter().any(|n| n == "execute_command"));
+
+        let enabled = vec!["read_file".to_string(), "list_directory".to_string()];
+        let limited = names(&get_available_tools_openai_with_mcp(None, Some(&enabled)));
+        assert_eq!(limited, vec!["read_file", "list_directory"]);
+    }
+
     #[test]
     fn test_preprocess_template_strips_ensure_ascii() {
         let input = r#"{{ tool | tojson(ensure_ascii=False) }}"#;
@@ -9,6 +9,7 @@ pub(crate) const CORE_TOOL_NAMES: &[&str] = &[
     "git_status", "git_diff", "git_commit",
     "check_background_process", "list_background_processes",
     "send_telegram", "spawn_agent",
+    "sql_query",
     "list_skills", "use_skill", "set_response_style",
     "ocr_screen",
     "browser_navigate", "browser_go_back", "browser_click", "browser_type", "browser_eval",
@@ -145,6 +146,18 @@ pub fn get_available_tools() -> Vec<Value> {
     tools
 }
 
+/// `get_available_tools` minus the tools the config's `enabled_tools` leaves out.
+pub fn get_enabled_tools(enabled_tools: Option<&[String]>) -> Vec<Value> {
+    get_available_tools()
+        .into_iter()
+        .filter(|tool| {
+            tool.get("name")
+                .and_then(|n| n.as_str())
+                .is_none_or(|name| llama_chat_tools::is_tool_enabled(enabled_tools, name))
+        })
+        .collect()
+}
+
 /// Get only the desktop automation tool definitions (for the MCP server).
 #[allow(dead_code)]
 pub fn get_desktop_tool_definitions() -> Vec<Value> {
@@ -11,23 +11,38 @@ mod command_executor;
 pub mod config_ext;
 pub mod compaction;
 mod context_eval;
+pub mod context_pool;
+mod context_overflow;
+mod duplicate_calls;
 pub mod filename_patterns;
 mod generation;
 pub mod gguf_info;
+pub mod gpu_devices;
 pub mod gguf_utils;
+#[cfg(test)]
+mod gguf_test_support;
+pub mod image_input;
+pub mod io_timeout;
 pub mod jinja_templates;
+mod logprobs;
 pub mod loop_detection;
 pub mod model_manager;
+pub mod model_validation;
+mod self_test;
+pub mod session;
 mod prompt_builder;
+mod prompt_echo;
 mod sampler;
 mod stop_conditions;
 pub mod sub_agent;
 pub mod sub_checks;
 pub mod templates;
 mod token_loop;
+mod tokenize_guard;
 mod tool_dispatch;
 mod tool_grammar;
 mod tool_output;
+mod tool_return;
 pub mod tool_tags;
 pub mod utils;
 pub mod vram_calculator;
@@ -47,12 +62,15 @@ pub type SharedConversationLogger = std::sync::Arc<std::sync::Mutex<llama_chat_d
 // Public API
 pub use generation::generate_llama_response;
 pub use generation::GenerationOutput;
+pub use generation::completion_count;
 pub use sub_checks::generate_title_text;
+pub use self_test::{run_self_test, SelfTestReport};
 pub use prompt_builder::warmup_system_prompt;
 pub use templates::get_universal_system_prompt_with_tags;
 pub use tool_tags::get_tool_tags_for_model;
-pub use model_manager::{get_model_status, load_model, ModelParams};
+pub use model_manager::{get_model_status, load_model, ModelError, ModelParams};
 pub use gguf_info::extract_model_info;
+pub use sampler::SAMPLER_TYPES;
 pub use vram_calculator::calculate_optimal_gpu_layers;
 
 /// Build a `DispatchContext` that connects the tools crate to the engine crate's
@@ -68,5 +86,7 @@ pub fn make_dispatch_context() -> llama_chat_tools::DispatchContext<'static> {
         }),
         discover_skills: None,
         get_skill: None,
+        tool_format: model_manager::loaded_tool_format(),
+        conversation_id: None,
     }
 }
@@ -0,0 +1,161 @@
+//! Per-token log-probabilities for the `logprobs` request option.
+//!
+//! Computed from the raw logits of the position just sampled (the model's
+//! distribution, before sampler filtering), so values are comparable across
+//! sampler settings.
+
+use llama_chat_types::{TokenLogprob, TopLogprob};
+use llama_cpp_2::context::LlamaContext;
+use llama_cpp_2::model::{LlamaModel, Special};
+use llama_cpp_2::token::LlamaToken;
+
+/// Largest top-k honoured; more just bloats every token payload.
+pub(crate) const MAX_TOP_LOGPROBS: u32 = 20;
+
+/// Log-softmax of `logits` at `chosen`, plus the `top_k` most likely
+/// `(index, logprob)` pairs, most likely first.
+pub(crate) fn logprobs_from_logits(
+    logits: &[f32],
+    chosen: usize,
+    top_k: usize,
+) -> (f32, Vec<(usize, f32)>) {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let log_sum = logits.iter().map(|&l| (l - max).exp()).sum::<f32>().ln() + max;
+    let chosen_logprob = logits
+        .get(chosen)
+        .map(|&l| l - log_sum)
+        .unwrap_or(f32::NEG_INFINITY);
+
+    let k = top_k.min(logits.len());
+    if k == 0 {
+        return (chosen_logprob, Vec::new());
+    }
+    let mut indices: Vec<usize> = (0..logits.len()).collect();
+    indices.select_nth_unstable_by(k - 1, |&a, &b| logits[b].total_cmp(&logits[a]));
+    indices.truncate(k);
+    indices.sort_by(|&a, &b| logits[b].total_cmp(&logits[a]));
+    let top = indices
+        .into_iter()
+        .map(|i| (i, logits[i] - log_sum))
+        .collect();
+    (chosen_logprob, top)
+}
+
+/// Capture logprobs for `chosen`, which was just sampled from `context`'s last logits.
+pub(crate) fn capture_logprobs(
+    model: &LlamaModel,
+    context: &LlamaContext<'_>,
+    chosen: LlamaToken,
+    top_k: u32,
+) -> TokenLogprob {
+    #[allow(deprecated)]
+    let token_text = |token: LlamaToken| {
+        model
+            .token_to_str(token, Special::Tokenize)
+            .unwrap_or_default()
+    };
+    let top_k = top_k.min(MAX_TOP_LOGPROBS) as usize;
+    let (logprob, top) = logprobs_from_logits(context.get_logits(), chosen.0 as usize, top_k);
+    TokenLogprob {
+        token: token_text(chosen),
+        logprob,
+        top: top
+            .into_iter()
+            .map(|(id, logprob)| TopLogprob {
+                tok: token_text(LlamaToken(id as i32)),
+                logprob,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logprobs_are_normalized_and_sorted() {
+        let logits = [1.0, 3.0, 2.0, -1.0];
+        let (chosen, top) = logprobs_from_logits(&logits, 2, 3);
+
+        let (_, all) = logprobs_from_logits(&logits, 0, logits.len());
+        let total: f32 = all.iter().map(|&(_, lp)| lp.exp()).sum();
+        assert!((total - 1.0).abs() < 1e-5, "probabilities sum to {total}");
+        assert_eq!(
+            top.iter().map(|&(i, _)| i).collect::<Vec<_>>(),
+            vec![1, 2, 0]
+        );
+        assert_eq!(top[1].1, chosen);
+        assert!(top.iter().all(|&(_, lp)| lp <= 0.0));
+    }
+
+    #[test]
+    fn test_top_k_clamped_to_vocab() {
+        let (_, top) = logprobs_from_logits(&[0.0, 0.0], 0, 10);
+        assert_eq!(top.len(), 2);
+        let (chosen, top) = logprobs_from_logits(&[0.0, 0.0], 5, 0);
+        assert!(top.is_empty());
+        assert_eq!(chosen, f32::NEG_INFINITY);
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_greedy_token_has_max_logprob() {
+        use llama_cpp_2::llama_batch::LlamaBatch;
+        use llama_cpp_2::model::AddBos;
+        use llama_cpp_2::sampling::LlamaSampler;
+        use std::num::NonZeroU32;
+        use std::sync::{Arc, Mutex};
+
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let state: llama_chat_types::SharedLlamaState = Arc::new(Mutex::new(None));
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(crate::load_model(
+                state.clone(),
+                &model_path,
+                Some(0),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        let guard = state.lock().unwrap();
+        let loaded = guard.as_ref().unwrap();
+        let model = loaded.model.as_ref().unwrap();
+        let mut ctx = crate::context_eval::create_fresh_context(
+            model,
+            &loaded.backend,
+            NonZeroU32::new(512).unwrap(),
+            false,
+            &llama_chat_types::SamplerConfig::default(),
+        )
+        .unwrap();
+
+        let tokens = model
+            .str_to_token("The capital of France is", AddBos::Always)
+            .unwrap();
+        let mut batch = LlamaBatch::new(tokens.len(), 1);
+        let last = tokens.len() - 1;
+        for (pos, &token) in tokens.iter().enumerate() {
+            batch.add(token, pos as i32, &[0], pos == last).unwrap();
+        }
+        ctx.decode(&mut batch).unwrap();
+
+        let mut sampler = LlamaSampler::greedy();
+        for pos in tokens.len()..tokens.len() + 4 {
+            let next = sampler.sample(&ctx, -1);
+            let logprobs = capture_logprobs(model, &ctx, next, 5);
+            assert_eq!(logprobs.top.len(), 5);
+            assert_eq!(logprobs.logprob, logprobs.top[0].logprob);
+            assert_eq!(logprobs.token, logprobs.top[0].tok);
+
+            batch.clear();
+            batch.add(next, pos as i32, &[0], true).unwrap();
+            ctx.decode(&mut batch).unwrap();
+        }
+    }
+}
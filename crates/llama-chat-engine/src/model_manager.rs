@@ -3,16 +3,46 @@ use llama_cpp_2::{
     llama_backend::LlamaBackend,
     model::{params::{LlamaModelParams, LlamaSplitMode}, LlamaModel},
 };
+use std::collections::HashMap;
 use std::fs;
 use std::io::BufReader;
 use std::sync::atomic::{AtomicU8, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
-use llama_chat_types::{LlamaState, ModelStatus, SharedLlamaState};
+use llama_chat_types::{
+    KvCacheTypes, LlamaState, LoadOutcome, LoadPhase, LoadedModelParams, ModelStatus,
+    RecommendedSampling, SharedLlamaState,
+};
 #[cfg(feature = "vision")]
 use llama_chat_types::VisionState;
+use super::gguf_utils::{detect_tool_format, value_to_string, value_to_u64};
+#[cfg(feature = "vision")]
+use super::io_timeout::with_models_io_timeout;
 // Re-export VRAM functions for backward compatibility (used by other modules)
 pub use super::vram_calculator::calculate_optimal_gpu_layers;
+use super::vram_calculator::{normalize_gpu_layers, KvCacheShape, ALL_GPU_LAYERS_SENTINEL};
+
+/// Copy of `LlamaState::tool_format` for tool dispatch, which runs on threads
+/// without access to the state. The process holds at most one model.
+static LOADED_TOOL_FORMAT: RwLock<Option<&'static str>> = RwLock::new(None);
+
+/// Native tool-call format of the loaded model (see `detect_tool_format`).
+pub fn loaded_tool_format() -> Option<&'static str> {
+    *LOADED_TOOL_FORMAT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Record the loaded model's tool format on the state and for tool dispatch.
+pub fn set_tool_format(state: &mut LlamaState, tool_format: Option<&'static str>) {
+    state.tool_format = tool_format;
+    *LOADED_TOOL_FORMAT
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = tool_format;
+}
+
+/// `detect_tool_format` as an Option: None for families without a native format.
+fn native_tool_format(architecture: &str, model_name: &str) -> Option<&'static str> {
+    Some(detect_tool_format(architecture, model_name)).filter(|&f| f != "unknown")
+}
 
 // Helper function to get model status
 pub fn get_model_status(llama_state: &SharedLlamaState) -> ModelStatus {
@@ -40,6 +70,8 @@ pub fn get_model_status(llama_state: &SharedLlamaState) -> ModelStatus {
                         has_vision: None,
                         tool_tags: None,
                         gpu_layers: state.gpu_layers,
+                        effective_backend: state.effective_backend.clone(),
+                        recommended_sampling: state.recommended_sampling,
                         block_count: None,
                         system_prompt_tokens: None,
                         tool_definitions_tokens: None,
@@ -61,6 +93,8 @@ pub fn get_model_status(llama_state: &SharedLlamaState) -> ModelStatus {
                     has_vision: None,
                     tool_tags: None,
                     gpu_layers: None,
+                    effective_backend: None,
+                    recommended_sampling: None,
                     block_count: None,
             system_prompt_tokens: None,
             tool_definitions_tokens: None,
@@ -83,6 +117,8 @@ pub fn get_model_status(llama_state: &SharedLlamaState) -> ModelStatus {
             has_vision: None,
             tool_tags: None,
             gpu_layers: None,
+            effective_backend: None,
+            recommended_sampling: None,
             block_count: None,
             system_prompt_tokens: None,
             tool_definitions_tokens: None,
@@ -101,6 +137,8 @@ pub struct ModelParams {
     pub use_mmap: bool,
     pub main_gpu: i32,
     pub split_mode: String,
+    /// KV cache types for contexts created on this model (unset = config's cache_type_k/v).
+    pub kv_cache_type: KvCacheTypes,
 }
 
 impl Default for ModelParams {
@@ -110,6 +148,7 @@ impl Default for ModelParams {
             use_mmap: true,
             main_gpu: 0,
             split_mode: "layer".to_string(),
+            kv_cache_type: KvCacheTypes::default(),
         }
     }
 }
@@ -134,10 +173,53 @@ extern "C" fn loading_progress_cb(progress: f32, user_data: *mut std::os::raw::c
     true // always continue loading
 }
 
-// Helper function to load a model
-pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, requested_gpu_layers: Option<u32>, model_params: Option<&ModelParams>, _mmproj_path: Option<&str>, progress: Option<Arc<AtomicU8>>) -> Result<(), String> {
+fn set_load_phase(phase_slot: &Option<Arc<AtomicU8>>, phase: LoadPhase) {
+    if let Some(slot) = phase_slot {
+        slot.store(phase.as_u8(), Ordering::Relaxed);
+    }
+}
+
+/// Why `load_model` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    /// The llama.cpp backend couldn't start, even on the CPU.
+    Backend(super::gpu_devices::BackendInitError),
+    /// Reading or loading the model itself failed.
+    Load(String),
+}
+
+impl std::fmt::Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Backend(e) => e.fmt(f),
+            Self::Load(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl From<String> for ModelError {
+    fn from(msg: String) -> Self {
+        Self::Load(msg)
+    }
+}
+
+impl From<ModelError> for String {
+    fn from(e: ModelError) -> Self {
+        e.to_string()
+    }
+}
+
+// Helper function to load a model.
+// `progress` receives the llama.cpp percent callback; `phase` receives coarse
+// LoadPhase markers, which advance even if the callback never fires.
+// Reloading the loaded model with the same weights-level settings only drops
+// the context (see `load_outcome_for`).
+pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, requested_gpu_layers: Option<u32>, model_params: Option<&ModelParams>, mmproj_path: Option<&str>, progress: Option<Arc<AtomicU8>>, phase: Option<Arc<AtomicU8>>) -> Result<LoadOutcome, ModelError> {
     log_debug!("system", "load_model called with path: {}", model_path);
 
+    // Running generations use the model without the state lock; wait for them
+    let _exclusive = super::context_pool::exclusive_model();
+
     // Handle poisoned mutex by recovering from panic
     let mut state_guard = llama_state.lock().unwrap_or_else(|poisoned| {
         log_debug!("system", "Mutex was poisoned, recovering...");
@@ -146,7 +228,17 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
 
     // Initialize backend if needed
     if state_guard.is_none() {
-        let backend = LlamaBackend::init().map_err(|e| format!("Failed to init backend: {e}"))?;
+        // A driver mismatch is reported, not retried here: llama.cpp only
+        // initializes once per process. The bridge respawns the worker with
+        // the GPUs hidden instead.
+        let backend = super::gpu_devices::init_backend(
+            super::gpu_devices::compiled_gpu_backend(),
+            LlamaBackend::init,
+        )
+        .map_err(|e| {
+            log_error!("system", "{}", e);
+            ModelError::Backend(e)
+        })?;
         #[cfg(feature = "dynamic-backends")]
         {
             log_info!("system", "Loading dynamic GPU backends...");
@@ -161,11 +253,21 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
             chat_template_type: None,
             chat_template_string: None,
             gpu_layers: None,
+            effective_backend: None,
             last_used: std::time::SystemTime::now(),
             general_name: None,
+            kv_cache_type: KvCacheTypes::default(),
+            loaded_params: None,
+            template_override: None,
+            recommended_sampling: None,
+            model_add_bos: None,
+            tool_format: None,
+            kv_elements_per_token: None,
+            kv_cache_vram_budget: None,
             cached_system_prompt: None,
             cached_prompt_key: None,
             inference_cache: None,
+            idle_contexts: Vec::new(),
             #[cfg(feature = "vision")]
             vision_state: None,
         });
@@ -175,40 +277,87 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
         .as_mut()
         .expect("Model state should be initialized");
 
-    // Note: we do NOT skip the reload if the same path is already loaded.
-    // The user may have changed structural settings (context size, KV cache type,
-    // GPU layers) that only take effect on reload. Always reload on explicit request.
-
-    // CRITICAL: Drop inference cache and vision state BEFORE dropping the model.
-    // Both borrow the model, so they must go first.
-    state.inference_cache = None;
-    #[cfg(feature = "vision")]
-    { state.vision_state = None; }
-    // Unload current model if any
-    state.model = None;
-    state.current_model_path = None;
+    set_load_phase(&phase, LoadPhase::ReadingMetadata);
 
     // Use requested GPU layers if provided, otherwise auto-calculate
-    let mut optimal_gpu_layers = requested_gpu_layers.unwrap_or_else(|| calculate_optimal_gpu_layers(model_path));
+    let requested_layers = requested_gpu_layers.unwrap_or_else(|| calculate_optimal_gpu_layers(model_path));
 
     // CRITICAL: Cap gpu_layers at model's actual layer count to avoid offloading
     // the output/embedding layer to GPU. ngl > n_layers causes llama_decode to hang
     // on Qwen3.5 (hybrid MoE+recurrent) with large context sizes.
-    if let Some(n_layers) = super::vram_calculator::read_gguf_block_count(model_path) {
-        if optimal_gpu_layers > n_layers {
+    let block_count = super::vram_calculator::read_gguf_block_count(model_path);
+    let optimal_gpu_layers = normalize_gpu_layers(requested_layers, block_count);
+    if optimal_gpu_layers != requested_layers {
+        if requested_layers >= ALL_GPU_LAYERS_SENTINEL {
+            log_info!(
+                "system",
+                "gpu_layers={} means all layers: resolved to {} (model block_count)",
+                requested_layers,
+                optimal_gpu_layers
+            );
+        } else {
             log_warn!(
                 "system",
                 "Capping gpu_layers from {} to {} (model block_count) to avoid output layer GPU offload hang",
-                optimal_gpu_layers,
-                n_layers
+                requested_layers,
+                optimal_gpu_layers
             );
-            optimal_gpu_layers = n_layers;
         }
     }
 
-    // Load new model with configured GPU acceleration and model params
+    // A GPU build on a machine without a usable device loads on the CPU instead
+    let (resolved_layers, effective_backend) = super::gpu_devices::resolve_load_backend(
+        optimal_gpu_layers,
+        super::gpu_devices::compiled_gpu_backend(),
+        || !super::gpu_devices::cpu_fallback_active() && !super::gpu_devices::enumerate_gpus().is_empty(),
+    );
+    if resolved_layers != optimal_gpu_layers {
+        log_warn!(
+            "system",
+            "No usable {} device found; loading on CPU instead of offloading {} layers",
+            super::gpu_devices::compiled_gpu_backend().unwrap_or("GPU"),
+            optimal_gpu_layers
+        );
+    }
+    let optimal_gpu_layers = resolved_layers;
+
     let defaults = ModelParams::default();
     let mp = model_params.unwrap_or(&defaults);
+    let requested = LoadedModelParams {
+        model_path: model_path.to_string(),
+        gpu_layers: optimal_gpu_layers,
+        mmproj_path: mmproj_path.map(str::to_string),
+        use_mlock: mp.use_mlock,
+        use_mmap: mp.use_mmap,
+        main_gpu: mp.main_gpu,
+        split_mode: mp.split_mode.clone(),
+    };
+
+    // Context size and KV cache type live in the context, not the weights:
+    // dropping the cached context is enough for the next one to pick them up.
+    let loaded = state.model.as_ref().and(state.loaded_params.as_ref());
+    if load_outcome_for(loaded, &requested) == LoadOutcome::ContextRebuild {
+        log_info!("system", "Model already loaded with the same settings; rebuilding context only");
+        state.inference_cache = None;
+        state.idle_contexts.clear();
+        state.kv_cache_type = mp.kv_cache_type;
+        state.last_used = std::time::SystemTime::now();
+        return Ok(LoadOutcome::ContextRebuild);
+    }
+
+    // CRITICAL: Drop inference cache and vision state BEFORE dropping the model.
+    // Both borrow the model, so they must go first.
+    state.inference_cache = None;
+    state.idle_contexts.clear();
+    #[cfg(feature = "vision")]
+    { state.vision_state = None; }
+    // Unload current model if any
+    state.model = None;
+    state.current_model_path = None;
+    state.loaded_params = None;
+    set_tool_format(state, None);
+
+    // Load new model with configured GPU acceleration and model params
     let mut llama_model_params = LlamaModelParams::default()
         .with_n_gpu_layers(optimal_gpu_layers)
         .with_use_mlock(mp.use_mlock)
@@ -241,13 +390,43 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
     if mp.split_mode != "layer" {
         log_info!("system", "Split mode: {}", mp.split_mode);
     }
+    if let Some(k) = mp.kv_cache_type.k {
+        log_info!("system", "KV cache type K: {}", k.as_str());
+    }
+    if let Some(v) = mp.kv_cache_type.v {
+        log_info!("system", "KV cache type V: {}", v.as_str());
+    }
 
+    // Measured before the weights take their share: what the offloaded layers
+    // leave of it is the KV cache's, and bounds the context size at generation.
+    let kv_cache_vram_budget = match (optimal_gpu_layers, block_count) {
+        (0, _) | (_, None) => None,
+        (layers, Some(blocks)) => super::context_pool::free_vram_bytes().map(|free| {
+            let file_bytes = fs::metadata(model_path).map(|m| m.len()).unwrap_or(0);
+            super::vram_calculator::kv_cache_budget_bytes(file_bytes, layers, blocks, free)
+        }),
+    };
+    if let Some(budget) = kv_cache_vram_budget {
+        log_info!(
+            "system",
+            "VRAM left for the KV cache after offloading {} layers: {:.2}GB",
+            optimal_gpu_layers,
+            budget as f64 / super::vram_calculator::BYTES_TO_GB
+        );
+    }
+
+    set_load_phase(&phase, LoadPhase::Allocating);
     let model = LlamaModel::load_from_file(&state.backend, model_path, &llama_model_params)
         .map_err(|e| format!("Failed to load model: {e}"))?;
 
     log_info!("system", "Model loaded successfully!");
 
-    // Read model's context length, token IDs, chat template, and general name from GGUF metadata
+    // Read model's context length, token IDs, chat template, general name and
+    // recommended sampling from GGUF metadata
+    let mut recommended_sampling = RecommendedSampling::default();
+    let mut model_add_bos = None;
+    let mut kv_elements_per_token = None;
+    let mut architecture = String::new();
     let (
         model_context_length,
         bos_token_id,
@@ -286,27 +465,7 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
                     .get("tokenizer.chat_template")
                     .map(|v| match v {
                         Value::String(s) => {
-                            let template_type = if s.contains("<|tool_call_start|>") {
-                                // LiquidAI LFM2/LFM2.5 — ChatML-style turns, but tool results go in a
-                                // `tool` role and tool calls use <|tool_call_start|> special tokens.
-                                "LFM2".to_string()
-                            } else if s.contains("<|im_start|>") && s.contains("<|im_end|>") {
-                                "ChatML".to_string() // Qwen, OpenAI format
-                            } else if s.contains("[INST]") && s.contains("[/INST]") {
-                                "Mistral".to_string() // Mistral format
-                            } else if s.contains("<|start_header_id|>") {
-                                "Llama3".to_string() // Llama 3 format
-                            } else if s.contains("<start_of_turn>") && s.contains("<end_of_turn>") {
-                                "Gemma".to_string() // Gemma 3 format
-                            } else if s.contains("<|start|>") && s.contains("<|end|>") && s.contains("<|channel|>") {
-                                "Harmony".to_string() // gpt-oss-20b Harmony format
-                            } else if s.contains("<|observation|>") && s.contains("<|user|>") && s.contains("<|assistant|>") {
-                                "GLM".to_string() // GLM-4 family (has <|observation|> role)
-                            } else if s.contains("<|system|>") && s.contains("<|user|>") && s.contains("<|assistant|>") && s.contains("<|end|>") {
-                                "Phi".to_string() // Phi-3/Phi-4 format
-                            } else {
-                                "Generic".to_string() // Fallback
-                            };
+                            let template_type = detect_chat_template_type(s).to_string();
                             (Some(template_type), Some(s.clone()))
                         }
                         _ => (None, None),
@@ -319,6 +478,17 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
                     _ => None,
                 });
 
+                recommended_sampling = recommended_sampling_from_metadata(&metadata);
+                model_add_bos = match metadata.get("tokenizer.ggml.add_bos_token") {
+                    Some(Value::Bool(b)) => Some(*b),
+                    _ => None,
+                };
+                if let Some(Value::String(arch)) = metadata.get("general.architecture") {
+                    architecture = arch.clone();
+                }
+                kv_elements_per_token =
+                    KvCacheShape::from_metadata(&metadata).map(|shape| shape.bytes_per_token(1.0));
+
                 (ctx_len, bos_id, eos_id, template_type, template_string, gen_name)
             } else {
                 (None, None, None, None, None, None)
@@ -357,6 +527,14 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
         }
     }
 
+    if !recommended_sampling.is_empty() {
+        log_info!(
+            "system",
+            "Model recommends sampling (used where the config has app defaults): {:?}",
+            recommended_sampling
+        );
+    }
+
     if let Some(ref template) = chat_template_type {
         log_info!("system", "Detected chat template type: {}", template);
     } else {
@@ -368,7 +546,7 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
 
     // Scan for mmproj companion file for vision support
     #[cfg(feature = "vision")]
-    let vision_state = scan_and_init_vision(&model, model_path, optimal_gpu_layers, _mmproj_path);
+    let vision_state = scan_and_init_vision(&model, model_path, optimal_gpu_layers, mmproj_path);
 
     state.model = Some(model);
     state.current_model_path = Some(model_path.to_string());
@@ -376,12 +554,31 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
     state.chat_template_type = chat_template_type;
     state.chat_template_string = chat_template_string;
     state.gpu_layers = Some(optimal_gpu_layers);
+    state.effective_backend = effective_backend.map(str::to_string);
     state.last_used = std::time::SystemTime::now();
     state.general_name = general_name.clone();
+    state.kv_cache_type = mp.kv_cache_type;
+    state.loaded_params = Some(requested);
+    // Template overrides are per-model; a new model starts from detection
+    state.template_override = None;
+    state.recommended_sampling = (!recommended_sampling.is_empty()).then_some(recommended_sampling);
+    state.model_add_bos = model_add_bos;
+    state.kv_elements_per_token = kv_elements_per_token;
+    state.kv_cache_vram_budget = kv_cache_vram_budget;
+    // Fall back to the file name when general.name is missing
+    let tool_format =
+        native_tool_format(&architecture, general_name.as_deref().unwrap_or(model_path));
+    set_tool_format(state, tool_format);
+    log_info!(
+        "system",
+        "Native tool-call format: {}",
+        tool_format.unwrap_or("unknown")
+    );
     // Invalidate caches (model changed)
     state.cached_system_prompt = None;
     state.cached_prompt_key = None;
     state.inference_cache = None;
+    state.idle_contexts.clear();
     #[cfg(feature = "vision")]
     { state.vision_state = vision_state; }
 
@@ -389,7 +586,62 @@ pub async fn load_model(llama_state: SharedLlamaState, model_path: &str, request
         log_info!("system", "Model general.name: {}", name);
     }
 
-    Ok(())
+    Ok(LoadOutcome::FullReload)
+}
+
+/// Sampler defaults from `general.sampling.*`. Floats go through their string
+/// form so an f32 0.6 reads as 0.6 rather than 0.6000000238.
+fn recommended_sampling_from_metadata(metadata: &HashMap<String, Value>) -> RecommendedSampling {
+    let float = |key: &str| {
+        metadata
+            .get(key)
+            .and_then(value_to_string)
+            .and_then(|s| s.trim().parse::<f64>().ok())
+    };
+    RecommendedSampling {
+        temperature: float("general.sampling.temp"),
+        top_p: float("general.sampling.top_p"),
+        top_k: metadata
+            .get("general.sampling.top_k")
+            .and_then(value_to_u64)
+            .and_then(|n| u32::try_from(n).ok()),
+        min_p: float("general.sampling.min_p"),
+        repetition_penalty: float("general.sampling.repetition_penalty"),
+    }
+}
+
+/// A load can skip reading the weights only when the same model is loaded with
+/// identical weights-level settings; anything else (e.g. new gpu_layers) reloads.
+fn load_outcome_for(loaded: Option<&LoadedModelParams>, requested: &LoadedModelParams) -> LoadOutcome {
+    if loaded == Some(requested) {
+        LoadOutcome::ContextRebuild
+    } else {
+        LoadOutcome::FullReload
+    }
+}
+
+/// Template type for each set of turn markers, checked in order: a chat
+/// template is of the first type whose markers it all contains.
+pub const CHAT_TEMPLATE_MARKERS: &[(&str, &[&str])] = &[
+    // LiquidAI LFM2/LFM2.5 — ChatML-style turns, but tool results go in a
+    // `tool` role and tool calls use <|tool_call_start|> special tokens.
+    ("LFM2", &["<|tool_call_start|>"]),
+    ("ChatML", &["<|im_start|>", "<|im_end|>"]), // Qwen, OpenAI format
+    ("Mistral", &["[INST]", "[/INST]"]),
+    ("Llama3", &["<|start_header_id|>"]),
+    ("Gemma", &["<start_of_turn>", "<end_of_turn>"]), // Gemma 3 format
+    ("Harmony", &["<|start|>", "<|end|>", "<|channel|>"]), // gpt-oss-20b
+    ("GLM", &["<|observation|>", "<|user|>", "<|assistant|>"]), // GLM-4 family
+    ("Phi", &["<|system|>", "<|user|>", "<|assistant|>", "<|end|>"]), // Phi-3/Phi-4
+];
+
+/// Classify a GGUF `tokenizer.chat_template` by its turn markers.
+/// Returns "Generic" when no known format matches.
+pub fn detect_chat_template_type(template: &str) -> &'static str {
+    CHAT_TEMPLATE_MARKERS
+        .iter()
+        .find(|(_, markers)| markers.iter().all(|m| template.contains(m)))
+        .map_or("Generic", |(template_type, _)| template_type)
 }
 
 #[cfg(feature = "vision")]
@@ -408,7 +660,13 @@ fn scan_and_init_vision(
 
     // Use user-specified mmproj path if provided, otherwise auto-detect
     let mmproj_str = if let Some(override_path) = mmproj_override {
-        if Path::new(override_path).exists() {
+        let override_owned = override_path.to_string();
+        let exists = with_models_io_timeout(move || Path::new(&override_owned).exists())
+            .unwrap_or_else(|e| {
+                log_warn!("system", "Checking mmproj {}: {}", override_path, e);
+                false
+            });
+        if exists {
             log_info!("system", "Using user-specified mmproj: {}", override_path);
             override_path.to_string()
         } else {
@@ -456,18 +714,27 @@ fn scan_and_init_vision(
 fn auto_detect_mmproj(model_path: &str) -> Option<String> {
     use std::path::Path;
 
-    let model_dir = Path::new(model_path).parent()?;
-    let mmproj_path = fs::read_dir(model_dir)
-        .ok()?
-        .filter_map(|entry| entry.ok())
-        .map(|e| e.path())
-        .find(|p| {
-            p.extension().map(|e| e == "gguf").unwrap_or(false)
-                && p.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| n.contains("mmproj"))
-                    .unwrap_or(false)
-        })?;
+    let model_dir = Path::new(model_path).parent()?.to_path_buf();
+    let scan = with_models_io_timeout(move || {
+        fs::read_dir(model_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|e| e.path())
+            .find(|p| {
+                p.extension().map(|e| e == "gguf").unwrap_or(false)
+                    && p.file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.contains("mmproj"))
+                        .unwrap_or(false)
+            })
+    });
+    let mmproj_path = match scan {
+        Ok(found) => found?,
+        Err(e) => {
+            log_warn!("system", "Skipping mmproj auto-detect: {}", e);
+            return None;
+        }
+    };
 
     let s = mmproj_path.to_string_lossy().to_string();
     log_info!("system", "Auto-detected mmproj file: {}", s);
@@ -475,3 +742,112 @@ fn auto_detect_mmproj(model_path: &str) -> Option<String> {
 }
 
 // Tests moved to vram_calculator.rs
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(gpu_layers: u32) -> LoadedModelParams {
+        LoadedModelParams {
+            model_path: "/models/a.gguf".to_string(),
+            gpu_layers,
+            mmproj_path: None,
+            use_mlock: false,
+            use_mmap: true,
+            main_gpu: 0,
+            split_mode: "layer".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_same_weights_settings_take_context_rebuild() {
+        // Context size / KV cache type are not part of the weights-level params
+        assert_eq!(
+            load_outcome_for(Some(&params(32)), &params(32)),
+            LoadOutcome::ContextRebuild
+        );
+    }
+
+    #[test]
+    fn test_weights_changes_force_full_reload() {
+        assert_eq!(load_outcome_for(Some(&params(32)), &params(16)), LoadOutcome::FullReload);
+        assert_eq!(load_outcome_for(None, &params(32)), LoadOutcome::FullReload);
+        let other_model = LoadedModelParams {
+            model_path: "/models/b.gguf".to_string(),
+            ..params(32)
+        };
+        assert_eq!(load_outcome_for(Some(&params(32)), &other_model), LoadOutcome::FullReload);
+        let mlock = LoadedModelParams {
+            use_mlock: true,
+            ..params(32)
+        };
+        assert_eq!(load_outcome_for(Some(&params(32)), &mlock), LoadOutcome::FullReload);
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_reload_outcomes_with_model() {
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let state: SharedLlamaState = Arc::new(std::sync::Mutex::new(None));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let load = |gpu_layers| {
+            rt.block_on(load_model(state.clone(), &model_path, Some(gpu_layers), None, None, None, None))
+                .unwrap()
+        };
+
+        assert_eq!(load(0), LoadOutcome::FullReload);
+        // Same layers (the context size comes from config at generation time)
+        assert_eq!(load(0), LoadOutcome::ContextRebuild);
+        assert!(state.lock().unwrap().as_ref().unwrap().model.is_some());
+        assert_eq!(load(1), LoadOutcome::FullReload);
+    }
+
+    #[test]
+    fn test_recommended_sampling_from_metadata() {
+        let metadata: HashMap<String, Value> = [
+            ("general.sampling.temp", Value::Float32(0.6)),
+            ("general.sampling.top_p", Value::Float32(0.95)),
+            ("general.sampling.top_k", Value::Int32(20)),
+            ("general.sampling.repetition_penalty", Value::Float64(1.05)),
+            ("general.name", Value::String("Qwen3".to_string())),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+
+        let sampling = recommended_sampling_from_metadata(&metadata);
+        assert_eq!(
+            sampling,
+            RecommendedSampling {
+                temperature: Some(0.6),
+                top_p: Some(0.95),
+                top_k: Some(20),
+                min_p: None,
+                repetition_penalty: Some(1.05),
+            }
+        );
+        assert!(recommended_sampling_from_metadata(&HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_recommended_sampling_sets_only_the_fields_it_names() {
+        let sampling = RecommendedSampling {
+            temperature: Some(0.6),
+            top_k: Some(40),
+            min_p: Some(0.05),
+            ..Default::default()
+        };
+        let mut config = llama_chat_types::SamplerConfig {
+            temperature: 1.1,
+            ..Default::default()
+        };
+        let applied = sampling.apply_to(&mut config);
+
+        assert_eq!(applied, ["temperature", "top_k", "min_p"]);
+        assert_eq!(config.temperature, 0.6);
+        assert_eq!(config.top_k, 40);
+        assert_eq!(config.min_p, 0.05);
+        assert_eq!(config.top_p, llama_chat_types::SamplerConfig::default().top_p);
+    }
+}
@@ -0,0 +1,224 @@
+// Pre-load model validation: read GGUF metadata and estimate VRAM without loading.
+// Used by /api/model/validate so the frontend can warn before a multi-GB load.
+
+use gguf_llms::Value;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::gguf_utils::{read_gguf_metadata_raw, MetadataExtractor};
+use super::model_manager::detect_chat_template_type;
+use super::vram_calculator::{
+    calculate_optimal_gpu_layers, get_available_vram_gb, normalize_gpu_layers, KvCacheShape,
+    BYTES_TO_GB, DEFAULT_VRAM_GB, F16_KV_BYTES, MB_TO_GB, VRAM_SAFETY_MARGIN_GB,
+};
+
+/// Context length used for the VRAM estimate when the caller doesn't specify one
+/// (capped at the model's own context length).
+pub const DEFAULT_VALIDATION_CONTEXT: u32 = 8192;
+
+/// Architectures that only produce embeddings and can't be used for chat.
+const EMBEDDING_ARCHITECTURES: &[&str] = &[
+    "bert",
+    "nomic-bert",
+    "nomic-bert-moe",
+    "jina-bert-v2",
+    "modern-bert",
+    "t5encoder",
+];
+
+/// Result of validating a model file before loading it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelValidation {
+    pub is_gguf: bool,
+    pub architecture: Option<String>,
+    pub template_type: Option<String>,
+    pub context_length: Option<u32>,
+    pub estimated_vram_mb: Option<u64>,
+    pub fits_in_vram: bool,
+    pub recommended_gpu_layers: u32,
+    pub warnings: Vec<String>,
+}
+
+impl ModelValidation {
+    /// A failed validation with `warning` as the reason.
+    pub fn not_gguf(warning: String) -> Self {
+        Self {
+            is_gguf: false,
+            architecture: None,
+            template_type: None,
+            context_length: None,
+            estimated_vram_mb: None,
+            fits_in_vram: false,
+            recommended_gpu_layers: 0,
+            warnings: vec![warning],
+        }
+    }
+}
+
+fn get_string(metadata: &HashMap<String, Value>, key: &str) -> Option<String> {
+    match metadata.get(key)? {
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Validate a model file without loading it.
+///
+/// `context_size` is the context the caller intends to load with (defaults to
+/// `DEFAULT_VALIDATION_CONTEXT`); `available_vram_gb` overrides VRAM detection.
+pub fn validate_model(
+    model_path: &str,
+    context_size: Option<u32>,
+    available_vram_gb: Option<f64>,
+) -> ModelValidation {
+    let file_size = match std::fs::metadata(model_path) {
+        Ok(m) if m.is_file() => m.len(),
+        Ok(_) => return ModelValidation::not_gguf("Path is not a file".to_string()),
+        Err(e) => return ModelValidation::not_gguf(format!("Cannot read model file: {e}")),
+    };
+
+    let metadata = match read_gguf_metadata_raw(model_path) {
+        Ok(m) => m,
+        Err(e) => return ModelValidation::not_gguf(format!("Not a GGUF model: {e}")),
+    };
+
+    let mut warnings = Vec::new();
+    let architecture = get_string(&metadata, "general.architecture");
+    let arch = architecture.as_deref().unwrap_or("llama");
+    let extractor = MetadataExtractor::new(&metadata);
+    let arch_u32 = |field: &str| extractor.get_u32(&format!("{arch}.{field}"));
+
+    if EMBEDDING_ARCHITECTURES.contains(&arch)
+        || metadata.contains_key(&format!("{arch}.pooling_type"))
+    {
+        warnings.push(format!(
+            "'{arch}' is an embedding-only model and cannot be used for chat"
+        ));
+    }
+
+    let template_type = get_string(&metadata, "tokenizer.chat_template")
+        .map(|t| detect_chat_template_type(&t).to_string());
+    match template_type.as_deref() {
+        None => warnings
+            .push("No chat template in GGUF metadata; the Mistral format will be used".to_string()),
+        Some("Generic") => warnings
+            .push("Unrecognized chat template; generic prompt formatting will be used".to_string()),
+        Some(_) => {}
+    }
+
+    let context_length = arch_u32("context_length");
+    let block_count = arch_u32("block_count");
+    let recommended_gpu_layers =
+        normalize_gpu_layers(calculate_optimal_gpu_layers(model_path), block_count);
+
+    let n_ctx = context_size
+        .unwrap_or(DEFAULT_VALIDATION_CONTEXT)
+        .min(context_length.unwrap_or(u32::MAX));
+    let kv_gb = match KvCacheShape::from_metadata(&metadata) {
+        Some(shape) => shape.kv_cache_bytes(n_ctx, F16_KV_BYTES) as f64 / BYTES_TO_GB,
+        None => {
+            warnings.push(
+                "Missing attention metadata; KV cache size not included in the estimate"
+                    .to_string(),
+            );
+            0.0
+        }
+    };
+
+    let estimated_gb = file_size as f64 / BYTES_TO_GB + kv_gb;
+    let available = available_vram_gb
+        .or_else(get_available_vram_gb)
+        .unwrap_or(DEFAULT_VRAM_GB);
+    let fits_in_vram = estimated_gb + VRAM_SAFETY_MARGIN_GB <= available;
+    if !fits_in_vram {
+        warnings.push(format!(
+            "Estimated {estimated_gb:.1} GB exceeds available VRAM ({available:.1} GB); layers will be split with CPU"
+        ));
+    }
+
+    ModelValidation {
+        is_gguf: true,
+        architecture,
+        template_type,
+        context_length,
+        estimated_vram_mb: Some((estimated_gb * MB_TO_GB).round() as u64),
+        fits_in_vram,
+        recommended_gpu_layers,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gguf_test_support::{write_test_gguf, TestValue};
+
+    #[test]
+    fn test_validate_compatible_model_stub() {
+        let path = write_test_gguf(
+            "validate-compatible",
+            &[
+                ("general.architecture", TestValue::Str("llama")),
+                ("llama.block_count", TestValue::U32(32)),
+                ("llama.context_length", TestValue::U32(8192)),
+                ("llama.embedding_length", TestValue::U32(4096)),
+                ("llama.attention.head_count", TestValue::U32(32)),
+                ("llama.attention.head_count_kv", TestValue::U32(8)),
+                (
+                    "tokenizer.chat_template",
+                    TestValue::Str("<|start_header_id|>user<|end_header_id|>"),
+                ),
+            ],
+        );
+
+        let result = validate_model(&path, Some(4096), Some(24.0));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(result.is_gguf);
+        assert_eq!(result.architecture.as_deref(), Some("llama"));
+        assert_eq!(result.template_type.as_deref(), Some("Llama3"));
+        assert_eq!(result.context_length, Some(8192));
+        assert!(result.fits_in_vram);
+        assert!(result.recommended_gpu_layers <= 32);
+        // 4096 ctx × 32 layers × 8 kv heads × 128 head_dim × 4 bytes = 512 MB
+        assert_eq!(result.estimated_vram_mb, Some(512));
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn test_validate_flags_embedding_model_and_missing_template() {
+        let path = write_test_gguf(
+            "validate-embedding",
+            &[
+                ("general.architecture", TestValue::Str("nomic-bert")),
+                ("nomic-bert.block_count", TestValue::U32(12)),
+                ("nomic-bert.pooling_type", TestValue::U32(1)),
+            ],
+        );
+
+        let result = validate_model(&path, None, Some(24.0));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(result.is_gguf);
+        assert!(result.template_type.is_none());
+        assert!(result.warnings.iter().any(|w| w.contains("embedding-only")));
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("No chat template")));
+    }
+
+    #[test]
+    fn test_validate_non_gguf_file() {
+        let path =
+            std::env::temp_dir().join(format!("validate-not-gguf-{}.gguf", std::process::id()));
+        std::fs::write(&path, b"definitely not a gguf file").unwrap();
+
+        let result = validate_model(&path.to_string_lossy(), None, Some(24.0));
+        let _ = std::fs::remove_file(&path);
+
+        assert!(!result.is_gguf);
+        assert!(!result.fits_in_vram);
+        assert_eq!(result.warnings.len(), 1);
+    }
+}
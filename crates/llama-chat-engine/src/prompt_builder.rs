@@ -2,14 +2,13 @@
 use llama_cpp_2::{
     context::LlamaContext,
     llama_batch::LlamaBatch,
-    model::AddBos,
 };
 use std::num::NonZeroU32;
 use std::time::Instant;
 
 use llama_chat_types::*;
-use super::context_eval::{build_context_params, CONTEXT_SIZE};
-use super::templates::apply_system_prompt_by_type_with_tags;
+use super::context_eval::{apply_kv_cache_override, build_context_params, resolve_context_size};
+use super::templates::{apply_system_prompt_by_type_with_tags, resolve_template, templated_add_bos};
 use super::tool_tags::{default_tags, derive_tool_tags_from_pairs, get_tool_tags_for_model, try_get_tool_tags_for_model, ToolTags};
 /// Special conversation ID for warmup cache (system prompt pre-evaluation).
 pub const WARMUP_CONVERSATION_ID: &str = "__warmup__";
@@ -27,7 +26,7 @@ pub fn warmup_system_prompt(
     use llama_chat_config::{load_config, db_config_to_sampler_config};
     use crate::config_ext::get_resolved_system_prompt;
 
-    let config = if let Some(id) = agent_id {
+    let mut config = if let Some(id) = agent_id {
         db_config_to_sampler_config(&db.load_config_for_agent(id))
     } else {
         load_config(db)
@@ -47,19 +46,24 @@ pub fn warmup_system_prompt(
         .map_err(|_| "Failed to lock LLaMA state")?;
     let state = state_guard.as_mut().ok_or("LLaMA state not initialized")?;
     let model = state.model.as_ref().ok_or("No model loaded")?;
+    apply_kv_cache_override(&mut config, state.kv_cache_type);
 
-    let context_size = config.context_size.unwrap_or_else(|| {
-        state
-            .model_context_length
-            .map(|ctx| ctx.min(CONTEXT_SIZE))
-            .unwrap_or(CONTEXT_SIZE)
-    });
+    let (context_size, _) = resolve_context_size(
+        config.context_size,
+        state.model_context_length,
+        config.default_context_cap,
+    );
 
     // Build a minimal conversation with just the system prompt
     let conversation_content = format!("SYSTEM:\n{system_prompt}\n\n");
 
-    let template_type = state.chat_template_type.clone();
-    let chat_template_string = state.chat_template_string.clone();
+    let (template_type, chat_template_string) = resolve_template(
+        state.chat_template_type.as_deref(),
+        state.chat_template_string.as_deref(),
+        state.template_override.as_deref(),
+    );
+    let template_type = template_type.map(str::to_string);
+    let chat_template_string = chat_template_string.map(str::to_string);
     let general_name = state.general_name.clone();
 
     let tags = get_tool_tags_for_model(general_name.as_deref()).with_overrides(
@@ -89,13 +93,21 @@ pub fn warmup_system_prompt(
         &bos_text,
         &eos_text,
         None,
+        config.enabled_tools.as_deref(),
+        config.tools_enabled,
         false,
         None,
     )?;
 
-    // Tokenize
+    // Tokenize with generation's BOS handling so the cached prefix matches
+    let add_bos = templated_add_bos(
+        None,
+        template_type.as_deref(),
+        chat_template_string.as_deref(),
+        state.model_add_bos,
+    );
     let tokens = model
-        .str_to_token(&prompt, AddBos::Never)
+        .str_to_token(&prompt, add_bos)
         .map_err(|e| format!("Warmup tokenization failed: {e}"))?;
 
     if tokens.is_empty() {
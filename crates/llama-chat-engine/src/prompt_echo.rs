@@ -0,0 +1,68 @@
+//! The `echo_prompt` request option.
+//!
+//! Sends the client one event, ahead of any tokens, saying what the
+//! generation was set up with: the resolved system prompt, the template type
+//! and the tool names. It exposes the system prompt, so it only works when the
+//! server runs with `LLAMA_CHAT_DEBUG=1`. The rendered prompt itself stays in
+//! the conversation log.
+
+use llama_chat_types::{PromptEcho, TokenData};
+
+/// The leading event for a request, or None unless it asked for `echo_prompt`
+/// and debugging is on. `tools_json` is the OpenAI-style tool list.
+pub(crate) fn prompt_echo_event(
+    echo_prompt: Option<bool>,
+    debug: bool,
+    system_prompt: String,
+    template_type: Option<&str>,
+    tools_json: &str,
+) -> Option<TokenData> {
+    if !(echo_prompt == Some(true) && debug) {
+        return None;
+    }
+    Some(TokenData {
+        prompt_echo: Some(PromptEcho {
+            system_prompt,
+            template_type: template_type.map(str::to_string),
+            tools: tool_names(tools_json),
+        }),
+        ..Default::default()
+    })
+}
+
+fn tool_names(tools_json: &str) -> Vec<String> {
+    let tools: Vec<serde_json::Value> = serde_json::from_str(tools_json).unwrap_or_default();
+    tools
+        .iter()
+        .filter_map(|tool| tool["function"]["name"].as_str().map(str::to_string))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOOLS: &str = r#"[{"type":"function","function":{"name":"read_file","parameters":{}}},
+        {"type":"function","function":{"name":"list_directory","parameters":{}}}]"#;
+
+    #[test]
+    fn test_echo_reports_the_system_prompt_template_and_tools() {
+        let event = prompt_echo_event(Some(true), true, "Be brief.".to_string(), Some("ChatML"), TOOLS).unwrap();
+        assert!(event.token.is_empty());
+        let echo = event.prompt_echo.expect("prompt echo");
+        assert_eq!(echo.system_prompt, "Be brief.");
+        assert_eq!(echo.template_type.as_deref(), Some("ChatML"));
+        assert_eq!(echo.tools, ["read_file", "list_directory"]);
+    }
+
+    #[test]
+    fn test_no_echo_unless_requested_and_debugging() {
+        for (echo_prompt, debug) in [(None, true), (Some(false), true), (Some(true), false)] {
+            let event = prompt_echo_event(echo_prompt, debug, String::new(), None, "");
+            assert!(event.is_none(), "{echo_prompt:?} {debug}");
+        }
+        // Tools off: no tool list
+        let event = prompt_echo_event(Some(true), true, String::new(), None, "").unwrap();
+        assert!(event.prompt_echo.unwrap().tools.is_empty());
+    }
+}
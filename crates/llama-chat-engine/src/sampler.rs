@@ -17,6 +17,60 @@ fn resolve_seed(config: &SamplerConfig) -> u32 {
     }
 }
 
+/// Temperatures at or below this are treated as 0 (deterministic).
+const GREEDY_TEMPERATURE_EPSILON: f32 = 1e-3;
+
+/// Every `sampler_type` `create_sampler` builds a distinct chain for; any other
+/// value gets the greedy chain.
+pub const SAMPLER_TYPES: &[&str] = &[
+    "Greedy",
+    "Temperature",
+    "Mirostat",
+    "TopP",
+    "TopK",
+    "Typical",
+    "MinP",
+    "TempExt",
+    "ChainTempTopP",
+    "ChainTempTopK",
+    "ChainFull",
+];
+
+/// Sampler types whose chain includes a temperature stage.
+const TEMPERATURE_SAMPLERS: &[&str] = &[
+    "Temperature",
+    "TempExt",
+    "ChainTempTopP",
+    "ChainTempTopK",
+    "ChainFull",
+];
+
+/// Clamp the configured temperature into `[temperature_min, temperature_max]`.
+/// Returns None when the result is effectively 0, i.e. sampling should be greedy.
+fn effective_temperature(config: &SamplerConfig) -> Option<f32> {
+    let min = config.temperature_min.max(0.0);
+    let max = config.temperature_max.max(min);
+    let temperature = if config.temperature.is_nan() {
+        min
+    } else {
+        config.temperature.clamp(min, max)
+    } as f32;
+    (temperature > GREEDY_TEMPERATURE_EPSILON).then_some(temperature)
+}
+
+/// Pick the sampler type actually built and the temperature it uses.
+/// A temperature sampler at temperature ~0 degenerates to greedy, so use greedy directly.
+fn resolve_sampler_type(config: &SamplerConfig) -> (&str, f32) {
+    let sampler_type = config.sampler_type.as_str();
+    if !TEMPERATURE_SAMPLERS.contains(&sampler_type) {
+        return (sampler_type, config.temperature as f32);
+    }
+    match effective_temperature(config) {
+        Some(temperature) => (sampler_type, temperature),
+        None => ("Greedy", 0.0),
+    }
+}
+
 /// Create a sampler based on the configuration.
 ///
 /// `model` is needed only for the DRY sampler; pass `None` if unavailable.
@@ -91,18 +145,39 @@ pub(crate) fn create_sampler(
         let _ = _model;
     }
 
-    match config.sampler_type.as_str() {
+    let (sampler_type, temperature) = resolve_sampler_type(config);
+    if sampler_type != config.sampler_type {
+        log_info!(
+            conversation_id,
+            "{} sampler with temperature {} — switching to Greedy",
+            config.sampler_type,
+            config.temperature
+        );
+    } else if TEMPERATURE_SAMPLERS.contains(&sampler_type)
+        && temperature != config.temperature as f32
+    {
+        log_info!(
+            conversation_id,
+            "Temperature {} clamped to {} (allowed range [{}, {}])",
+            config.temperature,
+            temperature,
+            config.temperature_min,
+            config.temperature_max
+        );
+    }
+
+    match sampler_type {
         "Temperature" => {
             log_info!(
                 conversation_id,
                 "Using Temperature sampler: temp={}, top_p={}, top_k={}",
-                config.temperature, config.top_p, config.top_k
+                temperature, config.top_p, config.top_k
             );
             let mut s: Vec<LlamaSampler> = Vec::new();
             if use_penalties { push_penalties(&mut s, config); }
             push_dry(&mut s, config, model);
             push_top_n_sigma(&mut s, config);
-            s.push(LlamaSampler::temp(config.temperature as f32));
+            s.push(LlamaSampler::temp(temperature));
             s.push(LlamaSampler::top_k(config.top_k as i32));
             s.push(LlamaSampler::top_p(config.top_p as f32, 1));
             if config.min_p > 0.0 {
@@ -181,14 +256,14 @@ pub(crate) fn create_sampler(
             log_info!(
                 conversation_id,
                 "Using TempExt (dynamic temperature) sampler: temp={}",
-                config.temperature
+                temperature
             );
             let mut s: Vec<LlamaSampler> = Vec::new();
             if use_penalties { push_penalties(&mut s, config); }
             push_dry(&mut s, config, model);
             push_top_n_sigma(&mut s, config);
             // temp_ext(t, delta, exponent) — delta/exponent not yet exposed in UI
-            s.push(LlamaSampler::temp_ext(config.temperature as f32, 0.0, 1.0));
+            s.push(LlamaSampler::temp_ext(temperature, 0.0, 1.0));
             push_tool_grammar(&mut s, model);
             s.push(LlamaSampler::dist(seed));
             LlamaSampler::chain(s, true)
@@ -198,13 +273,13 @@ pub(crate) fn create_sampler(
             log_info!(
                 conversation_id,
                 "Using ChainTempTopP: temp={}, top_p={}",
-                config.temperature, config.top_p
+                temperature, config.top_p
             );
             let mut s: Vec<LlamaSampler> = Vec::new();
             if use_penalties { push_penalties(&mut s, config); }
             push_dry(&mut s, config, model);
             push_top_n_sigma(&mut s, config);
-            s.push(LlamaSampler::temp(config.temperature as f32));
+            s.push(LlamaSampler::temp(temperature));
             s.push(LlamaSampler::top_p(config.top_p as f32, 1));
             push_tool_grammar(&mut s, model);
             s.push(LlamaSampler::dist(seed));
@@ -215,13 +290,13 @@ pub(crate) fn create_sampler(
             log_info!(
                 conversation_id,
                 "Using ChainTempTopK: temp={}, top_k={}",
-                config.temperature, config.top_k
+                temperature, config.top_k
             );
             let mut s: Vec<LlamaSampler> = Vec::new();
             if use_penalties { push_penalties(&mut s, config); }
             push_dry(&mut s, config, model);
             push_top_n_sigma(&mut s, config);
-            s.push(LlamaSampler::temp(config.temperature as f32));
+            s.push(LlamaSampler::temp(temperature));
             s.push(LlamaSampler::top_k(config.top_k as i32));
             push_tool_grammar(&mut s, model);
             s.push(LlamaSampler::dist(seed));
@@ -232,13 +307,13 @@ pub(crate) fn create_sampler(
             log_info!(
                 conversation_id,
                 "Using ChainFull: temp={}, top_k={}, top_p={}, min_p={}, typical_p={}",
-                config.temperature, config.top_k, config.top_p, config.min_p, config.typical_p
+                temperature, config.top_k, config.top_p, config.min_p, config.typical_p
             );
             let mut s: Vec<LlamaSampler> = Vec::new();
             if use_penalties { push_penalties(&mut s, config); }
             push_dry(&mut s, config, model);
             push_top_n_sigma(&mut s, config);
-            s.push(LlamaSampler::temp(config.temperature as f32));
+            s.push(LlamaSampler::temp(temperature));
             s.push(LlamaSampler::top_k(config.top_k as i32));
             s.push(LlamaSampler::top_p(config.top_p as f32, 1));
             if config.min_p > 0.0 {
@@ -267,3 +342,49 @@ pub(crate) fn create_sampler(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(sampler_type: &str, temperature: f64) -> SamplerConfig {
+        SamplerConfig {
+            sampler_type: sampler_type.to_string(),
+            temperature,
+            ..SamplerConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_zero_temperature_uses_greedy() {
+        for sampler_type in TEMPERATURE_SAMPLERS {
+            assert_eq!(resolve_sampler_type(&config(sampler_type, 0.0)), ("Greedy", 0.0));
+            assert_eq!(resolve_sampler_type(&config(sampler_type, 0.0001)).0, "Greedy");
+        }
+        // A floor above zero keeps temperature sampling
+        let floored = SamplerConfig {
+            temperature_min: 0.2,
+            ..config("Temperature", 0.0)
+        };
+        assert_eq!(resolve_sampler_type(&floored), ("Temperature", 0.2));
+    }
+
+    #[test]
+    fn test_out_of_range_temperature_is_clamped() {
+        assert_eq!(resolve_sampler_type(&config("ChainFull", 5.0)), ("ChainFull", 2.0));
+        assert_eq!(resolve_sampler_type(&config("Temperature", -1.0)).0, "Greedy");
+        assert_eq!(resolve_sampler_type(&config("Temperature", 0.7)), ("Temperature", 0.7));
+
+        let narrow = SamplerConfig {
+            temperature_max: 1.0,
+            ..config("ChainTempTopK", 1.3)
+        };
+        assert_eq!(resolve_sampler_type(&narrow), ("ChainTempTopK", 1.0));
+    }
+
+    #[test]
+    fn test_non_temperature_samplers_unchanged() {
+        assert_eq!(resolve_sampler_type(&config("TopP", 0.0)).0, "TopP");
+        assert_eq!(resolve_sampler_type(&config("Mirostat", 9.0)).0, "Mirostat");
+    }
+}
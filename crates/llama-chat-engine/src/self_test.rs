@@ -0,0 +1,148 @@
+//! Pipeline self-test: tokenize → decode → sample a few tokens on the loaded model.
+//!
+//! Catches silent GPU/driver breakage that a process health check misses. Runs
+//! on a disposable context, so the inference cache is untouched.
+
+use std::num::NonZeroU32;
+use std::time::Instant;
+
+use llama_chat_types::{SamplerConfig, SharedLlamaState};
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::AddBos;
+use llama_cpp_2::sampling::LlamaSampler;
+use serde::Serialize;
+
+use super::context_eval::create_fresh_context;
+
+/// Fixed prompt; raw text so the result doesn't depend on the chat template.
+const SELF_TEST_PROMPT: &str = "The capital of France is";
+const SELF_TEST_MAX_TOKENS: usize = 8;
+const SELF_TEST_CTX_SIZE: u32 = 512;
+
+/// Outcome of a successful self-test run.
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub ok: bool,
+    /// Text of the generated tokens (greedy, so stable for a given model).
+    pub first_tokens: String,
+    pub tokens_generated: usize,
+    pub gen_tok_per_sec: Option<f64>,
+}
+
+/// Run the self-test against the loaded model. Errors name the failing stage.
+pub fn run_self_test(llama_state: &SharedLlamaState) -> Result<SelfTestReport, String> {
+    let mut state_guard = llama_state
+        .lock()
+        .map_err(|_| "Failed to lock LLaMA state")?;
+    let state = state_guard.as_mut().ok_or("No model loaded")?;
+    let model = state.model.as_ref().ok_or("No model loaded")?;
+
+    let tokens = model
+        .str_to_token(SELF_TEST_PROMPT, AddBos::Always)
+        .map_err(|e| format!("Self-test tokenization failed: {e}"))?;
+    if tokens.is_empty() {
+        return Err("Self-test tokenization produced no tokens".to_string());
+    }
+
+    let n_ctx = NonZeroU32::new(SELF_TEST_CTX_SIZE).unwrap();
+    let offload_kqv = state.gpu_layers.unwrap_or(0) > 0;
+    let mut ctx = create_fresh_context(
+        model,
+        &state.backend,
+        n_ctx,
+        offload_kqv,
+        &SamplerConfig::default(),
+    )?;
+
+    let mut batch = LlamaBatch::new(tokens.len().max(1), 1);
+    let last = tokens.len() - 1;
+    for (pos, &token) in tokens.iter().enumerate() {
+        batch
+            .add(token, pos as i32, &[0], pos == last)
+            .map_err(|e| format!("Self-test batch add failed: {e}"))?;
+    }
+    ctx.decode(&mut batch)
+        .map_err(|e| format!("Self-test prompt decode failed: {e}"))?;
+
+    let mut sampler = LlamaSampler::greedy();
+    let eos_token = model.token_eos();
+    let mut first_tokens = String::new();
+    let mut generated = 0usize;
+    let gen_start = Instant::now();
+
+    for i in 0..SELF_TEST_MAX_TOKENS {
+        let next_token = sampler.sample(&ctx, -1);
+        if next_token == eos_token {
+            break;
+        }
+        #[allow(deprecated)]
+        let piece = model
+            .token_to_str(next_token, llama_cpp_2::model::Special::Tokenize)
+            .unwrap_or_default();
+        first_tokens.push_str(&piece);
+        generated += 1;
+
+        batch.clear();
+        batch
+            .add(next_token, (tokens.len() + i) as i32, &[0], true)
+            .map_err(|e| format!("Self-test batch add failed: {e}"))?;
+        ctx.decode(&mut batch)
+            .map_err(|e| format!("Self-test decode failed: {e}"))?;
+    }
+    let elapsed = gen_start.elapsed().as_secs_f64();
+    drop(ctx);
+    drop(state_guard);
+
+    if generated == 0 {
+        return Err(
+            "Self-test sampled end-of-generation immediately — no tokens produced".to_string(),
+        );
+    }
+    Ok(SelfTestReport {
+        ok: true,
+        first_tokens,
+        tokens_generated: generated,
+        gen_tok_per_sec: if elapsed > 0.0 {
+            Some(generated as f64 / elapsed)
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_self_test_without_model_errors() {
+        let state: SharedLlamaState = Arc::new(Mutex::new(None));
+        assert_eq!(run_self_test(&state).unwrap_err(), "No model loaded");
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_self_test_with_model_returns_tokens() {
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let state: SharedLlamaState = Arc::new(Mutex::new(None));
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(crate::load_model(
+                state.clone(),
+                &model_path,
+                Some(0),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        let report = run_self_test(&state).unwrap();
+        assert!(report.ok);
+        assert!(!report.first_tokens.is_empty());
+        assert!(report.tokens_generated > 0);
+    }
+}
@@ -0,0 +1,285 @@
+//! Save and restore the cached inference context via llama.cpp session files.
+//!
+//! A session file holds the KV cache and the evaluated tokens; a JSON sidecar
+//! (`<path>.json`) records what the context was created with, so a restore
+//! rebuilds a matching context and refuses files from a different model.
+
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use llama_chat_types::{InferenceCache, SamplerConfig, SharedLlamaState};
+use serde::{Deserialize, Serialize};
+
+use super::context_eval::create_fresh_context;
+
+/// Context parameters and ownership of a saved session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionMeta {
+    pub model_path: String,
+    pub conversation_id: String,
+    pub context_size: u32,
+    pub offload_kqv: bool,
+    pub flash_attention: bool,
+    pub cache_type_k: String,
+    pub cache_type_v: String,
+    /// Tokens in the KV cache (the restored token position).
+    pub n_tokens: usize,
+}
+
+/// Location of the automatic (shutdown) session for `model_path`, next to the
+/// database. Keyed by model so pooled workers don't overwrite each other.
+pub fn auto_session_path(db_path: &str, model_path: &str) -> PathBuf {
+    let stem: String = Path::new(model_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Path::new(db_path).with_file_name(format!("last_session_{stem}.bin"))
+}
+
+fn meta_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Read a session's sidecar without touching the model.
+pub fn read_session_meta(path: &Path) -> Result<SessionMeta, String> {
+    let text = std::fs::read_to_string(meta_path(path))
+        .map_err(|e| format!("Failed to read session metadata: {e}"))?;
+    serde_json::from_str(&text).map_err(|e| format!("Invalid session metadata: {e}"))
+}
+
+/// Remove a session file and its sidecar (missing files are fine).
+pub fn remove_session(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(meta_path(path));
+}
+
+/// Write the cached context to `path`.
+pub fn save_session(llama_state: &SharedLlamaState, path: &Path) -> Result<SessionMeta, String> {
+    let state_guard = llama_state
+        .lock()
+        .map_err(|_| "Failed to lock LLaMA state")?;
+    let state = state_guard.as_ref().ok_or("No model loaded")?;
+    let model_path = state.current_model_path.clone().ok_or("No model loaded")?;
+    let cache = state
+        .inference_cache
+        .as_ref()
+        .ok_or("No cached context to save")?;
+
+    cache
+        .context
+        .save_session_file(path, &cache.evaluated_tokens)
+        .map_err(|e| format!("Failed to save session: {e}"))?;
+
+    let meta = SessionMeta {
+        model_path,
+        conversation_id: cache.conversation_id.clone(),
+        context_size: cache.context_size,
+        offload_kqv: cache.offload_kqv,
+        flash_attention: cache.flash_attention,
+        cache_type_k: cache.cache_type_k.clone(),
+        cache_type_v: cache.cache_type_v.clone(),
+        n_tokens: cache.evaluated_tokens.len(),
+    };
+    let json = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    if let Err(e) = std::fs::write(meta_path(path), json) {
+        let _ = std::fs::remove_file(path);
+        return Err(format!("Failed to write session metadata: {e}"));
+    }
+    eprintln!(
+        "[WORKER] Saved session: {} tokens ({}) to {}",
+        meta.n_tokens,
+        meta.conversation_id,
+        path.display()
+    );
+    Ok(meta)
+}
+
+/// Restore a session saved by [`save_session`] into the inference cache.
+/// The loaded model must be the one the session was saved with.
+pub fn load_session(llama_state: &SharedLlamaState, path: &Path) -> Result<SessionMeta, String> {
+    let meta = read_session_meta(path)?;
+
+    let mut state_guard = llama_state
+        .lock()
+        .map_err(|_| "Failed to lock LLaMA state")?;
+    let state = state_guard.as_mut().ok_or("No model loaded")?;
+    if state.current_model_path.as_deref() != Some(meta.model_path.as_str()) {
+        return Err(format!(
+            "Session was saved with a different model ({})",
+            meta.model_path
+        ));
+    }
+    let model = state.model.as_ref().ok_or("No model loaded")?;
+
+    let n_ctx = NonZeroU32::new(meta.context_size).ok_or("Session has zero context size")?;
+    let config = SamplerConfig {
+        flash_attention: meta.flash_attention,
+        cache_type_k: meta.cache_type_k.clone(),
+        cache_type_v: meta.cache_type_v.clone(),
+        ..SamplerConfig::default()
+    };
+    // Drop the old context first so two KV caches are never allocated at once
+    state.inference_cache = None;
+    let mut context =
+        create_fresh_context(model, &state.backend, n_ctx, meta.offload_kqv, &config)?;
+    let tokens = context
+        .load_session_file(path, meta.context_size as usize)
+        .map_err(|e| format!("Failed to load session: {e}"))?;
+    if tokens.len() != meta.n_tokens {
+        return Err(format!(
+            "Session file holds {} tokens, metadata says {}",
+            tokens.len(),
+            meta.n_tokens
+        ));
+    }
+
+    eprintln!(
+        "[WORKER] Restored session: {} tokens ({}) from {}",
+        tokens.len(),
+        meta.conversation_id,
+        path.display()
+    );
+    state.inference_cache = Some(InferenceCache {
+        context,
+        conversation_id: meta.conversation_id.clone(),
+        evaluated_tokens: tokens,
+        context_size: meta.context_size,
+        offload_kqv: meta.offload_kqv,
+        flash_attention: meta.flash_attention,
+        cache_type_k: meta.cache_type_k.clone(),
+        cache_type_v: meta.cache_type_v.clone(),
+    });
+    Ok(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_session_commands_without_model_error() {
+        let state: SharedLlamaState = Arc::new(Mutex::new(None));
+        let dir = std::env::temp_dir().join(format!("llama-session-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("s.bin");
+
+        assert_eq!(save_session(&state, &path).unwrap_err(), "No model loaded");
+        assert!(load_session(&state, &path)
+            .unwrap_err()
+            .contains("Failed to read session metadata"));
+
+        let meta = SessionMeta {
+            model_path: "/models/a.gguf".to_string(),
+            conversation_id: "chat_1".to_string(),
+            context_size: 4096,
+            offload_kqv: false,
+            flash_attention: false,
+            cache_type_k: "f16".to_string(),
+            cache_type_v: "f16".to_string(),
+            n_tokens: 42,
+        };
+        std::fs::write(meta_path(&path), serde_json::to_string(&meta).unwrap()).unwrap();
+        assert_eq!(read_session_meta(&path).unwrap(), meta);
+        assert_eq!(load_session(&state, &path).unwrap_err(), "No model loaded");
+
+        remove_session(&path);
+        assert!(!meta_path(&path).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_auto_session_path_sits_next_to_db() {
+        assert_eq!(
+            auto_session_path(
+                "/data/llama_chat.db",
+                "/models/Qwen3 8B/qwen3-8b.Q4_K_M.gguf"
+            ),
+            PathBuf::from("/data/last_session_qwen3-8b.Q4_K_M.bin")
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_save_then_load_restores_token_position() {
+        use llama_cpp_2::llama_batch::LlamaBatch;
+        use llama_cpp_2::model::AddBos;
+
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let state: SharedLlamaState = Arc::new(Mutex::new(None));
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(crate::load_model(
+                state.clone(),
+                &model_path,
+                Some(0),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        let config = SamplerConfig::default();
+        let tokens = {
+            let mut guard = state.lock().unwrap();
+            let loaded = guard.as_mut().unwrap();
+            let model = loaded.model.as_ref().unwrap();
+            let mut ctx = create_fresh_context(
+                model,
+                &loaded.backend,
+                NonZeroU32::new(512).unwrap(),
+                false,
+                &config,
+            )
+            .unwrap();
+            let tokens = model
+                .str_to_token("The capital of France is", AddBos::Always)
+                .unwrap();
+            let mut batch = LlamaBatch::new(tokens.len(), 1);
+            let last = tokens.len() - 1;
+            for (pos, &token) in tokens.iter().enumerate() {
+                batch.add(token, pos as i32, &[0], pos == last).unwrap();
+            }
+            ctx.decode(&mut batch).unwrap();
+            loaded.inference_cache = Some(InferenceCache {
+                context: ctx,
+                conversation_id: "chat_session_test".to_string(),
+                evaluated_tokens: tokens.clone(),
+                context_size: 512,
+                offload_kqv: false,
+                flash_attention: config.flash_attention,
+                cache_type_k: config.cache_type_k.clone(),
+                cache_type_v: config.cache_type_v.clone(),
+            });
+            tokens
+        };
+
+        let path = std::env::temp_dir().join(format!("llama-session-{}.bin", uuid::Uuid::new_v4()));
+        let saved = save_session(&state, &path).unwrap();
+        assert_eq!(saved.n_tokens, tokens.len());
+
+        state.lock().unwrap().as_mut().unwrap().inference_cache = None;
+        let loaded = load_session(&state, &path).unwrap();
+        assert_eq!(loaded, saved);
+
+        let guard = state.lock().unwrap();
+        let cache = guard.as_ref().unwrap().inference_cache.as_ref().unwrap();
+        assert_eq!(cache.evaluated_tokens, tokens);
+        assert_eq!(cache.conversation_id, "chat_session_test");
+        drop(guard);
+        remove_session(&path);
+    }
+}
@@ -276,6 +276,65 @@ pub fn check_stop_conditions(
     StopConditionResult::no_stop()
 }
 
+/// A request's own `stop` sequences: trimmed of empty entries and duplicates.
+pub fn request_stop_sequences(stop: Option<&[String]>) -> Vec<String> {
+    let mut stops: Vec<String> = Vec::new();
+    for s in stop.unwrap_or_default() {
+        if !s.is_empty() && !stops.contains(s) {
+            stops.push(s.clone());
+        }
+    }
+    stops
+}
+
+/// A request `stop` sequence completed by `new_token`, and how many bytes of
+/// `response + new_token` come before it (the text to keep).
+///
+/// Unlike `check_stop_conditions`, only a full match counts: these are
+/// arbitrary delimiters, so a matching prefix is ordinary text.
+pub fn find_request_stop<'a>(
+    response: &str,
+    new_token: &str,
+    stops: &'a [String],
+    in_exec_block: bool,
+) -> Option<(&'a str, usize)> {
+    if in_exec_block || new_token.is_empty() {
+        return None;
+    }
+    stops
+        .iter()
+        .filter_map(|stop| {
+            // Only the tail a match ending in `new_token` can start in
+            let mut start = response.len().saturating_sub(stop.len().saturating_sub(1));
+            while !response.is_char_boundary(start) {
+                start -= 1;
+            }
+            let window = format!("{}{}", &response[start..], new_token);
+            window.find(stop.as_str()).map(|i| (stop.as_str(), start + i))
+        })
+        .min_by_key(|&(_, keep)| keep)
+}
+
+/// How many trailing bytes of `response` could still grow into a request
+/// `stop` sequence: the longest suffix that is a proper prefix of one. The
+/// stream holds these back so a delimiter that completes later never reaches
+/// the client.
+pub fn request_stop_holdback(response: &str, stops: &[String], in_exec_block: bool) -> usize {
+    if in_exec_block {
+        return 0;
+    }
+    stops
+        .iter()
+        .filter_map(|stop| {
+            (1..stop.len())
+                .rev()
+                .filter(|&k| stop.is_char_boundary(k))
+                .find(|&k| response.ends_with(&stop[..k]))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -314,6 +373,47 @@ mod tests {
         assert!(result.matched_token.is_none());
     }
 
+    #[test]
+    fn test_request_stop_halts_at_the_delimiter() {
+        let overrides = llama_chat_types::GenerationOverrides {
+            stop: Some(vec!["".to_string(), "###".to_string(), "###".to_string()]),
+            ..Default::default()
+        };
+        let stops = request_stop_sequences(overrides.stop.as_deref());
+        assert_eq!(stops, ["###"]);
+        // The configured stops are untouched
+        let mut config = llama_chat_types::SamplerConfig::default();
+        let configured = config.stop_tokens.clone();
+        overrides.apply_to(&mut config);
+        assert_eq!(config.stop_tokens, configured);
+
+        // Streamed token by token; the delimiter arrives split across tokens
+        let mut response = String::new();
+        let mut halted = None;
+        for token in ["name: Ada", "\n#", "##", "\nname: Bob"] {
+            if let Some((stop, keep)) = find_request_stop(&response, token, &stops, false) {
+                response = format!("{response}{token}")[..keep].to_string();
+                halted = Some(stop);
+                break;
+            }
+            response.push_str(token);
+        }
+        assert_eq!(halted, Some("###"));
+        assert_eq!(response, "name: Ada\n");
+
+        // A prefix alone is not a match; nor is anything inside a tool call
+        assert!(find_request_stop("name: Ada\n#", "#", &stops, false).is_none());
+        assert!(find_request_stop("a", "###", &stops, true).is_none());
+    }
+
+    #[test]
+    fn test_request_stop_inside_a_token() {
+        let stops = vec!["END".to_string(), "STOP".to_string()];
+        assert_eq!(find_request_stop("x", "yESTOPzEND", &stops, false), Some(("STOP", 3)));
+        // Multi-byte text before the match
+        assert_eq!(find_request_stop("héé", "EN", &["éEN".to_string()], false), Some(("éEN", 3)));
+    }
+
     #[test]
     fn test_outside_exec_block() {
         let stop_tokens = vec!["</ASSISTANT>".to_string()];
@@ -325,4 +425,15 @@ mod tests {
         assert!(result.should_stop);
         assert_eq!(result.matched_token.as_deref(), Some("</ASSISTANT>"));
     }
+
+    #[test]
+    fn test_request_stop_holdback_keeps_a_possible_prefix() {
+        let stops = vec!["###".to_string(), "END".to_string()];
+        assert_eq!(request_stop_holdback("Answer #", &stops, false), 1);
+        assert_eq!(request_stop_holdback("Answer ##", &stops, false), 2);
+        assert_eq!(request_stop_holdback("THE EN", &stops, false), 2);
+        assert_eq!(request_stop_holdback("Answer", &stops, false), 0);
+        assert_eq!(request_stop_holdback("Answer #", &stops, true), 0);
+        assert_eq!(request_stop_holdback("Answer #", &[], false), 0);
+    }
 }
@@ -7,6 +7,7 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 
 use super::command_executor::{check_and_execute_command_with_tags, inject_output_tokens};
+use super::duplicate_calls::ExecutedCalls;
 use super::generation::create_fresh_context;
 use super::tool_tags::ToolTags;
 use llama_chat_types::*;
@@ -175,6 +176,7 @@ pub fn run_sub_agent(
     let mut last_exec_scan_pos = 0usize;
     let mut recent_commands: Vec<String> = Vec::new();
     let mut consecutive_loop_blocks: usize = 0;
+    let mut executed_calls = ExecutedCalls::new(db.load_config().dedupe_tool_calls);
     let cancel = Arc::new(AtomicBool::new(false));
     let mut tool_calls_executed = 0u32;
     const MAX_AGENT_TOOL_CALLS: u32 = 20;
@@ -214,7 +216,8 @@ pub fn run_sub_agent(
             if let Ok(Some(exec_result)) = check_and_execute_command_with_tags(
                 &response, last_exec_scan_pos, conversation_id, model, tags,
                 None, // template_type
-                &mut recent_commands, &mut consecutive_loop_blocks, token_sender, token_pos,
+                &mut recent_commands, &mut consecutive_loop_blocks, &mut executed_calls,
+                token_sender, token_pos,
                 AGENT_CTX_SIZE, Some(cancel.clone()),
                 use_htmd, browser_backend,
                 mcp_manager.clone(), db.clone(),
@@ -3,7 +3,6 @@ use llama_chat_tools::McpToolDefInfo as McpToolDef;
 use super::system_prompts::get_universal_system_prompt_with_tags;
 
 /// Apply chat template formatting to conversation history (uses default tags).
-#[cfg(test)]
 pub fn apply_model_chat_template(
     conversation: &str,
     template_type: Option<&str>,
@@ -7,40 +7,46 @@ mod tests;
 // Re-export public API (preserves callers)
 pub use system_prompts::{
     get_behavioral_system_prompt,
+    get_plain_system_prompt,
     get_universal_system_prompt_with_tags,
 };
-pub use chat_templates::apply_model_chat_template_with_tags;
-
-#[cfg(test)]
-pub use chat_templates::apply_model_chat_template;
+pub use chat_templates::{apply_model_chat_template, apply_model_chat_template_with_tags};
 
+use crate::context_overflow::apply_overflow_policy;
 use crate::jinja_templates::{
     apply_native_chat_template, get_available_tools_openai_with_mcp, parse_conversation_for_jinja,
 };
 use crate::tool_tags::ToolTags;
 use llama_chat_tools::McpToolDefInfo as McpToolDef;
+use llama_chat_types::{OverflowPolicy, OverflowReport, ReasoningTags};
+use llama_cpp_2::model::AddBos;
 
 /// Try to render a prompt using the model's native Jinja2 chat template.
+/// With `tools_enabled` false the template gets no tools.
+#[allow(clippy::too_many_arguments)]
 fn try_jinja_render(
     template_str: &str,
     conversation: &str,
     bos_token: &str,
     eos_token: &str,
     mcp_tools: Option<&[McpToolDef]>,
+    enabled_tools: Option<&[String]>,
+    tools_enabled: bool,
     enable_thinking: bool,
     custom_system_prompt: Option<&str>,
 ) -> Result<String, String> {
     let system_prompt = match custom_system_prompt {
         Some(custom) => custom.to_string(),
-        None => get_behavioral_system_prompt(),
+        None if tools_enabled => get_behavioral_system_prompt(),
+        None => get_plain_system_prompt(),
     };
     let messages = parse_conversation_for_jinja(conversation, &system_prompt);
-    let tools = get_available_tools_openai_with_mcp(mcp_tools);
+    let tools = tools_enabled.then(|| get_available_tools_openai_with_mcp(mcp_tools, enabled_tools));
 
     apply_native_chat_template(
         template_str,
         messages,
-        Some(tools),
+        tools,
         None,
         true,
         bos_token,
@@ -49,6 +55,183 @@ fn try_jinja_render(
     )
 }
 
+/// The system prompt `apply_system_prompt_by_type_with_tags` renders with: the
+/// custom prompt if set, else the default for the path taken (`jinja` when the
+/// model's own template is used) with tools on or off.
+pub fn resolved_system_prompt(
+    jinja: bool,
+    tags: &ToolTags,
+    tools_enabled: bool,
+    custom_system_prompt: Option<&str>,
+) -> String {
+    match custom_system_prompt {
+        Some(custom) => custom.to_string(),
+        None if !tools_enabled => get_plain_system_prompt(),
+        None if jinja => get_behavioral_system_prompt(),
+        None => get_universal_system_prompt_with_tags(tags),
+    }
+}
+
+/// Turn delimiters across the supported templates, plus the internal
+/// `ROLE:` headers. A prefill containing any of them could open a new turn.
+const ROLE_MARKERS: &[&str] = &[
+    "<|im_start|>", "<|im_end|>", "<|start_header_id|>", "<|end_header_id|>", "<|eot_id|>",
+    "<start_of_turn>", "<end_of_turn>", "[INST]", "[/INST]", "<|user|>", "<|assistant|>",
+    "<|system|>", "<|end|>", "<|start|>", "<|endoftext|>", "</s>", "<|startoftext|>",
+];
+
+/// Reject an `assistant_prefill` that contains role or turn markers.
+pub fn validate_assistant_prefill(prefill: &str) -> Result<(), String> {
+    if let Some(marker) = ROLE_MARKERS.iter().find(|m| prefill.contains(*m)) {
+        return Err(format!("assistant_prefill must not contain role tags (found {marker})"));
+    }
+    if let Some(header) = prefill
+        .lines()
+        .map(str::trim_end)
+        .find(|l| matches!(*l, "SYSTEM:" | "USER:" | "ASSISTANT:"))
+    {
+        return Err(format!("assistant_prefill must not contain role tags (found {header})"));
+    }
+    Ok(())
+}
+
+/// Append `prefill` to a rendered prompt, which always ends with the assistant-turn
+/// opener, so the model continues from it. When the template opens the reasoning
+/// block itself, that block is closed first so the prefill lands in the answer.
+pub fn apply_assistant_prefill(
+    prompt: String,
+    prefill: Option<&str>,
+    reasoning_tags: Option<&ReasoningTags>,
+) -> String {
+    match prefill {
+        Some(prefill) if !prefill.is_empty() => {
+            match reasoning_tags.filter(|tags| prompt.trim_end().ends_with(&tags.open)) {
+                Some(tags) => format!("{prompt}{}\n\n{prefill}", tags.close),
+                None => prompt + prefill,
+            }
+        }
+        _ => prompt,
+    }
+}
+
+/// What a templated prompt is rendered with besides the conversation itself.
+pub struct PromptTemplate<'a> {
+    pub template_type: Option<&'a str>,
+    pub chat_template_string: Option<&'a str>,
+    pub tags: &'a ToolTags,
+    pub bos_text: &'a str,
+    pub eos_text: &'a str,
+    pub mcp_tools: Option<&'a [McpToolDef]>,
+    pub enabled_tools: Option<&'a [String]>,
+    pub tools_enabled: bool,
+    pub enable_thinking: bool,
+    pub custom_system_prompt: Option<&'a str>,
+    pub assistant_prefill: Option<&'a str>,
+    pub reasoning_tags: Option<&'a ReasoningTags>,
+}
+
+impl PromptTemplate<'_> {
+    /// Render "ROLE:\n..." conversation text with the template, tools and prefill.
+    pub fn render(&self, conversation: &str) -> Result<String, String> {
+        apply_system_prompt_by_type_with_tags(
+            conversation,
+            self.template_type,
+            self.chat_template_string,
+            self.tags,
+            self.bos_text,
+            self.eos_text,
+            self.mcp_tools,
+            self.enabled_tools,
+            self.tools_enabled,
+            self.enable_thinking,
+            self.custom_system_prompt,
+        )
+        .map(|prompt| apply_assistant_prefill(prompt, self.assistant_prefill, self.reasoning_tags))
+    }
+}
+
+/// The prompt a request evaluates. A `raw_completion` is the user's text as is:
+/// no template, tools, prefill or overflow trimming. Otherwise `conversation` is
+/// trimmed by `on_overflow`, with `fits` measuring each rendered candidate, and
+/// rendered through `template`.
+pub fn assemble_prompt(
+    raw_completion: bool,
+    user_message: &str,
+    conversation: &str,
+    on_overflow: OverflowPolicy,
+    template: &PromptTemplate<'_>,
+    mut fits: impl FnMut(&str) -> Result<bool, String>,
+) -> Result<(String, OverflowReport), String> {
+    if raw_completion {
+        return Ok((user_message.to_string(), OverflowReport { policy: on_overflow, trimmed_messages: 0 }));
+    }
+    let (conversation, overflow) = apply_overflow_policy(on_overflow, conversation, |content| {
+        fits(&template.render(content)?)
+    })?;
+    Ok((template.render(&conversation)?, overflow))
+}
+
+/// BOS handling for a `raw_completion` prompt, which bypasses the template (and
+/// with it the template's own BOS). On unless the request sets `add_bos: false`.
+pub fn raw_completion_add_bos(add_bos: Option<bool>) -> AddBos {
+    if add_bos.unwrap_or(true) {
+        AddBos::Always
+    } else {
+        AddBos::Never
+    }
+}
+
+/// Hardcoded templates that write the BOS text themselves.
+const BOS_WRITING_TEMPLATES: &[&str] = &["Mistral", "Llama3"];
+
+/// BOS handling for a templated prompt. A Jinja template that renders
+/// `bos_token`, or a hardcoded template that writes it, already starts with
+/// BOS; otherwise the model's `tokenizer.ggml.add_bos_token` decides (off when
+/// absent). The request's `add_bos` wins over both.
+pub fn templated_add_bos(
+    add_bos: Option<bool>,
+    template_type: Option<&str>,
+    chat_template: Option<&str>,
+    model_add_bos: Option<bool>,
+) -> AddBos {
+    let template_writes_bos = match chat_template {
+        Some(template) => template.contains("bos_token"),
+        None => template_type.is_some_and(|t| BOS_WRITING_TEMPLATES.contains(&t)),
+    };
+    let default = !template_writes_bos && model_add_bos.unwrap_or(false);
+    if add_bos.unwrap_or(default) {
+        AddBos::Always
+    } else {
+        AddBos::Never
+    }
+}
+
+/// Template names accepted as a `template_override`, one per hardcoded branch
+/// in `apply_model_chat_template_with_tags`.
+pub const SUPPORTED_TEMPLATES: &[&str] = &[
+    "ChatML", "LFM2", "Mistral", "Llama3", "Gemma", "Phi", "GLM", "Generic",
+];
+
+/// Whether `name` is one of `SUPPORTED_TEMPLATES`.
+pub fn is_supported_template(name: &str) -> bool {
+    SUPPORTED_TEMPLATES.contains(&name)
+}
+
+/// Pick the template type and Jinja string to render with.
+///
+/// A user override wins over the detected type and also skips the model's
+/// Jinja template, so the chosen hardcoded format is actually used.
+pub fn resolve_template<'a>(
+    detected_type: Option<&'a str>,
+    chat_template_string: Option<&'a str>,
+    template_override: Option<&'a str>,
+) -> (Option<&'a str>, Option<&'a str>) {
+    match template_override {
+        Some(name) => (Some(name), None),
+        None => (detected_type, chat_template_string),
+    }
+}
+
 /// Apply system prompt with model-specific tool tags.
 ///
 /// Primary path: render using the model's native Jinja2 chat template.
@@ -56,7 +239,9 @@ fn try_jinja_render(
 ///
 /// `custom_system_prompt`: when `Some`, overrides the default agentic system prompt
 /// (e.g. from an agent's configured `system_prompt`). `None` uses the universal
-/// agentic prompt.
+/// agentic prompt. `enabled_tools` limits the native tools the template lists;
+/// `tools_enabled` false leaves out tool definitions (native and MCP) entirely and
+/// swaps the agentic prompt for a plain chat one.
 #[allow(clippy::too_many_arguments)]
 pub fn apply_system_prompt_by_type_with_tags(
     conversation: &str,
@@ -66,12 +251,14 @@ pub fn apply_system_prompt_by_type_with_tags(
     bos_token: &str,
     eos_token: &str,
     mcp_tools: Option<&[McpToolDef]>,
+    enabled_tools: Option<&[String]>,
+    tools_enabled: bool,
     enable_thinking: bool,
     custom_system_prompt: Option<&str>,
 ) -> Result<String, String> {
     if let Some(template_str) = chat_template_string {
         sys_info!("Trying Jinja template rendering (primary path, template len={})", template_str.len());
-        match try_jinja_render(template_str, conversation, bos_token, eos_token, mcp_tools, enable_thinking, custom_system_prompt) {
+        match try_jinja_render(template_str, conversation, bos_token, eos_token, mcp_tools, enabled_tools, tools_enabled, enable_thinking, custom_system_prompt) {
             Ok(prompt) => {
                 sys_info!("Jinja template rendered successfully ({} chars)", prompt.len());
                 return Ok(prompt);
@@ -84,5 +271,11 @@ pub fn apply_system_prompt_by_type_with_tags(
         sys_info!("No Jinja template available, using hardcoded path");
     }
     sys_info!("Using hardcoded template (type={:?})", template_type);
+    if !tools_enabled {
+        let system_prompt = custom_system_prompt
+            .map(str::to_string)
+            .unwrap_or_else(get_plain_system_prompt);
+        return apply_model_chat_template_with_tags(conversation, template_type, tags, None, Some(&system_prompt));
+    }
     apply_model_chat_template_with_tags(conversation, template_type, tags, mcp_tools, custom_system_prompt)
 }
@@ -105,6 +105,14 @@ pub fn get_behavioral_system_prompt() -> String {
     )
 }
 
+/// System prompt for plain chat (`tools_enabled` off): no tool instructions.
+pub fn get_plain_system_prompt() -> String {
+    format!(
+        "You are a helpful AI assistant.\n\nCurrent date: {}",
+        current_datetime_string()
+    )
+}
+
 /// Get the universal system prompt using model-specific tool tags.
 pub fn get_universal_system_prompt_with_tags(tags: &ToolTags) -> String {
     let (os_name, _cwd, shell) = env_block();
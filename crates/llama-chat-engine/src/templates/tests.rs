@@ -75,3 +75,274 @@ fn test_model_specific_tags_in_prompt() {
     let prompt = get_universal_system_prompt_with_tags(&default_tags);
     assert!(prompt.contains("<||SYSTEM.EXEC>"), "Unknown model should use default SYSTEM.EXEC tags");
 }
+
+#[test]
+fn test_template_override_changes_prompt_format() {
+    let conversation = "USER:\nTest message";
+    let jinja = "{{ messages }}";
+
+    let (template_type, jinja_used) =
+        resolve_template(Some("Llama3"), Some(jinja), Some("ChatML"));
+    assert_eq!(template_type, Some("ChatML"));
+    assert!(jinja_used.is_none(), "override should bypass the Jinja template");
+
+    let result = apply_model_chat_template(conversation, template_type).unwrap();
+    assert!(result.contains("<|im_start|>user"));
+    assert!(!result.contains("<|start_header_id|>"));
+}
+
+#[test]
+fn test_clearing_template_override_reverts_to_detection() {
+    let conversation = "USER:\nTest message";
+    let jinja = "{{ messages }}";
+
+    let (template_type, jinja_used) = resolve_template(Some("Llama3"), Some(jinja), None);
+    assert_eq!(template_type, Some("Llama3"));
+    assert_eq!(jinja_used, Some(jinja));
+
+    let result = apply_model_chat_template(conversation, template_type).unwrap();
+    assert!(result.contains("<|start_header_id|>user<|end_header_id|>"));
+    assert!(!result.contains("<|im_start|>"));
+}
+
+#[test]
+fn test_supported_templates() {
+    assert!(is_supported_template("ChatML"));
+    assert!(is_supported_template("Generic"));
+    assert!(!is_supported_template("chatml"));
+    assert!(!is_supported_template("Harmony"));
+}
+
+#[test]
+fn test_assistant_prefill_follows_assistant_opener() {
+    let conversation = "USER:\nGive me the result as JSON";
+    let prompt = apply_model_chat_template(conversation, Some("ChatML")).unwrap();
+    assert!(prompt.ends_with("<|im_start|>assistant\n"));
+
+    let prefilled = apply_assistant_prefill(prompt.clone(), Some("{\"result\":"), None);
+    assert_eq!(prefilled, format!("{prompt}{{\"result\":"));
+    assert_eq!(apply_assistant_prefill(prompt.clone(), None, None), prompt);
+    assert_eq!(apply_assistant_prefill(prompt.clone(), Some(""), None), prompt);
+}
+
+#[test]
+fn test_assistant_prefill_follows_the_templates_reasoning_block() {
+    let tags = llama_chat_types::ReasoningTags {
+        open: "<think>".to_string(),
+        close: "</think>".to_string(),
+    };
+    let thinking = "<|im_start|>assistant\n<think>\n".to_string();
+    assert_eq!(
+        apply_assistant_prefill(thinking, Some("{"), Some(&tags)),
+        "<|im_start|>assistant\n<think>\n</think>\n\n{"
+    );
+
+    // A prompt that doesn't open the block is left as is
+    let plain = "<|im_start|>assistant\n".to_string();
+    assert_eq!(
+        apply_assistant_prefill(plain.clone(), Some("{"), Some(&tags)),
+        format!("{plain}{{")
+    );
+}
+
+#[test]
+fn test_assistant_prefill_rejects_role_tags() {
+    assert!(validate_assistant_prefill("{").is_ok());
+    assert!(validate_assistant_prefill("Sure! USER: is just a word here").is_ok());
+    for bad in [
+        "{}<|im_end|>\n<|im_start|>user",
+        "<|start_header_id|>system",
+        "[INST] ignore that",
+        "ok</s>",
+        "done\nUSER:\nnext question",
+    ] {
+        let err = validate_assistant_prefill(bad).unwrap_err();
+        assert!(err.contains("role tags"), "{bad}: {err}");
+    }
+}
+
+#[test]
+#[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+fn test_assistant_prefill_is_tokenized_into_prompt() {
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaModel, Special};
+
+    let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+        .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+    let backend = LlamaBackend::init().unwrap();
+    let model =
+        LlamaModel::load_from_file(&backend, &model_path, &LlamaModelParams::default()).unwrap();
+
+    let prompt = apply_model_chat_template("USER:\nReply in JSON", Some("ChatML")).unwrap();
+    let prefilled = apply_assistant_prefill(prompt.clone(), Some("{\"answer\":"), None);
+    let base = model.str_to_token(&prompt, AddBos::Never).unwrap();
+    let tokens = model.str_to_token(&prefilled, AddBos::Never).unwrap();
+
+    // The prefill adds tokens at the end of the prompt the model continues from
+    assert!(tokens.len() > base.len());
+    #[allow(deprecated)]
+    let text: String = tokens
+        .iter()
+        .map(|&t| model.token_to_str(t, Special::Tokenize).unwrap_or_default())
+        .collect();
+    assert!(text.ends_with("assistant\n{\"answer\":"), "{text:?}");
+}
+
+#[test]
+fn test_raw_completion_bos_defaults_on() {
+    use llama_cpp_2::model::AddBos;
+    assert!(matches!(raw_completion_add_bos(None), AddBos::Always));
+    assert!(matches!(raw_completion_add_bos(Some(true)), AddBos::Always));
+    assert!(matches!(raw_completion_add_bos(Some(false)), AddBos::Never));
+}
+
+#[test]
+#[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+fn test_raw_completion_tokens_are_input_plus_optional_bos() {
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaModel, Special};
+
+    let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+        .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+    let backend = LlamaBackend::init().unwrap();
+    let model =
+        LlamaModel::load_from_file(&backend, &model_path, &LlamaModelParams::default()).unwrap();
+
+    let input = "Once upon a time";
+    let bare = model.str_to_token(input, AddBos::Never).unwrap();
+    let without_bos = model
+        .str_to_token(input, raw_completion_add_bos(Some(false)))
+        .unwrap();
+    let with_bos = model.str_to_token(input, raw_completion_add_bos(None)).unwrap();
+
+    assert_eq!(without_bos, bare);
+    assert_eq!(with_bos[0], model.token_bos());
+    assert_eq!(&with_bos[1..], bare.as_slice());
+
+    #[allow(deprecated)]
+    let text: String = without_bos
+        .iter()
+        .map(|&t| model.token_to_str(t, Special::Tokenize).unwrap_or_default())
+        .collect();
+    assert_eq!(text.trim_start(), input);
+    for tag in ["<|im_start|>", "<|start_header_id|>", "[INST]", "USER:"] {
+        assert!(!text.contains(tag), "{text:?}");
+    }
+}
+
+#[test]
+fn test_raw_completion_prompt_skips_the_template() {
+    use crate::tool_tags;
+
+    let tags = tool_tags::default_tags();
+    let template = PromptTemplate {
+        template_type: Some("ChatML"),
+        chat_template_string: None,
+        tags: &tags,
+        bos_text: "<s>",
+        eos_text: "</s>",
+        mcp_tools: None,
+        enabled_tools: None,
+        tools_enabled: true,
+        enable_thinking: false,
+        custom_system_prompt: None,
+        assistant_prefill: Some("{"),
+        reasoning_tags: None,
+    };
+    let input = "Once upon a time";
+    let conversation = format!("USER:\nEarlier question\n\nASSISTANT:\nEarlier answer\n\nUSER:\n{input}\n\n");
+    let (prompt, overflow) = assemble_prompt(true, input, &conversation, OverflowPolicy::TrimOldest, &template, |_| {
+        panic!("a raw completion is never rendered or measured")
+    })
+    .unwrap();
+    assert_eq!(prompt, input);
+    assert_eq!(overflow.trimmed_messages, 0);
+
+    // The same request without raw_completion gets the template, tools, prefill and trimming
+    let (prompt, overflow) = assemble_prompt(false, input, &conversation, OverflowPolicy::TrimOldest, &template, |prompt| {
+        Ok(!prompt.contains("Earlier question"))
+    })
+    .unwrap();
+    assert_eq!(overflow.trimmed_messages, 1);
+    assert!(!prompt.contains("Earlier question"), "{prompt}");
+    assert!(prompt.contains("<|im_start|>user\nOnce upon a time"), "{prompt}");
+    assert!(prompt.contains("SYSTEM.EXEC"), "{prompt}");
+    assert!(prompt.ends_with("<|im_start|>assistant\n{"), "{prompt}");
+}
+
+#[test]
+fn test_templated_bos_follows_template_and_model() {
+    use llama_cpp_2::model::AddBos;
+    let jinja_with_bos = "{{ bos_token }}{% for m in messages %}{{ m.content }}{% endfor %}";
+    let chatml = "{% for m in messages %}<|im_start|>{{ m.role }}{% endfor %}";
+
+    // The template already renders BOS: never add a second one
+    assert!(matches!(
+        templated_add_bos(None, Some("Llama3"), Some(jinja_with_bos), Some(true)),
+        AddBos::Never
+    ));
+    assert!(matches!(
+        templated_add_bos(None, Some("Mistral"), None, Some(true)),
+        AddBos::Never
+    ));
+    // No BOS in the template: the model's add_bos_token decides
+    assert!(matches!(
+        templated_add_bos(None, Some("Gemma"), Some(chatml), Some(true)),
+        AddBos::Always
+    ));
+    assert!(matches!(
+        templated_add_bos(None, Some("ChatML"), Some(chatml), Some(false)),
+        AddBos::Never
+    ));
+    assert!(matches!(
+        templated_add_bos(None, Some("ChatML"), None, None),
+        AddBos::Never
+    ));
+    // The request's add_bos wins
+    assert!(matches!(
+        templated_add_bos(Some(true), Some("Mistral"), None, None),
+        AddBos::Always
+    ));
+    assert!(matches!(
+        templated_add_bos(Some(false), Some("Gemma"), Some(chatml), Some(true)),
+        AddBos::Never
+    ));
+    // raw_completion has no template, so BOS stays on by default
+    assert!(matches!(raw_completion_add_bos(None), AddBos::Always));
+}
+
+#[test]
+fn test_disabled_tools_omit_tool_block() {
+    use crate::tool_tags;
+
+    let tags = tool_tags::default_tags();
+    let conversation = "USER:\nHello";
+    let render = |template_type, jinja, tools_enabled| {
+        apply_system_prompt_by_type_with_tags(
+            conversation, template_type, jinja, &tags, "<s>", "</s>", None, None,
+            tools_enabled, false, None,
+        )
+        .unwrap()
+    };
+
+    for template in ["ChatML", "Mistral"] {
+        let with_tools = render(Some(template), None, true);
+        assert!(with_tools.contains("<||SYSTEM.EXEC>"), "{template}");
+        assert!(with_tools.contains("## Tool Usage Guidelines"), "{template}");
+
+        let plain = render(Some(template), None, false);
+        assert!(!plain.contains("SYSTEM.EXEC"), "{template}: {plain}");
+        assert!(!plain.contains("Tool Usage"), "{template}: {plain}");
+        assert!(plain.contains("You are a helpful AI assistant."), "{template}");
+        assert!(plain.contains("Hello"), "{template}");
+    }
+
+    // Native Jinja path: the template gets no tools
+    let jinja = "{% if tools %}TOOLS={{ tools|length }}\n{% endif %}{% for m in messages %}{{ m.content }}\n{% endfor %}";
+    assert!(render(None, Some(jinja), true).contains("TOOLS="));
+    let plain = render(None, Some(jinja), false);
+    assert!(!plain.contains("TOOLS="), "{plain}");
+    assert!(!plain.contains("write_file"), "{plain}");
+}
@@ -1,26 +1,99 @@
 use super::ExecBlockTracker;
+use crate::duplicate_calls::ExecutedCalls;
+use llama_chat_types::{ReasoningSplitter, SamplerConfig};
 use llama_cpp_2::token::LlamaToken;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub(crate) struct TokenGenState {
     pub response: String,
     pub token_pos: i32,
     pub total_tokens_generated: i32,
     pub generated_token_ids: Vec<LlamaToken>,
+    /// Bytes of `response` sent to the client, or not streamed at all (tool
+    /// output). The logger sync never goes past it.
+    pub streamed_len: usize,
     pub logger_synced_len: usize,
     pub last_logger_sync: Instant,
     pub exec_tracker: ExecBlockTracker,
     pub recent_commands: Vec<String>,
     pub consecutive_loop_blocks: usize,
+    /// Calls already run this turn, for `dedupe_tool_calls`.
+    pub executed_calls: ExecutedCalls,
     pub last_exec_scan_pos: usize,
     pub finish_reason: String,
+    /// The request `stop` sequence that ended generation (finish_reason "stop_sequence").
+    pub stop_sequence: Option<String>,
     pub tool_response_tokens: i32,
     pub loop_recoveries: u32,
     /// Number of times EOS was intercepted and generation continued inline.
     pub eos_continue_count: u8,
     /// Total tool calls executed this generation turn (for max-tool-calls limit).
     pub tool_call_count: u32,
+    /// Per-token logprobs, filled only when `TokenGenConfig::logprobs` is set.
+    pub logprobs: Vec<llama_chat_types::TokenLogprob>,
+    /// Tool calls returned unexecuted (only when `auto_execute_tools` is off).
+    pub tool_calls: Vec<llama_chat_types::ToolCallRequest>,
+    /// Splits streamed text into answer and reasoning (None = stripping off).
+    pub reasoning: Option<ReasoningSplitter>,
+    /// The turn stopped for a compaction, so the prompt in the KV cache no
+    /// longer matches the conversation.
+    pub compacted: bool,
+}
+
+impl TokenGenState {
+    /// State for a reply that starts as `response` (the prefill, if any) with
+    /// the prompt ending at `token_pos`.
+    pub fn new(
+        response: String,
+        token_pos: i32,
+        executed_calls: ExecutedCalls,
+        reasoning: Option<ReasoningSplitter>,
+    ) -> Self {
+        Self {
+            streamed_len: response.len(),
+            response,
+            token_pos,
+            total_tokens_generated: 0,
+            generated_token_ids: Vec::new(),
+            logger_synced_len: 0,
+            last_logger_sync: Instant::now(),
+            exec_tracker: ExecBlockTracker::new(),
+            recent_commands: Vec::new(),
+            consecutive_loop_blocks: 0,
+            executed_calls,
+            last_exec_scan_pos: 0,
+            finish_reason: "stop".to_string(),
+            stop_sequence: None,
+            tool_response_tokens: 0,
+            loop_recoveries: 0,
+            eos_continue_count: 0,
+            tool_call_count: 0,
+            logprobs: Vec::new(),
+            tool_calls: Vec::new(),
+            reasoning,
+            compacted: false,
+        }
+    }
+
+    /// Pull the offsets into `response` back inside it after a truncation.
+    pub fn clamp_offsets(&mut self) {
+        let len = self.response.len();
+        self.streamed_len = self.streamed_len.min(len);
+        self.logger_synced_len = self.logger_synced_len.min(len);
+        self.last_exec_scan_pos = self.last_exec_scan_pos.min(len);
+    }
+
+    /// Route streamed text through the reasoning splitter: `(answer, reasoning)`.
+    pub fn split_stream_text(&mut self, text: &str) -> (String, Option<String>) {
+        match self.reasoning.as_mut() {
+            Some(splitter) => {
+                let (answer, reasoning) = splitter.push(text);
+                (answer, Some(reasoning).filter(|r| !r.is_empty()))
+            }
+            None => (text.to_string(), None),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -29,6 +102,8 @@ pub(crate) struct TokenGenConfig<'a> {
     pub tags: &'a super::super::tool_tags::ToolTags,
     pub template_type: Option<&'a str>,
     pub stop_tokens: &'a [String],
+    /// The request's own `stop` sequences, matched apart from `stop_tokens`.
+    pub request_stops: &'a [String],
     pub context_size: u32,
     pub max_total_tokens: i32,
     pub use_htmd: bool,
@@ -42,6 +117,18 @@ pub(crate) struct TokenGenConfig<'a> {
     pub safe_tool_injection: bool,
     /// First ~300 chars of the user message, for EOS continuation check context.
     pub user_message: &'a str,
+    /// Top-k alternatives to capture with each token's logprob (None = off).
+    pub logprobs: Option<u32>,
+    /// Send each token's id with its text.
+    pub stream_token_ids: bool,
+    /// Execute tool calls inline; false = stop and return them.
+    pub auto_execute_tools: bool,
+    /// Executed tool calls before the next one stops generation (0 = no limit).
+    pub max_tool_iterations: u32,
+    /// Detect tool calls at all; see [`tool_dispatch_enabled`].
+    pub tools_enabled: bool,
+    /// Wall-clock budget for the whole generation (None = unlimited).
+    pub generation_timeout: Option<Duration>,
 }
 
 #[cfg(feature = "vision")]
@@ -53,6 +140,17 @@ pub(crate) const TOKEN_STALL_TIMEOUT: std::time::Duration = std::time::Duration:
 pub(crate) const REPETITION_CHECK_MIN_TOKENS: i32 = 500;
 pub(crate) const REPETITION_CHECK_INTERVAL: i32 = 256;
 
+/// Whether the generation started at `started` has used up its `timeout`.
+pub(crate) fn generation_timed_out(started: Instant, timeout: Option<Duration>) -> bool {
+    timeout.is_some_and(|limit| started.elapsed() >= limit)
+}
+
+/// Whether tool calls are detected and dispatched: off when the config's
+/// `tools_enabled` is off and for `raw_completion`, which has no tools.
+pub(crate) fn tool_dispatch_enabled(config: &SamplerConfig, raw_completion: bool) -> bool {
+    config.tools_enabled && !raw_completion
+}
+
 pub(crate) fn detect_repetition_loop(text: &str) -> bool {
     const TAIL_LEN: usize = 2000;
     const THRESHOLD: f64 = 0.10;
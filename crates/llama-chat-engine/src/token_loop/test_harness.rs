@@ -0,0 +1,122 @@
+//! Token-loop test support: a conversation, its logger and the config
+//! `generate_llama_response` would build, without a model.
+
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+use llama_chat_db::conversation::ConversationLogger;
+use llama_chat_db::SharedDatabase;
+use llama_cpp_2::llama_backend::LlamaBackend;
+use tokio::sync::mpsc;
+
+use super::token_text::{handle_token_text, SampledText, TextStep};
+use super::{TokenGenConfig, TokenGenState};
+use crate::browser::BrowserBackend;
+use crate::duplicate_calls::ExecutedCalls;
+use crate::tool_tags::ToolTags;
+use crate::SharedConversationLogger;
+
+fn backend() -> &'static LlamaBackend {
+    static BACKEND: OnceLock<LlamaBackend> = OnceLock::new();
+    BACKEND.get_or_init(|| LlamaBackend::init().expect("init llama backend"))
+}
+
+fn qwen_tags() -> ToolTags {
+    ToolTags::new("<tool_call>", "</tool_call>", "<tool_response>", "</tool_response>")
+}
+
+/// A conversation to generate into, with the loop's logger on it.
+pub(crate) struct Harness {
+    pub db: SharedDatabase,
+    pub logger: SharedConversationLogger,
+    pub conversation_id: String,
+    pub tags: ToolTags,
+    pub browser: BrowserBackend,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let db: SharedDatabase = Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let logger = ConversationLogger::new(db.clone(), None).unwrap();
+        let conversation_id = logger.get_conversation_id();
+        Self {
+            db,
+            logger: Arc::new(Mutex::new(logger)),
+            conversation_id,
+            tags: qwen_tags(),
+            browser: BrowserBackend::from_config(None),
+        }
+    }
+
+    /// The config `generate_llama_response` builds, with tools on.
+    pub fn config(&self) -> TokenGenConfig<'_> {
+        TokenGenConfig {
+            conversation_id: &self.conversation_id,
+            tags: &self.tags,
+            template_type: Some("ChatML"),
+            stop_tokens: &[],
+            request_stops: &[],
+            context_size: 8192,
+            max_total_tokens: 4096,
+            use_htmd: false,
+            browser_backend: &self.browser,
+            n_batch: 512,
+            mcp_manager: None,
+            db: self.db.clone(),
+            backend: backend(),
+            chat_template_string: None,
+            proactive_compaction: false,
+            safe_tool_injection: false,
+            user_message: "",
+            logprobs: None,
+            stream_token_ids: false,
+            auto_execute_tools: true,
+            max_tool_iterations: 0,
+            tools_enabled: true,
+            generation_timeout: None,
+        }
+    }
+}
+
+/// What came out of feeding a scripted model output to the loop.
+#[derive(Debug)]
+pub(crate) struct Fed {
+    /// The step for the last token fed (the first that wasn't `Next`).
+    pub step: TextStep,
+    /// Tokens consumed before stopping or handing off.
+    pub consumed: usize,
+    /// Everything sent to the client, concatenated.
+    pub streamed: String,
+}
+
+/// Feed `tokens` through `handle_token_text` as the loop does for sampled
+/// tokens, until one isn't `Next`.
+pub(crate) fn feed(gen: &mut TokenGenState, cfg: &TokenGenConfig<'_>, logger: &SharedConversationLogger, tokens: &[&str]) -> Fed {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let sender = Some(tx);
+    let mut step = TextStep::Next;
+    let mut consumed = 0;
+    for (i, text) in tokens.iter().enumerate() {
+        gen.token_pos += 1;
+        gen.total_tokens_generated += 1;
+        consumed += 1;
+        step = handle_token_text(
+            gen, cfg,
+            SampledText { text, id: i as i32, logprob: None },
+            &sender, logger, Instant::now(),
+        );
+        if step != TextStep::Next {
+            break;
+        }
+    }
+    drop(sender);
+    let mut streamed = String::new();
+    while let Ok(data) = rx.try_recv() {
+        streamed.push_str(&data.token);
+    }
+    Fed { step, consumed, streamed }
+}
+
+pub(crate) fn new_gen() -> TokenGenState {
+    TokenGenState::new(String::new(), 0, ExecutedCalls::new(false), None)
+}
@@ -0,0 +1,514 @@
+//! What `run_generation_loop` does with a sampled token's text: stop
+//! sequences, repetition checks, streaming, logger sync and the tool-call
+//! gate. Everything after it (execution, injection) needs the model.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use tokio::sync::mpsc;
+
+use llama_chat_db::event_log::log_event;
+use llama_chat_types::{TokenData, TokenLogprob};
+
+use super::super::stop_conditions::{check_stop_conditions, find_request_stop, request_stop_holdback};
+use super::super::tool_return::{tool_call_action, tool_iterations_exhausted, ToolCallAction};
+use super::{
+    detect_repetition_loop, generation_timed_out, TokenGenConfig, TokenGenState,
+    REPETITION_CHECK_INTERVAL, REPETITION_CHECK_MIN_TOKENS,
+};
+use crate::SharedConversationLogger;
+
+/// A decoded token, as text.
+pub(crate) struct SampledText<'a> {
+    pub text: &'a str,
+    pub id: i32,
+    pub logprob: Option<TokenLogprob>,
+}
+
+/// What the loop does after a token's text is handled.
+#[derive(Debug, PartialEq)]
+pub(crate) enum TextStep {
+    /// Generation is over; `gen.finish_reason` says why.
+    Stop,
+    /// Sample the next token.
+    Next,
+    /// A tool call may be complete: run the execution path.
+    Execute { parallel_complete: bool },
+}
+
+/// Checked before each sample: true, with `gen.finish_reason` set, when the
+/// user cancelled or the generation ran past `cfg.generation_timeout`.
+pub(crate) fn stop_before_sample(
+    gen: &mut TokenGenState,
+    cfg: &TokenGenConfig<'_>,
+    cancel: &AtomicBool,
+    gen_start_time: Instant,
+) -> bool {
+    if cancel.load(Ordering::Relaxed) {
+        log_info!(cfg.conversation_id, "Generation cancelled by user");
+        gen.finish_reason = "cancelled".to_string();
+        return true;
+    }
+
+    if generation_timed_out(gen_start_time, cfg.generation_timeout) {
+        let secs = gen_start_time.elapsed().as_secs();
+        log_info!(cfg.conversation_id, "Generation timed out after {}s", secs);
+        log_event(cfg.conversation_id, "generation_timeout", &format!(
+            "Stopped after {secs}s with {} tokens generated", gen.total_tokens_generated
+        ));
+        gen.finish_reason = "timeout".to_string();
+        return true;
+    }
+    false
+}
+
+pub(crate) fn handle_token_text(
+    gen: &mut TokenGenState,
+    cfg: &TokenGenConfig<'_>,
+    token: SampledText<'_>,
+    token_sender: &Option<mpsc::UnboundedSender<TokenData>>,
+    conversation_logger: &SharedConversationLogger,
+    gen_start_time: Instant,
+) -> TextStep {
+    let token_str = token.text;
+
+    // The request's own stop sequences: keep the text before the match
+    if let Some((stop, keep)) = find_request_stop(&gen.response, token_str, cfg.request_stops, gen.exec_tracker.is_inside()) {
+        let before = &token_str[..keep.saturating_sub(gen.response.len()).min(token_str.len())];
+        gen.response.truncate(keep);
+        gen.response.push_str(before);
+        gen.clamp_offsets();
+        flush_held_text(gen, cfg, token_sender);
+        log_event(cfg.conversation_id, "stop_sequence", &format!("Request stop sequence {stop:?} matched"));
+        gen.finish_reason = "stop_sequence".to_string();
+        gen.stop_sequence = Some(stop.to_string());
+        return TextStep::Stop;
+    }
+
+    // Check for stop sequences
+    let stop_result = check_stop_conditions(&gen.response, token_str, cfg.stop_tokens, gen.exec_tracker.is_inside());
+    if stop_result.should_stop {
+        if stop_result.partial_to_remove > 0 {
+            let new_len = gen.response.len().saturating_sub(stop_result.partial_to_remove);
+            gen.response.truncate(new_len);
+            gen.clamp_offsets();
+        }
+        return TextStep::Stop;
+    }
+
+    gen.response.push_str(token_str);
+    gen.exec_tracker.update(token_str, gen.response.len());
+    if let Some(ref logprob) = token.logprob {
+        gen.logprobs.push(logprob.clone());
+    }
+
+    // Periodic repetition loop detection
+    if gen.total_tokens_generated > REPETITION_CHECK_MIN_TOKENS
+        && gen.total_tokens_generated % REPETITION_CHECK_INTERVAL == 0
+        && detect_repetition_loop(&gen.response)
+    {
+        eprintln!("[LOOP_RECOVERY] Repetition loop detected at token {}, loop_recoveries={}", gen.total_tokens_generated, gen.loop_recoveries);
+        log_event(cfg.conversation_id, "loop_recovery", &format!("Repetition loop at token {}", gen.total_tokens_generated));
+        let notice = if gen.loop_recoveries < 1 {
+            gen.loop_recoveries += 1;
+            gen.finish_reason = "loop_recovery".to_string();
+            "\n\n[Repetition detected — retrying with different approach]"
+        } else {
+            gen.finish_reason = "error".to_string();
+            "\n\n[Generation stopped: repetition loop persists after recovery attempt]"
+        };
+        flush_held_text(gen, cfg, token_sender);
+        if let Some(ref sender) = token_sender {
+            let _ = sender.send(TokenData {
+                token: notice.to_string(),
+                tokens_used: gen.token_pos,
+                max_tokens: cfg.context_size as i32, status: None,
+                ..Default::default()
+            });
+        }
+        return TextStep::Stop;
+    }
+
+    // Stream token to frontend with live tok/s, holding back a tail that
+    // could still become a request stop sequence
+    let held = request_stop_holdback(&gen.response, cfg.request_stops, gen.exec_tracker.is_inside());
+    let stream_end = (gen.response.len() - held).max(gen.streamed_len);
+    if let (true, Some(sender)) = (stream_end > gen.streamed_len, token_sender.as_ref()) {
+        let elapsed_secs = gen_start_time.elapsed().as_secs_f64();
+        let live_tok_per_sec = if elapsed_secs > 0.1 {
+            Some(gen.total_tokens_generated as f64 / elapsed_secs)
+        } else {
+            None
+        };
+        let chunk = gen.response[gen.streamed_len..stream_end].to_string();
+        let (text, reasoning) = gen.split_stream_text(&chunk);
+        let _ = sender.send(TokenData {
+            token: text,
+            tokens_used: gen.token_pos,
+            max_tokens: cfg.context_size as i32,
+            gen_tok_per_sec: live_tok_per_sec,
+            gen_tokens: Some(gen.total_tokens_generated),
+            logprobs: token.logprob,
+            reasoning,
+            token_id: cfg.stream_token_ids.then_some(token.id),
+            ..Default::default()
+        });
+    }
+    gen.streamed_len = stream_end;
+
+    // Periodic sync to logger (every 200ms), up to what the client has seen
+    if gen.last_logger_sync.elapsed() >= std::time::Duration::from_millis(200) {
+        if let Ok(mut logger) = conversation_logger.lock() {
+            logger.set_token_counts(gen.token_pos, cfg.context_size as i32);
+            if gen.logger_synced_len < gen.streamed_len {
+                logger.log_token_bulk(&gen.response[gen.logger_synced_len..gen.streamed_len]);
+                gen.logger_synced_len = gen.streamed_len;
+            }
+        }
+        gen.last_logger_sync = Instant::now();
+    }
+
+    // Check for and execute tool calls in the response.
+    // Fast gate: only call the expensive detector when the new token
+    // contains a character that could close a tool call block.
+    let token_has_close_char = token_str.as_bytes().iter().any(|&b| b == b'>' || b == b']' || b == b'}');
+    // parallel_just_closed() is set to true for exactly the token that closed the fence,
+    // then cleared on the next update() call. We can't use is_in_parallel_block() here
+    // because update() already reset it to false before we check.
+    let parallel_complete = gen.exec_tracker.parallel_just_closed();
+
+    if !cfg.tools_enabled || !(parallel_complete || token_has_close_char) {
+        return TextStep::Next;
+    }
+
+    // return_tool_calls mode: stop at a complete call instead of executing it.
+    // An unclosed parallel fence falls through to the path below, which waits.
+    let in_open_parallel = gen.exec_tracker.is_in_parallel_block() && !parallel_complete;
+    if !in_open_parallel {
+        let scan_pos = if parallel_complete {
+            gen.exec_tracker.parallel_block_start()
+        } else {
+            gen.last_exec_scan_pos
+        };
+        let limit_reached =
+            tool_iterations_exhausted(gen.tool_call_count, cfg.max_tool_iterations);
+        match tool_call_action(cfg.auto_execute_tools, limit_reached, &gen.response, scan_pos, cfg.tags) {
+            ToolCallAction::Return(calls) => {
+                log_event(cfg.conversation_id, "tool_calls_returned", &format!(
+                    "{} call(s) returned unexecuted: {}",
+                    calls.len(),
+                    calls.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ")
+                ));
+                gen.tool_calls = calls;
+                gen.finish_reason = "tool_calls".to_string();
+                return TextStep::Stop;
+            }
+            ToolCallAction::LimitReached(calls) => {
+                let limit = cfg.max_tool_iterations;
+                log_event(cfg.conversation_id, "tool_limit", &format!(
+                    "{limit} tool call(s) executed and the wrap-up notice ignored; not running: {}",
+                    calls.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ")
+                ));
+                // Shown as a ⚠️ SYSTEM bubble, like the wrap-up notice
+                if let Ok(mut notice_logger) = llama_chat_db::logger::ConversationLogger::from_existing(
+                    cfg.db.clone(), cfg.conversation_id,
+                ) {
+                    notice_logger.log_message(
+                        "system",
+                        &format!("Tool call limit reached ({limit} per message) and the model kept calling tools. Generation was stopped before the next tool call; send a message to let the model continue."),
+                    );
+                }
+                gen.finish_reason = "tool_limit".to_string();
+                return TextStep::Stop;
+            }
+            ToolCallAction::Continue => return TextStep::Next,
+            ToolCallAction::Execute => {}
+        }
+    }
+
+    flush_held_text(gen, cfg, token_sender);
+    TextStep::Execute { parallel_complete }
+}
+
+/// Count an executed tool call against `cfg.max_tool_iterations` (0 = no
+/// limit). The call that reaches the limit gets a "wrap up now" notice
+/// appended to the response, returned for the loop to inject after the tool
+/// output; a call after it stops generation with finish_reason "tool_limit".
+pub(crate) fn count_tool_execution(gen: &mut TokenGenState, cfg: &TokenGenConfig<'_>) -> Option<String> {
+    gen.tool_call_count += 1;
+    let limit = cfg.max_tool_iterations;
+    if limit == 0 || gen.tool_call_count != limit {
+        return None;
+    }
+    // Plain text (no tool tags) so the model sees it as the start of its own
+    // assistant turn and continues with a text response, not another tool call.
+    let warning = format!(
+        "\n\n⚠️ [IMPORTANT: You have reached the maximum of {limit} tool calls. You MUST stop making tool calls immediately and write your complete final response now. Summarize everything you have gathered so far in clear prose. Do NOT invoke any more tools.]\n\n"
+    );
+    gen.response.push_str(&warning);
+    gen.streamed_len = gen.response.len();
+    // Fresh EOS continuation chances for the summary (it may have used all 3 by now)
+    gen.eos_continue_count = 0;
+    eprintln!("[TOOL_LIMIT] Reached {limit} tool calls — injecting wrap-up notice");
+    // Also persisted as a system message so the UI shows a distinct ⚠️ SYSTEM bubble
+    // (the inline warning text is stripped from assistant rendering by the frontend).
+    if let Ok(mut notice_logger) = llama_chat_db::logger::ConversationLogger::from_existing(
+        cfg.db.clone(), cfg.conversation_id,
+    ) {
+        notice_logger.log_message(
+            "system",
+            &format!("Tool call limit reached ({limit}). The model has been asked to stop making tool calls and write its final response."),
+        );
+    }
+    Some(warning)
+}
+
+/// Stream whatever `handle_token_text` held back as a possible stop prefix:
+/// before a tool runs, before text is injected, and when generation ends.
+pub(crate) fn flush_held_text(
+    gen: &mut TokenGenState,
+    cfg: &TokenGenConfig<'_>,
+    token_sender: &Option<mpsc::UnboundedSender<TokenData>>,
+) {
+    if gen.streamed_len >= gen.response.len() {
+        return;
+    }
+    let held = gen.response[gen.streamed_len..].to_string();
+    gen.streamed_len = gen.response.len();
+    if let Some(sender) = token_sender.as_ref() {
+        let (token, reasoning) = gen.split_stream_text(&held);
+        let _ = sender.send(TokenData {
+            token,
+            tokens_used: gen.token_pos,
+            max_tokens: cfg.context_size as i32,
+            reasoning,
+            ..Default::default()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tool_dispatch_enabled;
+    use crate::token_loop::test_harness::{feed, new_gen, Harness};
+
+    const TOOL_SCRIPT: &[&str] = &[
+        "Let me check.\n",
+        "<tool_call>",
+        "{\"name\": \"read_file\", ",
+        "\"arguments\": {\"path\": \"README.md\"}}",
+        "\n</tool_call>",
+        "\nThe file says...",
+    ];
+
+    #[test]
+    fn test_auto_execute_hands_the_call_to_the_executor() {
+        let h = Harness::new();
+        let mut gen = new_gen();
+        let fed = feed(&mut gen, &h.config(), &h.logger, TOOL_SCRIPT);
+        assert_eq!(fed.step, TextStep::Execute { parallel_complete: false });
+        assert!(gen.tool_calls.is_empty());
+        assert_eq!(gen.finish_reason, "stop");
+    }
+
+    #[test]
+    fn test_agent_loop_wraps_up_then_halts_at_max_tool_iterations() {
+        let h = Harness::new();
+        let cfg = TokenGenConfig { max_tool_iterations: 2, ..h.config() };
+        let mut gen = new_gen();
+        // A model that answers every tool result with another call
+        let call = ["<tool_call>{\"name\": \"list_directory\", \"arguments\": {\"path\": \".\"}}</tool_call>"];
+
+        let mut notices = Vec::new();
+        for _ in 0..2 {
+            let fed = feed(&mut gen, &cfg, &h.logger, &call);
+            assert_eq!(fed.step, TextStep::Execute { parallel_complete: false });
+            // What the executor leaves behind before the loop counts the call
+            gen.response.push_str("\n<tool_response>\na.txt\n</tool_response>\n");
+            gen.streamed_len = gen.response.len();
+            gen.last_exec_scan_pos = gen.response.len();
+            notices.push(count_tool_execution(&mut gen, &cfg));
+        }
+        assert_eq!(notices[0], None);
+        let notice = notices[1].as_deref().expect("wrap-up notice at the limit");
+        assert!(notice.contains("maximum of 2 tool calls"));
+        assert!(gen.response.ends_with(notice));
+        gen.last_exec_scan_pos = gen.response.len();
+
+        // The model ignores the notice and calls again: stopped, not executed
+        let fed = feed(&mut gen, &cfg, &h.logger, &call);
+        assert_eq!(fed.step, TextStep::Stop);
+        assert_eq!(gen.finish_reason, "tool_limit");
+        assert_eq!(gen.tool_call_count, 2);
+
+        // 0 disables both the notice and the stop
+        let cfg = TokenGenConfig { max_tool_iterations: 0, ..h.config() };
+        let mut gen = new_gen();
+        for _ in 0..300 {
+            assert_eq!(count_tool_execution(&mut gen, &cfg), None);
+        }
+        let fed = feed(&mut gen, &cfg, &h.logger, &call);
+        assert_eq!(fed.step, TextStep::Execute { parallel_complete: false });
+    }
+
+    #[test]
+    fn test_tools_disabled_streams_the_call_as_text() {
+        let h = Harness::new();
+        let sampler = llama_chat_types::SamplerConfig { tools_enabled: false, ..Default::default() };
+        let cfg = TokenGenConfig {
+            tools_enabled: tool_dispatch_enabled(&sampler, false),
+            ..h.config()
+        };
+        let mut gen = new_gen();
+        let fed = feed(&mut gen, &cfg, &h.logger, TOOL_SCRIPT);
+
+        assert_eq!(fed.step, TextStep::Next);
+        assert_eq!(fed.consumed, TOOL_SCRIPT.len());
+        assert!(gen.tool_calls.is_empty());
+        assert_eq!(fed.streamed, TOOL_SCRIPT.concat());
+    }
+
+    #[test]
+    fn test_raw_completion_never_dispatches_tools() {
+        let h = Harness::new();
+        let cfg = TokenGenConfig {
+            tools_enabled: tool_dispatch_enabled(&Default::default(), true),
+            ..h.config()
+        };
+        let mut gen = new_gen();
+        let fed = feed(&mut gen, &cfg, &h.logger, TOOL_SCRIPT);
+        assert_eq!(fed.step, TextStep::Next);
+        assert_eq!(fed.consumed, TOOL_SCRIPT.len());
+    }
+
+    #[test]
+    fn test_return_mode_stops_at_the_complete_call() {
+        let h = Harness::new();
+        let cfg = TokenGenConfig { auto_execute_tools: false, ..h.config() };
+        let mut gen = new_gen();
+        let fed = feed(&mut gen, &cfg, &h.logger, TOOL_SCRIPT);
+
+        // The call is complete once its JSON closes; the trailing text is never generated
+        assert_eq!(fed.step, TextStep::Stop);
+        assert_eq!(fed.consumed, 4);
+        assert_eq!(gen.finish_reason, "tool_calls");
+        assert_eq!(gen.tool_calls.len(), 1);
+        assert_eq!(gen.tool_calls[0].name, "read_file");
+        assert_eq!(gen.tool_calls[0].arguments, serde_json::json!({ "path": "README.md" }));
+        assert_eq!(fed.streamed, gen.response);
+        assert!(!gen.response.contains("The file says"));
+    }
+
+    #[test]
+    fn test_return_mode_leaves_plain_text_alone() {
+        let h = Harness::new();
+        let cfg = TokenGenConfig { auto_execute_tools: false, ..h.config() };
+        let mut gen = new_gen();
+        let fed = feed(&mut gen, &cfg, &h.logger, &["The answer is 42 > 41.", " Done."]);
+        assert_eq!(fed.step, TextStep::Next);
+        assert_eq!(fed.consumed, 2);
+        assert!(gen.tool_calls.is_empty());
+        assert_eq!(fed.streamed, "The answer is 42 > 41. Done.");
+    }
+
+    /// Feed one token at a time with the logger sync due before each.
+    fn feed_syncing(gen: &mut TokenGenState, cfg: &TokenGenConfig<'_>, h: &Harness, tokens: &[&str]) -> (TextStep, String) {
+        let mut streamed = String::new();
+        for token in tokens {
+            gen.last_logger_sync = Instant::now() - std::time::Duration::from_secs(1);
+            let fed = feed(gen, cfg, &h.logger, &[token]);
+            streamed.push_str(&fed.streamed);
+            if fed.step != TextStep::Next {
+                return (fed.step, streamed);
+            }
+        }
+        (TextStep::Next, streamed)
+    }
+
+    /// What `generate_llama_response` does once the loop is over.
+    fn finish_reply(gen: &TokenGenState, h: &Harness) -> String {
+        let mut logger = h.logger.lock().unwrap();
+        logger.log_token_bulk(&gen.response[gen.logger_synced_len..]);
+        logger.finish_assistant_message();
+        drop(logger);
+        h.db.get_messages(&h.conversation_id).unwrap().last().unwrap().content.clone()
+    }
+
+    #[test]
+    fn test_request_stop_spanning_synced_tokens_never_reaches_the_client() {
+        let h = Harness::new();
+        h.logger.lock().unwrap().start_assistant_message();
+        let stops = vec!["###".to_string()];
+        let cfg = TokenGenConfig { request_stops: &stops, ..h.config() };
+        let mut gen = new_gen();
+
+        let (step, streamed) = feed_syncing(&mut gen, &cfg, &h, &["Answer: 42 #", "#", "# ignored"]);
+        assert_eq!(step, TextStep::Stop);
+        assert_eq!(gen.finish_reason, "stop_sequence");
+        assert_eq!(gen.response, "Answer: 42 ");
+        assert_eq!(streamed, "Answer: 42 ");
+        assert!(gen.logger_synced_len <= gen.response.len());
+        assert_eq!(finish_reply(&gen, &h), "Answer: 42 ");
+    }
+
+    #[test]
+    fn test_held_stop_prefix_is_streamed_when_generation_ends() {
+        let h = Harness::new();
+        h.logger.lock().unwrap().start_assistant_message();
+        let stops = vec!["###".to_string()];
+        let cfg = TokenGenConfig { request_stops: &stops, ..h.config() };
+        let mut gen = new_gen();
+
+        let (step, mut streamed) = feed_syncing(&mut gen, &cfg, &h, &["Use #", "# for headings"]);
+        assert_eq!(step, TextStep::Next);
+        assert_eq!(streamed, "Use ## for headings");
+
+        let (_, tail) = feed_syncing(&mut gen, &cfg, &h, &[" #"]);
+        assert_eq!(tail, " ");
+        streamed.push_str(&tail);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        flush_held_text(&mut gen, &cfg, &Some(tx));
+        assert_eq!(rx.try_recv().unwrap().token, "#");
+        streamed.push_str("#");
+        assert_eq!(streamed, gen.response);
+        assert_eq!(finish_reply(&gen, &h), "Use ## for headings #");
+    }
+
+    #[test]
+    fn test_generation_past_its_timeout_stops_before_sampling() {
+        let h = Harness::new();
+        let cfg = TokenGenConfig {
+            generation_timeout: Some(std::time::Duration::from_millis(30)),
+            ..h.config()
+        };
+        let mut gen = new_gen();
+        let cancel = AtomicBool::new(false);
+        let started = Instant::now();
+
+        assert!(!stop_before_sample(&mut gen, &cfg, &cancel, started));
+        let fed = feed(&mut gen, &cfg, &h.logger, &["Working", " on it"]);
+        assert_eq!(fed.step, TextStep::Next);
+
+        std::thread::sleep(std::time::Duration::from_millis(40));
+        assert!(stop_before_sample(&mut gen, &cfg, &cancel, started));
+        assert_eq!(gen.finish_reason, "timeout");
+        assert_eq!(gen.response, "Working on it");
+    }
+
+    #[test]
+    fn test_no_timeout_only_stops_on_cancel() {
+        let h = Harness::new();
+        let cfg = h.config();
+        let mut gen = new_gen();
+        let cancel = AtomicBool::new(false);
+        let long_ago = Instant::now()
+            .checked_sub(std::time::Duration::from_secs(3600))
+            .unwrap_or_else(Instant::now);
+
+        assert!(!stop_before_sample(&mut gen, &cfg, &cancel, long_ago));
+        cancel.store(true, Ordering::Relaxed);
+        assert!(stop_before_sample(&mut gen, &cfg, &cancel, long_ago));
+        assert_eq!(gen.finish_reason, "cancelled");
+    }
+}
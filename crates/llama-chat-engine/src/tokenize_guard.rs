@@ -0,0 +1,105 @@
+//! Tokenize a prompt before generation, with panics caught.
+//!
+//! llama-cpp-2 tokenization can panic on certain inputs. Running it here, in a
+//! narrow caught step, turns that into an ordinary error before any context
+//! is created. Tokenization only reads the vocabulary, so the model stays loaded.
+
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use llama_cpp_2::model::{AddBos, LlamaModel};
+use llama_cpp_2::token::LlamaToken;
+
+/// Tokenize `prompt`, reporting a tokenizer error or panic as `Err`.
+pub(crate) fn validate_tokenizable(
+    model: &LlamaModel,
+    prompt: &str,
+    add_bos: AddBos,
+) -> Result<Vec<LlamaToken>, String> {
+    tokenize_caught(|| model.str_to_token(prompt, add_bos))
+}
+
+/// Run a tokenizer call, mapping both its error and a panic to a message.
+fn tokenize_caught<T, E: std::fmt::Display>(
+    tokenize: impl FnOnce() -> Result<Vec<T>, E>,
+) -> Result<Vec<T>, String> {
+    match catch_unwind(AssertUnwindSafe(tokenize)) {
+        Ok(Ok(tokens)) => Ok(tokens),
+        Ok(Err(e)) => Err(format!("Tokenization failed: {e}")),
+        Err(panic) => {
+            let msg = panic_message(panic.as_ref()).unwrap_or_else(|| "unknown panic".to_string());
+            eprintln!("[GENERATION] Tokenizer panicked: {msg}");
+            Err(format!(
+                "Tokenization crashed on this prompt ({msg}); try rephrasing or removing unusual characters"
+            ))
+        }
+    }
+}
+
+/// Text of a caught panic, when it was raised with a string message.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_successful_tokenization_passes_through() {
+        assert_eq!(
+            tokenize_caught(|| Ok::<_, String>(vec![1, 2, 3])).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_tokenizer_error_is_reported() {
+        let err = tokenize_caught(|| Err::<Vec<i32>, _>("bad utf-8")).unwrap_err();
+        assert_eq!(err, "Tokenization failed: bad utf-8");
+    }
+
+    #[test]
+    fn test_tokenizer_panic_becomes_error() {
+        let err = tokenize_caught(|| -> Result<Vec<i32>, String> {
+            panic!("byte index 3 is not a char boundary")
+        })
+        .unwrap_err();
+        assert!(err.contains("Tokenization crashed"), "{err}");
+        assert!(err.contains("not a char boundary"), "{err}");
+
+        let err = tokenize_caught(|| -> Result<Vec<i32>, String> { std::panic::panic_any(42_u8) })
+            .unwrap_err();
+        assert!(err.contains("unknown panic"), "{err}");
+    }
+
+    #[test]
+    #[ignore = "requires a GGUF model at LLAMA_CHAT_TEST_MODEL"]
+    fn test_normal_prompt_tokenizes() {
+        use std::sync::{Arc, Mutex};
+
+        let model_path = std::env::var("LLAMA_CHAT_TEST_MODEL")
+            .expect("set LLAMA_CHAT_TEST_MODEL to a GGUF model path");
+        let state: llama_chat_types::SharedLlamaState = Arc::new(Mutex::new(None));
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(crate::load_model(
+                state.clone(),
+                &model_path,
+                Some(0),
+                None,
+                None,
+                None,
+                None,
+            ))
+            .unwrap();
+
+        let guard = state.lock().unwrap();
+        let model = guard.as_ref().unwrap().model.as_ref().unwrap();
+        let tokens = validate_tokenizable(model, "Hello, world!", AddBos::Always).unwrap();
+        assert!(tokens.len() > 1);
+    }
+}
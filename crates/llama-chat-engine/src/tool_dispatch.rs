@@ -4,7 +4,7 @@ use std::time::Instant;
 use tokio::sync::mpsc;
 
 use llama_chat_command::background::execute_command_background;
-use llama_chat_command::{execute_command_streaming_with_timeout, strip_ansi_codes};
+use llama_chat_command::{run_command_streaming, strip_ansi_codes};
 use llama_chat_types::*;
 use super::sub_agent::{run_sub_agent};
 use super::tool_tags::ToolTags;
@@ -126,6 +126,77 @@ pub(crate) fn is_read_only_tool(name: &str) -> bool {
     READ_ONLY_TOOLS.contains(&name)
 }
 
+/// How long a `require_confirmation_for` tool call waits for the user.
+const CONFIRMATION_TIMEOUT_SECS: u64 = 300;
+
+/// True when the config's `require_confirmation_for` lists this tool.
+pub(crate) fn requires_confirmation(name: &str, db: &llama_chat_db::SharedDatabase) -> bool {
+    db.load_config().require_confirmation_for.iter().any(|n| n == name)
+}
+
+/// Poll a pending approval until it is approved (true), rejected, times out or
+/// the generation is cancelled (false).
+fn wait_for_approval(
+    db: &llama_chat_db::SharedDatabase,
+    approval_id: &str,
+    cancel: Option<&AtomicBool>,
+    timeout_secs: u64,
+) -> bool {
+    let deadline = Instant::now() + std::time::Duration::from_secs(timeout_secs);
+    loop {
+        if Instant::now() >= deadline {
+            let _ = db.resolve_pending_approval(approval_id, "timeout");
+            return false;
+        }
+        if cancel.is_some_and(|c| c.load(std::sync::atomic::Ordering::Relaxed)) {
+            let _ = db.resolve_pending_approval(approval_id, "rejected");
+            return false;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        match db.get_pending_approval_status(approval_id).as_deref() {
+            Some("approved") => return true,
+            Some("rejected") | Some("timeout") => return false,
+            _ => {}
+        }
+    }
+}
+
+/// Hold a gated tool call until the user confirms it: emits `confirmation_required`
+/// and waits for `POST /api/generation/confirm`. Returns the reply for the model
+/// when the call is declined, or None to go ahead and run it.
+pub(crate) fn await_tool_confirmation(
+    name: &str,
+    args: &serde_json::Value,
+    conversation_id: &str,
+    token_sender: &Option<mpsc::UnboundedSender<TokenData>>,
+    cancel: Option<&AtomicBool>,
+    db: &llama_chat_db::SharedDatabase,
+) -> Option<String> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let reason = format!("{name} requires confirmation");
+    if let Err(e) = db.create_pending_approval(&id, conversation_id, name, &args.to_string(), &reason) {
+        return Some(format!("Error: could not request confirmation for '{name}': {e}"));
+    }
+    llama_chat_db::event_log::log_event(conversation_id, "confirmation_requested", &format!("id={id} tool={name}"));
+    if let Some(ref sender) = token_sender {
+        let _ = sender.send(TokenData {
+            confirmation_required: Some(ConfirmationRequest {
+                id: id.clone(),
+                tool: name.to_string(),
+                args: args.clone(),
+            }),
+            ..Default::default()
+        });
+    }
+    if wait_for_approval(db, &id, cancel, CONFIRMATION_TIMEOUT_SECS) {
+        llama_chat_db::event_log::log_event(conversation_id, "confirmation_granted", &format!("id={id}"));
+        None
+    } else {
+        llama_chat_db::event_log::log_event(conversation_id, "confirmation_declined", &format!("id={id}"));
+        Some(format!("⛔ Tool '{name}' was not run: the user did not confirm it."))
+    }
+}
+
 /// Default timeout for native tool execution.
 const NATIVE_TOOL_TIMEOUT_SECS: u64 = 30;
 /// Browser tools may need longer for first-run startup, tab creation, and slow pages.
@@ -141,6 +212,7 @@ pub(crate) fn run_native_tool_with_timeout(
     db: llama_chat_db::SharedDatabase,
 ) -> Option<llama_chat_tools::NativeToolResult> {
     let cmd = command_text.to_string();
+    let conversation = conversation_id.to_string();
     let mcp = mcp_manager.clone();
     let db = db.clone();
 
@@ -152,7 +224,10 @@ pub(crate) fn run_native_tool_with_timeout(
 
     let (tx, rx) = std::sync::mpsc::channel();
     std::thread::spawn(move || {
-        let ctx = crate::make_dispatch_context();
+        let ctx = llama_chat_tools::DispatchContext {
+            conversation_id: Some(&conversation),
+            ..crate::make_dispatch_context()
+        };
         let mcp_ops: Option<&dyn llama_chat_tools::McpManagerOps> = mcp.as_deref().map(|m| m as &dyn llama_chat_tools::McpManagerOps);
         let result = llama_chat_tools::dispatch_native_tool(
             &cmd,
@@ -187,21 +262,19 @@ pub(crate) fn run_native_tool_with_timeout(
                 "{tool_name}: timed out after {timeout_secs}s"
             ));
             log_info!(conversation_id, "⏱️ Native tool timed out after {timeout_secs}s");
-            Some(llama_chat_tools::NativeToolResult::text_only(format!(
+            Some(llama_chat_tools::NativeToolResult::failure(format!(
                 "Error: Tool execution timed out after {timeout_secs} seconds. The network request may be slow or unresponsive. Please try again."
             )))
         }
         Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
             log_info!(conversation_id, "⚠️ Native tool thread panicked");
-            Some(llama_chat_tools::NativeToolResult::text_only("Error: Tool execution failed unexpectedly.".to_string()))
+            Some(llama_chat_tools::NativeToolResult::failure("Error: Tool execution failed unexpectedly.".to_string()))
         }
     }
 }
 
-/// Execute a single tool call given its parsed name and arguments.
-/// Returns (text_output, image_bytes). Used by the batch execution path.
 /// Execute a single tool call as part of a batch.
-/// Returns (output_text, images, duration_ms).
+/// Returns the tool's result and its duration in milliseconds.
 /// The caller (batch outer merge loop) is responsible for sending timing WS messages and
 /// logging to DB — this function intentionally does NOT emit tool_timing WS messages to
 /// avoid duplicate sends (the outer loop emits once for every tool, in order).
@@ -223,7 +296,18 @@ pub(crate) fn execute_single_tool(
     backend: &llama_cpp_2::llama_backend::LlamaBackend,
     chat_template_string: Option<&str>,
     tags: &ToolTags,
-) -> (String, Vec<Vec<u8>>, u64) {
+) -> (NativeToolResult, u64) {
+    // parallel_execute, spawn_agent and execute_command run here without reaching
+    // dispatch_native_tool, so its enabled_tools check is repeated up front
+    if let Some(refusal) = llama_chat_tools::disabled_tool_refusal(name, Some(&db)) {
+        return (NativeToolResult::failure(refusal), 0);
+    }
+    if requires_confirmation(name, &db) {
+        if let Some(declined) = await_tool_confirmation(name, args, conversation_id, token_sender, cancel.as_deref(), &db) {
+            return (NativeToolResult::failure(declined), 0);
+        }
+    }
+
     // parallel_execute: run multiple independent tool calls concurrently
     if name == "parallel_execute" {
         const BLOCKED_IN_PARALLEL: &[&str] = &["execute_command", "spawn_agent", "parallel_execute"];
@@ -231,11 +315,11 @@ pub(crate) fn execute_single_tool(
 
         let calls = match args.get("calls").and_then(|v| v.as_array()) {
             Some(c) if !c.is_empty() => c.clone(),
-            _ => return ("Error: 'calls' must be a non-empty array".to_string(), Vec::new(), 0),
+            _ => return (NativeToolResult::failure("Error: 'calls' must be a non-empty array".to_string()), 0),
         };
 
         if calls.len() > MAX_PARALLEL_CALLS {
-            return (format!("Error: parallel_execute supports at most {MAX_PARALLEL_CALLS} calls, got {}", calls.len()), Vec::new(), 0);
+            return (NativeToolResult::failure(format!("Error: parallel_execute supports at most {MAX_PARALLEL_CALLS} calls, got {}", calls.len())), 0);
         }
 
         // Validate and build (tool_name, flattened_json) pairs before spawning anything
@@ -243,10 +327,13 @@ pub(crate) fn execute_single_tool(
         for (i, call) in calls.iter().enumerate() {
             let tool_name = match call.get("tool").and_then(|v| v.as_str()) {
                 Some(n) => n.to_string(),
-                None => return (format!("Error: calls[{i}] missing 'tool' field"), Vec::new(), 0),
+                None => return (NativeToolResult::failure(format!("Error: calls[{i}] missing 'tool' field")), 0),
             };
             if BLOCKED_IN_PARALLEL.contains(&tool_name.as_str()) {
-                return (format!("Error: '{tool_name}' is not allowed inside parallel_execute"), Vec::new(), 0);
+                return (NativeToolResult::failure(format!("Error: '{tool_name}' is not allowed inside parallel_execute")), 0);
+            }
+            if requires_confirmation(&tool_name, &db) {
+                return (NativeToolResult::failure(format!("Error: '{tool_name}' requires confirmation; call it on its own, not inside parallel_execute")), 0);
             }
             let call_args = call.get("args").cloned().unwrap_or(serde_json::Value::Object(Default::default()));
             // Flatten: {"name": tool_name, ...args} — the format dispatch_native_tool expects
@@ -278,29 +365,36 @@ pub(crate) fn execute_single_tool(
 
         let mut parts: Vec<String> = Vec::new();
         let mut all_images: Vec<Vec<u8>> = Vec::new();
+        let mut all_succeeded = true;
         for (i, (tool_name, handle)) in handles.into_iter().enumerate() {
             match handle.join().unwrap_or(None) {
                 Some(r) => {
                     parts.push(format!("--- {}: {} ---\n{}", i + 1, tool_name, r.text.trim()));
                     all_images.extend(r.images);
+                    all_succeeded &= r.success;
                 }
                 None => {
                     parts.push(format!("--- {}: {} ---\nError: tool failed or timed out", i + 1, tool_name));
+                    all_succeeded = false;
                 }
             }
         }
 
-        let combined = parts.join("\n\n");
         let duration_ms = t.elapsed().as_millis() as u64;
         log_info!(conversation_id, "⚡ parallel_execute done in {}ms", duration_ms);
-        return (combined, all_images, duration_ms);
+        let combined = NativeToolResult {
+            text: parts.join("\n\n"),
+            images: all_images,
+            success: all_succeeded,
+        };
+        return (combined, duration_ms);
     }
 
     // spawn_agent: run a sub-agent with fresh context
     if name == "spawn_agent" {
         let task = args.get("task").and_then(|v| v.as_str()).unwrap_or("");
         if task.is_empty() {
-            return ("Error: 'task' argument is required for spawn_agent".to_string(), Vec::new(), 0);
+            return (NativeToolResult::failure("Error: 'task' argument is required for spawn_agent".to_string()), 0);
         }
         let extra_context = args.get("context").and_then(|v| v.as_str());
         let t = Instant::now();
@@ -316,9 +410,9 @@ pub(crate) fn execute_single_tool(
                 } else {
                     format!("{result}\n[sub_agent_session:{child_conv_id}]")
                 };
-                return (output, Vec::new(), t.elapsed().as_millis() as u64);
+                return (NativeToolResult::text_only(output), t.elapsed().as_millis() as u64);
             }
-            Err(e) => return (format!("Sub-agent error: {e}"), Vec::new(), t.elapsed().as_millis() as u64),
+            Err(e) => return (NativeToolResult::failure(format!("Sub-agent error: {e}")), t.elapsed().as_millis() as u64),
         }
     }
 
@@ -328,7 +422,7 @@ pub(crate) fn execute_single_tool(
             if !cmd.is_empty() {
                 // Security checks
                 if let Some(injection_msg) = detect_command_injection(cmd) {
-                    return (injection_msg, Vec::new(), 0);
+                    return (NativeToolResult::failure(injection_msg), 0);
                 }
                 if let Some(warning) = detect_destructive_command(cmd) {
                     eprintln!("[SECURITY] {}: {}", warning, &cmd[..cmd.len().min(100)]);
@@ -353,22 +447,9 @@ pub(crate) fn execute_single_tool(
                                     });
                                 }
                                 // Poll for decision (max 120 seconds)
-                                let deadline = std::time::Instant::now() + std::time::Duration::from_secs(120);
-                                let approved = loop {
-                                    if std::time::Instant::now() >= deadline {
-                                        let _ = db.resolve_pending_approval(&approval_id, "timeout");
-                                        break false;
-                                    }
-                                    std::thread::sleep(std::time::Duration::from_millis(500));
-                                    match db.get_pending_approval_status(&approval_id).as_deref() {
-                                        Some("approved") => break true,
-                                        Some("rejected") | Some("timeout") => break false,
-                                        _ => {}
-                                    }
-                                };
-                                if !approved {
+                                if !wait_for_approval(&db, &approval_id, None, 120) {
                                     llama_chat_db::event_log::log_event(conversation_id, "approval_rejected", &format!("id={approval_id}"));
-                                    return (format!("⛔ Command blocked: approval was not granted for `{}`.", &cmd[..cmd.len().min(120)]), Vec::new(), 0);
+                                    return (NativeToolResult::failure(format!("⛔ Command blocked: approval was not granted for `{}`.", &cmd[..cmd.len().min(120)])), 0);
                                 }
                                 llama_chat_db::event_log::log_event(conversation_id, "approval_granted", &format!("id={approval_id}"));
                             }
@@ -417,12 +498,12 @@ pub(crate) fn execute_single_tool(
                             });
                         }
                     });
-                    return (text, Vec::new(), 0);
+                    return (NativeToolResult::text_only(text), 0);
                 } else {
                     log_info!(conversation_id, "🐚 Batch: streaming execute_command (timeout={}s): {}", timeout_secs.unwrap_or(300), rtk_cmd);
                     let sender_clone = token_sender.clone();
                     let exec_start = Instant::now();
-                    let text = execute_command_streaming_with_timeout(&rtk_cmd, cancel, timeout_secs, &mut |line| {
+                    let outcome = run_command_streaming(&rtk_cmd, cancel, timeout_secs, &mut |line| {
                         if let Some(ref sender) = sender_clone {
                             let _ = sender.send(TokenData {
                                 token: format!("{}\n", strip_ansi_codes(line)),
@@ -439,7 +520,12 @@ pub(crate) fn execute_single_tool(
                     if let Some(ref sender) = token_sender {
                         let _ = sender.send(TokenData::default());
                     }
-                    return (text, Vec::new(), duration_ms);
+                    let result = NativeToolResult {
+                        text: outcome.output,
+                        images: Vec::new(),
+                        success: outcome.success,
+                    };
+                    return (result, duration_ms);
                 }
             }
         }
@@ -461,9 +547,78 @@ pub(crate) fn execute_single_tool(
         if let Some(ref sender) = token_sender {
             let _ = sender.send(TokenData::default());
         }
-        return (native_result.text, native_result.images, native_duration_ms);
+        return (native_result, native_duration_ms);
     }
 
     // Fallback: unknown tool
-    (format!("Error: Unknown or unsupported tool '{name}'"), Vec::new(), 0)
+    (NativeToolResult::failure(format!("Error: Unknown or unsupported tool '{name}'")), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    fn gated_db(tools: &[&str]) -> llama_chat_db::SharedDatabase {
+        let db: llama_chat_db::SharedDatabase =
+            Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let config = llama_chat_db::config::DbSamplerConfig {
+            require_confirmation_for: tools.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        db.save_config(&config).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_gated_tool_runs_only_after_confirm() {
+        let db = gated_db(&["write_file"]);
+        assert!(requires_confirmation("write_file", &db));
+        assert!(!requires_confirmation("read_file", &db));
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenData>();
+        let executed = Arc::new(AtomicBool::new(false));
+        let confirmer = {
+            let db = db.clone();
+            let executed = executed.clone();
+            std::thread::spawn(move || {
+                let event = rx.blocking_recv().unwrap();
+                let request = event.confirmation_required.expect("confirmation event");
+                assert_eq!(request.tool, "write_file");
+                assert_eq!(request.args["path"], "notes.txt");
+                std::thread::sleep(std::time::Duration::from_millis(700));
+                assert!(!executed.load(Ordering::SeqCst), "ran before confirm");
+                db.resolve_pending_approval(&request.id, "approved").unwrap();
+            })
+        };
+
+        let args = serde_json::json!({"path": "notes.txt", "content": "hi"});
+        let declined = await_tool_confirmation("write_file", &args, "conv", &Some(tx), None, &db);
+        if declined.is_none() {
+            executed.store(true, Ordering::SeqCst);
+        }
+        confirmer.join().unwrap();
+        assert!(executed.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_declined_or_cancelled_confirmation_blocks_the_call() {
+        let db = gated_db(&["execute_command"]);
+        let (tx, mut rx) = mpsc::unbounded_channel::<TokenData>();
+        let rejecter = {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                let id = rx.blocking_recv().unwrap().confirmation_required.unwrap().id;
+                db.resolve_pending_approval(&id, "rejected").unwrap();
+            })
+        };
+        let args = serde_json::json!({"command": "rm -rf build"});
+        let declined = await_tool_confirmation("execute_command", &args, "conv", &Some(tx), None, &db);
+        rejecter.join().unwrap();
+        assert!(declined.unwrap().contains("not run"));
+
+        let cancel = AtomicBool::new(true);
+        let declined = await_tool_confirmation("execute_command", &args, "conv", &None, Some(&cancel), &db);
+        assert!(declined.is_some());
+    }
 }
@@ -20,6 +20,7 @@ pub(crate) use summarize::{
     SUMMARIZE_THRESHOLD,
     summarize_tool_output,
 };
+pub(crate) use truncate::normalize_tool_whitespace;
 #[cfg(feature = "vision")]
 pub(crate) use image_summary::run_image_vision_summary;
 
@@ -109,6 +109,28 @@ pub fn maybe_truncate_tool_output(output: &str, tool_name: &str, conversation_id
     )
 }
 
+/// Strip trailing whitespace from every line and collapse runs of blank lines
+/// into one. Indentation is kept; leading and trailing blank lines are dropped.
+pub(crate) fn normalize_tool_whitespace(output: &str) -> String {
+    let mut normalized = String::with_capacity(output.len());
+    let mut pending_blank = false;
+    for line in output.lines().map(str::trim_end) {
+        if line.is_empty() {
+            pending_blank = !normalized.is_empty();
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push('\n');
+            if pending_blank {
+                normalized.push('\n');
+            }
+        }
+        pending_blank = false;
+        normalized.push_str(line);
+    }
+    normalized
+}
+
 /// Summarize large tool output using recursive map-reduce (sub-agent approach).
 /// Falls back to truncation if summarization fails.
 pub fn maybe_summarize_tool_output(
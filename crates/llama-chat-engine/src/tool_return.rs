@@ -0,0 +1,147 @@
+//! `auto_execute_tools = false`: stop generation at the first complete tool
+//! call and hand the parsed call back to the caller instead of running it.
+//! With auto-execution on, the same detection stops the run at a call made
+//! after `max_tool_iterations` calls (and the wrap-up notice) have run.
+
+use llama_chat_types::ToolCallRequest;
+
+use super::tool_parser::FORMAT_PRIORITY;
+use super::tool_tags::ToolTags;
+
+/// What the token loop should do after a token that may close a tool call.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ToolCallAction {
+    /// Run the normal inline detection/execution path.
+    Execute,
+    /// Return these calls with finish_reason "tool_calls".
+    Return(Vec<ToolCallRequest>),
+    /// `max_tool_iterations` calls already ran and the model was told to wrap
+    /// up; stop with finish_reason "tool_limit" instead of executing these.
+    LimitReached(Vec<ToolCallRequest>),
+    /// No complete call yet; keep generating.
+    Continue,
+}
+
+/// Whether `executed` calls use up a `max_tool_iterations` budget (0 = no limit).
+pub(crate) fn tool_iterations_exhausted(executed: u32, limit: u32) -> bool {
+    limit > 0 && executed >= limit
+}
+
+/// Decide how to handle tool calls in `response[scan_pos..]`.
+pub(crate) fn tool_call_action(
+    auto_execute: bool,
+    limit_reached: bool,
+    response: &str,
+    scan_pos: usize,
+    tags: &ToolTags,
+) -> ToolCallAction {
+    if auto_execute && !limit_reached {
+        return ToolCallAction::Execute;
+    }
+    let mut start = scan_pos.min(response.len());
+    while !response.is_char_boundary(start) {
+        start += 1;
+    }
+    match detect_tool_calls(&response[start..], tags) {
+        Some(calls) if auto_execute => ToolCallAction::LimitReached(calls),
+        Some(calls) => ToolCallAction::Return(calls),
+        None => ToolCallAction::Continue,
+    }
+}
+
+/// Parse the complete tool call(s) in `text` without executing them.
+fn detect_tool_calls(text: &str, tags: &ToolTags) -> Option<Vec<ToolCallRequest>> {
+    let command_text = detect_command_text(text, tags)?;
+    parse_command_text(&command_text)
+}
+
+/// Every tool call in a finished response, in order. Used to report the calls
+/// of an auto-executed run so clients don't have to re-parse the text.
+pub(crate) fn extract_tool_calls(response: &str, tags: &ToolTags) -> Vec<ToolCallRequest> {
+    let mut calls = Vec::new();
+    let mut rest = response;
+    while let Some(command_text) = detect_command_text(rest, tags) {
+        calls.extend(parse_command_text(&command_text).unwrap_or_default());
+        // Harmony and Gemma 4 detectors rewrite the call, so it can't be
+        // located in the text; stop after their first match.
+        let Some(pos) = rest.find(command_text.as_str()) else {
+            break;
+        };
+        rest = &rest[pos + command_text.len()..];
+    }
+    calls
+}
+
+fn detect_command_text(text: &str, tags: &ToolTags) -> Option<String> {
+    FORMAT_PRIORITY
+        .iter()
+        .find_map(|&(_, detect)| detect(text, tags))
+}
+
+fn parse_command_text(command_text: &str) -> Option<Vec<ToolCallRequest>> {
+    let raw_span = command_text.trim();
+    let tool_format = crate::model_manager::loaded_tool_format();
+    let calls: Vec<ToolCallRequest> =
+        llama_chat_tools::try_parse_all_from_raw_for_format(command_text, tool_format)
+            .into_iter()
+            .map(|(name, arguments)| ToolCallRequest {
+                name,
+                arguments,
+                raw_span: Some(raw_span.to_string()),
+            })
+            .collect();
+    if !calls.is_empty() {
+        return Some(calls);
+    }
+    // Legacy SYSTEM.EXEC blocks carry a bare shell command
+    (!raw_span.is_empty()).then(|| {
+        vec![ToolCallRequest {
+            name: "execute_command".to_string(),
+            arguments: serde_json::json!({ "command": raw_span }),
+            raw_span: Some(raw_span.to_string()),
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qwen_tags() -> ToolTags {
+        ToolTags::new(
+            "<tool_call>",
+            "</tool_call>",
+            "<tool_response>",
+            "</tool_response>",
+        )
+    }
+
+    #[test]
+    fn test_return_mode_ignores_text_before_scan_pos() {
+        let tags = qwen_tags();
+        let response = "<tool_call>{\"name\": \"a\", \"arguments\": {}}</tool_call> done.";
+        let scan_pos = response.find(" done").unwrap();
+        assert_eq!(
+            tool_call_action(false, false, response, scan_pos, &tags),
+            ToolCallAction::Continue
+        );
+    }
+
+    #[test]
+    fn test_final_response_yields_one_entry_per_call() {
+        let tags = qwen_tags();
+        let response = "Let me check.\n<tool_call>\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"README.md\"}}\n</tool_call>\n<tool_response>\n# Demo\n</tool_response>\nThe file is a short readme.";
+        let calls = extract_tool_calls(response, &tags);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "read_file");
+        assert_eq!(
+            calls[0].arguments,
+            serde_json::json!({ "path": "README.md" })
+        );
+        assert_eq!(
+            calls[0].raw_span.as_deref(),
+            Some("{\"name\": \"read_file\", \"arguments\": {\"path\": \"README.md\"}}")
+        );
+        assert!(extract_tool_calls("No tools needed: 2 > 1.", &tags).is_empty());
+    }
+}
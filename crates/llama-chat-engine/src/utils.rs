@@ -15,3 +15,73 @@ pub fn silent_command(program: &str) -> Command {
     }
     cmd
 }
+
+/// Rough token count for `text` without a tokenizer, for budgeting before a
+/// model is loaded. An estimate only: prefer the model's tokenizer when there
+/// is one. Tuned on BPE vocabularies (Llama 3, Qwen, cl100k), where a common
+/// English word with its leading space is one token, digits and punctuation
+/// split finer, and non-ASCII text is roughly one token per character.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut run = 1;
+        let mut take_while = |pred: fn(char) -> bool| {
+            while chars.next_if(|&next| pred(next)).is_some() {
+                run += 1;
+            }
+        };
+        tokens += match c {
+            c if c.is_ascii_alphabetic() => {
+                take_while(|c| c.is_ascii_alphabetic());
+                ((run + 2) / 6).max(1)
+            }
+            c if c.is_ascii_digit() => {
+                take_while(|c| c.is_ascii_digit());
+                run.div_ceil(3)
+            }
+            '\n' => {
+                take_while(|c| c == '\n');
+                1
+            }
+            // A single space joins the next word's token; indentation doesn't
+            c if c.is_whitespace() => {
+                take_while(|c| c.is_whitespace() && c != '\n');
+                if run > 1 { run.div_ceil(4) } else { 0 }
+            }
+            c if c.is_ascii() => {
+                take_while(|c| c.is_ascii_punctuation());
+                run.div_ceil(2)
+            }
+            _ => 1,
+        };
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens_is_close_to_real_counts() {
+        // Token counts from the cl100k / Llama 3 tokenizers
+        let samples = [
+            ("The quick brown fox jumps over the lazy dog.", 10),
+            (
+                "Four score and seven years ago our fathers brought forth on this continent, \
+                 a new nation, conceived in Liberty, and dedicated to the proposition that all \
+                 men are created equal.",
+                34,
+            ),
+            ("fn main() {\n    println!(\"Hello, world!\");\n}", 14),
+            (r#"{"name": "Ada", "age": 36}"#, 12),
+            ("你好，世界", 5),
+        ];
+        for (text, real) in samples {
+            let ratio = estimate_tokens(text) as f64 / real as f64;
+            assert!((0.75..=1.33).contains(&ratio), "{text:?}: {} vs {real}", estimate_tokens(text));
+        }
+        assert_eq!(estimate_tokens(""), 0);
+    }
+}
@@ -1,16 +1,20 @@
 // VRAM and GPU layer calculation utilities
 // Used by model_manager.rs for automatic GPU layer calculation
 //
-// Future enhancements planned:
-// - Dynamic VRAM detection using nvidia-smi (get_available_vram_gb)
-// - Accurate KV cache size calculation (calculate_kv_cache_size_gb)
-// - Automatic context size reduction when VRAM limited (calculate_safe_context_size)
+// KV-cache sizing (KvCacheShape) follows llama.cpp: 2 × layers × kv_heads × head_dim
+// × bytes/element per token, so GQA models get a proportionally smaller cache.
 
 use gguf_llms::{GgufHeader, GgufReader, Value};
+use std::collections::HashMap;
 use std::fs;
 use std::io::BufReader;
 
+use super::gguf_utils::{read_gguf_layout, read_gguf_metadata_raw};
+use super::gpu_devices::enumerate_gpus;
 use super::utils::silent_command;
+use super::context_eval::resolve_context_size;
+use llama_chat_types::KvCacheType;
+use serde::Serialize;
 
 // Constants for VRAM calculations
 pub const DEFAULT_VRAM_GB: f64 = 22.0; // Default VRAM assumption if detection fails
@@ -19,6 +23,7 @@ pub const VRAM_SAFETY_MARGIN_GB: f64 = 2.0; // Reserve 2GB for system overhead
 #[allow(dead_code)]
 pub const MB_TO_GB: f64 = 1024.0;
 pub const BYTES_TO_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+const BYTES_TO_MB: f64 = 1024.0 * 1024.0;
 #[allow(dead_code)]
 pub const KV_CACHE_MULTIPLIER: f64 = 4.0; // key + value, 2 bytes each (fp16)
 
@@ -34,6 +39,22 @@ pub const XLARGE_MODEL_LAYERS: u32 = 80;
 // VRAM utilization threshold
 pub const MIN_VRAM_RATIO: f64 = 0.1; // Minimum 10% VRAM required for GPU offloading
 
+// Requested gpu_layers at or above this mean "offload every layer" (999 is the common UI value)
+pub const ALL_GPU_LAYERS_SENTINEL: u32 = 999;
+
+/// Resolve a requested gpu_layers value against the model's block_count.
+///
+/// Values above the layer count (including the "all" sentinel / u32::MAX) clamp to
+/// block_count: ngl > n_layers would also offload the output layer, which hangs
+/// llama_decode on some architectures. Without a known block_count the request is
+/// passed through unchanged.
+pub fn normalize_gpu_layers(requested: u32, block_count: Option<u32>) -> u32 {
+    match block_count {
+        Some(n_layers) => requested.min(n_layers),
+        None => requested,
+    }
+}
+
 /// Detect available VRAM using nvidia-smi.
 /// Returns the available VRAM in GB, or DEFAULT_VRAM_GB if detection fails.
 ///
@@ -182,17 +203,7 @@ pub fn calculate_optimal_gpu_layers(model_path: &str) -> u32 {
     let vram_ratio = (available_vram_gb / model_size_gb).min(1.0);
 
     // Use actual layer count if available, otherwise estimate from model size
-    let total_layers = actual_layers.unwrap_or(
-        if model_size_gb < SMALL_MODEL_GB {
-            SMALL_MODEL_LAYERS
-        } else if model_size_gb < MEDIUM_MODEL_GB {
-            MEDIUM_MODEL_LAYERS
-        } else if model_size_gb < LARGE_MODEL_GB {
-            LARGE_MODEL_LAYERS
-        } else {
-            XLARGE_MODEL_LAYERS
-        }
-    );
+    let total_layers = actual_layers.unwrap_or_else(|| estimate_layer_count(model_size_gb));
 
     let mut optimal_layers = (total_layers as f64 * vram_ratio).floor() as u32;
 
@@ -238,161 +249,295 @@ pub fn calculate_optimal_gpu_layers(model_path: &str) -> u32 {
     optimal_layers.max(if vram_ratio > MIN_VRAM_RATIO { 1 } else { 0 })
 }
 
-/// Calculate safe context size based on available VRAM and model parameters.
-/// Returns (safe_context_size, was_reduced).
-///
-/// TODO: Integrate with model_manager.rs to automatically reduce context size
-/// when user requests exceed available VRAM capacity.
-#[allow(dead_code)]
-pub fn calculate_safe_context_size(
-    model_path: &str,
-    requested_ctx: u32,
-    available_vram_gb: Option<f64>,
-    gpu_layers: Option<u32>,
-) -> (u32, bool) {
-    let available_vram =
-        available_vram_gb.unwrap_or_else(|| get_available_vram_gb().unwrap_or(DEFAULT_VRAM_GB));
-
-    // Read model metadata to get architecture details
-    let (n_layers, n_kv_heads, embedding_len) = if let Ok(file) = fs::File::open(model_path) {
-        let mut reader = BufReader::new(file);
-        if let Ok(header) = GgufHeader::parse(&mut reader) {
-            if let Ok(metadata) = GgufReader::read_metadata(&mut reader, header.n_kv) {
-                // Try to get layer count, kv heads, embedding length
-                let layers = metadata
-                    .get("gemma3.block_count")
-                    .or_else(|| metadata.get("llama.block_count"))
-                    .and_then(|v| match v {
-                        Value::Uint32(n) => Some(*n),
-                        _ => None,
-                    })
-                    .unwrap_or(48); // Default to 48 layers
-
-                let kv_heads = metadata
-                    .get("gemma3.attention.head_count_kv")
-                    .or_else(|| metadata.get("llama.attention.head_count_kv"))
-                    .and_then(|v| match v {
-                        Value::Uint32(n) => Some(*n),
-                        _ => None,
-                    })
-                    .unwrap_or(8); // Default to 8 KV heads
-
-                let emb_len = metadata
-                    .get("gemma3.embedding_length")
-                    .or_else(|| metadata.get("llama.embedding_length"))
-                    .and_then(|v| match v {
-                        Value::Uint32(n) => Some(*n),
-                        _ => None,
-                    })
-                    .unwrap_or(3840); // Default to 3840
-
-                (layers, kv_heads, emb_len)
-            } else {
-                (48, 8, 3840) // Defaults
-            }
-        } else {
-            (48, 8, 3840)
-        }
-    } else {
-        (48, 8, 3840)
-    };
-
-    // Calculate head dimension
-    let head_dim = embedding_len / (n_kv_heads * 2); // Rough estimate
-
-    // Estimate model size
-    let model_size_gb = if let Ok(metadata) = fs::metadata(model_path) {
-        metadata.len() as f64 / BYTES_TO_GB
+/// Layer count guessed from file size when GGUF metadata has no block_count.
+fn estimate_layer_count(model_size_gb: f64) -> u32 {
+    if model_size_gb < SMALL_MODEL_GB {
+        SMALL_MODEL_LAYERS
+    } else if model_size_gb < MEDIUM_MODEL_GB {
+        MEDIUM_MODEL_LAYERS
+    } else if model_size_gb < LARGE_MODEL_GB {
+        LARGE_MODEL_LAYERS
     } else {
-        MEDIUM_MODEL_GB // Default estimate (12GB for ~12B parameter model)
-    };
+        XLARGE_MODEL_LAYERS
+    }
+}
 
-    // Calculate GPU layers (auto-detect if not provided by user)
-    let gpu_layers_count = gpu_layers.unwrap_or_else(|| calculate_optimal_gpu_layers(model_path));
+/// Bytes per KV-cache element for the default fp16 cache.
+pub const F16_KV_BYTES: f64 = 2.0;
+
+/// Bytes per KV-cache element. Quantized types store 32-element blocks with an
+/// fp16 scale (and, for the `_1` types, an fp16 min): Q8_0 = 34 bytes/block,
+/// Q5_1 = 24, Q5_0 = 22, Q4_1 = 20, Q4_0 = 18. TurboQuant types run as Q4_0.
+pub fn kv_cache_bytes_per_element(kv_cache_type: KvCacheType) -> f64 {
+    match kv_cache_type {
+        KvCacheType::F32 => 4.0,
+        KvCacheType::F16 => F16_KV_BYTES,
+        KvCacheType::Q8_0 => 34.0 / 32.0,
+        KvCacheType::Q5_1 => 24.0 / 32.0,
+        KvCacheType::Q5_0 => 22.0 / 32.0,
+        KvCacheType::Q4_1 => 20.0 / 32.0,
+        KvCacheType::Q4_0 | KvCacheType::Turbo2 | KvCacheType::Turbo3 | KvCacheType::Turbo4 => {
+            18.0 / 32.0
+        }
+    }
+}
 
-    // Calculate what fraction of the model is on GPU
-    let gpu_fraction = (gpu_layers_count as f64) / (n_layers as f64);
-    let model_vram_usage = model_size_gb * gpu_fraction;
+/// Smallest context `safe_context_for_budget` will reduce to.
+pub const MIN_SAFE_CONTEXT: u32 = 2048;
+
+/// Attention geometry needed to size the KV cache, read from GGUF metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KvCacheShape {
+    pub block_count: u32,
+    pub embedding_length: u32,
+    pub head_count: u32,
+    /// KV heads (< head_count for GQA/MQA models; equals head_count otherwise).
+    pub head_count_kv: u32,
+}
 
-    log_info!(
-        "system",
-        "GPU layers: {}/{} ({:.1}% of model)",
-        gpu_layers_count,
-        n_layers,
-        gpu_fraction * 100.0
-    );
-    log_info!(
-        "system",
-        "Model VRAM usage: {:.2}GB ({:.1}% of {:.2}GB total)",
-        model_vram_usage,
-        gpu_fraction * 100.0,
-        model_size_gb
-    );
+impl KvCacheShape {
+    /// Read `{arch}.block_count`, `{arch}.embedding_length` and the attention head
+    /// counts. Missing `head_count_kv` means no GQA (one KV head per query head).
+    pub fn from_metadata(metadata: &HashMap<String, Value>) -> Option<Self> {
+        let arch = match metadata.get("general.architecture") {
+            Some(Value::String(s)) => s.as_str(),
+            _ => "llama",
+        };
+        let get = |field: &str| match metadata.get(&format!("{arch}.{field}"))? {
+            Value::Uint32(n) => Some(*n),
+            Value::Uint64(n) => u32::try_from(*n).ok(),
+            _ => None,
+        };
+        let head_count = get("attention.head_count").filter(|&h| h > 0)?;
+        Some(Self {
+            block_count: get("block_count")?,
+            embedding_length: get("embedding_length")?,
+            head_count,
+            head_count_kv: get("attention.head_count_kv").unwrap_or(head_count),
+        })
+    }
 
-    // Available VRAM for KV cache = total - model_on_gpu - overhead
-    let vram_for_cache = (available_vram - model_vram_usage - VRAM_SAFETY_MARGIN_GB).max(0.0);
+    pub fn head_dim(&self) -> u32 {
+        self.embedding_length / self.head_count
+    }
 
-    log_info!(
-        "system",
-        "Available: {:.2}GB, Model: {:.2}GB, Available for KV cache: {:.2}GB",
-        available_vram,
-        model_size_gb,
-        vram_for_cache
-    );
+    /// KV-cache bytes per token of context: K and V for every layer's KV heads.
+    pub fn bytes_per_token(&self, bytes_per_element: f64) -> f64 {
+        2.0 * self.block_count as f64
+            * self.head_count_kv as f64
+            * self.head_dim() as f64
+            * bytes_per_element
+    }
 
-    // Calculate KV cache size for requested context
-    let requested_cache_gb =
-        calculate_kv_cache_size_gb(requested_ctx, n_layers, n_kv_heads, head_dim);
+    /// Total KV-cache bytes for `n_ctx` tokens.
+    pub fn kv_cache_bytes(&self, n_ctx: u32, bytes_per_element: f64) -> u64 {
+        (self.bytes_per_token(bytes_per_element) * n_ctx as f64) as u64
+    }
+}
 
-    log_info!(
-        "system",
-        "Requested context: {} tokens, KV cache: {:.2}GB",
+/// Pick the largest context that fits the VRAM left after model weights and overhead.
+/// Returns (context, was_reduced). Reduced values round down to a power of two,
+/// never below `MIN_SAFE_CONTEXT`.
+pub fn safe_context_for_budget(
+    requested_ctx: u32,
+    shape: &KvCacheShape,
+    bytes_per_element: f64,
+    model_vram_gb: f64,
+    available_vram_gb: f64,
+) -> (u32, bool) {
+    let vram_for_cache_gb = available_vram_gb - model_vram_gb - VRAM_SAFETY_MARGIN_GB;
+    safe_context_for_bytes(
         requested_ctx,
-        requested_cache_gb
-    );
+        shape.bytes_per_token(bytes_per_element),
+        (vram_for_cache_gb.max(0.0) * BYTES_TO_GB) as u64,
+    )
+}
 
-    if requested_cache_gb <= vram_for_cache {
-        // Requested context fits in VRAM
-        log_info!("system", "✓ Requested context size fits in available VRAM");
+/// `safe_context_for_budget` with the cache budget already worked out: the largest
+/// context whose `bytes_per_token` KV cache fits in `cache_budget_bytes`.
+pub fn safe_context_for_bytes(requested_ctx: u32, bytes_per_token: f64, cache_budget_bytes: u64) -> (u32, bool) {
+    if bytes_per_token * requested_ctx as f64 <= cache_budget_bytes as f64 {
         return (requested_ctx, false);
     }
 
-    // Calculate safe context size
-    // max_tokens = vram_for_cache / (layers × kv_heads × head_dim × 4)
-    let bytes_per_token =
-        n_layers as f64 * n_kv_heads as f64 * head_dim as f64 * KV_CACHE_MULTIPLIER;
-    let safe_tokens = ((vram_for_cache * BYTES_TO_GB) / bytes_per_token) as u32;
-
-    // Round down to nearest power of 2 for cleaner values
-    let safe_ctx = if safe_tokens >= 32768 {
-        32768
-    } else if safe_tokens >= 16384 {
-        16384
-    } else if safe_tokens >= 8192 {
-        8192
-    } else if safe_tokens >= 4096 {
-        4096
+    let safe_tokens = (cache_budget_bytes as f64 / bytes_per_token) as u32;
+    let rounded = if safe_tokens == 0 {
+        0
     } else {
-        2048
+        1u32 << (31 - safe_tokens.leading_zeros())
     };
+    (rounded.max(MIN_SAFE_CONTEXT).min(requested_ctx), true)
+}
 
-    log_warn!(
-        "system",
-        "⚠️  Requested context ({}) exceeds VRAM capacity!",
-        requested_ctx
-    );
-    log_warn!(
-        "system",
-        "⚠️  Auto-reducing to safe context size: {} tokens",
-        safe_ctx
-    );
+/// VRAM left for the KV cache once `gpu_layers` of the model's `block_count` layers
+/// are offloaded: the free VRAM before the load, minus that share of the weights
+/// and the safety margin. `load_model` stores it to size contexts against.
+pub fn kv_cache_budget_bytes(
+    model_file_bytes: u64,
+    gpu_layers: u32,
+    block_count: u32,
+    free_vram_bytes: u64,
+) -> u64 {
+    let gpu_fraction = gpu_layers.min(block_count) as f64 / block_count.max(1) as f64;
+    let weights_bytes = model_file_bytes as f64 * gpu_fraction;
+    let margin_bytes = VRAM_SAFETY_MARGIN_GB * BYTES_TO_GB;
+    (free_vram_bytes as f64 - weights_bytes - margin_bytes).max(0.0) as u64
+}
+
+/// Pre-load recommendation shown before a model is loaded (`/api/model/recommend`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Recommendation {
+    /// Summed over all GPUs; 0 when no GPU is visible.
+    pub total_vram_mb: u64,
+    pub free_vram_mb: u64,
+    /// Weights plus the KV cache for `recommended_context`.
+    pub est_model_mb: u64,
+    pub recommended_gpu_layers: u32,
+    /// Model layer count (from GGUF, or estimated from file size).
+    pub total_layers: u32,
+    pub recommended_context: u32,
+}
+
+/// What the recommendation needs to know about a model.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelFootprint {
+    pub file_size_bytes: u64,
+    /// Size of the per-layer `blk.N.*` tensors, from the GGUF tensor layout.
+    /// `None` splits the whole file evenly across layers.
+    pub block_bytes: Option<u64>,
+    pub block_count: Option<u32>,
+    pub context_length: Option<u32>,
+    pub shape: Option<KvCacheShape>,
+}
+
+/// Recommend GPU layers and context for a model given current VRAM figures.
+///
+/// The context starts where a generation would (`resolve_context_size`): the
+/// configured `context_size`, else the model's context bounded by the
+/// configured `default_context_cap`.
+/// With no GPU (`total_vram_mb == 0`) everything runs on the CPU. When the
+/// weights fit, all layers are offloaded and the context shrinks to what the
+/// remaining VRAM holds; otherwise layers are offloaded in proportion to the
+/// budget with the minimum context.
+pub fn recommend_for(
+    model: &ModelFootprint,
+    context_size: Option<u32>,
+    default_context_cap: u32,
+    total_vram_mb: u64,
+    free_vram_mb: u64,
+) -> Recommendation {
+    let model_mb = model.file_size_bytes as f64 / BYTES_TO_MB;
+    let total_layers = model
+        .block_count
+        .unwrap_or_else(|| estimate_layer_count(model_mb / MB_TO_GB));
+    let (target_ctx, _) =
+        resolve_context_size(context_size, model.context_length, default_context_cap);
+    let kv_mb = |ctx: u32| match &model.shape {
+        Some(shape) => shape.kv_cache_bytes(ctx, F16_KV_BYTES) as f64 / BYTES_TO_MB,
+        None => 0.0,
+    };
+
+    let budget_mb = free_vram_mb as f64 - VRAM_SAFETY_MARGIN_GB * MB_TO_GB;
+    let (recommended_gpu_layers, recommended_context) = if total_vram_mb == 0 {
+        (0, target_ctx)
+    } else if model_mb <= budget_mb {
+        let ctx = match &model.shape {
+            Some(shape) => {
+                safe_context_for_budget(
+                    target_ctx,
+                    shape,
+                    F16_KV_BYTES,
+                    model_mb / MB_TO_GB,
+                    free_vram_mb as f64 / MB_TO_GB,
+                )
+                .0
+            }
+            None => target_ctx,
+        };
+        (total_layers, ctx)
+    } else {
+        // Layer weights and cache are split per layer; offload as many layers as fit
+        let ctx = target_ctx.min(MIN_SAFE_CONTEXT);
+        let layer_weights_mb = model.block_bytes.map_or(model_mb, |b| b as f64 / BYTES_TO_MB);
+        let per_layer_mb = (layer_weights_mb + kv_mb(ctx)) / total_layers.max(1) as f64;
+        let layers = (budget_mb.max(0.0) / per_layer_mb).floor() as u32;
+        (layers.min(total_layers), ctx)
+    };
+
+    Recommendation {
+        total_vram_mb,
+        free_vram_mb,
+        est_model_mb: (model_mb + kv_mb(recommended_context)).round() as u64,
+        recommended_gpu_layers,
+        total_layers,
+        recommended_context,
+    }
+}
+
+/// Read a model's GGUF metadata and recommend GPU layers and context for the
+/// GPUs currently visible (CPU-only when there are none), starting from the
+/// configured `context_size` / `default_context_cap`.
+pub fn recommend(
+    model_path: &str,
+    context_size: Option<u32>,
+    default_context_cap: u32,
+) -> Result<Recommendation, String> {
+    let file_size_bytes = match fs::metadata(model_path) {
+        Ok(m) if m.is_file() => m.len(),
+        Ok(_) => return Err("Path is not a file".to_string()),
+        Err(e) => return Err(format!("Cannot read model file: {e}")),
+    };
+    let metadata =
+        read_gguf_metadata_raw(model_path).map_err(|e| format!("Not a GGUF model: {e}"))?;
+    let arch = match metadata.get("general.architecture") {
+        Some(Value::String(s)) => s.as_str(),
+        _ => "llama",
+    };
+    let arch_u32 = |field: &str| match metadata.get(&format!("{arch}.{field}"))? {
+        Value::Uint32(n) => Some(*n),
+        Value::Uint64(n) => u32::try_from(*n).ok(),
+        _ => None,
+    };
+    let block_bytes = read_gguf_layout(model_path)
+        .ok()
+        .map(|layout| layout.block_bytes(file_size_bytes))
+        .filter(|&bytes| bytes > 0);
+    let footprint = ModelFootprint {
+        file_size_bytes,
+        block_bytes,
+        block_count: arch_u32("block_count"),
+        context_length: arch_u32("context_length"),
+        shape: KvCacheShape::from_metadata(&metadata),
+    };
 
-    (safe_ctx, true) // Return safe context and flag that it was reduced
+    let gpus = enumerate_gpus();
+    let total_vram_mb = gpus.iter().map(|g| g.total_vram_mb).sum();
+    let free_vram_mb = gpus.iter().map(|g| g.free_vram_mb).sum();
+    Ok(recommend_for(&footprint, context_size, default_context_cap, total_vram_mb, free_vram_mb))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use llama_chat_types::DEFAULT_CONTEXT_CAP;
+
+    #[test]
+    fn test_normalize_gpu_layers_clamps_to_block_count() {
+        assert_eq!(normalize_gpu_layers(32, Some(16)), 16);
+        assert_eq!(normalize_gpu_layers(12, Some(16)), 12);
+        assert_eq!(normalize_gpu_layers(0, Some(16)), 0);
+    }
+
+    #[test]
+    fn test_normalize_gpu_layers_all_sentinel_resolves_to_block_count() {
+        assert_eq!(normalize_gpu_layers(ALL_GPU_LAYERS_SENTINEL, Some(40)), 40);
+        assert_eq!(normalize_gpu_layers(u32::MAX, Some(40)), 40);
+    }
+
+    #[test]
+    fn test_normalize_gpu_layers_unknown_block_count_passes_through() {
+        assert_eq!(normalize_gpu_layers(24, None), 24);
+    }
 
     #[test]
     fn test_calculate_kv_cache_size_gb() {
@@ -544,10 +689,229 @@ mod tests {
         assert!(layers == layers); // Always true, just checking it compiles/runs
     }
 
+    fn llama3_8b_metadata(kv_heads: u32) -> HashMap<String, Value> {
+        HashMap::from([
+            ("general.architecture".to_string(), Value::String("llama".to_string())),
+            ("llama.block_count".to_string(), Value::Uint32(32)),
+            ("llama.embedding_length".to_string(), Value::Uint32(4096)),
+            ("llama.attention.head_count".to_string(), Value::Uint32(32)),
+            ("llama.attention.head_count_kv".to_string(), Value::Uint32(kv_heads)),
+        ])
+    }
+
+    #[test]
+    fn test_kv_cache_shape_from_metadata() {
+        let shape = KvCacheShape::from_metadata(&llama3_8b_metadata(8)).unwrap();
+        assert_eq!(shape.block_count, 32);
+        assert_eq!(shape.head_count_kv, 8);
+        assert_eq!(shape.head_dim(), 128);
+
+        let mut no_gqa = llama3_8b_metadata(8);
+        no_gqa.remove("llama.attention.head_count_kv");
+        assert_eq!(KvCacheShape::from_metadata(&no_gqa).unwrap().head_count_kv, 32);
+
+        let mut no_heads = llama3_8b_metadata(8);
+        no_heads.remove("llama.attention.head_count");
+        assert!(KvCacheShape::from_metadata(&no_heads).is_none());
+    }
+
+    #[test]
+    fn test_kv_cache_bytes_formula() {
+        let shape = KvCacheShape::from_metadata(&llama3_8b_metadata(8)).unwrap();
+        // 2 (K+V) × 32 layers × 8 kv heads × 128 head_dim × 2 bytes = 128 KiB per token
+        assert_eq!(shape.bytes_per_token(F16_KV_BYTES), 131_072.0);
+        assert_eq!(shape.kv_cache_bytes(8192, F16_KV_BYTES), 1 << 30);
+
+        // Without GQA the cache is head_count / head_count_kv = 4× larger
+        let mha = KvCacheShape::from_metadata(&llama3_8b_metadata(32)).unwrap();
+        assert_eq!(
+            mha.kv_cache_bytes(8192, F16_KV_BYTES),
+            4 * shape.kv_cache_bytes(8192, F16_KV_BYTES)
+        );
+    }
+
+    #[test]
+    fn test_safe_context_for_budget() {
+        let shape = KvCacheShape::from_metadata(&llama3_8b_metadata(8)).unwrap();
+
+        // 8 GB − 4 GB weights − 2 GB margin = 2 GB → exactly 16K tokens of fp16 cache
+        assert_eq!(safe_context_for_budget(8192, &shape, F16_KV_BYTES, 4.0, 8.0), (8192, false));
+        assert_eq!(safe_context_for_budget(32768, &shape, F16_KV_BYTES, 4.0, 8.0), (16384, true));
+
+        // 1.5 GB left → 12K tokens fit, rounded down to 8K
+        assert_eq!(safe_context_for_budget(32768, &shape, F16_KV_BYTES, 4.5, 8.0), (8192, true));
+
+        // Nothing left → floor at MIN_SAFE_CONTEXT
+        assert_eq!(
+            safe_context_for_budget(32768, &shape, F16_KV_BYTES, 7.0, 8.0),
+            (MIN_SAFE_CONTEXT, true)
+        );
+    }
+
+    #[test]
+    fn test_kv_cache_budget_after_layer_offload() {
+        const GIB: u64 = 1 << 30;
+        // 8 GiB free, 8 GiB of weights: half the layers leave 8 − 4 − 2 = 2 GiB
+        assert_eq!(kv_cache_budget_bytes(8 * GIB, 16, 32, 8 * GIB), 2 * GIB);
+        // All layers offloaded leave nothing; a larger layer count is capped
+        assert_eq!(kv_cache_budget_bytes(8 * GIB, 999, 32, 8 * GIB), 0);
+
+        // The 2 GiB budget holds 16K tokens of the llama3 fp16 cache
+        let shape = KvCacheShape::from_metadata(&llama3_8b_metadata(8)).unwrap();
+        let per_token = shape.bytes_per_token(F16_KV_BYTES);
+        assert_eq!(safe_context_for_bytes(16384, per_token, 2 * GIB), (16384, false));
+        assert_eq!(safe_context_for_bytes(65536, per_token, 2 * GIB), (16384, true));
+        assert_eq!(safe_context_for_bytes(65536, per_token, 0), (MIN_SAFE_CONTEXT, true));
+    }
+
+    #[test]
+    fn test_quantized_kv_cache_bytes() {
+        let shape = KvCacheShape::from_metadata(&llama3_8b_metadata(8)).unwrap();
+        let f16 = shape.kv_cache_bytes(8192, kv_cache_bytes_per_element(KvCacheType::F16));
+        let q8 = shape.kv_cache_bytes(8192, kv_cache_bytes_per_element(KvCacheType::Q8_0));
+        let q4 = shape.kv_cache_bytes(8192, kv_cache_bytes_per_element(KvCacheType::Q4_0));
+
+        // 1 GiB fp16 → 34/64 GiB at Q8_0, 18/64 GiB at Q4_0
+        assert_eq!(f16, 1 << 30);
+        assert_eq!(q8, (1u64 << 30) / 64 * 34);
+        assert_eq!(q4, (1u64 << 30) / 64 * 18);
+    }
+
+    #[test]
+    fn test_quantized_kv_cache_extends_safe_context() {
+        let shape = KvCacheShape::from_metadata(&llama3_8b_metadata(8)).unwrap();
+        let budget = |kv| {
+            safe_context_for_budget(131072, &shape, kv_cache_bytes_per_element(kv), 4.0, 8.0)
+        };
+
+        // 2 GB for the cache: 16K tokens at fp16, ~30K at Q8_0, ~58K at Q4_0
+        assert_eq!(budget(KvCacheType::F16), (16384, true));
+        assert_eq!(budget(KvCacheType::Q8_0), (16384, true));
+        assert_eq!(budget(KvCacheType::Q4_0), (32768, true));
+    }
+
+    /// ~7.4 GB of weights for a 13B-class model: 40 layers, 5120 wide, 40 KV heads.
+    fn footprint_13b(context_length: u32) -> ModelFootprint {
+        ModelFootprint {
+            file_size_bytes: 7_600 * 1024 * 1024,
+            block_bytes: None,
+            block_count: Some(40),
+            context_length: Some(context_length),
+            shape: Some(KvCacheShape {
+                block_count: 40,
+                embedding_length: 5120,
+                head_count: 40,
+                head_count_kv: 40,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_recommend_full_offload_when_weights_fit() {
+        // 24 GB card: weights and a 4K fp16 cache (800 KiB/token) fit easily
+        let rec = recommend_for(&footprint_13b(4096), None, DEFAULT_CONTEXT_CAP, 24_576, 24_000);
+        assert_eq!(rec.recommended_gpu_layers, 40);
+        assert_eq!(rec.total_layers, 40);
+        // Model context (4K) is below the default cap, and it fits
+        assert_eq!(rec.recommended_context, 4096);
+        // Weights + 4K × 800 KiB/token of cache
+        assert_eq!(rec.est_model_mb, 7_600 + 3_200);
+        assert_eq!((rec.total_vram_mb, rec.free_vram_mb), (24_576, 24_000));
+    }
+
+    #[test]
+    fn test_recommend_caps_context_to_default_and_vram() {
+        // 128K model on a 24 GB card: capped to the default 8K, which fits
+        let rec = recommend_for(&footprint_13b(131072), None, DEFAULT_CONTEXT_CAP, 24_576, 24_000);
+        assert_eq!(rec.recommended_context, DEFAULT_CONTEXT_CAP);
+        assert_eq!(rec.recommended_gpu_layers, 40);
+
+        // 12 GB free: weights fit but only ~2.6 GB is left for cache → 2K context
+        let rec = recommend_for(&footprint_13b(131072), None, DEFAULT_CONTEXT_CAP, 12_288, 12_288);
+        assert_eq!(rec.recommended_gpu_layers, 40);
+        assert_eq!(rec.recommended_context, 2048);
+    }
+
+    #[test]
+    fn test_recommend_follows_the_configured_context() {
+        // default_context_cap = 16K lets the 128K model start from 16K
+        let rec = recommend_for(&footprint_13b(131072), None, 16384, 0, 0);
+        assert_eq!(rec.recommended_context, 16384);
+        // An explicit context_size wins over the cap, as it does at load
+        let rec = recommend_for(&footprint_13b(131072), Some(32768), DEFAULT_CONTEXT_CAP, 0, 0);
+        assert_eq!(rec.recommended_context, 32768);
+        // Cap 0: the model's full context
+        let rec = recommend_for(&footprint_13b(131072), None, 0, 0, 0);
+        assert_eq!(rec.recommended_context, 131072);
+    }
+
+    #[test]
+    fn test_recommend_partial_offload_when_weights_dont_fit() {
+        // 8 GB free − 2 GB margin = 6 GB for (7600 MB weights + 1600 MB cache) / 40 layers
+        let rec = recommend_for(&footprint_13b(131072), None, DEFAULT_CONTEXT_CAP, 8_192, 8_192);
+        assert_eq!(rec.recommended_context, MIN_SAFE_CONTEXT);
+        assert_eq!(rec.recommended_gpu_layers, 26);
+        assert!(rec.recommended_gpu_layers < rec.total_layers);
+
+        // With the tensor layout, the 600 MB of embeddings and output aren't split
+        // across layers: (7000 + 1600) / 40 per layer
+        let laid_out = ModelFootprint {
+            block_bytes: Some(7_000 * 1024 * 1024),
+            ..footprint_13b(131072)
+        };
+        let rec = recommend_for(&laid_out, None, DEFAULT_CONTEXT_CAP, 8_192, 8_192);
+        assert_eq!(rec.recommended_gpu_layers, 28);
+        assert_eq!(rec.est_model_mb, 7_600 + 1_600);
+    }
+
+    #[test]
+    fn test_recommend_without_gpu_runs_on_cpu() {
+        let rec = recommend_for(&footprint_13b(131072), None, DEFAULT_CONTEXT_CAP, 0, 0);
+        assert_eq!(rec.recommended_gpu_layers, 0);
+        assert_eq!(rec.recommended_context, DEFAULT_CONTEXT_CAP);
+        assert_eq!((rec.total_vram_mb, rec.free_vram_mb), (0, 0));
+
+        // No GGUF layer count or attention metadata: layers estimated from size
+        let bare = ModelFootprint {
+            file_size_bytes: 4 * 1024 * 1024 * 1024,
+            block_bytes: None,
+            block_count: None,
+            context_length: None,
+            shape: None,
+        };
+        let rec = recommend_for(&bare, None, DEFAULT_CONTEXT_CAP, 0, 0);
+        assert_eq!(rec.total_layers, SMALL_MODEL_LAYERS);
+        assert_eq!(rec.est_model_mb, 4096);
+    }
+
+    #[test]
+    fn test_recommend_reads_gguf_footprint() {
+        use crate::gguf_test_support::{write_test_gguf, TestValue};
+
+        let path = write_test_gguf(
+            "recommend",
+            &[
+                ("general.architecture", TestValue::Str("llama")),
+                ("llama.block_count", TestValue::U32(32)),
+                ("llama.context_length", TestValue::U32(4096)),
+                ("llama.embedding_length", TestValue::U32(4096)),
+                ("llama.attention.head_count", TestValue::U32(32)),
+                ("llama.attention.head_count_kv", TestValue::U32(8)),
+            ],
+        );
+        let rec = recommend(&path, None, DEFAULT_CONTEXT_CAP);
+        let _ = std::fs::remove_file(&path);
+
+        let rec = rec.unwrap();
+        assert_eq!(rec.total_layers, 32);
+        assert_eq!(rec.recommended_context, 4096);
+        assert!(recommend("nonexistent_file.gguf", None, DEFAULT_CONTEXT_CAP).is_err());
+    }
+
     #[test]
     fn test_safety_margin_is_subtracted_from_vram() {
         // The VRAM_SAFETY_MARGIN_GB should be 2.0
-        // This is used in calculate_safe_context_size to reserve memory
+        // This is used in safe_context_for_budget to reserve memory
         assert_eq!(VRAM_SAFETY_MARGIN_GB, 2.0);
 
         // Verify it's a reasonable value (not too high, not too low)
@@ -5,7 +5,9 @@ use std::collections::HashMap;
 use std::sync::Mutex as StdMutex;
 use std::sync::OnceLock;
 
+use crate::file_tools::FileToolLimits;
 use crate::utils::silent_command;
+use crate::NativeToolResult;
 
 // ─── ctags cache for lsp_query ─────────────────────────────────────────────────
 static CTAGS_CACHE: OnceLock<StdMutex<HashMap<String, (std::time::Instant, String)>>> = OnceLock::new();
@@ -80,16 +82,102 @@ pub fn lsp_ripgrep_symbols(target: &str) -> String {
     llama_chat_command::execute_command(&cmd)
 }
 
+/// Where and how `execute_python` runs a script. The default (not isolated)
+/// runs the ambient `python` with the inherited environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PythonEnv {
+    /// Working directory for the script; None = inherited.
+    pub working_dir: Option<std::path::PathBuf>,
+    /// Where the script file is written; None = the system temp dir.
+    pub script_dir: Option<std::path::PathBuf>,
+    /// Virtualenv whose interpreter runs the script.
+    pub venv: Option<std::path::PathBuf>,
+    /// Hide user site-packages (`PYTHONNOUSERSITE`).
+    pub isolated: bool,
+}
+
+impl PythonEnv {
+    /// From the config's `python_isolated` / `python_venv`. Isolated scripts of
+    /// a conversation run in its own session directory (see
+    /// [`python_session_dir`]), so parallel conversations never share a cwd.
+    pub fn from_config(db: Option<&llama_chat_db::SharedDatabase>, conversation_id: Option<&str>) -> Self {
+        let Some(config) = db.map(|db| db.load_config()) else {
+            return Self::default();
+        };
+        if !config.python_isolated {
+            return Self::default();
+        }
+        let session = python_session_dir(conversation_id);
+        Self {
+            working_dir: Some(session.join("work")),
+            script_dir: Some(session.join("scripts")),
+            venv: config
+                .python_venv
+                .filter(|v| !v.trim().is_empty())
+                .map(std::path::PathBuf::from),
+            isolated: true,
+        }
+    }
+
+    fn interpreter(&self) -> Result<std::path::PathBuf, String> {
+        let Some(venv) = &self.venv else {
+            return Ok("python".into());
+        };
+        let python = if cfg!(target_os = "windows") {
+            venv.join("Scripts").join("python.exe")
+        } else {
+            venv.join("bin").join("python")
+        };
+        if python.is_file() {
+            Ok(python)
+        } else {
+            Err(format!(
+                "Error: configured python_venv has no interpreter at {}",
+                python.display()
+            ))
+        }
+    }
+}
+
+/// `python_sessions/<conversation_id>` in the app data dir
+/// (`LLAMA_CHAT_DATA_DIR`, else the working directory). Calls without a
+/// conversation share `python_sessions/default`.
+pub fn python_session_dir(conversation_id: Option<&str>) -> std::path::PathBuf {
+    let base = std::env::var("LLAMA_CHAT_DATA_DIR").unwrap_or_else(|_| ".".to_string());
+    let key: String = conversation_id
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect();
+    let key = if key.is_empty() { "default".to_string() } else { key };
+    std::path::Path::new(&base).join("python_sessions").join(key)
+}
+
 /// Execute Python code by writing to a temp file and running it.
 /// This completely bypasses shell quoting — the code goes directly to a .py file.
-pub fn tool_execute_python(args: &Value) -> String {
+pub fn tool_execute_python(args: &Value) -> NativeToolResult {
+    tool_execute_python_in(args, &PythonEnv::default())
+}
+
+/// [`tool_execute_python`] with the working directory and interpreter from `env`.
+/// The call fails when the script exits non-zero.
+pub fn tool_execute_python_in(args: &Value, env: &PythonEnv) -> NativeToolResult {
     let code = match args.get("code").and_then(|v| v.as_str()) {
         Some(c) => c,
-        None => return "Error: 'code' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'code' argument is required".to_string()),
+    };
+    let interpreter = match env.interpreter() {
+        Ok(path) => path,
+        Err(e) => return NativeToolResult::failure(e),
     };
 
     // Write code to a temp file
-    let temp_dir = std::env::temp_dir();
+    let temp_dir = env.script_dir.clone().unwrap_or_else(std::env::temp_dir);
+    for dir in [Some(&temp_dir), env.working_dir.as_ref()].into_iter().flatten() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            return NativeToolResult::failure(format!("Error creating {}: {e}", dir.display()));
+        }
+    }
     let nanos = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -97,13 +185,23 @@ pub fn tool_execute_python(args: &Value) -> String {
     let temp_file = temp_dir.join(format!("llama_tool_{nanos}.py"));
 
     if let Err(e) = std::fs::write(&temp_file, code) {
-        return format!("Error writing temp file: {e}");
+        return NativeToolResult::failure(format!("Error writing temp file: {e}"));
     }
 
     // Run python on the temp file — no shell involved
-    let result = silent_command("python")
-        .arg(&temp_file)
-        .output();
+    let mut cmd = silent_command(&interpreter.to_string_lossy());
+    cmd.arg(&temp_file);
+    if let Some(dir) = &env.working_dir {
+        cmd.current_dir(dir);
+    }
+    if env.isolated {
+        cmd.env("PYTHONNOUSERSITE", "1");
+    }
+    if let Some(venv) = &env.venv {
+        cmd.env("VIRTUAL_ENV", venv);
+        cmd.env_remove("PYTHONHOME");
+    }
+    let result = cmd.output();
 
     // Clean up temp file
     let _ = std::fs::remove_file(&temp_file);
@@ -112,20 +210,31 @@ pub fn tool_execute_python(args: &Value) -> String {
         Ok(output) => {
             let stdout = String::from_utf8_lossy(&output.stdout);
             let stderr = String::from_utf8_lossy(&output.stderr);
-            if !stderr.is_empty() {
+            let text = if !stderr.is_empty() {
                 format!("{stdout}\nStderr: {stderr}")
             } else if stdout.is_empty() {
                 "Python script executed successfully (no output)".to_string()
             } else {
                 stdout.to_string()
+            };
+            if output.status.success() {
+                NativeToolResult::text_only(text)
+            } else {
+                NativeToolResult::failure(text)
             }
         }
-        Err(e) => format!("Error running Python: {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error running Python: {e}")),
     }
 }
 
 /// List directory contents with name, size, and type.
-pub fn tool_list_directory(args: &Value) -> String {
+pub fn tool_list_directory(args: &Value) -> NativeToolResult {
+    tool_list_directory_in(args, &FileToolLimits::default())
+}
+
+/// `list_directory` showing at most `limits.max_dir_entries` entries, then an
+/// "...and N more" footer.
+pub fn tool_list_directory_in(args: &Value, limits: &FileToolLimits) -> NativeToolResult {
     let path = args
         .get("path")
         .and_then(|v| v.as_str())
@@ -133,7 +242,7 @@ pub fn tool_list_directory(args: &Value) -> String {
 
     let entries = match std::fs::read_dir(path) {
         Ok(entries) => entries,
-        Err(e) => return format!("Error reading directory '{path}': {e}"),
+        Err(e) => return NativeToolResult::failure(format!("Error reading directory '{path}': {e}")),
     };
 
     let mut lines = Vec::new();
@@ -143,6 +252,8 @@ pub fn tool_list_directory(args: &Value) -> String {
 
     let mut sorted: Vec<_> = entries.filter_map(|e| e.ok()).collect();
     sorted.sort_by_key(|e| e.file_name());
+    let hidden = sorted.len().saturating_sub(limits.max_dir_entries);
+    sorted.truncate(limits.max_dir_entries);
 
     for entry in sorted {
         let name = entry.file_name().to_string_lossy().to_string();
@@ -162,12 +273,15 @@ pub fn tool_list_directory(args: &Value) -> String {
         };
         lines.push(format!("{name:<40} {size:>10} {file_type}"));
     }
+    if hidden > 0 {
+        lines.push(format!("...and {hidden} more"));
+    }
 
-    lines.join("\n")
+    NativeToolResult::text_only(lines.join("\n"))
 }
 
 /// Show git status of a repository.
-pub fn tool_git_status(args: &Value) -> String {
+pub fn tool_git_status(args: &Value) -> NativeToolResult {
     let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
     let mut cmd = silent_command("git");
     cmd.arg("status").arg("--short");
@@ -180,20 +294,20 @@ pub fn tool_git_status(args: &Value) -> String {
             if !output.status.success() {
                 let code = output.status.code().unwrap_or(-1);
                 let stderr = stderr.trim();
-                return format!("Error (exit {code}): {stderr}");
+                return NativeToolResult::failure(format!("Error (exit {code}): {stderr}"));
             }
-            if stdout.trim().is_empty() {
+            NativeToolResult::text_only(if stdout.trim().is_empty() {
                 "Working tree clean (no changes)".to_string()
             } else {
                 format!("Git status:\n{stdout}")
-            }
+            })
         }
-        Err(e) => format!("Error running git: {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error running git: {e}")),
     }
 }
 
 /// Show git diff for files.
-pub fn tool_git_diff(args: &Value) -> String {
+pub fn tool_git_diff(args: &Value) -> NativeToolResult {
     let path = args.get("path").and_then(|v| v.as_str());
     let staged = args.get("staged").and_then(|v| {
         v.as_bool().or_else(|| v.as_str().map(|s| s.eq_ignore_ascii_case("true")))
@@ -219,23 +333,23 @@ pub fn tool_git_diff(args: &Value) -> String {
             if !output.status.success() {
                 let code = output.status.code().unwrap_or(-1);
                 let stderr = stderr.trim();
-                return format!("Error (exit {code}): {stderr}");
+                return NativeToolResult::failure(format!("Error (exit {code}): {stderr}"));
             }
-            if stdout.trim().is_empty() {
+            NativeToolResult::text_only(if stdout.trim().is_empty() {
                 "No differences found".to_string()
             } else {
                 stdout.to_string()
-            }
+            })
         }
-        Err(e) => format!("Error running git: {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error running git: {e}")),
     }
 }
 
 /// Commit staged changes with a message.
-pub fn tool_git_commit(args: &Value) -> String {
+pub fn tool_git_commit(args: &Value) -> NativeToolResult {
     let message = match args.get("message").and_then(|v| v.as_str()) {
         Some(m) if !m.is_empty() => m,
-        _ => return "Error: 'message' argument is required".to_string(),
+        _ => return NativeToolResult::failure("Error: 'message' argument is required".to_string()),
     };
     let all = args.get("all").and_then(|v| {
         v.as_bool().or_else(|| v.as_str().map(|s| s.eq_ignore_ascii_case("true")))
@@ -256,21 +370,21 @@ pub fn tool_git_commit(args: &Value) -> String {
                 let code = output.status.code().unwrap_or(-1);
                 let stderr = stderr.trim();
                 let stdout = stdout.trim();
-                format!("Error (exit {code}): {stderr}\n{stdout}")
+                NativeToolResult::failure(format!("Error (exit {code}): {stderr}\n{stdout}"))
             } else {
-                stdout.to_string()
+                NativeToolResult::text_only(stdout.to_string())
             }
         }
-        Err(e) => format!("Error running git: {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error running git: {e}")),
     }
 }
 
 /// Find an executable by name: checks PATH first, then common installation directories.
 /// Returns a human-readable result with the resolved path or a list of searched locations.
-pub fn tool_find_executable(args: &Value) -> String {
+pub fn tool_find_executable(args: &Value) -> NativeToolResult {
     let name = match args.get("name").and_then(|v| v.as_str()) {
         Some(n) if !n.is_empty() => n,
-        _ => return "Error: 'name' argument is required".to_string(),
+        _ => return NativeToolResult::failure("Error: 'name' argument is required".to_string()),
     };
 
     // 1. Try `where` (Windows) / `which` (Unix) — checks PATH
@@ -284,7 +398,7 @@ pub fn tool_find_executable(args: &Value) -> String {
         let found = found.trim();
         if out.status.success() && !found.is_empty() {
             let first = found.lines().next().unwrap_or(found);
-            return format!("Found in PATH: {first}");
+            return NativeToolResult::text_only(format!("Found in PATH: {first}"));
         }
     }
 
@@ -336,7 +450,7 @@ pub fn tool_find_executable(args: &Value) -> String {
                     let full = entry.path().to_string_lossy().to_string() + suffix;
                     let p = std::path::Path::new(&full);
                     if p.exists() {
-                        return format!("Found (common location): {full}");
+                        return NativeToolResult::text_only(format!("Found (common location): {full}"));
                     }
                     searched.push(full);
                 }
@@ -345,20 +459,20 @@ pub fn tool_find_executable(args: &Value) -> String {
         }
         let p = std::path::Path::new(candidate);
         if p.exists() {
-            return format!("Found (common location): {candidate}");
+            return NativeToolResult::text_only(format!("Found (common location): {candidate}"));
         }
         searched.push(candidate.clone());
     }
 
-    format!(
+    NativeToolResult::text_only(format!(
         "'{name}' not found in PATH or common locations.\nSearched:\n{}",
         searched.iter().take(8).map(|s| format!("  {s}")).collect::<Vec<_>>().join("\n")
-    )
+    ))
 }
 
 /// Detect installed language runtimes and build tools in a single call.
 /// Returns a compact table of tool name, detected version, and resolved path.
-pub fn tool_check_environment(args: &Value) -> String {
+pub fn tool_check_environment(args: &Value) -> NativeToolResult {
     let filter = args.get("filter").and_then(|v| v.as_str()).unwrap_or("");
 
     // (display_name, binary_names_to_try, version_args)
@@ -424,25 +538,25 @@ pub fn tool_check_environment(args: &Value) -> String {
     }
 
     if rows.is_empty() {
-        return "No matching runtimes found.".to_string();
+        return NativeToolResult::text_only("No matching runtimes found.".to_string());
     }
-    format!("Environment ({} tools):\n{:<10} {:<40} {}\n{}\n{}",
+    NativeToolResult::text_only(format!("Environment ({} tools):\n{:<10} {:<40} {}\n{}\n{}",
         rows.len(),
         "Tool", "Version", "Path",
         "-".repeat(90),
         rows.join("\n")
-    )
+    ))
 }
 
-pub fn tool_list_background_processes() -> String {
+pub fn tool_list_background_processes() -> NativeToolResult {
     let procs = llama_chat_command::background::list_all_background_processes();
     if procs.is_empty() {
-        return "No background processes are currently tracked.".to_string();
+        return NativeToolResult::text_only("No background processes are currently tracked.".to_string());
     }
     let proc_count = procs.len();
     let mut lines = vec![format!("Background processes ({proc_count}):")];
     for (pid, cmd, _alive, status) in &procs {
         lines.push(format!("  PID {pid}: {cmd} [{status}]"));
     }
-    lines.join("\n")
+    NativeToolResult::text_only(lines.join("\n"))
 }
@@ -9,6 +9,7 @@ use crate::browser_tools;
 use crate::mcp_tools;
 use crate::parsing;
 use crate::screenshot_tool;
+use crate::sql_tools;
 use crate::telegram;
 use crate::tool_defs;
 use crate::{DispatchContext, McpManagerOps, NativeToolResult};
@@ -151,6 +152,7 @@ const VALIDATED_TOOLS: &[&str] = &[
     "insert_text",
     "undo_edit",
     "display_images",
+    "sql_query",
 ];
 
 fn validate_tool_args(tool_name: &str, args: &serde_json::Value) -> Result<(), String> {
@@ -253,6 +255,29 @@ fn value_type_name(v: &serde_json::Value) -> &'static str {
     }
 }
 
+/// Tools that stay off until `enabled_tools` names them.
+const OPT_IN_TOOLS: &[&str] = &["sql_query"];
+
+/// Whether native tool `name` is enabled; `None` (the default) enables every
+/// tool except the opt-in ones.
+pub fn is_tool_enabled(enabled_tools: Option<&[String]>, name: &str) -> bool {
+    match enabled_tools {
+        Some(names) => names.iter().any(|n| n == name),
+        None => !OPT_IN_TOOLS.contains(&name),
+    }
+}
+
+/// The reply the model gets when it calls a tool the config's `enabled_tools` leaves out.
+pub fn disabled_tool_refusal(
+    name: &str,
+    db: Option<&llama_chat_db::SharedDatabase>,
+) -> Option<String> {
+    let enabled_tools = db?.load_config().enabled_tools;
+    (!is_tool_enabled(enabled_tools.as_deref(), name)).then(|| {
+        format!("Error: tool '{name}' is disabled in this deployment. Use another tool or answer without it.")
+    })
+}
+
 pub fn dispatch_native_tool(
     text: &str,
     _use_htmd: bool,
@@ -261,18 +286,28 @@ pub fn dispatch_native_tool(
     ctx: &DispatchContext<'_>,
 ) -> Option<NativeToolResult> {
     let trimmed = text.trim();
-    let mut calls = parsing::try_parse_all_from_raw(trimmed);
+    if !parsing::looks_like_tool_call(trimmed) {
+        return None;
+    }
+    let mut calls = parsing::try_parse_all_from_raw_for_format(trimmed, ctx.tool_format);
     let (name, args) = calls.drain(..).next()?;
 
+    // MCP tools are configured per server, not through enabled_tools
+    if !mcp_manager.is_some_and(|mgr| mgr.is_mcp_tool(&name)) {
+        if let Some(refusal) = disabled_tool_refusal(&name, db) {
+            return Some(NativeToolResult::failure(refusal));
+        }
+    }
+
     if let Err(validation_error) = validate_tool_args(&name, &args) {
-        return Some(NativeToolResult::text_only(validation_error));
+        return Some(NativeToolResult::failure(validation_error));
     }
 
     if name != "take_screenshot"
         && llama_chat_desktop_tools::is_desktop_tool(&name)
         && llama_chat_desktop_tools::check_desktop_abort()
     {
-        return Some(NativeToolResult::text_only(
+        return Some(NativeToolResult::failure(
             "Desktop action aborted by user".to_string(),
         ));
     }
@@ -293,7 +328,16 @@ pub fn dispatch_native_tool(
         let result = if category == "mcp" {
             mcp_tools::tool_list_mcp_tools(mcp_manager, db)
         } else if let Some(get_catalog) = ctx.get_tool_catalog {
+            // Catalog lines are "name: description"; drop disabled tools
+            let enabled_tools = db.and_then(|d| d.load_config().enabled_tools);
             get_catalog(category)
+                .lines()
+                .filter(|line| {
+                    line.split_once(':')
+                        .is_none_or(|(tool, _)| is_tool_enabled(enabled_tools.as_deref(), tool))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
         } else {
             "Tool catalog not available".to_string()
         };
@@ -304,7 +348,10 @@ pub fn dispatch_native_tool(
             .get("tool_name")
             .and_then(|v| v.as_str())
             .unwrap_or("");
-        let native_schema = ctx.get_tool_schema.and_then(|f| f(tool_name));
+        let native_schema = ctx
+            .get_tool_schema
+            .filter(|_| disabled_tool_refusal(tool_name, db).is_none())
+            .and_then(|f| f(tool_name));
         let result = native_schema
             .or_else(|| mcp_tools::get_mcp_tool_schema(tool_name, mcp_manager))
             .unwrap_or_else(|| {
@@ -322,7 +369,7 @@ pub fn dispatch_native_tool(
             .map(|arr| arr.iter().filter_map(|v| v.as_str()).take(20).collect())
             .unwrap_or_default();
         if urls.is_empty() {
-            return Some(NativeToolResult::text_only(
+            return Some(NativeToolResult::failure(
                 "Error: 'urls' must be a non-empty array of image URLs".to_string(),
             ));
         }
@@ -344,21 +391,24 @@ pub fn dispatch_native_tool(
             &args, db,
         )));
     }
+    if name == "sql_query" {
+        return Some(NativeToolResult::text_only(sql_tools::tool_sql_query(&args, db)));
+    }
     if name == "spawn_agent" {
-        return Some(NativeToolResult::text_only(
+        return Some(NativeToolResult::failure(
             "Error: spawn_agent must be handled by the generation pipeline".to_string(),
         ));
     }
     if name == "browser_search" {
         let query = match args.get("query").and_then(|v| v.as_str()) {
             Some(q) if !q.trim().is_empty() => q.trim(),
-            _ => return Some(NativeToolResult::text_only("Error: 'query' is required".into())),
+            _ => return Some(NativeToolResult::failure("Error: 'query' is required".into())),
         };
         let encoded = urlencoding::encode(query);
         // gl=us&hl=en: force English/US results regardless of user's IP geo-location
         let search_url = format!("https://www.google.com/search?q={encoded}&gl=us&hl=en&num=8");
         if let Err(e) = browser_session::notify_tauri_browser_navigate(&search_url) {
-            return Some(NativeToolResult::text_only(format!(
+            return Some(NativeToolResult::failure(format!(
                 "Failed to open browser: {e}"
             )));
         }
@@ -379,7 +429,7 @@ pub fn dispatch_native_tool(
                 )));
             }
             Err(e) => {
-                return Some(NativeToolResult::text_only(format!(
+                return Some(NativeToolResult::failure(format!(
                     "Failed to read search results from browser: {e}"
                 )));
             }
@@ -388,7 +438,7 @@ pub fn dispatch_native_tool(
     if name == "open_browser_view" {
         let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
         if url.is_empty() {
-            return Some(NativeToolResult::text_only(
+            return Some(NativeToolResult::failure(
                 "Error: 'url' argument is required".to_string(),
             ));
         }
@@ -410,19 +460,17 @@ pub fn dispatch_native_tool(
         return Some(browser_tools::handle_browser_tool(browser_name, &args));
     }
 
-    if let Some(text) = text_tools::dispatch_text_tool(&name, &args, mcp_manager, db, ctx) {
-        return Some(NativeToolResult::text_only(text));
+    if let Some(result) = text_tools::dispatch_text_tool(&name, &args, mcp_manager, db, ctx) {
+        return Some(result);
     }
 
     mcp_tools::ensure_mcp_connected(mcp_manager, db);
     if let Some(mgr) = mcp_manager {
         if mgr.is_mcp_tool(&name) {
-            return Some(NativeToolResult::text_only(
-                match mgr.call_tool(&name, args) {
-                    Ok(output) => output,
-                    Err(e) => format!("MCP tool error: {e}"),
-                },
-            ));
+            return Some(match mgr.call_tool(&name, args) {
+                Ok(output) => NativeToolResult::text_only(output),
+                Err(e) => NativeToolResult::failure(format!("MCP tool error: {e}")),
+            });
         }
     }
     None
@@ -444,6 +492,8 @@ mod tests {
             get_tool_schema: None,
             discover_skills: None,
             get_skill: None,
+            tool_format: None,
+            conversation_id: None,
         }
     }
 
@@ -451,6 +501,66 @@ mod tests {
         dispatch_native_tool(text, false, None, None, &empty_ctx())
     }
 
+    #[test]
+    fn test_disabled_tool_is_refused() {
+        let db: llama_chat_db::SharedDatabase =
+            std::sync::Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let config = llama_chat_db::config::DbSamplerConfig {
+            enabled_tools: Some(vec!["read_file".to_string()]),
+            ..Default::default()
+        };
+        db.save_config(&config).unwrap();
+        let dispatch_with_db =
+            |text: &str| dispatch_native_tool(text, false, None, Some(&db), &empty_ctx()).unwrap();
+
+        let result =
+            dispatch_with_db(r#"{"name": "execute_python", "arguments": {"code": "print(1)"}}"#);
+        assert!(
+            result.text.contains("'execute_python' is disabled"),
+            "{}",
+            result.text
+        );
+        let result = dispatch_with_db(r#"{"name": "list_directory", "arguments": {"path": "."}}"#);
+        assert!(result.text.contains("disabled"), "{}", result.text);
+
+        // Enabled tools still run
+        let temp = std::env::temp_dir().join("native_tools_test_enabled_read.txt");
+        std::fs::write(&temp, "still readable").unwrap();
+        let json = format!(
+            r#"{{"name": "read_file", "arguments": {{"path": "{}"}}}}"#,
+            temp.display().to_string().replace('\\', "\\\\")
+        );
+        assert!(dispatch_with_db(&json).text.contains("still readable"));
+        std::fs::remove_file(&temp).ok();
+    }
+
+    #[test]
+    fn test_is_tool_enabled() {
+        assert!(is_tool_enabled(None, "execute_command"));
+        let enabled = vec!["read_file".to_string()];
+        assert!(is_tool_enabled(Some(&enabled), "read_file"));
+        assert!(!is_tool_enabled(Some(&enabled), "execute_command"));
+        assert!(!is_tool_enabled(Some(&[]), "read_file"));
+    }
+
+    #[test]
+    fn test_sql_query_is_opt_in() {
+        assert!(!is_tool_enabled(None, "sql_query"));
+        let db: llama_chat_db::SharedDatabase =
+            std::sync::Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let call = r#"{"name": "sql_query", "arguments": {"query": "SELECT COUNT(*) AS n FROM conversations"}}"#;
+        let result = dispatch_native_tool(call, false, None, Some(&db), &empty_ctx()).unwrap();
+        assert!(result.text.contains("'sql_query' is disabled"), "{}", result.text);
+
+        let config = llama_chat_db::config::DbSamplerConfig {
+            enabled_tools: Some(vec!["sql_query".to_string()]),
+            ..Default::default()
+        };
+        db.save_config(&config).unwrap();
+        let result = dispatch_native_tool(call, false, None, Some(&db), &empty_ctx()).unwrap();
+        assert_eq!(result.text, "n\n0\n(1 row)");
+    }
+
     #[test]
     fn test_dispatch_read_file_valid() {
         let temp = std::env::temp_dir().join("native_tools_test_read.txt");
@@ -486,6 +596,49 @@ mod tests {
         assert!(result.unwrap().text.contains("Directory listing"));
     }
 
+    /// A temp dir holding `count` empty files, and a DB whose config caps
+    /// `list_directory` at 3 entries.
+    fn directory_fixture(name: &str, count: usize) -> (std::path::PathBuf, llama_chat_db::SharedDatabase) {
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for i in 0..count {
+            std::fs::write(dir.join(format!("file_{i}.txt")), "").unwrap();
+        }
+        let db: llama_chat_db::SharedDatabase =
+            std::sync::Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        db.save_config(&llama_chat_db::config::DbSamplerConfig {
+            list_directory_max_entries: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        (dir, db)
+    }
+
+    fn list_directory_with(dir: &std::path::Path, db: &llama_chat_db::SharedDatabase) -> String {
+        let call = json!({ "name": "list_directory", "arguments": { "path": dir } }).to_string();
+        dispatch_native_tool(&call, false, None, Some(db), &empty_ctx()).unwrap().text
+    }
+
+    #[test]
+    fn test_list_directory_over_the_limit_gets_a_footer() {
+        let (dir, db) = directory_fixture("native_tools_test_list_large", 5);
+        let text = list_directory_with(&dir, &db);
+        assert!(text.contains("file_2.txt"), "{text}");
+        assert!(!text.contains("file_3.txt"), "{text}");
+        assert!(text.ends_with("...and 2 more"), "{text}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_small_directory_is_fully_listed() {
+        let (dir, db) = directory_fixture("native_tools_test_list_small", 3);
+        let text = list_directory_with(&dir, &db);
+        assert!((0..3).all(|i| text.contains(&format!("file_{i}.txt"))), "{text}");
+        assert!(!text.contains("more"), "{text}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_dispatch_unknown_tool_returns_none() {
         assert!(dispatch(r#"{"name": "unknown_tool", "arguments": {}}"#).is_none());
@@ -545,6 +698,94 @@ mod tests {
         assert!(result.unwrap().text.contains("Directory listing"));
     }
 
+    #[test]
+    fn test_prose_with_json_example_not_dispatched() {
+        let prose = r#"To list a folder you would send something like {"name": "list_directory", "arguments": {"path": "."}} and the app runs it."#;
+        assert!(!crate::parsing::looks_like_tool_call(prose));
+        assert!(dispatch(prose).is_none());
+
+        let fenced = "Here is an example config:\n```json\n{\"path\": \".\"}\n```";
+        assert!(!crate::parsing::looks_like_tool_call(fenced));
+        assert!(dispatch(fenced).is_none());
+    }
+
+    #[test]
+    fn test_real_tool_call_passes_gate() {
+        let call = r#"{"name": "list_directory", "arguments": {"path": "."}}"#;
+        assert!(crate::parsing::looks_like_tool_call(call));
+        assert!(dispatch(call).unwrap().text.contains("Directory listing"));
+
+        assert!(crate::parsing::looks_like_tool_call(r#"list_directory{"path": "."}"#));
+        assert!(crate::parsing::looks_like_tool_call(r#"read_file,{"path": "a.txt"}"#));
+        assert!(crate::parsing::looks_like_tool_call(r#"[read_file(path="a.txt")]"#));
+        assert!(crate::parsing::looks_like_tool_call(r#"{"command": "ls"}"#));
+        assert!(crate::parsing::looks_like_tool_call(r#"web-search{"query": "rust"}"#));
+        assert!(crate::parsing::looks_like_tool_call(r#"[fs.read_file(path="a.txt")]"#));
+        assert!(!crate::parsing::looks_like_tool_call(r#"{"foo": 1}"#));
+    }
+
+    #[test]
+    fn test_parse_mistral_tool_calls_wrapper() {
+        let (name, args) = crate::parsing::try_parse_tool_call(
+            r#"[TOOL_CALLS]read_file,{"path": "a.txt"}[/TOOL_CALLS]"#,
+        )
+        .unwrap();
+        assert_eq!(name, "read_file");
+        assert_eq!(args["path"], "a.txt");
+    }
+
+    #[test]
+    fn test_granite_format_tries_name_json_parser_first() {
+        let default_order: Vec<&str> = crate::parsing::single_call_parsers(None)
+            .iter()
+            .map(|&(name, _)| name)
+            .collect();
+        let granite_order: Vec<&str> = crate::parsing::single_call_parsers(Some("granite"))
+            .iter()
+            .map(|&(name, _)| name)
+            .collect();
+        assert_eq!(default_order[0], "json");
+        assert_eq!(granite_order[0], "name_json");
+        assert_eq!(granite_order.len(), default_order.len());
+        assert_eq!(crate::parsing::single_call_parsers(Some("unknown"))[0].0, "json");
+
+        let call = r#"list_directory{"path": "."}"#;
+        let (name, args) =
+            crate::parsing::try_parse_tool_call_for_format(call, Some("granite")).unwrap();
+        assert_eq!((name.as_str(), args["path"].as_str()), ("list_directory", Some(".")));
+        let granite_ctx = DispatchContext {
+            tool_format: Some("granite"),
+            ..empty_ctx()
+        };
+        let result = dispatch_native_tool(call, false, None, None, &granite_ctx).unwrap();
+        assert!(result.text.contains("Directory listing"));
+    }
+
+    #[test]
+    fn test_parse_hermes_tool_call_wrapper() {
+        let (name, args) = crate::parsing::try_parse_tool_call(
+            r#"<tool_call>{"name": "read_file", "arguments": {"path": "a.txt"}}</tool_call>"#,
+        )
+        .unwrap();
+        assert_eq!(name, "read_file");
+        assert_eq!(args["path"], "a.txt");
+    }
+
+    #[test]
+    fn test_wrapped_tool_calls_detected_as_boundaries() {
+        let tags = llama_chat_types::ToolTags::new("SYSTEM.EXEC>", "<SYSTEM.EXEC||>", "", "");
+        let detect = |text: &str| {
+            crate::FORMAT_PRIORITY
+                .iter()
+                .find_map(|&(_, detect)| detect(text, &tags))
+        };
+        let cmd = detect(r#"Reading it now. [TOOL_CALLS]read_file,{"path": "a.txt"}[/TOOL_CALLS]"#)
+            .unwrap();
+        assert_eq!(crate::parsing::try_parse_tool_call(&cmd).unwrap().0, "read_file");
+        let cmd = detect(r#"<tool_call>list_directory{"path": "."}</tool_call>"#).unwrap();
+        assert_eq!(crate::parsing::try_parse_tool_call(&cmd).unwrap().0, "list_directory");
+    }
+
     #[test]
     fn test_escape_newlines_in_json_strings() {
         let input = r#"{"name": "write_file", "arguments": {"content": "line1
@@ -584,9 +825,78 @@ text = "Invoice INV-2024-0847 total $1,234.56"
 match = re.search(r'\$[\d,]+\.\d+', text)
 print(f"Found: {match.group()}" if match else "No match")"#;
         let args = json!({ "code": code });
-        let result = command_tools::tool_execute_python(&args);
+        let result = command_tools::tool_execute_python(&args).text;
         if !result.contains("Error running Python") {
             assert!(result.contains("Found: $1,234.56"));
         }
     }
+
+    /// A venv whose "python" is a shell script reporting its cwd, the
+    /// isolation flag and where the script file was written.
+    #[cfg(unix)]
+    fn fake_venv(root: &std::path::Path) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let venv = root.join("venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        let python = venv.join("bin").join("python");
+        std::fs::write(&python, "#!/bin/sh\npwd\necho \"$PYTHONNOUSERSITE\"\necho \"$1\"\ntouch side_effect.txt\n").unwrap();
+        std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+        venv
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_isolated_python_runs_in_the_session_cwd() {
+        let root = std::env::temp_dir().join(format!("python_session_cwd_{}", std::process::id()));
+        let session = root.join("python_sessions").join("chat_a");
+        let env = command_tools::PythonEnv {
+            working_dir: Some(session.join("work")),
+            script_dir: Some(session.join("scripts")),
+            venv: Some(fake_venv(&root)),
+            isolated: true,
+        };
+        let result = command_tools::tool_execute_python_in(&json!({ "code": "print(1)" }), &env).text;
+        let mut lines = result.lines();
+        let cwd = std::fs::canonicalize(lines.next().unwrap().trim()).unwrap();
+        assert_eq!(cwd, std::fs::canonicalize(session.join("work")).unwrap(), "{result}");
+        assert_eq!(lines.next().map(str::trim), Some("1"), "{result}");
+        let script = std::path::PathBuf::from(lines.next().unwrap().trim());
+        assert!(script.starts_with(session.join("scripts")), "{result}");
+        // Relative writes land in the session dir, not wherever the app runs
+        assert!(session.join("work").join("side_effect.txt").is_file());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_python_env_follows_config() {
+        let db: llama_chat_db::SharedDatabase =
+            std::sync::Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        // Off by default: ambient interpreter and cwd
+        assert_eq!(command_tools::PythonEnv::from_config(Some(&db), Some("chat_a")), command_tools::PythonEnv::default());
+
+        let missing_venv = std::env::temp_dir().join("no_such_venv_for_tests");
+        db.save_config(&llama_chat_db::config::DbSamplerConfig {
+            python_isolated: true,
+            python_venv: Some(missing_venv.to_string_lossy().to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let env = command_tools::PythonEnv::from_config(Some(&db), Some("chat_a"));
+        assert!(env.isolated);
+        let session = command_tools::python_session_dir(Some("chat_a"));
+        assert_eq!(env.working_dir, Some(session.join("work")));
+        assert_eq!(env.script_dir, Some(session.join("scripts")));
+        assert_eq!(env.venv.as_deref(), Some(missing_venv.as_path()));
+        // Each conversation gets its own directory; ids can't climb out of it
+        let other = command_tools::PythonEnv::from_config(Some(&db), Some("chat_b"));
+        assert_ne!(other.working_dir, env.working_dir);
+        assert_eq!(
+            command_tools::python_session_dir(Some("../../etc")),
+            command_tools::python_session_dir(Some("etc"))
+        );
+
+        let result = command_tools::tool_execute_python_in(&json!({ "code": "print(1)" }), &env);
+        assert!(!result.success);
+        assert!(result.text.contains("python_venv has no interpreter"), "{}", result.text);
+    }
 }
@@ -4,7 +4,7 @@ use crate::command_tools;
 use crate::file_tools;
 use crate::mcp_tools;
 use crate::search_tools;
-use crate::{DispatchContext, McpManagerOps};
+use crate::{DispatchContext, McpManagerOps, NativeToolResult};
 use super::todo_store;
 
 // RTK compresses output for specific developer tooling only. Applying it to arbitrary
@@ -28,15 +28,28 @@ fn rtk_prefix_for_tool(cmd: &str) -> String {
     }
 }
 
+/// A foreground command's output; the call fails unless it exited zero.
+fn command_result(outcome: llama_chat_command::CommandOutcome) -> NativeToolResult {
+    if outcome.success {
+        NativeToolResult::text_only(outcome.output)
+    } else {
+        NativeToolResult::failure(outcome.output)
+    }
+}
+
+fn failure(text: impl Into<String>) -> Option<NativeToolResult> {
+    Some(NativeToolResult::failure(text.into()))
+}
+
 pub(super) fn dispatch_text_tool(
     name: &str,
     args: &Value,
     mcp_manager: Option<&dyn McpManagerOps>,
     db: Option<&llama_chat_db::SharedDatabase>,
     ctx: &DispatchContext<'_>,
-) -> Option<String> {
+) -> Option<NativeToolResult> {
     Some(match name {
-        "read_file" => file_tools::tool_read_file(args),
+        "read_file" => file_tools::tool_read_file_in(args, &file_tools::FileToolLimits::from_config(db)),
         "write_file" => file_tools::tool_write_file(args),
         "edit_file" => file_tools::tool_edit_file(args),
         "multi_edit" => file_tools::tool_multi_edit(args),
@@ -44,12 +57,17 @@ pub(super) fn dispatch_text_tool(
         "insert_text" => file_tools::tool_insert_text(args),
         "search_files" => search_tools::tool_search_files(args),
         "find_files" => search_tools::tool_find_files(args),
-        "execute_python" => command_tools::tool_execute_python(args),
-        "list_directory" => command_tools::tool_list_directory(args),
+        "execute_python" => {
+            let env = command_tools::PythonEnv::from_config(db, ctx.conversation_id);
+            command_tools::tool_execute_python_in(args, &env)
+        }
+        "list_directory" => {
+            command_tools::tool_list_directory_in(args, &file_tools::FileToolLimits::from_config(db))
+        }
         "execute_command" => {
             let command = args.get("command").and_then(|v| v.as_str()).unwrap_or("");
             if command.is_empty() {
-                return Some("Error: 'command' argument is required".to_string());
+                return failure("Error: 'command' argument is required");
             }
             let command = command.strip_prefix("rtk ").unwrap_or(command);
             let working_dir = args.get("working_directory").and_then(|v| v.as_str());
@@ -68,25 +86,28 @@ pub(super) fn dispatch_text_tool(
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false);
             if is_background {
-                llama_chat_command::background::execute_command_background(&command, |_| {})
+                NativeToolResult::text_only(llama_chat_command::background::execute_command_background(
+                    &command,
+                    |_| {},
+                ))
             } else {
                 let timeout = args.get("timeout").and_then(|v| v.as_u64());
-                llama_chat_command::execute_command_streaming_with_timeout(
+                command_result(llama_chat_command::run_command_streaming(
                     &command,
                     None,
                     timeout,
                     &mut |_| {},
-                )
+                ))
             }
         }
         "execute_pty" => {
             let command = args.get("command").and_then(|v| v.as_str()).unwrap_or("");
             if command.is_empty() {
-                return Some("Error: 'command' argument is required".to_string());
+                return failure("Error: 'command' argument is required");
             }
             let command = command.strip_prefix("rtk ").unwrap_or(command);
             let command = rtk_prefix_for_tool(command);
-            llama_chat_command::execute_command_pty(&command, None, |_: &str| {})
+            NativeToolResult::text_only(llama_chat_command::execute_command_pty(&command, None, |_: &str| {}))
         }
         "check_background_process" => {
             let pid = args
@@ -97,9 +118,7 @@ pub(super) fn dispatch_text_tool(
                 })
                 .unwrap_or(0) as u32;
             if pid == 0 {
-                return Some(
-                    "Error: 'pid' argument is required and must be a positive integer".to_string(),
-                );
+                return failure("Error: 'pid' argument is required and must be a positive integer");
             }
             let wait_seconds = args
                 .get("wait_seconds")
@@ -115,7 +134,11 @@ pub(super) fn dispatch_text_tool(
                         .or_else(|| v.as_str().and_then(|s| s.trim().parse::<u64>().ok()))
                 })
                 .unwrap_or(5) as usize;
-            llama_chat_command::background::check_background_process(pid, wait_seconds, max_checks)
+            NativeToolResult::text_only(llama_chat_command::background::check_background_process(
+                pid,
+                wait_seconds,
+                max_checks,
+            ))
         }
         "wait" => {
             let seconds = args
@@ -127,9 +150,9 @@ pub(super) fn dispatch_text_tool(
                 .unwrap_or(10)
                 .min(30);
             std::thread::sleep(std::time::Duration::from_secs(seconds));
-            format!(
+            NativeToolResult::text_only(format!(
                 "Waited {seconds} seconds. You can now check on background processes or continue.",
-            )
+            ))
         }
         "lsp_query" => {
             let action = args
@@ -140,7 +163,7 @@ pub(super) fn dispatch_text_tool(
             let file = args.get("file").and_then(|v| v.as_str());
             let path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
             if symbol.is_empty() && action != "symbols" && action != "diagnostics" {
-                "Error: 'symbol' is required".to_string()
+                NativeToolResult::failure("Error: 'symbol' is required".to_string())
             } else {
                 let result = match action {
                     "definition" => {
@@ -202,7 +225,7 @@ pub(super) fn dispatch_text_tool(
                                         "python -m py_compile {f} 2>&1"
                                     ))
                                 } else {
-                                    "Error: 'file' is required for Python diagnostics".to_string()
+                                    return failure("Error: 'file' is required for Python diagnostics");
                                 }
                             }
                             "ts" | "tsx" => llama_chat_command::execute_command(
@@ -214,7 +237,7 @@ pub(super) fn dispatch_text_tool(
                                         "nim check {f} 2>&1 | head -30"
                                     ))
                                 } else {
-                                    "Error: 'file' is required for Nim diagnostics".to_string()
+                                    return failure("Error: 'file' is required for Nim diagnostics");
                                 }
                             }
                             _ => "No diagnostic tool available for this file type. Use execute_command to run your build tool.".to_string(),
@@ -229,15 +252,17 @@ pub(super) fn dispatch_text_tool(
                         );
                         llama_chat_command::execute_command(&cmd)
                     }
-                    _ => format!(
-                        "Unknown action '{action}'. Use: definition, references, symbols, hover, diagnostics"
-                    ),
+                    _ => {
+                        return failure(format!(
+                            "Unknown action '{action}'. Use: definition, references, symbols, hover, diagnostics"
+                        ));
+                    }
                 };
-                if result.trim().is_empty() {
+                NativeToolResult::text_only(if result.trim().is_empty() {
                     format!("No results found for '{symbol}' ({action}) in {path}")
                 } else {
                     result
-                }
+                })
             }
         }
         "find_executable" => command_tools::tool_find_executable(args),
@@ -252,7 +277,7 @@ pub(super) fn dispatch_text_tool(
         "sleep" => {
             let seconds = args.get("seconds").and_then(|v| v.as_u64()).unwrap_or(5).min(30);
             std::thread::sleep(std::time::Duration::from_secs(seconds));
-            format!("Waited {seconds} seconds")
+            NativeToolResult::text_only(format!("Waited {seconds} seconds"))
         }
         "todo_write" => {
             let todos = args.get("todos").and_then(|v| v.as_str()).unwrap_or("[]");
@@ -263,11 +288,11 @@ pub(super) fn dispatch_text_tool(
                     if let Ok(mut store) = todo_store().lock() {
                         store.insert("default".to_string(), formatted.clone());
                     }
-                    format!("Todo list updated:\n{formatted}")
+                    NativeToolResult::text_only(format!("Todo list updated:\n{formatted}"))
                 }
-                Err(e) => format!(
+                Err(e) => NativeToolResult::failure(format!(
                     "Error: Invalid JSON for todos: {e}. Expected array of {{id, task, status}} objects."
-                ),
+                )),
             }
         }
         "todo_read" => {
@@ -276,17 +301,17 @@ pub(super) fn dispatch_text_tool(
                 .ok()
                 .and_then(|store| store.get("default").cloned())
                 .unwrap_or_else(|| "[]".to_string());
-            if todos == "[]" {
+            NativeToolResult::text_only(if todos == "[]" {
                 "No todos yet. Use todo_write to create a task checklist.".to_string()
             } else {
                 format!("Current todos:\n{todos}")
-            }
+            })
         }
         "list_skills" => {
             let cwd = std::env::current_dir().unwrap_or_default();
             if let Some(discover) = ctx.discover_skills {
                 let skills = discover(&cwd);
-                if skills.is_empty() {
+                NativeToolResult::text_only(if skills.is_empty() {
                     "No skills found. Create .md files in a 'skills/' directory with YAML frontmatter (name, description).".to_string()
                 } else {
                     let skill_count = skills.len();
@@ -297,9 +322,9 @@ pub(super) fn dispatch_text_tool(
                         output.push_str(&format!("  {s_name} — {s_desc}\n"));
                     }
                     output
-                }
+                })
             } else {
-                "Skills system not available".to_string()
+                NativeToolResult::failure("Skills system not available".to_string())
             }
         }
         "use_skill" => {
@@ -324,29 +349,29 @@ pub(super) fn dispatch_text_tool(
                                 content = content.replace(&placeholder, &replacement);
                             }
                         }
-                        format!("Skill '{skill_name}' loaded:\n\n{content}")
+                        NativeToolResult::text_only(format!("Skill '{skill_name}' loaded:\n\n{content}"))
                     }
-                    None => format!(
+                    None => NativeToolResult::failure(format!(
                         "Skill '{skill_name}' not found. Use list_skills to see available skills."
-                    ),
+                    )),
                 }
             } else {
-                "Skills system not available".to_string()
+                NativeToolResult::failure("Skills system not available".to_string())
             }
         }
         "set_response_style" => {
             let style = args.get("style").and_then(|v| v.as_str()).unwrap_or("detailed");
-            match style {
+            NativeToolResult::text_only(match style {
                 "brief" => "Response style set to BRIEF. From now on: be concise, skip explanations, show only results and actions. No preamble or summaries.".to_string(),
                 _ => "Response style set to DETAILED. From now on: explain your reasoning, show context, and provide thorough responses.".to_string(),
-            }
+            })
         }
         "open_url" => {
             let url = args.get("url").and_then(|v| v.as_str()).unwrap_or("");
             if url.is_empty() {
-                "Error: 'url' argument is required".to_string()
+                NativeToolResult::failure("Error: 'url' argument is required".to_string())
             } else if !url.starts_with("http://") && !url.starts_with("https://") {
-                format!("Error: URL must start with http:// or https://, got: {url}")
+                NativeToolResult::failure(format!("Error: URL must start with http:// or https://, got: {url}"))
             } else {
                 #[cfg(target_os = "windows")]
                 let result = std::process::Command::new("cmd")
@@ -357,8 +382,8 @@ pub(super) fn dispatch_text_tool(
                 #[cfg(target_os = "linux")]
                 let result = std::process::Command::new("xdg-open").arg(url).spawn();
                 match result {
-                    Ok(_) => format!("Opened {url} in the default browser"),
-                    Err(e) => format!("Failed to open URL: {e}"),
+                    Ok(_) => NativeToolResult::text_only(format!("Opened {url} in the default browser")),
+                    Err(e) => NativeToolResult::failure(format!("Failed to open URL: {e}")),
                 }
             }
         }
@@ -6,6 +6,7 @@ use std::path::Path;
 use std::sync::Mutex as StdMutex;
 use std::sync::OnceLock;
 
+use crate::NativeToolResult;
 use crate::doc_extractors::{
     extract_csv_structured, extract_docx_text, extract_eml_text, extract_epub_text,
     extract_odt_text, extract_pdf_with_pages, extract_pptx_text, extract_rtf_text,
@@ -16,8 +17,37 @@ use crate::doc_extractors::{
 mod editing;
 pub use editing::{tool_edit_file, tool_insert_text, tool_multi_edit, tool_undo_edit};
 
-/// Maximum file size to return inline (100 KB).
-const MAX_READ_SIZE: usize = 100 * 1024;
+/// Output caps for `read_file` and `list_directory`, read from the config on
+/// every call so a change applies to the next tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileToolLimits {
+    /// Most bytes `read_file` returns inline.
+    pub max_read_bytes: usize,
+    /// Most entries `list_directory` lists.
+    pub max_dir_entries: usize,
+}
+
+impl Default for FileToolLimits {
+    fn default() -> Self {
+        Self {
+            max_read_bytes: llama_chat_types::models::DEFAULT_READ_FILE_MAX_BYTES as usize,
+            max_dir_entries: llama_chat_types::models::DEFAULT_LIST_DIRECTORY_MAX_ENTRIES as usize,
+        }
+    }
+}
+
+impl FileToolLimits {
+    /// From the config's `read_file_max_bytes` / `list_directory_max_entries`.
+    pub fn from_config(db: Option<&llama_chat_db::SharedDatabase>) -> Self {
+        let Some(config) = db.map(|db| db.load_config()) else {
+            return Self::default();
+        };
+        Self {
+            max_read_bytes: config.read_file_max_bytes.max(1) as usize,
+            max_dir_entries: config.list_directory_max_entries.max(1) as usize,
+        }
+    }
+}
 
 // ─── File modification time cache (for edit_file concurrent modification detection) ──
 static FILE_MTIME_CACHE: OnceLock<StdMutex<HashMap<String, u64>>> = OnceLock::new();
@@ -184,10 +214,18 @@ const EXTRACTABLE_EXTENSIONS: &[&str] = &[
     "zip", "7z",
 ];
 
-pub fn tool_read_file(args: &Value) -> String {
+pub fn tool_read_file(args: &Value) -> NativeToolResult {
+    tool_read_file_in(args, &FileToolLimits::default())
+}
+
+/// `read_file` with output capped at `limits.max_read_bytes`. Documents are
+/// read through the extractors, which describe an unparseable file in their
+/// text; only a file that can't be read at all fails the call.
+pub fn tool_read_file_in(args: &Value, limits: &FileToolLimits) -> NativeToolResult {
+    let max_read_size = limits.max_read_bytes;
     let path = match args.get("path").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'path' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'path' argument is required".to_string()),
     };
 
     let path_lower = path.to_ascii_lowercase();
@@ -197,15 +235,15 @@ pub fn tool_read_file(args: &Value) -> String {
     {
         let bytes_check = match std::fs::read(path) {
             Ok(b) => b,
-            Err(e) => return format!("Error reading '{path}': {e}"),
+            Err(e) => return NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
         if has_binary_extension(path) || is_binary_content(&bytes_check) {
             let ext = std::path::Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
             if !EXTRACTABLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
                 let byte_count = bytes_check.len();
-                return format!(
+                return NativeToolResult::failure(format!(
                     "Error: '{path}' appears to be a binary file ({byte_count} bytes). Cannot read as text."
-                );
+                ));
             }
         }
     }
@@ -213,78 +251,78 @@ pub fn tool_read_file(args: &Value) -> String {
     // PDF files: extract text with optional page range
     if path_lower.ends_with(".pdf") {
         let pages_param = args.get("pages").and_then(|v| v.as_str()).unwrap_or("");
-        return extract_pdf_with_pages(path, pages_param, MAX_READ_SIZE);
+        return NativeToolResult::text_only(extract_pdf_with_pages(path, pages_param, max_read_size));
     }
 
     // DOCX files: extract text from ZIP/XML structure
     if path_lower.ends_with(".docx") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_docx_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_docx_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // PPTX files: extract text from ZIP/XML structure
     if path_lower.ends_with(".pptx") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_pptx_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_pptx_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // XLSX/XLS files: extract spreadsheet data as tab-separated text
     if path_lower.ends_with(".xlsx") || path_lower.ends_with(".xls") || path_lower.ends_with(".xlsm") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_xlsx_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_xlsx_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // EPUB ebooks: extract text from XHTML content files
     if path_lower.ends_with(".epub") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_epub_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_epub_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // ODT (LibreOffice Writer): extract text from content.xml
     if path_lower.ends_with(".odt") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_odt_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_odt_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // RTF: extract plain text
     if path_lower.ends_with(".rtf") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_rtf_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_rtf_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // ZIP archives: list contents
     if path_lower.ends_with(".zip") || path_lower.ends_with(".7z") || path_lower.ends_with(".tar.gz") || path_lower.ends_with(".tgz") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_zip_listing(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_zip_listing(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // CSV files: structured parsing with headers
     if path_lower.ends_with(".csv") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_csv_structured(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_csv_structured(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
     // Email files: extract headers, body, attachment listing
     if path_lower.ends_with(".eml") || path_lower.ends_with(".msg") {
         return match std::fs::read(path) {
-            Ok(bytes) => extract_eml_text(&bytes, MAX_READ_SIZE),
-            Err(e) => format!("Error reading '{path}': {e}"),
+            Ok(bytes) => NativeToolResult::text_only(extract_eml_text(&bytes, max_read_size)),
+            Err(e) => NativeToolResult::failure(format!("Error reading '{path}': {e}")),
         };
     }
 
@@ -310,12 +348,14 @@ pub fn tool_read_file(args: &Value) -> String {
         Err(e) => {
             // If cache returned a binary error, propagate it
             if e.starts_with("Error:") || e.starts_with("Binary file:") {
-                return e;
+                return NativeToolResult::failure(e);
             }
             // If reading failed, try encoding detection fallback
             match std::fs::read(path) {
-                Ok(bytes) => return read_with_encoding_detection(&bytes, MAX_READ_SIZE),
-                Err(e2) => return format!("Error reading '{path}': {e2}"),
+                Ok(bytes) => {
+                    return NativeToolResult::text_only(read_with_encoding_detection(&bytes, max_read_size));
+                }
+                Err(e2) => return NativeToolResult::failure(format!("Error reading '{path}': {e2}")),
             }
         }
     };
@@ -389,10 +429,10 @@ pub fn tool_read_file(args: &Value) -> String {
     }
 
     // Apply truncation if still too large
-    if result.len() > MAX_READ_SIZE {
-        truncate_text_content(&result, MAX_READ_SIZE)
+    if result.len() > max_read_size {
+        NativeToolResult::text_only(truncate_text_content(&result, max_read_size))
     } else {
-        result
+        NativeToolResult::text_only(result)
     }
 }
 
@@ -429,21 +469,21 @@ pub fn read_with_encoding_detection(bytes: &[u8], max_chars: usize) -> String {
 }
 
 /// Write content to a file, creating parent directories as needed.
-pub fn tool_write_file(args: &Value) -> String {
+pub fn tool_write_file(args: &Value) -> NativeToolResult {
     let path = match args.get("path").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'path' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'path' argument is required".to_string()),
     };
     let content = match args.get("content").and_then(|v| v.as_str()) {
         Some(c) => c,
-        None => return "Error: 'content' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'content' argument is required".to_string()),
     };
 
     // Create parent directories if they don't exist
     if let Some(parent) = Path::new(path).parent() {
         if !parent.exists() {
             if let Err(e) = std::fs::create_dir_all(parent) {
-                return format!("Error creating directories for '{path}': {e}");
+                return NativeToolResult::failure(format!("Error creating directories for '{path}': {e}"));
             }
         }
     }
@@ -460,8 +500,8 @@ pub fn tool_write_file(args: &Value) -> String {
             invalidate_read_cache(path);
             invalidate_file_cache(path);
             let byte_count = content.len();
-            format!("Written {byte_count} bytes to {path}")
+            NativeToolResult::text_only(format!("Written {byte_count} bytes to {path}"))
         }
-        Err(e) => format!("Error writing '{path}': {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error writing '{path}': {e}")),
     }
 }
@@ -69,19 +69,19 @@ fn simple_diff(old: &str, new: &str, path: &str) -> String {
     diff
 }
 
-pub fn tool_edit_file(args: &Value) -> String {
+pub fn tool_edit_file(args: &Value) -> NativeToolResult {
     let path = match args.get("path").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'path' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'path' argument is required".to_string()),
     };
     let old_string = match args.get("old_string").and_then(|v| v.as_str()) {
         Some(s) => s,
-        None => return "Error: 'old_string' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'old_string' argument is required".to_string()),
     };
     let new_string = args.get("new_string").and_then(|v| v.as_str()).unwrap_or("");
 
     if old_string.is_empty() {
-        return "Error: 'old_string' cannot be empty".to_string();
+        return NativeToolResult::failure("Error: 'old_string' cannot be empty".to_string());
     }
 
     if let Some(cached_mtime) = file_mtime_cache().lock().ok().and_then(|c| c.get(path).copied()) {
@@ -94,7 +94,7 @@ pub fn tool_edit_file(args: &Value) -> String {
 
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
-        Err(e) => return format!("Error reading '{path}': {e}"),
+        Err(e) => return NativeToolResult::failure(format!("Error reading '{path}': {e}")),
     };
 
     let match_count = content.matches(old_string).count();
@@ -119,13 +119,15 @@ pub fn tool_edit_file(args: &Value) -> String {
                     invalidate_read_cache(path);
                     invalidate_file_cache(path);
                     let diff = simple_diff(&norm_content, &new_content, path);
-                    format!("Edited {path} (curly quotes normalized) at line {line_num}:\n{diff}")
+                    NativeToolResult::text_only(format!(
+                        "Edited {path} (curly quotes normalized) at line {line_num}:\n{diff}"
+                    ))
                 }
-                Err(e) => format!("Error writing '{path}': {e}"),
+                Err(e) => NativeToolResult::failure(format!("Error writing '{path}': {e}")),
             };
         }
         if norm_count > 1 {
-            return format!("Error: old_string found {norm_count} times in {path} (after curly quote normalization). Include more surrounding context to make it unique.");
+            return NativeToolResult::failure(format!("Error: old_string found {norm_count} times in {path} (after curly quote normalization). Include more surrounding context to make it unique."));
         }
         // CRLF normalization fallback: files created on Windows often use \r\n but the
         // model supplies \n-only strings. Normalize both sides and retry.
@@ -149,18 +151,20 @@ pub fn tool_edit_file(args: &Value) -> String {
                     invalidate_read_cache(path);
                     invalidate_file_cache(path);
                     let diff = simple_diff(&crlf_content, &new_content, path);
-                    format!("Edited {path} (CRLF normalized) at line {line_num}:\n{diff}")
+                    NativeToolResult::text_only(format!(
+                        "Edited {path} (CRLF normalized) at line {line_num}:\n{diff}"
+                    ))
                 }
-                Err(e) => format!("Error writing '{path}': {e}"),
+                Err(e) => NativeToolResult::failure(format!("Error writing '{path}': {e}")),
             };
         }
         if crlf_count > 1 {
-            return format!("Error: old_string found {crlf_count} times in {path} (after CRLF normalization). Include more surrounding context to make it unique.");
+            return NativeToolResult::failure(format!("Error: old_string found {crlf_count} times in {path} (after CRLF normalization). Include more surrounding context to make it unique."));
         }
-        return format!("Error: old_string not found in {path}. Make sure the text matches exactly (including whitespace and newlines).");
+        return NativeToolResult::failure(format!("Error: old_string not found in {path}. Make sure the text matches exactly (including whitespace and newlines)."));
     }
     if match_count > 1 {
-        return format!("Error: old_string found {match_count} times in {path}. Include more surrounding context to make it unique.");
+        return NativeToolResult::failure(format!("Error: old_string found {match_count} times in {path}. Include more surrounding context to make it unique."));
     }
 
     let match_pos = content.find(old_string).unwrap();
@@ -179,23 +183,24 @@ pub fn tool_edit_file(args: &Value) -> String {
             invalidate_read_cache(path);
             invalidate_file_cache(path);
             let diff = simple_diff(&content, &new_content, path);
-            format!("Edited {path} at line {line_num}:\n{diff}")
+            NativeToolResult::text_only(format!("Edited {path} at line {line_num}:\n{diff}"))
         }
-        Err(e) => format!("Error writing '{path}': {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error writing '{path}': {e}")),
     }
 }
 
-pub fn tool_multi_edit(args: &Value) -> String {
+pub fn tool_multi_edit(args: &Value) -> NativeToolResult {
     let edits = match args.get("edits").and_then(|v| v.as_array()) {
         Some(e) => e,
-        None => return "Error: 'edits' argument is required and must be an array".to_string(),
+        None => return NativeToolResult::failure("Error: 'edits' argument is required and must be an array".to_string()),
     };
 
     if edits.is_empty() {
-        return "Error: 'edits' array is empty".to_string();
+        return NativeToolResult::failure("Error: 'edits' array is empty".to_string());
     }
 
     let mut results: Vec<String> = Vec::new();
+    let mut failed = false;
 
     for (i, edit) in edits.iter().enumerate() {
         let path = match edit.get("path").and_then(|v| v.as_str()) {
@@ -203,6 +208,7 @@ pub fn tool_multi_edit(args: &Value) -> String {
             None => {
                 let edit_num = i + 1;
                 results.push(format!("Edit {edit_num}: Error: missing 'path'"));
+                failed = true;
                 break;
             }
         };
@@ -211,6 +217,7 @@ pub fn tool_multi_edit(args: &Value) -> String {
             None => {
                 let edit_num = i + 1;
                 results.push(format!("Edit {edit_num}: Error: missing 'old_string'"));
+                failed = true;
                 break;
             }
         };
@@ -222,57 +229,63 @@ pub fn tool_multi_edit(args: &Value) -> String {
             "new_string": new_string,
         }));
 
-        let ok = !result.starts_with("Error:");
         let edit_num = i + 1;
-        results.push(format!("Edit {edit_num} ({path}): {result}"));
-        if !ok {
+        let text = &result.text;
+        results.push(format!("Edit {edit_num} ({path}): {text}"));
+        if !result.success {
+            failed = true;
             break;
         }
     }
 
-    results.join("\n\n")
+    let text = results.join("\n\n");
+    if failed {
+        NativeToolResult::failure(text)
+    } else {
+        NativeToolResult::text_only(text)
+    }
 }
 
-pub fn tool_undo_edit(args: &Value) -> String {
+pub fn tool_undo_edit(args: &Value) -> NativeToolResult {
     let path = match args.get("path").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'path' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'path' argument is required".to_string()),
     };
 
     let backup_path = format!("{path}.llama_bak");
     let backup_content = match std::fs::read_to_string(&backup_path) {
         Ok(c) => c,
-        Err(_) => return format!("Error: no backup found for {path}. Only the most recent edit_file can be undone."),
+        Err(_) => return NativeToolResult::failure(format!("Error: no backup found for {path}. Only the most recent edit_file can be undone.")),
     };
 
     match std::fs::write(path, &backup_content) {
         Ok(()) => {
             let _ = std::fs::remove_file(&backup_path);
-            format!("Restored {path} to its state before the last edit")
+            NativeToolResult::text_only(format!("Restored {path} to its state before the last edit"))
         }
-        Err(e) => format!("Error restoring '{path}': {e}"),
+        Err(e) => NativeToolResult::failure(format!("Error restoring '{path}': {e}")),
     }
 }
 
-pub fn tool_insert_text(args: &Value) -> String {
+pub fn tool_insert_text(args: &Value) -> NativeToolResult {
     let path = match args.get("path").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'path' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'path' argument is required".to_string()),
     };
     let text = match args.get("text").and_then(|v| v.as_str()) {
         Some(t) => t,
-        None => return "Error: 'text' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'text' argument is required".to_string()),
     };
     let line = args.get("line").and_then(|v| {
         v.as_u64().or_else(|| v.as_str().and_then(|s| s.trim().parse::<u64>().ok()))
     }).unwrap_or(0) as usize;
     if line == 0 {
-        return "Error: 'line' argument is required and must be >= 1".to_string();
+        return NativeToolResult::failure("Error: 'line' argument is required and must be >= 1".to_string());
     }
 
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
-        Err(e) => return format!("Error reading '{path}': {e}"),
+        Err(e) => return NativeToolResult::failure(format!("Error reading '{path}': {e}")),
     };
 
     let mut lines: Vec<&str> = content.lines().collect();
@@ -291,7 +304,9 @@ pub fn tool_insert_text(args: &Value) -> String {
     }
 
     match std::fs::write(path, &new_content) {
-        Ok(()) => format!("Inserted {inserted_count} line(s) at line {line} in {path}"),
-        Err(e) => format!("Error writing '{path}': {e}"),
+        Ok(()) => NativeToolResult::text_only(format!(
+            "Inserted {inserted_count} line(s) at line {line} in {path}"
+        )),
+        Err(e) => NativeToolResult::failure(format!("Error writing '{path}': {e}")),
     }
 }
@@ -19,6 +19,7 @@ pub mod browser_session;
 pub mod wry_browser;
 pub mod mcp_tools;
 pub mod screenshot_tool;
+pub mod sql_tools;
 pub mod telegram;
 pub mod tool_parser;
 pub mod tool_defs;
@@ -26,8 +27,8 @@ mod dispatch;
 mod utils;
 
 pub use dispatch::{
-    dispatch_native_tool, extract_execute_command_with_opts, extract_tool_args_summary,
-    extract_tool_name,
+    disabled_tool_refusal, dispatch_native_tool, extract_execute_command_with_opts,
+    extract_tool_args_summary, extract_tool_name, is_tool_enabled,
 };
 pub use doc_extractors::*;
 pub use file_tools::{read_with_encoding_detection, truncate_text_content};
@@ -80,6 +81,11 @@ pub struct DispatchContext<'a> {
     pub get_tool_schema: Option<&'a dyn Fn(&str) -> Option<String>>,
     pub discover_skills: Option<&'a dyn Fn(&std::path::Path) -> Vec<SkillInfo>>,
     pub get_skill: Option<&'a dyn Fn(&std::path::Path, &str) -> Option<SkillInfo>>,
+    /// The loaded model's native tool-call format, parsed before the others.
+    pub tool_format: Option<&'a str>,
+    /// Conversation the call belongs to; keys per-conversation state such as
+    /// the isolated `execute_python` working directory.
+    pub conversation_id: Option<&'a str>,
 }
 
 /// Minimal skill info for the tools crate.
@@ -1,7 +1,7 @@
 //! MCP (Model Context Protocol) server management tools.
 
 use serde_json::Value;
-use crate::McpManagerOps;
+use crate::{McpManagerOps, NativeToolResult};
 
 /// Ensure MCP servers are connected (lazy init). Call before any MCP tool access.
 pub fn ensure_mcp_connected(
@@ -91,15 +91,15 @@ pub fn get_mcp_tool_schema(
 pub fn tool_list_mcp_servers(
     mcp_manager: Option<&dyn McpManagerOps>,
     db: Option<&llama_chat_db::SharedDatabase>,
-) -> String {
+) -> NativeToolResult {
     let db = match db {
         Some(d) => d,
-        None => return "Error: Database not available".to_string(),
+        None => return NativeToolResult::failure("Error: Database not available".to_string()),
     };
 
     let configs = llama_chat_db::mcp::load_mcp_servers(db);
     if configs.is_empty() {
-        return "No MCP servers configured.".to_string();
+        return NativeToolResult::text_only("No MCP servers configured.".to_string());
     }
 
     let statuses = mcp_manager.map(|mgr| mgr.get_server_statuses()).unwrap_or_default();
@@ -138,22 +138,22 @@ pub fn tool_list_mcp_servers(
             }
         }
     }
-    lines.join("\n")
+    NativeToolResult::text_only(lines.join("\n"))
 }
 
 pub fn tool_add_mcp_server(
     args: &Value,
     mcp_manager: Option<&dyn McpManagerOps>,
     db: Option<&llama_chat_db::SharedDatabase>,
-) -> String {
+) -> NativeToolResult {
     let db = match db {
         Some(d) => d,
-        None => return "Error: Database not available".to_string(),
+        None => return NativeToolResult::failure("Error: Database not available".to_string()),
     };
 
     let name = match args.get("name").and_then(|v| v.as_str()) {
         Some(n) if !n.is_empty() => n.to_string(),
-        _ => return "Error: 'name' argument is required".to_string(),
+        _ => return NativeToolResult::failure("Error: 'name' argument is required".to_string()),
     };
 
     let transport_str = args.get("transport").and_then(|v| v.as_str()).unwrap_or("stdio");
@@ -162,7 +162,7 @@ pub fn tool_add_mcp_server(
         "stdio" => {
             let command = match args.get("command").and_then(|v| v.as_str()) {
                 Some(c) if !c.is_empty() => c.to_string(),
-                _ => return "Error: 'command' argument is required for stdio transport".to_string(),
+                _ => return NativeToolResult::failure("Error: 'command' argument is required for stdio transport".to_string()),
             };
             let cmd_args: Vec<String> = args.get("args")
                 .and_then(|v| v.as_array())
@@ -177,11 +177,11 @@ pub fn tool_add_mcp_server(
         "http" => {
             let url = match args.get("url").and_then(|v| v.as_str()) {
                 Some(u) if !u.is_empty() => u.to_string(),
-                _ => return "Error: 'url' argument is required for http transport".to_string(),
+                _ => return NativeToolResult::failure("Error: 'url' argument is required for http transport".to_string()),
             };
             llama_chat_types::mcp_config::McpTransport::Http { url }
         }
-        other => return format!("Error: Unknown transport type '{other}'. Use 'stdio' or 'http'."),
+        other => return NativeToolResult::failure(format!("Error: Unknown transport type '{other}'. Use 'stdio' or 'http'.")),
     };
 
     let id = uuid::Uuid::new_v4().to_string();
@@ -193,14 +193,14 @@ pub fn tool_add_mcp_server(
     };
 
     if let Err(e) = llama_chat_db::mcp::save_mcp_server(db, &config) {
-        return format!("Error saving MCP server: {e}");
+        return NativeToolResult::failure(format!("Error saving MCP server: {e}"));
     }
 
     // Refresh connections to connect the new server
     let mut tool_list = String::new();
     if let Some(mgr) = mcp_manager {
         if let Err(e) = mgr.refresh_connections(db) {
-            return format!("Server saved but failed to connect: {e}");
+            return NativeToolResult::failure(format!("Server saved but failed to connect: {e}"));
         }
         let statuses = mgr.get_server_statuses();
         if let Some(status) = statuses.iter().find(|s| s.name == name) {
@@ -212,27 +212,31 @@ pub fn tool_add_mcp_server(
                     format!("\nAvailable tools: {tools_str}")
                 };
             } else {
-                return format!("MCP server '{name}' saved but failed to connect. Check the command/URL and try again.");
+                return NativeToolResult::failure(format!(
+                    "MCP server '{name}' saved but failed to connect. Check the command/URL and try again."
+                ));
             }
         }
     }
 
-    format!("Added MCP server '{name}' successfully.{tool_list}\nNote: New tools will be available in the next message.")
+    NativeToolResult::text_only(format!(
+        "Added MCP server '{name}' successfully.{tool_list}\nNote: New tools will be available in the next message."
+    ))
 }
 
 pub fn tool_remove_mcp_server(
     args: &Value,
     mcp_manager: Option<&dyn McpManagerOps>,
     db: Option<&llama_chat_db::SharedDatabase>,
-) -> String {
+) -> NativeToolResult {
     let db = match db {
         Some(d) => d,
-        None => return "Error: Database not available".to_string(),
+        None => return NativeToolResult::failure("Error: Database not available".to_string()),
     };
 
     let name = match args.get("name").and_then(|v| v.as_str()) {
         Some(n) if !n.is_empty() => n,
-        _ => return "Error: 'name' argument is required".to_string(),
+        _ => return NativeToolResult::failure("Error: 'name' argument is required".to_string()),
     };
 
     let configs = llama_chat_db::mcp::load_mcp_servers(db);
@@ -242,12 +246,12 @@ pub fn tool_remove_mcp_server(
         Some(s) => s,
         None => {
             let available: Vec<&str> = configs.iter().map(|c| c.name.as_str()).collect();
-            return if available.is_empty() {
+            return NativeToolResult::failure(if available.is_empty() {
                 format!("MCP server '{name}' not found. No MCP servers are configured.")
             } else {
                 let avail_str = available.join(", ");
                 format!("MCP server '{name}' not found. Available servers: {avail_str}")
-            };
+            });
         }
     };
 
@@ -255,7 +259,7 @@ pub fn tool_remove_mcp_server(
     let server_name = server.name.clone();
 
     if let Err(e) = llama_chat_db::mcp::delete_mcp_server(db, &server_id) {
-        return format!("Error removing MCP server: {e}");
+        return NativeToolResult::failure(format!("Error removing MCP server: {e}"));
     }
 
     // Refresh to disconnect the removed server
@@ -263,5 +267,5 @@ pub fn tool_remove_mcp_server(
         let _ = mgr.refresh_connections(db);
     }
 
-    format!("Removed MCP server '{server_name}' successfully.")
+    NativeToolResult::text_only(format!("Removed MCP server '{server_name}' successfully."))
 }
@@ -283,24 +283,130 @@ pub fn try_parse_with_fixups(input: &str) -> Option<Value> {
     None
 }
 
+/// Tags that unambiguously mark a tool-call envelope, whatever the model family.
+const TOOL_CALL_MARKERS: &[&str] = &[
+    "<tool_call>",
+    "<|tool_call>",
+    "[TOOL_CALLS]",
+    "<function=",
+    "<arg_key>",
+    "SYSTEM.EXEC",
+];
+
+/// Cheap gate run before the fixup chain: does `text` carry a recognizable
+/// tool-call envelope?
+///
+/// Accepts a known tool tag, a leading JSON object/array with a `"name"` key,
+/// a leading bare-args object whose keys match a native tool signature, or a
+/// leading `tool_name{...}` / `tool_name,{...}` / `[tool_name(...)]` call.
+/// Ordinary prose that merely quotes JSON (e.g. a model explaining a format)
+/// is rejected so it never reaches dispatch.
+pub fn looks_like_tool_call(text: &str) -> bool {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return false;
+    }
+    if TOOL_CALL_MARKERS.iter().any(|m| trimmed.contains(m)) {
+        return true;
+    }
+    match trimmed.as_bytes()[0] {
+        b'{' => trimmed.contains("\"name\"") || try_infer_tool_from_bare_args(trimmed).is_some(),
+        b'[' => {
+            let inner = trimmed[1..].trim_start();
+            (inner.starts_with('{') && inner.contains("\"name\""))
+                || leading_identifier(inner).is_some_and(|(_, rest)| rest.starts_with('('))
+        }
+        _ => leading_identifier(trimmed)
+            .is_some_and(|(_, rest)| rest.starts_with('{') || rest.starts_with(",{")),
+    }
+}
+
+/// Split a leading `[A-Za-z0-9_.-]+` identifier off `text`, returning `(ident, rest)`.
+/// `-` and `.` are allowed because MCP tool names use them (`my-server.search`).
+fn leading_identifier(text: &str) -> Option<(&str, &str)> {
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+        .unwrap_or(text.len());
+    if end == 0 {
+        return None;
+    }
+    Some((&text[..end], text[end..].trim_start()))
+}
+
+/// Wrapper tag pairs stripped before payload parsing: Mistral `[TOOL_CALLS]`
+/// and Hermes/Qwen `<tool_call>`. The close tag is optional because models
+/// often stop generating right after the payload.
+const TOOL_CALL_WRAPPERS: &[(&str, &str)] = &[
+    ("[TOOL_CALLS]", "[/TOOL_CALLS]"),
+    ("<tool_call>", "</tool_call>"),
+];
+
+/// Strip a surrounding `[TOOL_CALLS]...[/TOOL_CALLS]` or `<tool_call>...</tool_call>`
+/// wrapper so the inner payload can be parsed. Returns the input (trimmed) unchanged
+/// when no wrapper is present.
+pub fn strip_tool_call_wrapper(text: &str) -> &str {
+    let trimmed = text.trim();
+    for (open, close) in TOOL_CALL_WRAPPERS {
+        if let Some(inner) = trimmed.strip_prefix(open) {
+            let inner = inner.strip_suffix(close).unwrap_or(inner);
+            return inner.trim();
+        }
+    }
+    trimmed
+}
+
+/// A single-call tool format parser: `Some((name, args))` when the text matches.
+pub type SingleCallParser = fn(&str) -> Option<(String, Value)>;
+
+/// Single-call parsers, in the order tried when the model's format is unknown.
+const SINGLE_CALL_PARSERS: &[(&str, SingleCallParser)] = &[
+    ("json", try_parse_json_format),
+    ("lfm2", try_parse_lfm2_python_call_format),
+    ("mistral", try_parse_mistral_comma_format),
+    ("llama3", try_parse_llama3_xml_format),
+    ("glm", try_parse_glm_xml_format),
+    ("name_json", try_parse_name_json_format),
+];
+
+/// Parser for a model's native tool format, as reported by `detect_tool_format`.
+fn native_parser_name(tool_format: &str) -> Option<&'static str> {
+    match tool_format {
+        "qwen" => Some("json"),
+        "mistral" => Some("mistral"),
+        "llama3" => Some("llama3"),
+        "granite" => Some("name_json"),
+        _ => None,
+    }
+}
+
+/// `SINGLE_CALL_PARSERS` with the model's native format (if known) moved to the front.
+pub fn single_call_parsers(tool_format: Option<&str>) -> Vec<(&'static str, SingleCallParser)> {
+    let mut parsers = SINGLE_CALL_PARSERS.to_vec();
+    if let Some(native) = tool_format.and_then(native_parser_name) {
+        if let Some(pos) = parsers.iter().position(|&(name, _)| name == native) {
+            let parser = parsers.remove(pos);
+            parsers.insert(0, parser);
+        }
+    }
+    parsers
+}
+
 /// Try to parse a tool call text into (name, arguments) using all supported formats.
 ///
 /// Returns `Some((name, args))` if parsed, `None` otherwise.
 pub fn try_parse_tool_call(text: &str) -> Option<(String, Value)> {
-    let trimmed = text.trim();
-    if let Some(result) = try_parse_json_format(trimmed) {
-        Some(result)
-    } else if let Some(result) = try_parse_lfm2_python_call_format(trimmed) {
-        Some(result)
-    } else if let Some(result) = try_parse_mistral_comma_format(trimmed) {
-        Some(result)
-    } else if let Some(result) = try_parse_llama3_xml_format(trimmed) {
-        Some(result)
-    } else if let Some(result) = try_parse_glm_xml_format(trimmed) {
-        Some(result)
-    } else {
-        try_parse_name_json_format(trimmed)
-    }
+    try_parse_tool_call_for_format(text, None)
+}
+
+/// [`try_parse_tool_call`], trying the model's native `tool_format` first.
+pub fn try_parse_tool_call_for_format(
+    text: &str,
+    tool_format: Option<&str>,
+) -> Option<(String, Value)> {
+    let trimmed = strip_tool_call_wrapper(text);
+    single_call_parsers(tool_format)
+        .into_iter()
+        .find_map(|(_, parse)| parse(trimmed))
 }
 
 /// Parse ALL tool calls from raw text, supporting batch JSON arrays.
@@ -309,7 +415,27 @@ pub fn try_parse_tool_call(text: &str) -> Option<(String, Value)> {
 /// to single-call formats (Mistral comma, Llama3 XML, Name+JSON, bare args).
 /// Returns empty vec if nothing could be parsed.
 pub fn try_parse_all_from_raw(text: &str) -> Vec<(String, Value)> {
-    let trimmed = text.trim();
+    try_parse_all_from_raw_for_format(text, None)
+}
+
+/// [`try_parse_all_from_raw`], trying the model's native `tool_format` before
+/// the JSON batch parser when it is not JSON itself.
+pub fn try_parse_all_from_raw_for_format(
+    text: &str,
+    tool_format: Option<&str>,
+) -> Vec<(String, Value)> {
+    let trimmed = strip_tool_call_wrapper(text);
+    let parsers = single_call_parsers(tool_format);
+
+    // A non-JSON native format sits at the front; it beats the JSON batch parser
+    let has_native = tool_format
+        .and_then(native_parser_name)
+        .is_some_and(|native| native != "json");
+    if has_native {
+        if let Some(result) = (parsers[0].1)(trimmed) {
+            return vec![result];
+        }
+    }
 
     // JSON array/object — may contain multiple calls
     if let Some(calls) = try_parse_all_json_calls(trimmed) {
@@ -317,19 +443,11 @@ pub fn try_parse_all_from_raw(text: &str) -> Vec<(String, Value)> {
     }
 
     // Single-call formats — return as vec of 1
-    if let Some(result) = try_parse_lfm2_python_call_format(trimmed) {
-        return vec![result];
-    }
-    if let Some(result) = try_parse_mistral_comma_format(trimmed) {
-        return vec![result];
-    }
-    if let Some(result) = try_parse_llama3_xml_format(trimmed) {
-        return vec![result];
-    }
-    if let Some(result) = try_parse_glm_xml_format(trimmed) {
-        return vec![result];
-    }
-    if let Some(result) = try_parse_name_json_format(trimmed) {
+    if let Some(result) = parsers
+        .iter()
+        .filter(|&&(name, _)| name != "json")
+        .find_map(|&(_, parse)| parse(trimmed))
+    {
         return vec![result];
     }
     if let Some(result) = try_infer_tool_from_bare_args(trimmed) {
@@ -2,6 +2,8 @@
 
 use serde_json::Value;
 
+use crate::NativeToolResult;
+
 const MAX_SEARCH_MATCHES: usize = 50;
 const MAX_SEARCH_OUTPUT_CHARS: usize = 8000;
 const MAX_SEARCH_FILE_SIZE: u64 = 2 * 1024 * 1024; // 2MB — skip large files
@@ -62,10 +64,10 @@ fn build_walker(
 
 /// Search file contents by pattern (literal or regex) across a directory.
 /// Uses the `ignore` crate for .gitignore-aware traversal.
-pub fn tool_search_files(args: &Value) -> String {
+pub fn tool_search_files(args: &Value) -> NativeToolResult {
     let pattern = match args.get("pattern").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'pattern' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'pattern' argument is required".to_string()),
     };
     let search_path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
     let include = args.get("include").and_then(|v| v.as_str()).unwrap_or("");
@@ -77,7 +79,7 @@ pub fn tool_search_files(args: &Value) -> String {
         Ok(r) => r,
         Err(_) => match regex::Regex::new(&regex::escape(pattern)) {
             Ok(r) => r,
-            Err(e) => return format!("Error: invalid pattern: {e}"),
+            Err(e) => return NativeToolResult::failure(format!("Error: invalid pattern: {e}")),
         },
     };
 
@@ -151,7 +153,7 @@ pub fn tool_search_files(args: &Value) -> String {
     }
 
     if results.is_empty() {
-        return format!("No matches found for '{pattern}' in {search_path}");
+        return NativeToolResult::text_only(format!("No matches found for '{pattern}' in {search_path}"));
     }
 
     let mut output = format!(
@@ -169,17 +171,17 @@ pub fn tool_search_files(args: &Value) -> String {
         output.push('\n');
         chars += line.len() + 1;
     }
-    output
+    NativeToolResult::text_only(output)
 }
 
 const MAX_FIND_RESULTS: usize = 100;
 
 /// Find files by glob-like pattern recursively.
 /// Uses the `ignore` crate for .gitignore-aware traversal.
-pub fn tool_find_files(args: &Value) -> String {
+pub fn tool_find_files(args: &Value) -> NativeToolResult {
     let pattern = match args.get("pattern").and_then(|v| v.as_str()) {
         Some(p) => p,
-        None => return "Error: 'pattern' argument is required".to_string(),
+        None => return NativeToolResult::failure("Error: 'pattern' argument is required".to_string()),
     };
     let search_path = args.get("path").and_then(|v| v.as_str()).unwrap_or(".");
     let exclude = args.get("exclude").and_then(|v| v.as_str()).unwrap_or("");
@@ -194,7 +196,7 @@ pub fn tool_find_files(args: &Value) -> String {
     }
 
     if results.is_empty() {
-        return format!("No files matching '{pattern}' found in {search_path}");
+        return NativeToolResult::text_only(format!("No files matching '{pattern}' found in {search_path}"));
     }
 
     let total = results.len();
@@ -207,5 +209,5 @@ pub fn tool_find_files(args: &Value) -> String {
     if truncated {
         output.push_str(&format!("... (results capped at {MAX_FIND_RESULTS})\n"));
     }
-    output
+    NativeToolResult::text_only(output)
 }
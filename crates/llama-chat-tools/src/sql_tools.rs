@@ -0,0 +1,73 @@
+//! `sql_query` tool — read-only SELECTs over the app's conversation database.
+//!
+//! Off unless listed in the config's `enabled_tools` (see `OPT_IN_TOOLS`).
+
+use serde_json::Value;
+
+/// Default and maximum rows returned per query.
+const DEFAULT_MAX_ROWS: usize = 50;
+const MAX_ROWS_LIMIT: usize = 500;
+/// Output is cut at this many characters.
+const MAX_OUTPUT_CHARS: usize = 8000;
+
+/// Run the `sql_query` tool and render the rows as a pipe-separated table.
+pub fn tool_sql_query(args: &Value, db: Option<&llama_chat_db::SharedDatabase>) -> String {
+    let query = match args.get("query").and_then(|v| v.as_str()) {
+        Some(q) if !q.trim().is_empty() => q,
+        _ => return "Error: 'query' argument is required".to_string(),
+    };
+    let Some(db) = db else {
+        return "Error: no database available".to_string();
+    };
+    let max_rows = args
+        .get("max_rows")
+        .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.trim().parse().ok())))
+        .map_or(DEFAULT_MAX_ROWS, |n| (n as usize).clamp(1, MAX_ROWS_LIMIT));
+
+    match db.read_only_query(query, max_rows) {
+        Ok(result) => format_rows(&result),
+        Err(e) => format!("Error: {e}"),
+    }
+}
+
+fn format_rows(result: &llama_chat_db::read_only_query::QueryRows) -> String {
+    let mut out = result.columns.join(" | ");
+    for row in &result.rows {
+        out.push('\n');
+        out.push_str(&row.join(" | "));
+    }
+    let mut footer = format!("\n({} row{})", result.rows.len(), if result.rows.len() == 1 { "" } else { "s" });
+    if result.truncated {
+        footer.push_str(" [more rows not shown; raise max_rows or narrow the query]");
+    }
+    if out.chars().count() > MAX_OUTPUT_CHARS {
+        out = out.chars().take(MAX_OUTPUT_CHARS).collect();
+        out.push_str("\n[Truncated]");
+    }
+    out + &footer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sql_query_renders_a_compact_table() {
+        let db: llama_chat_db::SharedDatabase =
+            std::sync::Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "hi", 10, 0).unwrap();
+        db.insert_message(&id, "assistant", "hello", 11, 1).unwrap();
+
+        let out = tool_sql_query(
+            &json!({"query": "SELECT role, content FROM messages ORDER BY sequence_order"}),
+            Some(&db),
+        );
+        assert_eq!(out, "role | content\nuser | hi\nassistant | hello\n(2 rows)");
+
+        let out = tool_sql_query(&json!({"query": "DELETE FROM messages"}), Some(&db));
+        assert!(out.starts_with("Error: Only SELECT"), "{out}");
+        assert_eq!(db.get_messages(&id).unwrap().len(), 2);
+    }
+}
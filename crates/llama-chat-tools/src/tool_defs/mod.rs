@@ -8,7 +8,7 @@
 //!   - `input_tools`         — click_screen, type_text, press_key, scroll, drag (with verify params)
 //!   - `clipboard_tools`     — clipboard read/write/html/files
 //!   - `ui_automation_tools` — UIA interactions, OCR-click combos
-//!   - `system_tools`        — processes, audio, registry, notifications, git, sleep
+//!   - `system_tools`        — processes, audio, registry, notifications, git, sleep, sql_query
 //!   - `agent_tools`         — MCP, spawn_agent, todo, skills, add_mcp_server
 
 mod tool_defs_categories;
@@ -21,7 +21,7 @@ use tool_defs_categories::{
 
 // ─── Verification helper (re-exported for tests) ────────────────────────────
 #[cfg(test)]
-pub const EXPECTED_TOOL_COUNT: usize = 147;
+pub const EXPECTED_TOOL_COUNT: usize = 148;
 
 // ─── Public API ─────────────────────────────────────────────────────────────
 
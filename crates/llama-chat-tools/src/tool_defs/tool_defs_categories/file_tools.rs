@@ -6,7 +6,7 @@ pub static FILE_TOOLS: &[ToolDef] = &[
     // ─── read_file ───
     ToolDef {
         name: "read_file",
-        description: "Read the contents of a file. Supports PDF, DOCX, XLSX, PPTX, EPUB, ODT, RTF, CSV, EML, ZIP, and non-UTF8 encoded files. Returns the file text (truncated at 100KB for large files). Binary files (exe, images, audio, etc.) are rejected — use a specialized tool for those.",
+        description: "Read the contents of a file. Supports PDF, DOCX, XLSX, PPTX, EPUB, ODT, RTF, CSV, EML, ZIP, and non-UTF8 encoded files. Returns the file text (truncated at a configured size, 100KB by default). Binary files (exe, images, audio, etc.) are rejected — use a specialized tool for those.",
         params: Params::Simple(&[
             p("path", "string", "Path to the file to read"),
             p("offset", "integer", "Line number to start reading from (1-based). Use with limit to read specific portions of large files."),
@@ -308,6 +308,16 @@ pub static SYSTEM_TOOLS: &[ToolDef] = &[
         ]),
         required: &["seconds"],
     },
+    // ─── sql_query ───
+    ToolDef {
+        name: "sql_query",
+        description: "Run a read-only SELECT against the app's conversation database (tables: conversations, messages, agents, tool_calls, ...; app settings and secrets are not readable). Only a single SELECT (or WITH ... SELECT) is accepted. Filter message text with message_text(content) LIKE '%...%' (large messages are stored compressed). Returns rows as a pipe-separated table.",
+        params: Params::Simple(&[
+            p("query", "string", "A single SELECT statement"),
+            p("max_rows", "integer", "Maximum rows to return (default 50, max 500)"),
+        ]),
+        required: &["query"],
+    },
     // ─── dialog_handler_stop ───
     ToolDef {
         name: "dialog_handler_stop",
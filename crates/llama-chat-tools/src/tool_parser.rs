@@ -43,6 +43,14 @@ lazy_static::lazy_static! {
     static ref MISTRAL_JSON_PREFIX: Regex = Regex::new(
         r"\[TOOL_CALLS\]\s*\{"
     ).unwrap();
+
+    // Explicitly closed wrappers around any payload format:
+    //   [TOOL_CALLS]read_file,{"path":"x"}[/TOOL_CALLS]   (Mistral comma format)
+    //   <tool_call>{"name":"read_file",...}</tool_call>     (Hermes/Qwen)
+    // The inner payload is handed to the parsing fixup chain as-is.
+    static ref WRAPPED_TOOL_CALL_PATTERN: Regex = Regex::new(
+        r"(?s)(?:\[TOOL_CALLS\](.+?)\[/TOOL_CALLS\]|<tool_call>(.+?)</tool_call>)"
+    ).unwrap();
 }
 
 /// Extract balanced JSON starting at position `start` in `text`.
@@ -128,6 +136,7 @@ pub const FORMAT_PRIORITY: &[(&str, FormatDetector)] = &[
     ("tool_call_json", detect_tool_call_json),
     ("mistral_bracket", detect_mistral_bracket),
     ("mistral_json", detect_mistral_json),
+    ("wrapped", detect_wrapped),
 ];
 
 fn detect_model_specific(text: &str, tags: &ToolTags) -> Option<String> {
@@ -298,3 +307,16 @@ fn detect_mistral_json(text: &str, _tags: &ToolTags) -> Option<String> {
         None
     }
 }
+
+/// Detect a payload enclosed in explicit `[TOOL_CALLS]...[/TOOL_CALLS]` or
+/// `<tool_call>...</tool_call>` boundaries. Runs last so the stricter
+/// bracket/JSON detectors above get first pick of the same text.
+fn detect_wrapped(text: &str, _tags: &ToolTags) -> Option<String> {
+    let caps = WRAPPED_TOOL_CALL_PATTERN.captures(text)?;
+    let inner = caps.get(1).or_else(|| caps.get(2))?.as_str().trim();
+    if inner.is_empty() {
+        None
+    } else {
+        Some(inner.to_string())
+    }
+}
@@ -0,0 +1,79 @@
+//! Compile-time build information: which GPU/vision backends were compiled in,
+//! plus crate and llama.cpp binding versions. Reported by `/api/info` and
+//! `/api/system/usage` so clients don't have to guess from behavior.
+
+/// Version of this build (workspace crates share one version).
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Version of the `llama-cpp-2` bindings the workspace is pinned to (see Cargo.lock).
+pub const LLAMA_CPP_VERSION: &str = "0.1.151";
+
+/// Names of the optional backend features enabled at compile time, in a stable order.
+pub fn compiled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "cuda") {
+        features.push("cuda");
+    }
+    if cfg!(feature = "metal") {
+        features.push("metal");
+    }
+    if cfg!(feature = "dynamic-backends") {
+        features.push("dynamic-backends");
+    }
+    if cfg!(feature = "vision") {
+        features.push("vision");
+    }
+    features
+}
+
+/// JSON summary used by the system/info routes.
+pub fn build_info_json() -> serde_json::Value {
+    serde_json::json!({
+        "version": CRATE_VERSION,
+        "llama_cpp_version": LLAMA_CPP_VERSION,
+        "features": compiled_features(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compiled_features_match_cfg() {
+        let features = compiled_features();
+        assert_eq!(features.contains(&"cuda"), cfg!(feature = "cuda"));
+        assert_eq!(features.contains(&"metal"), cfg!(feature = "metal"));
+        assert_eq!(features.contains(&"vision"), cfg!(feature = "vision"));
+        assert_eq!(
+            features.contains(&"dynamic-backends"),
+            cfg!(feature = "dynamic-backends")
+        );
+    }
+
+    #[cfg(not(any(
+        feature = "cuda",
+        feature = "metal",
+        feature = "vision",
+        feature = "dynamic-backends"
+    )))]
+    #[test]
+    fn test_default_build_has_no_backend_features() {
+        assert!(compiled_features().is_empty());
+    }
+
+    #[cfg(all(feature = "cuda", feature = "vision", not(feature = "metal")))]
+    #[test]
+    fn test_cuda_vision_build() {
+        let features = compiled_features();
+        assert!(features.starts_with(&["cuda"]));
+        assert!(features.ends_with(&["vision"]));
+    }
+
+    #[test]
+    fn test_build_info_json_shape() {
+        let info = build_info_json();
+        assert_eq!(info["version"], CRATE_VERSION);
+        assert!(info["features"].is_array());
+    }
+}
@@ -0,0 +1,9 @@
+//! `LLAMA_CHAT_DEBUG=1`: the one switch for debug-only behavior, such as the
+//! `echo_prompt` request option and chat-socket stream statistics.
+
+/// Environment variable that turns debugging on.
+pub const DEBUG_ENV: &str = "LLAMA_CHAT_DEBUG";
+
+pub fn debug_enabled() -> bool {
+    std::env::var(DEBUG_ENV).ok().as_deref() == Some("1")
+}
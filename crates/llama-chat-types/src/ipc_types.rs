@@ -5,7 +5,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::event_log::ConversationEvent;
-use crate::models::{TokenBreakdown, ToolTimingLive};
+use crate::models::{AlternativeCompletion, ConfirmationRequest, GenerationOverrides, LoadOutcome, LoadPhase, OverflowPolicy, OverflowReport, PromptEcho, PromptEvalProgress, RecommendedSampling, TokenBreakdown, TokenLogprob, ToolCallRequest, ToolTimingLive};
 
 /// Request sent from server to worker via stdin.
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,6 +27,9 @@ pub enum WorkerCommand {
         /// Agent ID to use for loading agent-specific config (KV cache, context size, etc.).
         #[serde(default)]
         agent_id: Option<String>,
+        /// KV cache type overrides for this load (unset = use the config's cache types).
+        #[serde(default)]
+        kv_cache_type: crate::models::KvCacheTypes,
     },
     /// Unload the current model (free memory within the process).
     UnloadModel,
@@ -44,9 +47,19 @@ pub enum WorkerCommand {
         /// Set this for new conversations so the worker uses the correct config from the start.
         #[serde(default)]
         agent_id: Option<String>,
+        /// What to do when the conversation doesn't fit the context.
+        #[serde(default)]
+        on_overflow: OverflowPolicy,
+        /// Per-request sampler overrides merged over the stored config.
+        #[serde(default)]
+        overrides: GenerationOverrides,
+    },
+    /// Cancel the in-progress generation. With a `conversation_id`, only if that
+    /// conversation is the one generating; otherwise the request is ignored.
+    CancelGeneration {
+        #[serde(default)]
+        conversation_id: Option<String>,
     },
-    /// Cancel the in-progress generation.
-    CancelGeneration,
     /// Generate a short title for a conversation (no conversation logging).
     GenerateTitle {
         conversation_id: String,
@@ -62,12 +75,30 @@ pub enum WorkerCommand {
     GetGlobalStatus,
     /// List available compute backends (CUDA, Vulkan, CPU, etc.).
     GetAvailableBackends,
-    /// Force compact a conversation (manual user action).
-    CompactConversation { conversation_id: String },
+    /// Force compact a conversation (manual user action). With `turns`, only
+    /// the oldest that many turns are summarized.
+    CompactConversation {
+        conversation_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        turns: Option<usize>,
+    },
     /// Call an MCP tool by qualified name from the server side (for remote providers).
     CallMcpTool { name: String, args_json: String },
     /// Get full MCP tool definitions (with JSON schemas) for all connected servers.
     GetMcpToolDefinitions,
+    /// Force a chat template for the loaded model (None = back to detection).
+    SetTemplateOverride { template: Option<String> },
+    /// Replace the system prompt (None = agentic default) and re-warm it in the
+    /// background, keeping the loaded model.
+    SetSystemPrompt { prompt: Option<String> },
+    /// Run a short fixed-prompt generation to prove load → decode → sample works.
+    SelfTest,
+    /// Write the cached inference context (KV cache + tokens) to a session file.
+    SaveSession { path: String },
+    /// Restore a session file saved by SaveSession into the inference cache.
+    LoadSession { path: String },
+    /// Count `text`'s tokens with the loaded model's tokenizer (no BOS).
+    CountTokens { text: String },
     /// Health check.
     Ping,
     /// Graceful shutdown.
@@ -96,6 +127,15 @@ pub enum WorkerPayload {
         block_count: Option<u32>,
         general_name: Option<String>,
         has_vision: Option<bool>,
+        /// Full reload, or context-only rebuild of the already-loaded model.
+        #[serde(default)]
+        load_outcome: LoadOutcome,
+        /// Backend the model runs on ("cpu" after a no-device fallback).
+        #[serde(default)]
+        effective_backend: Option<String>,
+        /// Sampler defaults from the model's GGUF `general.sampling.*` keys.
+        #[serde(default)]
+        recommended_sampling: Option<RecommendedSampling>,
     },
     /// Model unloaded.
     ModelUnloaded,
@@ -128,6 +168,18 @@ pub enum WorkerPayload {
         status: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         tool_timing: Option<ToolTimingLive>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt_eval_progress: Option<PromptEvalProgress>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        logprobs: Option<TokenLogprob>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reasoning: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        confirmation_required: Option<ConfirmationRequest>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_id: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt_echo: Option<PromptEcho>,
     },
     /// Generation completed successfully.
     GenerationComplete {
@@ -146,11 +198,27 @@ pub enum WorkerPayload {
         prompt_eval_ms: Option<f64>,
         /// Number of prompt tokens evaluated.
         prompt_tokens: Option<i32>,
-        /// Why generation stopped: "stop", "length", "cancelled", "tool_calls", "error".
+        /// Why generation stopped: "stop", "length", "cancelled", "timeout", "tool_calls", "error".
         finish_reason: Option<String>,
         /// Token usage breakdown by category.
         #[serde(skip_serializing_if = "Option::is_none")]
         token_breakdown: Option<TokenBreakdown>,
+        /// Overflow policy applied and how many messages it trimmed.
+        #[serde(default)]
+        overflow: OverflowReport,
+        /// Per-token logprobs, when the request asked for them.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        logprobs: Vec<TokenLogprob>,
+        /// Tool calls in the response. Unexecuted when finish_reason is "tool_calls"
+        /// (auto_execute_tools off); already run otherwise.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<ToolCallRequest>,
+        /// The request `stop` sequence that ended it (finish_reason "stop_sequence").
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stop_sequence: Option<String>,
+        /// Completions 2..=n when the request asked for `n` > 1.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        alternatives: Vec<AlternativeCompletion>,
     },
     /// Generation was cancelled by the user.
     GenerationCancelled,
@@ -168,8 +236,12 @@ pub enum WorkerPayload {
     McpStatus {
         servers: Vec<McpServerStatus>,
     },
-    /// Model loading progress update (0-100).
-    LoadingProgress { progress: u8 },
+    /// Model loading progress update (0-100, 101 = warming up) with its coarse phase.
+    LoadingProgress {
+        progress: u8,
+        #[serde(default)]
+        phase: Option<LoadPhase>,
+    },
     /// Health check response.
     Pong,
     /// Available compute backends.
@@ -184,6 +256,22 @@ pub enum WorkerPayload {
     McpToolResult { result: Option<String>, error: Option<String> },
     /// Full MCP tool definitions with schemas.
     McpToolDefinitions { tools: Vec<McpToolDefPayload> },
+    /// Template override applied (echoes the active override).
+    TemplateOverrideSet { template: Option<String> },
+    /// System prompt swapped; its warmup is running in the background.
+    SystemPromptSet,
+    /// Self-test passed: the generated text and its speed.
+    SelfTestResult {
+        first_tokens: String,
+        tokens_generated: usize,
+        gen_tok_per_sec: Option<f64>,
+    },
+    /// Session written; `n_tokens` is the saved token position.
+    SessionSaved { conversation_id: String, n_tokens: usize },
+    /// Session restored into the inference cache.
+    SessionLoaded { conversation_id: String, n_tokens: usize },
+    /// Result of a CountTokens command.
+    TokenCount { count: usize },
     /// An error occurred.
     Error { message: String },
 }
@@ -1,3 +1,6 @@
+pub mod build_info;
+pub mod debug;
+pub mod log_buffer;
 pub mod logger;
 pub mod models;
 pub mod tool_tags;
@@ -5,6 +8,7 @@ pub mod ipc_types;
 pub mod event_log;
 pub mod mcp_config;
 pub mod native_tool_result;
+pub mod reasoning;
 
 // Re-export key types at crate root for convenience
 pub use models::*;
@@ -14,3 +18,4 @@ pub use event_log::ConversationEvent;
 pub use logger::{Logger, LOGGER};
 pub use mcp_config::{McpServerConfig, McpTransport};
 pub use native_tool_result::NativeToolResult;
+pub use reasoning::{split_reasoning, ReasoningSplitter, ReasoningTags};
@@ -0,0 +1,161 @@
+//! Bounded in-memory buffer of recent log lines, tailed by `GET /api/logs/stream`.
+//!
+//! Fed by `Logger::log` (server-side log macros) and by the worker's forwarded
+//! stderr. Oldest lines are evicted once the buffer is full.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// Lines kept for late subscribers.
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+/// Longer messages are cut so one huge dump can't crowd out the buffer.
+const MAX_MESSAGE_CHARS: usize = 4000;
+/// Env var names containing any of these are treated as secrets.
+const SECRET_ENV_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD"];
+
+/// One buffered log line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    /// Increases by one per pushed line; subscribers resume from the last seen.
+    pub seq: u64,
+    pub timestamp: String,
+    pub level: String,
+    /// "system", a conversation id, or "worker" for forwarded worker stderr.
+    pub source: String,
+    pub message: String,
+}
+
+/// Severity order used by the `level` filter: DEBUG < INFO < WARN < ERROR.
+pub fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "DEBUG" => 0,
+        "WARN" | "WARNING" => 2,
+        "ERROR" => 3,
+        _ => 1,
+    }
+}
+
+struct Inner {
+    lines: VecDeque<LogLine>,
+    next_seq: u64,
+}
+
+pub struct LogBuffer {
+    inner: Mutex<Inner>,
+    capacity: usize,
+    secrets: Vec<String>,
+}
+
+impl LogBuffer {
+    /// Buffer that redacts the current values of secret-looking env vars.
+    pub fn new(capacity: usize) -> Self {
+        let secrets = std::env::vars()
+            .filter(|(name, value)| {
+                let name = name.to_ascii_uppercase();
+                value.len() >= 4 && SECRET_ENV_MARKERS.iter().any(|m| name.contains(m))
+            })
+            .map(|(_, value)| value)
+            .collect();
+        Self::with_secrets(capacity, secrets)
+    }
+
+    fn with_secrets(capacity: usize, secrets: Vec<String>) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                lines: VecDeque::with_capacity(capacity),
+                next_seq: 0,
+            }),
+            capacity,
+            secrets,
+        }
+    }
+
+    pub fn push(&self, level: &str, source: &str, message: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut message: String = message.chars().take(MAX_MESSAGE_CHARS).collect();
+        for secret in &self.secrets {
+            if message.contains(secret.as_str()) {
+                message = message.replace(secret.as_str(), "[redacted]");
+            }
+        }
+        let timestamp = chrono::Local::now()
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string();
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        if inner.lines.len() == self.capacity {
+            inner.lines.pop_front();
+        }
+        let seq = inner.next_seq;
+        inner.next_seq += 1;
+        inner.lines.push_back(LogLine {
+            seq,
+            timestamp,
+            level: level.to_string(),
+            source: source.to_string(),
+            message,
+        });
+    }
+
+    /// Buffered lines with `seq >= from`, oldest first.
+    pub fn lines_since(&self, from: u64) -> Vec<LogLine> {
+        let Ok(inner) = self.inner.lock() else {
+            return Vec::new();
+        };
+        inner
+            .lines
+            .iter()
+            .filter(|l| l.seq >= from)
+            .cloned()
+            .collect()
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref LOG_BUFFER: LogBuffer = LogBuffer::new(LOG_BUFFER_CAPACITY);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_buffer_evicts_oldest() {
+        let buffer = LogBuffer::with_secrets(3, Vec::new());
+        for i in 0..5 {
+            buffer.push("INFO", "system", &format!("line {i}"));
+        }
+        let lines = buffer.lines_since(0);
+        let messages: Vec<&str> = lines.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(messages, ["line 2", "line 3", "line 4"]);
+        assert_eq!(lines.iter().map(|l| l.seq).collect::<Vec<_>>(), [2, 3, 4]);
+
+        // Resuming from a seq returns only what came after it
+        assert_eq!(buffer.lines_since(4).len(), 1);
+        assert!(buffer.lines_since(5).is_empty());
+    }
+
+    #[test]
+    fn test_secrets_are_redacted_and_messages_capped() {
+        let buffer = LogBuffer::with_secrets(10, vec!["sk-live-1234".to_string()]);
+        buffer.push("WARN", "worker", "auth failed with key sk-live-1234");
+        buffer.push("INFO", "system", &"x".repeat(MAX_MESSAGE_CHARS + 10));
+
+        let lines = buffer.lines_since(0);
+        assert_eq!(lines[0].message, "auth failed with key [redacted]");
+        assert_eq!(lines[0].source, "worker");
+        assert_eq!(lines[1].message.chars().count(), MAX_MESSAGE_CHARS);
+    }
+
+    #[test]
+    fn test_level_rank_orders_levels() {
+        assert!(level_rank("debug") < level_rank("INFO"));
+        assert!(level_rank("INFO") < level_rank("warning"));
+        assert!(level_rank("WARN") < level_rank("ERROR"));
+    }
+}
@@ -52,6 +52,8 @@ impl Logger {
     }
 
     pub fn log(&self, conversation_id: &str, level: &str, message: &str) {
+        // The live log stream works even with file logging off
+        crate::log_buffer::LOG_BUFFER.push(level, conversation_id, message);
         if !self.enabled.load(Ordering::Relaxed) {
             return;
         }
@@ -12,10 +12,15 @@ use crate::tool_tags::{TagPair, ToolTags};
 #[path = "models/payloads.rs"]
 mod payloads;
 pub use payloads::{
-    ApprovalRequest, BrowseFilesResponse, ChatMessage, ChatRequest, ChatResponse,
-    ConversationContentResponse, ConversationFile, ConversationsResponse, FileItem, MessagePart,
-    ModelLoadRequest, ModelResponse, ModelStatus, ToolTiming, ToolTimingLive, TokenData,
+    ApprovalRequest, BrowseFilesResponse, ChatMessage, ChatRequest, ChatResponse, ChatUsage,
+    ConfirmationRequest, ConversationContentResponse, ConversationFile, ConversationPageResponse,
+    ConversationSummaryEntry, ConversationsResponse, FileItem, KvCacheType, KvCacheTypes, LoadOutcome, LoadPhase,
+    LoadProgressEvent, MessagePart, ModelLoadRequest, ModelResponse, ModelStatus, PromptEcho, PromptEvalProgress, ToolTiming, ToolTimingLive, TokenData,
+    TokenLogprob, ToolCallRequest, TopLogprob, AlternativeCompletion,
 };
+#[path = "models/sampler_presets.rs"]
+mod sampler_presets;
+pub use sampler_presets::{sampler_preset, sampler_presets, unknown_sampler_preset, SamplerPreset};
 
 // Import logging macros
 use crate::sys_debug;
@@ -25,8 +30,8 @@ use crate::sys_debug;
 /// SAFETY: The `context` field stores a `LlamaContext` whose lifetime is erased
 /// to `'static`. The actual lifetime is tied to the `LlamaModel` in the parent
 /// `LlamaState`. The context MUST be dropped (set to `None`) before the model
-/// is dropped. This invariant is enforced by clearing `inference_cache` in
-/// `model_manager.rs` before any model change or unload.
+/// is dropped. This invariant is enforced by clearing `inference_cache` (and
+/// `idle_contexts`) in `model_manager.rs` before any model change or unload.
 pub struct InferenceCache {
     /// Reusable LlamaContext with erased lifetime (model must outlive this).
     pub context: LlamaContext<'static>,
@@ -86,6 +91,182 @@ pub struct TokenBreakdown {
     pub model_response: i32,
 }
 
+/// What generation does when the conversation doesn't fit the context window.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Compact (summarize) older messages, then fail if still too large.
+    #[default]
+    AutoReduceContext,
+    /// Drop the oldest messages from the prompt until it fits. The DB is untouched.
+    TrimOldest,
+    /// Fail immediately without compacting or trimming.
+    Error,
+}
+
+/// Overflow handling applied to one generation, reported on completion.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OverflowReport {
+    pub policy: OverflowPolicy,
+    /// Messages left out of the prompt by `TrimOldest`.
+    pub trimmed_messages: usize,
+}
+
+/// Per-request sampler overrides, merged over the stored config for one generation.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GenerationOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Cap on generated tokens (the context budget still applies).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampler_type: Option<String>,
+    /// Named preset (see `sampler_presets()`, including the ones defined in
+    /// config) expanded before the fields above, so explicitly set ones win over it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampler_preset: Option<String>,
+    /// Return this many top alternatives with each token's log-probability.
+    /// Off by default: capturing them costs a pass over the vocabulary per token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u32>,
+    /// Text the assistant's reply is forced to start with (e.g. `{` for JSON).
+    /// Appended after the assistant-turn opener and included in the returned content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assistant_prefill: Option<String>,
+    /// Send the user text to the model as-is: no chat template, system prompt,
+    /// conversation history or tool definitions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_completion: Option<bool>,
+    /// Prepend BOS at tokenization. Defaults to true for `raw_completion`; for
+    /// templated prompts the default comes from the model and template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub add_bos: Option<bool>,
+    /// Include each sampled token's id in the streamed `TokenData`, for
+    /// debugging templates and tokenization. Off by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_token_ids: Option<bool>,
+    /// Before any tokens, send one `prompt_echo` event with the resolved system
+    /// prompt, template type and tool names. Only honored when the server runs
+    /// with `LLAMA_CHAT_DEBUG=1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub echo_prompt: Option<bool>,
+    /// Extra stop strings for this request, on top of the configured or model
+    /// ones. A match ends generation with finish_reason "stop_sequence".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// Independent completions to generate for the prompt (default 1, at most
+    /// `max_completions`). The first streams and is stored as usual; the others
+    /// branch off the same cached prompt and come back as `alternatives`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+}
+
+impl GenerationOverrides {
+    /// Just the sampler fields, as stored per conversation. Per-turn options
+    /// (`max_tokens`, `logprobs`, prefill, raw mode, BOS) are dropped.
+    pub fn sampler_only(&self) -> Self {
+        Self {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            seed: self.seed,
+            sampler_type: self.sampler_type.clone(),
+            sampler_preset: self.sampler_preset.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Overwrite the sampler fields that are set, after expanding
+    /// `sampler_preset` (unknown names are ignored here). `max_tokens`, `logprobs`,
+    /// `assistant_prefill`, `raw_completion`, `add_bos`, `stream_token_ids`,
+    /// `echo_prompt`, `stop` and `n` have no config field; generation reads them
+    /// directly (`stop` never touches `stop_tokens`).
+    pub fn apply_to(&self, config: &mut SamplerConfig) {
+        let preset = self
+            .sampler_preset
+            .as_deref()
+            .and_then(|name| sampler_preset(name, &config.sampler_presets));
+        if let Some(preset) = preset {
+            // A preset never names another preset
+            let params = GenerationOverrides { sampler_preset: None, ..preset.params.sampler_only() };
+            params.apply_to(config);
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = top_p;
+        }
+        if let Some(top_k) = self.top_k {
+            config.top_k = top_k;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        if let Some(ref sampler_type) = self.sampler_type {
+            config.sampler_type = sampler_type.clone();
+        }
+    }
+}
+
+/// Sampler defaults a model ships in its GGUF `general.sampling.*` keys.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RecommendedSampling {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f64>,
+    /// Seeds `repeat_penalty`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f64>,
+}
+
+impl RecommendedSampling {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Overwrite the fields the model recommends. Only meant for a config
+    /// holding no sampler choices of the user's; their explicit settings are
+    /// layered on afterwards (see `resolve_generation_config`). Returns the
+    /// names of the fields set.
+    pub fn apply_to(&self, config: &mut SamplerConfig) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if let Some(temperature) = self.temperature {
+            config.temperature = temperature;
+            applied.push("temperature");
+        }
+        if let Some(top_p) = self.top_p {
+            config.top_p = top_p;
+            applied.push("top_p");
+        }
+        if let Some(top_k) = self.top_k {
+            config.top_k = top_k;
+            applied.push("top_k");
+        }
+        if let Some(min_p) = self.min_p {
+            config.min_p = min_p;
+            applied.push("min_p");
+        }
+        if let Some(penalty) = self.repetition_penalty {
+            config.repeat_penalty = penalty;
+            applied.push("repeat_penalty");
+        }
+        applied
+    }
+}
+
 // Configuration structure
 #[derive(Deserialize, Serialize, Clone)]
 pub struct SamplerConfig {
@@ -153,6 +334,7 @@ pub struct SamplerConfig {
     // App settings
     #[serde(default)]
     pub web_browser_backend: Option<String>,
+    /// Models folder; relative model paths resolve against it (None = paths used as given).
     #[serde(default)]
     pub models_directory: Option<String>,
     // Hardware / context / sampler params
@@ -193,19 +375,147 @@ pub struct SamplerConfig {
     // Provider API keys (JSON blob for OpenAI-compatible providers)
     #[serde(default)]
     pub provider_api_keys: Option<String>,
-    // Max tool calls per remote provider turn (safety limit)
+    // Max tool calls per remote provider turn (safety limit); local models
+    // use max_tool_iterations
     #[serde(default = "default_max_tool_calls")]
     pub max_tool_calls: i32,
     #[serde(default = "default_loop_detection_limit")]
     pub loop_detection_limit: i32,
+    /// zstd-compress large message bodies at rest (older rows stay readable either way).
+    #[serde(default)]
+    pub compress_messages: bool,
+    /// Allow absolute model paths outside `models_directory` (relative paths must
+    /// always stay inside it).
+    #[serde(default = "default_true")]
+    pub allow_external_model_paths: bool,
     /// Enable thinking/reasoning mode (Qwen3, DeepSeek-R1, GLM-4, Gemma-4).
     /// None = use model default (true when supported). Some(false) = disable.
     #[serde(default)]
     pub thinking_mode: Option<bool>,
+    /// Run detected tool calls inline (default). When false, generation stops at
+    /// the first complete tool call and returns it with finish_reason "tool_calls".
+    #[serde(default = "default_true")]
+    pub auto_execute_tools: bool,
+    /// Temperature guardrails: the sampler clamps `temperature` into
+    /// `[temperature_min, temperature_max]`; ~0 switches to greedy sampling.
+    #[serde(default)]
+    pub temperature_min: f64,
+    #[serde(default = "default_temperature_max")]
+    pub temperature_max: f64,
+    /// Stop a generation that runs longer than this many seconds, keeping what
+    /// was produced (finish_reason "timeout"). None = no limit.
+    #[serde(default)]
+    pub generation_timeout_secs: Option<u32>,
+    /// Native tools the model is told about and may call. None = all tools;
+    /// MCP tools are unaffected.
+    #[serde(default)]
+    pub enabled_tools: Option<Vec<String>>,
+    /// Collapse blank-line runs and trailing whitespace in tool output before it
+    /// is injected into the model context. The stored transcript keeps the raw text.
+    #[serde(default = "default_true")]
+    pub normalize_tool_output: bool,
+    /// Upper bound on the context size when `context_size` is cleared (it
+    /// defaults to 32768), so models advertising 128K+ don't allocate a huge KV
+    /// cache. 0 = use the model's full advertised context.
+    #[serde(default = "default_context_cap")]
+    pub default_context_cap: u32,
+    /// Tell the model about tools and dispatch its tool calls (default). When
+    /// false the prompt has no tool definitions and the model is a plain chat
+    /// assistant, for small models that derail on tool blocks.
+    #[serde(default = "default_true")]
+    pub tools_enabled: bool,
+    /// Tags delimiting a reasoning model's thinking. That text is streamed
+    /// separately and left out of the stored answer; blank disables stripping.
+    #[serde(default = "default_reasoning_tag_open")]
+    pub reasoning_tag_open: String,
+    #[serde(default = "default_reasoning_tag_close")]
+    pub reasoning_tag_close: String,
+    /// Tools (e.g. `write_file`, `execute_command`) whose calls pause the
+    /// generation until confirmed via `POST /api/generation/confirm`. Empty = none.
+    #[serde(default)]
+    pub require_confirmation_for: Vec<String>,
+    /// Generations the worker runs at once, each with its own context on the
+    /// one loaded model. Further requests queue for a free context.
+    #[serde(default = "default_max_parallel_generations")]
+    pub max_parallel_generations: u32,
+    /// Named sampler preset applied at generation time, over the model's
+    /// recommended sampling. It doesn't touch an agent's saved sampler
+    /// fields, and per-conversation and per-request parameters win over it.
+    #[serde(default)]
+    pub sampler_preset: Option<String>,
+    /// Presets defined in config, selectable next to the built-in ones (a
+    /// same-named one replaces the built-in).
+    #[serde(default)]
+    pub sampler_presets: Vec<SamplerPreset>,
+    /// How often a streaming reply is saved to the database, so a crash
+    /// mid-generation leaves a recoverable partial message. 0 = every sync.
+    #[serde(default = "default_autosave_interval_ms")]
+    pub autosave_interval_ms: u32,
+    /// Run `execute_python` scripts in the conversation's own directory
+    /// (`python_sessions/<id>` in the app data dir) with user site-packages
+    /// hidden, instead of the ambient interpreter setup.
+    #[serde(default)]
+    pub python_isolated: bool,
+    /// Virtualenv whose interpreter isolated scripts run with.
+    #[serde(default)]
+    pub python_venv: Option<String>,
+    /// Tool/command executions allowed while answering one local-model user
+    /// message. The call that reaches it is followed by a notice asking the
+    /// model to wrap up; a call after that stops generation with finish_reason
+    /// "tool_limit". 0 = no limit. Remote providers use `max_tool_calls` instead.
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: u32,
+    /// Answer a tool call identical (same name and arguments) to one already
+    /// run this turn with the earlier result instead of running it again.
+    #[serde(default = "default_true")]
+    pub dedupe_tool_calls: bool,
+    /// Largest `n` (completions per request) that is accepted.
+    #[serde(default = "default_max_completions")]
+    pub max_completions: u32,
+    /// Most bytes `read_file` returns inline; longer output is truncated.
+    #[serde(default = "default_read_file_max_bytes")]
+    pub read_file_max_bytes: u32,
+    /// Most entries `list_directory` lists; the rest are counted in a footer.
+    #[serde(default = "default_list_directory_max_entries")]
+    pub list_directory_max_entries: u32,
+    /// Model the server loads and warms up at startup; None starts unloaded.
+    #[serde(default)]
+    pub preload_model: Option<String>,
+    /// GPU layers for `preload_model` (None = the loader's default).
+    #[serde(default)]
+    pub preload_gpu_layers: Option<u32>,
 }
 
 fn default_max_tool_calls() -> i32 { 2000 }
 fn default_loop_detection_limit() -> i32 { 15 }
+fn default_temperature_max() -> f64 { 2.0 }
+fn default_context_cap() -> u32 { DEFAULT_CONTEXT_CAP }
+fn default_max_parallel_generations() -> u32 { 1 }
+fn default_autosave_interval_ms() -> u32 { DEFAULT_AUTOSAVE_INTERVAL_MS }
+fn default_max_tool_iterations() -> u32 { DEFAULT_MAX_TOOL_ITERATIONS }
+fn default_max_completions() -> u32 { DEFAULT_MAX_COMPLETIONS }
+fn default_read_file_max_bytes() -> u32 { DEFAULT_READ_FILE_MAX_BYTES }
+fn default_list_directory_max_entries() -> u32 { DEFAULT_LIST_DIRECTORY_MAX_ENTRIES }
+fn default_reasoning_tag_open() -> String { "<think>".to_string() }
+fn default_reasoning_tag_close() -> String { "</think>".to_string() }
+
+/// Default for [`SamplerConfig::default_context_cap`].
+pub const DEFAULT_CONTEXT_CAP: u32 = 8192;
+
+/// Default for [`SamplerConfig::autosave_interval_ms`].
+pub const DEFAULT_AUTOSAVE_INTERVAL_MS: u32 = 5000;
+
+/// Default for [`SamplerConfig::max_tool_iterations`].
+pub const DEFAULT_MAX_TOOL_ITERATIONS: u32 = 200;
+
+/// Default for [`SamplerConfig::max_completions`].
+pub const DEFAULT_MAX_COMPLETIONS: u32 = 4;
+
+/// Default for [`SamplerConfig::read_file_max_bytes`] (100 KB).
+pub const DEFAULT_READ_FILE_MAX_BYTES: u32 = 100 * 1024;
+
+/// Default for [`SamplerConfig::list_directory_max_entries`].
+pub const DEFAULT_LIST_DIRECTORY_MAX_ENTRIES: u32 = 500;
 
 fn default_true() -> bool {
     true
@@ -336,7 +646,33 @@ impl Default for SamplerConfig {
             provider_api_keys: None,
             max_tool_calls: 2000,
             loop_detection_limit: 15,
+            compress_messages: false,
+            allow_external_model_paths: true,
             thinking_mode: None,
+            auto_execute_tools: true,
+            temperature_min: 0.0,
+            temperature_max: 2.0,
+            generation_timeout_secs: None,
+            enabled_tools: None,
+            normalize_tool_output: true,
+            default_context_cap: DEFAULT_CONTEXT_CAP,
+            tools_enabled: true,
+            reasoning_tag_open: default_reasoning_tag_open(),
+            reasoning_tag_close: default_reasoning_tag_close(),
+            require_confirmation_for: Vec::new(),
+            max_parallel_generations: 1,
+            sampler_preset: None,
+            sampler_presets: Vec::new(),
+            autosave_interval_ms: DEFAULT_AUTOSAVE_INTERVAL_MS,
+            python_isolated: false,
+            python_venv: None,
+            max_tool_iterations: DEFAULT_MAX_TOOL_ITERATIONS,
+            dedupe_tool_calls: true,
+            max_completions: DEFAULT_MAX_COMPLETIONS,
+            read_file_max_bytes: DEFAULT_READ_FILE_MAX_BYTES,
+            list_directory_max_entries: DEFAULT_LIST_DIRECTORY_MAX_ENTRIES,
+            preload_model: None,
+            preload_gpu_layers: None,
         }
     }
 }
@@ -486,6 +822,20 @@ pub fn translate_tool_for_model(
     }
 }
 
+/// Settings baked into the loaded weights. A load request that matches these
+/// can keep the model and only rebuild its context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedModelParams {
+    pub model_path: String,
+    /// Effective layer count after capping at the model's block count.
+    pub gpu_layers: u32,
+    pub mmproj_path: Option<String>,
+    pub use_mlock: bool,
+    pub use_mmap: bool,
+    pub main_gpu: i32,
+    pub split_mode: String,
+}
+
 // Shared state for LLaMA
 pub struct LlamaState {
     pub backend: LlamaBackend,
@@ -495,13 +845,38 @@ pub struct LlamaState {
     pub chat_template_type: Option<String>, // Store detected template type
     pub chat_template_string: Option<String>, // Store full Jinja2 template from model
     pub gpu_layers: Option<u32>,            // Number of GPU layers offloaded
+    /// Backend the model runs on ("cuda", "vulkan", "cpu"); None when it couldn't be probed.
+    pub effective_backend: Option<String>,
     pub last_used: std::time::SystemTime,
     pub general_name: Option<String>,       // Model's general.name from GGUF metadata
+    /// KV cache types requested at load time; override the config's cache_type_k/v.
+    pub kv_cache_type: KvCacheTypes,
+    /// Weights-level settings of the loaded model (None when nothing is loaded).
+    pub loaded_params: Option<LoadedModelParams>,
+    /// User-selected chat template name; overrides `chat_template_type` when set.
+    pub template_override: Option<String>,
+    /// Sampler defaults from the model's GGUF `general.sampling.*` keys.
+    pub recommended_sampling: Option<RecommendedSampling>,
+    /// GGUF `tokenizer.ggml.add_bos_token`: whether the model expects a BOS the
+    /// template doesn't write (see `templated_add_bos`).
+    pub model_add_bos: Option<bool>,
+    /// Native tool-call format from `detect_tool_format` ("mistral", "granite", ...);
+    /// its parser is tried first. None when the family is unknown.
+    pub tool_format: Option<&'static str>,
+    /// K and V elements the loaded model stores per context token (GGUF attention
+    /// geometry); sizes parked contexts against free VRAM. None when unknown.
+    pub kv_elements_per_token: Option<f64>,
+    /// VRAM the KV cache can use next to the offloaded layers, measured at load;
+    /// contexts are reduced to fit it. None on the CPU or when VRAM is unknown.
+    pub kv_cache_vram_budget: Option<u64>,
     // Cached resolved system prompt (invalidated on config or model change)
     pub cached_system_prompt: Option<String>,
     pub cached_prompt_key: Option<(Option<String>, Option<String>)>, // (system_prompt, general_name)
     /// Cached inference context for KV cache reuse. MUST be dropped before model.
     pub inference_cache: Option<InferenceCache>,
+    /// Contexts of other conversations parked by parallel generations, oldest
+    /// first. Same drop-before-model invariant as `inference_cache`.
+    pub idle_contexts: Vec<InferenceCache>,
     #[cfg(feature = "vision")]
     /// Vision/multimodal context (if mmproj loaded). MUST be dropped before model.
     pub vision_state: Option<VisionState>,
@@ -1,4 +1,4 @@
-use super::ToolTags;
+use super::{GenerationOverrides, OverflowPolicy, RecommendedSampling, ToolTags};
 use serde::{Deserialize, Serialize};
 
 /// One typed segment of a message (text, tool_call, tool_result, reasoning).
@@ -19,6 +19,117 @@ pub struct ToolTimingLive {
     pub duration_ms: u64,
 }
 
+/// How many prompt tokens have been evaluated so far, for "reading context N%".
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PromptEvalProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Coarse model-load phase. Reported even when llama.cpp's percent callback
+/// never fires, so clients always see the load move forward.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadPhase {
+    Started,
+    ReadingMetadata,
+    Allocating,
+    WarmingUp,
+    Complete,
+    Failed,
+}
+
+impl LoadPhase {
+    /// Encode for an `AtomicU8` shared with the loading thread.
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// Decode a value written by `as_u8`. Unknown values map to `Started`.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::ReadingMetadata,
+            2 => Self::Allocating,
+            3 => Self::WarmingUp,
+            4 => Self::Complete,
+            5 => Self::Failed,
+            _ => Self::Started,
+        }
+    }
+}
+
+/// What a load request actually did. Loading the already-loaded model with
+/// the same weights-level settings keeps the model and only rebuilds the
+/// context (new context size / KV cache type), which skips reading the weights.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadOutcome {
+    #[default]
+    FullReload,
+    ContextRebuild,
+}
+
+/// One model-load progress event, broadcast to status WebSocket clients.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LoadProgressEvent {
+    pub phase: LoadPhase,
+    /// Percent of tensors loaded (0-100).
+    pub progress: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl LoadProgressEvent {
+    pub fn new(phase: LoadPhase, progress: u8) -> Self {
+        Self {
+            phase,
+            progress,
+            model_path: None,
+            error: None,
+        }
+    }
+}
+
+/// Log-probability of one generated token, with the most likely alternatives.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f32,
+    /// Top-k candidates at this position, most likely first.
+    pub top: Vec<TopLogprob>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TopLogprob {
+    pub tok: String,
+    pub logprob: f32,
+}
+
+/// A tool call parsed out of the model's response. Handed back instead of
+/// being executed when `auto_execute_tools` is off; reported for observability
+/// otherwise.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ToolCallRequest {
+    pub name: String,
+    pub arguments: serde_json::Value,
+    /// The call text as the model emitted it, without its wrapper tags.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_span: Option<String>,
+}
+
+/// One of the extra completions of an `n` > 1 request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AlternativeCompletion {
+    pub content: String,
+    /// Thinking split off `content`, as for the streamed completion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    pub finish_reason: String,
+    pub gen_tokens: i32,
+}
+
 /// Carries an approval request to the frontend for dangerous tool calls.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ApprovalRequest {
@@ -28,6 +139,26 @@ pub struct ApprovalRequest {
     pub reason: String,
 }
 
+/// A call to a `require_confirmation_for` tool, held until the user confirms it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ConfirmationRequest {
+    pub id: String,
+    pub tool: String,
+    pub args: serde_json::Value,
+}
+
+/// What a generation was set up with, sent first when the request asked for
+/// `echo_prompt`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PromptEcho {
+    /// The system prompt the template was rendered with.
+    pub system_prompt: String,
+    /// Template type in use (after any `template_override`).
+    pub template_type: Option<String>,
+    /// Names of the tool definitions given to the model.
+    pub tools: Vec<String>,
+}
+
 #[derive(Serialize, Clone, Default)]
 pub struct TokenData {
     pub token: String,
@@ -44,6 +175,23 @@ pub struct TokenData {
     /// When present, the frontend should pause and show an approve/reject dialog.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub approval_required: Option<ApprovalRequest>,
+    /// A gated tool call waiting for `POST /api/generation/confirm`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation_required: Option<ConfirmationRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_eval_progress: Option<PromptEvalProgress>,
+    /// Set when the request asked for `logprobs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<TokenLogprob>,
+    /// Reasoning-block text, streamed apart from `token` (see `reasoning_tag_open`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// Id of the sampled token; set when the request asked for `stream_token_ids`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<i32>,
+    /// Leading event of an `echo_prompt` request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_echo: Option<PromptEcho>,
 }
 
 #[derive(Deserialize)]
@@ -63,6 +211,17 @@ pub struct ChatRequest {
     /// one to finish and send a synthetic done event.
     #[serde(default)]
     pub reconnect: bool,
+    /// Context-overflow behavior for this request (default: auto-reduce).
+    #[serde(default)]
+    pub on_overflow: OverflowPolicy,
+    /// Wait for generation to finish and return the full reply with `usage`,
+    /// instead of returning immediately and streaming over the WebSocket.
+    #[serde(default)]
+    pub include_usage: bool,
+    /// Optional `temperature`, `top_p`, `top_k`, `max_tokens`, `seed`, `sampler_type`
+    /// for this request only; stored config is not modified.
+    #[serde(flatten)]
+    pub overrides: GenerationOverrides,
 }
 
 #[derive(Serialize)]
@@ -71,6 +230,22 @@ pub struct ChatResponse {
     pub conversation_id: String,
     pub tokens_used: Option<i32>,
     pub max_tokens: Option<i32>,
+    /// Final token accounting; only set for `include_usage` requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ChatUsage>,
+}
+
+/// Token counts and rates of a finished generation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatUsage {
+    /// Prompt tokens evaluated for this turn (cached prefix tokens excluded).
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_tok_per_sec: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gen_tok_per_sec: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -101,6 +276,17 @@ pub struct ChatMessage {
     /// LLM-generated short title (≤50 chars). Present on user messages after background gen.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_count: Option<i32>,
+    /// Creation time in milliseconds since Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at_millis: Option<i64>,
+    /// Reasoning block of an assistant reply, shown apart from `content`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// Reply cut off by a crash; `content` is the part saved before it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
 }
 
 #[derive(Serialize)]
@@ -121,6 +307,29 @@ pub struct ConversationsResponse {
     pub conversations: Vec<ConversationFile>,
 }
 
+/// Sidebar entry returned by the paginated conversation list (no message bodies).
+#[derive(Serialize)]
+pub struct ConversationSummaryEntry {
+    pub id: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_preview: Option<String>,
+    pub message_count: i64,
+    /// Last activity time in milliseconds since Unix epoch.
+    pub updated_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ConversationPageResponse {
+    pub conversations: Vec<ConversationSummaryEntry>,
+    pub limit: usize,
+    pub offset: usize,
+}
+
 #[derive(Serialize)]
 pub struct ToolTiming {
     pub name: String,
@@ -177,6 +386,12 @@ pub struct ModelStatus {
     pub tool_tags: Option<ToolTags>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gpu_layers: Option<u32>,
+    /// Backend the loaded model runs on; "cpu" when a GPU build found no usable device.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_backend: Option<String>,
+    /// Sampler defaults the model recommends (GGUF `general.sampling.*`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recommended_sampling: Option<RecommendedSampling>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub block_count: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -198,12 +413,128 @@ pub struct ModelStatus {
 #[derive(Deserialize)]
 pub struct ModelLoadRequest {
     pub model_path: String,
+    /// Layers to offload. `-1` (or any value above the model's layer count) means "all";
+    /// the loader clamps to the GGUF block_count.
+    #[serde(default, deserialize_with = "deserialize_gpu_layers")]
     pub gpu_layers: Option<u32>,
     pub mmproj_path: Option<String>,
     pub context_size: Option<u32>,
     pub flash_attention: Option<bool>,
+    /// Per-cache overrides of `kv_cache_type` (`f16`, `q8_0`, `turbo2`, ...); K and V
+    /// may differ.
     pub cache_type_k: Option<String>,
     pub cache_type_v: Option<String>,
+    /// KV cache quantization for both K and V. Overrides the config's cache types
+    /// for this load; None keeps the configured types.
+    #[serde(default)]
+    pub kv_cache_type: Option<KvCacheType>,
+}
+
+impl ModelLoadRequest {
+    /// The KV cache types requested for this load: `kv_cache_type` for both caches,
+    /// with `cache_type_k`/`cache_type_v` taking precedence for their own cache.
+    pub fn kv_cache_types(&self) -> Result<KvCacheTypes, String> {
+        let parse = |field: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|s| KvCacheType::parse(s).ok_or_else(|| format!("Unsupported {field}: {s}")))
+                .transpose()
+        };
+        Ok(KvCacheTypes {
+            k: parse("cache_type_k", &self.cache_type_k)?.or(self.kv_cache_type),
+            v: parse("cache_type_v", &self.cache_type_v)?.or(self.kv_cache_type),
+        })
+    }
+}
+
+/// KV cache element type. Quantized caches trade a little accuracy for
+/// roughly 2× (Q8_0) or 3.5× (Q4_0) longer contexts in the same VRAM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KvCacheType {
+    F32,
+    #[default]
+    F16,
+    Q8_0,
+    Q4_0,
+    Q4_1,
+    Q5_0,
+    Q5_1,
+    /// TurboQuant types. Current llama.cpp builds have no TURBO GGML types, so
+    /// these run as Q4_0.
+    #[serde(alias = "turbo2_0")]
+    Turbo2,
+    #[serde(alias = "turbo3_0")]
+    Turbo3,
+    #[serde(alias = "turbo4_0")]
+    Turbo4,
+}
+
+impl KvCacheType {
+    const ALL: [KvCacheType; 10] = [
+        KvCacheType::F32,
+        KvCacheType::F16,
+        KvCacheType::Q8_0,
+        KvCacheType::Q4_0,
+        KvCacheType::Q4_1,
+        KvCacheType::Q5_0,
+        KvCacheType::Q5_1,
+        KvCacheType::Turbo2,
+        KvCacheType::Turbo3,
+        KvCacheType::Turbo4,
+    ];
+
+    /// The config string used by `SamplerConfig::cache_type_k`/`cache_type_v`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            KvCacheType::F32 => "f32",
+            KvCacheType::F16 => "f16",
+            KvCacheType::Q8_0 => "q8_0",
+            KvCacheType::Q4_0 => "q4_0",
+            KvCacheType::Q4_1 => "q4_1",
+            KvCacheType::Q5_0 => "q5_0",
+            KvCacheType::Q5_1 => "q5_1",
+            KvCacheType::Turbo2 => "turbo2",
+            KvCacheType::Turbo3 => "turbo3",
+            KvCacheType::Turbo4 => "turbo4",
+        }
+    }
+
+    /// Inverse of [`KvCacheType::as_str`] (case-insensitive, `turbo2_0` style
+    /// aliases included).
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_suffix("_0").filter(|t| t.starts_with("turbo")).unwrap_or(s);
+        Self::ALL.into_iter().find(|kv| kv.as_str().eq_ignore_ascii_case(s))
+    }
+}
+
+/// Load-time KV cache types for the K and V caches; each None keeps the
+/// config's `cache_type_k`/`cache_type_v`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KvCacheTypes {
+    pub k: Option<KvCacheType>,
+    pub v: Option<KvCacheType>,
+}
+
+impl KvCacheTypes {
+    /// The same type for both caches.
+    pub fn both(kv: KvCacheType) -> Self {
+        Self { k: Some(kv), v: Some(kv) }
+    }
+}
+
+/// Accept `-1` as "all layers" (mapped to `u32::MAX`) alongside plain non-negative counts.
+fn deserialize_gpu_layers<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: Option<i64> = Option::deserialize(deserializer)?;
+    match value {
+        None => Ok(None),
+        Some(n) if n < 0 => Ok(Some(u32::MAX)),
+        Some(n) => Ok(Some(u32::try_from(n).unwrap_or(u32::MAX))),
+    }
 }
 
 #[derive(Serialize)]
@@ -211,4 +542,57 @@ pub struct ModelResponse {
     pub success: bool,
     pub message: String,
     pub status: Option<ModelStatus>,
+    /// Set on successful loads: full reload vs. context-only rebuild.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_outcome: Option<LoadOutcome>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_request(json: &str) -> ModelLoadRequest {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_every_kv_cache_type_the_ui_offers_parses() {
+        let offered = [
+            "f32", "f16", "q8_0", "q4_0", "q4_1", "q5_0", "q5_1", "turbo2", "turbo3", "turbo4",
+            "turbo2_0", "turbo3_0", "turbo4_0",
+        ];
+        for value in offered {
+            let kv = KvCacheType::parse(value).unwrap_or_else(|| panic!("{value} rejected"));
+            assert!(value.starts_with(kv.as_str()), "{value} -> {kv:?}");
+
+            let request = load_request(&format!(
+                r#"{{"model_path":"m.gguf","cache_type_k":"{value}","cache_type_v":"{value}"}}"#
+            ));
+            assert_eq!(request.kv_cache_types().unwrap(), KvCacheTypes::both(kv), "{value}");
+
+            let request = load_request(&format!(r#"{{"model_path":"m.gguf","kv_cache_type":"{value}"}}"#));
+            assert_eq!(request.kv_cache_type, Some(kv), "{value}");
+        }
+        assert!(KvCacheType::parse("q2_k").is_none());
+    }
+
+    #[test]
+    fn test_load_request_kv_cache_types_mix_k_and_v() {
+        let request = load_request(
+            r#"{"model_path":"m.gguf","kv_cache_type":"q8_0","cache_type_k":"turbo2","cache_type_v":"f16"}"#,
+        );
+        assert_eq!(
+            request.kv_cache_types().unwrap(),
+            KvCacheTypes { k: Some(KvCacheType::Turbo2), v: Some(KvCacheType::F16) }
+        );
+
+        let request = load_request(r#"{"model_path":"m.gguf","kv_cache_type":"q4_0"}"#);
+        assert_eq!(request.kv_cache_types().unwrap(), KvCacheTypes::both(KvCacheType::Q4_0));
+
+        let request = load_request(r#"{"model_path":"m.gguf"}"#);
+        assert_eq!(request.kv_cache_types().unwrap(), KvCacheTypes::default());
+
+        let request = load_request(r#"{"model_path":"m.gguf","cache_type_v":"bogus"}"#);
+        assert!(request.kv_cache_types().unwrap_err().contains("cache_type_v"));
+    }
 }
@@ -0,0 +1,187 @@
+use super::GenerationOverrides;
+use serde::{Deserialize, Serialize};
+
+/// A named set of sampler parameters, selected with `sampler_preset`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SamplerPreset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Only the sampler fields are used.
+    pub params: GenerationOverrides,
+}
+
+/// The built-in presets, from most to least predictable.
+fn builtin_sampler_presets() -> Vec<SamplerPreset> {
+    let preset = |name: &str, description: &str, sampler_type: &str, temperature, top_p, top_k| SamplerPreset {
+        name: name.to_string(),
+        description: description.to_string(),
+        params: GenerationOverrides {
+            sampler_type: Some(sampler_type.to_string()),
+            temperature: Some(temperature),
+            top_p,
+            top_k,
+            ..Default::default()
+        },
+    };
+    vec![
+        preset(
+            "deterministic",
+            "Always picks the most likely token; the same prompt gives the same reply",
+            "Greedy",
+            0.0,
+            None,
+            None,
+        ),
+        preset(
+            "precise",
+            "Low temperature for factual answers and code",
+            "Temperature",
+            0.3,
+            Some(0.9),
+            Some(40),
+        ),
+        preset(
+            "balanced",
+            "General-purpose chat",
+            "Temperature",
+            0.7,
+            Some(0.95),
+            Some(40),
+        ),
+        preset(
+            "creative",
+            "More varied wording for brainstorming and fiction",
+            "Temperature",
+            1.0,
+            Some(0.95),
+            Some(100),
+        ),
+    ]
+}
+
+/// The built-in presets followed by the ones defined in config
+/// (`SamplerConfig::sampler_presets`). A configured preset with a built-in's
+/// name replaces it.
+pub fn sampler_presets(configured: &[SamplerPreset]) -> Vec<SamplerPreset> {
+    let mut presets: Vec<SamplerPreset> = builtin_sampler_presets()
+        .into_iter()
+        .filter(|builtin| !configured.iter().any(|p| p.name.eq_ignore_ascii_case(&builtin.name)))
+        .collect();
+    presets.extend(configured.iter().cloned());
+    presets
+}
+
+/// Look up a preset by name (case-insensitive).
+pub fn sampler_preset(name: &str, configured: &[SamplerPreset]) -> Option<SamplerPreset> {
+    sampler_presets(configured)
+        .into_iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Error for a `sampler_preset` that names no preset.
+pub fn unknown_sampler_preset(name: &str, configured: &[SamplerPreset]) -> String {
+    let presets = sampler_presets(configured);
+    let names: Vec<&str> = presets.iter().map(|p| p.name.as_str()).collect();
+    format!(
+        "Unknown sampler_preset '{}' (expected one of: {})",
+        name,
+        names.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SamplerConfig;
+
+    fn expand(overrides: &GenerationOverrides) -> SamplerConfig {
+        let mut config = SamplerConfig::default();
+        overrides.apply_to(&mut config);
+        config
+    }
+
+    fn preset(name: &str) -> GenerationOverrides {
+        GenerationOverrides {
+            sampler_preset: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_each_preset_expands_to_its_parameters() {
+        let config = expand(&preset("deterministic"));
+        assert_eq!((config.sampler_type.as_str(), config.temperature), ("Greedy", 0.0));
+
+        for (name, temperature, top_p, top_k) in [
+            ("precise", 0.3, 0.9, 40),
+            ("balanced", 0.7, 0.95, 40),
+            ("creative", 1.0, 0.95, 100),
+        ] {
+            let config = expand(&preset(name));
+            assert_eq!(config.sampler_type, "Temperature", "{name}");
+            assert_eq!(
+                (config.temperature, config.top_p, config.top_k),
+                (temperature, top_p, top_k),
+                "{name}"
+            );
+        }
+
+        assert_eq!(sampler_presets(&[]).len(), 4);
+        assert!(sampler_preset(" Creative ", &[]).is_some());
+        assert!(sampler_preset("wild", &[]).is_none());
+        assert!(unknown_sampler_preset("wild", &[]).contains("precise, balanced, creative"));
+    }
+
+    #[test]
+    fn test_explicit_params_win_over_the_preset() {
+        let overrides = GenerationOverrides {
+            temperature: Some(0.2),
+            top_k: Some(5),
+            ..preset("creative")
+        };
+        let config = expand(&overrides);
+        assert_eq!(config.temperature, 0.2);
+        assert_eq!(config.top_k, 5);
+        // Fields left unset still come from the preset
+        assert_eq!(config.top_p, 0.95);
+
+        // An unknown name leaves the config as it was
+        let config = expand(&preset("wild"));
+        assert_eq!(config.temperature, SamplerConfig::default().temperature);
+    }
+
+    #[test]
+    fn test_presets_defined_in_config_are_selectable() {
+        let configured = vec![
+            SamplerPreset {
+                name: "wild".to_string(),
+                description: String::new(),
+                params: GenerationOverrides {
+                    temperature: Some(1.6),
+                    top_k: Some(200),
+                    ..Default::default()
+                },
+            },
+            SamplerPreset {
+                name: "Balanced".to_string(),
+                description: "House default".to_string(),
+                params: GenerationOverrides {
+                    temperature: Some(0.5),
+                    ..Default::default()
+                },
+            },
+        ];
+        let names: Vec<String> = sampler_presets(&configured).into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["deterministic", "precise", "creative", "wild", "Balanced"]);
+
+        let mut config = SamplerConfig {
+            sampler_presets: configured,
+            ..Default::default()
+        };
+        preset("wild").apply_to(&mut config);
+        assert_eq!((config.temperature, config.top_k), (1.6, 200));
+        preset("balanced").apply_to(&mut config);
+        assert_eq!(config.temperature, 0.5);
+    }
+}
@@ -6,13 +6,19 @@ pub struct NativeToolResult {
     /// Raw image bytes (PNG/JPEG) for vision pipeline injection.
     /// Only populated by tools like `take_screenshot` when capture succeeds.
     pub images: Vec<Vec<u8>>,
+    /// Whether the tool did what was asked. Failed calls are logged as such in
+    /// the conversation's tool history and are never reused for duplicates.
+    pub success: bool,
 }
 
 impl NativeToolResult {
     pub fn text_only(text: String) -> Self {
-        Self { text, images: Vec::new() }
+        Self { text, images: Vec::new(), success: true }
     }
     pub fn with_image(text: String, image_bytes: Vec<u8>) -> Self {
-        Self { text, images: vec![image_bytes] }
+        Self { text, images: vec![image_bytes], success: true }
+    }
+    pub fn failure(text: String) -> Self {
+        Self { text, images: Vec::new(), success: false }
     }
 }
@@ -0,0 +1,219 @@
+//! Reasoning ("thinking") blocks emitted by reasoning models.
+//!
+//! Models such as Qwen3 and DeepSeek-R1 wrap their chain of thought in a tag
+//! pair (`<think>…</think>` by default, configurable). The engine streams that
+//! text separately from the answer, and only the answer is stored as message
+//! content, so reasoning never makes it into the next prompt.
+
+use crate::SamplerConfig;
+
+/// Open/close tag pair delimiting a reasoning block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReasoningTags {
+    pub open: String,
+    pub close: String,
+}
+
+impl ReasoningTags {
+    /// Tags from the config, or `None` when either tag is blank (stripping disabled).
+    pub fn from_config(config: &SamplerConfig) -> Option<Self> {
+        let open = config.reasoning_tag_open.trim();
+        let close = config.reasoning_tag_close.trim();
+        if open.is_empty() || close.is_empty() {
+            return None;
+        }
+        Some(Self {
+            open: open.to_string(),
+            close: close.to_string(),
+        })
+    }
+}
+
+/// Split a complete response into `(answer, reasoning)`.
+///
+/// A close tag with no open tag before it means the template prefilled the
+/// open tag, so everything before it is reasoning. An unterminated block runs
+/// to the end. Blocks are joined with a blank line; when any is found the
+/// answer is trimmed.
+pub fn split_reasoning(text: &str, tags: &ReasoningTags) -> (String, Option<String>) {
+    let mut answer = String::new();
+    let mut blocks: Vec<&str> = Vec::new();
+    let mut rest = text;
+
+    if let Some(close) = rest.find(&tags.close) {
+        if rest.find(&tags.open).is_none_or(|open| open > close) {
+            blocks.push(&rest[..close]);
+            rest = &rest[close + tags.close.len()..];
+        }
+    }
+    while let Some(open) = rest.find(&tags.open) {
+        answer.push_str(&rest[..open]);
+        let body = &rest[open + tags.open.len()..];
+        match body.find(&tags.close) {
+            Some(close) => {
+                blocks.push(&body[..close]);
+                rest = &body[close + tags.close.len()..];
+            }
+            None => {
+                blocks.push(body);
+                rest = "";
+            }
+        }
+    }
+    answer.push_str(rest);
+
+    if blocks.is_empty() {
+        return (answer, None);
+    }
+    let reasoning: Vec<&str> = blocks
+        .iter()
+        .map(|b| b.trim())
+        .filter(|b| !b.is_empty())
+        .collect();
+    let reasoning = (!reasoning.is_empty()).then(|| reasoning.join("\n\n"));
+    (answer.trim().to_string(), reasoning)
+}
+
+/// Incremental [`split_reasoning`] for a token stream.
+///
+/// Text that could be the start of a tag is held back until the next push, so
+/// a tag split across tokens is still recognised and never leaks into either side.
+#[derive(Debug, Clone)]
+pub struct ReasoningSplitter {
+    tags: ReasoningTags,
+    in_reasoning: bool,
+    pending: String,
+}
+
+impl ReasoningSplitter {
+    /// `starts_in_reasoning` is set when the prompt already ends with the open tag.
+    pub fn new(tags: ReasoningTags, starts_in_reasoning: bool) -> Self {
+        Self {
+            tags,
+            in_reasoning: starts_in_reasoning,
+            pending: String::new(),
+        }
+    }
+
+    /// Feed a chunk; returns the `(answer, reasoning)` text that is now settled.
+    pub fn push(&mut self, text: &str) -> (String, String) {
+        self.pending.push_str(text);
+        let mut answer = String::new();
+        let mut reasoning = String::new();
+        loop {
+            let tag = if self.in_reasoning {
+                &self.tags.close
+            } else {
+                &self.tags.open
+            };
+            let out = if self.in_reasoning {
+                &mut reasoning
+            } else {
+                &mut answer
+            };
+            if let Some(pos) = self.pending.find(tag.as_str()) {
+                out.push_str(&self.pending[..pos]);
+                self.pending.drain(..pos + tag.len());
+                self.in_reasoning = !self.in_reasoning;
+                continue;
+            }
+            let keep = partial_tag_suffix(&self.pending, tag);
+            let settled = self.pending.len() - keep;
+            out.push_str(&self.pending[..settled]);
+            self.pending.drain(..settled);
+            return (answer, reasoning);
+        }
+    }
+
+    /// Flush held-back text at the end of the stream.
+    pub fn finish(&mut self) -> (String, String) {
+        let rest = std::mem::take(&mut self.pending);
+        if self.in_reasoning {
+            (String::new(), rest)
+        } else {
+            (rest, String::new())
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `tag`.
+fn partial_tag_suffix(text: &str, tag: &str) -> usize {
+    (1..tag.len().min(text.len() + 1))
+        .rev()
+        .find(|&n| {
+            text.is_char_boundary(text.len() - n) && tag.starts_with(&text[text.len() - n..])
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn think_tags() -> ReasoningTags {
+        ReasoningTags {
+            open: "<think>".to_string(),
+            close: "</think>".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_split_separates_reasoning_from_answer() {
+        let tags = think_tags();
+        let (answer, reasoning) =
+            split_reasoning("<think>\nUser wants 2+2.\n</think>\n\nIt's 4.", &tags);
+        assert_eq!(answer, "It's 4.");
+        assert_eq!(reasoning.as_deref(), Some("User wants 2+2."));
+
+        // Prefilled open tag, unterminated block, and no block at all
+        let (answer, reasoning) = split_reasoning("hmm</think>Done", &tags);
+        assert_eq!(
+            (answer.as_str(), reasoning.as_deref()),
+            ("Done", Some("hmm"))
+        );
+        let (answer, reasoning) = split_reasoning("Hi <think>still going", &tags);
+        assert_eq!(
+            (answer.as_str(), reasoning.as_deref()),
+            ("Hi", Some("still going"))
+        );
+        assert_eq!(
+            split_reasoning(" plain ", &tags),
+            (" plain ".to_string(), None)
+        );
+        assert_eq!(
+            split_reasoning("<think></think>ok", &tags),
+            ("ok".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_splitter_handles_tags_split_across_tokens() {
+        let tokens = ["<th", "ink>a", " b</", "thi", "nk>", "ans", "wer <", "3"];
+        let mut splitter = ReasoningSplitter::new(think_tags(), false);
+        let (mut answer, mut reasoning) = (String::new(), String::new());
+        for token in tokens {
+            let (a, r) = splitter.push(token);
+            answer.push_str(&a);
+            reasoning.push_str(&r);
+        }
+        let (a, r) = splitter.finish();
+        answer.push_str(&a);
+        reasoning.push_str(&r);
+        assert_eq!(reasoning, "a b");
+        assert_eq!(answer, "answer <3");
+
+        let mut prefilled = ReasoningSplitter::new(think_tags(), true);
+        assert_eq!(
+            prefilled.push("plan</think>go"),
+            ("go".to_string(), "plan".to_string())
+        );
+    }
+
+    #[test]
+    fn test_blank_tags_disable_stripping() {
+        let mut config = SamplerConfig::default();
+        assert_eq!(ReasoningTags::from_config(&config), Some(think_tags()));
+        config.reasoning_tag_close = " ".to_string();
+        assert_eq!(ReasoningTags::from_config(&config), None);
+    }
+}
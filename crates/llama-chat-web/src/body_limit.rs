@@ -0,0 +1,160 @@
+//! Request body size limits, enforced by the route dispatcher before any
+//! handler buffers the body.
+//!
+//! JSON routes are capped by `LLAMA_CHAT_MAX_BODY_BYTES`; the generate routes,
+//! whose bodies carry base64 `image_data`, by the higher
+//! `LLAMA_CHAT_MAX_GENERATE_BODY_BYTES`. Oversized bodies get a 413.
+
+use std::sync::OnceLock;
+
+use hyper::body::HttpBody;
+use hyper::{Body, Method, Request, Response};
+
+use crate::response_helpers::{api_error, ApiError};
+
+/// Cap for ordinary JSON bodies when `LLAMA_CHAT_MAX_BODY_BYTES` is unset or invalid.
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 4 * 1024 * 1024;
+/// Cap for generate bodies when `LLAMA_CHAT_MAX_GENERATE_BODY_BYTES` is unset or invalid.
+pub const DEFAULT_MAX_GENERATE_BODY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Routes whose bodies may carry base64 images (generation, and conversation
+/// imports that include them).
+const GENERATE_PATHS: &[&str] = &[
+    "/api/chat",
+    "/api/chat/stream",
+    "/v1/chat/completions",
+    "/api/conversations/import",
+];
+/// Routes that stream their body to disk and enforce their own cap.
+const STREAMED_PATHS: &[&str] = &["/api/upload"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyLimits {
+    pub default: u64,
+    pub generate: u64,
+}
+
+impl BodyLimits {
+    /// Limits from the environment, read once per process.
+    pub fn configured() -> Self {
+        static LIMITS: OnceLock<BodyLimits> = OnceLock::new();
+        *LIMITS.get_or_init(|| Self {
+            default: env_limit("LLAMA_CHAT_MAX_BODY_BYTES", DEFAULT_MAX_BODY_BYTES),
+            generate: env_limit(
+                "LLAMA_CHAT_MAX_GENERATE_BODY_BYTES",
+                DEFAULT_MAX_GENERATE_BODY_BYTES,
+            ),
+        })
+    }
+
+    /// Cap for a request, or `None` when the dispatcher doesn't limit it
+    /// (bodiless methods and self-limiting streamed routes).
+    pub fn for_request(&self, method: &Method, path: &str) -> Option<u64> {
+        if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+            || STREAMED_PATHS.contains(&path)
+        {
+            return None;
+        }
+        Some(if GENERATE_PATHS.contains(&path) {
+            self.generate
+        } else {
+            self.default
+        })
+    }
+}
+
+fn env_limit(var: &str, default: u64) -> u64 {
+    std::env::var(var)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(default)
+}
+
+/// Buffer the body of `req`, failing with a 413 once it exceeds `limit` bytes.
+///
+/// A `Content-Length` over the limit is rejected without reading anything;
+/// chunked bodies are read only up to the limit.
+pub async fn limit_body(req: Request<Body>, limit: u64) -> Result<Request<Body>, Response<Body>> {
+    let too_large = || {
+        api_error(
+            ApiError::PAYLOAD_TOO_LARGE,
+            format!("Request body exceeds the {limit} byte limit"),
+        )
+    };
+    let declared = req
+        .headers()
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    if declared.is_some_and(|len| len > limit) {
+        return Err(too_large());
+    }
+
+    let (parts, mut body) = req.into_parts();
+    let mut buf = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk
+            .map_err(|_| api_error(ApiError::INVALID_REQUEST, "Failed to read request body"))?;
+        if (buf.len() + chunk.len()) as u64 > limit {
+            return Err(too_large());
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(Request::from_parts(parts, Body::from(buf)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::StatusCode;
+
+    fn post(path: &str, body: Vec<u8>, content_length: bool) -> Request<Body> {
+        let mut builder = Request::post(path);
+        if content_length {
+            builder = builder.header("content-length", body.len());
+        }
+        builder.body(Body::from(body)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected_with_413() {
+        for content_length in [true, false] {
+            let response = limit_body(post("/api/config", vec![b'x'; 65], content_length), 64)
+                .await
+                .unwrap_err();
+            assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(body["code"], "payload_too_large");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_passes_through() {
+        let req = limit_body(
+            post("/api/config", br#"{"temperature":0.7}"#.to_vec(), true),
+            64,
+        )
+        .await
+        .unwrap();
+        assert_eq!(req.uri().path(), "/api/config");
+        let bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+        assert_eq!(&bytes[..], br#"{"temperature":0.7}"#);
+    }
+
+    #[test]
+    fn test_generate_routes_get_the_higher_limit() {
+        let limits = BodyLimits {
+            default: 10,
+            generate: 1000,
+        };
+        assert_eq!(
+            limits.for_request(&Method::POST, "/api/chat/stream"),
+            Some(1000)
+        );
+        assert_eq!(limits.for_request(&Method::POST, "/api/config"), Some(10));
+        assert_eq!(limits.for_request(&Method::GET, "/api/config"), None);
+        assert_eq!(limits.for_request(&Method::POST, "/api/upload"), None);
+    }
+}
@@ -9,13 +9,16 @@
 extern crate llama_chat_types;
 
 pub mod agent_heartbeat_runner;
+pub mod body_limit;
 pub mod remote;
 pub mod keychain;
+pub mod listen_addr;
 pub mod native_tools_bridge;
 pub mod request;
 pub mod request_parsing;
 pub mod response_helpers;
 pub mod routes;
+pub mod shutdown;
 pub mod skills;
 pub mod providers;
 pub mod websocket;
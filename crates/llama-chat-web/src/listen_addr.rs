@@ -0,0 +1,91 @@
+//! Listen address of the standalone web server.
+//!
+//! `LLAMA_CHAT_BIND_ADDR` (default `0.0.0.0`) and `LLAMA_CHAT_PORT` (default
+//! `18080`) choose the interface and port, e.g. to sit behind a reverse proxy.
+//! `tools/start-dev` reads the same variables to know where to wait.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+pub const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+pub const DEFAULT_PORT: u16 = 18080;
+
+/// Parse a bind address and port; `None` or blank values take the defaults.
+/// `localhost` means `127.0.0.1`. Port 0 is rejected since clients (and the
+/// LAN/UPnP URLs) need a known port.
+pub fn parse_listen_addr(
+    bind_addr: Option<&str>,
+    port: Option<&str>,
+) -> Result<SocketAddr, String> {
+    let ip = match bind_addr.map(str::trim).filter(|s| !s.is_empty()) {
+        None => DEFAULT_BIND_ADDR,
+        Some("localhost") => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Some(s) => s
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse()
+            .map_err(|_| format!("Invalid bind address '{s}': expected an IP such as 0.0.0.0"))?,
+    };
+    let port = match port.map(str::trim).filter(|s| !s.is_empty()) {
+        None => DEFAULT_PORT,
+        Some(s) => match s.parse::<u16>() {
+            Ok(0) | Err(_) => return Err(format!("Invalid port '{s}': expected 1-65535")),
+            Ok(p) => p,
+        },
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// Listen address from `LLAMA_CHAT_BIND_ADDR` / `LLAMA_CHAT_PORT`.
+pub fn listen_addr() -> Result<SocketAddr, String> {
+    let bind_addr = std::env::var("LLAMA_CHAT_BIND_ADDR").ok();
+    let port = std::env::var("LLAMA_CHAT_PORT").ok();
+    parse_listen_addr(bind_addr.as_deref(), port.as_deref())
+}
+
+/// Port the server listens on (the default if the env config is invalid,
+/// in which case the server refuses to start anyway).
+pub fn server_port() -> u16 {
+    listen_addr().map(|a| a.port()).unwrap_or(DEFAULT_PORT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_previous_fixed_address() {
+        assert_eq!(
+            parse_listen_addr(None, None).unwrap(),
+            "0.0.0.0:18080".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_listen_addr(Some("  "), Some("")).unwrap(),
+            parse_listen_addr(None, None).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_custom_addresses_parse() {
+        assert_eq!(
+            parse_listen_addr(Some("127.0.0.1"), Some("9000")).unwrap(),
+            "127.0.0.1:9000".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_listen_addr(Some("localhost"), None).unwrap(),
+            "127.0.0.1:18080".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_listen_addr(Some("[::1]"), Some("8443")).unwrap(),
+            "[::1]:8443".parse::<SocketAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_address_and_port_are_rejected() {
+        let err = parse_listen_addr(Some("not-an-ip"), None).unwrap_err();
+        assert!(err.contains("Invalid bind address 'not-an-ip'"), "{err}");
+        assert!(parse_listen_addr(Some("0.0.0.0:8080"), None).is_err());
+        assert!(parse_listen_addr(None, Some("0")).is_err());
+        assert!(parse_listen_addr(None, Some("70000")).is_err());
+        assert!(parse_listen_addr(None, Some("http")).is_err());
+    }
+}
@@ -31,5 +31,8 @@ pub fn make_dispatch_context() -> llama_chat_tools::DispatchContext<'static> {
                 content: s.content,
             })
         }),
+        // Remote providers return structured tool calls, so there is no native format
+        tool_format: None,
+        conversation_id: None,
     }
 }
@@ -72,6 +72,7 @@ pub async fn generate(
     let loop_limit = config.as_ref()
         .map(|c| c.loop_detection_limit as u32)
         .unwrap_or(15);
+    let enabled_tools = config.as_ref().and_then(|c| c.enabled_tools.clone());
 
     let api_key_owned = api_key.to_string();
     let provider_id_owned = provider_id.to_string();
@@ -98,7 +99,7 @@ pub async fn generate(
             mcp_proxy.as_ref().map(|p| p as &dyn llama_chat_tools::McpManagerOps);
 
         // Get tool definitions for the agentic loop
-        let tools = get_agentic_tools(mcp_ops);
+        let tools = get_agentic_tools(mcp_ops, enabled_tools.as_deref());
         let has_tools = !tools.is_empty();
 
         provider_log(&conv_id_owned, "provider_start",
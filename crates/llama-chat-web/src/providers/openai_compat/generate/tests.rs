@@ -59,7 +59,7 @@ mod tests {
     #[test]
     fn test_get_agentic_tools() {
         use crate::providers::openai_compat_request::get_agentic_tools;
-        let tools = get_agentic_tools(None);
+        let tools = get_agentic_tools(None, None);
         assert!(!tools.is_empty());
         // All returned tools must have a valid function name
         for tool in &tools {
@@ -168,9 +168,13 @@ pub(super) fn summarize_tool_output(output: &str, tool_name: &str, url: &str, ap
 
 /// Get the subset of tool definitions suitable for cloud provider agentic loops.
 /// Returns tools in OpenAI function-calling format, including MCP tools when available.
-pub(super) fn get_agentic_tools(mcp: Option<&dyn llama_chat_tools::McpManagerOps>) -> Vec<Value> {
-    use llama_chat_engine::jinja_templates::get_available_tools_openai;
-    let mut tools = get_available_tools_openai();
+/// Native tools outside `enabled_tools` are left out (None = all).
+pub(super) fn get_agentic_tools(
+    mcp: Option<&dyn llama_chat_tools::McpManagerOps>,
+    enabled_tools: Option<&[String]>,
+) -> Vec<Value> {
+    use llama_chat_engine::jinja_templates::get_available_tools_openai_with_mcp;
+    let mut tools = get_available_tools_openai_with_mcp(None, enabled_tools);
     if let Some(mgr) = mcp {
         for td in mgr.get_tool_definitions() {
             tools.push(td.to_openai_function());
@@ -1,12 +1,14 @@
 // Request parsing utilities for HTTP handlers
 
-use hyper::{Body, Response, StatusCode, Uri};
+use hyper::{Body, Response, Uri};
 use serde::de::DeserializeOwned;
 
+use crate::response_helpers::{api_error, ApiError};
+
 /// Parse JSON request body into a typed structure.
 ///
 /// Returns the deserialized value on success, or an error Response on failure.
-/// The error Response includes proper CORS headers and error message in JSON format.
+/// The error Response is an `ApiError` (`invalid_json` / `invalid_request`).
 ///
 /// # Example
 /// ```ignore
@@ -20,12 +22,10 @@ pub async fn parse_json_body<T: DeserializeOwned>(body: Body) -> Result<T, Respo
     let body_bytes = match hyper::body::to_bytes(body).await {
         Ok(bytes) => bytes,
         Err(_) => {
-            return Err(Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .header("content-type", "application/json")
-                .header("access-control-allow-origin", "*")
-                .body(Body::from(r#"{"error":"Failed to read request body"}"#))
-                .unwrap());
+            return Err(api_error(
+                ApiError::INVALID_REQUEST,
+                "Failed to read request body",
+            ));
         }
     };
 
@@ -41,12 +41,7 @@ pub async fn parse_json_body<T: DeserializeOwned>(body: Body) -> Result<T, Respo
         Ok(parsed) => Ok(parsed),
         Err(e) => {
             sys_error!("[REQUEST] JSON parsing error: {}", e);
-            Err(Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .header("content-type", "application/json")
-                .header("access-control-allow-origin", "*")
-                .body(Body::from(r#"{"error":"Invalid JSON format"}"#))
-                .unwrap())
+            Err(api_error(ApiError::INVALID_JSON, "Invalid JSON format"))
         }
     }
 }
@@ -135,4 +130,22 @@ mod tests {
             Some("hello world".to_string())
         );
     }
+
+    #[tokio::test]
+    async fn test_parse_json_body_invalid_json_is_api_error() {
+        #[derive(serde::Deserialize)]
+        struct Payload {
+            #[allow(dead_code)]
+            name: String,
+        }
+        let response = parse_json_body::<Payload>(Body::from("{not json"))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::BAD_REQUEST);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], "invalid_json");
+        assert_eq!(body["message"], "Invalid JSON format");
+    }
 }
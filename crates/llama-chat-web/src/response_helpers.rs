@@ -39,6 +39,84 @@ pub fn json_error(status: StatusCode, message: &str) -> Response<Body> {
         .unwrap()
 }
 
+/// Typed API error: a stable machine-readable `code` plus a human message.
+///
+/// Serialized as `{"code":..,"message":..,"error":..}` — `error` repeats the
+/// message for clients written against the older `{"error":..}` bodies. The
+/// HTTP status is derived from the code, so a code always maps to one status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub const INVALID_JSON: &'static str = "invalid_json";
+    pub const INVALID_REQUEST: &'static str = "invalid_request";
+    pub const MODEL_NOT_FOUND: &'static str = "model_not_found";
+    pub const NOT_FOUND: &'static str = "not_found";
+    pub const NO_MODEL_LOADED: &'static str = "no_model_loaded";
+    pub const CONFLICT: &'static str = "conflict";
+    pub const PAYLOAD_TOO_LARGE: &'static str = "payload_too_large";
+    pub const MODEL_LOAD_FAILED: &'static str = "model_load_failed";
+    pub const WORKER_UNAVAILABLE: &'static str = "worker_unavailable";
+    pub const NOT_AVAILABLE: &'static str = "not_available";
+    pub const INTERNAL_ERROR: &'static str = "internal_error";
+
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// A failure reported by a worker: `no_model_loaded` when it has no model,
+    /// otherwise `worker_unavailable`.
+    pub fn worker(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let code = if message.starts_with("No model loaded") {
+            Self::NO_MODEL_LOADED
+        } else {
+            Self::WORKER_UNAVAILABLE
+        };
+        Self::new(code, message)
+    }
+
+    /// HTTP status for this error's code; unknown codes are server errors.
+    pub fn status(&self) -> StatusCode {
+        match self.code {
+            Self::INVALID_JSON | Self::INVALID_REQUEST => StatusCode::BAD_REQUEST,
+            Self::MODEL_NOT_FOUND | Self::NOT_FOUND => StatusCode::NOT_FOUND,
+            Self::NO_MODEL_LOADED | Self::CONFLICT => StatusCode::CONFLICT,
+            Self::PAYLOAD_TOO_LARGE => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::WORKER_UNAVAILABLE | Self::NOT_AVAILABLE => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn into_response(self) -> Response<Body> {
+        #[derive(Serialize)]
+        struct ApiErrorBody<'a> {
+            code: &'a str,
+            message: &'a str,
+            error: &'a str,
+        }
+        json_response(
+            self.status(),
+            &ApiErrorBody {
+                code: self.code,
+                message: &self.message,
+                error: &self.message,
+            },
+        )
+    }
+}
+
+/// Build a structured error response (see [`ApiError`]).
+pub fn api_error(code: &'static str, message: impl Into<String>) -> Response<Body> {
+    ApiError::new(code, message).into_response()
+}
+
 /// Build a JSON success response
 /// TODO: Use this for standardized success responses instead of json_raw
 #[allow(dead_code)]
@@ -94,8 +172,6 @@ pub fn text_response(status: StatusCode, body: impl Into<Body>) -> Response<Body
 }
 
 /// Build a Server-Sent Events response with CORS headers
-/// TODO: Use for SSE streaming endpoints (alternative to WebSocket)
-#[allow(dead_code)]
 pub fn sse_response(body: Body) -> Response<Body> {
     with_cors(Response::builder().status(StatusCode::OK))
         .header("content-type", "text/event-stream")
@@ -119,4 +195,60 @@ mod tests {
         let response = json_error(StatusCode::BAD_REQUEST, r#"Error "quoted""#);
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    async fn body_json(response: Response<Body>) -> serde_json::Value {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_api_error_shape_and_status() {
+        let response = api_error(
+            ApiError::MODEL_NOT_FOUND,
+            r#"Model file "x.gguf" not found"#,
+        );
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        assert_eq!(
+            body_json(response).await,
+            serde_json::json!({
+                "code": "model_not_found",
+                "message": r#"Model file "x.gguf" not found"#,
+                "error": r#"Model file "x.gguf" not found"#,
+            })
+        );
+
+        let response = api_error(ApiError::INVALID_JSON, "Invalid JSON format");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_json(response).await["code"], "invalid_json");
+    }
+
+    #[test]
+    fn test_api_error_code_statuses() {
+        let status = |code| ApiError::new(code, "").status();
+        assert_eq!(status(ApiError::NO_MODEL_LOADED), StatusCode::CONFLICT);
+        assert_eq!(
+            status(ApiError::PAYLOAD_TOO_LARGE),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            status(ApiError::WORKER_UNAVAILABLE),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(
+            status(ApiError::MODEL_LOAD_FAILED),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            status(ApiError::INTERNAL_ERROR),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let err = ApiError::worker("No model loaded and no model configured for this conversation");
+        assert_eq!(err.code, ApiError::NO_MODEL_LOADED);
+        assert_eq!(
+            ApiError::worker("Worker stdin closed").code,
+            ApiError::WORKER_UNAVAILABLE
+        );
+    }
 }
@@ -1,7 +1,11 @@
 use std::convert::Infallible;
 
-use hyper::{Body, Response, StatusCode};
+use hyper::{Body, Request, Response, StatusCode};
 use llama_chat_db::SharedDatabase;
+use serde::Deserialize;
+
+use crate::request_parsing::parse_json_body;
+use crate::response_helpers::{api_error, json_response, ApiError};
 
 /// POST /api/approval/:id/approve  — approve a pending dangerous tool call
 pub async fn handle_approve(id: &str, db: SharedDatabase) -> Result<Response<Body>, Infallible> {
@@ -28,3 +32,53 @@ pub async fn handle_reject(id: &str, db: SharedDatabase) -> Result<Response<Body
         .body(Body::from(body))
         .unwrap())
 }
+
+#[derive(Deserialize)]
+struct ConfirmRequest {
+    id: String,
+    #[serde(default = "default_approve")]
+    approve: bool,
+}
+
+fn default_approve() -> bool {
+    true
+}
+
+/// POST /api/generation/confirm  — let a `require_confirmation_for` tool call run
+/// (`{"id": ...}`), or decline it with `"approve": false`
+pub async fn handle_generation_confirm(
+    req: Request<Body>,
+    db: SharedDatabase,
+) -> Result<Response<Body>, Infallible> {
+    let confirm: ConfirmRequest = match parse_json_body(req.into_body()).await {
+        Ok(c) => c,
+        Err(error_response) => return Ok(error_response),
+    };
+    match db.get_pending_approval_status(&confirm.id).as_deref() {
+        None => {
+            return Ok(api_error(
+                ApiError::NOT_FOUND,
+                format!("No pending tool call '{}'", confirm.id),
+            ))
+        }
+        Some("pending") => {}
+        Some(status) => {
+            return Ok(api_error(
+                ApiError::CONFLICT,
+                format!("Tool call '{}' is already {status}", confirm.id),
+            ))
+        }
+    }
+    let status = if confirm.approve {
+        "approved"
+    } else {
+        "rejected"
+    };
+    if let Err(e) = db.resolve_pending_approval(&confirm.id, status) {
+        return Ok(api_error(ApiError::INTERNAL_ERROR, e));
+    }
+    Ok(json_response(
+        StatusCode::OK,
+        &serde_json::json!({ "success": true, "status": status }),
+    ))
+}
@@ -15,26 +15,24 @@ use llama_chat_db::conversation::ConversationLogger;
 use llama_chat_db::SharedDatabase;
 use llama_chat_types::models::ChatRequest;
 #[cfg(not(feature = "mock"))]
-use llama_chat_types::models::{ChatMessage, ChatResponse};
+use llama_chat_types::models::{ChatMessage, ChatResponse, ChatUsage};
 use crate::request_parsing::parse_json_body;
-use crate::response_helpers::json_error;
-#[cfg(not(feature = "mock"))]
-use crate::response_helpers::json_response;
+use crate::response_helpers::{api_error, json_response, ApiError};
 #[cfg(not(feature = "mock"))]
 use crate::worker_pool::{resolve_bridge_for_conversation, resolve_bridge_for_request, WorkerPool};
 #[cfg(not(feature = "mock"))]
 use crate::websocket::{
     handle_conversation_watch, handle_websocket, make_server_continuation_message,
-    should_server_auto_continue, spawn_message_title_generation, spawn_title_generation,
+    prompt_echo_message, should_server_auto_continue, spawn_message_title_generation, spawn_title_generation,
     MAX_SERVER_AUTO_CONTINUES,
 };
 use crate::websocket_utils::{
-    build_json_error_response, build_websocket_upgrade_response,
-    calculate_websocket_accept_key, get_websocket_key, is_websocket_upgrade,
+    build_websocket_upgrade_response, calculate_websocket_accept_key, get_websocket_key,
+    is_websocket_upgrade,
 };
 
 #[cfg(not(feature = "mock"))]
-use llama_chat_worker::worker::worker_bridge::{GenerationResult, SharedWorkerBridge};
+use llama_chat_worker::worker::worker_bridge::{GenerateOptions, GenerationResult, SharedWorkerBridge};
 
 // Helper function to get current timestamp for logging
 #[cfg(not(feature = "mock"))]
@@ -69,6 +67,94 @@ fn resolve_system_prompt(
     }
 }
 
+/// 409 `no_model_loaded` when the worker has no model and config names none to
+/// load (see `llama_chat_config::require_model`).
+#[cfg(not(feature = "mock"))]
+fn require_model(
+    model_loaded: bool,
+    db: &llama_chat_db::Database,
+    conversation_id: Option<&str>,
+    agent_id: Option<&str>,
+) -> Result<(), Response<Body>> {
+    llama_chat_config::require_model(model_loaded, db, conversation_id, agent_id)
+        .map_err(|e| api_error(ApiError::NO_MODEL_LOADED, e))
+}
+
+/// Response for an `include_usage` request, built from the worker's completion
+/// payload. A cancelled generation returns the partial reply without usage.
+#[cfg(not(feature = "mock"))]
+fn completed_chat_response(
+    conversation_id: String,
+    content: String,
+    result: GenerationResult,
+) -> ChatResponse {
+    let mut message = ChatMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        role: "assistant".to_string(),
+        content,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        prompt_tok_per_sec: None,
+        gen_tok_per_sec: None,
+        gen_eval_ms: None,
+        gen_tokens: None,
+        prompt_eval_ms: None,
+        prompt_tokens: None,
+        compacted: false,
+        sequence_order: None,
+        parts: vec![],
+        title: None,
+        token_count: None,
+        created_at_millis: None,
+        reasoning: None,
+        interrupted: false,
+    };
+    let GenerationResult::Complete {
+        tokens_used,
+        max_tokens,
+        prompt_tok_per_sec,
+        gen_tok_per_sec,
+        gen_eval_ms,
+        gen_tokens,
+        prompt_eval_ms,
+        prompt_tokens,
+        ..
+    } = result
+    else {
+        return ChatResponse {
+            message,
+            conversation_id,
+            tokens_used: None,
+            max_tokens: None,
+            usage: None,
+        };
+    };
+
+    message.prompt_tok_per_sec = prompt_tok_per_sec;
+    message.gen_tok_per_sec = gen_tok_per_sec;
+    message.gen_eval_ms = gen_eval_ms;
+    message.gen_tokens = gen_tokens;
+    message.prompt_eval_ms = prompt_eval_ms;
+    message.prompt_tokens = prompt_tokens;
+    let prompt_tokens = prompt_tokens.unwrap_or(0);
+    let completion_tokens = gen_tokens.unwrap_or(0);
+    ChatResponse {
+        message,
+        conversation_id,
+        tokens_used: Some(tokens_used),
+        max_tokens: Some(max_tokens),
+        usage: Some(ChatUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            prompt_tok_per_sec,
+            gen_tok_per_sec,
+        }),
+    }
+}
+
 #[cfg(not(feature = "mock"))]
 pub async fn handle_post_chat(
     req: Request<Body>,
@@ -94,7 +180,7 @@ pub async fn handle_post_chat(
         .await
         {
             Ok(bridge) => bridge,
-            Err(e) => return Ok(json_error(StatusCode::SERVICE_UNAVAILABLE, &e)),
+            Err(e) => return Ok(ApiError::worker(e).into_response()),
         };
 
         // Check for test mode environment variable
@@ -124,22 +210,33 @@ pub async fn handle_post_chat(
                     sequence_order: None,
                     parts: vec![],
                     title: None,
+                    token_count: None,
+                    created_at_millis: None,
+                    reasoning: None,
+                    interrupted: false,
                 },
                 conversation_id: chat_request
                     .conversation_id
                     .unwrap_or_else(|| format!("{}", uuid::Uuid::new_v4())),
                 tokens_used: None,
                 max_tokens: None,
+                usage: None,
             };
 
             return Ok(json_response(StatusCode::OK, &response));
         }
 
         // Get model's general_name from bridge metadata
-        let general_name = bridge
-            .model_status()
-            .await
-            .and_then(|m| m.general_name.clone());
+        let model_status = bridge.model_status().await;
+        if let Err(response) = require_model(
+            model_status.is_some(),
+            &db,
+            chat_request.conversation_id.as_deref(),
+            chat_request.agent_id.as_deref(),
+        ) {
+            return Ok(response);
+        }
+        let general_name = model_status.and_then(|m| m.general_name.clone());
 
         // Create or load conversation logger
         let conversation_logger = if let Some(conversation_id) = &chat_request.conversation_id {
@@ -147,9 +244,9 @@ pub async fn handle_post_chat(
             match ConversationLogger::from_existing(db.clone(), conversation_id) {
                 Ok(logger) => Arc::new(Mutex::new(logger)),
                 Err(e) => {
-                    return Ok(json_error(
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        &format!("Failed to load conversation: {e}"),
+                    return Ok(api_error(
+                        ApiError::INTERNAL_ERROR,
+                        format!("Failed to load conversation: {e}"),
                     ));
                 }
             }
@@ -163,9 +260,9 @@ pub async fn handle_post_chat(
             match ConversationLogger::new(db.clone(), system_prompt.as_deref()) {
                 Ok(logger) => Arc::new(Mutex::new(logger)),
                 Err(e) => {
-                    return Ok(json_error(
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        &format!("Failed to create conversation logger: {e}"),
+                    return Ok(api_error(
+                        ApiError::INTERNAL_ERROR,
+                        format!("Failed to create conversation logger: {e}"),
                     ));
                 }
             }
@@ -201,20 +298,39 @@ pub async fn handle_post_chat(
         );
 
         match bridge
-            .generate(
+            .generate_with_options(
                 chat_request.message.clone(),
                 Some(conversation_id.clone()),
                 true, // skip_user_logging — already logged above
                 chat_request.image_data.clone(),
                 chat_request.agent_id.clone(),
+                GenerateOptions::from(&chat_request),
             )
             .await
         {
+            Ok((mut token_rx, done_rx)) if chat_request.include_usage => {
+                // Synchronous mode: block until the worker reports completion
+                let mut content = String::new();
+                while let Some(token_data) = token_rx.recv().await {
+                    content.push_str(&token_data.token);
+                }
+                return Ok(match done_rx.await {
+                    Ok(GenerationResult::Error(e)) => ApiError::worker(e).into_response(),
+                    Ok(result) => json_response(
+                        StatusCode::OK,
+                        &completed_chat_response(conversation_id, content, result),
+                    ),
+                    Err(_) => api_error(
+                        ApiError::WORKER_UNAVAILABLE,
+                        "Worker stopped before generation finished",
+                    ),
+                });
+            }
             Ok(_receivers) => {
                 // Drop receivers — generation runs in worker, client watches via WebSocket
             }
             Err(e) => {
-                return Ok(json_error(StatusCode::SERVICE_UNAVAILABLE, &e));
+                return Ok(ApiError::worker(e).into_response());
             }
         }
 
@@ -238,10 +354,15 @@ pub async fn handle_post_chat(
                 sequence_order: None,
                 parts: vec![],
                 title: None,
+                token_count: None,
+                created_at_millis: None,
+                reasoning: None,
+                interrupted: false,
             },
             conversation_id,
             tokens_used: None, // Will be updated via WebSocket
             max_tokens: None,  // Will be updated via WebSocket
+            usage: None,
         };
 
         Ok(json_response(StatusCode::OK, &chat_response))
@@ -266,10 +387,15 @@ pub async fn handle_post_chat(
                 sequence_order: None,
                 parts: vec![],
                 title: None,
+                token_count: None,
+                created_at_millis: None,
+                reasoning: None,
+                interrupted: false,
             },
             conversation_id: "test-conversation".to_string(),
             tokens_used: None,
             max_tokens: None,
+            usage: None,
         };
         Ok(json_response(StatusCode::OK, &mock_response))
     }
@@ -303,8 +429,17 @@ pub async fn handle_post_chat_stream(
         .await
         {
             Ok(bridge) => bridge,
-            Err(e) => return Ok(json_error(StatusCode::SERVICE_UNAVAILABLE, &e)),
+            Err(e) => return Ok(ApiError::worker(e).into_response()),
         };
+        let model_loaded = bridge.model_status().await.is_some();
+        if let Err(response) = require_model(
+            model_loaded,
+            &db,
+            chat_request.conversation_id.as_deref(),
+            chat_request.agent_id.as_deref(),
+        ) {
+            return Ok(response);
+        }
         let bridge_clone = bridge.clone();
         let db_clone = db.clone();
         let original_message = chat_request.message.clone();
@@ -319,6 +454,7 @@ pub async fn handle_post_chat_stream(
             .map(str::to_string);
         let initial_image_data = chat_request.image_data.clone();
         let initial_auto_continue = chat_request.auto_continue;
+        let generate_options = GenerateOptions::from(&chat_request);
 
         tokio::spawn(async move {
             let mut current_message = original_message.clone();
@@ -335,12 +471,13 @@ pub async fn handle_post_chat_stream(
                 };
 
                 let (mut token_rx, done_rx) = match bridge_clone
-                    .generate(
+                    .generate_with_options(
                         current_message.clone(),
                         current_conv_id.clone(),
                         skip_user_log,
                         image_data,
                         initial_agent_id.clone(),
+                        generate_options.clone(),
                     )
                     .await
                 {
@@ -358,6 +495,14 @@ pub async fn handle_post_chat_stream(
                 };
 
                 while let Some(token_data) = token_rx.recv().await {
+                    // echo_prompt: the same event the chat socket sends
+                    if let Some(ref echo) = token_data.prompt_echo {
+                        let event = format!("data: {}\n\n", prompt_echo_message(echo));
+                        if sender.send_data(Bytes::from(event)).await.is_err() {
+                            break 'gen_loop;
+                        }
+                        continue;
+                    }
                     let json_str = serde_json::to_string(&token_data).unwrap_or_else(|_| {
                         r#"{"token":"","tokens_used":0,"max_tokens":0}"#.to_string()
                     });
@@ -381,6 +526,11 @@ pub async fn handle_post_chat_stream(
                         prompt_tokens,
                         finish_reason,
                         token_breakdown,
+                        overflow,
+                        logprobs,
+                        tool_calls,
+                        stop_sequence,
+                        alternatives,
                     }) => {
                         if is_new_conversation {
                             let _ = db_clone.set_conversation_worker_id(
@@ -393,7 +543,7 @@ pub async fn handle_post_chat_stream(
                         }
 
                         bridge_clone
-                            .set_last_finish_reason(finish_reason.clone())
+                            .set_last_finish_reason(Some(&conversation_id), finish_reason.clone())
                             .await;
 
                         let finish_str = finish_reason.as_deref().unwrap_or("");
@@ -409,7 +559,7 @@ pub async fn handle_post_chat_stream(
                         }
 
                         final_conv_id_for_title = Some(conversation_id.clone());
-                        let done_json = serde_json::json!({
+                        let mut done_json = serde_json::json!({
                             "type": "done",
                             "conversation_id": conversation_id,
                             "tokens_used": tokens_used,
@@ -421,8 +571,21 @@ pub async fn handle_post_chat_stream(
                             "prompt_eval_ms": prompt_eval_ms,
                             "prompt_tokens": prompt_tokens,
                             "finish_reason": finish_reason,
-                            "token_breakdown": token_breakdown
+                            "token_breakdown": token_breakdown,
+                            "overflow": overflow
                         });
+                        if !logprobs.is_empty() {
+                            done_json["logprobs"] = serde_json::json!(logprobs);
+                        }
+                        if !tool_calls.is_empty() {
+                            done_json["tool_calls"] = serde_json::json!(tool_calls);
+                        }
+                        if let Some(stop) = stop_sequence {
+                            done_json["stop_sequence"] = serde_json::json!(stop);
+                        }
+                        if !alternatives.is_empty() {
+                            done_json["alternatives"] = serde_json::json!(alternatives);
+                        }
                         if sender
                             .send_data(Bytes::from(format!("data: {done_json}\n\n")))
                             .await
@@ -484,8 +647,8 @@ pub async fn handle_post_chat_stream(
     #[cfg(feature = "mock")]
     {
         let _ = chat_request;
-        Ok(json_error(
-            StatusCode::OK,
+        Ok(api_error(
+            ApiError::NOT_AVAILABLE,
             "Streaming not available (mock feature enabled)",
         ))
     }
@@ -510,6 +673,177 @@ pub async fn handle_post_chat_cancel(
         .unwrap())
 }
 
+/// POST /api/conversation/{id}/cancel — cancel that conversation's generation only.
+///
+/// Every generating worker is asked; each ignores the cancel unless it is
+/// generating `conversation_id`, so other conversations keep running.
+pub async fn handle_post_conversation_cancel(
+    #[cfg(not(feature = "mock"))] pool: WorkerPool,
+    #[cfg(feature = "mock")] _pool: (),
+    conversation_id: &str,
+) -> Result<Response<Body>, Infallible> {
+    #[allow(unused_mut)]
+    let mut cancelled = false;
+    #[cfg(not(feature = "mock"))]
+    for entry in pool.list_entries() {
+        if !entry.bridge.is_generating().await {
+            continue;
+        }
+        if entry.bridge.is_generating_conversation(conversation_id).await {
+            cancelled = true;
+        }
+        entry.bridge.cancel_conversation(conversation_id).await;
+    }
+    sys_info!(
+        "[API_CHAT_CANCEL] Cancel requested for {} (generating: {})",
+        conversation_id,
+        cancelled
+    );
+    Ok(json_response(
+        StatusCode::OK,
+        &serde_json::json!({ "success": true, "cancelled": cancelled }),
+    ))
+}
+
+/// Body of `POST /api/conversation/{id}/regenerate`. Every field is optional;
+/// unset sampler fields keep the conversation's config.
+#[cfg(not(feature = "mock"))]
+#[derive(serde::Deserialize, Default)]
+struct RegenerateRequest {
+    #[serde(default)]
+    temperature: Option<f64>,
+    #[serde(default)]
+    seed: Option<i32>,
+    #[serde(default)]
+    sampler_type: Option<String>,
+    /// Wait for the re-run and return it with usage, as for `/api/chat`.
+    #[serde(default)]
+    include_usage: bool,
+}
+
+#[cfg(not(feature = "mock"))]
+impl RegenerateRequest {
+    fn generate_options(&self) -> GenerateOptions {
+        GenerateOptions {
+            overrides: llama_chat_types::models::GenerationOverrides {
+                temperature: self.temperature,
+                seed: self.seed,
+                sampler_type: self.sampler_type.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Drop everything after the last user message and return that message's text,
+/// ready to be generated again. `None` when the conversation has no user message.
+#[cfg(not(feature = "mock"))]
+fn truncate_last_turn(db: &llama_chat_db::Database, conversation_id: &str) -> Result<Option<String>, String> {
+    let messages = db.get_messages(conversation_id)?;
+    let Some(last_user) = messages.iter().rposition(|m| m.role == "user") else {
+        return Ok(None);
+    };
+    if let Some(reply) = messages.get(last_user + 1) {
+        db.truncate_messages(conversation_id, reply.sequence_order)?;
+    }
+    Ok(Some(messages[last_user].content.clone()))
+}
+
+/// POST /api/conversation/{id}/regenerate — re-run the last turn, optionally
+/// with different sampler settings, replacing the previous assistant reply.
+#[cfg(not(feature = "mock"))]
+pub async fn handle_post_conversation_regenerate(
+    req: Request<Body>,
+    pool: WorkerPool,
+    db: SharedDatabase,
+    conversation_id: &str,
+) -> Result<Response<Body>, Infallible> {
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Ok(api_error(ApiError::INVALID_REQUEST, "Failed to read body")),
+    };
+    let regenerate: RegenerateRequest = if body.is_empty() {
+        RegenerateRequest::default()
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(r) => r,
+            Err(e) => return Ok(api_error(ApiError::INVALID_JSON, format!("Invalid JSON: {e}"))),
+        }
+    };
+
+    match db.conversation_exists(conversation_id) {
+        Ok(true) => {}
+        Ok(false) => return Ok(api_error(ApiError::NOT_FOUND, "Conversation not found")),
+        Err(e) => return Ok(api_error(ApiError::INTERNAL_ERROR, e)),
+    }
+    let bridge = match resolve_bridge_for_conversation(&pool, &db, Some(conversation_id)).await {
+        Ok(bridge) => bridge,
+        Err(e) => return Ok(ApiError::worker(e).into_response()),
+    };
+    if bridge.is_generating_conversation(conversation_id).await {
+        return Ok(api_error(ApiError::CONFLICT, "Conversation is still generating"));
+    }
+    let model_loaded = bridge.model_status().await.is_some();
+    if let Err(response) = require_model(model_loaded, &db, Some(conversation_id), None) {
+        return Ok(response);
+    }
+
+    let user_message = match truncate_last_turn(&db, conversation_id) {
+        Ok(Some(message)) => message,
+        Ok(None) => {
+            return Ok(api_error(
+                ApiError::INVALID_REQUEST,
+                "Conversation has no user message to regenerate",
+            ))
+        }
+        Err(e) => return Ok(api_error(ApiError::INTERNAL_ERROR, e)),
+    };
+    sys_info!(
+        "[{}] [API_REGENERATE] Re-running last turn of {} (temperature: {:?}, seed: {:?})",
+        timestamp_now(),
+        conversation_id,
+        regenerate.temperature,
+        regenerate.seed
+    );
+
+    match bridge
+        .generate_with_options(
+            user_message,
+            Some(conversation_id.to_string()),
+            true, // the user message is still logged; only the reply was dropped
+            None,
+            None,
+            regenerate.generate_options(),
+        )
+        .await
+    {
+        Ok((mut token_rx, done_rx)) if regenerate.include_usage => {
+            let mut content = String::new();
+            while let Some(token_data) = token_rx.recv().await {
+                content.push_str(&token_data.token);
+            }
+            Ok(match done_rx.await {
+                Ok(GenerationResult::Error(e)) => ApiError::worker(e).into_response(),
+                Ok(result) => json_response(
+                    StatusCode::OK,
+                    &completed_chat_response(conversation_id.to_string(), content, result),
+                ),
+                Err(_) => api_error(
+                    ApiError::WORKER_UNAVAILABLE,
+                    "Worker stopped before generation finished",
+                ),
+            })
+        }
+        // Tokens stream to conversation watchers over WebSocket
+        Ok(_receivers) => Ok(json_response(
+            StatusCode::OK,
+            &serde_json::json!({ "success": true, "conversation_id": conversation_id }),
+        )),
+        Err(e) => Ok(ApiError::worker(e).into_response()),
+    }
+}
+
 pub async fn handle_websocket_chat_stream(
     req: Request<Body>,
     #[cfg(not(feature = "mock"))] pool: WorkerPool,
@@ -518,10 +852,7 @@ pub async fn handle_websocket_chat_stream(
 ) -> Result<Response<Body>, Infallible> {
     // Check if the request wants to upgrade to WebSocket
     if !is_websocket_upgrade(&req) {
-        return Ok(build_json_error_response(
-            StatusCode::BAD_REQUEST,
-            "WebSocket upgrade required",
-        ));
+        return Ok(api_error(ApiError::INVALID_REQUEST, "WebSocket upgrade required"));
     }
 
     // Extract the WebSocket key and calculate accept key
@@ -578,10 +909,7 @@ pub async fn handle_conversation_watch_websocket(
 
     // Check for WebSocket upgrade
     if !is_websocket_upgrade(&req) {
-        return Ok(build_json_error_response(
-            StatusCode::BAD_REQUEST,
-            "Expected WebSocket upgrade",
-        ));
+        return Ok(api_error(ApiError::INVALID_REQUEST, "Expected WebSocket upgrade"));
     }
 
     // Get WebSocket key and calculate accept key
@@ -592,7 +920,7 @@ pub async fn handle_conversation_watch_websocket(
     {
         let bridge_ws = match resolve_bridge_for_conversation(&pool, &db, Some(&conversation_id)).await {
             Ok(bridge) => bridge,
-            Err(e) => return Ok(build_json_error_response(StatusCode::SERVICE_UNAVAILABLE, &e)),
+            Err(e) => return Ok(ApiError::worker(e).into_response()),
         };
         let db_ws = db.clone();
 
@@ -629,3 +957,193 @@ pub async fn handle_conversation_watch_websocket(
     // Return 101 Switching Protocols
     Ok(build_websocket_upgrade_response(&accept_key))
 }
+
+#[cfg(all(test, not(feature = "mock")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_response_carries_completion_usage() {
+        let result = GenerationResult::Complete {
+            conversation_id: "chat_1".to_string(),
+            tokens_used: 412,
+            max_tokens: 8192,
+            prompt_tok_per_sec: Some(950.5),
+            gen_tok_per_sec: Some(42.25),
+            gen_eval_ms: Some(1420.0),
+            gen_tokens: Some(60),
+            prompt_eval_ms: Some(370.0),
+            prompt_tokens: Some(352),
+            finish_reason: Some("stop".to_string()),
+            token_breakdown: None,
+            overflow: Default::default(),
+            logprobs: vec![],
+            tool_calls: vec![],
+            stop_sequence: None,
+            alternatives: vec![],
+        };
+        let response =
+            completed_chat_response("chat_1".to_string(), "Hello there".to_string(), result);
+
+        assert_eq!(response.message.content, "Hello there");
+        assert_eq!(response.tokens_used, Some(412));
+        assert_eq!(response.max_tokens, Some(8192));
+        assert_eq!(
+            response.usage,
+            Some(ChatUsage {
+                prompt_tokens: 352,
+                completion_tokens: 60,
+                total_tokens: 412,
+                prompt_tok_per_sec: Some(950.5),
+                gen_tok_per_sec: Some(42.25),
+            })
+        );
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["usage"]["total_tokens"], 412);
+        assert_eq!(json["message"]["gen_tok_per_sec"], 42.25);
+    }
+
+    #[test]
+    fn test_cancelled_sync_response_has_no_usage() {
+        let response = completed_chat_response(
+            "chat_1".to_string(),
+            "Partial".to_string(),
+            GenerationResult::Cancelled,
+        );
+        assert_eq!(response.message.content, "Partial");
+        assert!(response.usage.is_none());
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("usage").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chat_without_model_is_409_no_model_loaded() {
+        let db = llama_chat_db::Database::new(":memory:").unwrap();
+        let response = require_model(false, &db, None, None).unwrap_err();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], "no_model_loaded");
+
+        assert!(require_model(true, &db, None, None).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sse_stream_leads_with_the_prompt_echo() {
+        use llama_chat_worker::worker::process_manager::ProcessManager;
+        use llama_chat_worker::worker::worker_bridge::WorkerBridge;
+        use std::process::{Command, Stdio};
+
+        // Stand-in worker: the echo, one token, then completion
+        let script = r#"while read -r line; do
+             id=$(printf '%s' "$line" | sed -n 's/^{"id":\([0-9]*\),.*/\1/p')
+             case "$line" in *'"type":"Generate"'*)
+               printf '{"id":%s,"payload":{"type":"Token","token":"","tokens_used":0,"max_tokens":4096,"prompt_echo":{"system_prompt":"Be brief.","template_type":"ChatML","tools":[]}}}\n' "$id"
+               printf '{"id":%s,"payload":{"type":"Token","token":"Hi","tokens_used":1,"max_tokens":4096}}\n' "$id"
+               printf '{"id":%s,"payload":{"type":"GenerationComplete","conversation_id":"c1","tokens_used":1,"max_tokens":4096,"finish_reason":"stop"}}\n' "$id" ;;
+             esac
+           done"#;
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawn mock worker");
+        let db: SharedDatabase = Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        let bridge = Arc::new(WorkerBridge::new(
+            Arc::new(ProcessManager::from_child(child, ":memory:")),
+            db.clone(),
+        ));
+        let pool = WorkerPool::new(bridge, ":memory:", db.clone());
+
+        // Nothing loaded or configured: refused before the stream starts
+        let req = Request::builder()
+            .method("POST")
+            .body(Body::from(r#"{"message":"hi","echo_prompt":true}"#))
+            .unwrap();
+        let response = handle_post_chat_stream(req, pool.clone(), db.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(db.list_conversations(10, 0).unwrap().is_empty());
+
+        let mut config = db.load_config();
+        config.model_path = Some("/models/a.gguf".to_string());
+        db.save_config(&config).unwrap();
+        let req = Request::builder()
+            .method("POST")
+            .body(Body::from(r#"{"message":"hi","echo_prompt":true}"#))
+            .unwrap();
+        let response = handle_post_chat_stream(req, pool.clone(), db).await.unwrap();
+        let bytes = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            hyper::body::to_bytes(response.into_body()),
+        )
+        .await
+        .expect("stream ends")
+        .unwrap();
+        let events: Vec<serde_json::Value> = String::from_utf8_lossy(&bytes)
+            .split("\n\n")
+            .filter_map(|event| event.strip_prefix("data: "))
+            .filter(|data| *data != "[DONE]")
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+
+        assert_eq!(events[0]["type"], "prompt_echo");
+        assert_eq!(events[0]["system_prompt"], "Be brief.");
+        assert_eq!(events[1]["token"], "Hi");
+        assert_eq!(events.last().unwrap()["type"], "done");
+        pool.shutdown_all();
+    }
+
+    #[test]
+    fn test_regenerate_replaces_the_last_assistant_reply() {
+        let db = llama_chat_db::Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "First question", 10, 0).unwrap();
+        db.insert_message(&id, "assistant", "First answer", 11, 1).unwrap();
+        db.insert_message(&id, "user", "Tell me a story", 12, 2).unwrap();
+        db.insert_message(&id, "assistant", "Once upon a time.", 13, 3).unwrap();
+
+        assert_eq!(truncate_last_turn(&db, &id).unwrap().as_deref(), Some("Tell me a story"));
+        let roles: Vec<_> = db
+            .get_messages(&id)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.role, m.content))
+            .collect();
+        assert_eq!(
+            roles.last(),
+            Some(&("user".to_string(), "Tell me a story".to_string()))
+        );
+        assert_eq!(roles.len(), 3);
+
+        // A turn whose reply never landed is re-run without deleting anything
+        assert_eq!(truncate_last_turn(&db, &id).unwrap().as_deref(), Some("Tell me a story"));
+        assert_eq!(db.get_messages(&id).unwrap().len(), 3);
+
+        let empty = db.create_conversation().unwrap();
+        assert_eq!(truncate_last_turn(&db, &empty).unwrap(), None);
+    }
+
+    #[test]
+    fn test_regenerate_overrides_are_applied_to_the_rerun() {
+        let regenerate: RegenerateRequest = serde_json::from_value(serde_json::json!({
+            "temperature": 1.4,
+            "seed": 7,
+            "sampler_type": "Greedy"
+        }))
+        .unwrap();
+        let options = regenerate.generate_options();
+
+        let db = llama_chat_db::Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        let stored = llama_chat_config::load_config_for_conversation(&db, &id);
+        let config = llama_chat_config::load_config_with_overrides(&db, &id, &options.overrides);
+        assert_eq!(config.temperature, 1.4);
+        assert_eq!(config.seed, 7);
+        assert_eq!(config.sampler_type, "Greedy");
+        assert_eq!(config.top_k, stored.top_k);
+    }
+}
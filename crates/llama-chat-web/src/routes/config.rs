@@ -4,13 +4,32 @@ use hyper::{Body, Request, Response, StatusCode};
 use std::convert::Infallible;
 
 use crate::request_parsing::parse_json_body;
-use crate::response_helpers::{json_error, json_raw};
+use crate::response_helpers::{api_error, json_raw, json_response, ApiError};
 use llama_chat_config::{
     db_config_to_sampler_config, load_config_for_conversation, sampler_config_to_db,
 };
 use llama_chat_db::SharedDatabase;
 use llama_chat_types::logger::LOGGER;
-use llama_chat_types::models::SamplerConfig;
+use llama_chat_types::models::{sampler_preset, sampler_presets, unknown_sampler_preset, SamplerConfig};
+use serde::Deserialize;
+
+mod reload;
+pub use reload::ConfigReload;
+#[cfg(not(feature = "mock"))]
+use reload::reload_for_change;
+
+/// `POST /api/config` body: the config plus the GPU layer count to reload the
+/// loaded model with, which is not a stored setting.
+#[derive(Deserialize)]
+struct ConfigUpdate {
+    #[serde(flatten)]
+    config: SamplerConfig,
+    #[serde(default)]
+    gpu_layers: Option<u32>,
+    /// Set by a client that loads the model itself right after saving.
+    #[serde(default)]
+    skip_reload: bool,
+}
 
 pub async fn handle_get_config(
     #[cfg(not(feature = "mock"))]
@@ -23,66 +42,147 @@ pub async fn handle_get_config(
 
     match serde_json::to_string(&config) {
         Ok(config_json) => Ok(json_raw(StatusCode::OK, config_json)),
-        Err(_) => Ok(json_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize configuration",
-        )),
+        Err(_) => Ok(api_error(ApiError::INTERNAL_ERROR, "Failed to serialize configuration")),
     }
 }
 
 pub async fn handle_post_config(
     req: Request<Body>,
     #[cfg(not(feature = "mock"))]
-    _bridge: llama_chat_worker::worker::worker_bridge::SharedWorkerBridge,
+    bridge: llama_chat_worker::worker::worker_bridge::SharedWorkerBridge,
     #[cfg(feature = "mock")] _bridge: (),
     db: SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
     // Parse request body
-    let incoming_config: SamplerConfig = match parse_json_body(req.into_body()).await {
-        Ok(config) => config,
+    let ConfigUpdate { config: incoming_config, gpu_layers, skip_reload } = match parse_json_body(req.into_body()).await {
+        Ok(update) => update,
         Err(error_response) => return Ok(error_response),
     };
 
     // Basic validation
     if !(0.0..=2.0).contains(&incoming_config.temperature) {
-        return Ok(json_error(
-            StatusCode::BAD_REQUEST,
-            "temperature must be between 0.0 and 2.0",
-        ));
+        return Ok(api_error(ApiError::INVALID_REQUEST, "temperature must be between 0.0 and 2.0"));
     }
     if !(0.0..=1.0).contains(&incoming_config.top_p) {
-        return Ok(json_error(
-            StatusCode::BAD_REQUEST,
-            "top_p must be between 0.0 and 1.0",
-        ));
+        return Ok(api_error(ApiError::INVALID_REQUEST, "top_p must be between 0.0 and 1.0"));
     }
     if incoming_config.context_size == Some(0) {
-        return Ok(json_error(
-            StatusCode::BAD_REQUEST,
-            "context_size must be positive",
-        ));
+        return Ok(api_error(ApiError::INVALID_REQUEST, "context_size must be positive"));
+    }
+    if incoming_config.sampler_presets.iter().any(|p| p.name.trim().is_empty()) {
+        return Ok(api_error(ApiError::INVALID_REQUEST, "sampler_presets entries need a name"));
+    }
+    if let Some(name) = incoming_config.sampler_preset.as_deref() {
+        if sampler_preset(name, &incoming_config.sampler_presets).is_none() {
+            return Ok(api_error(
+                ApiError::INVALID_REQUEST,
+                unknown_sampler_preset(name, &incoming_config.sampler_presets),
+            ));
+        }
     }
 
     // Load existing config to preserve model_history
-    let existing_db_config = db.load_config();
+    let mut existing_db_config = db.load_config();
 
     // Merge: take incoming values but keep existing model_history
     let mut merged = sampler_config_to_db(&incoming_config);
-    merged.model_history = existing_db_config.model_history;
+    merged.model_history = std::mem::take(&mut existing_db_config.model_history);
+    let system_prompt_changed = merged.system_prompt != existing_db_config.system_prompt;
 
     match db.save_config(&merged) {
         Ok(_) => {
             // Sync file logging toggle at runtime
             LOGGER.set_enabled(!merged.disable_file_logging);
-            Ok(json_raw(StatusCode::OK, r#"{"success":true}"#.to_string()))
+            // Re-warm the new prompt on the resident model instead of paying for it next turn
+            #[cfg(not(feature = "mock"))]
+            if system_prompt_changed {
+                if let Err(e) = bridge.set_system_prompt(merged.system_prompt.clone()).await {
+                    sys_debug!("[CONFIG] System prompt re-warm skipped: {}", e);
+                }
+            }
+            #[cfg(feature = "mock")]
+            let _ = system_prompt_changed;
+            // Load-affecting changes apply to the resident model now, not after a manual reload
+            #[cfg(not(feature = "mock"))]
+            let reload = match skip_reload {
+                true => None,
+                false => start_config_reload(&bridge, &db, &existing_db_config, &merged, gpu_layers).await,
+            };
+            #[cfg(feature = "mock")]
+            let reload: Option<ConfigReload> = {
+                let _ = (existing_db_config, gpu_layers, skip_reload);
+                None
+            };
+            Ok(json_response(
+                StatusCode::OK,
+                &serde_json::json!({ "success": true, "reload": reload }),
+            ))
         }
-        Err(e) => Ok(json_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            &format!("Failed to save configuration: {e}"),
+        Err(e) => Ok(api_error(
+            ApiError::INTERNAL_ERROR,
+            format!("Failed to save configuration: {e}"),
         )),
     }
 }
 
+/// Start the reload a config update needs on the loaded model, in the
+/// background; progress goes out as model-load events. Nothing is started
+/// when no model is loaded (the next load uses the new settings) or while a
+/// load or generation is running. A `model_path` naming the resident model is
+/// not a switch. Shared by `POST /api/config` and the desktop `save_config`.
+#[cfg(not(feature = "mock"))]
+pub async fn start_config_reload(
+    bridge: &llama_chat_worker::worker::worker_bridge::SharedWorkerBridge,
+    db: &SharedDatabase,
+    old: &llama_chat_db::config::DbSamplerConfig,
+    new: &llama_chat_db::config::DbSamplerConfig,
+    gpu_layers: Option<u32>,
+) -> Option<ConfigReload> {
+    let loaded = bridge.model_status().await.filter(|meta| meta.loaded)?;
+    let switch = match new.model_path.as_deref().filter(|_| new.model_path != old.model_path) {
+        Some(requested) => match llama_chat_config::resolve_model_path(
+            requested,
+            new.models_directory.as_deref(),
+            new.allow_external_model_paths,
+        )
+        .and_then(llama_chat_config::ModelTarget::into_file)
+        {
+            Ok(path) if path == loaded.model_path => None,
+            resolved => Some(resolved),
+        },
+        None => None,
+    };
+    let mut reload = reload_for_change(old, new, switch.is_some(), loaded.gpu_layers, gpu_layers)?;
+
+    let model_path = match switch {
+        Some(Ok(path)) => path,
+        Some(Err(e)) => {
+            reload.note = Some(e);
+            return Some(reload);
+        }
+        None => loaded.model_path.clone(),
+    };
+    if bridge.is_loading() || bridge.is_generating().await {
+        reload.note = Some("A load or generation is running; reload the model once it finishes".to_string());
+        return Some(reload);
+    }
+
+    let action = reload.action;
+    let (bridge, db) = (bridge.clone(), db.clone());
+    let gpu_layers = gpu_layers.or(loaded.gpu_layers);
+    tokio::spawn(async move {
+        match bridge.load_model(&model_path, gpu_layers, None, None).await {
+            Ok(meta) => {
+                llama_chat_config::add_to_model_history(&db, &meta.model_path);
+                sys_info!("[CONFIG] Applied config change: {:?} (requested {:?})", meta.load_outcome, action);
+            }
+            Err(e) => sys_warn!("[CONFIG] Reload after config change failed: {}", e),
+        }
+    });
+    reload.started = true;
+    Some(reload)
+}
+
 /// Extract conversation ID from path like /api/conversations/{id}/config
 fn extract_conversation_id_from_config_path(path: &str) -> Option<String> {
     let stripped = path.strip_prefix("/api/conversations/")?;
@@ -104,10 +204,7 @@ pub async fn handle_get_conversation_config(
     let conversation_id = match extract_conversation_id_from_config_path(path) {
         Some(id) => id,
         None => {
-            return Ok(json_error(
-                StatusCode::BAD_REQUEST,
-                "Invalid conversation ID",
-            ));
+            return Ok(api_error(ApiError::INVALID_REQUEST, "Invalid conversation ID"));
         }
     };
 
@@ -115,13 +212,21 @@ pub async fn handle_get_conversation_config(
 
     match serde_json::to_string(&config) {
         Ok(json) => Ok(json_raw(StatusCode::OK, json)),
-        Err(_) => Ok(json_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize configuration",
-        )),
+        Err(_) => Ok(api_error(ApiError::INTERNAL_ERROR, "Failed to serialize configuration")),
     }
 }
 
+/// GET /api/sampler/presets — the built-in and configured presets, and the one selected, if any
+pub async fn handle_get_sampler_presets(db: SharedDatabase) -> Result<Response<Body>, Infallible> {
+    let config = db.load_config();
+    Ok(json_response(
+        StatusCode::OK,
+        &serde_json::json!({
+            "presets": sampler_presets(&config.sampler_presets),
+            "active": config.sampler_preset,
+        }),
+    ))
+}
 
 /// GET /api/config/provider-keys — get configured provider API keys (masked)
 pub async fn handle_get_provider_keys(db: SharedDatabase) -> Result<Response<Body>, Infallible> {
@@ -183,16 +288,16 @@ pub async fn handle_set_provider_key(
 ) -> Result<Response<Body>, Infallible> {
     let body = match hyper::body::to_bytes(req.into_body()).await {
         Ok(b) => b,
-        Err(_) => return Ok(json_error(StatusCode::BAD_REQUEST, "Failed to read body")),
+        Err(_) => return Ok(api_error(ApiError::INVALID_REQUEST, "Failed to read body")),
     };
     let json: serde_json::Value = match serde_json::from_slice(&body) {
         Ok(v) => v,
-        Err(_) => return Ok(json_error(StatusCode::BAD_REQUEST, "Invalid JSON")),
+        Err(_) => return Ok(api_error(ApiError::INVALID_JSON, "Invalid JSON")),
     };
 
     let provider = match json.get("provider").and_then(|p| p.as_str()) {
         Some(p) => p.to_string(),
-        None => return Ok(json_error(StatusCode::BAD_REQUEST, "provider is required")),
+        None => return Ok(api_error(ApiError::INVALID_REQUEST, "provider is required")),
     };
     let api_key = json.get("api_key").and_then(|k| k.as_str()).unwrap_or("");
     let base_url = json.get("base_url").and_then(|u| u.as_str());
@@ -237,9 +342,9 @@ pub async fn handle_set_provider_key(
             serde_json::to_string(&serde_json::json!({"success": true, "provider": provider}))
                 .unwrap(),
         )),
-        Err(e) => Ok(json_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            &format!("Failed: {e}"),
+        Err(e) => Ok(api_error(
+            ApiError::INTERNAL_ERROR,
+            format!("Failed: {e}"),
         )),
     }
 }
@@ -272,11 +377,11 @@ pub async fn handle_set_active_provider(
 ) -> Result<Response<Body>, Infallible> {
     let body = match hyper::body::to_bytes(req.into_body()).await {
         Ok(b) => b,
-        Err(_) => return Ok(json_error(StatusCode::BAD_REQUEST, "Failed to read body")),
+        Err(_) => return Ok(api_error(ApiError::INVALID_REQUEST, "Failed to read body")),
     };
     let json: serde_json::Value = match serde_json::from_slice(&body) {
         Ok(v) => v,
-        Err(_) => return Ok(json_error(StatusCode::BAD_REQUEST, "Invalid JSON")),
+        Err(_) => return Ok(api_error(ApiError::INVALID_JSON, "Invalid JSON")),
     };
 
     let provider = json
@@ -299,9 +404,9 @@ pub async fn handle_set_active_provider(
             }))
             .unwrap(),
         )),
-        Err(e) => Ok(json_error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            &format!("Failed: {e}"),
+        Err(e) => Ok(api_error(
+            ApiError::INTERNAL_ERROR,
+            format!("Failed: {e}"),
         )),
     }
 }
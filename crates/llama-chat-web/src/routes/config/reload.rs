@@ -0,0 +1,102 @@
+// What a `POST /api/config` update means for the loaded model.
+//
+// Weights-level settings (model path, gpu_layers, mmap/mlock, GPU placement)
+// only apply after a full reload. Context-level ones (context size, KV cache
+// type, batch sizes, RoPE) only need the cached contexts dropped, which a load
+// with unchanged weights settings does without reading the weights again.
+// Everything else (temperature, prompts, ...) is read on the next request.
+
+use llama_chat_db::config::DbSamplerConfig;
+use llama_chat_types::models::LoadOutcome;
+use serde::Serialize;
+
+/// Reported as `reload` in the config-update response.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ConfigReload {
+    pub(super) action: LoadOutcome,
+    /// Settings that made it necessary.
+    pub(super) changed: Vec<&'static str>,
+    /// False when it could not be started now; `note` says why.
+    pub(super) started: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) note: Option<String>,
+}
+
+/// The reload a saved config needs, or None if the next request picks it up.
+/// `model_switch` is whether `new.model_path` names another model than the
+/// loaded one. `requested_gpu_layers` is the update's `gpu_layers` (not a
+/// stored setting), compared against what the model was loaded with.
+pub(super) fn reload_for_change(
+    old: &DbSamplerConfig,
+    new: &DbSamplerConfig,
+    model_switch: bool,
+    loaded_gpu_layers: Option<u32>,
+    requested_gpu_layers: Option<u32>,
+) -> Option<ConfigReload> {
+    let weights = [
+        ("model_path", model_switch),
+        ("gpu_layers", requested_gpu_layers.is_some() && requested_gpu_layers != loaded_gpu_layers),
+        ("use_mlock", new.use_mlock != old.use_mlock),
+        ("use_mmap", new.use_mmap != old.use_mmap),
+        ("main_gpu", new.main_gpu != old.main_gpu),
+        ("split_mode", new.split_mode != old.split_mode),
+    ];
+    let context = [
+        ("context_size", new.context_size != old.context_size),
+        ("cache_type_k", new.cache_type_k != old.cache_type_k),
+        ("cache_type_v", new.cache_type_v != old.cache_type_v),
+        ("flash_attention", new.flash_attention != old.flash_attention),
+        ("n_batch", new.n_batch != old.n_batch),
+        ("n_ubatch", new.n_ubatch != old.n_ubatch),
+        ("rope_freq_base", new.rope_freq_base != old.rope_freq_base),
+        ("rope_freq_scale", new.rope_freq_scale != old.rope_freq_scale),
+    ];
+    let changed = |fields: &[(&'static str, bool)]| -> Vec<&'static str> {
+        fields.iter().filter(|(_, changed)| *changed).map(|(name, _)| *name).collect()
+    };
+
+    let (action, changed) = match (changed(&weights), changed(&context)) {
+        (weights, context) if !weights.is_empty() => (LoadOutcome::FullReload, [weights, context].concat()),
+        (_, context) if !context.is_empty() => (LoadOutcome::ContextRebuild, context),
+        _ => return None,
+    };
+    Some(ConfigReload { action, changed, started: false, note: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gpu_layers_change_triggers_a_full_reload() {
+        let config = DbSamplerConfig::default();
+        let reload = reload_for_change(&config, &config, false, Some(32), Some(16)).expect("reload");
+        assert_eq!(reload.action, LoadOutcome::FullReload);
+        assert_eq!(reload.changed, ["gpu_layers"]);
+
+        // The same layer count as loaded is not a change
+        assert!(reload_for_change(&config, &config, false, Some(32), Some(32)).is_none());
+
+        let moved = DbSamplerConfig { model_path: Some("/models/b.gguf".to_string()), ..config.clone() };
+        let reload = reload_for_change(&config, &moved, true, Some(32), None).expect("reload");
+        assert_eq!((reload.action, reload.changed), (LoadOutcome::FullReload, vec!["model_path"]));
+
+        // Saving the path of the model already loaded is not a switch
+        assert!(reload_for_change(&config, &moved, false, Some(32), None).is_none());
+    }
+
+    #[test]
+    fn test_context_size_change_only_rebuilds_the_context() {
+        let config = DbSamplerConfig::default();
+        let bigger = DbSamplerConfig { context_size: Some(32768), ..config.clone() };
+        let reload = reload_for_change(&config, &bigger, false, Some(32), None).expect("reload");
+        assert_eq!((reload.action, reload.changed), (LoadOutcome::ContextRebuild, vec!["context_size"]));
+    }
+
+    #[test]
+    fn test_temperature_change_needs_no_reload() {
+        let config = DbSamplerConfig::default();
+        let warmer = DbSamplerConfig { temperature: config.temperature + 0.5, ..config.clone() };
+        assert!(reload_for_change(&config, &warmer, false, Some(32), None).is_none());
+    }
+}
@@ -6,17 +6,19 @@ use std::collections::HashMap;
 use std::convert::Infallible;
 
 use llama_chat_db::SharedDatabase;
-use llama_chat_types::models::{ChatMessage, ConversationContentResponse, ConversationFile, ConversationsResponse, MessagePart, ToolTiming};
-use crate::response_helpers::{json_error, json_raw, serialize_with_fallback};
+use llama_chat_types::models::{ChatMessage, ConversationContentResponse, ConversationFile, ConversationPageResponse, ConversationSummaryEntry, ConversationsResponse, MessagePart, ToolTiming};
+use crate::response_helpers::{api_error, json_error, json_raw, serialize_with_fallback, ApiError};
 use crate::worker_pool::{resolve_bridge_for_conversation, WorkerPool};
 
 #[path = "conversation/management.rs"]
 mod management;
 pub use management::{
-    handle_batch_delete_conversations, handle_compact_conversation,
-    handle_conversation_token_analysis, handle_create_conversation,
-    handle_delete_conversation, handle_delete_summary, handle_export_conversation,
-    handle_rename_conversation, handle_truncate_conversation, handle_update_summary,
+    handle_batch_delete_conversations, handle_clear_conversations, handle_compact_conversation,
+    handle_conversation_token_analysis, handle_create_conversation, handle_delete_conversation,
+    handle_delete_summary, handle_export_conversation, handle_find_in_conversation,
+    handle_get_flattened_conversation, handle_get_tool_calls, handle_get_transcript,
+    handle_import_conversation, handle_rename_conversation, handle_truncate_conversation,
+    handle_update_summary,
 };
 
 /// Load tool timing events from the event log for a conversation.
@@ -45,11 +47,12 @@ pub async fn handle_get_conversation(
     let filename = &path[18..]; // Remove "/api/conversation/"
 
     let conversation_id = filename;
-    let records_result = db.get_messages(filename);
+    let records_result = db.get_messages_if_exists(filename);
 
     // Load messages directly from DB to preserve timing metadata
     match records_result {
-        Ok(records) => {
+        Ok(None) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
+        Ok(Some(records)) => {
             // Rebuild messages: merge consecutive assistant tool_call + tool results
             // into a single assistant message with <tool_call>/<tool_response> tags
             // so the frontend renders the same widget UI as during live streaming.
@@ -100,6 +103,10 @@ pub async fn handle_get_conversation(
                             sequence_order: Some(rec.sequence_order),
                             parts,
                             title: None,
+                            token_count: rec.token_count,
+                            created_at_millis: rec.created_at_millis,
+                            reasoning: rec.reasoning.clone(),
+                            interrupted: rec.interrupted,
                         });
                         msg_idx += 1;
                     }
@@ -124,6 +131,10 @@ pub async fn handle_get_conversation(
                         sequence_order: Some(rec.sequence_order),
                         parts,
                         title: rec.title.clone(),
+                        token_count: rec.token_count,
+                        created_at_millis: rec.created_at_millis,
+                        reasoning: rec.reasoning.clone(),
+                        interrupted: rec.interrupted,
                     });
                     msg_idx += 1;
                     i += 1;
@@ -156,7 +167,7 @@ pub async fn handle_get_conversation(
 
             Ok(json_raw(StatusCode::OK, response_json))
         }
-        Err(_) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
+        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
     }
 }
 
@@ -173,6 +184,11 @@ pub async fn handle_get_conversations(
     #[cfg(feature = "mock")] _llama_state: (),
     db: SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
+    // `?limit=` switches to the paginated summary listing
+    if let Some(limit) = crate::request_parsing::get_query_param(req.uri(), "limit") {
+        return Ok(list_conversation_page(req, &limit, &db));
+    }
+
     // Parse optional search query from URL
     let query = crate::request_parsing::get_query_param(req.uri(), "q")
         .map(|v| v.to_lowercase());
@@ -181,7 +197,7 @@ pub async fn handle_get_conversations(
     let mut conversations = Vec::new();
     let mut seen_ids = std::collections::HashSet::new();
 
-    match db.list_conversations() {
+    match db.list_all_conversations() {
         Ok(records) => {
             for record in records {
                 // Deduplicate conversation IDs
@@ -192,11 +208,9 @@ pub async fn handle_get_conversations(
                     continue;
                 }
 
-                // Extract timestamp from conversation ID (chat_YYYY-MM-DD-HH-mm-ss-SSS)
-                let timestamp_part = clean_id
-                    .strip_prefix("chat_")
-                    .unwrap_or(&clean_id)
-                    .to_string();
+                // Extract timestamp from conversation ID (chat_YYYY-MM-DD-HH-mm-ss-SSS-…)
+                let timestamp_part =
+                    llama_chat_db::conversation_id_timestamp(&clean_id).to_string();
 
                 // Use DB title for display_name when available
                 let title = db.get_conversation_title(&clean_id).ok().flatten();
@@ -236,6 +250,47 @@ pub async fn handle_get_conversations(
     Ok(json_raw(StatusCode::OK, response_json))
 }
 
+/// Default and maximum page sizes for `GET /api/conversations?limit=&offset=`.
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+/// GET /api/conversations?limit=&offset= — one page of summaries, most recent first.
+fn list_conversation_page(req: &Request<Body>, limit: &str, db: &SharedDatabase) -> Response<Body> {
+    let limit = limit
+        .parse::<usize>()
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = crate::request_parsing::get_query_param(req.uri(), "offset")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    match db.list_conversations(limit, offset) {
+        Ok(summaries) => {
+            let conversations = summaries
+                .into_iter()
+                .map(|s| ConversationSummaryEntry {
+                    id: s.id,
+                    title: s.title,
+                    last_message_preview: s.last_message_preview,
+                    message_count: s.message_count,
+                    updated_at: s.updated_at,
+                    provider_id: s.provider_id,
+                    worker_id: s.worker_id,
+                })
+                .collect();
+            let response = ConversationPageResponse { conversations, limit, offset };
+            json_raw(
+                StatusCode::OK,
+                serialize_with_fallback(&response, r#"{"conversations":[]}"#),
+            )
+        }
+        Err(e) => {
+            sys_error!("Failed to list conversation page: {}", e);
+            json_error(StatusCode::INTERNAL_SERVER_ERROR, &e)
+        }
+    }
+}
+
 /// GET /api/conversations/:id/events — return in-memory event log for a conversation
 pub async fn handle_get_conversation_events(
     path: &str,
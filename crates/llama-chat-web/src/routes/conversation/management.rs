@@ -6,6 +6,12 @@ use serde::Deserialize;
 struct CreateConversationRequest {
     title: Option<String>,
     worker_id: Option<String>,
+    /// Per-conversation system prompt; overrides the agent / global prompt when set.
+    #[serde(default)]
+    system_prompt: Option<String>,
+    /// Per-conversation sampler settings; win over the agent / global config.
+    #[serde(default)]
+    sampler: Option<llama_chat_types::GenerationOverrides>,
 }
 
 pub async fn handle_truncate_conversation(
@@ -63,19 +69,63 @@ pub async fn handle_truncate_conversation(
     }
 }
 
+/// Optional body of the compact route; without `turns` everything is compacted.
+#[derive(Deserialize, Default)]
+struct CompactConversationRequest {
+    turns: Option<usize>,
+}
+
 pub async fn handle_compact_conversation(
+    req: hyper::Request<Body>,
     conversation_id: &str,
     pool: WorkerPool,
     db: SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
+    let body = match hyper::body::to_bytes(req.into_body()).await {
+        Ok(b) => b,
+        Err(_) => return Ok(api_error(ApiError::INVALID_REQUEST, "Failed to read body")),
+    };
+    let request: CompactConversationRequest = if body.is_empty() {
+        CompactConversationRequest::default()
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(r) => r,
+            Err(_) => return Ok(api_error(ApiError::INVALID_JSON, "Invalid JSON")),
+        }
+    };
+    if request.turns == Some(0) {
+        return Ok(api_error(ApiError::INVALID_REQUEST, "turns must be at least 1"));
+    }
+    if let Some(turns) = request.turns {
+        match db.turn_compaction_cutoff(conversation_id, turns) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Ok(api_error(
+                    ApiError::INVALID_REQUEST,
+                    "Not enough turns to compact; the latest turn stays live",
+                ))
+            }
+            Err(e) => return Ok(api_error(ApiError::INTERNAL_ERROR, e)),
+        }
+    }
+
     let bridge = match resolve_bridge_for_conversation(&pool, &db, Some(conversation_id)).await {
         Ok(bridge) => bridge,
-        Err(e) => return Ok(json_error(StatusCode::SERVICE_UNAVAILABLE, &e)),
+        Err(e) => return Ok(api_error(ApiError::WORKER_UNAVAILABLE, e)),
     };
 
-    match bridge.compact_conversation(conversation_id).await {
-        Ok(()) => Ok(json_raw(StatusCode::OK, r#"{"ok":true}"#.to_string())),
-        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
+    match bridge.compact_conversation(conversation_id, request.turns).await {
+        Ok(()) => {
+            let messages = db.get_messages(conversation_id).unwrap_or_default();
+            let archived = messages.iter().filter(|m| m.compacted).count();
+            let body = serde_json::json!({
+                "ok": true,
+                "live_messages": messages.len() - archived,
+                "archived_messages": archived,
+            });
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Err(e) => Ok(api_error(ApiError::INTERNAL_ERROR, e)),
     }
 }
 
@@ -112,6 +162,23 @@ pub async fn handle_delete_summary(
     }
 }
 
+/// Longest title accepted by the rename route, in characters.
+const MAX_TITLE_CHARS: usize = 200;
+
+/// Trim a user-supplied title and check it is non-empty and within the cap.
+fn validate_title(raw: &str) -> Result<&str, String> {
+    let title = raw.trim();
+    if title.is_empty() {
+        return Err("title must not be empty".to_string());
+    }
+    if title.chars().count() > MAX_TITLE_CHARS {
+        return Err(format!(
+            "title must be at most {MAX_TITLE_CHARS} characters"
+        ));
+    }
+    Ok(title)
+}
+
 pub async fn handle_rename_conversation(
     req: Request<Body>,
     conversation_id: &str,
@@ -126,21 +193,20 @@ pub async fn handle_rename_conversation(
         Err(_) => return Ok(json_error(StatusCode::BAD_REQUEST, "Invalid JSON")),
     };
     let title = match json.get("title").and_then(|t| t.as_str()) {
-        Some(t) if !t.is_empty() => t,
-        _ => return Ok(json_error(StatusCode::BAD_REQUEST, "title is required")),
+        Some(t) => match validate_title(t) {
+            Ok(title) => title,
+            Err(e) => return Ok(json_error(StatusCode::BAD_REQUEST, &e)),
+        },
+        None => return Ok(json_error(StatusCode::BAD_REQUEST, "title is required")),
     };
 
-    let conn = db.connection();
-    match conn.execute(
-        "UPDATE conversations SET title = ?1, updated_at = ?2 WHERE id = ?3",
-        rusqlite::params![title, chrono::Utc::now().timestamp(), conversation_id],
-    ) {
-        Ok(0) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
-        Ok(_) => Ok(json_raw(
+    match db.set_conversation_title(conversation_id, title) {
+        Ok(false) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
+        Ok(true) => Ok(json_raw(
             StatusCode::OK,
             serde_json::to_string(&json!({"success": true, "title": title})).unwrap(),
         )),
-        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed: {e}"))),
+        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
     }
 }
 
@@ -154,6 +220,8 @@ pub async fn handle_create_conversation(
         Err(_) => CreateConversationRequest {
             title: None,
             worker_id: None,
+            system_prompt: None,
+            sampler: None,
         },
     };
     let title = create.title.as_deref().unwrap_or("New conversation");
@@ -170,32 +238,121 @@ pub async fn handle_create_conversation(
         }
     }
 
-    let conv_id = format!("chat_{}", chrono::Local::now().format("%Y-%m-%d-%H-%M-%S-%3f"));
+    let conv_id = llama_chat_db::generate_conversation_id();
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
 
+    let system_prompt = create
+        .system_prompt
+        .as_deref()
+        .map(str::trim)
+        .filter(|sp| !sp.is_empty());
+
+    let sampler = create
+        .sampler
+        .as_ref()
+        .map(llama_chat_types::GenerationOverrides::sampler_only)
+        .filter(|s| *s != llama_chat_types::GenerationOverrides::default());
+    let overrides_json = sampler.as_ref().and_then(|s| serde_json::to_string(s).ok());
+
     let conn = db.connection();
     match conn.execute(
-        "INSERT INTO conversations (id, title, created_at, updated_at, worker_id) VALUES (?1, ?2, ?3, ?3, ?4)",
-        rusqlite::params![conv_id, title, now, normalized_worker_id],
+        "INSERT INTO conversations (id, title, created_at, updated_at, worker_id, system_prompt, overrides) VALUES (?1, ?2, ?3, ?3, ?4, ?5, ?6)",
+        rusqlite::params![conv_id, title, now, normalized_worker_id, system_prompt, overrides_json],
     ) {
         Ok(_) => Ok(json_raw(
             StatusCode::OK,
-            serde_json::to_string(&json!({"id": conv_id, "title": title, "worker_id": create.worker_id})).unwrap(),
+            serde_json::to_string(&json!({
+                "id": conv_id,
+                "title": title,
+                "worker_id": create.worker_id,
+                "system_prompt": system_prompt,
+                "sampler": sampler,
+            }))
+            .unwrap(),
         )),
         Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed: {e}"))),
     }
 }
 
+#[derive(Deserialize)]
+struct ImportConversationRequest {
+    /// Legacy CLI log, e.g. `assets/conversations/chat_2025-01-01-12-00-00-000.txt`
+    path: String,
+}
+
+/// Directory the legacy CLI wrote its logs to; imports can't name files elsewhere.
+const IMPORT_ROOT: &str = "assets/conversations";
+
+/// Canonical path of an importable `.txt` log inside `root`. The path is
+/// resolved first, so `../` segments and symlinks can't leave `root`.
+fn resolve_import_path(
+    path: &str,
+    root: &std::path::Path,
+) -> Result<std::path::PathBuf, (StatusCode, &'static str)> {
+    if !path.ends_with(".txt") {
+        return Err((StatusCode::BAD_REQUEST, "Only legacy .txt conversation logs can be imported"));
+    }
+    let not_found = (StatusCode::NOT_FOUND, "File not found");
+    let root = root.canonicalize().map_err(|_| not_found)?;
+    let file = std::path::Path::new(path).canonicalize().map_err(|_| not_found)?;
+    if !file.starts_with(&root) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Only logs under assets/conversations can be imported",
+        ));
+    }
+    if !file.is_file() {
+        return Err(not_found);
+    }
+    Ok(file)
+}
+
+/// POST /api/conversations/import — import a legacy CLI `chat_*.txt` log as a new conversation.
+pub async fn handle_import_conversation(
+    req: Request<Body>,
+    db: SharedDatabase,
+) -> Result<Response<Body>, Infallible> {
+    let import: ImportConversationRequest =
+        match crate::request_parsing::parse_json_body(req.into_body()).await {
+            Ok(body) => body,
+            Err(_) => return Ok(json_error(StatusCode::BAD_REQUEST, "path is required")),
+        };
+    let file = match resolve_import_path(import.path.trim(), std::path::Path::new(IMPORT_ROOT)) {
+        Ok(file) => file,
+        Err((status, message)) => return Ok(json_error(status, message)),
+    };
+    let path = file.to_string_lossy();
+
+    match llama_chat_db::logger::ConversationLogger::import_from_text(db.clone(), &path) {
+        Ok(conv_id) => {
+            sys_info!("Imported legacy conversation {} as {}", path, conv_id);
+            let title = db.get_conversation_title(&conv_id).ok().flatten();
+            Ok(json_raw(
+                StatusCode::OK,
+                serde_json::to_string(&json!({"id": conv_id, "title": title})).unwrap(),
+            ))
+        }
+        Err(e) => {
+            sys_error!("Failed to import conversation {}: {}", path, e);
+            Ok(json_error(StatusCode::BAD_REQUEST, &e))
+        }
+    }
+}
+
 pub async fn handle_delete_conversation(
     path: &str,
     #[cfg(not(feature = "mock"))] _llama_state: llama_chat_worker::worker::worker_bridge::SharedWorkerBridge,
     #[cfg(feature = "mock")] _llama_state: (),
     db: SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
-    let filename = &path["/api/conversations/".len()..];
+    // Accepts both /api/conversations/{id} and /api/conversation/{id}
+    let filename = path
+        .strip_prefix("/api/conversations/")
+        .or_else(|| path.strip_prefix("/api/conversation/"))
+        .unwrap_or_default();
     if filename.contains("..") || filename.contains("/") || filename.contains("\\") {
         return Ok(json_error(StatusCode::BAD_REQUEST, "Invalid filename"));
     }
@@ -208,7 +365,8 @@ pub async fn handle_delete_conversation(
 
     let conversation_id = filename;
     match db.delete_conversation(conversation_id) {
-        Ok(_) => {
+        Ok(false) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
+        Ok(true) => {
             sys_info!("Deleted conversation: {}", conversation_id);
             let images_dir = std::path::PathBuf::from("assets/images").join(conversation_id);
             if images_dir.exists() {
@@ -284,6 +442,217 @@ pub async fn handle_export_conversation(
     }
 }
 
+/// Full ordered transcript for debugging: every stored message, SYSTEM notices
+/// and compacted history included, split into text/command/tool blocks.
+pub async fn handle_get_transcript(
+    conversation_id: &str,
+    db: SharedDatabase,
+) -> Result<Response<Body>, Infallible> {
+    match db.get_transcript(conversation_id) {
+        Ok(Some(entries)) => {
+            let body = json!({ "conversation_id": conversation_id, "entries": entries });
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Ok(None) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
+        Err(e) => {
+            sys_error!("Failed to load transcript: {}", e);
+            Ok(json_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to load transcript",
+            ))
+        }
+    }
+}
+
+/// Every tool call executed in the conversation with its arguments, raw result,
+/// duration and outcome, in execution order.
+pub async fn handle_get_tool_calls(
+    conversation_id: &str,
+    db: SharedDatabase,
+) -> Result<Response<Body>, Infallible> {
+    match db.get_tool_calls(conversation_id) {
+        Ok(Some(calls)) => {
+            let body = json!({ "conversation_id": conversation_id, "tool_calls": calls });
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Ok(None) => Ok(api_error(ApiError::NOT_FOUND, "Conversation not found")),
+        Err(e) => {
+            sys_error!("Failed to load tool calls: {}", e);
+            Ok(api_error(ApiError::INTERNAL_ERROR, "Failed to load tool calls"))
+        }
+    }
+}
+
+/// Render a stored conversation through `apply_model_chat_template`, or `None`
+/// if the conversation doesn't exist.
+fn flatten_conversation(
+    db: &llama_chat_db::Database,
+    conversation_id: &str,
+    template: Option<&str>,
+) -> Result<Option<String>, String> {
+    if !db.conversation_exists(conversation_id)? {
+        return Ok(None);
+    }
+    let conversation = db.get_conversation_as_text(conversation_id)?;
+    llama_chat_engine::templates::apply_model_chat_template(&conversation, template).map(Some)
+}
+
+/// GET /api/conversation/{id}/flatten?template= — the conversation as one raw
+/// prompt string, rendered with `template` or the conversation's loaded model's.
+pub async fn handle_get_flattened_conversation(
+    req: &Request<Body>,
+    conversation_id: &str,
+    pool: WorkerPool,
+    db: SharedDatabase,
+) -> Result<Response<Body>, Infallible> {
+    use llama_chat_engine::templates::{is_supported_template, SUPPORTED_TEMPLATES};
+
+    let template = match crate::request_parsing::get_query_param(req.uri(), "template") {
+        Some(t) if is_supported_template(&t) => t,
+        Some(t) => {
+            let message = format!(
+                "Unknown template '{t}'. Supported: {}",
+                SUPPORTED_TEMPLATES.join(", ")
+            );
+            return Ok(json_error(StatusCode::BAD_REQUEST, &message));
+        }
+        None => {
+            let model =
+                match resolve_bridge_for_conversation(&pool, &db, Some(conversation_id)).await {
+                    Ok(bridge) => bridge.model_status().await,
+                    Err(_) => None,
+                };
+            match model.and_then(|m| m.template_override.or(m.chat_template_type)) {
+                Some(t) => t,
+                None => {
+                    return Ok(json_error(
+                        StatusCode::BAD_REQUEST,
+                        "template is required when no model is loaded",
+                    ))
+                }
+            }
+        }
+    };
+
+    match flatten_conversation(&db, conversation_id, Some(&template)) {
+        Ok(Some(prompt)) => {
+            let body = json!({
+                "conversation_id": conversation_id,
+                "template": template,
+                "prompt": prompt,
+            });
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Ok(None) => Ok(json_error(StatusCode::NOT_FOUND, "Conversation not found")),
+        Err(e) => {
+            sys_error!("Failed to flatten conversation: {}", e);
+            Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &e))
+        }
+    }
+}
+
+/// Characters of context kept on each side of the first match in a find snippet.
+const FIND_SNIPPET_CONTEXT: usize = 60;
+
+/// Matcher for a find query: a case-insensitive literal substring, or a
+/// case-insensitive regex when `regex` is set.
+fn find_matcher(query: &str, regex: bool) -> Result<regex::Regex, String> {
+    let pattern = if regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    regex::RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("Invalid regex: {e}"))
+}
+
+/// Scan the visible (non-compacted) messages for `matcher`. Each hit carries the
+/// message's index among those messages, a snippet around the first match and
+/// that match's char range within the snippet, for highlighting.
+fn find_in_messages(
+    messages: &[llama_chat_db::conversation::MessageRecord],
+    matcher: &regex::Regex,
+) -> Vec<Value> {
+    messages
+        .iter()
+        .filter(|m| !m.compacted)
+        .enumerate()
+        .filter_map(|(index, m)| {
+            let found: Vec<_> = matcher
+                .find_iter(&m.content)
+                .filter(|f| !f.is_empty())
+                .collect();
+            let first = found.first()?;
+            let before: Vec<char> = m.content[..first.start()].chars().collect();
+            let after: Vec<char> = m.content[first.end()..].chars().collect();
+            let lead = &before[before.len().saturating_sub(FIND_SNIPPET_CONTEXT)..];
+            let tail = &after[..after.len().min(FIND_SNIPPET_CONTEXT)];
+
+            let mut snippet = String::new();
+            if lead.len() < before.len() {
+                snippet.push('…');
+            }
+            snippet.extend(lead);
+            let match_start = snippet.chars().count();
+            snippet.push_str(first.as_str());
+            let match_end = snippet.chars().count();
+            snippet.extend(tail);
+            if tail.len() < after.len() {
+                snippet.push('…');
+            }
+            Some(json!({
+                "index": index,
+                "sequence_order": m.sequence_order,
+                "role": m.role,
+                "timestamp": m.timestamp,
+                "snippet": snippet,
+                "match_start": match_start,
+                "match_end": match_end,
+                "match_count": found.len(),
+            }))
+        })
+        .collect()
+}
+
+/// GET /api/conversation/{id}/find?q=&regex= — messages of one conversation
+/// matching `q` (case-insensitive), with highlightable snippets.
+pub async fn handle_find_in_conversation(
+    req: &Request<Body>,
+    conversation_id: &str,
+    db: SharedDatabase,
+) -> Result<Response<Body>, Infallible> {
+    let query = match crate::request_parsing::get_query_param(req.uri(), "q") {
+        Some(q) if !q.is_empty() => q,
+        _ => return Ok(api_error(ApiError::INVALID_REQUEST, "q is required")),
+    };
+    let regex = crate::request_parsing::get_query_param(req.uri(), "regex")
+        .is_some_and(|v| v == "true" || v == "1");
+    let matcher = match find_matcher(&query, regex) {
+        Ok(m) => m,
+        Err(e) => return Ok(api_error(ApiError::INVALID_REQUEST, e)),
+    };
+
+    match db.get_messages_if_exists(conversation_id) {
+        Ok(Some(messages)) => {
+            let body = json!({
+                "conversation_id": conversation_id,
+                "query": query,
+                "regex": regex,
+                "matches": find_in_messages(&messages, &matcher),
+            });
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Ok(None) => Ok(api_error(ApiError::NOT_FOUND, "Conversation not found")),
+        Err(e) => {
+            sys_error!("Failed to search conversation: {}", e);
+            Ok(api_error(ApiError::INTERNAL_ERROR, "Failed to search conversation"))
+        }
+    }
+}
+
 pub async fn handle_batch_delete_conversations(
     req: Request<Body>,
     db: SharedDatabase,
@@ -306,8 +675,8 @@ pub async fn handle_batch_delete_conversations(
     let mut failed = 0;
     for id in &ids {
         match db.delete_conversation(id) {
-            Ok(_) => deleted += 1,
-            Err(_) => failed += 1,
+            Ok(true) => deleted += 1,
+            Ok(false) | Err(_) => failed += 1,
         }
     }
 
@@ -317,6 +686,26 @@ pub async fn handle_batch_delete_conversations(
     ))
 }
 
+/// Env flag that enables `POST /api/conversations/clear` (off unless set to "1").
+const CLEAR_ALL_FLAG: &str = "LLAMA_CHAT_ALLOW_CLEAR_ALL";
+
+/// POST /api/conversations/clear — delete every conversation (test/demo reset).
+pub async fn handle_clear_conversations(db: SharedDatabase) -> Result<Response<Body>, Infallible> {
+    if std::env::var(CLEAR_ALL_FLAG).unwrap_or_default() != "1" {
+        return Ok(json_error(
+            StatusCode::FORBIDDEN,
+            &format!("Clearing all conversations is disabled (set {CLEAR_ALL_FLAG}=1 to enable)"),
+        ));
+    }
+    match db.clear_all_conversations() {
+        Ok(removed) => Ok(json_raw(
+            StatusCode::OK,
+            json!({"success": true, "deleted": removed}).to_string(),
+        )),
+        Err(e) => Ok(json_error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
+    }
+}
+
 pub async fn handle_conversation_token_analysis(
     conversation_id: &str,
     db: SharedDatabase,
@@ -402,3 +791,317 @@ pub async fn handle_conversation_token_analysis(
         serde_json::to_string(&analysis).unwrap(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llama_chat_db::Database;
+    use std::sync::Arc;
+
+    async fn rename(db: &SharedDatabase, id: &str, body: Value) -> (StatusCode, Value) {
+        let req = Request::builder()
+            .method("PATCH")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = handle_rename_conversation(req, id, db.clone())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[test]
+    fn test_import_rejects_paths_outside_the_conversations_dir() {
+        let base = std::env::temp_dir().join(format!("llama_chat_import_{}", std::process::id()));
+        let root = base.join("assets/conversations");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("chat_a.txt"), "USER:\nhi\n").unwrap();
+        std::fs::write(base.join("secret.txt"), "not a log").unwrap();
+
+        let inside = root.join("chat_a.txt");
+        let resolved = resolve_import_path(inside.to_str().unwrap(), &root).unwrap();
+        assert_eq!(resolved, inside.canonicalize().unwrap());
+
+        let escape = root.join("../../secret.txt");
+        let err = resolve_import_path(escape.to_str().unwrap(), &root).unwrap_err();
+        assert_eq!(err.0, StatusCode::BAD_REQUEST);
+        let missing = root.join("chat_missing.txt");
+        assert_eq!(resolve_import_path(missing.to_str().unwrap(), &root).unwrap_err().0, StatusCode::NOT_FOUND);
+        assert_eq!(resolve_import_path("/etc/passwd", &root).unwrap_err().0, StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_created_conversation_sampler_wins_over_model_recommendation() {
+        use llama_chat_worker::worker::process_manager::ProcessManager;
+        use llama_chat_worker::worker::worker_bridge::WorkerBridge;
+
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        let child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("cat > /dev/null")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("spawn idle worker");
+        let bridge = Arc::new(WorkerBridge::new(
+            Arc::new(ProcessManager::from_child(child, ":memory:")),
+            db.clone(),
+        ));
+        let pool = WorkerPool::new(bridge, ":memory:", db.clone());
+
+        // Both values equal the app defaults
+        let body = json!({"title": "Pinned", "sampler": {"temperature": 0.7, "top_k": 20}});
+        let req = Request::builder()
+            .method("POST")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = handle_create_conversation(req, pool.clone(), db.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let created: Value = serde_json::from_slice(&bytes).unwrap();
+        let id = created["id"].as_str().unwrap();
+
+        let recommended = llama_chat_types::models::RecommendedSampling {
+            temperature: Some(0.3),
+            top_p: Some(0.8),
+            top_k: Some(64),
+            ..Default::default()
+        };
+        let config = llama_chat_config::resolve_generation_config(
+            &db,
+            id,
+            Some(&recommended),
+            &llama_chat_types::GenerationOverrides::default(),
+        );
+        assert_eq!((config.temperature, config.top_k), (0.7, 20));
+        // Fields the conversation left unset still take the recommendation
+        assert_eq!(config.top_p, 0.8);
+        pool.shutdown_all();
+    }
+
+    #[tokio::test]
+    async fn test_rename_conversation() {
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+        let mut events = db.subscribe_conversation_events();
+
+        let (status, body) = rename(&db, &id, json!({"title": "  Trip planning "})).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["title"], "Trip planning");
+        assert_eq!(
+            db.get_conversation_title(&id).unwrap().as_deref(),
+            Some("Trip planning")
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            llama_chat_db::ConversationEvent::Renamed {
+                conversation_id: id,
+                title: "Trip planning".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_rejects_empty_and_overlong_titles() {
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+
+        let (status, _) = rename(&db, &id, json!({"title": "   "})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let long = "a".repeat(MAX_TITLE_CHARS + 1);
+        let (status, _) = rename(&db, &id, json!({"title": long})).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(db.get_conversation_title(&id).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_rename_unknown_conversation_is_404() {
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        let (status, _) = rename(&db, "chat_missing", json!({"title": "x"})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_transcript_endpoint_reconstructs_blocks_in_order() {
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "run ls", 10, 0).unwrap();
+        db.insert_message(&id, "assistant", "[COMMAND: ls]\na.txt\n\nOne file.", 11, 1)
+            .unwrap();
+        db.insert_message(&id, "system", "[WARNING] Tool loop detected", 12, 2)
+            .unwrap();
+
+        let response = handle_get_transcript(&id, db.clone()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        let entries = body["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1]["role"], "assistant");
+        assert_eq!(entries[1]["timestamp"], 11);
+        assert_eq!(
+            entries[1]["blocks"],
+            json!([
+                {"type": "command", "command": "ls", "output": "a.txt"},
+                {"type": "text", "text": "One file."},
+            ])
+        );
+        assert_eq!(entries[2]["role"], "system");
+        assert_eq!(
+            entries[2]["blocks"][0]["text"],
+            "[WARNING] Tool loop detected"
+        );
+
+        let missing = handle_get_transcript("chat_missing", db).await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn find(db: &SharedDatabase, id: &str, query: &str) -> (StatusCode, Value) {
+        let req = Request::builder()
+            .uri(format!("/api/conversation/{id}/find?{query}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = handle_find_in_conversation(&req, id, db.clone())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    fn find_db() -> (SharedDatabase, String) {
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "Where is the Config file?", 10, 0)
+            .unwrap();
+        db.insert_message(&id, "assistant", "See ~/.config and the config cache.", 11, 1)
+            .unwrap();
+        db.insert_message(&id, "user", "Thanks, error 404 fixed.", 12, 2)
+            .unwrap();
+        (db, id)
+    }
+
+    #[tokio::test]
+    async fn test_find_matches_substring_case_insensitively() {
+        let (db, id) = find_db();
+        let (status, body) = find(&db, &id, "q=CONFIG").await;
+        assert_eq!(status, StatusCode::OK);
+        let matches = body["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0]["index"], 0);
+        assert_eq!(matches[0]["snippet"], "Where is the Config file?");
+        assert_eq!(matches[0]["match_start"], 13);
+        assert_eq!(matches[0]["match_end"], 19);
+        assert_eq!(matches[1]["index"], 1);
+        assert_eq!(matches[1]["role"], "assistant");
+        assert_eq!(matches[1]["match_count"], 2);
+
+        let (status, body) = find(&db, &id, "q=banana").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["matches"], json!([]));
+
+        let (status, _) = find(&db, &id, "q=").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = find(&db, "chat_missing", "q=config").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_find_regex_flag() {
+        let (db, id) = find_db();
+        let (status, body) = find(&db, &id, "q=error%20%5Cd%2B&regex=true").await;
+        assert_eq!(status, StatusCode::OK);
+        let matches = body["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0]["index"], 2);
+        let snippet = matches[0]["snippet"].as_str().unwrap();
+        let highlighted: String = snippet
+            .chars()
+            .skip(matches[0]["match_start"].as_u64().unwrap() as usize)
+            .take(9)
+            .collect();
+        assert_eq!(highlighted, "error 404");
+
+        // Without the flag the pattern is a literal.
+        let (_, body) = find(&db, &id, "q=error%20%5Cd%2B").await;
+        assert_eq!(body["matches"], json!([]));
+        let (status, _) = find(&db, &id, "q=%28unclosed&regex=true").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_find_searches_compressed_messages() {
+        let db: SharedDatabase = Arc::new(Database::new(":memory:").unwrap());
+        db.set_message_compression(true);
+        let id = db.create_conversation().unwrap();
+        let big = format!("{}the needle{}", "log line\n".repeat(600), "\nend".repeat(200));
+        db.insert_message(&id, "assistant", &big, 10, 0).unwrap();
+
+        let (status, body) = find(&db, &id, "q=needle").await;
+        assert_eq!(status, StatusCode::OK);
+        let matches = body["matches"].as_array().unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0]["snippet"].as_str().unwrap().contains("the needle"));
+    }
+
+    #[test]
+    fn test_find_snippet_is_trimmed_around_the_match() {
+        let matcher = find_matcher("needle", false).unwrap();
+        let db = Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        let content = format!("{}needle{}", "é".repeat(100), "z".repeat(100));
+        db.insert_message(&id, "user", &content, 10, 0).unwrap();
+
+        let hits = find_in_messages(&db.get_messages(&id).unwrap(), &matcher);
+        let snippet = hits[0]["snippet"].as_str().unwrap();
+        assert_eq!(
+            snippet,
+            format!(
+                "…{}needle{}…",
+                "é".repeat(FIND_SNIPPET_CONTEXT),
+                "z".repeat(FIND_SNIPPET_CONTEXT)
+            )
+        );
+        assert_eq!(hits[0]["match_start"], FIND_SNIPPET_CONTEXT + 1);
+    }
+
+    #[test]
+    fn test_flatten_matches_template_output_per_type() {
+        use llama_chat_engine::templates::apply_model_chat_template;
+
+        let db = Database::new(":memory:").unwrap();
+        let id = db.create_conversation().unwrap();
+        db.insert_message(&id, "user", "What is 2 + 2?", 10, 0)
+            .unwrap();
+        db.insert_message(&id, "assistant", "4.", 11, 1).unwrap();
+        db.insert_message(&id, "user", "And times 3?", 12, 2)
+            .unwrap();
+        let conversation = db.get_conversation_as_text(&id).unwrap();
+
+        let chatml = flatten_conversation(&db, &id, Some("ChatML"))
+            .unwrap()
+            .unwrap();
+        let llama3 = flatten_conversation(&db, &id, Some("Llama3"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            chatml,
+            apply_model_chat_template(&conversation, Some("ChatML")).unwrap()
+        );
+        assert_eq!(
+            llama3,
+            apply_model_chat_template(&conversation, Some("Llama3")).unwrap()
+        );
+        assert_ne!(chatml, llama3);
+        assert!(chatml.contains("And times 3?"));
+
+        assert_eq!(
+            flatten_conversation(&db, "chat_missing", Some("ChatML")).unwrap(),
+            None
+        );
+    }
+}
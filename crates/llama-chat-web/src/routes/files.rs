@@ -7,8 +7,35 @@ use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
+use llama_chat_engine::io_timeout::with_models_io_timeout;
 use llama_chat_types::models::{BrowseFilesResponse, FileItem};
-use crate::response_helpers::{json_error, json_raw, json_response};
+use crate::response_helpers::{api_error, json_error, json_raw, json_response, ApiError};
+
+/// Entries of `dir`, read with blocking I/O.
+fn list_browse_entries(dir: &str) -> std::io::Result<Vec<FileItem>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if let (Some(name), Some(path_str)) =
+            (path.file_name().and_then(|n| n.to_str()), path.to_str())
+        {
+            let is_directory = path.is_dir();
+            let size = if !is_directory {
+                entry.metadata().ok().map(|m| m.len())
+            } else {
+                None
+            };
+
+            files.push(FileItem {
+                name: name.to_string(),
+                path: path_str.to_string(),
+                is_directory,
+                size,
+            });
+        }
+    }
+    Ok(files)
+}
 
 
 pub async fn handle_get_browse(
@@ -47,7 +74,6 @@ pub async fn handle_get_browse(
         return Ok(json_error(StatusCode::FORBIDDEN, "Path not allowed"));
     }
 
-    let mut files = Vec::new();
     let current_path = browse_path.to_string();
     // Show parent path unless we're at a root (drive root on Windows, /app on Docker)
     let is_root = browse_path == "/app/models"
@@ -63,35 +89,22 @@ pub async fn handle_get_browse(
         None
     };
 
-    let mut dir = match fs::read_dir(browse_path).await {
-        Ok(d) => d,
-        Err(e) => {
+    // A hung mount fails the request after the models I/O timeout
+    let dir = current_path.clone();
+    let listing = tokio::task::spawn_blocking(move || {
+        with_models_io_timeout(move || list_browse_entries(&dir))
+    })
+    .await
+    .unwrap_or_else(|_| Err("Models directory call failed".to_string()));
+    let mut files = match listing {
+        Ok(Ok(files)) => files,
+        Ok(Err(e)) => {
             sys_error!("Failed to read directory {}: {}", browse_path, e);
             return Ok(json_error(StatusCode::NOT_FOUND, "Directory not found"));
         }
+        Err(e) => return Ok(api_error(ApiError::NOT_AVAILABLE, e)),
     };
 
-    while let Ok(Some(entry)) = dir.next_entry().await {
-        let path = entry.path();
-        if let (Some(name), Some(path_str)) =
-            (path.file_name().and_then(|n| n.to_str()), path.to_str())
-        {
-            let is_directory = path.is_dir();
-            let size = if !is_directory {
-                entry.metadata().await.ok().map(|m| m.len())
-            } else {
-                None
-            };
-
-            files.push(FileItem {
-                name: name.to_string(),
-                path: path_str.to_string(),
-                is_directory,
-                size,
-            });
-        }
-    }
-
     // Sort: directories first, then files, both alphabetically
     files.sort_by(|a, b| match (a.is_directory, b.is_directory) {
         (true, false) => std::cmp::Ordering::Less,
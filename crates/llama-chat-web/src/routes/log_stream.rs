@@ -0,0 +1,68 @@
+// Live backend log tail: GET /api/logs/stream?level=warn
+//
+// Sends the buffered recent lines, then new ones as they arrive, one SSE
+// `data:` event (a JSON LogLine) per line.
+
+use hyper::body::Bytes;
+use hyper::{Body, Request, Response};
+use std::convert::Infallible;
+use std::time::Duration;
+
+use llama_chat_types::log_buffer::{level_rank, LOG_BUFFER};
+
+use crate::request_parsing::get_query_param;
+use crate::response_helpers::sse_response;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// An idle stream still writes this often, so a closed client is noticed.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+pub async fn handle_get_log_stream(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let min_rank = get_query_param(req.uri(), "level")
+        .map(|level| level_rank(&level))
+        .unwrap_or(0);
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut next_seq = 0;
+        let mut idle = Duration::ZERO;
+        loop {
+            let lines = LOG_BUFFER.lines_since(next_seq);
+            if let Some(last) = lines.last() {
+                next_seq = last.seq + 1;
+            }
+            let mut sent = false;
+            for line in lines.iter().filter(|l| level_rank(&l.level) >= min_rank) {
+                let Ok(json) = serde_json::to_string(line) else {
+                    continue;
+                };
+                if sender
+                    .send_data(Bytes::from(format!("data: {json}\n\n")))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                sent = true;
+            }
+            idle = if sent {
+                Duration::ZERO
+            } else {
+                idle + POLL_INTERVAL
+            };
+            if idle >= KEEPALIVE_INTERVAL {
+                if sender
+                    .send_data(Bytes::from(": keepalive\n\n"))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                idle = Duration::ZERO;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+
+    Ok(sse_response(body))
+}
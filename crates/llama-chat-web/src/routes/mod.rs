@@ -6,6 +6,7 @@ pub mod conversation;
 pub mod download;
 pub mod files;
 pub mod frontend_logs;
+pub mod log_stream;
 pub mod health;
 pub mod hub;
 pub mod model;
@@ -5,21 +5,21 @@ use serde::Deserialize;
 use std::convert::Infallible;
 use std::fs;
 use std::io::BufReader;
-use tokio::task::spawn_blocking;
 use gguf_llms::{GgufHeader, GgufReader};
 
-use llama_chat_config::add_to_model_history;
+use llama_chat_config::{add_to_model_history, resolve_model_path, ModelTarget, MODEL_NOT_FOUND};
 use llama_chat_db::SharedDatabase;
 use llama_chat_engine::filename_patterns::{detect_architecture, detect_parameters, detect_quantization};
 use llama_chat_engine::gguf_utils::{
     value_to_display_string, MetadataExtractor,
 };
+use llama_chat_engine::io_timeout::MODELS_DIR_UNRESPONSIVE;
 #[cfg(not(feature = "mock"))]
 use llama_chat_engine::get_tool_tags_for_model;
 #[cfg(not(feature = "mock"))]
 use llama_chat_types::models::{ModelLoadRequest, ModelResponse};
 use crate::request_parsing::parse_json_body;
-use crate::response_helpers::{json_error, json_raw, serialize_with_fallback};
+use crate::response_helpers::{api_error, json_raw, serialize_with_fallback, ApiError};
 
 #[cfg(not(feature = "mock"))]
 use llama_chat_worker::worker::worker_bridge::SharedWorkerBridge;
@@ -28,18 +28,20 @@ use crate::worker_pool::WorkerPool;
 
 mod backend_install;
 mod helpers;
+mod info_cache;
 #[path = "model/lifecycle.rs"]
 mod lifecycle;
 
 #[allow(unused_imports)]
 pub use backend_install::handle_post_backends_install;
 pub use lifecycle::{
-    handle_get_backends, handle_get_model_history, handle_post_model_hard_unload,
-    handle_post_model_history, handle_post_model_load, handle_post_model_unload,
+    handle_get_backends, handle_get_model_history, handle_get_model_history_entries,
+    handle_post_model_hard_unload, handle_post_model_history, handle_post_model_load, handle_post_model_unload,
 };
+use info_cache::{model_info_cache, FileStamp};
 use helpers::{
-    default_model_status_json, detect_nvidia_gpu_hardware, enrich_model_info_from_gguf,
-    scan_directory_for_gguf_files, scan_for_mmproj_files,
+    default_model_status_json, detect_nvidia_gpu_hardware, enrich_model_info_from_gguf, models_io,
+    prompt_token_count, prompt_tokens_json, scan_for_mmproj_files,
 };
 
 // File size constants
@@ -60,6 +62,7 @@ const XLARGE_MODEL_LAYERS: u32 = 80; // 70B+
 
 pub async fn handle_get_model_info(
     req: Request<Body>,
+    db: SharedDatabase,
     #[cfg(not(feature = "mock"))] _bridge: SharedWorkerBridge,
     #[cfg(feature = "mock")] _bridge: (),
 ) -> Result<Response<Body>, Infallible> {
@@ -84,10 +87,7 @@ pub async fn handle_get_model_info(
 
     if model_path.is_empty() {
         sys_error!("[DEBUG] ERROR: No path parameter provided");
-        return Ok(json_error(
-            StatusCode::BAD_REQUEST,
-            "Model path is required",
-        ));
+        return Ok(api_error(ApiError::INVALID_REQUEST, "Model path is required"));
     }
 
     // URL decode the path properly
@@ -95,81 +95,81 @@ pub async fn handle_get_model_info(
         urlencoding::decode(model_path).unwrap_or(std::borrow::Cow::Borrowed(model_path));
     sys_debug!("[DEBUG] Decoded path: {}", decoded_path);
 
-    // Check if file exists
-    let path_obj = std::path::Path::new(&*decoded_path);
-    let exists = path_obj.exists();
-    sys_debug!("[DEBUG] File exists: {}", exists);
-    sys_debug!("[DEBUG] Path is file: {}", path_obj.is_file());
-    sys_debug!("[DEBUG] Path is dir: {}", path_obj.is_dir());
-
-    if !exists {
-        sys_error!(
-            "[DEBUG] ERROR: File does not exist at path: {}",
-            decoded_path
-        );
-        return Ok(json_error(StatusCode::NOT_FOUND, "Model file not found"));
-    }
-
-    // Check if path is a directory
-    if path_obj.is_dir() {
-        sys_debug!("[DEBUG] Path is a directory, scanning for .gguf files...");
-
-        // Find all .gguf files in the directory (off the async runtime)
-        let dir_path = path_obj.to_path_buf();
-        let gguf_files = spawn_blocking(move || scan_directory_for_gguf_files(&dir_path))
-            .await
-            .unwrap_or_else(|_| Vec::new());
-
-        let response_json = if gguf_files.is_empty() {
-            serde_json::json!({
-                "error": "This is a directory. No .gguf files found in this directory.",
-                "is_directory": true,
-                "suggestions": []
-            })
-        } else {
-            serde_json::json!({
-                "error": format!("This is a directory. Found {} .gguf file(s). Please select one:", gguf_files.len()),
+    // Follow symlinks and check the configured models directory (bounded, in case the
+    // models directory is on a hung mount)
+    let config = db.load_config();
+    let requested = decoded_path.to_string();
+    let resolved = models_io(move || {
+        resolve_model_path(
+            &requested,
+            config.models_directory.as_deref(),
+            config.allow_external_model_paths,
+        )
+    })
+    .await;
+    let resolved_path = match resolved {
+        Ok(Ok(ModelTarget::File(path))) => path,
+        Ok(Ok(ModelTarget::Directory { gguf_files, .. })) => {
+            let message = if gguf_files.is_empty() {
+                "This is a directory. No .gguf files found in this directory.".to_string()
+            } else {
+                format!("This is a directory. Found {} .gguf file(s). Please select one:", gguf_files.len())
+            };
+            // ApiError fields plus the directory's candidates
+            let response_json = serde_json::json!({
+                "code": ApiError::INVALID_REQUEST,
+                "message": message,
+                "error": message,
                 "is_directory": true,
                 "suggestions": gguf_files
-            })
-        };
+            });
 
-        sys_debug!(
-            "[DEBUG] Returning directory error with {} suggestions",
-            gguf_files.len()
-        );
-        return Ok(json_raw(StatusCode::BAD_REQUEST, response_json.to_string()));
-    }
-
-    // Check if file has .gguf extension
-    if let Some(ext) = path_obj.extension() {
-        if !ext.eq_ignore_ascii_case("gguf") {
-            sys_error!("[DEBUG] ERROR: File is not a .gguf file");
-            return Ok(json_error(
-                StatusCode::BAD_REQUEST,
-                "File must have .gguf extension",
-            ));
+            sys_debug!(
+                "[DEBUG] Returning directory error with {} suggestions",
+                gguf_files.len()
+            );
+            return Ok(json_raw(StatusCode::BAD_REQUEST, response_json.to_string()));
         }
-    } else {
-        sys_error!("[DEBUG] ERROR: File has no extension");
-        return Ok(json_error(
-            StatusCode::BAD_REQUEST,
-            "File must have .gguf extension",
-        ));
-    }
+        Ok(Err(e)) if e.starts_with(MODEL_NOT_FOUND) => {
+            sys_error!("[DEBUG] ERROR: {}", e);
+            return Ok(api_error(ApiError::MODEL_NOT_FOUND, MODEL_NOT_FOUND));
+        }
+        Ok(Err(e)) => {
+            sys_error!("[DEBUG] ERROR: {}", e);
+            return Ok(api_error(ApiError::INVALID_REQUEST, e));
+        }
+        Err(e) => return Ok(api_error(ApiError::NOT_AVAILABLE, e)),
+    };
+    sys_debug!("[DEBUG] Resolved model path: {}", resolved_path);
 
     // Extract basic model information
-    let metadata_path = decoded_path.to_string();
-    let file_metadata = match spawn_blocking(move || fs::metadata(&metadata_path)).await {
+    let metadata_path = resolved_path.clone();
+    let (file_metadata, stamp) = match models_io(move || {
+        let path = std::path::Path::new(&metadata_path);
+        Ok::<_, std::io::Error>((fs::metadata(path)?, FileStamp::read(path)?))
+    })
+    .await
+    {
         Ok(Ok(metadata)) => metadata,
-        Ok(Err(_)) | Err(_) => {
-            return Ok(json_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
+        Ok(Err(_)) => {
+            return Ok(api_error(
+                ApiError::INTERNAL_ERROR,
                 "Failed to read file metadata",
             ));
         }
+        Err(e) => return Ok(api_error(ApiError::NOT_AVAILABLE, e)),
     };
 
+    // ?refresh=1 re-parses even when the file looks unchanged
+    let refresh = crate::request_parsing::get_query_param(req.uri(), "refresh")
+        .is_some_and(|v| v == "1" || v == "true");
+    if !refresh {
+        if let Some(cached) = model_info_cache().get(&resolved_path, stamp) {
+            sys_debug!("[DEBUG] Model info served from cache: {}", decoded_path);
+            return Ok(json_raw(StatusCode::OK, cached.to_string()));
+        }
+    }
+
     let file_size_bytes = file_metadata.len();
     let file_size = if file_size_bytes >= BYTES_PER_GB {
         format!("{:.1} GB", file_size_bytes as f64 / BYTES_PER_GB as f64)
@@ -214,8 +214,8 @@ pub async fn handle_get_model_info(
     });
 
     // Try to parse GGUF metadata
-    let metadata_path = decoded_path.to_string();
-    let metadata_result = spawn_blocking(
+    let metadata_path = resolved_path.clone();
+    let metadata_result = models_io(
         move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
             let file = fs::File::open(&metadata_path)?;
             let mut reader = BufReader::new(file);
@@ -240,11 +240,18 @@ pub async fn handle_get_model_info(
         enrich_model_info_from_gguf(&mut model_info, &extractor);
     }
 
-    // Scan for mmproj companion files (vision/multimodal support)
+    // Scan for mmproj companion files (vision/multimodal support). They sit next
+    // to the path as given, not next to a symlink's target.
+    let requested_path = std::path::Path::new(&*decoded_path);
+    let path_obj = if requested_path.is_absolute() {
+        requested_path
+    } else {
+        std::path::Path::new(&resolved_path)
+    };
     let mmproj_path = path_obj.to_path_buf();
-    let mmproj_files = spawn_blocking(move || scan_for_mmproj_files(&mmproj_path))
+    let mmproj_files = models_io(move || scan_for_mmproj_files(&mmproj_path))
         .await
-        .unwrap_or_else(|_| Vec::new());
+        .unwrap_or_default();
 
     if !mmproj_files.is_empty() {
         let dir_str = path_obj
@@ -277,10 +284,223 @@ pub async fn handle_get_model_info(
         model_info["mmproj_files"] = serde_json::json!(mmproj_json);
     }
 
+    model_info_cache().insert(&resolved_path, stamp, model_info.clone());
     Ok(json_raw(StatusCode::OK, model_info.to_string()))
 }
 
+/// GET /api/model/validate?path=&context_size=&text= — pre-load compatibility and VRAM check.
+/// Reads GGUF metadata only; never loads the model. With `text`, also reports its
+/// `prompt_tokens` (an estimate unless the model is already loaded) and warns if
+/// it won't fit the context.
+pub async fn handle_get_model_validate(
+    req: Request<Body>,
+    db: SharedDatabase,
+    #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
+    #[cfg(feature = "mock")] _bridge: (),
+) -> Result<Response<Body>, Infallible> {
+    use llama_chat_engine::model_validation::{validate_model, ModelValidation, DEFAULT_VALIDATION_CONTEXT};
+
+    let model_path = match crate::request_parsing::get_query_param(req.uri(), "path") {
+        Some(p) if !p.is_empty() => p,
+        _ => return Ok(api_error(ApiError::INVALID_REQUEST, "Model path is required")),
+    };
+    let context_size = crate::request_parsing::get_query_param(req.uri(), "context_size")
+        .and_then(|v| v.parse::<u32>().ok());
+    let text = crate::request_parsing::get_query_param(req.uri(), "text").filter(|t| !t.is_empty());
+
+    // Same resolution as loading: a path the load route would reject fails here too
+    let config = db.load_config();
+    let validation = models_io(move || {
+        match resolve_model_path(
+            &model_path,
+            config.models_directory.as_deref(),
+            config.allow_external_model_paths,
+        )
+        .and_then(ModelTarget::into_file)
+        {
+            Ok(path) => {
+                let validation = validate_model(&path, context_size, None);
+                (Some(path), validation)
+            }
+            Err(e) => (None, ModelValidation::not_gguf(e)),
+        }
+    })
+    .await;
+
+    match validation {
+        Ok((path, mut result)) => {
+            let Some(text) = text else {
+                return Ok(json_raw(
+                    StatusCode::OK,
+                    serialize_with_fallback(&result, r#"{"is_gguf":false}"#),
+                ));
+            };
+            #[cfg(not(feature = "mock"))]
+            let (count, estimated) = prompt_token_count(text, path.as_deref(), &bridge).await;
+            #[cfg(feature = "mock")]
+            let (count, estimated) = prompt_token_count(text, path.as_deref(), ()).await;
+            let n_ctx = context_size
+                .unwrap_or(DEFAULT_VALIDATION_CONTEXT)
+                .min(result.context_length.unwrap_or(u32::MAX));
+            if count > n_ctx as usize {
+                let approx = if estimated { "~" } else { "" };
+                result.warnings.push(format!(
+                    "Prompt is {approx}{count} tokens, more than the {n_ctx}-token context"
+                ));
+            }
+            let mut body = serde_json::to_value(&result).unwrap_or_else(|_| serde_json::json!({ "is_gguf": false }));
+            body["prompt_tokens"] = prompt_tokens_json((count, estimated));
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Err(e) if e == MODELS_DIR_UNRESPONSIVE => Ok(api_error(ApiError::NOT_AVAILABLE, e)),
+        Err(_) => Ok(api_error(ApiError::INTERNAL_ERROR, "Model validation failed")),
+    }
+}
+
+/// GET /api/model/recommend?path=&text= — pre-load GPU layer and context recommendation
+/// from GGUF metadata and current free VRAM (CPU-only figures when there's no GPU).
+/// With `text`, also reports its `prompt_tokens` as the validate route does.
+/// The context starts from the saved `context_size` / `default_context_cap`.
+pub async fn handle_get_model_recommend(
+    req: Request<Body>,
+    db: SharedDatabase,
+    #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
+    #[cfg(feature = "mock")] _bridge: (),
+) -> Result<Response<Body>, Infallible> {
+    let model_path = match crate::request_parsing::get_query_param(req.uri(), "path") {
+        Some(p) if !p.is_empty() => p,
+        _ => return Ok(api_error(ApiError::INVALID_REQUEST, "Model path is required")),
+    };
+    let text = crate::request_parsing::get_query_param(req.uri(), "text").filter(|t| !t.is_empty());
+
+    let config = db.load_config();
+    let (context_size, context_cap) = (config.context_size, config.default_context_cap);
+    let path = model_path.clone();
+    match models_io(move || {
+        llama_chat_engine::vram_calculator::recommend(&path, context_size, context_cap)
+    })
+    .await
+    {
+        Ok(Ok(recommendation)) => {
+            let Some(text) = text else {
+                return Ok(json_raw(StatusCode::OK, serialize_with_fallback(&recommendation, "{}")));
+            };
+            #[cfg(not(feature = "mock"))]
+            let prompt_tokens = prompt_token_count(text, Some(&model_path), &bridge).await;
+            #[cfg(feature = "mock")]
+            let prompt_tokens = prompt_token_count(text, Some(&model_path), ()).await;
+            let mut body = serde_json::to_value(&recommendation).unwrap_or_else(|_| serde_json::json!({}));
+            body["prompt_tokens"] = prompt_tokens_json(prompt_tokens);
+            Ok(json_raw(StatusCode::OK, body.to_string()))
+        }
+        Ok(Err(e)) => Ok(api_error(ApiError::INVALID_REQUEST, e)),
+        Err(e) if e == MODELS_DIR_UNRESPONSIVE => Ok(api_error(ApiError::NOT_AVAILABLE, e)),
+        Err(_) => Ok(api_error(ApiError::INTERNAL_ERROR, "Model recommendation failed")),
+    }
+}
+
+/// GET /api/model/templates — template names accepted by the override route, plus the
+/// loaded model's detected template and current override.
+pub async fn handle_get_model_templates(
+    #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
+    #[cfg(feature = "mock")] _bridge: (),
+) -> Result<Response<Body>, Infallible> {
+    #[cfg(not(feature = "mock"))]
+    let (detected, active_override) = match bridge.model_status().await {
+        Some(meta) if meta.loaded => (meta.chat_template_type, meta.template_override),
+        _ => (None, None),
+    };
+    #[cfg(feature = "mock")]
+    let (detected, active_override): (Option<String>, Option<String>) = (None, None);
+
+    let body = serde_json::json!({
+        "templates": llama_chat_engine::templates::SUPPORTED_TEMPLATES,
+        "detected": detected,
+        "override": active_override,
+    });
+    Ok(json_raw(StatusCode::OK, body.to_string()))
+}
+
+/// POST /api/model/template-override — `{"template": "ChatML"}` forces a template for the
+/// loaded model; `{"template": null}` reverts to the detected one.
+pub async fn handle_post_model_template_override(
+    req: Request<Body>,
+    #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
+    #[cfg(feature = "mock")] _bridge: (),
+) -> Result<Response<Body>, Infallible> {
+    #[derive(Deserialize)]
+    struct TemplateOverrideRequest {
+        #[serde(default)]
+        template: Option<String>,
+    }
+
+    let request: TemplateOverrideRequest = match parse_json_body(req.into_body()).await {
+        Ok(req) => req,
+        Err(error_response) => return Ok(error_response),
+    };
+    let template = request.template.filter(|t| !t.trim().is_empty());
+    if let Some(ref name) = template {
+        if !llama_chat_engine::templates::is_supported_template(name) {
+            return Ok(api_error(
+                ApiError::INVALID_REQUEST,
+                format!("Unknown chat template '{name}'"),
+            ));
+        }
+    }
+
+    #[cfg(not(feature = "mock"))]
+    {
+        match bridge.set_template_override(template).await {
+            Ok(active) => Ok(json_raw(
+                StatusCode::OK,
+                serde_json::json!({ "success": true, "override": active }).to_string(),
+            )),
+            Err(e) if e == "No model loaded" => Ok(api_error(ApiError::NO_MODEL_LOADED, e)),
+            Err(e) => Ok(api_error(ApiError::CONFLICT, e)),
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    {
+        let _ = template;
+        Ok(api_error(
+            ApiError::NOT_AVAILABLE,
+            "Template override not available (mock feature enabled)",
+        ))
+    }
+}
+
+/// GET /api/model/selftest — run a few tokens through the loaded model.
+/// 200 `{ok: true, first_tokens, tokens_generated, gen_tok_per_sec}`, or 503 `{ok: false, error}`.
+pub async fn handle_get_model_selftest(
+    #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
+    #[cfg(feature = "mock")] _bridge: (),
+) -> Result<Response<Body>, Infallible> {
+    #[cfg(not(feature = "mock"))]
+    {
+        match bridge.self_test().await {
+            Ok(report) => Ok(json_raw(
+                StatusCode::OK,
+                serde_json::to_string(&report).unwrap_or_default(),
+            )),
+            Err(e) => Ok(json_raw(
+                StatusCode::SERVICE_UNAVAILABLE,
+                serde_json::json!({ "ok": false, "error": e }).to_string(),
+            )),
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    {
+        Ok(json_raw(
+            StatusCode::SERVICE_UNAVAILABLE,
+            r#"{"ok":false,"error":"Self-test not available (mock feature enabled)"}"#.to_string(),
+        ))
+    }
+}
+
 pub async fn handle_get_model_status(
+    req: Request<Body>,
     #[cfg(not(feature = "mock"))] pool: WorkerPool,
     #[cfg(feature = "mock")] _pool: (),
     db: SharedDatabase,
@@ -330,9 +550,16 @@ pub async fn handle_get_model_status(
         let is_loading = bridge.is_loading();
         let is_generating = bridge.is_generating().await;
         let active_conv_id = bridge.active_conversation_id().await;
-        // Expose finish_reason after generation ends (cleared on next generation start)
-        let last_finish_reason = if !is_generating {
-            bridge.last_finish_reason().await
+        // Expose finish_reason after generation ends (cleared on next generation start).
+        // `?conversation_id=` selects that conversation's reason, so a parallel
+        // generation for another conversation neither hides nor overwrites it.
+        let finish_conv_id = crate::request_parsing::get_query_param(req.uri(), "conversation_id");
+        let still_generating = match finish_conv_id.as_deref() {
+            Some(conv_id) => bridge.is_generating_conversation(conv_id).await,
+            None => is_generating,
+        };
+        let last_finish_reason = if !still_generating {
+            bridge.last_finish_reason(finish_conv_id.as_deref()).await
         } else { None };
         // Try bridge status first (from WebSocket), fall back to worker global status (from IPC)
         let status_msg = match bridge.status_message().await {
@@ -376,6 +603,8 @@ pub async fn handle_get_model_status(
                     has_vision: Some(meta.has_vision),
                     tool_tags: tags,
                     gpu_layers: meta.gpu_layers,
+                    effective_backend: meta.effective_backend.clone(),
+                    recommended_sampling: meta.recommended_sampling,
                     block_count: meta.block_count,
                     system_prompt_tokens: if sys_tokens > 0 { Some(sys_tokens) } else { None },
                     tool_definitions_tokens: if tool_tokens > 0 { Some(tool_tokens) } else { None },
@@ -407,6 +636,8 @@ pub async fn handle_get_model_status(
                     has_vision: None,
                     tool_tags: None,
                     gpu_layers: None,
+                    effective_backend: None,
+                    recommended_sampling: None,
                     block_count: None,
                     system_prompt_tokens: if sys_tokens > 0 { Some(sys_tokens) } else { None },
                     tool_definitions_tokens: if tool_tokens > 0 { Some(tool_tokens) } else { None },
@@ -439,7 +670,7 @@ pub async fn handle_get_model_status(
 
     #[cfg(feature = "mock")]
     {
-        let _ = &db;
+        let _ = (&req, &db);
         Ok(json_raw(
             StatusCode::OK,
             default_model_status_json(),
@@ -7,7 +7,7 @@ use std::path::PathBuf;
 use tokio::sync::mpsc;
 use tokio::task::spawn_blocking;
 
-use crate::response_helpers::json_error;
+use crate::response_helpers::{api_error, ApiError};
 
 const GPU_BACKEND_RELEASE_URL: &str =
     "https://github.com/AgustinJimenez/llama_cpp_rs_chat/releases/download/backends/ggml-cuda.dll";
@@ -19,9 +19,9 @@ pub async fn handle_post_backends_install() -> Result<Response<Body>, Infallible
             .unwrap_or_else(|| std::path::Path::new("."))
             .to_path_buf(),
         Err(e) => {
-            return Ok(json_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &format!("Cannot determine app directory: {e}"),
+            return Ok(api_error(
+                ApiError::INTERNAL_ERROR,
+                format!("Cannot determine app directory: {e}"),
             ));
         }
     };
@@ -4,28 +4,53 @@ use llama_chat_engine::{get_tool_tags_for_model, tool_tags::get_tag_pairs_for_mo
 use llama_chat_engine::gguf_utils::{
     detect_tool_format, extract_default_system_prompt, MetadataExtractor,
 };
+use llama_chat_engine::io_timeout::with_models_io_timeout;
+use llama_chat_engine::utils::estimate_tokens;
+#[cfg(not(feature = "mock"))]
+use llama_chat_worker::worker::worker_bridge::SharedWorkerBridge;
 
-pub(super) fn default_model_status_json() -> String {
-    r#"{"loaded":false,"model_path":null,"last_used":null,"memory_usage_mb":null}"#.to_string()
+/// Run a blocking models-directory call off the async runtime, bounded by the
+/// configured I/O timeout so a hung mount fails the request instead of stalling it.
+pub(super) async fn models_io<T, F>(op: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || with_models_io_timeout(op))
+        .await
+        .unwrap_or_else(|_| Err("Models directory call failed".to_string()))
 }
 
-pub(super) fn scan_directory_for_gguf_files(path: &std::path::Path) -> Vec<String> {
-    let mut gguf_files = Vec::new();
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            let entry_path = entry.path();
-            if entry_path.is_file() {
-                if let Some(ext) = entry_path.extension() {
-                    if ext.eq_ignore_ascii_case("gguf") {
-                        if let Some(filename) = entry_path.file_name().and_then(|n| n.to_str()) {
-                            gguf_files.push(filename.to_string());
-                        }
-                    }
-                }
+/// `text`'s token count for a pre-load check on `model_path`, and whether it's
+/// an estimate. Uses the real tokenizer when that model is the one loaded (and
+/// the worker is free), else `estimate_tokens`.
+pub(super) async fn prompt_token_count(
+    text: String,
+    model_path: Option<&str>,
+    #[cfg(not(feature = "mock"))] bridge: &SharedWorkerBridge,
+    #[cfg(feature = "mock")] _bridge: (),
+) -> (usize, bool) {
+    #[cfg(not(feature = "mock"))]
+    if let Some(path) = model_path {
+        let is_loaded = matches!(bridge.model_status().await, Some(meta) if meta.loaded && meta.model_path == path);
+        if is_loaded {
+            if let Ok(count) = bridge.count_tokens(text.clone()).await {
+                return (count, false);
             }
         }
     }
-    gguf_files
+    #[cfg(feature = "mock")]
+    let _ = model_path;
+    (estimate_tokens(&text), true)
+}
+
+/// The `prompt_tokens` field of the validate/recommend responses.
+pub(super) fn prompt_tokens_json((count, estimated): (usize, bool)) -> serde_json::Value {
+    serde_json::json!({ "count": count, "estimated": estimated })
+}
+
+pub(super) fn default_model_status_json() -> String {
+    r#"{"loaded":false,"model_path":null,"last_used":null,"memory_usage_mb":null}"#.to_string()
 }
 
 pub(super) fn scan_for_mmproj_files(model_path: &std::path::Path) -> Vec<(String, u64)> {
@@ -128,10 +153,10 @@ pub(super) fn enrich_model_info_from_gguf(
         }
     }
     if let Some(val) = extractor.get_arch_field(&arch, "block_count") {
-        model_info["block_count"] = serde_json::json!(val.clone());
-        if let Ok(count) = val.parse::<u32>() {
-            model_info["estimated_layers"] = serde_json::json!(count);
-        }
+        model_info["block_count"] = serde_json::json!(val);
+    }
+    if let Some(count) = extractor.get_u32(&format!("{arch}.block_count")) {
+        model_info["estimated_layers"] = serde_json::json!(count);
     }
 
     if let Some(val) = extractor.get_string("tokenizer.chat_template") {
@@ -0,0 +1,137 @@
+// Cache of `/api/model/info` responses.
+//
+// Parsing a GGUF header means reading every metadata key, which is slow on
+// network drives and the UI asks on every hover. Entries are keyed by path and
+// checked against the file's mtime and size, plus its directory's mtime (an
+// added or removed mmproj companion changes the response too).
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Entries kept before the oldest one is dropped.
+const MAX_ENTRIES: usize = 256;
+
+/// What has to match for a cached entry to still describe the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    dir_modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    pub(super) fn read(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let dir_modified = path
+            .parent()
+            .and_then(|dir| std::fs::metadata(dir).ok())
+            .and_then(|m| m.modified().ok());
+        Ok(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            dir_modified,
+        })
+    }
+}
+
+struct Entry {
+    stamp: FileStamp,
+    info: serde_json::Value,
+    stored: u64,
+}
+
+#[derive(Default)]
+pub(super) struct ModelInfoCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    next_stored: AtomicU64,
+}
+
+impl ModelInfoCache {
+    /// The cached info for `path`, unless the file changed since it was stored.
+    pub(super) fn get(&self, path: &str, stamp: FileStamp) -> Option<serde_json::Value> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(path)
+            .filter(|entry| entry.stamp == stamp)
+            .map(|entry| entry.info.clone())
+    }
+
+    pub(super) fn insert(&self, path: &str, stamp: FileStamp, info: serde_json::Value) {
+        let stored = self.next_stored.fetch_add(1, Ordering::Relaxed);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= MAX_ENTRIES && !entries.contains_key(path) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(path.to_string(), Entry { stamp, info, stored });
+    }
+}
+
+/// The process-wide cache used by `handle_get_model_info`.
+pub(super) fn model_info_cache() -> &'static ModelInfoCache {
+    static CACHE: OnceLock<ModelInfoCache> = OnceLock::new();
+    CACHE.get_or_init(ModelInfoCache::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_model(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("model_info_cache_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model.gguf");
+        std::fs::write(&path, b"GGUF").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_unchanged_file_is_served_from_cache() {
+        let path = temp_model("hit");
+        let key = path.to_string_lossy().to_string();
+        let cache = ModelInfoCache::default();
+        assert!(cache.get(&key, FileStamp::read(&path).unwrap()).is_none());
+
+        cache.insert(&key, FileStamp::read(&path).unwrap(), serde_json::json!({"name": "model.gguf"}));
+        let hit = cache.get(&key, FileStamp::read(&path).unwrap());
+        assert_eq!(hit, Some(serde_json::json!({"name": "model.gguf"})));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_changed_mtime_forces_a_reparse() {
+        let path = temp_model("mtime");
+        let key = path.to_string_lossy().to_string();
+        let cache = ModelInfoCache::default();
+        cache.insert(&key, FileStamp::read(&path).unwrap(), serde_json::json!({}));
+
+        // Same size, newer mtime: the stale entry must not be returned
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        drop(file);
+        assert!(cache.get(&key, FileStamp::read(&path).unwrap()).is_none());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_oldest_entry_is_dropped_when_full() {
+        let cache = ModelInfoCache::default();
+        let stamp = FileStamp { modified: None, len: 0, dir_modified: None };
+        for i in 0..=MAX_ENTRIES {
+            cache.insert(&format!("/m/{i}.gguf"), stamp, serde_json::json!(i));
+        }
+        assert!(cache.get("/m/0.gguf", stamp).is_none());
+        assert_eq!(cache.get(&format!("/m/{MAX_ENTRIES}.gguf"), stamp), Some(serde_json::json!(MAX_ENTRIES)));
+    }
+}
@@ -10,6 +10,13 @@ pub async fn handle_get_model_history(
     Ok(json_raw(StatusCode::OK, response_json))
 }
 
+/// GET /api/model/history/entries — history with last-loaded time, display name, and size.
+pub async fn handle_get_model_history_entries(db: SharedDatabase) -> Result<Response<Body>, Infallible> {
+    let entries = db.get_model_history_entries().unwrap_or_default();
+    let response_json = serialize_with_fallback(&entries, "[]");
+    Ok(json_raw(StatusCode::OK, response_json))
+}
+
 pub async fn handle_post_model_history(
     req: Request<Body>,
     #[cfg(not(feature = "mock"))] _bridge: SharedWorkerBridge,
@@ -42,11 +49,29 @@ pub async fn handle_post_model_load(
 
     #[cfg(not(feature = "mock"))]
     {
-        let load_request: ModelLoadRequest = match parse_json_body(req.into_body()).await {
+        let mut load_request: ModelLoadRequest = match parse_json_body(req.into_body()).await {
             Ok(req) => req,
             Err(error_response) => return Ok(error_response),
         };
 
+        // Relative paths resolve under the configured models directory; escapes are rejected
+        let config = db.load_config();
+        load_request.model_path = match llama_chat_config::resolve_model_path(
+            &load_request.model_path,
+            config.models_directory.as_deref(),
+            config.allow_external_model_paths,
+        )
+        .and_then(llama_chat_config::ModelTarget::into_file)
+        {
+            Ok(path) => path,
+            Err(e) => return Ok(api_error(ApiError::INVALID_REQUEST, e)),
+        };
+
+        let kv_cache_type = match load_request.kv_cache_types() {
+            Ok(kv) => kv,
+            Err(e) => return Ok(api_error(ApiError::INVALID_REQUEST, e)),
+        };
+
         // This endpoint loads into the persistent `default` worker. Free memory first
         // by unloading other idle workers if the machine can't hold this model alongside
         // them (prevents two co-resident model copies OOMing the GPU on low-RAM machines).
@@ -54,11 +79,12 @@ pub async fn handle_post_model_load(
             .await;
 
         match bridge
-            .load_model(
+            .load_model_with_kv_cache(
                 &load_request.model_path,
                 load_request.gpu_layers,
                 load_request.mmproj_path,
                 None,
+                kv_cache_type,
             )
             .await
         {
@@ -78,6 +104,8 @@ pub async fn handle_post_model_load(
                     has_vision: Some(meta.has_vision),
                     tool_tags: tags,
                     gpu_layers: meta.gpu_layers,
+                    effective_backend: meta.effective_backend.clone(),
+                    recommended_sampling: meta.recommended_sampling,
                     block_count: meta.block_count,
                     system_prompt_tokens: None,
                     tool_definitions_tokens: None,
@@ -90,6 +118,7 @@ pub async fn handle_post_model_load(
                     success: true,
                     message: format!("Model loaded successfully from {}", load_request.model_path),
                     status: Some(status),
+                    load_outcome: Some(meta.load_outcome),
                 };
 
                 let response_json = serialize_with_fallback(
@@ -99,31 +128,19 @@ pub async fn handle_post_model_load(
 
                 Ok(json_raw(StatusCode::OK, response_json))
             }
-            Err(e) => {
-                let response = ModelResponse {
-                    success: false,
-                    message: format!("Failed to load model: {e}"),
-                    status: None,
-                };
-                let response_json = serialize_with_fallback(
-                    &response,
-                    &format!(
-                        r#"{{"success":false,"message":"Failed to load model: {e}","status":null}}"#
-                    ),
-                );
-
-                Ok(json_raw(StatusCode::INTERNAL_SERVER_ERROR, response_json))
-            }
+            Err(e) => Ok(api_error(
+                ApiError::MODEL_LOAD_FAILED,
+                format!("Failed to load model: {e}"),
+            )),
         }
     }
 
     #[cfg(feature = "mock")]
     {
         let _ = (req, &db);
-        Ok(json_raw(
-            StatusCode::SERVICE_UNAVAILABLE,
-            r#"{"success":false,"message":"Model loading not available (mock feature enabled)"}"#
-                .to_string(),
+        Ok(api_error(
+            ApiError::NOT_AVAILABLE,
+            "Model loading not available (mock feature enabled)",
         ))
     }
 }
@@ -149,6 +166,8 @@ pub async fn handle_post_model_unload(
                     has_vision: None,
                     tool_tags: None,
                     gpu_layers: None,
+                    effective_backend: None,
+                    recommended_sampling: None,
                     block_count: None,
                     system_prompt_tokens: None,
                     tool_definitions_tokens: None,
@@ -161,6 +180,7 @@ pub async fn handle_post_model_unload(
                     success: true,
                     message: "Model unloaded successfully".to_string(),
                     status: Some(status),
+                    load_outcome: None,
                 };
                 let response_json = serialize_with_fallback(
                     &response,
@@ -168,29 +188,18 @@ pub async fn handle_post_model_unload(
                 );
                 Ok(json_raw(StatusCode::OK, response_json))
             }
-            Err(e) => {
-                let response = ModelResponse {
-                    success: false,
-                    message: format!("Failed to unload model: {e}"),
-                    status: None,
-                };
-                let response_json = serialize_with_fallback(
-                    &response,
-                    &format!(
-                        r#"{{"success":false,"message":"Failed to unload model: {e}","status":null}}"#
-                    ),
-                );
-                Ok(json_raw(StatusCode::INTERNAL_SERVER_ERROR, response_json))
-            }
+            Err(e) => Ok(api_error(
+                ApiError::INTERNAL_ERROR,
+                format!("Failed to unload model: {e}"),
+            )),
         }
     }
 
     #[cfg(feature = "mock")]
     {
-        Ok(json_raw(
-            StatusCode::SERVICE_UNAVAILABLE,
-            r#"{"success":false,"message":"Model unloading not available (mock feature enabled)"}"#
-                .to_string(),
+        Ok(api_error(
+            ApiError::NOT_AVAILABLE,
+            "Model unloading not available (mock feature enabled)",
         ))
     }
 }
@@ -207,19 +216,18 @@ pub async fn handle_post_model_hard_unload(
                 r#"{"success":true,"message":"Worker process killed, memory reclaimed"}"#
                     .to_string(),
             )),
-            Err(e) => Ok(json_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &format!("Failed to force unload: {e}"),
+            Err(e) => Ok(api_error(
+                ApiError::INTERNAL_ERROR,
+                format!("Failed to force unload: {e}"),
             )),
         }
     }
 
     #[cfg(feature = "mock")]
     {
-        Ok(json_raw(
-            StatusCode::SERVICE_UNAVAILABLE,
-            r#"{"success":false,"message":"Force unload not available (mock feature enabled)"}"#
-                .to_string(),
+        Ok(api_error(
+            ApiError::NOT_AVAILABLE,
+            "Force unload not available (mock feature enabled)",
         ))
     }
 }
@@ -245,9 +253,9 @@ pub async fn handle_get_backends(
                     serde_json::to_string(&body).unwrap(),
                 ))
             }
-            Err(e) => Ok(json_error(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &format!("Failed to get backends: {e}"),
+            Err(e) => Ok(api_error(
+                ApiError::WORKER_UNAVAILABLE,
+                format!("Failed to get backends: {e}"),
             )),
         }
     }
@@ -10,9 +10,13 @@ use std::convert::Infallible;
 
 use crate::request_parsing::parse_json_body;
 use crate::response_helpers::json_error;
+use llama_chat_db::SharedDatabase;
+use llama_chat_types::models::AlternativeCompletion;
 
 #[cfg(not(feature = "mock"))]
-use llama_chat_worker::worker::worker_bridge::{GenerationResult, SharedWorkerBridge};
+use llama_chat_types::models::GenerationOverrides;
+#[cfg(not(feature = "mock"))]
+use llama_chat_worker::worker::worker_bridge::{GenerateOptions, GenerationResult, SharedWorkerBridge};
 
 // ── Request types ──────────────────────────────────────────────────────────────
 
@@ -30,6 +34,9 @@ struct OpenAiChatRequest {
     stream: bool,
     #[serde(default)]
     model: Option<String>,
+    /// Completions to return; above 1 needs a non-streaming request.
+    #[serde(default)]
+    n: Option<u32>,
 }
 
 // ── /v1/models ─────────────────────────────────────────────────────────────────
@@ -40,30 +47,17 @@ pub async fn handle_get_models(
 ) -> Result<Response<Body>, Infallible> {
     #[cfg(not(feature = "mock"))]
     {
-        let model_id = get_model_id(&bridge).await;
+        let model_id = loaded_model_id(&bridge).await;
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
-        let body = serde_json::json!({
-            "object": "list",
-            "data": [{
-                "id": model_id,
-                "object": "model",
-                "created": now,
-                "owned_by": "local"
-            }]
-        });
-        Ok(json_body(StatusCode::OK, body))
+        Ok(json_body(StatusCode::OK, models_list_body(model_id.as_deref(), now)))
     }
 
     #[cfg(feature = "mock")]
     {
-        let body = serde_json::json!({
-            "object": "list",
-            "data": [{ "id": "local-model", "object": "model", "created": 0, "owned_by": "local" }]
-        });
-        Ok(json_body(StatusCode::OK, body))
+        Ok(json_body(StatusCode::OK, models_list_body(Some("local-model"), 0)))
     }
 }
 
@@ -73,6 +67,7 @@ pub async fn handle_post_chat_completions(
     req: Request<Body>,
     #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
     #[cfg(feature = "mock")] _bridge: (),
+    db: SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
     let oai_req: OpenAiChatRequest = match parse_json_body(req.into_body()).await {
         Ok(r) => r,
@@ -82,6 +77,15 @@ pub async fn handle_post_chat_completions(
     if oai_req.messages.is_empty() {
         return Ok(json_error(StatusCode::BAD_REQUEST, "messages must not be empty"));
     }
+    if oai_req.stream && oai_req.n.is_some_and(|n| n > 1) {
+        return Ok(json_error(StatusCode::BAD_REQUEST, "n > 1 is not supported with stream"));
+    }
+    // Checked before dispatch so a rejected request never reaches the conversation
+    let max_completions = db.load_config().max_completions;
+    let completions = match llama_chat_engine::completion_count(oai_req.n, max_completions) {
+        Ok(count) => count,
+        Err(e) => return Ok(openai_error(StatusCode::BAD_REQUEST, &e)),
+    };
 
     // Build a single user message from the OpenAI messages array.
     // Strategy: concatenate all messages with role prefixes, then use the
@@ -97,23 +101,29 @@ pub async fn handle_post_chat_completions(
         if oai_req.stream {
             stream_response(bridge, user_prompt, model_id).await
         } else {
-            blocking_response(bridge, user_prompt, model_id).await
+            blocking_response(bridge, user_prompt, model_id, completions as u32).await
         }
     }
 
     #[cfg(feature = "mock")]
     {
         let _ = user_prompt;
+        let extra = completions - 1;
+        let alternatives = vec![
+            AlternativeCompletion {
+                content: "mock response".to_string(),
+                reasoning: None,
+                finish_reason: "stop".to_string(),
+                gen_tokens: 0,
+            };
+            extra
+        ];
         let body = serde_json::json!({
             "id": format!("chatcmpl-{}", uuid::Uuid::new_v4()),
             "object": "chat.completion",
             "created": 0u64,
             "model": "local-model",
-            "choices": [{
-                "index": 0,
-                "message": { "role": "assistant", "content": "mock response" },
-                "finish_reason": "stop"
-            }],
+            "choices": choices_json("mock response", "stop", &alternatives),
             "usage": { "prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0 }
         });
         Ok(json_body(StatusCode::OK, body))
@@ -204,6 +214,7 @@ async fn blocking_response(
     bridge: SharedWorkerBridge,
     user_prompt: String,
     model_id: String,
+    n: u32,
 ) -> Result<Response<Body>, Infallible> {
     let completion_id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
     let created = std::time::SystemTime::now()
@@ -211,14 +222,16 @@ async fn blocking_response(
         .unwrap_or_default()
         .as_secs();
 
-    let (mut token_rx, done_rx) = match bridge.generate(user_prompt, None, false, None, None).await {
+    let options = GenerateOptions {
+        overrides: GenerationOverrides { n: Some(n), ..Default::default() },
+        ..Default::default()
+    };
+    let (mut token_rx, done_rx) = match bridge
+        .generate_with_options(user_prompt, None, false, None, None, options)
+        .await
+    {
         Ok(rx) => rx,
-        Err(e) => {
-            let body = serde_json::json!({
-                "error": { "message": e, "type": "server_error", "code": 500 }
-            });
-            return Ok(json_body(StatusCode::INTERNAL_SERVER_ERROR, body));
-        }
+        Err(e) => return Ok(openai_error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
     };
 
     let mut full_text = String::new();
@@ -227,32 +240,27 @@ async fn blocking_response(
     }
 
     let finish_reason = match done_rx.await {
-        Ok(GenerationResult::Complete { finish_reason, tokens_used, gen_tokens, prompt_tokens, .. }) => {
+        Ok(GenerationResult::Complete {
+            finish_reason, tokens_used, gen_tokens, prompt_tokens, alternatives, ..
+        }) => {
+            let finish_reason = finish_reason.unwrap_or_else(|| "stop".to_string());
+            let alternative_tokens: i32 = alternatives.iter().map(|a| a.gen_tokens).sum();
             let body = serde_json::json!({
                 "id": completion_id,
                 "object": "chat.completion",
                 "created": created,
                 "model": model_id,
-                "choices": [{
-                    "index": 0,
-                    "message": { "role": "assistant", "content": full_text },
-                    "finish_reason": finish_reason.unwrap_or_else(|| "stop".to_string())
-                }],
+                "choices": choices_json(&full_text, &finish_reason, &alternatives),
                 "usage": {
                     "prompt_tokens": prompt_tokens.unwrap_or(0),
-                    "completion_tokens": gen_tokens.unwrap_or(0),
-                    "total_tokens": tokens_used
+                    "completion_tokens": gen_tokens.unwrap_or(0) + alternative_tokens,
+                    "total_tokens": tokens_used + alternative_tokens
                 }
             });
             return Ok(json_body(StatusCode::OK, body));
         }
         Ok(GenerationResult::Cancelled) => "stop",
-        Ok(GenerationResult::Error(e)) => {
-            let body = serde_json::json!({
-                "error": { "message": e, "type": "server_error", "code": 500 }
-            });
-            return Ok(json_body(StatusCode::INTERNAL_SERVER_ERROR, body));
-        }
+        Ok(GenerationResult::Error(e)) => return Ok(openai_error(StatusCode::INTERNAL_SERVER_ERROR, &e)),
         Err(_) => "stop",
     };
 
@@ -273,6 +281,35 @@ async fn blocking_response(
 
 // ── Helpers ────────────────────────────────────────────────────────────────────
 
+/// `choices` for a blocking response: the streamed completion at index 0,
+/// then the `n` > 1 alternatives in order.
+fn choices_json(
+    content: &str,
+    finish_reason: &str,
+    alternatives: &[AlternativeCompletion],
+) -> Vec<serde_json::Value> {
+    std::iter::once((content, finish_reason))
+        .chain(alternatives.iter().map(|a| (a.content.as_str(), a.finish_reason.as_str())))
+        .enumerate()
+        .map(|(index, (content, finish_reason))| {
+            serde_json::json!({
+                "index": index,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": finish_reason
+            })
+        })
+        .collect()
+}
+
+/// OpenAI-style error body; a 4xx is the client's fault.
+fn openai_error(status: StatusCode, message: &str) -> Response<Body> {
+    let kind = if status.is_client_error() { "invalid_request_error" } else { "server_error" };
+    let body = serde_json::json!({
+        "error": { "message": message, "type": kind, "code": status.as_u16() }
+    });
+    json_body(status, body)
+}
+
 /// Convert OpenAI messages array to a single user prompt string.
 ///
 /// If the array is just one user message we pass it through directly.
@@ -316,20 +353,45 @@ fn extract_text_content(content: &serde_json::Value) -> String {
 /// Return the loaded model's id string (filename without extension, or a generic fallback).
 #[cfg(not(feature = "mock"))]
 async fn get_model_id(bridge: &SharedWorkerBridge) -> String {
-    if let Some(meta) = bridge.model_status().await {
-        if meta.loaded {
-            // Prefer general_name; fall back to file stem of model_path
-            if let Some(name) = meta.general_name {
-                return name;
-            }
-            return std::path::Path::new(&meta.model_path)
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("local-model")
-                .to_string();
-        }
+    loaded_model_id(bridge)
+        .await
+        .unwrap_or_else(|| "local-model".to_string())
+}
+
+/// Id of the loaded model, or None when nothing is loaded.
+#[cfg(not(feature = "mock"))]
+async fn loaded_model_id(bridge: &SharedWorkerBridge) -> Option<String> {
+    let meta = bridge.model_status().await.filter(|m| m.loaded)?;
+    Some(model_id_for(meta.general_name.as_deref(), &meta.model_path))
+}
+
+/// Prefer `general.name`; fall back to the file stem of the model path.
+#[cfg(any(not(feature = "mock"), test))]
+fn model_id_for(general_name: Option<&str>, model_path: &str) -> String {
+    if let Some(name) = general_name.filter(|n| !n.trim().is_empty()) {
+        return name.to_string();
     }
-    "local-model".to_string()
+    std::path::Path::new(model_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("local-model")
+        .to_string()
+}
+
+/// `/v1/models` body: the loaded model, or an empty list when none is loaded.
+fn models_list_body(model_id: Option<&str>, created: u64) -> serde_json::Value {
+    let data: Vec<serde_json::Value> = model_id
+        .map(|id| {
+            serde_json::json!({
+                "id": id,
+                "object": "model",
+                "created": created,
+                "owned_by": "local"
+            })
+        })
+        .into_iter()
+        .collect();
+    serde_json::json!({ "object": "list", "data": data })
 }
 
 fn json_body(status: StatusCode, body: serde_json::Value) -> Response<Body> {
@@ -372,3 +434,81 @@ fn error_chunk(id: &str, model: &str, created: u64, error: &str) -> String {
     })
     .to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models_list_with_loaded_model() {
+        let id = model_id_for(Some("Qwen3 8B"), "/models/qwen3-8b-q4_k_m.gguf");
+        let body = models_list_body(Some(&id), 1_700_000_000);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "object": "list",
+                "data": [{
+                    "id": "Qwen3 8B",
+                    "object": "model",
+                    "created": 1_700_000_000u64,
+                    "owned_by": "local"
+                }]
+            })
+        );
+        assert_eq!(
+            model_id_for(None, "/models/qwen3-8b-q4_k_m.gguf"),
+            "qwen3-8b-q4_k_m"
+        );
+    }
+
+    #[cfg(all(unix, not(feature = "mock")))]
+    #[tokio::test]
+    async fn test_n_above_max_completions_is_rejected_before_dispatch() {
+        use llama_chat_worker::worker::process_manager::ProcessManager;
+        use llama_chat_worker::worker::worker_bridge::WorkerBridge;
+        use std::sync::Arc;
+
+        let db: SharedDatabase = Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+        db.save_config(&llama_chat_db::config::DbSamplerConfig {
+            max_completions: 2,
+            ..Default::default()
+        })
+        .unwrap();
+        // A worker that never answers: reaching it would hang the request
+        let child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("cat > /dev/null")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .expect("spawn idle worker");
+        let bridge = Arc::new(WorkerBridge::new(
+            Arc::new(ProcessManager::from_child(child, ":memory:")),
+            db.clone(),
+        ));
+
+        let body = serde_json::json!({"messages": [{"role": "user", "content": "hi"}], "n": 3});
+        let req = Request::builder().method("POST").body(Body::from(body.to_string())).unwrap();
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            handle_post_chat_completions(req, bridge.clone(), db.clone()),
+        )
+        .await
+        .expect("rejected without waiting on the worker")
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(error["error"]["type"], "invalid_request_error");
+        assert_eq!(error["error"]["message"], "n must be at most 2 (max_completions)");
+        assert!(db.list_conversations(10, 0).unwrap().is_empty());
+        bridge.kill();
+    }
+
+    #[test]
+    fn test_models_list_when_nothing_loaded() {
+        let body = models_list_body(None, 0);
+        assert_eq!(body, serde_json::json!({ "object": "list", "data": [] }));
+    }
+}
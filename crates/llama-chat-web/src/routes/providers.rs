@@ -148,9 +148,7 @@ pub async fn handle_provider_stream(
     );
 
     let is_new_conversation = request.conversation_id.is_none();
-    let conv_id = request.conversation_id.unwrap_or_else(|| {
-        format!("chat_{}", chrono::Local::now().format("%Y-%m-%d-%H-%M-%S-%3f"))
-    });
+    let conv_id = request.conversation_id.unwrap_or_else(llama_chat_db::generate_conversation_id);
     // Tag the conversation with the agent ID (for remote-provider agents).
     if is_new_conversation {
         if let Some(ref aid) = request.agent_id {
@@ -393,9 +391,7 @@ pub async fn handle_provider_generate(
         full_response.push_str(&token_data.token);
     }
 
-    let conv_id = request.conversation_id.unwrap_or_else(|| {
-        format!("chat_{}", chrono::Local::now().format("%Y-%m-%d-%H-%M-%S-%3f"))
-    });
+    let conv_id = request.conversation_id.unwrap_or_else(llama_chat_db::generate_conversation_id);
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -6,19 +6,20 @@ use hyper::{Body, Request, Response, StatusCode};
 
 use llama_chat_db::SharedDatabase;
 
+use crate::listen_addr::server_port;
 use crate::remote;
 use crate::remote::upnp;
 use crate::response_helpers::{json_error, json_response};
 
-const SERVER_PORT: u16 = 18080;
-
 /// GET /api/remote/status
 pub async fn handle_get_status(db: SharedDatabase) -> Result<Response<Body>, Infallible> {
     let token = remote::get_or_create_token(&db);
-    let lan_url = remote::lan_url(SERVER_PORT);
+    let port = server_port();
+    let lan_url = remote::lan_url(port);
 
     let body = serde_json::json!({
         "token": token,
+        "port": port,
         "lan_url": lan_url,
         "lan_qr": lan_url.as_ref().map(|url| format!("{url}#token={token}")),
     });
@@ -38,7 +39,7 @@ pub async fn handle_upnp_enable(db: SharedDatabase) -> Result<Response<Body>, In
         }
     };
 
-    match upnp::enable(local_ip, SERVER_PORT).await {
+    match upnp::enable(local_ip, server_port()).await {
         Ok(access) => {
             let token = remote::get_or_create_token(&db);
             let public_url = format!("http://{}:{}", access.external_ip, access.external_port);
@@ -56,13 +57,14 @@ pub async fn handle_upnp_enable(db: SharedDatabase) -> Result<Response<Body>, In
 }
 
 /// POST /api/remote/upnp/disable  body: {"external_port": 18080}
+/// (defaults to the server port)
 pub async fn handle_upnp_disable(req: Request<Body>) -> Result<Response<Body>, Infallible> {
     let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap_or_default();
     let port: u16 = serde_json::from_slice::<serde_json::Value>(&body_bytes)
         .ok()
         .and_then(|v| v["external_port"].as_u64())
         .and_then(|p| u16::try_from(p).ok())
-        .unwrap_or(SERVER_PORT);
+        .unwrap_or_else(server_port);
 
     match upnp::disable(port).await {
         Ok(()) => Ok(json_response(StatusCode::OK, &serde_json::json!({"success": true}))),
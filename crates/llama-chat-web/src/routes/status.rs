@@ -11,6 +11,7 @@ use crate::websocket_utils::{
 use crate::websocket::handle_status_ws;
 #[cfg(not(feature = "mock"))]
 use llama_chat_worker::worker::worker_bridge::SharedWorkerBridge;
+use llama_chat_db::SharedDatabase;
 
 /// WebSocket upgrade handler for persistent status/health connection.
 ///
@@ -22,6 +23,7 @@ pub async fn handle_status_websocket(
     req: Request<Body>,
     #[cfg(not(feature = "mock"))] bridge: SharedWorkerBridge,
     #[cfg(feature = "mock")] _bridge: (),
+    db: SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
     if !is_websocket_upgrade(&req) {
         return Ok(build_json_error_response(
@@ -40,7 +42,7 @@ pub async fn handle_status_websocket(
         tokio::spawn(async move {
             match hyper::upgrade::on(req).await {
                 Ok(upgraded) => {
-                    if let Err(e) = handle_status_ws(upgraded, bridge_ws).await {
+                    if let Err(e) = handle_status_ws(upgraded, bridge_ws, db).await {
                         sys_error!("[WS_STATUS ERROR] {}", e);
                     }
                 }
@@ -53,7 +55,7 @@ pub async fn handle_status_websocket(
 
     #[cfg(feature = "mock")]
     {
-        let _ = req;
+        let _ = (req, db);
     }
 
     Ok(build_websocket_upgrade_response(&accept_key))
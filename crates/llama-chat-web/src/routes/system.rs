@@ -65,10 +65,8 @@ pub async fn handle_app_info() -> Result<Response<Body>, Infallible> {
         "version": env!("CARGO_PKG_VERSION"),
         "platform": std::env::consts::OS,
         "arch": std::env::consts::ARCH,
-        "features": {
-            "vision": cfg!(feature = "vision"),
-            "cuda": cfg!(feature = "cuda"),
-        },
+        "features": llama_chat_types::build_info::compiled_features(),
+        "build": llama_chat_types::build_info::build_info_json(),
     });
     Ok(json_raw(
         StatusCode::OK,
@@ -76,6 +74,35 @@ pub async fn handle_app_info() -> Result<Response<Body>, Infallible> {
     ))
 }
 
+/// GET /api/capabilities — what this build understands, without a model loaded
+pub async fn handle_get_capabilities() -> Result<Response<Body>, Infallible> {
+    Ok(json_raw(
+        StatusCode::OK,
+        serde_json::to_string(&capabilities()).unwrap(),
+    ))
+}
+
+fn capabilities() -> serde_json::Value {
+    let tools: Vec<String> = llama_chat_tools::tool_defs::all_tool_definitions()
+        .iter()
+        .filter_map(|t| t.get("name").and_then(|n| n.as_str()).map(str::to_string))
+        .collect();
+    let template_detection: Vec<serde_json::Value> = llama_chat_engine::model_manager::CHAT_TEMPLATE_MARKERS
+        .iter()
+        .map(|(template_type, markers)| {
+            serde_json::json!({"template_type": template_type, "markers": markers})
+        })
+        .collect();
+    serde_json::json!({
+        // Hardcoded prompt formats; models with a Jinja chat template render with it instead
+        "template_types": llama_chat_engine::templates::SUPPORTED_TEMPLATES,
+        "template_detection": template_detection,
+        "tools": tools,
+        "sampler_types": llama_chat_engine::SAMPLER_TYPES,
+        "gguf_value_types": llama_chat_engine::gguf_utils::GGUF_VALUE_TYPES,
+    })
+}
+
 /// GET /api/docs — list all API endpoints
 pub async fn handle_api_docs() -> Result<Response<Body>, Infallible> {
     let e = |method: &str, path: &str, desc: &str| -> serde_json::Value {
@@ -84,6 +111,11 @@ pub async fn handle_api_docs() -> Result<Response<Body>, Infallible> {
     let endpoints = vec![
         e("GET", "/health", "Health check"),
         e("GET", "/api/info", "App and system info"),
+        e(
+            "GET",
+            "/api/capabilities",
+            "Supported template types, tools, sampler types and GGUF value types",
+        ),
         e("GET", "/api/docs", "This endpoint — API documentation"),
         e("POST", "/api/chat", "Send message (local model)"),
         e(
@@ -195,7 +227,7 @@ pub async fn handle_api_docs() -> Result<Response<Body>, Infallible> {
             "Generate with cloud provider (SSE streaming)",
         ),
         e("GET", "/api/config", "Get sampler/app configuration"),
-        e("POST", "/api/config", "Update configuration"),
+        e("POST", "/api/config", "Update configuration; reloads the loaded model when load settings change"),
         e(
             "GET",
             "/api/config/provider-keys",
@@ -350,6 +382,7 @@ pub async fn handle_system_usage() -> Result<Response<Body>, Infallible> {
         // Apple Silicon shares one pool between CPU and GPU — the UI collapses the
         // separate VRAM/RAM bars into a single "Unified Memory" bar when true.
         "unified_memory": cfg!(target_os = "macos"),
+        "compiled_features": llama_chat_types::build_info::compiled_features(),
     });
 
     Ok(json_raw(
@@ -688,3 +721,34 @@ pub async fn handle_kill_process(
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(value: &serde_json::Value) -> Vec<&str> {
+        value.as_array().unwrap().iter().filter_map(|v| v.as_str()).collect()
+    }
+
+    #[test]
+    fn test_capabilities_enumerate_templates_and_tools() {
+        let caps = capabilities();
+        assert_eq!(
+            names(&caps["template_types"]),
+            ["ChatML", "LFM2", "Mistral", "Llama3", "Gemma", "Phi", "GLM", "Generic"]
+        );
+
+        let tools = names(&caps["tools"]);
+        assert_eq!(tools.len(), llama_chat_tools::tool_defs::all_tool_definitions().len());
+        for tool in ["read_file", "write_file", "execute_command", "list_directory", "browser_search"] {
+            assert!(tools.contains(&tool), "{tool} missing");
+        }
+
+        let detection = caps["template_detection"].as_array().unwrap();
+        let chatml = detection.iter().find(|d| d["template_type"] == "ChatML").unwrap();
+        assert_eq!(names(&chatml["markers"]), ["<|im_start|>", "<|im_end|>"]);
+
+        assert!(names(&caps["sampler_types"]).contains(&"Greedy"));
+        assert!(names(&caps["gguf_value_types"]).contains(&"array"));
+    }
+}
@@ -1,7 +1,7 @@
 //! Browser and desktop tool handlers.
 
 use tokio::task::spawn_blocking;
-use super::helpers::{fetch_url_as_text, FETCH_TIMEOUT_SECS};
+use super::helpers::{fetch_url_as_text, FETCH_TASK_TIMEOUT_SECS};
 use tokio::time::timeout;
 
 const NAV_TIMEOUT_SECS: u64 = 30;
@@ -118,7 +118,7 @@ pub async fn handle_browser_fetch_text(tool_arguments: &serde_json::Value) -> se
     }
     let max_chars = tool_arguments.get("max_chars").and_then(|v| v.as_u64()).unwrap_or(8000) as usize;
     match timeout(
-        std::time::Duration::from_secs(FETCH_TIMEOUT_SECS + 5),
+        std::time::Duration::from_secs(FETCH_TASK_TIMEOUT_SECS),
         spawn_blocking(move || fetch_url_as_text(&url, max_chars)),
     ).await {
         Ok(Ok(result)) => result,
@@ -3,7 +3,7 @@
 use tokio::task::spawn_blocking;
 use tokio::time::{timeout, Duration};
 
-use super::helpers::{fetch_url_as_text, FETCH_TIMEOUT_SECS, MAX_TEXT_CHARS};
+use super::helpers::{fetch_url_as_text, FETCH_TASK_TIMEOUT_SECS, MAX_TEXT_CHARS};
 
 pub async fn handle_bash(tool_arguments: &serde_json::Value) -> serde_json::Value {
     let command = tool_arguments.get("command").and_then(|v| v.as_str()).unwrap_or("");
@@ -75,7 +75,7 @@ pub async fn handle_web_fetch(tool_arguments: &serde_json::Value) -> serde_json:
 
     let url_owned = url.to_string();
     match timeout(
-        Duration::from_secs(FETCH_TIMEOUT_SECS + 5),
+        Duration::from_secs(FETCH_TASK_TIMEOUT_SECS),
         spawn_blocking(move || fetch_url_as_text(&url_owned, max_chars)),
     )
     .await
@@ -87,7 +87,7 @@ pub async fn handle_web_fetch(tool_arguments: &serde_json::Value) -> serde_json:
         }),
         Err(_) => serde_json::json!({
             "success": false,
-            "error": format!("Web fetch timed out after {}s", FETCH_TIMEOUT_SECS)
+            "error": format!("Web fetch timed out after {}s", FETCH_TASK_TIMEOUT_SECS)
         }),
     }
 }
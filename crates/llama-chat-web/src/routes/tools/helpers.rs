@@ -8,7 +8,13 @@ use tokio::time::{timeout, Duration};
 use crate::request_parsing::get_query_param;
 use crate::response_helpers::{json_error, json_raw};
 
-pub(super) const FETCH_TIMEOUT_SECS: u64 = 15;
+const FETCH_TIMEOUT_SECS: u64 = 15;
+/// Tries per fetch; only connection failures, timeouts and gateway errors are retried.
+const FETCH_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled for each later one.
+const FETCH_RETRY_BACKOFF_MS: u64 = 500;
+/// Guard around a whole fetch task, with room for every attempt.
+pub(super) const FETCH_TASK_TIMEOUT_SECS: u64 = FETCH_TIMEOUT_SECS * FETCH_ATTEMPTS as u64 + 5;
 const MAX_RESPONSE_BYTES: usize = 100_000;
 pub(super) const MAX_TEXT_CHARS: usize = 10_000;
 
@@ -29,6 +35,44 @@ pub(super) async fn canonicalize_allowed(path: &str) -> Result<PathBuf, String>
     Err("Path not allowed".to_string())
 }
 
+/// Whether a failed request is worth repeating. 4xx responses and bad URLs are not.
+fn is_transient_fetch_error(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::Status(code, _) => matches!(code, 502..=504),
+        ureq::Error::Transport(t) => matches!(
+            t.kind(),
+            ureq::ErrorKind::Dns
+                | ureq::ErrorKind::ConnectionFailed
+                | ureq::ErrorKind::Io
+                | ureq::ErrorKind::ProxyConnect
+        ),
+    }
+}
+
+/// Run `call` up to `attempts` times while it fails with a transient error,
+/// backing off between tries. On failure returns the last error and the number
+/// of attempts made.
+fn retry_transient<T, E>(
+    attempts: u32,
+    backoff: Duration,
+    is_transient: impl Fn(&E) -> bool,
+    mut call: impl FnMut() -> Result<T, E>,
+) -> Result<T, (E, u32)> {
+    let mut delay = backoff;
+    let mut attempt = 1;
+    loop {
+        match call() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < attempts && is_transient(&e) => {
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err((e, attempt)),
+        }
+    }
+}
+
 pub fn fetch_url_as_text(url: &str, max_chars: usize) -> serde_json::Value {
     sys_debug!("[WEB_FETCH] Fetching URL: {}", url);
     let agent = ureq::AgentBuilder::new()
@@ -37,9 +81,14 @@ pub fn fetch_url_as_text(url: &str, max_chars: usize) -> serde_json::Value {
         .user_agent("Mozilla/5.0 (compatible; LlamaChat/1.0)")
         .build();
 
-    let response = match agent.get(url).call() {
+    let response = match retry_transient(
+        FETCH_ATTEMPTS,
+        Duration::from_millis(FETCH_RETRY_BACKOFF_MS),
+        is_transient_fetch_error,
+        || agent.get(url).call(),
+    ) {
         Ok(r) => r,
-        Err(ureq::Error::Status(code, resp)) => {
+        Err((ureq::Error::Status(code, resp), _)) => {
             let body = resp.into_string().unwrap_or_default();
             let mut preview_end = body.len().min(500);
             while preview_end > 0 && !body.is_char_boundary(preview_end) {
@@ -53,10 +102,18 @@ pub fn fetch_url_as_text(url: &str, max_chars: usize) -> serde_json::Value {
                 "body_preview": &body[..preview_end]
             });
         }
-        Err(e) => {
+        Err((e, attempts)) => {
+            let error = if attempts > 1 {
+                format!(
+                    "Failed to fetch URL '{}' after {} attempts: {}",
+                    url, attempts, e
+                )
+            } else {
+                format!("Failed to fetch URL '{}': {}", url, e)
+            };
             return serde_json::json!({
                 "success": false,
-                "error": format!("Failed to fetch URL '{}': {}", url, e),
+                "error": error,
                 "url": url
             });
         }
@@ -128,7 +185,7 @@ pub async fn handle_get_web_fetch(req: Request<Body>) -> Result<Response<Body>,
         .unwrap_or(MAX_TEXT_CHARS);
 
     let result = match timeout(
-        Duration::from_secs(FETCH_TIMEOUT_SECS + 5),
+        Duration::from_secs(FETCH_TASK_TIMEOUT_SECS),
         spawn_blocking(move || fetch_url_as_text(&url, max_chars)),
     )
     .await
@@ -140,7 +197,7 @@ pub async fn handle_get_web_fetch(req: Request<Body>) -> Result<Response<Body>,
         }),
         Err(_) => serde_json::json!({
             "success": false,
-            "error": format!("Web fetch timed out after {}s", FETCH_TIMEOUT_SECS)
+            "error": format!("Web fetch timed out after {}s", FETCH_TASK_TIMEOUT_SECS)
         }),
     };
 
@@ -228,3 +285,89 @@ pub async fn handle_post_extract_text(req: Request<Body>) -> Result<Response<Bod
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[derive(Debug, PartialEq)]
+    enum FakeError {
+        ConnectionReset,
+        NotFound,
+    }
+
+    /// Injected transport: fails with each scripted error in turn, then succeeds.
+    fn flaky_transport<'a>(
+        failures: &'a [FakeError],
+        calls: &'a Cell<usize>,
+    ) -> impl FnMut() -> Result<&'static str, FakeError> + 'a {
+        move || {
+            let n = calls.get();
+            calls.set(n + 1);
+            match failures.get(n) {
+                Some(FakeError::ConnectionReset) => Err(FakeError::ConnectionReset),
+                Some(FakeError::NotFound) => Err(FakeError::NotFound),
+                None => Ok("page"),
+            }
+        }
+    }
+
+    fn transient(e: &FakeError) -> bool {
+        *e == FakeError::ConnectionReset
+    }
+
+    #[test]
+    fn test_single_retry_recovers_from_transient_failure() {
+        let calls = Cell::new(0);
+        let result = retry_transient(
+            FETCH_ATTEMPTS,
+            Duration::ZERO,
+            transient,
+            flaky_transport(&[FakeError::ConnectionReset], &calls),
+        );
+        assert_eq!(result, Ok("page"));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_permanent_failure_is_not_retried() {
+        let calls = Cell::new(0);
+        let result = retry_transient(
+            FETCH_ATTEMPTS,
+            Duration::ZERO,
+            transient,
+            flaky_transport(&[FakeError::NotFound], &calls),
+        );
+        assert_eq!(result, Err((FakeError::NotFound, 1)));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_gives_up_after_all_attempts() {
+        let calls = Cell::new(0);
+        let failures = [
+            FakeError::ConnectionReset,
+            FakeError::ConnectionReset,
+            FakeError::ConnectionReset,
+        ];
+        let result = retry_transient(
+            FETCH_ATTEMPTS,
+            Duration::ZERO,
+            transient,
+            flaky_transport(&failures, &calls),
+        );
+        assert_eq!(result, Err((FakeError::ConnectionReset, FETCH_ATTEMPTS)));
+        assert_eq!(calls.get(), FETCH_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn test_http_status_classification() {
+        let status =
+            |code| ureq::Error::Status(code, ureq::Response::new(code, "status", "").unwrap());
+        assert!(!is_transient_fetch_error(&status(404)));
+        assert!(!is_transient_fetch_error(&status(429)));
+        assert!(!is_transient_fetch_error(&status(500)));
+        assert!(is_transient_fetch_error(&status(503)));
+    }
+}
@@ -19,12 +19,14 @@ pub use helpers::fetch_url_as_text;
 #[cfg(not(feature = "mock"))]
 use llama_chat_worker::worker::worker_bridge::SharedWorkerBridge;
 
-/// GET /api/tools/available — list all available tools with their schemas
+/// GET /api/tools/available — list the enabled tools with their schemas
 pub async fn handle_get_available_tools(
     #[cfg(not(feature = "mock"))] _bridge: SharedWorkerBridge,
     #[cfg(feature = "mock")] _bridge: (),
+    db: llama_chat_db::SharedDatabase,
 ) -> Result<Response<Body>, Infallible> {
-    let core_tools = llama_chat_engine::jinja_templates::get_available_tools();
+    let enabled_tools = db.load_config().enabled_tools;
+    let core_tools = llama_chat_engine::jinja_templates::get_enabled_tools(enabled_tools.as_deref());
     let body = serde_json::json!({
         "core_tools": core_tools.len(),
         "tools": core_tools,
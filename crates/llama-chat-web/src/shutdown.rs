@@ -0,0 +1,67 @@
+//! Graceful server shutdown: wait for Ctrl-C / SIGTERM (console close on Windows),
+//! then stop workers cleanly and flush the database before the process exits.
+
+use std::time::Duration;
+
+use llama_chat_db::Database;
+
+use crate::worker_pool::WorkerPool;
+
+/// How long each worker gets to exit after `Shutdown` before it is force-killed.
+pub const WORKER_SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// Resolve when the process is asked to terminate.
+pub async fn wait_for_shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            sys_warn!("[SHUTDOWN] Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                sys_warn!("[SHUTDOWN] Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(windows)]
+    let terminate = async {
+        match tokio::signal::windows::ctrl_close() {
+            Ok(mut close) => {
+                close.recv().await;
+            }
+            Err(e) => {
+                sys_warn!("[SHUTDOWN] Failed to listen for console close: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(any(unix, windows)))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    sys_info!("[SHUTDOWN] Termination signal received");
+}
+
+/// Stop every worker (graceful `Shutdown`, then force-kill after `grace`) and
+/// flush the database. Safe to call once the HTTP server has stopped accepting requests.
+pub async fn shutdown(pool: &WorkerPool, db: &Database, grace: Duration) {
+    pool.shutdown_all_gracefully(grace).await;
+    match db.flush() {
+        Ok(()) => sys_info!("[SHUTDOWN] Database flushed"),
+        Err(e) => sys_warn!("[SHUTDOWN] {}", e),
+    }
+}
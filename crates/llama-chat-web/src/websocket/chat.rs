@@ -1,19 +1,20 @@
 //! Chat WebSocket handler — real-time token streaming with server-side auto-continue.
 
 use futures_util::{stream::SplitSink, SinkExt, StreamExt};
-use hyper::upgrade::Upgraded;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::{Duration, Instant};
 use tokio_tungstenite::tungstenite::protocol::Message as WsMessage;
 use tokio_tungstenite::WebSocketStream;
 
 use llama_chat_db::SharedDatabase;
 use llama_chat_types::models::ChatRequest;
-use llama_chat_worker::worker::worker_bridge::GenerationResult;
+use llama_chat_worker::worker::worker_bridge::{GenerateOptions, GenerationResult};
 use crate::worker_pool::{resolve_bridge_for_request, WorkerPool};
 
 use super::{
-    make_server_continuation_message, should_server_auto_continue, ACTIVE_WS_CONNECTIONS,
-    MAX_SERVER_AUTO_CONTINUES,
+    make_server_continuation_message, parse_client_control, progress_message,
+    should_server_auto_continue, ClientControl, ProgressThrottle, ACTIVE_WS_CONNECTIONS,
+    MAX_SERVER_AUTO_CONTINUES, PROGRESS_EVERY_TOKENS, PROGRESS_INTERVAL,
 };
 use super::title::spawn_title_generation;
 use std::sync::atomic::Ordering;
@@ -28,8 +29,8 @@ pub(super) struct WsStreamDebug {
     pub chars_sent: usize,
 }
 
-pub(super) async fn flush_pending_tokens(
-    ws_sender: &mut SplitSink<WebSocketStream<Upgraded>, WsMessage>,
+pub(super) async fn flush_pending_tokens<S>(
+    ws_sender: &mut SplitSink<WebSocketStream<S>, WsMessage>,
     pending_tokens: &mut String,
     pending_tokens_used: &mut Option<i32>,
     pending_max_tokens: &mut Option<i32>,
@@ -37,7 +38,10 @@ pub(super) async fn flush_pending_tokens(
     pending_gen_tokens: &mut Option<i32>,
     next_flush: &mut Instant,
     debug: &mut WsStreamDebug,
-) -> Result<(), ()> {
+) -> Result<(), ()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     if pending_tokens.is_empty() {
         *next_flush = Instant::now() + WS_TOKEN_FLUSH_INTERVAL;
         return Ok(());
@@ -102,6 +106,18 @@ pub async fn handle_websocket(
     )
     .await;
 
+    chat_socket(ws_stream, pool, db).await
+}
+
+/// Serve chat requests on an open socket until the client closes it.
+pub(crate) async fn chat_socket<S>(
+    ws_stream: WebSocketStream<S>,
+    pool: WorkerPool,
+    db: SharedDatabase,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
     let _conn_count = ACTIVE_WS_CONNECTIONS.fetch_add(1, Ordering::SeqCst) + 1;
@@ -178,6 +194,22 @@ pub async fn handle_websocket(
                         break;
                     }
                 };
+                // Same check as the HTTP routes, before anything is logged
+                let model_loaded = bridge.model_status().await.is_some();
+                if let Err(e) = llama_chat_config::require_model(
+                    model_loaded,
+                    &db,
+                    chat_request.conversation_id.as_deref(),
+                    chat_request.agent_id.as_deref(),
+                ) {
+                    let error_msg = serde_json::json!({
+                        "type": "error",
+                        "code": crate::response_helpers::ApiError::NO_MODEL_LOADED,
+                        "error": e
+                    });
+                    let _ = ws_sender.send(WsMessage::Text(error_msg.to_string())).await;
+                    break;
+                }
 
                 // ── Server-side auto-continue setup ────────────────────────
                 let original_message = chat_request.message.clone();
@@ -200,8 +232,7 @@ pub async fn handle_websocket(
                 let mut pending_gen_tokens: Option<i32> = None;
                 let mut next_flush = Instant::now() + WS_TOKEN_FLUSH_INTERVAL;
                 let mut debug = WsStreamDebug {
-                    enabled: std::env::var("LLAMA_CHAT_WS_STREAM_DEBUG").ok().as_deref()
-                        == Some("1"),
+                    enabled: llama_chat_types::debug::debug_enabled(),
                     last_log: Instant::now(),
                     chunks_sent: 0,
                     chars_sent: 0,
@@ -213,18 +244,24 @@ pub async fn handle_websocket(
                 // Reset on every token; fire → synthetic error so the UI recovers.
                 const WORKER_SILENCE_TIMEOUT: Duration = Duration::from_secs(180);
                 let mut worker_silence_deadline = Instant::now() + WORKER_SILENCE_TIMEOUT;
+                // Set by an in-band {"type":"stop"}; suppresses server auto-continue
+                let mut stop_requested = false;
+                // Throttled {tokens_used, max_tokens} for the progress bar, alongside the text stream
+                let mut progress =
+                    ProgressThrottle::new(PROGRESS_INTERVAL, PROGRESS_EVERY_TOKENS, Instant::now());
 
                 'gen_loop: loop {
                     let skip_user_log = server_auto_continue_count > 0 || chat_request.auto_continue;
                     let image_data = if server_auto_continue_count == 0 { chat_request.image_data.clone() } else { None };
 
-                    let (mut rx, done_rx) = match bridge
-                        .generate(
+                    let (request_id, mut rx, done_rx) = match bridge
+                        .start_generation(
                             current_message.clone(),
                             current_conv_id.clone(),
                             skip_user_log,
                             image_data,
                             chat_request.agent_id.clone(),
+                            GenerateOptions::from(&chat_request),
                         )
                         .await
                     {
@@ -253,6 +290,11 @@ pub async fn handle_websocket(
                             token_result = rx.recv() => {
                                 match token_result {
                                     Some(token_data) => {
+                                        // echo_prompt: what the generation was set up with, ahead of any text
+                                        if let Some(ref echo) = token_data.prompt_echo {
+                                            let echo_json = super::prompt_echo_message(echo);
+                                            let _ = ws_sender.send(WsMessage::Text(echo_json.to_string())).await;
+                                        }
                                         // Status messages: send via WebSocket AND update bridge for API polling
                                         if let Some(status) = &token_data.status {
                                             let status_json = serde_json::json!({
@@ -271,6 +313,23 @@ pub async fn handle_websocket(
                                             });
                                             let _ = ws_sender.send(WsMessage::Text(timing_json.to_string())).await;
                                         }
+                                        // Prompt ingestion progress for long contexts ("reading context N%")
+                                        if let Some(progress) = token_data.prompt_eval_progress {
+                                            let progress_json = serde_json::json!({
+                                                "type": "prompt_eval_progress",
+                                                "done": progress.done,
+                                                "total": progress.total
+                                            });
+                                            let _ = ws_sender.send(WsMessage::Text(progress_json.to_string())).await;
+                                        }
+                                        // Reasoning-model thinking, streamed apart from the answer text
+                                        if let Some(ref reasoning) = token_data.reasoning {
+                                            let reasoning_json = serde_json::json!({
+                                                "type": "reasoning",
+                                                "text": reasoning
+                                            });
+                                            let _ = ws_sender.send(WsMessage::Text(reasoning_json.to_string())).await;
+                                        }
                                         // Approval requests: flush pending tokens first, then send the gate
                                         if let Some(ref approval) = token_data.approval_required {
                                             let _ = flush_pending_tokens(
@@ -293,6 +352,27 @@ pub async fn handle_websocket(
                                             let _ = ws_sender.send(WsMessage::Text(approval_json.to_string())).await;
                                             let _ = ws_sender.flush().await;
                                         }
+                                        // require_confirmation_for tools: same ordering, resolved via /api/generation/confirm
+                                        if let Some(ref confirmation) = token_data.confirmation_required {
+                                            let _ = flush_pending_tokens(
+                                                &mut ws_sender,
+                                                &mut pending_tokens,
+                                                &mut pending_tokens_used,
+                                                &mut pending_max_tokens,
+                                                &mut pending_gen_tok_per_sec,
+                                                &mut pending_gen_tokens,
+                                                &mut next_flush,
+                                                &mut debug,
+                                            ).await;
+                                            let confirmation_json = serde_json::json!({
+                                                "type": "confirmation_required",
+                                                "id": confirmation.id,
+                                                "tool": confirmation.tool,
+                                                "args": confirmation.args
+                                            });
+                                            let _ = ws_sender.send(WsMessage::Text(confirmation_json.to_string())).await;
+                                            let _ = ws_sender.flush().await;
+                                        }
                                         pending_tokens.push_str(&token_data.token);
                                         pending_tokens_used = Some(token_data.tokens_used);
                                         pending_max_tokens = Some(token_data.max_tokens);
@@ -305,6 +385,11 @@ pub async fn handle_websocket(
                                         heartbeat_deadline = Instant::now() + Duration::from_secs(15);
                                         worker_silence_deadline = Instant::now() + WORKER_SILENCE_TIMEOUT;
 
+                                        if !token_data.token.is_empty() && progress.on_token(Instant::now()) {
+                                            let progress_json = progress_message(token_data.tokens_used, token_data.max_tokens);
+                                            let _ = ws_sender.send(WsMessage::Text(progress_json.to_string())).await;
+                                        }
+
                                         if pending_tokens.len() >= WS_TOKEN_FLUSH_MAX_CHARS
                                             && flush_pending_tokens(
                                                 &mut ws_sender,
@@ -336,7 +421,7 @@ pub async fn handle_websocket(
                                         ).await;
 
                                         match done_rx.await {
-                                            Ok(GenerationResult::Complete { conversation_id, prompt_tok_per_sec, gen_tok_per_sec, gen_eval_ms, gen_tokens, prompt_eval_ms, prompt_tokens, finish_reason, token_breakdown, .. }) => {
+                                            Ok(GenerationResult::Complete { conversation_id, prompt_tok_per_sec, gen_tok_per_sec, gen_eval_ms, gen_tokens, prompt_eval_ms, prompt_tokens, finish_reason, token_breakdown, overflow, logprobs, tool_calls, stop_sequence, alternatives, .. }) => {
                                                 if chat_request.conversation_id.is_none() {
                                                     let _ = db.set_conversation_worker_id(
                                                         &conversation_id,
@@ -344,10 +429,10 @@ pub async fn handle_websocket(
                                                     );
                                                 }
                                                 eprintln!("[WS_CHAT] Complete: conv={conversation_id}, finish={finish_reason:?}");
-                                                bridge.set_last_finish_reason(finish_reason.clone()).await;
+                                                bridge.set_last_finish_reason(Some(&conversation_id), finish_reason.clone()).await;
                                                 // Store done — send after can_continue check so the frontend
                                                 // WS isn't closed before server auto-continue has a chance to run.
-                                                completed_done_msg = Some(serde_json::json!({
+                                                let mut done_msg = serde_json::json!({
                                                     "type": "done",
                                                     "conversation_id": conversation_id,
                                                     "prompt_tok_per_sec": prompt_tok_per_sec,
@@ -357,8 +442,22 @@ pub async fn handle_websocket(
                                                     "prompt_eval_ms": prompt_eval_ms,
                                                     "prompt_tokens": prompt_tokens,
                                                     "finish_reason": finish_reason,
-                                                    "token_breakdown": token_breakdown
-                                                }));
+                                                    "token_breakdown": token_breakdown,
+                                                    "overflow": overflow
+                                                });
+                                                if !logprobs.is_empty() {
+                                                    done_msg["logprobs"] = serde_json::json!(logprobs);
+                                                }
+                                                if !tool_calls.is_empty() {
+                                                    done_msg["tool_calls"] = serde_json::json!(tool_calls);
+                                                }
+                                                if let Some(stop) = stop_sequence {
+                                                    done_msg["stop_sequence"] = serde_json::json!(stop);
+                                                }
+                                                if !alternatives.is_empty() {
+                                                    done_msg["alternatives"] = serde_json::json!(alternatives);
+                                                }
+                                                completed_done_msg = Some(done_msg);
                                                 completed_conv_id = Some(conversation_id);
                                                 completed_finish_reason = finish_reason;
                                             }
@@ -449,6 +548,23 @@ pub async fn handle_websocket(
                                     Some(Ok(WsMessage::Ping(data))) => {
                                         let _ = ws_sender.send(WsMessage::Pong(data)).await;
                                     }
+                                    // In-band stop: cancel now; the worker's Cancelled result
+                                    // ends this turn with the usual "abort" message
+                                    Some(Ok(WsMessage::Text(text))) => {
+                                        if parse_client_control(&text) == Some(ClientControl::Stop) && !stop_requested {
+                                            sys_info!("[WS_CHAT] Stop requested over socket");
+                                            stop_requested = true;
+                                            // Only this socket's conversation: others may share the worker
+                                            let conv_id = match current_conv_id.clone() {
+                                                Some(id) => Some(id),
+                                                None => bridge.generation_conversation_id(request_id).await,
+                                            };
+                                            match conv_id {
+                                                Some(conv_id) => bridge.cancel_conversation(&conv_id).await,
+                                                None => sys_warn!("[WS_CHAT] Stop before the generation started; nothing to cancel"),
+                                            }
+                                        }
+                                    }
                                     Some(Err(_e)) => {
                                         eprintln!("[WS_CHAT] BREAK: client error: {_e}");
                                         break 'gen_loop;
@@ -461,7 +577,8 @@ pub async fn handle_websocket(
 
                     // ── Server-side continuation decision ──────────────────
                     let finish_str = completed_finish_reason.as_deref().unwrap_or("");
-                    let can_continue = should_server_auto_continue(finish_str)
+                    let can_continue = !stop_requested
+                        && should_server_auto_continue(finish_str)
                         && server_auto_continue_count < MAX_SERVER_AUTO_CONTINUES;
 
                     if let Some(conv_id) = completed_conv_id {
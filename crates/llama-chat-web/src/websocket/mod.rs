@@ -1,6 +1,7 @@
 //! WebSocket handlers for chat streaming, conversation watching, and status.
 
 use std::sync::atomic::AtomicU32;
+use tokio::time::{Duration, Instant};
 
 // Global counter for active WebSocket connections.
 pub static ACTIVE_WS_CONNECTIONS: AtomicU32 = AtomicU32::new(0);
@@ -40,6 +41,106 @@ pub fn make_server_continuation_message(finish_reason: &str, original_message: &
     }
 }
 
+/// Client→server control messages accepted on the chat socket mid-stream.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ClientControl {
+    /// `{"type":"stop"}` — cancel this socket's generation.
+    Stop,
+}
+
+/// Parse a text frame received while streaming. Anything unrecognized is ignored.
+pub(crate) fn parse_client_control(text: &str) -> Option<ClientControl> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    match value.get("type")?.as_str()? {
+        "stop" => Some(ClientControl::Stop),
+        _ => None,
+    }
+}
+
+/// Status-socket message for one model-load event:
+/// `{"type":"load_progress","phase":..,"progress":..}` plus `model_path`/`error` when set.
+pub(crate) fn load_progress_message(event: &llama_chat_types::LoadProgressEvent) -> serde_json::Value {
+    let mut msg = serde_json::to_value(event).unwrap_or_else(|_| serde_json::json!({}));
+    msg["type"] = serde_json::json!("load_progress");
+    msg
+}
+
+/// A `{"type":"progress"}` message goes out on the chat socket once this long
+/// has passed since the last one...
+pub(crate) const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// ...or once this many tokens have streamed, whichever comes first.
+pub(crate) const PROGRESS_EVERY_TOKENS: u32 = 16;
+
+/// Throttles token-count progress messages, which the UI progress bar reads
+/// instead of parsing every token message.
+pub(crate) struct ProgressThrottle {
+    interval: Duration,
+    every_tokens: u32,
+    tokens_since: u32,
+    last_sent: Instant,
+}
+
+impl ProgressThrottle {
+    pub(crate) fn new(interval: Duration, every_tokens: u32, now: Instant) -> Self {
+        Self {
+            interval,
+            every_tokens,
+            tokens_since: 0,
+            last_sent: now,
+        }
+    }
+
+    /// Count one streamed token; true when a progress message is due, i.e.
+    /// `every_tokens` tokens or `interval` have passed since the last one.
+    pub(crate) fn on_token(&mut self, now: Instant) -> bool {
+        self.tokens_since += 1;
+        if self.tokens_since >= self.every_tokens
+            || now.duration_since(self.last_sent) >= self.interval
+        {
+            self.tokens_since = 0;
+            self.last_sent = now;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Chat-socket progress message: `{"type":"progress","tokens_used":..,"max_tokens":..}`.
+pub(crate) fn progress_message(tokens_used: i32, max_tokens: i32) -> serde_json::Value {
+    serde_json::json!({
+        "type": "progress",
+        "tokens_used": tokens_used,
+        "max_tokens": max_tokens,
+    })
+}
+
+/// `echo_prompt` event, sent ahead of the generation's text on both the chat
+/// socket and the SSE stream.
+pub(crate) fn prompt_echo_message(echo: &llama_chat_types::PromptEcho) -> serde_json::Value {
+    serde_json::json!({
+        "type": "prompt_echo",
+        "system_prompt": echo.system_prompt,
+        "template_type": echo.template_type,
+        "tools": echo.tools,
+    })
+}
+
+/// Message sent to watchers and status sockets for a conversation deletion
+/// (`conversation_deleted`) or rename (`conversation_renamed`).
+pub(crate) fn conversation_event_message(event: &llama_chat_db::ConversationEvent) -> serde_json::Value {
+    match event {
+        llama_chat_db::ConversationEvent::Deleted { conversation_id } => {
+            serde_json::json!({ "type": "conversation_deleted", "conversation_id": conversation_id })
+        }
+        llama_chat_db::ConversationEvent::Renamed { conversation_id, title } => serde_json::json!({
+            "type": "conversation_renamed",
+            "conversation_id": conversation_id,
+            "title": title,
+        }),
+    }
+}
+
 mod chat;
 mod watch;
 mod status;
@@ -51,3 +152,6 @@ pub use watch::handle_conversation_watch;
 pub use status::handle_status_ws;
 pub use terminal::handle_terminal_ws;
 pub use title::{sanitize_title, spawn_message_title_generation, spawn_title_generation, strip_tool_tags};
+
+#[cfg(test)]
+mod tests;
@@ -2,20 +2,24 @@
 
 use futures_util::{SinkExt, StreamExt};
 use hyper::upgrade::Upgraded;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::Duration;
 use tokio_tungstenite::tungstenite::protocol::Message as WsMessage;
 use tokio_tungstenite::WebSocketStream;
 
+use llama_chat_db::SharedDatabase;
 use llama_chat_worker::worker::worker_bridge::SharedWorkerBridge;
 
-use super::ACTIVE_WS_CONNECTIONS;
+use super::{conversation_event_message, load_progress_message, ACTIVE_WS_CONNECTIONS};
 use std::sync::atomic::Ordering;
 
 /// Persistent status WebSocket — keeps alive with pings, sends initial model
-/// status, and lets the frontend detect server crashes via TCP close.
+/// status, streams model-load progress and conversation deletions/renames, and lets
+/// the frontend detect server crashes via TCP close.
 pub async fn handle_status_ws(
     upgraded: Upgraded,
     bridge: SharedWorkerBridge,
+    db: SharedDatabase,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let ws_stream = WebSocketStream::from_raw_socket(
         upgraded,
@@ -24,11 +28,27 @@ pub async fn handle_status_ws(
     )
     .await;
 
+    status_socket(ws_stream, bridge, db).await
+}
+
+/// Serve the status stream on an open socket until the client closes it.
+pub(crate) async fn status_socket<S>(
+    ws_stream: WebSocketStream<S>,
+    bridge: SharedWorkerBridge,
+    db: SharedDatabase,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
     let _ = ACTIVE_WS_CONNECTIONS.fetch_add(1, Ordering::SeqCst);
     sys_info!("[WS_STATUS] Status WebSocket connected");
 
+    // Subscribe before the initial status so no load event falls in the gap
+    let mut load_events = bridge.subscribe_load_events();
+    let mut conversation_events = db.subscribe_conversation_events();
+
     // Send initial model status
     let loaded = bridge.model_status().await.is_some();
     let init_msg = serde_json::json!({
@@ -55,6 +75,25 @@ pub async fn handle_status_ws(
                     break;
                 }
             }
+            event = load_events.recv() => {
+                match event {
+                    Ok(event) => {
+                        let msg = load_progress_message(&event);
+                        if ws_sender.send(WsMessage::Text(msg.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                    // Slow client: skip missed percentages, later events carry the latest state
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            Some(event) = conversation_events.recv() => {
+                let msg = conversation_event_message(&event);
+                if ws_sender.send(WsMessage::Text(msg.to_string())).await.is_err() {
+                    break;
+                }
+            }
             ws_msg = ws_receiver.next() => {
                 match ws_msg {
                     Some(Ok(WsMessage::Close(_))) | None => {
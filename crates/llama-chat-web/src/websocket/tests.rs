@@ -0,0 +1,398 @@
+use super::*;
+
+use futures_util::{SinkExt, StreamExt};
+use std::sync::Arc;
+use tokio::time::Duration;
+use tokio_tungstenite::tungstenite::protocol::{Message as WsMessage, Role};
+use tokio_tungstenite::WebSocketStream;
+
+#[test]
+fn test_parse_client_control() {
+    assert_eq!(
+        parse_client_control(r#"{"type":"stop"}"#),
+        Some(ClientControl::Stop)
+    );
+    assert_eq!(parse_client_control(r#"{"type":"ping"}"#), None);
+    assert_eq!(parse_client_control(r#"{"message":"hi"}"#), None);
+    assert_eq!(parse_client_control("not json"), None);
+}
+
+/// Chat socket served over an in-memory duplex, backed by a shell "worker"
+/// that streams two tokens per Generate and answers CancelGeneration with
+/// `on_cancel` (a payload JSON object) for that generation. The app config
+/// names a model unless `configured_model` is None.
+#[cfg(unix)]
+async fn chat_socket_with_mock_worker(
+    on_cancel: &str,
+) -> (WebSocketStream<tokio::io::DuplexStream>, tokio::task::JoinHandle<()>) {
+    chat_socket_with_model(on_cancel, Some("/models/a.gguf")).await
+}
+
+#[cfg(unix)]
+async fn chat_socket_with_model(
+    on_cancel: &str,
+    configured_model: Option<&str>,
+) -> (WebSocketStream<tokio::io::DuplexStream>, tokio::task::JoinHandle<()>) {
+    use llama_chat_worker::worker::process_manager::ProcessManager;
+    use llama_chat_worker::worker::worker_bridge::WorkerBridge;
+    use std::process::{Command, Stdio};
+
+    let script = format!(
+        r#"gen=0
+           while read -r line; do
+             id=$(printf '%s' "$line" | sed -n 's/^{{"id":\([0-9]*\),.*/\1/p')
+             case "$line" in
+               *'"type":"Generate"'*)
+                 gen=$id
+                 printf '{{"id":%s,"payload":{{"type":"Token","token":"tok1 ","tokens_used":1,"max_tokens":4096}}}}\n' "$gen"
+                 printf '{{"id":%s,"payload":{{"type":"Token","token":"tok2 ","tokens_used":2,"max_tokens":4096}}}}\n' "$gen" ;;
+               *'"CancelGeneration"'*) printf '{{"id":%s,"payload":%s}}\n' "$gen" '{on_cancel}' ;;
+             esac
+           done"#
+    );
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn mock worker");
+    let db: llama_chat_db::SharedDatabase =
+        Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+    let mut config = db.load_config();
+    config.model_path = configured_model.map(str::to_string);
+    db.save_config(&config).unwrap();
+    let bridge = Arc::new(WorkerBridge::new(
+        Arc::new(ProcessManager::from_child(child, ":memory:")),
+        db.clone(),
+    ));
+    let pool = crate::worker_pool::WorkerPool::new(bridge, ":memory:", db.clone());
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server_ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+    let client_ws = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+    let server = tokio::spawn(async move {
+        let _ = chat::chat_socket(server_ws, pool.clone(), db).await;
+        pool.shutdown_all();
+    });
+    (client_ws, server)
+}
+
+/// Send a chat request, wait for its tokens, stop in-band, and return every
+/// non-token message that follows until the server goes quiet.
+#[cfg(unix)]
+async fn stop_after_tokens(ws: &mut WebSocketStream<tokio::io::DuplexStream>) -> Vec<serde_json::Value> {
+    ws.send(WsMessage::Text(r#"{"message":"hi"}"#.to_string()))
+        .await
+        .unwrap();
+    let mut streamed = String::new();
+    while !streamed.contains("tok2") {
+        let msg = next_json(ws).await;
+        if msg["type"] == "token" {
+            streamed.push_str(msg["token"].as_str().unwrap());
+        }
+    }
+    ws.send(WsMessage::Text(r#"{"type":"stop"}"#.to_string()))
+        .await
+        .unwrap();
+
+    let mut after = Vec::new();
+    while let Ok(Some(Ok(WsMessage::Text(text)))) =
+        tokio::time::timeout(Duration::from_millis(500), ws.next()).await
+    {
+        let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+        if v["type"] != "token" {
+            after.push(v);
+        }
+    }
+    after
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_stop_message_cancels_stream_with_abort() {
+    let (mut client_ws, server) =
+        chat_socket_with_mock_worker(r#"{"type":"GenerationCancelled"}"#).await;
+
+    let after = stop_after_tokens(&mut client_ws).await;
+    assert_eq!(
+        after.iter().map(|m| m["type"].as_str().unwrap()).collect::<Vec<_>>(),
+        ["abort"]
+    );
+
+    drop(client_ws);
+    server.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_stop_suppresses_server_auto_continue() {
+    // The generation hit "length" just as the stop arrived: finish, don't continue
+    let (mut client_ws, server) = chat_socket_with_mock_worker(
+        r#"{"type":"GenerationComplete","conversation_id":"c1","tokens_used":2,"max_tokens":4096,"finish_reason":"length"}"#,
+    )
+    .await;
+
+    let after = stop_after_tokens(&mut client_ws).await;
+    let types: Vec<_> = after.iter().map(|m| m["type"].as_str().unwrap()).collect();
+    assert_eq!(types, ["done"], "{after:?}");
+    assert_eq!(after[0]["finish_reason"], "length");
+
+    drop(client_ws);
+    server.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_stopping_one_socket_leaves_other_conversations_running() {
+    use llama_chat_worker::worker::process_manager::ProcessManager;
+    use llama_chat_worker::worker::worker_bridge::WorkerBridge;
+    use std::process::{Command, Stdio};
+
+    // Stand-in worker shared by both sockets: streams two tokens per Generate and
+    // ends the generation of whichever conversation a CancelGeneration names
+    // (all of them when it names none)
+    let script = r#"while read -r line; do
+         id=$(printf '%s' "$line" | sed -n 's/^{"id":\([0-9]*\),.*/\1/p')
+         conv=$(printf '%s' "$line" | sed -n 's/.*"conversation_id":"\([a-z0-9]*\)".*/\1/p')
+         case "$line" in
+           *'"type":"Generate"'*)
+             eval "gen_$conv=$id"
+             printf '{"id":%s,"payload":{"type":"Token","token":"tok1 ","tokens_used":1,"max_tokens":4096}}\n' "$id"
+             printf '{"id":%s,"payload":{"type":"Token","token":"tok2 ","tokens_used":2,"max_tokens":4096}}\n' "$id" ;;
+           *'"CancelGeneration"'*)
+             for c in ${conv:-c1 c2}; do
+               eval "gen=\$gen_$c"
+               [ -n "$gen" ] && printf '{"id":%s,"payload":{"type":"GenerationCancelled"}}\n' "$gen"
+             done ;;
+         esac
+       done"#;
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn mock worker");
+    let db: llama_chat_db::SharedDatabase =
+        Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+    let mut config = db.load_config();
+    config.model_path = Some("/models/a.gguf".to_string());
+    db.save_config(&config).unwrap();
+    let bridge = Arc::new(WorkerBridge::new(
+        Arc::new(ProcessManager::from_child(child, ":memory:")),
+        db.clone(),
+    ));
+    let pool = crate::worker_pool::WorkerPool::new(bridge.clone(), ":memory:", db.clone());
+
+    let mut sockets = Vec::new();
+    let mut servers = Vec::new();
+    for conv_id in ["c1", "c2"] {
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        let server_ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+        let mut client_ws = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+        servers.push(tokio::spawn(chat::chat_socket(server_ws, pool.clone(), db.clone())));
+        let request = serde_json::json!({"message": "hi", "conversation_id": conv_id});
+        client_ws.send(WsMessage::Text(request.to_string())).await.unwrap();
+        let mut streamed = String::new();
+        while !streamed.contains("tok2") {
+            let msg = next_json(&mut client_ws).await;
+            if msg["type"] == "token" {
+                streamed.push_str(msg["token"].as_str().unwrap());
+            }
+        }
+        sockets.push(client_ws);
+    }
+
+    sockets[0]
+        .send(WsMessage::Text(r#"{"type":"stop"}"#.to_string()))
+        .await
+        .unwrap();
+    let mut stopped = Vec::new();
+    while let Ok(Some(Ok(WsMessage::Text(text)))) =
+        tokio::time::timeout(Duration::from_millis(500), sockets[0].next()).await
+    {
+        let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+        stopped.push(v["type"].as_str().unwrap().to_string());
+    }
+    assert!(stopped.iter().any(|t| t == "abort"), "{stopped:?}");
+
+    // The other conversation is still generating and its socket got nothing
+    assert!(bridge.is_generating_conversation("c2").await);
+    assert!(!bridge.is_generating_conversation("c1").await);
+    let quiet = tokio::time::timeout(Duration::from_millis(300), sockets[1].next()).await;
+    assert!(quiet.is_err(), "{quiet:?}");
+
+    drop(sockets);
+    for server in servers {
+        let _ = server.await.unwrap();
+    }
+    pool.shutdown_all();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_chat_without_model_errors_before_reaching_the_worker() {
+    let (mut client_ws, server) =
+        chat_socket_with_model(r#"{"type":"GenerationCancelled"}"#, None).await;
+
+    client_ws
+        .send(WsMessage::Text(r#"{"message":"hi"}"#.to_string()))
+        .await
+        .unwrap();
+    let first = next_json(&mut client_ws).await;
+    assert_eq!(first["type"], "error");
+    assert_eq!(first["code"], "no_model_loaded");
+    // The mock streams tokens for any Generate it gets; none may follow
+    while let Ok(Some(Ok(WsMessage::Text(text)))) =
+        tokio::time::timeout(Duration::from_millis(300), client_ws.next()).await
+    {
+        let msg: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_ne!(msg["type"], "token", "generation was dispatched: {msg}");
+    }
+
+    drop(client_ws);
+    server.await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_status_socket_streams_load_phases() {
+    use llama_chat_worker::worker::process_manager::ProcessManager;
+    use llama_chat_worker::worker::worker_bridge::WorkerBridge;
+    use std::process::{Command, Stdio};
+
+    // Stand-in worker: report phases inline, then answer the LoadModel
+    let script = r#"while read -r line; do
+         id=$(printf '%s' "$line" | sed -n 's/^{"id":\([0-9]*\),.*/\1/p')
+         case "$line" in *'"LoadModel"'*)
+           printf '{"id":0,"payload":{"type":"LoadingProgress","progress":0,"phase":"started"}}\n'
+           printf '{"id":0,"payload":{"type":"LoadingProgress","progress":45,"phase":"allocating"}}\n'
+           printf '{"id":%s,"payload":{"type":"ModelLoaded","model_path":"/models/a.gguf","context_length":4096,"gpu_layers":99,"has_vision":false}}\n' "$id" ;;
+         esac
+       done"#;
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn mock worker");
+    let db: llama_chat_db::SharedDatabase =
+        Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+    let bridge = Arc::new(WorkerBridge::new(
+        Arc::new(ProcessManager::from_child(child, ":memory:")),
+        db.clone(),
+    ));
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server_ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+    let mut client_ws = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+    let server = tokio::spawn(status::status_socket(server_ws, bridge.clone(), db));
+
+    let init = next_json(&mut client_ws).await;
+    assert_eq!(init, serde_json::json!({ "type": "model_status", "loaded": false }));
+
+    bridge.load_model("/models/a.gguf", None, None, None).await.unwrap();
+    let mut events = Vec::new();
+    loop {
+        let msg = next_json(&mut client_ws).await;
+        assert_eq!(msg["type"], "load_progress");
+        let done = msg["phase"] == "complete";
+        events.push(msg);
+        if done {
+            break;
+        }
+    }
+
+    let phases: Vec<_> = events.iter().map(|e| e["phase"].as_str().unwrap()).collect();
+    assert_eq!(phases, ["started", "allocating", "complete"]);
+    assert_eq!(events[1]["progress"], 45);
+    assert_eq!(events[2]["progress"], 100);
+    assert_eq!(events[2]["model_path"], "/models/a.gguf");
+
+    drop(client_ws);
+    server.await.unwrap().unwrap();
+    bridge.kill();
+}
+
+async fn next_json(ws: &mut WebSocketStream<tokio::io::DuplexStream>) -> serde_json::Value {
+    match tokio::time::timeout(Duration::from_secs(5), ws.next()).await {
+        Ok(Some(Ok(WsMessage::Text(text)))) => serde_json::from_str(&text).unwrap(),
+        other => panic!("no text frame: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_watch_sends_snapshot_then_live_update() {
+    use llama_chat_db::{Database, StreamingUpdate};
+
+    let db = Arc::new(Database::new(":memory:").unwrap());
+    let conv_id = db.create_conversation().unwrap();
+    db.insert_message(&conv_id, "user", "existing question", 0, 0)
+        .unwrap();
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server_ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+    let mut client_ws = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+    let server = tokio::spawn(watch::watch_conversation(
+        server_ws,
+        conv_id.clone(),
+        Some(4096),
+        db.clone(),
+    ));
+
+    let snapshot = next_json(&mut client_ws).await;
+    assert_eq!(snapshot["type"], "update");
+    assert_eq!(snapshot["snapshot"], true);
+    assert_eq!(snapshot["max_tokens"], 4096);
+    let content = snapshot["content"].as_str().unwrap();
+    assert!(content.contains("existing question"), "{content}");
+
+    // The watcher subscribed before the snapshot, so this reaches it
+    db.insert_message(&conv_id, "assistant", "live answer", 0, 0)
+        .unwrap();
+    db.broadcast_streaming_update(StreamingUpdate {
+        conversation_id: conv_id.clone(),
+        partial_content: String::new(),
+        tokens_used: 12,
+        max_tokens: 4096,
+        is_complete: true,
+    });
+
+    let live = next_json(&mut client_ws).await;
+    assert!(live.get("snapshot").is_none());
+    assert_eq!(live["tokens_used"], 12);
+    let content = live["content"].as_str().unwrap();
+    assert!(content.contains("existing question"), "{content}");
+    assert!(content.contains("live answer"), "{content}");
+
+    drop(client_ws);
+    server.await.unwrap().unwrap();
+}
+
+#[test]
+fn test_progress_throttle_cadence() {
+    use tokio::time::Instant;
+
+    // Fast stream: 100 tokens 1 ms apart → one progress message per 16 tokens
+    let start = Instant::now();
+    let mut throttle = ProgressThrottle::new(PROGRESS_INTERVAL, PROGRESS_EVERY_TOKENS, start);
+    let sent_at: Vec<u32> = (1..=100)
+        .filter(|&i| throttle.on_token(start + Duration::from_millis(i.into())))
+        .collect();
+    assert_eq!(sent_at, [16, 32, 48, 64, 80, 96]);
+
+    // Slow stream: a token every 100 ms → one message per 250 ms, not per token
+    let mut throttle = ProgressThrottle::new(PROGRESS_INTERVAL, PROGRESS_EVERY_TOKENS, start);
+    let sent_at: Vec<u32> = (1..=10)
+        .filter(|&i| throttle.on_token(start + Duration::from_millis(100 * u64::from(i))))
+        .collect();
+    assert_eq!(sent_at, [3, 6, 9]);
+
+    let msg = progress_message(120, 8192);
+    assert_eq!(msg["type"], "progress");
+    assert_eq!(msg["tokens_used"], 120);
+    assert_eq!(msg["max_tokens"], 8192);
+    assert!(msg.get("token").is_none());
+}
@@ -2,6 +2,7 @@
 
 use futures_util::{SinkExt, StreamExt};
 use hyper::upgrade::Upgraded;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::time::{Duration, Instant};
 use tokio_tungstenite::tungstenite::protocol::Message as WsMessage;
 use tokio_tungstenite::WebSocketStream;
@@ -9,7 +10,7 @@ use tokio_tungstenite::WebSocketStream;
 use llama_chat_db::SharedDatabase;
 use llama_chat_worker::worker::worker_bridge::SharedWorkerBridge;
 
-use super::ACTIVE_WS_CONNECTIONS;
+use super::{conversation_event_message, ACTIVE_WS_CONNECTIONS};
 use std::sync::atomic::Ordering;
 
 /// WebSocket handler for watching conversation updates via broadcast channel.
@@ -26,17 +27,37 @@ pub async fn handle_conversation_watch(
     )
     .await;
 
+    // Get max_tokens from bridge metadata (can't count tokens without model in-process)
+    let max_tokens = bridge
+        .model_status()
+        .await
+        .and_then(|m| m.context_length)
+        .map(|c| c as i32);
+
+    watch_conversation(ws_stream, conversation_id, max_tokens, db).await
+}
+
+/// Send a snapshot of the conversation as stored, then relay its live updates
+/// until the client disconnects or the conversation is deleted.
+pub(crate) async fn watch_conversation<S>(
+    ws_stream: WebSocketStream<S>,
+    conv_id: String,
+    max_tokens: Option<i32>,
+    db: SharedDatabase,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
     let _ = ACTIVE_WS_CONNECTIONS.fetch_add(1, Ordering::SeqCst);
 
-    let conv_id = conversation_id;
-
     sys_info!("[WS_WATCH] Watching conversation: {}", conv_id);
 
     // Subscribe to streaming updates FIRST (before reading initial content)
     // This prevents race conditions where generation completes before we subscribe
     let mut streaming_rx = db.subscribe_streaming();
+    let mut conversation_events = db.subscribe_conversation_events();
     sys_debug!("[WS_WATCH] Subscribed to streaming updates via broadcast channel");
 
     // Read initial content from database (now safe - we won't miss broadcasts)
@@ -56,16 +77,10 @@ pub async fn handle_conversation_watch(
         initial_content.len()
     );
 
-    // Get max_tokens from bridge metadata (can't count tokens without model in-process)
-    let max_tokens = bridge
-        .model_status()
-        .await
-        .and_then(|m| m.context_length)
-        .map(|c| c as i32);
-
     // Send initial content with token info
     let initial_msg = serde_json::json!({
         "type": "update",
+        "snapshot": true,
         "content": initial_content,
         "tokens_used": null,
         "max_tokens": max_tokens
@@ -80,6 +95,17 @@ pub async fn handle_conversation_watch(
 
     loop {
         tokio::select! {
+            // Deletions and renames of this conversation
+            Some(event) = conversation_events.recv() => {
+                if event.conversation_id() != conv_id {
+                    continue;
+                }
+                let msg = conversation_event_message(&event);
+                let _ = ws_sender.send(WsMessage::Text(msg.to_string())).await;
+                if matches!(event, llama_chat_db::ConversationEvent::Deleted { .. }) {
+                    break;
+                }
+            }
             // Receive streaming updates from broadcast channel
             update_result = streaming_rx.recv() => {
                 match update_result {
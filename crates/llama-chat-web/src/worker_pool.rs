@@ -7,21 +7,23 @@
 //! **Global agent workers** (`agent_workers`):
 //!   One per agent, created when the user clicks "Activate" in the agents modal.
 //!   Keeps the model warm in VRAM so the first conversation is instant.
-//!   Shared across conversations that use this agent — as long as only one is
-//!   active at a time.
+//!   Shared by every conversation that uses this agent. Concurrent requests
+//!   run in parallel inside the worker, up to `max_parallel_generations`.
 //!
-//! **Per-conversation overflow workers** (`conversation_workers`):
-//!   Spawned automatically when a second conversation tries to use an agent
-//!   whose global worker is already generating. Each overflow conversation gets
-//!   its own dedicated process, enabling true parallel inference.
-//!   Cleaned up when the agent is stopped or the conversation is removed.
+//! **Per-conversation workers** (`conversation_workers`):
+//!   Spawned lazily when a conversation uses a local agent that has no global
+//!   worker, or as an overflow worker when the global worker's generation
+//!   slots are all taken by other conversations. Cleaned up when the agent is
+//!   stopped or the conversation is removed.
 //!
 //! # Routing (`resolve_bridge_for_conversation`)
 //!
-//! 1. Conversation already has an overflow worker → use it.
+//! 1. Conversation already has its own worker → use it.
 //! 2. Conversation has a local agent with a global worker:
-//!    a. Global worker is free → use it.
-//!    b. Global worker is busy → spawn overflow worker, bind to conversation.
+//!    a. Global worker has a free generation slot, or is already generating
+//!       this conversation → use it.
+//!    b. Every slot is taken by other conversations → spawn overflow worker,
+//!       bind to conversation.
 //! 3. Conversation has a local agent but no global worker → spawn lazily.
 //! 4. Legacy `worker_id` on conversation → use that worker.
 //! 5. Default worker.
@@ -48,8 +50,8 @@ pub struct WorkerPool {
     workers: Arc<RwLock<HashMap<WorkerId, WorkerEntry>>>,
     /// agent_id → worker_id: one pre-loaded global worker per agent (Activate button).
     agent_workers: Arc<RwLock<HashMap<String, WorkerId>>>,
-    /// conversation_id → worker_id: overflow workers for parallel conversations.
-    /// Only populated when a second conversation needs the same agent simultaneously.
+    /// conversation_id → worker_id: workers spawned lazily for conversations whose
+    /// agent has no global worker.
     conversation_workers: Arc<RwLock<HashMap<String, WorkerId>>>,
     db_path: String,
     db: SharedDatabase,
@@ -283,6 +285,27 @@ impl WorkerPool {
         }
     }
 
+    /// Graceful variant of `shutdown_all` for server exit: every worker is sent
+    /// `Shutdown` in parallel and given `grace` to exit before being force-killed.
+    pub async fn shutdown_all_gracefully(&self, grace: std::time::Duration) {
+        let entries: Vec<WorkerEntry> = self
+            .workers
+            .write()
+            .ok()
+            .map(|mut w| w.drain().map(|(_, entry)| entry).collect())
+            .unwrap_or_default();
+        futures_util::future::join_all(
+            entries.iter().map(|entry| entry.bridge.shutdown(grace)),
+        )
+        .await;
+        if let Ok(mut m) = self.agent_workers.write() {
+            m.clear();
+        }
+        if let Ok(mut m) = self.conversation_workers.write() {
+            m.clear();
+        }
+    }
+
     /// Drop any agent/conversation bindings pointing at `worker_id`, then kill it so it
     /// fully releases memory. Routing respawns + reloads it lazily on next use.
     async fn evict_named_worker(&self, worker_id: &str) {
@@ -516,6 +539,13 @@ pub fn lookup_worker_id_for_conversation(
     db.get_conversation_worker_id(conversation_id).ok().flatten()
 }
 
+/// Whether a conversation needs its own overflow worker because the shared one
+/// already runs `active` generations for other conversations and has no slot
+/// left (`max_parallel_generations`, at least 1).
+fn needs_overflow_worker(active: usize, generating_this_conversation: bool, max_parallel: u32) -> bool {
+    !generating_this_conversation && active >= max_parallel.max(1) as usize
+}
+
 /// Resolve (or auto-spawn) the worker bridge for a conversation.
 ///
 /// See module-level doc for routing order.
@@ -525,12 +555,12 @@ pub async fn resolve_bridge_for_conversation(
     conversation_id: Option<&str>,
 ) -> Result<SharedWorkerBridge, String> {
     if let Some(conv_id) = conversation_id {
-        // 1. Conversation already has its own overflow worker.
+        // 1. Conversation already has its own worker.
         if let Some(worker_id) = pool.get_worker_for_conversation(conv_id) {
             if let Some(bridge) = pool.get(&worker_id) {
                 return Ok(bridge);
             }
-            // Overflow worker died — clean up and fall through.
+            // Its worker died — clean up and fall through.
             let _ = pool.unbind_conversation_worker(conv_id);
         }
 
@@ -547,20 +577,19 @@ pub async fn resolve_bridge_for_conversation(
                         let gpu_layers =
                             u32::try_from(agent.main_gpu).ok().filter(|&l| l > 0);
 
-                        // 2a. Global agent worker exists and is free → use it.
+                        // 2a. Global agent worker exists and has room → use it.
                         if let Some(global_wid) = pool.get_worker_for_agent(&agent_id) {
                             if let Some(bridge) = pool.get(&global_wid) {
-                                if !bridge.is_generating().await {
-                                    return Ok(bridge);
-                                }
-                                // 2b. Global worker is busy → spawn overflow only if it's
-                                // generating for a *different* conversation. If it's already
-                                // handling THIS conversation, just reuse it.
-                                if bridge.active_conversation_id().await.as_deref()
-                                    == Some(conv_id)
-                                {
+                                let max_parallel = db.load_config().max_parallel_generations;
+                                if !needs_overflow_worker(
+                                    bridge.active_generation_count().await,
+                                    bridge.is_generating_conversation(conv_id).await,
+                                    max_parallel,
+                                ) {
                                     return Ok(bridge);
                                 }
+                                // 2b. Every slot is busy with other conversations → spawn
+                                // an overflow worker; the global one would reject it.
                                 match pool
                                     .spawn_worker_for_conversation(
                                         conv_id,
@@ -782,3 +811,25 @@ fn total_gpu_vram_bytes() -> u64 {
 fn model_file_size(path: &str) -> u64 {
     std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_concurrent_conversation_gets_an_overflow_worker_by_default() {
+        let db = llama_chat_db::Database::new(":memory:").unwrap();
+        let max_parallel = db.load_config().max_parallel_generations;
+        assert_eq!(max_parallel, 1);
+
+        // Conversation A is generating on the shared worker: B can't share it
+        assert!(needs_overflow_worker(1, false, max_parallel));
+        // A's own follow-up stays on the worker that already serves it
+        assert!(!needs_overflow_worker(1, true, max_parallel));
+        // An idle worker takes anyone
+        assert!(!needs_overflow_worker(0, false, max_parallel));
+        // With more slots, B runs next to A on the shared worker
+        assert!(!needs_overflow_worker(1, false, 2));
+        assert!(needs_overflow_worker(2, false, 2));
+    }
+}
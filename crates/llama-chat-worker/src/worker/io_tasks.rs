@@ -9,26 +9,76 @@ use std::io::{BufRead, BufReader, Write};
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, oneshot, Mutex as TokioMutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex as TokioMutex};
 
 use super::ipc_types::*;
-use super::worker_bridge::{ActiveGeneration, ModelMeta, PendingRequest};
+use super::worker_bridge::{ActiveGeneration, ActiveGenerations, ModelMeta, PendingRequest};
 use llama_chat_db::{conversation::ConversationLogger, SharedDatabase};
-use llama_chat_types::models::TokenData;
+use llama_chat_types::models::{LoadPhase, LoadProgressEvent, TokenData};
 
 pub const MAX_AUTO_RECOVERY_CRASHES: u32 = 2;
 
 /// Best-effort: write a system-role notice into the conversation so a crash or a
 /// failed auto-recovery attempt is visible in conversation history, not just the
 /// transient live token stream (which the UI never persists).
-fn persist_crash_notice(db: &SharedDatabase, conversation_id: Option<&str>, notice: &str) {
-    let Some(conv_id) = conversation_id else {
+fn persist_crash_notice(db: &SharedDatabase, conversation_ids: &[String], notice: &str) {
+    for conv_id in conversation_ids {
+        match ConversationLogger::from_existing(db.clone(), conv_id) {
+            Ok(mut logger) => logger.log_message("system", notice),
+            Err(e) => {
+                eprintln!("[BRIDGE] Failed to persist crash notice to conversation {conv_id}: {e}")
+            }
+        }
+    }
+}
+
+/// Conversations of the generations the crashed worker was running, oldest
+/// request first and each conversation once.
+fn conversations_by_age(gens: &HashMap<u64, ActiveGeneration>) -> Vec<String> {
+    let mut by_age: Vec<_> = gens.values().collect();
+    by_age.sort_by_key(|ag| ag.request_id);
+    let mut conversations: Vec<String> = Vec::new();
+    for conv_id in by_age.into_iter().filter_map(|ag| ag.conversation_id.as_ref()) {
+        if !conversations.contains(conv_id) {
+            conversations.push(conv_id.clone());
+        }
+    }
+    conversations
+}
+
+/// Re-send a chat template override to a freshly reloaded worker and mirror
+/// the result into the cached model metadata.
+async fn reapply_template_override(
+    template: String,
+    request_id: u64,
+    pending: &Arc<TokioMutex<HashMap<u64, PendingRequest>>>,
+    cmd_tx: &Arc<TokioMutex<mpsc::UnboundedSender<String>>>,
+    model_meta: &Arc<TokioMutex<Option<ModelMeta>>>,
+) {
+    let request = WorkerRequest {
+        id: request_id,
+        command: WorkerCommand::SetTemplateOverride { template: Some(template) },
+    };
+    let Ok(json) = serde_json::to_string(&request) else {
         return;
     };
-    match ConversationLogger::from_existing(db.clone(), conv_id) {
-        Ok(mut logger) => logger.log_message("system", notice),
-        Err(e) => {
-            eprintln!("[BRIDGE] Failed to persist crash notice to conversation {conv_id}: {e}")
+    let (tx, rx) = oneshot::channel::<WorkerPayload>();
+    pending.lock().await.insert(request_id, PendingRequest { tx });
+    let _ = cmd_tx.lock().await.send(json);
+
+    match tokio::time::timeout(std::time::Duration::from_secs(10), rx).await {
+        Ok(Ok(WorkerPayload::TemplateOverrideSet { template })) => {
+            eprintln!("[BRIDGE] Template override restored: {template:?}");
+            if let Some(meta) = model_meta.lock().await.as_mut() {
+                meta.template_override = template;
+            }
+        }
+        Ok(Ok(WorkerPayload::Error { message })) => {
+            eprintln!("[BRIDGE] Failed to restore template override: {message}");
+        }
+        _ => {
+            pending.lock().await.remove(&request_id);
+            eprintln!("[BRIDGE] Template override restore: timeout or unexpected response");
         }
     }
 }
@@ -38,11 +88,36 @@ fn persist_crash_notice(db: &SharedDatabase, conversation_id: Option<&str>, noti
 pub struct CrashRecoveryCtx {
     pub model_path: Option<String>,
     pub gpu_layers: Option<u32>,
-    pub conversation_id: Option<String>,
+    /// Conversations that were generating when the worker crashed, oldest first.
+    /// All of them are resumed after the reload.
+    pub conversation_ids: Vec<String>,
     pub agent_id: Option<String>,
+    /// Chat template override in effect before the crash; re-sent after the reload
+    /// because a respawned worker starts without one.
+    pub template_override: Option<String>,
     pub crash_count: u32,
 }
 
+/// Map a worker payload to the load-progress event clients should see, if any.
+/// `ModelLoaded` is the completion marker for both requested and auto-recovery loads.
+pub(crate) fn load_event_for(payload: &WorkerPayload) -> Option<LoadProgressEvent> {
+    match payload {
+        WorkerPayload::LoadingProgress { progress, phase } => {
+            let phase = phase.unwrap_or(if *progress > 100 {
+                LoadPhase::WarmingUp
+            } else {
+                LoadPhase::Allocating
+            });
+            Some(LoadProgressEvent::new(phase, (*progress).min(100)))
+        }
+        WorkerPayload::ModelLoaded { model_path, .. } => Some(LoadProgressEvent {
+            model_path: Some(model_path.clone()),
+            ..LoadProgressEvent::new(LoadPhase::Complete, 100)
+        }),
+        _ => None,
+    }
+}
+
 /// Task that writes serialized commands to the worker's stdin.
 pub async fn stdin_writer_task(
     mut cmd_rx: mpsc::UnboundedReceiver<String>,
@@ -72,10 +147,11 @@ pub async fn stdin_writer_task(
 pub async fn stdout_reader_task(
     stdout: std::process::ChildStdout,
     pending: Arc<TokioMutex<HashMap<u64, PendingRequest>>>,
-    active_generation: Arc<TokioMutex<Option<ActiveGeneration>>>,
+    active_generation: ActiveGenerations,
     model_meta: Arc<TokioMutex<Option<ModelMeta>>>,
     last_model_path: Arc<TokioMutex<Option<String>>>,
     loading_progress: Arc<AtomicU8>,
+    load_events: broadcast::Sender<LoadProgressEvent>,
     process_manager: Arc<super::process_manager::ProcessManager>,
     cmd_tx: Arc<TokioMutex<mpsc::UnboundedSender<String>>>,
     recovery_ctx: Arc<TokioMutex<CrashRecoveryCtx>>,
@@ -127,38 +203,49 @@ pub async fn stdout_reader_task(
             max_tokens,
             status,
             tool_timing,
+            prompt_eval_progress,
+            logprobs,
+            reasoning,
+            confirmation_required,
+            token_id,
+            prompt_echo,
         } = payload
         {
-            let gen = active_generation.lock().await;
-            if let Some(ref ag) = *gen {
-                if ag.request_id == id {
-                    let _ = ag.token_tx.send(TokenData {
-                        token,
-                        tokens_used,
-                        max_tokens,
-                        status,
-                        tool_timing,
-                        ..Default::default()
-                    });
-                    continue;
-                }
+            if let Some(ag) = active_generation.lock().await.get(&id) {
+                let _ = ag.token_tx.send(TokenData {
+                    token,
+                    tokens_used,
+                    max_tokens,
+                    status,
+                    tool_timing,
+                    prompt_eval_progress,
+                    logprobs,
+                    reasoning,
+                    confirmation_required,
+                    token_id,
+                    prompt_echo,
+                    ..Default::default()
+                });
             }
             continue;
         }
 
         // Handle generation started — update active conversation ID
         if let WorkerPayload::GenerationStarted { conversation_id } = &payload {
-            let mut gen = active_generation.lock().await;
-            if let Some(ref mut ag) = *gen {
-                if ag.request_id == id {
-                    ag.conversation_id = Some(conversation_id.clone());
-                }
+            if let Some(ag) = active_generation.lock().await.get_mut(&id) {
+                ag.conversation_id = Some(conversation_id.clone());
             }
             continue;
         }
 
+        // Forward load progress/completion to status WebSocket subscribers
+        if let Some(event) = load_event_for(&payload) {
+            // No subscribers is fine — the atomic below still backs polling
+            let _ = load_events.send(event);
+        }
+
         // Handle loading progress — update atomic, don't dispatch
-        if let WorkerPayload::LoadingProgress { progress } = payload {
+        if let WorkerPayload::LoadingProgress { progress, .. } = payload {
             loading_progress.store(progress, Ordering::Relaxed);
             continue;
         }
@@ -174,6 +261,9 @@ pub async fn stdout_reader_task(
             has_vision,
             gpu_layers,
             block_count,
+            load_outcome,
+            effective_backend,
+            recommended_sampling,
         } = &payload
         {
             let supports_thinking = chat_template_string
@@ -186,11 +276,15 @@ pub async fn stdout_reader_task(
                 model_path: model_path.clone(),
                 context_length: *context_length,
                 chat_template_type: chat_template_type.clone(),
+                template_override: None,
                 general_name: general_name.clone(),
                 has_vision: has_vision.unwrap_or(false),
                 gpu_layers: *gpu_layers,
                 block_count: *block_count,
                 supports_thinking,
+                load_outcome: *load_outcome,
+                effective_backend: effective_backend.clone(),
+                recommended_sampling: *recommended_sampling,
             });
             eprintln!("[BRIDGE] Model metadata cached: {model_path}");
         }
@@ -243,22 +337,19 @@ pub async fn stdout_reader_task(
             if let Some(meta) = model_meta.lock().await.as_ref() {
                 ctx.model_path = Some(meta.model_path.clone());
                 ctx.gpu_layers = meta.gpu_layers;
+                ctx.template_override = meta.template_override.clone();
             }
-            // Save conversation ID from active generation (if any)
-            if let Some(conv_id) = active_generation
-                .lock()
-                .await
-                .as_ref()
-                .and_then(|ag| ag.conversation_id.clone())
-            {
-                ctx.conversation_id = Some(conv_id);
+            // Save every conversation that was generating (parallel generations)
+            let conversations = conversations_by_age(&*active_generation.lock().await);
+            if !conversations.is_empty() {
+                ctx.conversation_ids = conversations;
             }
             ctx.crash_count += 1;
             eprintln!(
-                "[BRIDGE] Crash #{} — model={:?} conv={:?}",
+                "[BRIDGE] Crash #{} — model={:?} convs={:?}",
                 ctx.crash_count,
                 ctx.model_path.as_deref(),
-                ctx.conversation_id.as_deref()
+                ctx.conversation_ids
             );
             (ctx.crash_count, ctx.model_path.is_some())
         };
@@ -266,9 +357,23 @@ pub async fn stdout_reader_task(
         // If within auto-recovery limit, keep the UI spinner and don't show crash message.
         // Otherwise, clear generation and notify the UI.
         let will_auto_recover = crash_count <= MAX_AUTO_RECOVERY_CRASHES && has_model;
+
+        // The dead worker's replies won't finish; keep what it saved
+        let crashed_conversations: Vec<String> = active_generation
+            .lock()
+            .await
+            .values()
+            .filter_map(|ag| ag.conversation_id.clone())
+            .collect();
+        for conv_id in &crashed_conversations {
+            if let Err(e) = db.recover_interrupted_messages_in(conv_id) {
+                eprintln!("[BRIDGE] Failed to flag interrupted reply in {conv_id}: {e}");
+            }
+        }
+
         if !will_auto_recover {
-            let mut gen = active_generation.lock().await;
-            if let Some(ag) = gen.take() {
+            let mut gens = active_generation.lock().await;
+            for (_, ag) in gens.drain() {
                 let _ = ag.token_tx.send(TokenData {
                     token: "\n\n[Worker process crashed — restarting automatically.]".to_string(),
                     tokens_used: 0,
@@ -285,8 +390,8 @@ pub async fn stdout_reader_task(
         } else {
             // Drop the old active generation channels (they're connected to the dead worker)
             // but don't send any crash message to the UI
-            let mut gen = active_generation.lock().await;
-            if let Some(ag) = gen.take() {
+            let mut gens = active_generation.lock().await;
+            for (_, ag) in gens.drain() {
                 // Resolve the pending request silently so it doesn't hang
                 if let Some(req) = pending.lock().await.remove(&ag.request_id) {
                     let _ = req.tx.send(WorkerPayload::Error {
@@ -321,7 +426,7 @@ pub async fn stdout_reader_task(
             );
             persist_crash_notice(
                 &db,
-                ctx.conversation_id.as_deref(),
+                &ctx.conversation_ids,
                 &format!(
                     "❌ Worker process crashed (#{}) and auto-recovery gave up — please reload \
                      the model manually to continue.",
@@ -356,6 +461,7 @@ pub async fn stdout_reader_task(
                             model_meta,
                             last_model_path,
                             loading_progress,
+                            load_events,
                             process_manager,
                             ct,
                             rc,
@@ -382,6 +488,7 @@ pub async fn stdout_reader_task(
                 let mm = model_meta.clone();
                 let lmp = last_model_path.clone();
                 let lp = loading_progress.clone();
+                let le = load_events.clone();
                 let pm = process_manager.clone();
                 let ct = cmd_tx.clone();
                 let rc = recovery_ctx.clone();
@@ -418,6 +525,8 @@ pub async fn stdout_reader_task(
                                     gpu_layers: ctx.gpu_layers,
                                     mmproj_path: None,
                                     agent_id: ctx.agent_id.clone(),
+                                    // Recovery reloads follow the saved config's cache types
+                                    kv_cache_type: Default::default(),
                                 },
                             };
                             if let Ok(json) = serde_json::to_string(&load_req) {
@@ -433,6 +542,7 @@ pub async fn stdout_reader_task(
                                     let mm2 = mm.clone();
                                     let lmp2 = lmp.clone();
                                     let lp2 = lp.clone();
+                                    let le2 = le.clone();
                                     let pm2 = pm.clone();
                                     let ct2 = ct.clone();
                                     let rc2 = rc.clone();
@@ -442,8 +552,8 @@ pub async fn stdout_reader_task(
                                     let gen2 = pm2.generation();
                                     Some(tokio::task::spawn_local(async move {
                                         stdout_reader_task(
-                                            stdout, p2, ag2, mm2, lmp2, lp2, pm2, ct2, rc2, ar2,
-                                            sm2, db2, gen2,
+                                            stdout, p2, ag2, mm2, lmp2, lp2, le2, pm2, ct2, rc2,
+                                            ar2, sm2, db2, gen2,
                                         )
                                         .await;
                                     }))
@@ -460,24 +570,33 @@ pub async fn stdout_reader_task(
                                 {
                                     Ok(Ok(WorkerPayload::ModelLoaded { .. })) => {
                                         eprintln!("[BRIDGE] Model auto-reloaded successfully");
+                                        if let Some(template) = ctx.template_override.clone() {
+                                            let id = 905_000 + ctx.crash_count as u64;
+                                            reapply_template_override(template, id, &p, &ct, &mm)
+                                                .await;
+                                        }
                                         // Clear auto_recovering — model is loaded, frontend won't race
                                         ar.store(false, Ordering::SeqCst);
-                                        if let Some(ref conv_id) = ctx.conversation_id {
+                                        for (i, conv_id) in ctx.conversation_ids.iter().enumerate() {
                                             eprintln!(
                                                 "[BRIDGE] Auto-continuing generation for {conv_id} (crash #{})",
                                                 ctx.crash_count
                                             );
-                                            let gen_id: u64 = 910_000 + ctx.crash_count as u64;
+                                            let gen_id: u64 =
+                                                910_000 + ctx.crash_count as u64 * 1_000 + i as u64;
 
                                             // Register as active generation so status API reports
                                             // active_conversation_id (sidebar green dot, frontend reconnect)
                                             let (token_tx, _token_rx) =
                                                 mpsc::unbounded_channel::<TokenData>();
-                                            *ag.lock().await = Some(ActiveGeneration {
-                                                request_id: gen_id,
-                                                token_tx,
-                                                conversation_id: Some(conv_id.clone()),
-                                            });
+                                            ag.lock().await.insert(
+                                                gen_id,
+                                                ActiveGeneration {
+                                                    request_id: gen_id,
+                                                    token_tx,
+                                                    conversation_id: Some(conv_id.clone()),
+                                                },
+                                            );
 
                                             let gen_req = WorkerRequest {
                                                 id: gen_id,
@@ -487,6 +606,8 @@ pub async fn stdout_reader_task(
                                                     skip_user_logging: true,
                                                     image_data: None,
                                                     agent_id: ctx.agent_id.clone(),
+                                                    on_overflow: Default::default(),
+                                                    overrides: Default::default(),
                                                 },
                                             };
                                             if let Ok(json) = serde_json::to_string(&gen_req) {
@@ -504,7 +625,7 @@ pub async fn stdout_reader_task(
                                                 let ag3 = ag.clone();
                                                 tokio::task::spawn_local(async move {
                                                     let _ = gen_rx.await;
-                                                    ag3.lock().await.take();
+                                                    ag3.lock().await.remove(&gen_id);
                                                     eprintln!("[BRIDGE] Auto-continue generation cleared");
                                                 });
                                             }
@@ -515,7 +636,7 @@ pub async fn stdout_reader_task(
                                         ar.store(false, Ordering::SeqCst);
                                         persist_crash_notice(
                                             &db,
-                                            ctx.conversation_id.as_deref(),
+                                            &ctx.conversation_ids,
                                             &format!(
                                                 "❌ Auto-recovery failed to reload the model \
                                                  after crash #{}: {message}. Please reload the \
@@ -531,7 +652,7 @@ pub async fn stdout_reader_task(
                                         ar.store(false, Ordering::SeqCst);
                                         persist_crash_notice(
                                             &db,
-                                            ctx.conversation_id.as_deref(),
+                                            &ctx.conversation_ids,
                                             &format!(
                                                 "❌ Auto-recovery timed out reloading the model \
                                                  after crash #{} (120s limit). Please reload the \
@@ -561,7 +682,8 @@ pub async fn stdout_reader_task(
                                 );
                                 let gen = pm.generation();
                                 stdout_reader_task(
-                                    stdout, p, ag, mm, lmp, lp, pm, ct, rc, ar, sm.clone(), db, gen,
+                                    stdout, p, ag, mm, lmp, lp, le, pm, ct, rc, ar, sm.clone(), db,
+                                    gen,
                                 )
                                 .await;
                             }
@@ -573,3 +695,72 @@ pub async fn stdout_reader_task(
     }
     eprintln!("[BRIDGE] Stdout reader task exiting (old worker)");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llama_chat_types::models::LoadOutcome;
+
+    #[tokio::test]
+    async fn test_template_override_is_resent_after_reload() {
+        let pending: Arc<TokioMutex<HashMap<u64, PendingRequest>>> = Default::default();
+        let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+        let cmd_tx = Arc::new(TokioMutex::new(tx));
+        let meta = Arc::new(TokioMutex::new(Some(ModelMeta {
+            loaded: true,
+            model_path: "/models/a.gguf".to_string(),
+            context_length: None,
+            chat_template_type: Some("ChatML".to_string()),
+            template_override: None,
+            general_name: None,
+            has_vision: false,
+            gpu_layers: None,
+            block_count: None,
+            supports_thinking: false,
+            load_outcome: LoadOutcome::FullReload,
+            effective_backend: None,
+            recommended_sampling: None,
+        })));
+
+        // Stand-in worker: answer the command the way handle_set_template_override does
+        let worker_pending = pending.clone();
+        let worker = tokio::spawn(async move {
+            let request: WorkerRequest = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+            let WorkerCommand::SetTemplateOverride { template } = request.command else {
+                panic!("expected SetTemplateOverride");
+            };
+            let reply = worker_pending.lock().await.remove(&request.id).unwrap();
+            let _ = reply.tx.send(WorkerPayload::TemplateOverrideSet { template });
+        });
+
+        reapply_template_override("Mistral".to_string(), 905_001, &pending, &cmd_tx, &meta).await;
+        worker.await.unwrap();
+
+        let meta = meta.lock().await;
+        assert_eq!(meta.as_ref().unwrap().template_override.as_deref(), Some("Mistral"));
+        assert!(pending.lock().await.is_empty());
+    }
+
+    #[test]
+    fn test_legacy_progress_without_phase() {
+        let event = load_event_for(&WorkerPayload::LoadingProgress { progress: 30, phase: None }).unwrap();
+        assert_eq!(event, LoadProgressEvent::new(LoadPhase::Allocating, 30));
+        let event = load_event_for(&WorkerPayload::LoadingProgress { progress: 101, phase: None }).unwrap();
+        assert_eq!(event.phase, LoadPhase::WarmingUp);
+        assert!(load_event_for(&WorkerPayload::Pong).is_none());
+    }
+
+    #[test]
+    fn test_crash_resumes_every_generating_conversation() {
+        let gens: HashMap<u64, ActiveGeneration> = [(7, Some("b")), (3, Some("a")), (9, None), (8, Some("a"))]
+            .into_iter()
+            .map(|(request_id, conv)| {
+                let (token_tx, _) = mpsc::unbounded_channel::<TokenData>();
+                let conversation_id = conv.map(str::to_string);
+                (request_id, ActiveGeneration { request_id, token_tx, conversation_id })
+            })
+            .collect();
+        assert_eq!(conversations_by_age(&gens), ["a", "b"]);
+        assert!(conversations_by_age(&HashMap::new()).is_empty());
+    }
+}
@@ -3,9 +3,14 @@
 //! Spawns the worker as a child process (same binary with `--worker` flag),
 //! monitors its health, and restarts it on crash.
 
-use std::process::{Child, Command, Stdio};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStderr, Command, Stdio};
 use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Poll interval while waiting for the worker to exit on its own.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(25);
 
 /// Manages the worker child process lifecycle.
 pub struct ProcessManager {
@@ -20,20 +25,34 @@ pub struct ProcessManager {
     /// Set to true when this worker is intentionally shut down.
     /// The stdout reader task checks this before attempting crash recovery.
     is_shutdown: AtomicBool,
+    /// Spawn workers with the GPUs hidden. Set after a CUDA driver mismatch,
+    /// by the bridge or by the stderr forwarder when the worker aborts natively.
+    cpu_fallback: Arc<AtomicBool>,
+    /// Whether the running worker was spawned with `cpu_fallback` set.
+    running_on_cpu: AtomicBool,
 }
 
 impl ProcessManager {
     /// Spawn a new worker process.
     pub fn spawn(db_path: &str) -> Result<Self, String> {
-        let child = spawn_worker(db_path)?;
+        let cpu_fallback = Arc::new(AtomicBool::new(false));
+        let child = spawn_worker(db_path, &cpu_fallback)?;
+        let mut pm = Self::from_child(child, db_path);
+        pm.cpu_fallback = cpu_fallback;
+        Ok(pm)
+    }
 
-        Ok(Self {
+    /// Wrap an already-spawned worker process (tests drive a shell stand-in).
+    pub fn from_child(child: Child, db_path: &str) -> Self {
+        Self {
             child: Mutex::new(Some(child)),
             db_path: db_path.to_string(),
             restart_count: AtomicU32::new(0),
             generation: AtomicU64::new(0),
             is_shutdown: AtomicBool::new(false),
-        })
+            cpu_fallback: Arc::new(AtomicBool::new(false)),
+            running_on_cpu: AtomicBool::new(false),
+        }
     }
 
     /// Take the child's stdin handle for writing commands.
@@ -72,20 +91,70 @@ impl ProcessManager {
         }
     }
 
+    /// Mark the worker as intentionally shutting down so the stdout reader
+    /// treats the coming EOF as an exit, not a crash to recover from.
+    pub fn begin_shutdown(&self) {
+        self.is_shutdown.store(true, Ordering::SeqCst);
+    }
+
+    /// Wait up to `grace` for the worker to exit on its own (after a `Shutdown`
+    /// command), then force-kill it. Returns true if it exited within the grace period.
+    ///
+    /// Blocking — call from `spawn_blocking` in async code.
+    pub fn wait_or_kill(&self, grace: Duration) -> bool {
+        self.begin_shutdown();
+        let deadline = Instant::now() + grace;
+        loop {
+            let exited = match self.child.lock() {
+                Ok(mut guard) => match guard.as_mut() {
+                    Some(child) => !matches!(child.try_wait(), Ok(None)),
+                    None => true,
+                },
+                Err(_) => false,
+            };
+            if exited {
+                if let Ok(mut guard) = self.child.lock() {
+                    *guard = None;
+                }
+                eprintln!("[PROCESS_MGR] Worker exited gracefully");
+                return true;
+            }
+            if Instant::now() >= deadline {
+                eprintln!("[PROCESS_MGR] Worker did not exit within {grace:?}, force-killing");
+                self.kill();
+                return false;
+            }
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+
     /// Returns true if this worker was intentionally shut down (not a crash).
     pub fn is_shutdown(&self) -> bool {
         self.is_shutdown.load(Ordering::SeqCst)
     }
 
+    /// Spawn every later worker on the CPU, with the GPUs hidden. Takes effect
+    /// on the next `restart`.
+    pub fn fall_back_to_cpu(&self) {
+        self.cpu_fallback.store(true, Ordering::SeqCst);
+    }
+
+    /// True if the running worker was spawned on the CPU after a driver mismatch.
+    pub fn running_on_cpu(&self) -> bool {
+        self.running_on_cpu.load(Ordering::SeqCst)
+    }
+
     /// Restart the worker process (after kill or crash).
     pub fn restart(&self) -> Result<(), String> {
         // Kill existing if still alive
         self.kill();
 
-        let child = spawn_worker(&self.db_path)?;
+        let child = spawn_worker(&self.db_path, &self.cpu_fallback)?;
         if let Ok(mut guard) = self.child.lock() {
             *guard = Some(child);
         }
+        self.running_on_cpu
+            .store(self.cpu_fallback.load(Ordering::SeqCst), Ordering::SeqCst);
         self.restart_count.fetch_add(1, Ordering::Relaxed);
         self.generation.fetch_add(1, Ordering::SeqCst);
         // kill() (called above) latches is_shutdown=true so the dying reader skips crash
@@ -116,26 +185,159 @@ impl Drop for ProcessManager {
 }
 
 /// Spawn a worker child process using the current executable.
-fn spawn_worker(db_path: &str) -> Result<Child, String> {
+fn spawn_worker(db_path: &str, cpu_fallback: &Arc<AtomicBool>) -> Result<Child, String> {
     let exe = std::env::current_exe().map_err(|e| format!("Cannot find own executable: {e}"))?;
 
     eprintln!("[PROCESS_MGR] Spawning worker: {} --worker --db-path {db_path}", exe.display());
 
+    let mut cmd = worker_command(exe, db_path, cpu_fallback.load(Ordering::SeqCst));
+
+    // On Windows, prevent the worker from opening a visible console window
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to spawn worker: {e}"))?;
+    if let Some(stderr) = child.stderr.take() {
+        forward_worker_stderr(stderr, cpu_fallback.clone());
+    }
+    Ok(child)
+}
+
+/// The worker command line. On the CPU fallback the GPUs are hidden in the
+/// child's environment, so ggml-cuda registers no device there.
+fn worker_command(exe: impl AsRef<std::ffi::OsStr>, db_path: &str, cpu_fallback: bool) -> Command {
     let mut cmd = Command::new(exe);
     cmd.arg("--worker")
         .arg("--db-path")
         .arg(db_path)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::inherit()); // Worker logs go to parent's stderr
+        .stderr(Stdio::piped()); // Echoed to our stderr by forward_worker_stderr
+    if cpu_fallback {
+        cmd.env("CUDA_VISIBLE_DEVICES", "")
+            .env(llama_chat_engine::gpu_devices::CPU_FALLBACK_ENV, "1");
+    }
+    cmd
+}
 
-    // On Windows, prevent the worker from opening a visible console window
-    #[cfg(windows)]
-    {
-        use std::os::windows::process::CommandExt;
-        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+/// Echo the worker's stderr to ours and copy each line into the log buffer
+/// behind `/api/logs/stream`. Reads bytes, not `lines()`: native code can
+/// write invalid UTF-8, and stopping early would fill the pipe and block the worker.
+/// A CUDA driver mismatch sets `cpu_fallback`, so the crash-recovery restart
+/// comes up on the CPU.
+fn forward_worker_stderr(stderr: ChildStderr, cpu_fallback: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(stderr);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let _ = std::io::stderr().write_all(&buf);
+                    let line = String::from_utf8_lossy(&buf);
+                    let line = line.trim_end();
+                    if !line.is_empty() {
+                        llama_chat_types::log_buffer::LOG_BUFFER.push(
+                            worker_line_level(line),
+                            "worker",
+                            line,
+                        );
+                        // A driver mismatch aborts natively in the worker; say why here
+                        if llama_chat_engine::gpu_devices::is_cuda_driver_mismatch(line) {
+                            cpu_fallback.store(true, Ordering::SeqCst);
+                            let notice = format!(
+                                "CUDA driver/runtime mismatch detected; the worker restarts on the CPU. {}",
+                                llama_chat_engine::gpu_devices::CUDA_DRIVER_REMEDIATION
+                            );
+                            eprintln!("[BRIDGE] {notice}");
+                            llama_chat_types::log_buffer::LOG_BUFFER.push("ERROR", "worker", &notice);
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Worker stderr has no structured level; infer one from the text.
+fn worker_line_level(line: &str) -> &'static str {
+    let upper = line.to_ascii_uppercase();
+    if upper.contains("ERROR") || upper.contains("PANIC") {
+        "ERROR"
+    } else if upper.contains("WARN") {
+        "WARN"
+    } else {
+        "INFO"
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn spawn_mock_worker(script: &str) -> ProcessManager {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawn mock worker");
+        ProcessManager::from_child(child, ":memory:")
+    }
+
+    #[test]
+    fn test_wait_or_kill_reaps_worker_that_exits() {
+        let pm = spawn_mock_worker("exit 0");
+        let started = Instant::now();
+        assert!(pm.wait_or_kill(Duration::from_secs(5)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(pm.is_shutdown());
+        assert!(pm.child.lock().unwrap().is_none());
     }
 
-    cmd.spawn()
-        .map_err(|e| format!("Failed to spawn worker: {e}"))
+    #[test]
+    fn test_wait_or_kill_force_kills_unresponsive_worker() {
+        let pm = spawn_mock_worker("sleep 30");
+        let started = Instant::now();
+        assert!(!pm.wait_or_kill(Duration::from_millis(200)));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(pm.child.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cpu_fallback_worker_hides_the_gpus() {
+        let env = |cmd: &Command| -> Vec<(String, String)> {
+            cmd.get_envs()
+                .map(|(k, v)| {
+                    let v = v.map(|v| v.to_string_lossy().to_string()).unwrap_or_default();
+                    (k.to_string_lossy().to_string(), v)
+                })
+                .collect()
+        };
+        assert!(env(&worker_command("llama_chat", "db.sqlite", false)).is_empty());
+        let cpu = env(&worker_command("llama_chat", "db.sqlite", true));
+        assert!(cpu.contains(&("CUDA_VISIBLE_DEVICES".to_string(), String::new())), "{cpu:?}");
+        assert!(cpu.iter().any(|(k, _)| k == llama_chat_engine::gpu_devices::CPU_FALLBACK_ENV));
+
+        // The flag only reaches workers spawned after it is set
+        let pm = spawn_mock_worker("exit 0");
+        pm.fall_back_to_cpu();
+        assert!(!pm.running_on_cpu());
+        assert!(pm.cpu_fallback.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_worker_line_level_inferred_from_text() {
+        assert_eq!(worker_line_level("[WORKER] Generation thread panicked: boom"), "ERROR");
+        assert_eq!(worker_line_level("[WORKER] Failed: error loading model"), "ERROR");
+        assert_eq!(worker_line_level("WARNING: GGUF EOS token mismatch"), "WARN");
+        assert_eq!(worker_line_level("[WORKER] Model loaded successfully"), "INFO");
+    }
 }
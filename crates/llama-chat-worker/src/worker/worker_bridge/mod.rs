@@ -7,17 +7,22 @@ use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, oneshot, Mutex as TokioMutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex as TokioMutex};
 use tokio::time::{timeout, Duration};
 
 use super::io_tasks::{stdin_writer_task, stdout_reader_task, CrashRecoveryCtx};
 use super::ipc_types::*;
 use super::process_manager::ProcessManager;
 use llama_chat_db::SharedDatabase;
-use llama_chat_types::models::TokenData;
+use llama_chat_types::models::{KvCacheTypes, LoadPhase, LoadProgressEvent, TokenData};
 
 mod types;
-pub use types::{ActiveGeneration, GenerationResult, ModelMeta, PendingRequest};
+#[cfg(all(test, unix))]
+mod tests;
+pub use types::{
+    ActiveGeneration, ActiveGenerations, FinishReasons, GenerateOptions, GenerationResult, ModelMeta,
+    PendingRequest,
+};
 use types::oneshot_adapter;
 
 /// Shared reference to the WorkerBridge.
@@ -30,8 +35,8 @@ pub struct WorkerBridge {
     cmd_tx: Arc<TokioMutex<mpsc::UnboundedSender<String>>>,
     /// Tracks pending requests awaiting a response.
     pending: Arc<TokioMutex<HashMap<u64, PendingRequest>>>,
-    /// Active generation token forwarding.
-    active_generation: Arc<TokioMutex<Option<ActiveGeneration>>>,
+    /// Active generations' token forwarding, by request ID.
+    active_generation: ActiveGenerations,
     /// Cached model metadata.
     model_meta: Arc<TokioMutex<Option<ModelMeta>>>,
     /// True while a model load is in progress.
@@ -40,14 +45,16 @@ pub struct WorkerBridge {
     auto_recovering: Arc<AtomicBool>,
     /// Model loading progress (0-100), updated by stdout reader from worker IPC.
     loading_progress: Arc<AtomicU8>,
+    /// Load phase/progress events for streaming clients (status WebSocket).
+    load_events: broadcast::Sender<LoadProgressEvent>,
     /// Model path being loaded (for status reporting during load).
     loading_model_path: Arc<TokioMutex<Option<String>>>,
     /// Last successfully loaded model path — never cleared so it survives crash-recovery cycles.
     last_model_path: Arc<TokioMutex<Option<String>>>,
     /// Status message (e.g. "Compacting conversation (5/43)") visible via API.
     status_message: Arc<TokioMutex<Option<String>>>,
-    /// Last generation finish reason (for polling-based auto-continue).
-    last_finish_reason: Arc<TokioMutex<Option<String>>>,
+    /// Generation finish reasons per conversation (for polling-based auto-continue).
+    finish_reasons: Arc<TokioMutex<FinishReasons>>,
     /// Next request ID counter.
     next_id: AtomicU64,
     /// Process manager for kill/restart.
@@ -75,10 +82,10 @@ impl WorkerBridge {
 
         let pending: Arc<TokioMutex<HashMap<u64, PendingRequest>>> =
             Arc::new(TokioMutex::new(HashMap::new()));
-        let active_generation: Arc<TokioMutex<Option<ActiveGeneration>>> =
-            Arc::new(TokioMutex::new(None));
+        let active_generation: ActiveGenerations = Arc::new(TokioMutex::new(HashMap::new()));
         let model_meta: Arc<TokioMutex<Option<ModelMeta>>> = Arc::new(TokioMutex::new(None));
         let loading_progress: Arc<AtomicU8> = Arc::new(AtomicU8::new(0));
+        let (load_events, _) = broadcast::channel::<LoadProgressEvent>(64);
 
         // Stdin writer task
         tokio::spawn(stdin_writer_task(cmd_rx, stdin_handle));
@@ -97,6 +104,7 @@ impl WorkerBridge {
             model_meta.clone(),
             last_model_path.clone(),
             loading_progress.clone(),
+            load_events.clone(),
             process_manager.clone(),
             cmd_tx_arc.clone(),
             recovery_ctx.clone(),
@@ -114,10 +122,11 @@ impl WorkerBridge {
             loading: AtomicBool::new(false),
             auto_recovering,
             loading_progress,
+            load_events,
             loading_model_path: Arc::new(TokioMutex::new(None)),
             last_model_path,
             status_message,
-            last_finish_reason: Arc::new(TokioMutex::new(None)),
+            finish_reasons: Arc::new(TokioMutex::new(FinishReasons::default())),
             next_id: AtomicU64::new(1),
             process_manager,
             recovery_ctx,
@@ -131,6 +140,21 @@ impl WorkerBridge {
         self.process_manager.kill();
     }
 
+    /// Ask the worker to exit cleanly (cancels any generation and lets it finish
+    /// its DB writes), wait up to `grace`, then force-kill.
+    /// Returns true if the worker exited on its own.
+    pub async fn shutdown(&self, grace: Duration) -> bool {
+        self.process_manager.begin_shutdown();
+        self.send_fire_and_forget(WorkerCommand::Shutdown).await;
+        let pm = self.process_manager.clone();
+        tokio::task::spawn_blocking(move || pm.wait_or_kill(grace))
+            .await
+            .unwrap_or_else(|_| {
+                self.process_manager.kill();
+                false
+            })
+    }
+
     /// Send a command and wait for the response.
     async fn send_and_wait(&self, command: WorkerCommand) -> Result<WorkerPayload, String> {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
@@ -168,6 +192,59 @@ impl WorkerBridge {
         gpu_layers: Option<u32>,
         mmproj_path: Option<String>,
         agent_id: Option<String>,
+    ) -> Result<ModelMeta, String> {
+        self.load_model_with_kv_cache(model_path, gpu_layers, mmproj_path, agent_id, KvCacheTypes::default())
+            .await
+    }
+
+    /// Load the config's `preload_model` so the first request after startup
+    /// doesn't pay for the load; the worker warms up the system prompt as part
+    /// of every load. The path resolves like the load route's, so
+    /// `models_directory` / `allow_external_model_paths` apply. None when no
+    /// preload is configured, or when another load (the frontend's own
+    /// startup load) is pending or done. A failure is logged and leaves the
+    /// worker unloaded.
+    pub async fn preload_configured_model(&self) -> Option<Result<ModelMeta, String>> {
+        let config = self.db.load_config();
+        let requested = config.preload_model.filter(|p| !p.trim().is_empty())?;
+        let (root, allow_external) = (config.models_directory, config.allow_external_model_paths);
+        let resolved = tokio::task::spawn_blocking(move || {
+            llama_chat_engine::io_timeout::with_models_io_timeout(move || {
+                llama_chat_config::resolve_model_path(&requested, root.as_deref(), allow_external)
+                    .and_then(llama_chat_config::ModelTarget::into_file)
+            })
+        })
+        .await
+        .map_err(|e| format!("Preload path check failed: {e}"))
+        .and_then(|checked| checked.and_then(|resolved| resolved));
+        let model_path = match resolved {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("[BRIDGE] Preload skipped, starting unloaded: {e}");
+                return Some(Err(e));
+            }
+        };
+        if self.is_loading() || self.model_status().await.is_some_and(|meta| meta.loaded) {
+            eprintln!("[BRIDGE] Preload of {model_path} skipped: another load is pending or done");
+            return None;
+        }
+        eprintln!("[BRIDGE] Preloading {model_path}");
+        let result = self.load_model(&model_path, config.preload_gpu_layers, None, None).await;
+        match &result {
+            Ok(_) => eprintln!("[BRIDGE] Preloaded {model_path}"),
+            Err(e) => eprintln!("[BRIDGE] Preload of {model_path} failed, starting unloaded: {e}"),
+        }
+        Some(result)
+    }
+
+    /// Load a model, overriding the config's KV cache types for contexts on this model.
+    pub async fn load_model_with_kv_cache(
+        &self,
+        model_path: &str,
+        gpu_layers: Option<u32>,
+        mmproj_path: Option<String>,
+        agent_id: Option<String>,
+        kv_cache_type: KvCacheTypes,
     ) -> Result<ModelMeta, String> {
         // If the bridge is auto-recovering from a crash, don't accept external load requests
         // to avoid racing with the recovery thread's own LoadModel command.
@@ -189,8 +266,9 @@ impl WorkerBridge {
             self.send_and_wait(WorkerCommand::LoadModel {
                 model_path: model_path.to_string(),
                 gpu_layers,
-                mmproj_path,
+                mmproj_path: mmproj_path.clone(),
                 agent_id: agent_id.clone(),
+                kv_cache_type,
             }),
         )
         .await
@@ -205,11 +283,13 @@ impl WorkerBridge {
                 *self.loading_model_path.lock().await = None;
                 // Kill and restart the worker so it doesn't stay hung
                 let _ = self.force_unload().await;
-                return Err(format!(
+                let message = format!(
                     "Model load timed out after {LOAD_TIMEOUT_SECS}s. This usually means the \
                      context size + KV cache exceeds available VRAM. Try reducing context size \
                      or using a smaller KV cache quantization (e.g. q4_0)."
-                ));
+                );
+                self.emit_load_failed(model_path, &message);
+                return Err(message);
             }
         };
 
@@ -217,7 +297,14 @@ impl WorkerBridge {
         self.loading_progress.store(0, Ordering::Relaxed);
         *self.loading_model_path.lock().await = None;
 
-        match payload? {
+        let payload = match payload {
+            Ok(payload) => payload,
+            Err(e) => {
+                self.emit_load_failed(model_path, &e);
+                return Err(e);
+            }
+        };
+        match payload {
             WorkerPayload::ModelLoaded {
                 model_path,
                 context_length,
@@ -227,6 +314,9 @@ impl WorkerBridge {
                 has_vision,
                 gpu_layers,
                 block_count,
+                load_outcome,
+                effective_backend,
+                recommended_sampling,
             } => {
                 let supports_thinking = chat_template_string
                     .as_deref()
@@ -237,11 +327,15 @@ impl WorkerBridge {
                     model_path,
                     context_length,
                     chat_template_type,
+                    template_override: None,
                     general_name,
                     has_vision: has_vision.unwrap_or(false),
                     gpu_layers,
                     block_count,
                     supports_thinking,
+                    load_outcome,
+                    effective_backend,
+                    recommended_sampling,
                 };
                 *self.last_model_path.lock().await = Some(meta.model_path.clone());
                 *self.model_meta.lock().await = Some(meta.clone());
@@ -249,11 +343,49 @@ impl WorkerBridge {
                 self.recovery_ctx.lock().await.agent_id = agent_id;
                 Ok(meta)
             }
-            WorkerPayload::Error { message } => Err(message),
+            WorkerPayload::Error { message }
+                if llama_chat_engine::gpu_devices::is_cuda_driver_mismatch(&message)
+                    && !self.process_manager.running_on_cpu() =>
+            {
+                // The CUDA backend can't come up in this process; respawn the
+                // worker with the GPUs hidden and load once more on the CPU.
+                llama_chat_types::sys_warn!(
+                    "[LOAD] {} ({message})",
+                    llama_chat_engine::gpu_devices::CPU_FALLBACK_NOTICE
+                );
+                self.process_manager.fall_back_to_cpu();
+                self.force_unload().await?;
+                Box::pin(self.load_model_with_kv_cache(
+                    model_path,
+                    gpu_layers,
+                    mmproj_path,
+                    agent_id,
+                    kv_cache_type,
+                ))
+                .await
+            }
+            WorkerPayload::Error { message } => {
+                self.emit_load_failed(model_path, &message);
+                Err(message)
+            }
             _ => Err("Unexpected response to LoadModel".to_string()),
         }
     }
 
+    /// Subscribe to model-load progress events. Completion is emitted by the
+    /// stdout reader on `ModelLoaded`, so auto-recovery loads are covered too.
+    pub fn subscribe_load_events(&self) -> broadcast::Receiver<LoadProgressEvent> {
+        self.load_events.subscribe()
+    }
+
+    fn emit_load_failed(&self, model_path: &str, message: &str) {
+        let _ = self.load_events.send(LoadProgressEvent {
+            model_path: Some(model_path.to_string()),
+            error: Some(message.to_string()),
+            ..LoadProgressEvent::new(LoadPhase::Failed, 0)
+        });
+    }
+
     /// Check if a model is currently being loaded (includes auto-recovery loading).
     pub fn is_loading(&self) -> bool {
         self.loading.load(Ordering::SeqCst) || self.auto_recovering.load(Ordering::SeqCst)
@@ -321,7 +453,7 @@ impl WorkerBridge {
 
         // Drop active generation
         {
-            *self.active_generation.lock().await = None;
+            self.active_generation.lock().await.clear();
         }
 
         // Restart the worker
@@ -353,6 +485,7 @@ impl WorkerBridge {
                 self.model_meta.clone(),
                 self.last_model_path.clone(),
                 self.loading_progress.clone(),
+                self.load_events.clone(),
                 self.process_manager.clone(),
                 self.cmd_tx.clone(),
                 // Reuse the bridge's persistent recovery_ctx (not a fresh default) so
@@ -387,13 +520,19 @@ impl WorkerBridge {
         }
     }
 
-    /// Force compact a conversation (manual user action).
-    pub async fn compact_conversation(&self, conversation_id: &str) -> Result<(), String> {
+    /// Force compact a conversation (manual user action); `turns` limits it to
+    /// the oldest that many turns.
+    pub async fn compact_conversation(
+        &self,
+        conversation_id: &str,
+        turns: Option<usize>,
+    ) -> Result<(), String> {
         const COMPACT_TIMEOUT_SECS: u64 = 3600; // 1 hour — large contexts can take many minutes
         let payload = match timeout(
             Duration::from_secs(COMPACT_TIMEOUT_SECS),
             self.send_and_wait(WorkerCommand::CompactConversation {
                 conversation_id: conversation_id.to_string(),
+                turns,
             }),
         )
         .await
@@ -430,17 +569,28 @@ impl WorkerBridge {
 
     /// Check if a generation is currently active (streaming tokens or executing tools).
     pub async fn is_generating(&self) -> bool {
-        self.active_generation.lock().await.is_some()
+        !self.active_generation.lock().await.is_empty()
+    }
+
+    /// Number of generations currently active on this worker.
+    pub async fn active_generation_count(&self) -> usize {
+        self.active_generation.lock().await.len()
     }
 
-    /// Get the conversation ID of the active generation, if any.
+    /// Get the conversation ID of the oldest active generation, if any.
     pub async fn active_conversation_id(&self) -> Option<String> {
+        let gens = self.active_generation.lock().await;
+        let oldest = gens.keys().min()?;
+        gens[oldest].conversation_id.clone()
+    }
+
+    /// Check if `conversation_id` is one of the active generations.
+    pub async fn is_generating_conversation(&self, conversation_id: &str) -> bool {
         self.active_generation
             .lock()
             .await
-            .as_ref()?
-            .conversation_id
-            .clone()
+            .values()
+            .any(|ag| ag.conversation_id.as_deref() == Some(conversation_id))
     }
 
     /// Set a status message visible via the API (e.g. compaction progress).
@@ -483,19 +633,20 @@ impl WorkerBridge {
         self.status_message.lock().await.clone()
     }
 
-    /// Get the last finish reason (non-consuming — cleared on next generation start).
-    pub async fn last_finish_reason(&self) -> Option<String> {
-        self.last_finish_reason.lock().await.clone()
+    /// Get the last finish reason of `conversation_id`, or of the most recent
+    /// generation when `None` (non-consuming — cleared on next generation start).
+    pub async fn last_finish_reason(&self, conversation_id: Option<&str>) -> Option<String> {
+        self.finish_reasons.lock().await.get(conversation_id)
     }
 
-    /// Store the finish reason when generation completes.
-    pub async fn set_last_finish_reason(&self, reason: Option<String>) {
-        *self.last_finish_reason.lock().await = reason;
+    /// Store the finish reason when a conversation's generation completes.
+    pub async fn set_last_finish_reason(&self, conversation_id: Option<&str>, reason: Option<String>) {
+        self.finish_reasons.lock().await.record(conversation_id, reason);
     }
 
     /// Clear the finish reason (called at generation start).
-    pub async fn clear_last_finish_reason(&self) {
-        *self.last_finish_reason.lock().await = None;
+    pub async fn clear_last_finish_reason(&self, conversation_id: Option<&str>) {
+        self.finish_reasons.lock().await.clear(conversation_id);
     }
 
     /// Start a generation request. Returns a receiver for streaming tokens.
@@ -508,11 +659,51 @@ impl WorkerBridge {
         image_data: Option<Vec<String>>,
         agent_id: Option<String>,
     ) -> Result<(mpsc::UnboundedReceiver<TokenData>, oneshot::Receiver<GenerationResult>), String>
+    {
+        self.generate_with_options(
+            user_message,
+            conversation_id,
+            skip_user_logging,
+            image_data,
+            agent_id,
+            GenerateOptions::default(),
+        )
+        .await
+    }
+
+    /// Start a generation with a context-overflow policy and sampler overrides.
+    pub async fn generate_with_options(
+        &self,
+        user_message: String,
+        conversation_id: Option<String>,
+        skip_user_logging: bool,
+        image_data: Option<Vec<String>>,
+        agent_id: Option<String>,
+        options: GenerateOptions,
+    ) -> Result<(mpsc::UnboundedReceiver<TokenData>, oneshot::Receiver<GenerationResult>), String>
+    {
+        let (_, token_rx, done_rx) = self
+            .start_generation(user_message, conversation_id, skip_user_logging, image_data, agent_id, options)
+            .await?;
+        Ok((token_rx, done_rx))
+    }
+
+    /// Like [`Self::generate_with_options`], also returning the request ID that
+    /// [`Self::generation_conversation_id`] looks the generation up by.
+    pub async fn start_generation(
+        &self,
+        user_message: String,
+        conversation_id: Option<String>,
+        skip_user_logging: bool,
+        image_data: Option<Vec<String>>,
+        agent_id: Option<String>,
+        options: GenerateOptions,
+    ) -> Result<(u64, mpsc::UnboundedReceiver<TokenData>, oneshot::Receiver<GenerationResult>), String>
     {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
 
         // Clear previous finish reason
-        self.clear_last_finish_reason().await;
+        self.clear_last_finish_reason(conversation_id.as_deref()).await;
 
         // Create token channel
         let (token_tx, token_rx) = mpsc::unbounded_channel::<TokenData>();
@@ -522,12 +713,15 @@ impl WorkerBridge {
 
         // Register active generation
         {
-            let mut gen = self.active_generation.lock().await;
-            *gen = Some(ActiveGeneration {
-                request_id: id,
-                token_tx,
-                conversation_id: conversation_id.clone(),
-            });
+            let mut gens = self.active_generation.lock().await;
+            gens.insert(
+                id,
+                ActiveGeneration {
+                    request_id: id,
+                    token_tx,
+                    conversation_id: conversation_id.clone(),
+                },
+            );
         }
 
         // Register completion handler
@@ -538,7 +732,13 @@ impl WorkerBridge {
             pending.insert(
                 id,
                 PendingRequest {
-                    tx: oneshot_adapter(done_tx, active_gen, self.last_finish_reason.clone()),
+                    tx: oneshot_adapter(
+                        done_tx,
+                        id,
+                        active_gen,
+                        conversation_id.clone(),
+                        self.finish_reasons.clone(),
+                    ),
                 },
             );
         }
@@ -552,6 +752,8 @@ impl WorkerBridge {
                 skip_user_logging,
                 image_data,
                 agent_id,
+                on_overflow: options.on_overflow,
+                overrides: options.overrides,
             },
         };
         let json =
@@ -562,12 +764,34 @@ impl WorkerBridge {
             .send(json)
             .map_err(|_| "Worker stdin closed".to_string())?;
 
-        Ok((token_rx, done_rx))
+        Ok((id, token_rx, done_rx))
+    }
+
+    /// Conversation of the active generation started as `request_id`. A new
+    /// conversation's ID is known once the worker reports GenerationStarted.
+    pub async fn generation_conversation_id(&self, request_id: u64) -> Option<String> {
+        self.active_generation
+            .lock()
+            .await
+            .get(&request_id)
+            .and_then(|ag| ag.conversation_id.clone())
     }
 
     /// Cancel the in-progress generation.
     pub async fn cancel_generation(self: &Arc<Self>) {
-        self.send_fire_and_forget(WorkerCommand::CancelGeneration).await;
+        self.send_fire_and_forget(WorkerCommand::CancelGeneration {
+            conversation_id: None,
+        })
+        .await;
+    }
+
+    /// Cancel the generation for `conversation_id`; the worker ignores it if
+    /// that conversation isn't the one generating.
+    pub async fn cancel_conversation(self: &Arc<Self>, conversation_id: &str) {
+        self.send_fire_and_forget(WorkerCommand::CancelGeneration {
+            conversation_id: Some(conversation_id.to_string()),
+        })
+        .await;
     }
 
     /// Refresh MCP server connections in the worker.
@@ -620,6 +844,88 @@ impl WorkerBridge {
         }
     }
 
+    /// Force a chat template for the loaded model, or clear the override with `None`.
+    /// Returns the override now in effect.
+    pub async fn set_template_override(
+        &self,
+        template: Option<String>,
+    ) -> Result<Option<String>, String> {
+        match self
+            .send_and_wait(WorkerCommand::SetTemplateOverride { template })
+            .await?
+        {
+            WorkerPayload::TemplateOverrideSet { template } => {
+                if let Some(meta) = self.model_meta.lock().await.as_mut() {
+                    meta.template_override = template.clone();
+                }
+                Ok(template)
+            }
+            WorkerPayload::Error { message } => Err(message),
+            _ => Err("Unexpected response to SetTemplateOverride".to_string()),
+        }
+    }
+
+    /// Swap the system prompt on the loaded model; the worker re-warms it in the background.
+    pub async fn set_system_prompt(&self, prompt: Option<String>) -> Result<(), String> {
+        match self
+            .send_and_wait(WorkerCommand::SetSystemPrompt { prompt })
+            .await?
+        {
+            WorkerPayload::SystemPromptSet => Ok(()),
+            WorkerPayload::Error { message } => Err(message),
+            _ => Err("Unexpected response to SetSystemPrompt".to_string()),
+        }
+    }
+
+    /// Run the worker's pipeline self-test on the loaded model.
+    pub async fn self_test(&self) -> Result<llama_chat_engine::SelfTestReport, String> {
+        match self.send_and_wait(WorkerCommand::SelfTest).await? {
+            WorkerPayload::SelfTestResult {
+                first_tokens,
+                tokens_generated,
+                gen_tok_per_sec,
+            } => Ok(llama_chat_engine::SelfTestReport {
+                ok: true,
+                first_tokens,
+                tokens_generated,
+                gen_tok_per_sec,
+            }),
+            WorkerPayload::Error { message } => Err(message),
+            _ => Err("Unexpected response to SelfTest".to_string()),
+        }
+    }
+
+    /// Count `text`'s tokens with the loaded model's tokenizer.
+    pub async fn count_tokens(&self, text: String) -> Result<usize, String> {
+        match self.send_and_wait(WorkerCommand::CountTokens { text }).await? {
+            WorkerPayload::TokenCount { count } => Ok(count),
+            WorkerPayload::Error { message } => Err(message),
+            _ => Err("Unexpected response to CountTokens".to_string()),
+        }
+    }
+
+    /// Save the worker's cached context to a session file. Returns the saved
+    /// `(conversation_id, n_tokens)`.
+    pub async fn save_session(&self, path: &str) -> Result<(String, usize), String> {
+        let command = WorkerCommand::SaveSession { path: path.to_string() };
+        match self.send_and_wait(command).await? {
+            WorkerPayload::SessionSaved { conversation_id, n_tokens } => Ok((conversation_id, n_tokens)),
+            WorkerPayload::Error { message } => Err(message),
+            _ => Err("Unexpected response to SaveSession".to_string()),
+        }
+    }
+
+    /// Restore a session file into the worker's inference cache. Returns the
+    /// restored `(conversation_id, n_tokens)`.
+    pub async fn load_session(&self, path: &str) -> Result<(String, usize), String> {
+        let command = WorkerCommand::LoadSession { path: path.to_string() };
+        match self.send_and_wait(command).await? {
+            WorkerPayload::SessionLoaded { conversation_id, n_tokens } => Ok((conversation_id, n_tokens)),
+            WorkerPayload::Error { message } => Err(message),
+            _ => Err("Unexpected response to LoadSession".to_string()),
+        }
+    }
+
     /// Get available compute backends from the worker.
     pub async fn get_available_backends(
         &self,
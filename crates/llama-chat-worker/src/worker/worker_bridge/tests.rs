@@ -0,0 +1,145 @@
+use std::process::{Command, Stdio};
+
+use super::*;
+
+/// A bridge over a shell "worker" that answers every LoadModel with `reply`
+/// (a payload JSON object) and ignores everything else.
+fn bridge_with_mock_worker(reply: &str, db: SharedDatabase) -> WorkerBridge {
+    let script = format!(
+        r#"while read -r line; do
+             id=$(printf '%s' "$line" | sed -n 's/^{{"id":\([0-9]*\),.*/\1/p')
+             case "$line" in *'"LoadModel"'*) printf '{{"id":%s,"payload":%s}}\n' "$id" '{reply}' ;; esac
+           done"#
+    );
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn mock worker");
+    WorkerBridge::new(Arc::new(ProcessManager::from_child(child, ":memory:")), db)
+}
+
+fn db_with_preload(model: Option<&str>) -> SharedDatabase {
+    db_with_config(llama_chat_db::config::DbSamplerConfig {
+        preload_model: model.map(str::to_string),
+        preload_gpu_layers: Some(12),
+        ..Default::default()
+    })
+}
+
+fn db_with_config(config: llama_chat_db::config::DbSamplerConfig) -> SharedDatabase {
+    let db: SharedDatabase = Arc::new(llama_chat_db::Database::new(":memory:").unwrap());
+    db.save_config(&config).unwrap();
+    db
+}
+
+/// A directory holding an empty `preload.gguf`, and the file's canonical path.
+fn models_dir(name: &str) -> (std::path::PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("preload_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("preload.gguf");
+    std::fs::write(&file, b"").unwrap();
+    let file = std::fs::canonicalize(file).unwrap().to_string_lossy().to_string();
+    (dir, file)
+}
+
+fn loaded_reply(model_path: &str) -> String {
+    format!(
+        r#"{{"type":"ModelLoaded","model_path":{},"context_length":4096,"gpu_layers":12,"has_vision":false}}"#,
+        serde_json::to_string(model_path).unwrap()
+    )
+}
+
+#[tokio::test]
+async fn test_preload_reports_the_model_loaded_shortly_after_startup() {
+    let (dir, model) = models_dir("startup");
+    let bridge = Arc::new(bridge_with_mock_worker(&loaded_reply(&model), db_with_preload(Some(&model))));
+
+    // Startup spawns the preload and carries on
+    let preload = tokio::spawn({
+        let bridge = bridge.clone();
+        async move { bridge.preload_configured_model().await }
+    });
+    let status = timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(meta) = bridge.model_status().await.filter(|m| m.loaded) {
+                return meta;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("model loaded within 5s");
+
+    assert_eq!(status.model_path, model);
+    assert_eq!(status.gpu_layers, Some(12));
+    assert!(preload.await.unwrap().expect("preload configured").is_ok());
+    bridge.kill();
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_failed_or_missing_preload_leaves_the_worker_unloaded() {
+    let reply = r#"{"type":"Error","message":"no such file"}"#;
+    let (dir, model) = models_dir("failed");
+    let bridge = bridge_with_mock_worker(reply, db_with_preload(Some(&model)));
+    let result = bridge.preload_configured_model().await.expect("preload configured");
+    assert_eq!(result.unwrap_err(), "no such file");
+    assert!(bridge.model_status().await.is_none_or(|m| !m.loaded));
+    bridge.kill();
+
+    // A path that doesn't resolve never reaches the worker
+    let bridge = bridge_with_mock_worker(reply, db_with_preload(Some("/models/missing.gguf")));
+    let result = bridge.preload_configured_model().await.expect("preload configured");
+    assert!(result.unwrap_err().starts_with(llama_chat_config::MODEL_NOT_FOUND));
+    bridge.kill();
+
+    let bridge = bridge_with_mock_worker(reply, db_with_preload(None));
+    assert!(bridge.preload_configured_model().await.is_none());
+    bridge.kill();
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_preload_honors_the_models_directory_and_pending_loads() {
+    let (root, _) = models_dir("root");
+    let (outside, external) = models_dir("outside");
+    let reply = loaded_reply(&external);
+    let db = db_with_config(llama_chat_db::config::DbSamplerConfig {
+        preload_model: Some(external.clone()),
+        models_directory: Some(root.to_string_lossy().to_string()),
+        allow_external_model_paths: false,
+        ..Default::default()
+    });
+    let bridge = bridge_with_mock_worker(&reply, db);
+    let result = bridge.preload_configured_model().await.expect("preload configured");
+    assert!(result.unwrap_err().contains("outside"), "external path must be rejected");
+    assert!(bridge.model_status().await.is_none_or(|m| !m.loaded));
+    bridge.kill();
+
+    // The frontend's own load got there first
+    let bridge = bridge_with_mock_worker(&reply, db_with_preload(Some(&external)));
+    bridge.loading.store(true, Ordering::SeqCst);
+    assert!(bridge.preload_configured_model().await.is_none());
+    bridge.kill();
+    let _ = std::fs::remove_dir_all(root);
+    let _ = std::fs::remove_dir_all(outside);
+}
+
+#[test]
+fn test_parallel_finish_reasons_are_kept_per_conversation() {
+    let mut reasons = FinishReasons::default();
+    reasons.record(Some("a"), Some("max_tokens".to_string()));
+    reasons.record(Some("b"), Some("stop".to_string()));
+    assert_eq!(reasons.get(Some("a")).as_deref(), Some("max_tokens"));
+    assert_eq!(reasons.get(Some("b")).as_deref(), Some("stop"));
+    assert_eq!(reasons.get(None).as_deref(), Some("stop"));
+
+    // A new generation for "b" clears only its own reason
+    reasons.clear(Some("b"));
+    assert_eq!(reasons.get(Some("a")).as_deref(), Some("max_tokens"));
+    assert!(reasons.get(Some("b")).is_none());
+    assert!(reasons.get(None).is_none());
+}
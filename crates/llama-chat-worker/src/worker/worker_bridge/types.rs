@@ -1,5 +1,6 @@
 //! Types used by the WorkerBridge: metadata, pending requests, generation state, results.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use tokio::sync::{mpsc, oneshot, Mutex as TokioMutex};
@@ -14,11 +15,35 @@ pub struct ModelMeta {
     pub model_path: String,
     pub context_length: Option<u32>,
     pub chat_template_type: Option<String>,
+    /// User-selected template that overrides `chat_template_type` (None = detected).
+    pub template_override: Option<String>,
     pub general_name: Option<String>,
     pub has_vision: bool,
     pub gpu_layers: Option<u32>,
     pub block_count: Option<u32>,
     pub supports_thinking: bool,
+    /// How the last load was done (full reload vs. context-only rebuild).
+    pub load_outcome: llama_chat_types::models::LoadOutcome,
+    /// Backend the model runs on ("cpu" after a no-device fallback).
+    pub effective_backend: Option<String>,
+    /// Sampler defaults from the model's GGUF `general.sampling.*` keys.
+    pub recommended_sampling: Option<llama_chat_types::models::RecommendedSampling>,
+}
+
+/// Per-request generation options beyond the message itself.
+#[derive(Debug, Clone, Default)]
+pub struct GenerateOptions {
+    pub on_overflow: llama_chat_types::models::OverflowPolicy,
+    pub overrides: llama_chat_types::models::GenerationOverrides,
+}
+
+impl From<&llama_chat_types::models::ChatRequest> for GenerateOptions {
+    fn from(req: &llama_chat_types::models::ChatRequest) -> Self {
+        Self {
+            on_overflow: req.on_overflow,
+            overrides: req.overrides.clone(),
+        }
+    }
 }
 
 /// A pending request awaiting a response from the worker.
@@ -33,6 +58,49 @@ pub struct ActiveGeneration {
     pub conversation_id: Option<String>,
 }
 
+/// Finish reasons of ended generations, kept per conversation so parallel
+/// generations don't overwrite each other's reason.
+#[derive(Debug, Default)]
+pub struct FinishReasons {
+    by_conversation: HashMap<String, String>,
+    /// Reason of the most recently ended generation, for callers that don't name a
+    /// conversation. Cleared whenever a generation starts, like the old single slot.
+    latest: Option<String>,
+}
+
+impl FinishReasons {
+    /// Record how a generation for `conversation_id` ended (`None` forgets it).
+    pub fn record(&mut self, conversation_id: Option<&str>, reason: Option<String>) {
+        if let Some(conv_id) = conversation_id {
+            match &reason {
+                Some(r) => self.by_conversation.insert(conv_id.to_string(), r.clone()),
+                None => self.by_conversation.remove(conv_id),
+            };
+        }
+        self.latest = reason;
+    }
+
+    /// Forget the reason of `conversation_id` because a new generation started for it.
+    pub fn clear(&mut self, conversation_id: Option<&str>) {
+        if let Some(conv_id) = conversation_id {
+            self.by_conversation.remove(conv_id);
+        }
+        self.latest = None;
+    }
+
+    /// Reason for `conversation_id`, or the latest one when no conversation is named.
+    pub fn get(&self, conversation_id: Option<&str>) -> Option<String> {
+        match conversation_id {
+            Some(conv_id) => self.by_conversation.get(conv_id).cloned(),
+            None => self.latest.clone(),
+        }
+    }
+}
+
+/// Streaming generations by request ID; more than one when the worker runs
+/// generations in parallel (`max_parallel_generations`).
+pub type ActiveGenerations = Arc<TokioMutex<HashMap<u64, ActiveGeneration>>>;
+
 /// Result of a completed generation.
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -49,6 +117,13 @@ pub enum GenerationResult {
         prompt_tokens: Option<i32>,
         finish_reason: Option<String>,
         token_breakdown: Option<llama_chat_types::models::TokenBreakdown>,
+        overflow: llama_chat_types::models::OverflowReport,
+        logprobs: Vec<llama_chat_types::models::TokenLogprob>,
+        tool_calls: Vec<llama_chat_types::models::ToolCallRequest>,
+        /// Request `stop` sequence that ended the generation.
+        stop_sequence: Option<String>,
+        /// Completions 2..=n of an `n` > 1 request.
+        alternatives: Vec<llama_chat_types::models::AlternativeCompletion>,
     },
     Cancelled,
     Error(String),
@@ -57,16 +132,19 @@ pub enum GenerationResult {
 /// Adapt a GenerationResult oneshot into a WorkerPayload oneshot for the pending map.
 pub(super) fn oneshot_adapter(
     done_tx: oneshot::Sender<GenerationResult>,
-    active_gen: Arc<TokioMutex<Option<ActiveGeneration>>>,
-    finish_reason_store: Arc<TokioMutex<Option<String>>>,
+    request_id: u64,
+    active_gen: ActiveGenerations,
+    conversation_id: Option<String>,
+    finish_reasons: Arc<TokioMutex<FinishReasons>>,
 ) -> oneshot::Sender<WorkerPayload> {
     let (payload_tx, payload_rx) = oneshot::channel::<WorkerPayload>();
 
     tokio::spawn(async move {
         if let Ok(payload) = payload_rx.await {
             // Clear active generation
-            *active_gen.lock().await = None;
+            active_gen.lock().await.remove(&request_id);
 
+            let conv_id = conversation_id.as_deref();
             let result = match payload {
                 WorkerPayload::GenerationComplete {
                     conversation_id,
@@ -80,9 +158,14 @@ pub(super) fn oneshot_adapter(
                     prompt_tokens,
                     finish_reason,
                     token_breakdown,
+                    overflow,
+                    logprobs,
+                    tool_calls,
+                    stop_sequence,
+                    alternatives,
                 } => {
                     // Store finish_reason for polling-based auto-continue
-                    *finish_reason_store.lock().await = finish_reason.clone();
+                    finish_reasons.lock().await.record(conv_id, finish_reason.clone());
                     GenerationResult::Complete {
                         conversation_id,
                         tokens_used,
@@ -95,18 +178,23 @@ pub(super) fn oneshot_adapter(
                         prompt_tokens,
                         finish_reason,
                         token_breakdown,
+                        overflow,
+                        logprobs,
+                        tool_calls,
+                        stop_sequence,
+                        alternatives,
                     }
                 }
                 WorkerPayload::GenerationCancelled => {
-                    *finish_reason_store.lock().await = Some("cancelled".to_string());
+                    finish_reasons.lock().await.record(conv_id, Some("cancelled".to_string()));
                     GenerationResult::Cancelled
                 }
                 WorkerPayload::Error { message } => {
-                    *finish_reason_store.lock().await = Some("error".to_string());
+                    finish_reasons.lock().await.record(conv_id, Some("error".to_string()));
                     GenerationResult::Error(message)
                 }
                 _ => {
-                    *finish_reason_store.lock().await = Some("error".to_string());
+                    finish_reasons.lock().await.record(conv_id, Some("error".to_string()));
                     GenerationResult::Error("Unexpected response".to_string())
                 }
             };
@@ -0,0 +1,245 @@
+//! Token forwarding from a generation to the main loop, with backpressure.
+//!
+//! The engine streams into an unbounded tokio channel; the forwarder drains it
+//! straight away into a small pending queue and hands tokens to the bounded
+//! `token_tx` as the main loop (and the stdout pipe behind it) keeps up. While
+//! the consumer is stalled the queue stays bounded:
+//! - progress-only updates (token counts, status, prompt progress) replace the
+//!   previous pending progress update;
+//! - at capacity, text is appended to the last pending token. Text is never
+//!   dropped; per-token `logprobs`/`token_id` of merged tokens are, and the
+//!   merged token's `status` says so.
+//!
+//! Tool timings, confirmation requests and prompt echoes are never merged.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crossbeam_channel::{SendTimeoutError, Sender};
+use llama_chat_types::models::TokenData;
+use tokio::sync::mpsc;
+
+use crate::worker::ipc_types::{WorkerPayload, WorkerResponse};
+
+/// Capacity of the worker's shared `token_tx` channel.
+pub(super) const TOKEN_CHANNEL_CAPACITY: usize = 256;
+
+/// Tokens held per generation while `token_tx` is full.
+const MAX_PENDING: usize = 64;
+
+/// How long a send waits before the forwarder drains the engine channel again.
+const SEND_RETRY: Duration = Duration::from_millis(20);
+
+/// Status put on a token that absorbed others and lost their per-token details.
+pub(super) const COALESCED_STATUS: &str = "Client fell behind; streamed tokens were merged";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct ForwardStats {
+    /// Most tokens pending at once.
+    pub(super) peak_pending: usize,
+    /// Tokens merged into an earlier one.
+    pub(super) coalesced: usize,
+}
+
+/// Forward `tokens` to `tx` until the engine closes its side and all of
+/// them are sent (or the main loop is gone).
+pub(super) fn forward_tokens(
+    req_id: u64,
+    mut tokens: mpsc::UnboundedReceiver<TokenData>,
+    tx: &Sender<WorkerResponse>,
+) -> ForwardStats {
+    let mut pending = Pending::default();
+    // Converted and offered to `tx`, which was full
+    let mut stalled: Option<WorkerResponse> = None;
+    loop {
+        if stalled.is_none() && pending.queue.is_empty() {
+            match tokens.blocking_recv() {
+                Some(token) => pending.push(token),
+                None => break,
+            }
+        }
+        while let Ok(token) = tokens.try_recv() {
+            pending.push(token);
+        }
+        let next = stalled
+            .take()
+            .or_else(|| pending.queue.pop_front().map(|t| token_response(req_id, t)));
+        let Some(response) = next else {
+            continue;
+        };
+        match tx.send_timeout(response, SEND_RETRY) {
+            Ok(()) => {}
+            Err(SendTimeoutError::Timeout(response)) => stalled = Some(response),
+            Err(SendTimeoutError::Disconnected(_)) => break,
+        }
+    }
+    pending.stats
+}
+
+#[derive(Default)]
+struct Pending {
+    queue: VecDeque<TokenData>,
+    stats: ForwardStats,
+}
+
+impl Pending {
+    fn push(&mut self, token: TokenData) {
+        match self.queue.back_mut() {
+            Some(last) if is_progress(&token) && is_progress(last) => {
+                *last = token;
+                self.stats.coalesced += 1;
+            }
+            Some(last) if self.queue.len() >= MAX_PENDING && can_merge(last, &token) => {
+                merge(last, token);
+                self.stats.coalesced += 1;
+            }
+            _ => self.queue.push_back(token),
+        }
+        self.stats.peak_pending = self.stats.peak_pending.max(self.queue.len());
+    }
+}
+
+/// Carries nothing a later update doesn't supersede.
+fn is_progress(token: &TokenData) -> bool {
+    token.token.is_empty()
+        && token.reasoning.is_none()
+        && token.logprobs.is_none()
+        && token.token_id.is_none()
+        && is_plain(token)
+}
+
+/// No discrete event that has to reach the client on its own.
+fn is_plain(token: &TokenData) -> bool {
+    token.tool_timing.is_none() && token.confirmation_required.is_none() && token.prompt_echo.is_none()
+}
+
+fn can_merge(last: &TokenData, next: &TokenData) -> bool {
+    is_plain(last) && is_plain(next)
+}
+
+fn merge(last: &mut TokenData, next: TokenData) {
+    last.token.push_str(&next.token);
+    if let Some(reasoning) = next.reasoning {
+        last.reasoning.get_or_insert_with(String::new).push_str(&reasoning);
+    }
+    last.tokens_used = next.tokens_used;
+    last.max_tokens = next.max_tokens;
+    last.prompt_eval_progress = next.prompt_eval_progress.or(last.prompt_eval_progress.take());
+    if last.logprobs.is_some() || last.token_id.is_some() || next.logprobs.is_some() || next.token_id.is_some() {
+        last.logprobs = None;
+        last.token_id = None;
+        last.status = Some(COALESCED_STATUS.to_string());
+    } else {
+        last.status = next.status.or(last.status.take());
+    }
+}
+
+fn token_response(req_id: u64, token_data: TokenData) -> WorkerResponse {
+    WorkerResponse::ok(
+        req_id,
+        WorkerPayload::Token {
+            token: token_data.token,
+            tokens_used: token_data.tokens_used,
+            max_tokens: token_data.max_tokens,
+            status: token_data.status,
+            tool_timing: token_data.tool_timing,
+            prompt_eval_progress: token_data.prompt_eval_progress,
+            logprobs: token_data.logprobs,
+            reasoning: token_data.reasoning,
+            confirmation_required: token_data.confirmation_required,
+            token_id: token_data.token_id,
+            prompt_echo: token_data.prompt_echo,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str, pos: i32) -> TokenData {
+        TokenData { token: s.to_string(), tokens_used: pos, ..Default::default() }
+    }
+
+    fn received_text(rx: &crossbeam_channel::Receiver<WorkerResponse>) -> (String, usize) {
+        let mut out = String::new();
+        let mut messages = 0;
+        for response in rx.try_iter() {
+            if let WorkerPayload::Token { token, .. } = response.payload {
+                out.push_str(&token);
+                messages += 1;
+            }
+        }
+        (out, messages)
+    }
+
+    #[test]
+    fn test_slow_consumer_keeps_the_buffer_bounded_without_losing_text() {
+        let (tx, rx) = crossbeam_channel::bounded(4);
+        let (token_tx, token_rx) = mpsc::unbounded_channel();
+        let mut expected = String::new();
+        for i in 0..5000 {
+            let piece = format!("t{i} ");
+            expected.push_str(&piece);
+            token_tx.send(text(&piece, i)).unwrap();
+            // Progress updates in between, as during prompt eval / tool runs
+            token_tx.send(TokenData { tokens_used: i, ..Default::default() }).unwrap();
+        }
+        drop(token_tx);
+
+        // Nobody reads until the forwarder has had to hold tokens back
+        let forwarder = std::thread::spawn(move || forward_tokens(7, token_rx, &tx));
+        std::thread::sleep(Duration::from_millis(100));
+        let mut got = String::new();
+        let mut messages = 0;
+        loop {
+            let (chunk, n) = received_text(&rx);
+            got.push_str(&chunk);
+            messages += n;
+            if forwarder.is_finished() && rx.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let stats = forwarder.join().unwrap();
+
+        assert_eq!(got, expected);
+        assert!(stats.peak_pending <= MAX_PENDING, "{stats:?}");
+        assert!(stats.coalesced > 0);
+        assert!(messages < 10_000, "{messages} messages forwarded");
+    }
+
+    #[test]
+    fn test_merging_marks_dropped_per_token_details() {
+        let mut pending = Pending::default();
+        for i in 0..MAX_PENDING {
+            pending.push(text("a", i as i32));
+        }
+        pending.push(TokenData { token_id: Some(42), ..text("b", 99) });
+        assert_eq!(pending.queue.len(), MAX_PENDING);
+        let last = pending.queue.back().unwrap();
+        assert_eq!((last.token.as_str(), last.tokens_used, last.token_id), ("ab", 99, None));
+        assert_eq!(last.status.as_deref(), Some(COALESCED_STATUS));
+
+        // Discrete events are kept as their own entries
+        pending.push(TokenData {
+            tool_timing: Some(llama_chat_types::models::ToolTimingLive {
+                name: "read_file".to_string(),
+                duration_ms: 3,
+            }),
+            ..Default::default()
+        });
+        assert_eq!(pending.queue.len(), MAX_PENDING + 1);
+    }
+
+    #[test]
+    fn test_progress_updates_replace_each_other() {
+        let mut pending = Pending::default();
+        pending.push(text("hi", 1));
+        pending.push(TokenData { tokens_used: 2, ..Default::default() });
+        pending.push(TokenData { tokens_used: 3, status: Some("Evaluating".into()), ..Default::default() });
+        assert_eq!(pending.queue.len(), 2);
+        assert_eq!(pending.queue[1].tokens_used, 3);
+        assert_eq!(pending.queue[0].token, "hi");
+    }
+}
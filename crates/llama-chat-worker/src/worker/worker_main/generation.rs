@@ -5,12 +5,20 @@ use std::thread;
 use crossbeam_channel::Sender;
 use llama_chat_db::SharedDatabase;
 use llama_chat_engine::generate_llama_response;
-use llama_chat_types::models::{SharedLlamaState, TokenData};
+use llama_chat_types::models::{GenerationOverrides, OverflowPolicy, SharedLlamaState, TokenData};
 
 use crate::mcp::McpManager;
 
 use crate::worker::ipc_types::{WorkerPayload, WorkerResponse};
 
+/// A generation thread and the handles for cancelling it.
+pub(super) struct RunningGeneration {
+    pub(super) handle: thread::JoinHandle<()>,
+    pub(super) cancel: Arc<AtomicBool>,
+    /// Conversation being generated; filled in once a new conversation has its ID.
+    pub(super) conversation: Arc<Mutex<Option<String>>>,
+}
+
 pub(super) struct GenerationParams {
     pub(super) req_id: u64,
     pub(super) user_message: String,
@@ -18,9 +26,13 @@ pub(super) struct GenerationParams {
     pub(super) skip_user_logging: bool,
     pub(super) image_data: Option<Vec<String>>,
     pub(super) agent_id: Option<String>,
+    pub(super) on_overflow: OverflowPolicy,
+    pub(super) overrides: GenerationOverrides,
     pub(super) llama_state: SharedLlamaState,
     pub(super) db: SharedDatabase,
     pub(super) cancel: Arc<AtomicBool>,
+    /// Filled in with the conversation ID so cancels can target it.
+    pub(super) active_conversation: Arc<Mutex<Option<String>>>,
     pub(super) tx: Sender<WorkerResponse>,
     pub(super) mcp_manager: Arc<McpManager>,
 }
@@ -51,9 +63,12 @@ pub(super) fn run_generation(params: GenerationParams) {
         skip_user_logging,
         image_data,
         agent_id,
+        on_overflow,
+        overrides,
         llama_state,
         db,
         cancel,
+        active_conversation,
         tx,
         mcp_manager,
     } = params;
@@ -86,6 +101,7 @@ pub(super) fn run_generation(params: GenerationParams) {
 
         {
             let conv_id = shared_logger.lock().unwrap().get_conversation_id();
+            *active_conversation.lock().unwrap() = Some(conv_id.clone());
             let _ = tx.send(WorkerResponse::ok(
                 req_id,
                 WorkerPayload::GenerationStarted {
@@ -100,23 +116,15 @@ pub(super) fn run_generation(params: GenerationParams) {
             logger.log_message_with_tokens("USER", &user_message, Some(estimated_tokens));
         }
 
-        let (token_sender, mut token_receiver) = mpsc::unbounded_channel::<TokenData>();
+        let (token_sender, token_receiver) = mpsc::unbounded_channel::<TokenData>();
         let tx_clone = tx.clone();
         let forward_thread = thread::spawn(move || {
-            while let Some(token_data) = token_receiver.blocking_recv() {
-                let response = WorkerResponse::ok(
-                    req_id,
-                    WorkerPayload::Token {
-                        token: token_data.token,
-                        tokens_used: token_data.tokens_used,
-                        max_tokens: token_data.max_tokens,
-                        status: token_data.status,
-                        tool_timing: token_data.tool_timing,
-                    },
+            let stats = super::backpressure::forward_tokens(req_id, token_receiver, &tx_clone);
+            if stats.coalesced > 0 {
+                eprintln!(
+                    "[WORKER] Slow token consumer: {} update(s) coalesced, peak {} pending",
+                    stats.coalesced, stats.peak_pending
                 );
-                if tx_clone.send(response).is_err() {
-                    break;
-                }
             }
         });
 
@@ -131,6 +139,8 @@ pub(super) fn run_generation(params: GenerationParams) {
             image_data.as_deref(),
             Some(mcp_manager),
             agent_id.as_deref(),
+            on_overflow,
+            &overrides,
         )
         .await;
 
@@ -158,6 +168,11 @@ pub(super) fn run_generation(params: GenerationParams) {
                         prompt_tokens: output.prompt_tokens,
                         finish_reason: Some(output.finish_reason),
                         token_breakdown: output.token_breakdown,
+                        overflow: output.overflow,
+                        logprobs: output.logprobs,
+                        tool_calls: output.tool_calls,
+                        stop_sequence: output.stop_sequence,
+                        alternatives: output.alternatives,
                     },
                 ));
             }
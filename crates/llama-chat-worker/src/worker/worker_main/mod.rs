@@ -7,7 +7,9 @@
 //! Thread design:
 //! - Thread 0 (stdin reader): reads lines → stdin_rx channel
 //! - Thread 1 (main loop): selects between stdin_rx and token_rx, writes to stdout
-//! - Thread 2 (generation, temporary): runs generate_llama_response, sends tokens
+//! - Threads 2..n (one per generation, temporary): run generate_llama_response,
+//!   send tokens. Up to `max_parallel_generations` decode at once, each on its
+//!   own context; with the default of 1 a second Generate is rejected.
 
 use std::io::{self, BufRead, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -21,6 +23,7 @@ use llama_chat_db::{Database, SharedDatabase};
 use crate::mcp::McpManager;
 use llama_chat_types::models::SharedLlamaState;
 
+mod backpressure;
 mod crash_handler;
 mod generation;
 mod model_commands;
@@ -28,7 +31,7 @@ mod other_commands;
 mod stdout;
 
 use crash_handler::install_crash_handler;
-use generation::{GenerationParams, run_generation};
+use generation::{GenerationParams, RunningGeneration, run_generation};
 use stdout::{steal_stdout_for_ipc, write_response, write_response_no_flush};
 
 /// Run the worker process. This function never returns normally.
@@ -63,6 +66,13 @@ pub fn run_worker(db_path: &str) {
     // Initialize event log with DB persistence
     llama_chat_db::event_log::init_event_log(db.clone());
 
+    // Respawned after a driver mismatch: the GPUs are hidden, stay on the CPU
+    if std::env::var_os(llama_chat_engine::gpu_devices::CPU_FALLBACK_ENV).is_some() {
+        llama_chat_engine::gpu_devices::set_cpu_fallback();
+        eprintln!("[WORKER] ⚠️ {}", llama_chat_engine::gpu_devices::CPU_FALLBACK_NOTICE);
+        llama_chat_db::event_log::set_global_status(llama_chat_engine::gpu_devices::CPU_FALLBACK_NOTICE);
+    }
+
     // Detect and kill orphaned processes from previous sessions
     let orphans = llama_chat_command::background::get_orphaned_processes(&db);
     if !orphans.is_empty() {
@@ -92,11 +102,9 @@ pub fn run_worker(db_path: &str) {
 
     // Channels
     let (stdin_tx, stdin_rx): (Sender<String>, Receiver<String>) = crossbeam_channel::unbounded();
+    // Bounded so a stalled stdout reader holds generations back (see `backpressure`)
     let (token_tx, token_rx): (Sender<WorkerResponse>, Receiver<WorkerResponse>) =
-        crossbeam_channel::unbounded();
-
-    // Cancellation flag for generation
-    let cancel_flag = Arc::new(AtomicBool::new(false));
+        crossbeam_channel::bounded(backpressure::TOKEN_CHANNEL_CAPACITY);
 
     // Shutdown guard: kills tracked background processes when the worker exits
     struct BgProcessGuard;
@@ -126,7 +134,8 @@ pub fn run_worker(db_path: &str) {
     });
 
     // Main loop (Thread 1)
-    let mut generation_thread: Option<thread::JoinHandle<()>> = None;
+    let mut generations: Vec<RunningGeneration> = Vec::new();
+    let mut rewarm = model_commands::RewarmThread::default();
     // Clone the IPC file handle for use by blocking-operation status threads.
     let ipc_for_status: Arc<Mutex<std::fs::File>> = Arc::new(Mutex::new(
         ipc_out.try_clone().expect("Failed to clone IPC file handle"),
@@ -137,12 +146,8 @@ pub fn run_worker(db_path: &str) {
     eprintln!("[WORKER] Ready, waiting for commands...");
 
     loop {
-        // Check if generation thread finished
-        if let Some(ref handle) = generation_thread {
-            if handle.is_finished() {
-                generation_thread = None;
-            }
-        }
+        // Join generation threads that finished
+        reap_finished(&mut generations);
 
         // Wait for either a token or a command.
         // Tokens are batched with time-based flushing to reduce pipe I/O pressure
@@ -195,11 +200,7 @@ pub fn run_worker(db_path: &str) {
                 },
             }
             // If only tokens were received, loop back to select
-            if let Some(ref handle) = generation_thread {
-                if handle.is_finished() {
-                    generation_thread = None;
-                }
-            }
+            reap_finished(&mut generations);
         };
 
         // Parse command
@@ -246,15 +247,16 @@ pub fn run_worker(db_path: &str) {
                 other_commands::handle_get_available_backends(req_id, &mut ipc_writer);
             }
 
-            WorkerCommand::CompactConversation { conversation_id } => {
+            WorkerCommand::CompactConversation { conversation_id, turns } => {
                 // Reject if generation is in progress
-                if generation_thread.as_ref().map(|h| !h.is_finished()).unwrap_or(false) {
+                if any_running(&generations) {
                     write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot compact while generation is in progress"));
                     continue;
                 }
                 other_commands::handle_compact_conversation(
                     req_id,
                     conversation_id,
+                    turns,
                     llama_state.clone(),
                     &db,
                     ipc_for_status.clone(),
@@ -268,51 +270,124 @@ pub fn run_worker(db_path: &str) {
 
             WorkerCommand::Shutdown => {
                 eprintln!("[WORKER] Shutdown requested");
-                cancel_flag.store(true, Ordering::SeqCst);
-                if let Some(handle) = generation_thread.take() {
-                    let _ = handle.join();
-                }
+                cancel_and_join_all(&mut generations, &token_rx, |response| {
+                    write_response_no_flush(&mut ipc_writer, &response);
+                });
+                let _ = ipc_writer.flush();
+                rewarm.join();
+                model_commands::auto_save_session(&llama_state, db_path);
                 write_response(&mut ipc_writer, &WorkerResponse::ok(req_id, WorkerPayload::Pong));
                 break;
             }
 
-            WorkerCommand::LoadModel { model_path, gpu_layers, mmproj_path, agent_id } => {
-                if generation_thread.is_some() {
+            WorkerCommand::LoadModel { model_path, gpu_layers, mmproj_path, agent_id, kv_cache_type } => {
+                if any_running(&generations) {
                     write_response(
                         &mut ipc_writer,
                         &WorkerResponse::error(req_id, "Cannot load model while generation is in progress"),
                     );
                     continue;
                 }
+                rewarm.join();
                 model_commands::handle_load_model(
                     req_id,
                     model_path,
                     gpu_layers,
                     mmproj_path,
                     agent_id,
+                    kv_cache_type,
                     llama_state.clone(),
                     &db,
+                    db_path,
                     &mut ipc_writer,
                 );
             }
 
             WorkerCommand::UnloadModel => {
-                if generation_thread.is_some() {
-                    cancel_flag.store(true, Ordering::SeqCst);
-                    if let Some(handle) = generation_thread.take() {
-                        let _ = handle.join();
-                    }
-                }
-                model_commands::handle_unload_model(req_id, &llama_state, &mut ipc_writer);
+                cancel_and_join_all(&mut generations, &token_rx, |response| {
+                    write_response_no_flush(&mut ipc_writer, &response);
+                });
+                let _ = ipc_writer.flush();
+                model_commands::handle_unload_model(req_id, &llama_state, &mut rewarm, &mut ipc_writer);
             }
 
             WorkerCommand::GetModelStatus => {
                 model_commands::handle_get_model_status(req_id, &llama_state, &mut ipc_writer);
             }
 
-            WorkerCommand::CancelGeneration => {
-                cancel_flag.store(true, Ordering::SeqCst);
-                eprintln!("[WORKER] Cancellation flag set");
+            WorkerCommand::SetTemplateOverride { template } => {
+                if any_running(&generations) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot change chat template while generation is in progress"));
+                    continue;
+                }
+                model_commands::handle_set_template_override(req_id, template, &llama_state, &mut ipc_writer);
+            }
+
+            WorkerCommand::SetSystemPrompt { prompt } => {
+                if any_running(&generations) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot change system prompt while generation is in progress"));
+                    continue;
+                }
+                model_commands::handle_set_system_prompt(req_id, prompt, &llama_state, &db, &mut rewarm, &mut ipc_writer);
+            }
+
+            WorkerCommand::SelfTest => {
+                if any_running(&generations) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot run self-test while generation is in progress"));
+                    continue;
+                }
+                model_commands::handle_self_test(req_id, &llama_state, &mut ipc_writer);
+            }
+
+            WorkerCommand::SaveSession { path } => {
+                if any_running(&generations) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot save session while generation is in progress"));
+                    continue;
+                }
+                model_commands::handle_save_session(req_id, path, &llama_state, &mut ipc_writer);
+            }
+
+            WorkerCommand::LoadSession { path } => {
+                if any_running(&generations) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot load session while generation is in progress"));
+                    continue;
+                }
+                model_commands::handle_load_session(req_id, path, &llama_state, &mut ipc_writer);
+            }
+
+            WorkerCommand::CountTokens { text } => {
+                if any_running(&generations) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, "Cannot count tokens while generation is in progress"));
+                    continue;
+                }
+                model_commands::handle_count_tokens(req_id, text, &llama_state, &mut ipc_writer);
+            }
+
+            WorkerCommand::CancelGeneration { conversation_id } => {
+                let mut cancelled = 0;
+                for running in generations.iter().filter(|g| !g.handle.is_finished()) {
+                    let active = running.conversation.lock().unwrap().clone();
+                    if cancel_applies(conversation_id.as_deref(), active.as_deref()) {
+                        running.cancel.store(true, Ordering::SeqCst);
+                        cancelled += 1;
+                        // Messages queued behind the cancelled turn would otherwise run next
+                        if let Some(conv) = active.as_deref() {
+                            match db.clear_queued_messages(conv) {
+                                Ok(0) => {}
+                                Ok(n) => eprintln!("[WORKER] Dropped {n} queued message(s) for conv={conv}"),
+                                Err(e) => eprintln!("[WORKER] Failed to drop queued messages for conv={conv}: {e}"),
+                            }
+                        }
+                    }
+                }
+                if cancelled > 0 {
+                    eprintln!("[WORKER] Cancellation flag set on {cancelled} generation(s)");
+                } else {
+                    eprintln!(
+                        "[WORKER] Ignoring cancel for conv={}: not generating",
+                        conversation_id.as_deref().unwrap_or("?")
+                    );
+                }
                 // No response needed for cancel (fire-and-forget)
             }
 
@@ -320,19 +395,13 @@ pub fn run_worker(db_path: &str) {
                 conversation_id,
                 prompt,
             } => {
-                // Clean up finished generation thread before checking availability
-                if let Some(handle) = generation_thread.take() {
-                    if handle.is_finished() {
-                        let _ = handle.join();
-                    } else {
-                        // Still actually running — put it back and reject
-                        generation_thread = Some(handle);
-                        write_response(
-                            &mut ipc_writer,
-                            &WorkerResponse::error(req_id, "Cannot generate title while generation is in progress"),
-                        );
-                        continue;
-                    }
+                reap_finished(&mut generations);
+                if any_running(&generations) {
+                    write_response(
+                        &mut ipc_writer,
+                        &WorkerResponse::error(req_id, "Cannot generate title while generation is in progress"),
+                    );
+                    continue;
                 }
                 other_commands::handle_generate_title(
                     req_id,
@@ -349,57 +418,71 @@ pub fn run_worker(db_path: &str) {
                 skip_user_logging,
                 image_data,
                 agent_id,
+                on_overflow,
+                overrides,
             } => {
-                // Clean up finished generation thread before checking availability.
-                if let Some(handle) = generation_thread.take() {
-                    if handle.is_finished() {
-                        let _ = handle.join();
-                    } else if cancel_flag.load(Ordering::SeqCst) {
-                        // Cancel was requested — wait up to 3s for the thread to finish
-                        eprintln!("[WORKER] Waiting for cancelled generation to finish...");
-                        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3);
-                        while !handle.is_finished() && std::time::Instant::now() < deadline {
-                            std::thread::sleep(std::time::Duration::from_millis(50));
-                        }
-                        if handle.is_finished() {
-                            let _ = handle.join();
-                            eprintln!("[WORKER] Cancelled generation cleaned up");
-                        } else {
-                            // Still stuck after 3s — reject
-                            generation_thread = Some(handle);
-                            write_response(
-                                &mut ipc_writer,
-                                &WorkerResponse::error(req_id, "Generation still cancelling, please wait"),
-                            );
-                            continue;
-                        }
-                    } else {
+                // Clean up finished generation threads before checking availability.
+                reap_finished(&mut generations);
+                // With parallelism above 1, generations past the limit queue in the engine
+                let max_parallel = db.load_config().max_parallel_generations.max(1) as usize;
+                llama_chat_engine::context_pool::generation_slots().set_capacity(max_parallel);
+                if max_parallel == 1 && !generations.is_empty() {
+                    if generations.iter().any(|g| !g.cancel.load(Ordering::SeqCst)) {
                         // Still actually running, not cancelled — reject
-                        generation_thread = Some(handle);
                         write_response(
                             &mut ipc_writer,
                             &WorkerResponse::error(req_id, "Generation already in progress"),
                         );
                         continue;
                     }
+                    // Cancel was requested — wait up to 3s for the thread to finish
+                    eprintln!("[WORKER] Waiting for cancelled generation to finish...");
+                    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3);
+                    while any_running(&generations) && std::time::Instant::now() < deadline {
+                        std::thread::sleep(std::time::Duration::from_millis(50));
+                    }
+                    reap_finished(&mut generations);
+                    if !generations.is_empty() {
+                        // Still stuck after 3s — reject
+                        write_response(
+                            &mut ipc_writer,
+                            &WorkerResponse::error(req_id, "Generation still cancelling, please wait"),
+                        );
+                        continue;
+                    }
+                    eprintln!("[WORKER] Cancelled generation cleaned up");
                 }
 
-                // Reset cancel flag
-                cancel_flag.store(false, Ordering::SeqCst);
+                // Nothing to generate with: fail before any template or tokenization work
+                if let Err(e) = llama_chat_config::require_model(
+                    model_loaded(&llama_state),
+                    &db,
+                    conversation_id.as_deref(),
+                    agent_id.as_deref(),
+                ) {
+                    write_response(&mut ipc_writer, &WorkerResponse::error(req_id, e));
+                    continue;
+                }
+
+                let cancel_flag = Arc::new(AtomicBool::new(false));
+                let generating_conversation = Arc::new(Mutex::new(conversation_id.clone()));
 
                 let state = llama_state.clone();
                 let db = db.clone();
                 let cancel = cancel_flag.clone();
                 let tx = token_tx.clone();
                 let mcp = mcp_manager.clone();
+                let active_conversation = generating_conversation.clone();
 
                 eprintln!(
-                    "[WORKER] Starting generation: conv={}, msg_len={}",
+                    "[WORKER] Starting generation: conv={}, msg_len={}, running={}/{}",
                     conversation_id.as_deref().unwrap_or("new"),
-                    user_message.len()
+                    user_message.len(),
+                    generations.len() + 1,
+                    max_parallel
                 );
 
-                generation_thread = Some(thread::spawn(move || {
+                let handle = thread::spawn(move || {
                     let tx_panic = tx.clone();
                     let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                         run_generation(GenerationParams {
@@ -409,9 +492,12 @@ pub fn run_worker(db_path: &str) {
                             skip_user_logging,
                             image_data,
                             agent_id,
+                            on_overflow,
+                            overrides,
                             llama_state: state,
                             db,
                             cancel,
+                            active_conversation,
                             tx,
                             mcp_manager: mcp,
                         });
@@ -427,7 +513,12 @@ pub fn run_worker(db_path: &str) {
                         eprintln!("[WORKER] Generation thread panicked: {msg}");
                         let _ = tx_panic.send(WorkerResponse::error(req_id, format!("Generation panicked: {msg}")));
                     }
-                }));
+                });
+                generations.push(RunningGeneration {
+                    handle,
+                    cancel: cancel_flag,
+                    conversation: generating_conversation,
+                });
             }
         }
     }
@@ -436,3 +527,144 @@ pub fn run_worker(db_path: &str) {
     crate::prevent_sleep::force_release();
     std::process::exit(0);
 }
+
+/// Join the generation threads that have finished.
+fn reap_finished(generations: &mut Vec<RunningGeneration>) {
+    let (finished, running): (Vec<_>, Vec<_>) =
+        generations.drain(..).partition(|g| g.handle.is_finished());
+    *generations = running;
+    for generation in finished {
+        let _ = generation.handle.join();
+    }
+}
+
+fn any_running(generations: &[RunningGeneration]) -> bool {
+    generations.iter().any(|g| !g.handle.is_finished())
+}
+
+/// Cancel every generation and wait for all of them to stop, handing what
+/// they still send on `token_rx` to `forward`. The channel is bounded: a
+/// generation blocked on a full channel only stops once it's drained.
+fn cancel_and_join_all(
+    generations: &mut Vec<RunningGeneration>,
+    token_rx: &Receiver<WorkerResponse>,
+    mut forward: impl FnMut(WorkerResponse),
+) {
+    const DRAIN_POLL: std::time::Duration = std::time::Duration::from_millis(20);
+    for generation in generations.iter() {
+        generation.cancel.store(true, Ordering::SeqCst);
+    }
+    while any_running(generations) {
+        match token_rx.recv_timeout(DRAIN_POLL) {
+            Ok(response) => forward(response),
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => {}
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => thread::sleep(DRAIN_POLL),
+        }
+    }
+    for generation in generations.drain(..) {
+        let _ = generation.handle.join();
+    }
+    // Terminal responses sent right before the threads exited
+    while let Ok(response) = token_rx.try_recv() {
+        forward(response);
+    }
+}
+
+/// Whether a cancel for `requested` (None = whatever is running) applies to the
+/// generation of `active` (None = nothing generating).
+fn cancel_applies(requested: Option<&str>, active: Option<&str>) -> bool {
+    match requested {
+        None => true,
+        Some(id) => active == Some(id),
+    }
+}
+
+fn model_loaded(llama_state: &SharedLlamaState) -> bool {
+    llama_state
+        .lock()
+        .map(|g| g.as_ref().is_some_and(|s| s.model.is_some()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_only_stops_the_matching_conversation() {
+        // B is generating: cancelling A leaves it running, cancelling B stops it
+        assert!(!cancel_applies(Some("chat_a"), Some("chat_b")));
+        assert!(cancel_applies(Some("chat_b"), Some("chat_b")));
+        // Nothing generating: a targeted cancel is ignored
+        assert!(!cancel_applies(Some("chat_a"), None));
+        // Untargeted cancel keeps stopping the active generation
+        assert!(cancel_applies(None, Some("chat_b")));
+    }
+
+    #[test]
+    fn test_cancel_and_join_all_drains_a_full_token_channel() {
+        let (tx, rx) = crossbeam_channel::bounded(2);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let handle = thread::spawn({
+            let cancel = cancel.clone();
+            move || {
+                // Streams into the channel nobody reads, then sends its terminal response
+                while !cancel.load(Ordering::SeqCst) {
+                    let _ = tx.send(WorkerResponse::ok(1, WorkerPayload::Pong));
+                }
+                let _ = tx.send(WorkerResponse::ok(1, WorkerPayload::GenerationCancelled));
+            }
+        });
+        // Let the generation fill the channel and block on it
+        while !rx.is_full() {
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let mut generations = vec![RunningGeneration {
+            handle,
+            cancel,
+            conversation: Arc::new(Mutex::new(None)),
+        }];
+
+        let (done_tx, done_rx) = crossbeam_channel::bounded(1);
+        thread::spawn(move || {
+            let mut forwarded = Vec::new();
+            cancel_and_join_all(&mut generations, &rx, |response| forwarded.push(response));
+            let _ = done_tx.send((generations.len(), forwarded));
+        });
+        let (left, forwarded) = done_rx
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("cancel_and_join_all returned");
+        assert_eq!(left, 0);
+        assert!(matches!(
+            forwarded.last().map(|r| &r.payload),
+            Some(WorkerPayload::GenerationCancelled)
+        ));
+    }
+
+    #[test]
+    fn test_cancel_command_wire_format() {
+        let legacy: WorkerCommand = serde_json::from_str(r#"{"type":"CancelGeneration"}"#).unwrap();
+        assert!(matches!(
+            legacy,
+            WorkerCommand::CancelGeneration {
+                conversation_id: None
+            }
+        ));
+        let json = serde_json::to_string(&WorkerCommand::CancelGeneration {
+            conversation_id: Some("chat_a".to_string()),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"type":"CancelGeneration","conversation_id":"chat_a"}"#);
+    }
+
+    #[test]
+    fn test_set_system_prompt_wire_format() {
+        let json = serde_json::to_string(&WorkerCommand::SetSystemPrompt {
+            prompt: Some("Be brief.".to_string()),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"type":"SetSystemPrompt","prompt":"Be brief."}"#);
+        let reply: WorkerPayload = serde_json::from_str(r#"{"type":"SystemPromptSet"}"#).unwrap();
+        assert!(matches!(reply, WorkerPayload::SystemPromptSet));
+    }
+}
@@ -1,43 +1,72 @@
-//! LoadModel, UnloadModel, GetModelStatus command handlers.
+//! LoadModel, UnloadModel, GetModelStatus, SetTemplateOverride, SetSystemPrompt,
+//! SelfTest, SaveSession/LoadSession, CountTokens command handlers.
 
 use std::io::Write;
+use std::path::Path;
 use std::sync::Arc;
 
+use llama_chat_config::{resolve_model_path, ModelTarget};
 use llama_chat_db::SharedDatabase;
 use llama_chat_engine::model_manager::{get_model_status, load_model, ModelParams};
-use llama_chat_types::models::SharedLlamaState;
+use llama_chat_engine::session;
+use llama_chat_engine::templates::{is_supported_template, SUPPORTED_TEMPLATES};
+use llama_chat_types::models::{KvCacheTypes, LoadPhase, SharedLlamaState};
 
 use super::super::ipc_types::*;
 use super::stdout::write_response;
 
 /// Handle LoadModel command. Polls progress and writes LoadingProgress messages inline.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_load_model(
     req_id: u64,
     model_path: String,
     gpu_layers: Option<u32>,
     mmproj_path: Option<String>,
     agent_id: Option<String>,
+    kv_cache_type: KvCacheTypes,
     llama_state: SharedLlamaState,
     db: &SharedDatabase,
+    db_path: &str,
     ipc_writer: &mut impl Write,
 ) {
-    eprintln!("[WORKER] Loading model: {model_path} (gpu_layers: {gpu_layers:?}, mmproj: {mmproj_path:?}, agent: {agent_id:?})");
-
     let db_config = if let Some(ref id) = agent_id {
         db.load_config_for_agent(id)
     } else {
         db.load_config()
     };
+    let model_path = match resolve_model_path(
+        &model_path,
+        db_config.models_directory.as_deref(),
+        db_config.allow_external_model_paths,
+    )
+    .and_then(ModelTarget::into_file)
+    {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("[WORKER] Rejected model path: {e}");
+            write_response(ipc_writer, &WorkerResponse::error(req_id, e));
+            return;
+        }
+    };
+    eprintln!("[WORKER] Loading model: {model_path} (gpu_layers: {gpu_layers:?}, mmproj: {mmproj_path:?}, agent: {agent_id:?})");
     let model_params = ModelParams {
         use_mlock: db_config.use_mlock,
         use_mmap: db_config.use_mmap,
         main_gpu: db_config.main_gpu,
         split_mode: db_config.split_mode.clone(),
+        kv_cache_type,
     };
 
     // Progress tracking: AtomicU8 written by llama.cpp callback, polled inline below.
+    // The phase slot carries coarse markers from load_model for when the callback is silent.
     let progress = Arc::new(std::sync::atomic::AtomicU8::new(0));
     let progress_for_load = progress.clone();
+    let phase = Arc::new(std::sync::atomic::AtomicU8::new(LoadPhase::Started.as_u8()));
+    let phase_for_load = phase.clone();
+    write_response(ipc_writer, &WorkerResponse::ok(
+        req_id,
+        WorkerPayload::LoadingProgress { progress: 0, phase: Some(LoadPhase::Started) },
+    ));
 
     // Run model loading in a background thread so we can poll progress from here
     let state_for_load = llama_state.clone();
@@ -53,18 +82,22 @@ pub fn handle_load_model(
             Some(&model_params),
             mmproj_path.as_deref(),
             Some(progress_for_load),
+            Some(phase_for_load),
         ))
     });
 
     // Poll progress from the main thread (which owns ipc_writer) and write directly
-    let mut last_sent: u8 = 0;
+    let mut last_sent = (0u8, LoadPhase::Started);
     while !load_handle.is_finished() {
         std::thread::sleep(std::time::Duration::from_millis(100));
-        let current = progress.load(std::sync::atomic::Ordering::Relaxed);
+        let current = (
+            progress.load(std::sync::atomic::Ordering::Relaxed),
+            LoadPhase::from_u8(phase.load(std::sync::atomic::Ordering::Relaxed)),
+        );
         if current != last_sent {
             write_response(ipc_writer, &WorkerResponse::ok(
                 req_id,
-                WorkerPayload::LoadingProgress { progress: current },
+                WorkerPayload::LoadingProgress { progress: current.0, phase: Some(current.1) },
             ));
             last_sent = current;
         }
@@ -73,7 +106,7 @@ pub fn handle_load_model(
     let result = load_handle.join().expect("Model load thread panicked");
 
     match result {
-        Ok(()) => {
+        Ok(load_outcome) => {
             let guard = llama_state.lock().unwrap();
             let s = guard.as_ref().unwrap();
             let block_count = s.current_model_path.as_deref()
@@ -90,12 +123,21 @@ pub fn handle_load_model(
                 has_vision: Some(s.vision_state.is_some()),
                 #[cfg(not(feature = "vision"))]
                 has_vision: Some(false),
+                load_outcome,
+                effective_backend: s.effective_backend.clone(),
+                recommended_sampling: s.recommended_sampling,
             };
             drop(guard);
-            eprintln!("[WORKER] Model loaded successfully");
+            eprintln!("[WORKER] Model loaded successfully ({load_outcome:?})");
+
+            // A session saved on graceful shutdown replaces the warmup entirely
+            if restore_auto_session(&llama_state, db_path) {
+                write_response(ipc_writer, &WorkerResponse::ok(req_id, payload));
+                return;
+            }
 
             // Signal frontend that model file is loaded, now warming up system prompt
-            write_response(ipc_writer, &WorkerResponse::ok(0, WorkerPayload::LoadingProgress { progress: 101 }));
+            write_response(ipc_writer, &WorkerResponse::ok(0, WorkerPayload::LoadingProgress { progress: 101, phase: Some(LoadPhase::WarmingUp) }));
 
             // Pre-evaluate system prompt into KV cache for faster first response.
             // Run in background thread with 30s timeout to prevent hanging the
@@ -128,22 +170,100 @@ pub fn handle_load_model(
     }
 }
 
+/// Restore the shutdown session saved for the model just loaded, if any.
+/// The file is consumed either way so a bad session isn't retried forever.
+fn restore_auto_session(llama_state: &SharedLlamaState, db_path: &str) -> bool {
+    let Some(model_path) = loaded_model_path(llama_state) else {
+        return false;
+    };
+    let path = session::auto_session_path(db_path, &model_path);
+    if !path.exists() {
+        return false;
+    }
+    let restored = match session::load_session(llama_state, &path) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("[WORKER] Session restore failed (non-fatal): {e}");
+            false
+        }
+    };
+    session::remove_session(&path);
+    restored
+}
+
+/// Save the cached context on graceful shutdown so the next worker can resume it.
+pub fn auto_save_session(llama_state: &SharedLlamaState, db_path: &str) {
+    let has_cache = llama_state
+        .lock()
+        .map(|g| g.as_ref().is_some_and(|s| s.inference_cache.is_some()))
+        .unwrap_or(false);
+    let Some(model_path) = loaded_model_path(llama_state).filter(|_| has_cache) else {
+        return;
+    };
+    let path = session::auto_session_path(db_path, &model_path);
+    if let Err(e) = session::save_session(llama_state, &path) {
+        eprintln!("[WORKER] Session auto-save failed (non-fatal): {e}");
+        session::remove_session(&path);
+    }
+}
+
+fn loaded_model_path(llama_state: &SharedLlamaState) -> Option<String> {
+    llama_state
+        .lock()
+        .ok()?
+        .as_ref()
+        .and_then(|s| s.current_model_path.clone())
+}
+
+/// The background re-warm started by SetSystemPrompt, if one is running.
+/// Joined before the model is unloaded or replaced so it never outlives it.
+#[derive(Default)]
+pub struct RewarmThread(Option<std::thread::JoinHandle<()>>);
+
+impl RewarmThread {
+    /// Wait for the running re-warm, if any, to finish.
+    pub fn join(&mut self) {
+        if let Some(handle) = self.0.take() {
+            if handle.join().is_err() {
+                eprintln!("[WORKER] System prompt re-warm thread panicked");
+            }
+        }
+    }
+
+    fn spawn(&mut self, f: impl FnOnce() + Send + 'static) {
+        self.join();
+        self.0 = Some(std::thread::spawn(f));
+    }
+}
+
 /// Handle UnloadModel command.
 pub fn handle_unload_model(
     req_id: u64,
     llama_state: &SharedLlamaState,
+    rewarm: &mut RewarmThread,
     ipc_writer: &mut impl Write,
 ) {
     eprintln!("[WORKER] Unloading model");
+    rewarm.join();
+    // Running generations use the model without the state lock; wait for them
+    let _exclusive = llama_chat_engine::context_pool::exclusive_model();
     let mut guard = llama_state.lock().unwrap();
     if let Some(ref mut state) = *guard {
         state.inference_cache = None;
+        state.idle_contexts.clear();
         #[cfg(feature = "vision")]
         { state.vision_state = None; }
         state.model = None;
         state.current_model_path = None;
+        state.loaded_params = None;
         state.cached_system_prompt = None;
         state.cached_prompt_key = None;
+        state.template_override = None;
+        state.recommended_sampling = None;
+        state.model_add_bos = None;
+        state.kv_elements_per_token = None;
+        state.kv_cache_vram_budget = None;
+        llama_chat_engine::model_manager::set_tool_format(state, None);
     }
     drop(guard);
     eprintln!("[WORKER] Model unloaded");
@@ -175,3 +295,176 @@ pub fn handle_get_model_status(
     };
     write_response(ipc_writer, &WorkerResponse::ok(req_id, payload));
 }
+
+/// Handle SetTemplateOverride command. `None` clears the override.
+pub fn handle_set_template_override(
+    req_id: u64,
+    template: Option<String>,
+    llama_state: &SharedLlamaState,
+    ipc_writer: &mut impl Write,
+) {
+    if let Some(ref name) = template {
+        if !is_supported_template(name) {
+            write_response(ipc_writer, &WorkerResponse::error(
+                req_id,
+                format!("Unknown chat template '{name}' (supported: {})", SUPPORTED_TEMPLATES.join(", ")),
+            ));
+            return;
+        }
+    }
+
+    let mut guard = llama_state.lock().unwrap();
+    let Some(state) = guard.as_mut().filter(|s| s.model.is_some()) else {
+        drop(guard);
+        write_response(ipc_writer, &WorkerResponse::error(req_id, "No model loaded"));
+        return;
+    };
+    eprintln!("[WORKER] Template override: {template:?} (detected: {:?})", state.chat_template_type);
+    state.template_override = template.clone();
+    drop(guard);
+    write_response(ipc_writer, &WorkerResponse::ok(req_id, WorkerPayload::TemplateOverrideSet { template }));
+}
+
+/// Handle CountTokens command.
+pub fn handle_count_tokens(
+    req_id: u64,
+    text: String,
+    llama_state: &SharedLlamaState,
+    ipc_writer: &mut impl Write,
+) {
+    let guard = llama_state.lock().unwrap();
+    let Some(model) = guard.as_ref().and_then(|s| s.model.as_ref()) else {
+        drop(guard);
+        write_response(ipc_writer, &WorkerResponse::error(req_id, "No model loaded"));
+        return;
+    };
+    let result = model.str_to_token(&text, llama_cpp_2::model::AddBos::Never);
+    drop(guard);
+    let response = match result {
+        Ok(tokens) => WorkerResponse::ok(req_id, WorkerPayload::TokenCount { count: tokens.len() }),
+        Err(e) => WorkerResponse::error(req_id, format!("Tokenization failed: {e}")),
+    };
+    write_response(ipc_writer, &response);
+}
+
+/// Handle SetSystemPrompt command. Replies once the prompt is cached, then
+/// re-warms the KV prefix on a background thread so the next message is fast.
+pub fn handle_set_system_prompt(
+    req_id: u64,
+    prompt: Option<String>,
+    llama_state: &SharedLlamaState,
+    db: &SharedDatabase,
+    rewarm: &mut RewarmThread,
+    ipc_writer: &mut impl Write,
+) {
+    if let Err(e) = llama_chat_engine::config_ext::set_system_prompt(db.as_ref(), llama_state, prompt) {
+        write_response(ipc_writer, &WorkerResponse::error(req_id, e));
+        return;
+    }
+    eprintln!("[WORKER] System prompt updated, re-warming");
+    write_response(ipc_writer, &WorkerResponse::ok(req_id, WorkerPayload::SystemPromptSet));
+
+    let warmup_state = llama_state.clone();
+    let warmup_db = db.clone();
+    rewarm.spawn(move || {
+        match llama_chat_engine::warmup_system_prompt(warmup_state, warmup_db.as_ref(), None) {
+            Ok(()) => eprintln!("[WORKER] System prompt re-warm complete"),
+            Err(e) => eprintln!("[WORKER] System prompt re-warm failed (non-fatal): {e}"),
+        }
+    });
+}
+
+/// Handle SelfTest command: a few greedy tokens from the loaded model.
+pub fn handle_self_test(
+    req_id: u64,
+    llama_state: &SharedLlamaState,
+    ipc_writer: &mut impl Write,
+) {
+    eprintln!("[WORKER] Running self-test");
+    match llama_chat_engine::run_self_test(llama_state) {
+        Ok(report) => {
+            eprintln!("[WORKER] Self-test ok: {:?}", report.first_tokens);
+            write_response(ipc_writer, &WorkerResponse::ok(
+                req_id,
+                WorkerPayload::SelfTestResult {
+                    first_tokens: report.first_tokens,
+                    tokens_generated: report.tokens_generated,
+                    gen_tok_per_sec: report.gen_tok_per_sec,
+                },
+            ));
+        }
+        Err(e) => {
+            eprintln!("[WORKER] Self-test failed: {e}");
+            write_response(ipc_writer, &WorkerResponse::error(req_id, e));
+        }
+    }
+}
+
+/// Handle SaveSession command: write the cached context to `path`.
+pub fn handle_save_session(
+    req_id: u64,
+    path: String,
+    llama_state: &SharedLlamaState,
+    ipc_writer: &mut impl Write,
+) {
+    match session::save_session(llama_state, Path::new(&path)) {
+        Ok(meta) => write_response(ipc_writer, &WorkerResponse::ok(
+            req_id,
+            WorkerPayload::SessionSaved {
+                conversation_id: meta.conversation_id,
+                n_tokens: meta.n_tokens,
+            },
+        )),
+        Err(e) => {
+            eprintln!("[WORKER] Session save failed: {e}");
+            write_response(ipc_writer, &WorkerResponse::error(req_id, e));
+        }
+    }
+}
+
+/// Handle LoadSession command: restore `path` into the inference cache.
+pub fn handle_load_session(
+    req_id: u64,
+    path: String,
+    llama_state: &SharedLlamaState,
+    ipc_writer: &mut impl Write,
+) {
+    match session::load_session(llama_state, Path::new(&path)) {
+        Ok(meta) => write_response(ipc_writer, &WorkerResponse::ok(
+            req_id,
+            WorkerPayload::SessionLoaded {
+                conversation_id: meta.conversation_id,
+                n_tokens: meta.n_tokens,
+            },
+        )),
+        Err(e) => {
+            eprintln!("[WORKER] Session load failed: {e}");
+            write_response(ipc_writer, &WorkerResponse::error(req_id, e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_unload_waits_for_the_rewarm_thread() {
+        let llama_state: SharedLlamaState = Arc::new(Mutex::new(None));
+        let finished = Arc::new(AtomicBool::new(false));
+        let mut rewarm = RewarmThread::default();
+        let flag = finished.clone();
+        rewarm.spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let mut out = Vec::new();
+        handle_unload_model(1, &llama_state, &mut rewarm, &mut out);
+        assert!(finished.load(Ordering::SeqCst), "unload returned before the re-warm ended");
+        assert!(rewarm.0.is_none());
+        assert!(String::from_utf8(out).unwrap().contains("ModelUnloaded"));
+    }
+}
@@ -107,6 +107,7 @@ pub fn handle_get_available_backends(
 pub fn handle_compact_conversation(
     req_id: u64,
     conversation_id: String,
+    turns: Option<usize>,
     llama_state: SharedLlamaState,
     db: &SharedDatabase,
     ipc_for_status: Arc<std::sync::Mutex<std::fs::File>>,
@@ -129,7 +130,12 @@ pub fn handle_compact_conversation(
         }
     });
 
-    match llama_chat_engine::compaction::force_compact_conversation(&conversation_id, db, &llama_state, Some(&status_tx)) {
+    let result = match turns {
+        Some(turns) => llama_chat_engine::compaction::compact_oldest_turns(&conversation_id, turns, db, &llama_state, Some(&status_tx))
+            .map(|_| ()),
+        None => llama_chat_engine::compaction::force_compact_conversation(&conversation_id, db, &llama_state, Some(&status_tx)),
+    };
+    match result {
         Ok(()) => {
             eprintln!("[WORKER] Manual compaction complete for conv={conversation_id}");
             drop(status_tx);
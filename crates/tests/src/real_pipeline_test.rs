@@ -96,6 +96,7 @@ async fn run_test(model_path: &str, num_rounds: usize) {
         None,     // default model params
         None,     // no mmproj
         Some(progress),
+        None,     // no phase markers
     )
     .await
     .expect("Failed to load model");
@@ -177,6 +178,8 @@ async fn run_test(model_path: &str, num_rounds: usize) {
                     None,
                     None,
                     None,
+                    Default::default(),
+                    &Default::default(),
                 ),
             )
             .await;
@@ -164,7 +164,7 @@ pub async fn generate_stream(
                     if first_user.is_none() || first_asst.is_none() { return; }
 
                     // Check if title already exists
-                    let existing_title = db_bg.list_conversations().ok()
+                    let existing_title = db_bg.list_all_conversations().ok()
                         .and_then(|convs| convs.into_iter().find(|c| c.id == conv_clean))
                         .map(|c| c.title)
                         .unwrap_or_default();
@@ -1,6 +1,8 @@
 //! Configuration Tauri commands — get/save app settings.
 
 use crate::web::database::SharedDatabase;
+use crate::web::routes::config::start_config_reload;
+use crate::web::worker::worker_bridge::SharedWorkerBridge;
 use crate::web::models::SamplerConfig;
 use crate::web::config::*;
 
@@ -12,9 +14,14 @@ pub async fn get_config(db: tauri::State<'_, SharedDatabase>) -> Result<SamplerC
     Ok(db_config_to_sampler_config(&db_config))
 }
 
+/// Like `POST /api/config`: load-affecting changes reload the resident model
+/// unless `skip_reload` is set by a caller that loads it itself.
 #[tauri::command]
 pub async fn save_config(
     config: SamplerConfig,
+    gpu_layers: Option<u32>,
+    skip_reload: Option<bool>,
+    bridge: tauri::State<'_, SharedWorkerBridge>,
     db: tauri::State<'_, SharedDatabase>,
 ) -> Result<serde_json::Value, String> {
     if !(0.0..=2.0).contains(&config.temperature) {
@@ -23,16 +30,20 @@ pub async fn save_config(
     if !(0.0..=1.0).contains(&config.top_p) {
         return Err("top_p must be between 0.0 and 1.0".into());
     }
-    if config.context_size.unwrap_or(0) == 0 {
+    if config.context_size == Some(0) {
         return Err("context_size must be positive".into());
     }
 
-    let existing = db.load_config();
+    let mut existing = db.load_config();
     let mut merged = sampler_config_to_db(&config);
-    merged.model_history = existing.model_history;
+    merged.model_history = std::mem::take(&mut existing.model_history);
 
     db.save_config(&merged)
         .map_err(|e| format!("Failed to save configuration: {e}"))?;
 
-    Ok(serde_json::json!({"success": true}))
+    let reload = match skip_reload.unwrap_or(false) {
+        true => None,
+        false => start_config_reload(&bridge, &db, &existing, &merged, gpu_layers).await,
+    };
+    Ok(serde_json::json!({"success": true, "reload": reload}))
 }
@@ -10,14 +10,14 @@ use crate::web::models::*;
 pub async fn get_conversations(
     db: tauri::State<'_, SharedDatabase>,
 ) -> Result<ConversationsResponse, String> {
-    let records = db.list_conversations().unwrap_or_default();
+    let records = db.list_all_conversations().unwrap_or_default();
     let mut seen_ids = std::collections::HashSet::new();
     let mut conversations = Vec::new();
     for r in records {
         if !seen_ids.insert(r.id.clone()) {
             continue;
         }
-        let timestamp_part = r.id.strip_prefix("chat_").unwrap_or(&r.id).to_string();
+        let timestamp_part = llama_chat_db::conversation_id_timestamp(&r.id).to_string();
         let title_opt = db.get_conversation_title(&r.id).ok().flatten();
         let display_name = title_opt.clone()
             .unwrap_or_else(|| format!("Chat {timestamp_part}"));
@@ -39,7 +39,9 @@ pub async fn get_conversation(
     db: tauri::State<'_, SharedDatabase>,
 ) -> Result<ConversationContentResponse, String> {
     let conversation_id = &filename;
-    let db_messages = db.get_messages(conversation_id).unwrap_or_default();
+    let db_messages = db
+        .get_messages_if_exists(conversation_id)?
+        .ok_or_else(|| "Conversation not found".to_string())?;
     // Rebuild messages: merge consecutive assistant tool_call + tool results
     // into a single message with <tool_call>/<tool_response> tags for widget rendering
     let mut messages: Vec<crate::web::models::ChatMessage> = Vec::new();
@@ -77,6 +79,8 @@ pub async fn get_conversation(
                     gen_tokens: m.gen_tokens, prompt_eval_ms: m.prompt_eval_ms, prompt_tokens: m.prompt_tokens,
                     compacted: false, sequence_order: Some(m.sequence_order),
                     parts: Vec::new(), title: None,
+                    token_count: m.token_count, created_at_millis: m.created_at_millis,
+                    reasoning: m.reasoning.clone(),
                 });
                 msg_idx += 1;
             }
@@ -91,6 +95,8 @@ pub async fn get_conversation(
                 gen_tokens: m.gen_tokens, prompt_eval_ms: m.prompt_eval_ms, prompt_tokens: m.prompt_tokens,
                 compacted: m.compacted, sequence_order: Some(m.sequence_order),
                 parts: Vec::new(), title: None,
+                token_count: m.token_count, created_at_millis: m.created_at_millis,
+                reasoning: m.reasoning.clone(),
             });
             msg_idx += 1;
             idx += 1;
@@ -122,6 +128,7 @@ pub async fn get_conversation(
                 compacted: false,
                 sequence_order: None,
                 parts: Vec::new(), title: None,
+                token_count: None, created_at_millis: None, reasoning: None, interrupted: true,
             });
             // Save recovered content as a real message and clear buffer
             if let Ok(mut logger) = web::database::conversation::ConversationLogger::from_existing(
@@ -157,7 +164,9 @@ pub async fn delete_conversation(
     if !filename.starts_with("chat_") {
         return Err("Invalid conversation file".into());
     }
-    db.delete_conversation(&filename)?;
+    if !db.delete_conversation(&filename)? {
+        return Err("Conversation not found".into());
+    }
     Ok(serde_json::json!({"success": true}))
 }
 
@@ -176,7 +185,7 @@ pub async fn compact_conversation(
     conversation_id: String,
     bridge: tauri::State<'_, crate::web::worker::worker_bridge::SharedWorkerBridge>,
 ) -> Result<(), String> {
-    bridge.compact_conversation(&conversation_id).await
+    bridge.compact_conversation(&conversation_id, None).await
 }
 
 #[tauri::command]
@@ -212,6 +221,7 @@ pub fn parse_conversation_to_messages(content: &str) -> Vec<crate::web::models::
                     compacted: false,
                     sequence_order: None,
                     parts: Vec::new(), title: None,
+                    token_count: None, created_at_millis: None, reasoning: None, interrupted: false,
                 });
                 sequence += 1;
             }
@@ -238,6 +248,7 @@ pub fn parse_conversation_to_messages(content: &str) -> Vec<crate::web::models::
             compacted: false,
             sequence_order: None,
             parts: Vec::new(), title: None,
+            token_count: None, created_at_millis: None, reasoning: None, interrupted: false,
         });
     }
 
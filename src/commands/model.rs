@@ -12,6 +12,7 @@ use crate::web::config::*;
 
 #[tauri::command]
 pub async fn get_model_status(
+    conversation_id: Option<String>,
     bridge: tauri::State<'_, SharedWorkerBridge>,
     db: tauri::State<'_, SharedDatabase>,
 ) -> Result<ModelStatus, String> {
@@ -19,8 +20,12 @@ pub async fn get_model_status(
     let progress = if is_loading { Some(bridge.loading_progress()) } else { None };
     let is_generating = bridge.is_generating().await;
     let active_conv_id = if is_generating { bridge.active_conversation_id().await } else { None };
-    let last_finish_reason = if !is_generating {
-        bridge.last_finish_reason().await
+    let still_generating = match conversation_id.as_deref() {
+        Some(conv_id) => bridge.is_generating_conversation(conv_id).await,
+        None => is_generating,
+    };
+    let last_finish_reason = if !still_generating {
+        bridge.last_finish_reason(conversation_id.as_deref()).await
     } else {
         None
     };
@@ -47,6 +52,8 @@ pub async fn get_model_status(
                 has_vision: Some(meta.has_vision),
                 tool_tags: tags,
                 gpu_layers: meta.gpu_layers,
+                effective_backend: meta.effective_backend.clone(),
+                recommended_sampling: meta.recommended_sampling,
                 block_count: meta.block_count,
                 system_prompt_tokens: None,
                 tool_definitions_tokens: None,
@@ -69,6 +76,8 @@ pub async fn get_model_status(
             has_vision: None,
             tool_tags: None,
             gpu_layers: None,
+            effective_backend: None,
+            recommended_sampling: None,
             block_count: None,
             system_prompt_tokens: None,
             tool_definitions_tokens: None,
@@ -97,7 +106,8 @@ pub async fn load_model(
     bridge: tauri::State<'_, SharedWorkerBridge>,
     db: tauri::State<'_, SharedDatabase>,
 ) -> Result<ModelResponse, String> {
-    match bridge.load_model(&request.model_path, request.gpu_layers, request.mmproj_path, None).await {
+    let kv_cache_type = request.kv_cache_types()?;
+    match bridge.load_model_with_kv_cache(&request.model_path, request.gpu_layers, request.mmproj_path, None, kv_cache_type).await {
         Ok(meta) => {
             add_to_model_history(&db, &request.model_path);
             let config = load_config(&db);
@@ -118,6 +128,8 @@ pub async fn load_model(
                     has_vision: Some(meta.has_vision),
                     tool_tags: Some(get_tool_tags_for_model(meta.general_name.as_deref())),
                     gpu_layers: meta.gpu_layers,
+                    effective_backend: meta.effective_backend.clone(),
+                    recommended_sampling: meta.recommended_sampling,
                     block_count: meta.block_count,
                     system_prompt_tokens: None,
                     tool_definitions_tokens: None,
@@ -126,12 +138,14 @@ pub async fn load_model(
                     supports_thinking: None,
                     is_agent_model: None,
                 }),
+                load_outcome: Some(meta.load_outcome),
             })
         }
         Err(e) => Ok(ModelResponse {
             success: false,
             message: format!("Failed to load model: {e}"),
             status: None,
+            load_outcome: None,
         }),
     }
 }
@@ -157,6 +171,8 @@ pub async fn unload_model(
                 has_vision: None,
                 tool_tags: None,
                 gpu_layers: None,
+                effective_backend: None,
+                recommended_sampling: None,
                 block_count: None,
                 system_prompt_tokens: None,
                 tool_definitions_tokens: None,
@@ -165,11 +181,13 @@ pub async fn unload_model(
                 supports_thinking: None,
                 is_agent_model: None,
             }),
+            load_outcome: None,
         }),
         Err(e) => Ok(ModelResponse {
             success: false,
             message: format!("Failed to unload model: {e}"),
             status: None,
+            load_outcome: None,
         }),
     }
 }
@@ -210,6 +228,13 @@ pub async fn get_model_history(
     db.get_model_history()
 }
 
+#[tauri::command]
+pub async fn get_model_history_entries(
+    db: tauri::State<'_, SharedDatabase>,
+) -> Result<Vec<web::database::config::ModelHistoryEntry>, String> {
+    db.get_model_history_entries()
+}
+
 #[tauri::command]
 pub async fn add_model_history(
     model_path: String,
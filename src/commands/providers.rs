@@ -131,9 +131,7 @@ pub async fn stream_provider(
 ) -> Result<serde_json::Value, String> {
     let api_keys = load_provider_api_keys_json(&db);
 
-    let conv_id = conversation_id.unwrap_or_else(|| {
-        format!("chat_{}", chrono::Local::now().format("%Y-%m-%d-%H-%M-%S-%3f"))
-    });
+    let conv_id = conversation_id.unwrap_or_else(llama_chat_db::generate_conversation_id);
 
     let provider_prompt = crate::web::providers::compose_prompt(
         &provider,
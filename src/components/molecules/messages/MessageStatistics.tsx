@@ -48,6 +48,16 @@ const FINISH_REASON_BADGES: Record<string, { color: string; title: string; label
       'Maximum auto-continue attempts reached — the model could not complete the task within the context limit',
     label: 'max retries reached',
   },
+  tool_limit: {
+    color: 'text-orange-400',
+    title: 'Tool call limit for this message reached (max_tool_iterations) — generation stopped',
+    label: 'tool limit',
+  },
+  stop_sequence: {
+    color: 'text-gray-400',
+    title: 'Generation ended at a stop sequence supplied with the request',
+    label: 'stop sequence',
+  },
   infinite_loop: {
     color: 'text-red-400',
     title: 'Model got stuck repeating the same tool call — generation force-stopped',
@@ -16,8 +16,6 @@ import { useUIContext } from '../../hooks/useUIContext';
 import { isTauriEnv } from '../../utils/tauri';
 
 const TAURI = isTauriEnv();
-// Web-mode backend (port 18080) — used to open the wry browser window when not in Tauri.
-const WEB_BACKEND = 'http://127.0.0.1:18080';
 
 async function tauriInvoke<T = unknown>(cmd: string, args?: Record<string, unknown>): Promise<T> {
   const { invoke } = await import('@tauri-apps/api/core');
@@ -195,7 +193,8 @@ export const BrowserView = React.memo(() => {
   // a standalone wry (WebView2) window — a real browser, not an iframe.
   const webNavigate = useCallback(async (url: string) => {
     try {
-      await fetch(`${WEB_BACKEND}/api/browser/navigate`, {
+      // Same origin as the page: the backend itself, or the Vite proxy in dev
+      await fetch('/api/browser/navigate', {
         method: 'POST',
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify({ url }),
@@ -9,7 +9,7 @@ const TOKEN_COPIED_RESET_MS = 2000;
 /**
  * Replace the port in a server-provided LAN URL with the port the browser
  * is actually running on. This ensures the QR code works in dev (Vite :14000)
- * and production (Rust :18080) without any special config.
+ * and production (Rust, LLAMA_CHAT_PORT) without any special config.
  */
 function adaptPort(serverUrl: string): string {
   try {
@@ -27,6 +27,8 @@ function apiFetch(url: string, init?: RequestInit): Promise<Response> {
 
 interface RemoteStatus {
   token: string;
+  /** Port the server listens on (LLAMA_CHAT_PORT). */
+  port: number;
   lan_url: string | null;
   lan_qr: string | null;
 }
@@ -35,6 +37,7 @@ interface UpnpResult {
   success: boolean;
   public_url?: string;
   public_qr?: string;
+  external_port?: number;
   error?: string;
 }
 
@@ -76,13 +79,14 @@ export const RemoteAccess = (): JSX.Element => {
       .finally(() => setUpnpLoading(false));
   }, []);
 
+  const externalPort = upnpResult?.external_port ?? status?.port;
   const handleDisableUpnp = useCallback(() => {
     setUpnpLoading(true);
-    apiFetch('/api/remote/upnp/disable', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: JSON.stringify({ external_port: 18080 }) })
+    apiFetch('/api/remote/upnp/disable', { method: 'POST', headers: { 'Content-Type': 'application/json' }, body: JSON.stringify({ external_port: externalPort }) })
       .then(() => setUpnpResult(null))
       .catch(() => {})
       .finally(() => setUpnpLoading(false));
-  }, []);
+  }, [externalPort]);
 
   const handleCopyToken = useCallback(() => {
     if (!status?.token) return;
@@ -97,7 +101,7 @@ export const RemoteAccess = (): JSX.Element => {
   }
 
   // For LAN URLs: adapt port to match the current browser port so the QR code
-  // works in both dev (Vite :14000) and production (:18080).
+  // works in both dev (Vite :14000) and production (LLAMA_CHAT_PORT).
   const adaptedLanUrl = status?.lan_url ? adaptPort(status.lan_url) : null;
   const adaptedLanQr = adaptedLanUrl && status?.token ? `${adaptedLanUrl}#token=${status.token}` : null;
   const qrUrl = upnpResult?.public_qr ?? adaptedLanQr;
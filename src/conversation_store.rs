@@ -0,0 +1,264 @@
+//! Bounded conversation history for the legacy `AppState` commands.
+//!
+//! At most `cap` conversations stay in memory. The least recently used one is
+//! spilled to `<spill_dir>/<id>.json` and read back on its next access, so a
+//! long desktop session no longer grows without bound. Spill files only live
+//! for one session: reading one back deletes it, and opening or dropping the
+//! store clears the directory, so plaintext history doesn't pile up on disk.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+use crate::Message;
+
+/// Conversations kept in memory before the least recently used is spilled.
+pub const DEFAULT_CONVERSATION_CAP: usize = 32;
+
+/// Overrides `DEFAULT_CONVERSATION_CAP`.
+pub const CONVERSATION_CAP_ENV: &str = "LLAMA_CHAT_CONVERSATION_CAP";
+
+pub struct ConversationStore {
+    cap: usize,
+    spill_dir: PathBuf,
+    in_memory: HashMap<String, Vec<Message>>,
+    /// Conversation ids from least to most recently used.
+    recency: VecDeque<String>,
+}
+
+impl ConversationStore {
+    /// `cap` is clamped to at least 1. Spill files left in `spill_dir` by an
+    /// earlier session are deleted.
+    pub fn new(cap: usize, spill_dir: impl Into<PathBuf>) -> Self {
+        let store = Self {
+            cap: cap.max(1),
+            spill_dir: spill_dir.into(),
+            in_memory: HashMap::new(),
+            recency: VecDeque::new(),
+        };
+        store.clear_spills();
+        store
+    }
+
+    /// Spill directory in the app data dir (`LLAMA_CHAT_DATA_DIR`, the working
+    /// directory if unset), next to the database and logs.
+    pub fn default_spill_dir() -> PathBuf {
+        let base = std::env::var("LLAMA_CHAT_DATA_DIR").unwrap_or_else(|_| ".".to_string());
+        PathBuf::from(base).join("legacy_conversations")
+    }
+
+    /// `LLAMA_CHAT_CONVERSATION_CAP`, or `DEFAULT_CONVERSATION_CAP` when unset or invalid.
+    pub fn configured_cap() -> usize {
+        std::env::var(CONVERSATION_CAP_ENV)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_CONVERSATION_CAP)
+    }
+
+    /// Number of conversations currently held in memory.
+    pub fn in_memory_len(&self) -> usize {
+        self.in_memory.len()
+    }
+
+    /// Append a message, creating the conversation if needed.
+    pub fn push(&mut self, conversation_id: &str, message: Message) -> Result<(), String> {
+        self.load(conversation_id)?;
+        self.in_memory
+            .entry(conversation_id.to_string())
+            .or_default()
+            .push(message);
+        self.touch(conversation_id);
+        self.evict_over_cap()
+    }
+
+    /// Messages of one conversation (empty if unknown), from memory or disk.
+    pub fn get(&mut self, conversation_id: &str) -> Result<Vec<Message>, String> {
+        if !self.load(conversation_id)? {
+            return Ok(Vec::new());
+        }
+        self.touch(conversation_id);
+        let messages = self.in_memory[conversation_id].clone();
+        self.evict_over_cap()?;
+        Ok(messages)
+    }
+
+    /// Every conversation, in memory or spilled. Spilled ones are read without
+    /// being brought back into memory.
+    pub fn all(&self) -> Result<HashMap<String, Vec<Message>>, String> {
+        let mut all = self.in_memory.clone();
+        let entries = match std::fs::read_dir(&self.spill_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(all),
+            Err(e) => return Err(format!("Failed to read {}: {e}", self.spill_dir.display())),
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if !all.contains_key(id) {
+                all.insert(id.to_string(), read_spilled(&path)?);
+            }
+        }
+        Ok(all)
+    }
+
+    /// Ensure the conversation is in memory; false when it exists nowhere.
+    fn load(&mut self, conversation_id: &str) -> Result<bool, String> {
+        if self.in_memory.contains_key(conversation_id) {
+            return Ok(true);
+        }
+        let path = self.spill_path(conversation_id);
+        if !path.exists() {
+            return Ok(false);
+        }
+        let messages = read_spilled(&path)?;
+        let _ = std::fs::remove_file(&path);
+        self.in_memory.insert(conversation_id.to_string(), messages);
+        Ok(true)
+    }
+
+    fn touch(&mut self, conversation_id: &str) {
+        self.recency.retain(|id| id != conversation_id);
+        self.recency.push_back(conversation_id.to_string());
+    }
+
+    fn evict_over_cap(&mut self) -> Result<(), String> {
+        while self.in_memory.len() > self.cap {
+            let Some(oldest) = self.recency.pop_front() else {
+                break;
+            };
+            if let Some(messages) = self.in_memory.remove(&oldest) {
+                self.spill(&oldest, &messages)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn spill(&self, conversation_id: &str, messages: &[Message]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.spill_dir)
+            .map_err(|e| format!("Failed to create {}: {e}", self.spill_dir.display()))?;
+        let json = serde_json::to_string(messages)
+            .map_err(|e| format!("Failed to serialize conversation {conversation_id}: {e}"))?;
+        let path = self.spill_path(conversation_id);
+        std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Delete every spill file; failures are ignored (best-effort cleanup).
+    fn clear_spills(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.spill_dir) else {
+            return;
+        };
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// Ids are uuids from `send_message`; anything else is sanitized so it
+    /// can't escape the spill directory.
+    fn spill_path(&self, conversation_id: &str) -> PathBuf {
+        let safe: String = conversation_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.spill_dir.join(format!("{safe}.json"))
+    }
+}
+
+impl Default for ConversationStore {
+    fn default() -> Self {
+        Self::new(Self::configured_cap(), Self::default_spill_dir())
+    }
+}
+
+impl Drop for ConversationStore {
+    fn drop(&mut self) {
+        self.clear_spills();
+        // Only removed when nothing else was put there
+        let _ = std::fs::remove_dir(&self.spill_dir);
+    }
+}
+
+fn read_spilled(path: &Path) -> Result<Vec<Message>, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(content: &str) -> Message {
+        Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: "user".to_string(),
+            content: content.to_string(),
+            timestamp: 0,
+        }
+    }
+
+    fn temp_spill_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("llama_chat_store_test_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_memory_stays_within_cap() {
+        let dir = temp_spill_dir();
+        let mut store = ConversationStore::new(2, &dir);
+        for i in 0..5 {
+            store.push(&format!("conv-{i}"), message("hi")).unwrap();
+            assert!(store.in_memory_len() <= 2);
+        }
+        assert_eq!(store.in_memory_len(), 2);
+        assert_eq!(store.all().unwrap().len(), 5);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_evicted_conversation_is_read_back_from_disk() {
+        let dir = temp_spill_dir();
+        let mut store = ConversationStore::new(1, &dir);
+        store.push("first", message("one")).unwrap();
+        store.push("first", message("two")).unwrap();
+        store.push("second", message("other")).unwrap();
+        assert!(dir.join("first.json").exists());
+
+        let first = store.get("first").unwrap();
+        let contents: Vec<&str> = first.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["one", "two"]);
+        assert_eq!(store.in_memory_len(), 1);
+        assert!(dir.join("second.json").exists());
+        assert!(store.get("missing").unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_spills_do_not_outlive_the_session() {
+        let dir = temp_spill_dir();
+        let mut store = ConversationStore::new(1, &dir);
+        store.push("first", message("secret")).unwrap();
+        store.push("second", message("other")).unwrap();
+        assert!(dir.join("first.json").exists());
+        drop(store);
+        assert!(!dir.exists());
+
+        // Leftovers from a session that didn't shut down cleanly
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stale.json"), "[]").unwrap();
+        let store = ConversationStore::new(1, &dir);
+        assert!(store.all().unwrap().is_empty());
+        drop(store);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
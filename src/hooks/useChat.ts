@@ -179,6 +179,7 @@ export function useChat() {
           if (msg.compacted) m.compacted = true;
           if (msg.sequence_order != null) m.sequenceOrder = msg.sequence_order as number;
           if (msg.title) m.title = String(msg.title);
+          if (msg.reasoning != null) m.reasoning = String(msg.reasoning);
           return m;
         });
         // Assign persisted tool timings to messages by sequential index.
@@ -595,6 +596,8 @@ export function useChat() {
   // ─── Stop ──────────────────────────────────────────────────────────────
 
   const stopGeneration = useCallback(() => {
+    // A message queued behind the stopped reply would otherwise send as soon as it ends
+    setQueuedMessage(null);
     abortGeneration();
   }, [abortGeneration]);
 
@@ -610,7 +613,7 @@ export function useChat() {
 
     const startPolling = async () => {
       try {
-        const status = (await getModelStatus()) as {
+        const status = (await getModelStatus(currentConversationId)) as {
           generating?: boolean;
           active_conversation_id?: string;
           status_message?: string;
@@ -658,7 +661,7 @@ export function useChat() {
         intervalId = setInterval(async () => {
           if (!active) return;
           try {
-            const s = (await getModelStatus()) as {
+            const s = (await getModelStatus(currentConversationId)) as {
               generating?: boolean;
               active_conversation_id?: string;
               status_message?: string;
@@ -680,6 +683,7 @@ export function useChat() {
                   ? { sequenceOrder: msg.sequence_order as number }
                   : {}),
                 ...(msg.title ? { title: String(msg.title) } : {}),
+                ...(msg.reasoning != null ? { reasoning: String(msg.reasoning) } : {}),
                 ...(msg.gen_tok_per_sec != null
                   ? {
                       timings: {
@@ -156,6 +156,17 @@ export function useGenerationStream(deps: UseGenerationStreamDeps) {
               });
             },
 
+            onReasoning: (text) => {
+              if (streamSeqRef.current !== streamSeq) return;
+              setMessages((prev) =>
+                prev.map((msg) =>
+                  msg.id === assistantMessageId
+                    ? { ...msg, reasoning: (msg.reasoning ?? '') + text }
+                    : msg,
+                ),
+              );
+            },
+
             onTimingsUpdate: (timings) => {
               if (streamSeqRef.current !== streamSeq) return;
               // Merge with current timings (can't use updater — setLastTimings takes value only)
@@ -125,6 +125,8 @@ export function useMessageParsing(message: Message, toolTags?: ToolTags): Parsed
 
   const thinkingContent = useMemo(() => {
     if (harmony) return null;
+    // Reasoning split off by the backend (streamed or stored apart from content)
+    if (message.reasoning != null) return message.reasoning.trim();
     // Handle prefill-mode thinking: models like Qwen3 inject <think> as the assistant
     // prefix via chat template, so the stream has thinking content followed by </think>
     // with no opening tag. Wrap it so the regex below can match uniformly.
@@ -143,17 +145,19 @@ export function useMessageParsing(message: Message, toolTags?: ToolTags): Parsed
     // Return empty string (not null) for unclosed thinking — this allows the
     // ThinkingBlock to render immediately when <think> opens, even before content arrives.
     return unclosedMatch ? unclosedMatch[1].trim() || '' : null;
-  }, [message.content, harmony]);
+  }, [message.content, message.reasoning, harmony]);
 
   const isThinkingStreaming = useMemo(() => {
     if (harmony || thinkingContent == null) return false;
+    // Backend-split reasoning is still streaming until answer text arrives
+    if (message.reasoning != null) return message.content.trim() === '';
     // For prefill-mode models: thinking is done once </think> appears in the content.
     const firstClose = message.content.indexOf('</think>');
     const firstOpen = message.content.indexOf('<think>');
     const isPrefillMode = firstClose !== -1 && (firstOpen === -1 || firstOpen > firstClose);
     if (isPrefillMode) return false; // </think> is present, so thinking is complete
     return !/<think>[\s\S]*?<\/think>/.test(message.content);
-  }, [message.content, harmony, thinkingContent]);
+  }, [message.content, message.reasoning, harmony, thinkingContent]);
 
   const segments = useMemo(() => {
     const raw = harmony ? harmony.segments : buildSegments(effectiveContent, toolTags);
@@ -4,7 +4,7 @@ const DEFAULT_MEMORY_USAGE_MB = 512;
 const LOADING_POLL_INTERVAL_MS = 200;
 const STATUS_POLL_INTERVAL_MS = 5000;
 
-import type { SamplerConfig, ToolTags } from '../types';
+import type { ModelMetadata, SamplerConfig, ToolTags } from '../types';
 import {
   getModelStatus,
   loadModel as loadModelCmd,
@@ -26,6 +26,8 @@ interface ModelStatus {
   has_vision?: boolean;
   tool_tags?: ToolTags;
   gpu_layers?: number;
+  effective_backend?: string;
+  recommended_sampling?: ModelMetadata['recommended_params'];
   block_count?: number;
   system_prompt_tokens?: number;
   tool_definitions_tokens?: number;
@@ -140,12 +142,15 @@ export const useModel = () => {
       };
 
       try {
-        // First update the configuration if provided
+        // First update the configuration if provided; the load below applies it
         if (config) {
-          await saveConfig({
-            ...config,
-            model_path: modelPath,
-          });
+          await saveConfig(
+            {
+              ...config,
+              model_path: modelPath,
+            },
+            { skipReload: true },
+          );
         }
 
         // Then load the model (pass gpu_layers and mmproj_path from config if available)
@@ -15,17 +15,31 @@ pub mod chat_mock;
 #[cfg(feature = "mock")]
 use chat_mock::{ChatConfig, ChatEngine, SamplerType};
 
+pub mod conversation_store;
+use conversation_store::ConversationStore;
+
 // Application state
 pub struct AppState {
-    pub conversations: Arc<Mutex<HashMap<String, Vec<Message>>>>,
+    /// Bounded in memory; older conversations spill to disk (see `ConversationStore`).
+    pub conversations: Arc<Mutex<ConversationStore>>,
     pub chat_engine: Arc<Mutex<Option<ChatEngine>>>,
     pub sampler_config: Arc<Mutex<SamplerConfig>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
+        Self::with_conversation_cap(ConversationStore::configured_cap())
+    }
+}
+
+impl AppState {
+    /// Keep at most `cap` conversations in memory; the rest spill to disk.
+    pub fn with_conversation_cap(cap: usize) -> Self {
         Self {
-            conversations: Arc::new(Mutex::new(HashMap::new())),
+            conversations: Arc::new(Mutex::new(ConversationStore::new(
+                cap,
+                ConversationStore::default_spill_dir(),
+            ))),
             chat_engine: Arc::new(Mutex::new(None)),
             sampler_config: Arc::new(Mutex::new(SamplerConfig::default())),
         }
@@ -158,13 +172,11 @@ pub async fn send_message(
     };
 
     // Add to conversation history
-    {
-        let mut conversations = state.conversations.lock().unwrap();
-        let conversation = conversations
-            .entry(conversation_id.clone())
-            .or_default();
-        conversation.push(user_message.clone());
-    }
+    state
+        .conversations
+        .lock()
+        .unwrap()
+        .push(&conversation_id, user_message.clone())?;
 
     // Get current config by cloning to avoid holding locks
     let current_config = {
@@ -218,11 +230,11 @@ pub async fn send_message(
     };
 
     // Add AI response to conversation
-    {
-        let mut conversations = state.conversations.lock().unwrap();
-        let conversation = conversations.get_mut(&conversation_id).unwrap();
-        conversation.push(ai_message.clone());
-    }
+    state
+        .conversations
+        .lock()
+        .unwrap()
+        .push(&conversation_id, ai_message.clone())?;
 
     Ok(ChatResponse {
         message: ai_message,
@@ -233,19 +245,14 @@ pub async fn send_message(
 pub async fn get_conversations(
     state: State<'_, AppState>,
 ) -> Result<HashMap<String, Vec<Message>>, String> {
-    let conversations = state.conversations.lock().unwrap();
-    Ok(conversations.clone())
+    state.conversations.lock().unwrap().all()
 }
 
 pub async fn get_conversation(
     conversation_id: String,
     state: State<'_, AppState>,
 ) -> Result<Vec<Message>, String> {
-    let conversations = state.conversations.lock().unwrap();
-    Ok(conversations
-        .get(&conversation_id)
-        .cloned()
-        .unwrap_or_default())
+    state.conversations.lock().unwrap().get(&conversation_id)
 }
 
 pub async fn get_sampler_config() -> Result<SamplerConfig, String> {
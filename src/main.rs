@@ -145,6 +145,11 @@ fn main() {
                     .expect("Failed to initialize SQLite database"),
             );
             eprintln!("[TAURI] Database initialized at {db_path_str}");
+            match db.recover_interrupted_messages() {
+                Ok(0) => {}
+                Ok(n) => eprintln!("[TAURI] Marked {n} interrupted reply(s) from the last run"),
+                Err(e) => eprintln!("[TAURI] Failed to recover interrupted replies: {e}"),
+            }
 
             // Initialize background process tracking for remote provider tool calls
             let bg_session_id = format!("tauri_{}", std::process::id());
@@ -160,16 +165,27 @@ fn main() {
             );
             eprintln!("[TAURI] Worker process spawned, bridge ready");
 
-            // HTTP API server (agents, conversations, config, …) on 18080 so the
-            // webview's `/api` fetches work in the desktop app — served against the
-            // desktop database and the worker we just spawned (no duplicate worker).
+            // Load the configured model in the background, unless the
+            // frontend already started its own load
+            {
+                let bridge = bridge.clone();
+                tauri::async_runtime::spawn(async move {
+                    bridge.preload_configured_model().await;
+                });
+            }
+
+            // HTTP API server (agents, conversations, config, …) on the configured
+            // port (LLAMA_CHAT_PORT, default 18080) so the webview's `/api` fetches
+            // work in the desktop app — served against the desktop database and the
+            // worker we just spawned (no duplicate worker). Loopback only.
             let worker_pool =
                 web::worker_pool::WorkerPool::new(bridge.clone(), db_path_str.clone(), db.clone());
             {
                 let api_db = db.clone();
                 let worker_pool_for_api = worker_pool.clone();
                 tauri::async_runtime::spawn(async move {
-                    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], 18080));
+                    let port = llama_chat_web::listen_addr::server_port();
+                    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
                     eprintln!("[TAURI] HTTP API server starting on http://{addr}");
                     if let Err(e) = web::http_dispatch::serve(api_db, worker_pool_for_api, addr).await {
                         eprintln!("[TAURI] HTTP API server error: {e}");
@@ -335,6 +351,7 @@ fn main() {
             commands::model::hard_unload,
             commands::model::get_model_info,
             commands::model::get_model_history,
+            commands::model::get_model_history_entries,
             commands::model::add_model_history,
             // Conversations
             commands::conversation::get_conversations,
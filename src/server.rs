@@ -1,7 +1,6 @@
 // Server startup, single-instance enforcement, and main server loop.
 
 use std::convert::Infallible;
-use std::net::SocketAddr;
 use std::sync::Arc;
 
 use hyper::server::conn::AddrStream;
@@ -55,15 +54,47 @@ pub fn enforce_single_instance() {
     //  so we rely on the OS to reclaim the file on next startup instead.)
 }
 
+/// Bind the configured listen address (see `listen_addr`), with a clear error when
+/// the address is invalid or the port is already taken.
+fn bind_listener() -> std::io::Result<std::net::TcpListener> {
+    use std::io::{Error, ErrorKind};
+
+    let addr = llama_chat_web::listen_addr::listen_addr()
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    std::net::TcpListener::bind(addr).map_err(|e| {
+        let message = if e.kind() == ErrorKind::AddrInUse {
+            format!(
+                "Port {} is already in use on {}; stop the other process or set LLAMA_CHAT_PORT",
+                addr.port(),
+                addr.ip()
+            )
+        } else {
+            format!("Cannot listen on {addr}: {e}")
+        };
+        Error::new(e.kind(), message)
+    })
+}
+
 pub async fn server_main() -> std::io::Result<()> {
     enforce_single_instance();
 
+    // Bind before spawning the worker so a taken port fails fast
+    let listener = bind_listener().inspect_err(|e| eprintln!("❌ {e}"))?;
+    let addr = listener.local_addr()?;
+
     // Initialize SQLite database
     let db: SharedDatabase = Arc::new(
         Database::new("assets/llama_chat.db").expect("Failed to initialize SQLite database"),
     );
     println!("📦 SQLite database initialized at assets/llama_chat.db");
 
+    // Replies left streaming by a crash in the last run
+    match db.recover_interrupted_messages() {
+        Ok(0) => {}
+        Ok(n) => println!("🩹 Marked {n} interrupted reply(s) from the last run"),
+        Err(e) => eprintln!("⚠️ Failed to recover interrupted replies: {e}"),
+    }
+
     // Initialize background process tracking so remote provider tool calls can register processes
     let bg_session_id = format!("web_{}", std::process::id());
     llama_chat_command::background::init_background_tracking(db.clone(), bg_session_id);
@@ -88,6 +119,15 @@ pub async fn server_main() -> std::io::Result<()> {
         );
         Arc::new(WorkerBridge::new(pm, db.clone()))
     };
+
+    // Load the configured model in the background; the server answers meanwhile
+    #[cfg(not(feature = "mock"))]
+    {
+        let bridge = worker_bridge.clone();
+        tokio::spawn(async move {
+            bridge.preload_configured_model().await;
+        });
+    }
     let worker_pool = WorkerPool::new(worker_bridge.clone(), "assets/llama_chat.db", db.clone());
 
     // Spawn the agent heartbeat background task
@@ -137,12 +177,14 @@ pub async fn server_main() -> std::io::Result<()> {
             .ok()
             .and_then(|h| h.into_string().ok())
             .unwrap_or_else(|| "llama-chat".to_string());
-        llama_chat_web::remote::mdns::start(18080, &ip_str, &hostname)
+        llama_chat_web::remote::mdns::start(addr.port(), &ip_str, &hostname)
     };
 
     // Start server
-    let addr = SocketAddr::from(([0, 0, 0, 0], 18080));
-    let server = Server::bind(&addr).serve(make_svc);
+    let server = Server::from_tcp(listener)
+        .map_err(std::io::Error::other)?
+        .serve(make_svc)
+        .with_graceful_shutdown(llama_chat_web::shutdown::wait_for_shutdown_signal());
 
     println!("🦙 LLaMA Chat Web Server starting on http://{addr}");
     println!("📡 Worker pool initialized with default worker for model inference");
@@ -164,9 +206,20 @@ pub async fn server_main() -> std::io::Result<()> {
     println!("  GET  /api/browse           - Browse model files");
     println!("  GET  /                     - Web interface");
 
-    server
-        .await
-        .map_err(std::io::Error::other)?;
+    let served = server.await.map_err(std::io::Error::other);
 
-    Ok(())
+    // Stop accepting requests first, then stop workers and flush the DB so nothing
+    // is left orphaned or mid-write.
+    println!("🛑 Shutting down...");
+    #[cfg(not(feature = "mock"))]
+    llama_chat_web::shutdown::shutdown(
+        &worker_pool,
+        &db,
+        llama_chat_web::shutdown::WORKER_SHUTDOWN_GRACE,
+    )
+    .await;
+    #[cfg(feature = "mock")]
+    let _ = db.flush();
+
+    served
 }
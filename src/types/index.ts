@@ -27,6 +27,10 @@ export interface Message {
   parts?: MessagePart[];
   /** LLM-generated short title (≤50 chars). Set on user messages after background gen. */
   title?: string;
+  /** Reasoning-model thinking, kept out of `content` (assistant messages only). */
+  reasoning?: string;
+  /** Reply cut off by a crash mid-generation; `content` is the part saved before it. */
+  interrupted?: boolean;
 }
 
 /** Dynamic tag pair for per-model tag configuration. */
@@ -88,6 +92,7 @@ export interface SamplerConfig {
   model_path?: string;
   system_prompt?: string;
   context_size?: number;
+  default_context_cap?: number; // used when context_size is unset; 0 = full model context
   gpu_layers?: number; // Number of layers to offload to GPU
   // Tool tag overrides (undefined = use auto-detected)
   tool_tag_exec_open?: string;
@@ -124,6 +129,37 @@ export interface SamplerConfig {
   loop_detection_limit?: number;
   // Thinking mode: undefined/null = use model default (enabled when supported), false = disabled
   thinking_mode?: boolean | null;
+  // Tool definitions and tool-call dispatch (default true); false = plain chat assistant
+  tools_enabled?: boolean;
+  // Reasoning block tags; that text streams separately and is not stored in the answer ('' = off)
+  reasoning_tag_open?: string;
+  reasoning_tag_close?: string;
+  // Tools whose calls pause until the user confirms them (e.g. ['write_file', 'execute_command'])
+  require_confirmation_for?: string[];
+  // Generations the worker runs at once on the loaded model (default 1); extra requests queue
+  max_parallel_generations?: number;
+  // Named sampler preset ('precise', 'balanced', 'creative', 'deterministic'); null = stored values
+  sampler_preset?: string | null;
+  // Extra presets defined in config; one named like a built-in replaces it
+  sampler_presets?: { name: string; description?: string; params: Record<string, unknown> }[];
+  // Milliseconds between saves of a streaming reply, for crash recovery (default 5000; 0 = every sync)
+  autosave_interval_ms?: number;
+  // Run execute_python in a per-conversation directory (python_sessions/<id>), optionally with a virtualenv's interpreter
+  python_isolated?: boolean;
+  python_venv?: string | null;
+  // Tool calls per user message before the model is told to wrap up; one more stops with 'tool_limit' (default 200; 0 = no limit)
+  max_tool_iterations?: number;
+  // Reuse the earlier result when the model repeats an identical tool call within a turn (default true)
+  dedupe_tool_calls?: boolean;
+  // Largest `n` (completions per request) accepted (default 4)
+  max_completions?: number;
+  // Most bytes read_file returns inline (default 102400)
+  read_file_max_bytes?: number;
+  // Most entries list_directory shows before an "...and N more" footer (default 500)
+  list_directory_max_entries?: number;
+  // Model loaded and warmed up when the server starts (null = start unloaded)
+  preload_model?: string | null;
+  preload_gpu_layers?: number | null;
 }
 
 /** A named, reusable model+config preset. Conversations reference one agent by ID. */
@@ -51,6 +51,7 @@ export interface StreamingCallbacks {
   onStatus?: (message: string) => void;
   onToolTiming?: (name: string, durationMs: number) => void;
   onApprovalRequired?: (request: ApprovalRequest) => void;
+  onReasoning?: (text: string) => void;
 }
 
 export interface ChatTransport {
@@ -103,6 +104,7 @@ function handleStreamMessage(
     onStatus?: (message: string) => void;
     onToolTiming?: (name: string, durationMs: number) => void;
     onApprovalRequired?: (request: ApprovalRequest) => void;
+    onReasoning?: (text: string) => void;
   },
   settle: (error?: Error) => void,
   markAborted: () => void,
@@ -120,6 +122,11 @@ function handleStreamMessage(
       return;
     }
 
+    if (message.type === 'reasoning') {
+      callbacks.onReasoning?.(message.text);
+      return;
+    }
+
     if (message.type === 'token') {
       if (message.tokens_used !== undefined) state.lastTokensUsed = message.tokens_used;
       if (message.max_tokens !== undefined) state.lastMaxTokens = message.max_tokens;
@@ -175,6 +182,17 @@ function handleStreamMessage(
       return;
     }
 
+    // require_confirmation_for tools share the approval dialog (same pending-approval id)
+    if (message.type === 'confirmation_required') {
+      callbacks.onApprovalRequired?.({
+        id: message.id as string,
+        tool: message.tool as string,
+        args: message.args as Record<string, unknown>,
+        reason: `${message.tool as string} requires confirmation before it runs`,
+      });
+      return;
+    }
+
     if (message.type === 'abort') {
       markAborted();
     }
@@ -191,7 +209,15 @@ function handleStreamMessage(
 
 function streamViaWebSocket(
   request: ChatRequest,
-  { onToken, onComplete, onError, onStatus, onToolTiming, onApprovalRequired }: StreamingCallbacks,
+  {
+    onToken,
+    onComplete,
+    onError,
+    onStatus,
+    onToolTiming,
+    onApprovalRequired,
+    onReasoning,
+  }: StreamingCallbacks,
   abortSignal?: AbortSignal,
 ): Promise<void> {
   const safeOnToken = onToken ?? (() => {});
@@ -221,7 +247,12 @@ function streamViaWebSocket(
     const markAborted = () => {
       state.wasAborted = true;
       if (!state.isCompleted) {
-        fetch('/api/chat/cancel', { method: 'POST' }).catch(() => {});
+        // Stop in-band on the stream being watched; fall back to the cancel route
+        if (currentWs?.readyState === WebSocket.OPEN) {
+          currentWs.send(JSON.stringify({ type: 'stop' }));
+        } else {
+          fetch('/api/chat/cancel', { method: 'POST' }).catch(() => {});
+        }
       }
       settle(new Error('Request aborted'));
     };
@@ -287,6 +318,7 @@ function streamViaWebSocket(
             onStatus,
             onToolTiming,
             onApprovalRequired,
+            onReasoning,
           },
           settle,
           markAborted,
@@ -35,6 +35,8 @@ export interface GenerationCallbacks {
   onToolTiming?: (name: string, durationMs: number) => void;
   /** A dangerous tool call is waiting for human approval */
   onApprovalRequired?: (request: ApprovalRequest) => void;
+  /** Thinking text of a reasoning model, streamed apart from the answer */
+  onReasoning?: (text: string) => void;
 }
 
 export interface GenerationResult {
@@ -113,6 +115,9 @@ export class LocalGenerationStream implements GenerationStream {
         onApprovalRequired: (approvalReq) => {
           callbacks.onApprovalRequired?.(approvalReq);
         },
+        onReasoning: (text) => {
+          callbacks.onReasoning?.(text);
+        },
       },
       signal,
     );
@@ -29,6 +29,8 @@ interface ModelResponse {
   success: boolean;
   message: string;
   status?: ModelStatus;
+  /** Whether the load reread the weights or only rebuilt the context. */
+  load_outcome?: 'full_reload' | 'context_rebuild';
 }
 
 interface ConversationFile {
@@ -147,15 +149,23 @@ export async function getConfig(): Promise<SamplerConfig> {
   return fetchJson<SamplerConfig>('/api/config');
 }
 
-export async function saveConfig(config: SamplerConfig): Promise<void> {
+/**
+ * Save the app settings. Load-affecting changes reload the resident model,
+ * unless `skipReload` is set because the caller loads the model itself.
+ */
+export async function saveConfig(
+  config: SamplerConfig,
+  options: { skipReload?: boolean } = {},
+): Promise<void> {
+  const skipReload = options.skipReload ?? false;
   if (isTauriEnv()) {
-    await invokeCmd('save_config', { config });
+    await invokeCmd('save_config', { config, gpuLayers: config.gpu_layers, skipReload });
     return;
   }
   const response = await fetch('/api/config', {
     method: 'POST',
     headers: { 'Content-Type': 'application/json' },
-    body: JSON.stringify(config),
+    body: JSON.stringify({ ...config, skip_reload: skipReload }),
   });
   if (!response.ok) throw new Error('Failed to save configuration');
 }
@@ -196,11 +206,13 @@ export async function clearAppErrors(): Promise<{ success: boolean; deleted: num
 
 // ─── Model ────────────────────────────────────────────────────────────
 
-export async function getModelStatus(): Promise<ModelStatus> {
+/** `conversationId` scopes `last_finish_reason` to that conversation's generation. */
+export async function getModelStatus(conversationId?: string | null): Promise<ModelStatus> {
   if (isTauriEnv()) {
-    return invokeCmd<ModelStatus>('get_model_status');
+    return invokeCmd<ModelStatus>('get_model_status', { conversationId: conversationId ?? null });
   }
-  return fetchJson<ModelStatus>('/api/model/status');
+  const query = conversationId ? `?conversation_id=${encodeURIComponent(conversationId)}` : '';
+  return fetchJson<ModelStatus>(`/api/model/status${query}`);
 }
 
 export async function loadModel(
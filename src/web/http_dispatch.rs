@@ -7,6 +7,7 @@
 use std::convert::Infallible;
 
 use hyper::{Body, Method, Request, Response, StatusCode};
+use llama_chat_web::body_limit::{self, BodyLimits};
 use llama_chat_web::remote;
 
 #[cfg(not(feature = "mock"))]
@@ -68,6 +69,15 @@ pub async fn dispatch(
         }
     }
 
+    // Cap the body before any handler buffers it
+    let req = match BodyLimits::configured().for_request(&method, &path) {
+        Some(limit) => match body_limit::limit_body(req, limit).await {
+            Ok(req) => req,
+            Err(response) => return Ok(response),
+        },
+        None => req,
+    };
+
     #[cfg(not(feature = "mock"))]
     let pool = worker_pool.expect("Worker pool missing");
 
@@ -87,6 +97,7 @@ pub async fn dispatch(
 
         // App info & API docs
         (&Method::GET, "/api/info") => super::routes::system::handle_app_info().await?,
+        (&Method::GET, "/api/capabilities") => super::routes::system::handle_get_capabilities().await?,
         (&Method::GET, "/api/docs") => super::routes::system::handle_api_docs().await?,
 
         // System monitoring
@@ -126,6 +137,11 @@ pub async fn dispatch(
             super::routes::frontend_logs::handle_post_frontend_logs(req).await?
         }
 
+        // Live server/worker log tail (SSE)
+        (&Method::GET, "/api/logs/stream") => {
+            super::routes::log_stream::handle_get_log_stream(req).await?
+        }
+
         // App-level frontend/runtime errors
         (&Method::POST, "/api/errors") => {
             super::routes::app_errors::handle_record_app_error(req, db.clone()).await?
@@ -165,7 +181,7 @@ pub async fn dispatch(
         }
 
         (&Method::GET, "/ws/status") => {
-            super::routes::status::handle_status_websocket(req, bridge.clone()).await?
+            super::routes::status::handle_status_websocket(req, bridge.clone(), db.clone()).await?
         }
 
         (&Method::GET, "/ws/terminal") => {
@@ -181,6 +197,10 @@ pub async fn dispatch(
             super::routes::config::handle_post_config(req, bridge.clone(), db.clone()).await?
         }
 
+        (&Method::GET, "/api/sampler/presets") => {
+            super::routes::config::handle_get_sampler_presets(db.clone()).await?
+        }
+
         // Provider API keys
         (&Method::GET, "/api/config/provider-keys") => {
             super::routes::config::handle_get_provider_keys(db.clone()).await?
@@ -237,12 +257,11 @@ pub async fn dispatch(
             super::routes::conversation::handle_truncate_conversation(req, path, db.clone()).await?
         }
 
-        // Conversation compact (manual compaction from UI)
-        (&Method::POST, path)
-            if path.starts_with("/api/conversations/") && path.ends_with("/compact") =>
-        {
-            let id = &path["/api/conversations/".len()..path.len() - "/compact".len()];
-            super::routes::conversation::handle_compact_conversation(id, pool.clone(), db.clone())
+        // Conversation compact (manual compaction from UI, or the oldest N turns).
+        // The plural path is kept as an alias for older clients.
+        (&Method::POST, path) if compact_conversation_id(path).is_some() => {
+            let id = compact_conversation_id(path).unwrap_or_default();
+            super::routes::conversation::handle_compact_conversation(req, id, pool.clone(), db.clone())
                 .await?
         }
 
@@ -262,9 +281,14 @@ pub async fn dispatch(
 
         // Conversation rename (PATCH must be before DELETE catch-all)
         (&Method::PATCH, path)
-            if path.starts_with("/api/conversations/") && path.ends_with("/title") =>
+            if (path.starts_with("/api/conversations/")
+                || path.starts_with("/api/conversation/"))
+                && path.ends_with("/title") =>
         {
-            let id = &path["/api/conversations/".len()..path.len() - "/title".len()];
+            let id = path
+                .trim_start_matches("/api/conversations/")
+                .trim_start_matches("/api/conversation/")
+                .trim_end_matches("/title");
             super::routes::conversation::handle_rename_conversation(req, id, db.clone()).await?
         }
 
@@ -276,6 +300,61 @@ pub async fn dispatch(
             super::routes::conversation::handle_export_conversation(&req, id, db.clone()).await?
         }
 
+        // Debug transcript (must be before generic /api/conversation/{id})
+        (&Method::GET, path)
+            if path.starts_with("/api/conversation/") && path.ends_with("/transcript") =>
+        {
+            let id = &path["/api/conversation/".len()..path.len() - "/transcript".len()];
+            super::routes::conversation::handle_get_transcript(id, db.clone()).await?
+        }
+
+        // Structured tool-call log (must be before generic /api/conversation/{id})
+        (&Method::GET, path)
+            if path.starts_with("/api/conversation/") && path.ends_with("/tools") =>
+        {
+            let id = &path["/api/conversation/".len()..path.len() - "/tools".len()];
+            super::routes::conversation::handle_get_tool_calls(id, db.clone()).await?
+        }
+
+        // Conversation flattened to one prompt (must be before generic /api/conversation/{id})
+        (&Method::GET, path)
+            if path.starts_with("/api/conversation/") && path.ends_with("/flatten") =>
+        {
+            let id = &path["/api/conversation/".len()..path.len() - "/flatten".len()];
+            super::routes::conversation::handle_get_flattened_conversation(
+                &req,
+                id,
+                pool.clone(),
+                db.clone(),
+            )
+            .await?
+        }
+
+        // Find within one conversation (must be before generic /api/conversation/{id})
+        (&Method::GET, path)
+            if path.starts_with("/api/conversation/") && path.ends_with("/find") =>
+        {
+            let id = &path["/api/conversation/".len()..path.len() - "/find".len()];
+            super::routes::conversation::handle_find_in_conversation(&req, id, db.clone()).await?
+        }
+
+        // Re-run the last turn with optional sampler overrides
+        (&Method::POST, path)
+            if path.starts_with("/api/conversation/") && path.ends_with("/regenerate") =>
+        {
+            let id = &path["/api/conversation/".len()..path.len() - "/regenerate".len()];
+            super::routes::chat::handle_post_conversation_regenerate(req, pool.clone(), db.clone(), id)
+                .await?
+        }
+
+        // Cancel one conversation's generation (must be before generic /api/conversation/{id})
+        (&Method::POST, path)
+            if path.starts_with("/api/conversation/") && path.ends_with("/cancel") =>
+        {
+            let id = &path["/api/conversation/".len()..path.len() - "/cancel".len()];
+            super::routes::chat::handle_post_conversation_cancel(pool.clone(), id).await?
+        }
+
         // Conversation endpoints
         (&Method::POST, path)
             if path.starts_with("/api/conversation/") && path.ends_with("/queue") =>
@@ -299,6 +378,10 @@ pub async fn dispatch(
                 .await?
         }
 
+        (&Method::POST, "/api/conversations/import") => {
+            super::routes::conversation::handle_import_conversation(req, db.clone()).await?
+        }
+
         (&Method::PATCH, path)
             if path.starts_with("/api/conversations/") && path.ends_with("/worker") =>
         {
@@ -312,12 +395,18 @@ pub async fn dispatch(
             .await?
         }
 
+        (&Method::POST, "/api/conversations/clear") => {
+            super::routes::conversation::handle_clear_conversations(db.clone()).await?
+        }
+
         // Batch delete (must be before single delete /api/conversations/{id})
         (&Method::DELETE, "/api/conversations/batch") => {
             super::routes::conversation::handle_batch_delete_conversations(req, db.clone()).await?
         }
 
-        (&Method::DELETE, path) if path.starts_with("/api/conversations/") => {
+        (&Method::DELETE, path)
+            if path.starts_with("/api/conversations/") || path.starts_with("/api/conversation/") =>
+        {
             super::routes::conversation::handle_delete_conversation(path, bridge.clone(), db.clone())
                 .await?
         }
@@ -382,17 +471,41 @@ pub async fn dispatch(
 
         // Model endpoints
         (&Method::GET, "/api/model/info") => {
-            super::routes::model::handle_get_model_info(req, bridge.clone()).await?
+            super::routes::model::handle_get_model_info(req, db.clone(), bridge.clone()).await?
+        }
+
+        (&Method::GET, "/api/model/validate") => {
+            super::routes::model::handle_get_model_validate(req, db.clone(), bridge.clone()).await?
+        }
+
+        (&Method::GET, "/api/model/recommend") => {
+            super::routes::model::handle_get_model_recommend(req, db.clone(), bridge.clone()).await?
+        }
+
+        (&Method::GET, "/api/model/templates") => {
+            super::routes::model::handle_get_model_templates(bridge.clone()).await?
+        }
+
+        (&Method::POST, "/api/model/template-override") => {
+            super::routes::model::handle_post_model_template_override(req, bridge.clone()).await?
+        }
+
+        (&Method::GET, "/api/model/selftest") => {
+            super::routes::model::handle_get_model_selftest(bridge.clone()).await?
         }
 
         (&Method::GET, "/api/model/status") => {
-            super::routes::model::handle_get_model_status(pool.clone(), db.clone()).await?
+            super::routes::model::handle_get_model_status(req, pool.clone(), db.clone()).await?
         }
 
         (&Method::GET, "/api/model/history") => {
             super::routes::model::handle_get_model_history(bridge.clone(), db.clone()).await?
         }
 
+        (&Method::GET, "/api/model/history/entries") => {
+            super::routes::model::handle_get_model_history_entries(db.clone()).await?
+        }
+
         (&Method::POST, "/api/model/history") => {
             super::routes::model::handle_post_model_history(req, bridge.clone(), db.clone()).await?
         }
@@ -591,7 +704,7 @@ pub async fn dispatch(
 
         // Tool execution
         (&Method::GET, "/api/tools/available") => {
-            super::routes::tools::handle_get_available_tools(bridge.clone()).await?
+            super::routes::tools::handle_get_available_tools(bridge.clone(), db.clone()).await?
         }
         (&Method::POST, "/api/tools/execute") => {
             super::routes::tools::handle_post_tools_execute(req, bridge.clone()).await?
@@ -690,13 +803,17 @@ pub async fn dispatch(
             let id = &path["/api/approval/".len()..path.len() - "/reject".len()];
             super::routes::approval::handle_reject(id, db.clone()).await?
         }
+        // Confirmation for require_confirmation_for tools (write_file, execute_command, ...)
+        (&Method::POST, "/api/generation/confirm") => {
+            super::routes::approval::handle_generation_confirm(req, db.clone()).await?
+        }
 
         // OpenAI-compatible server endpoints (for clients like openclaw)
         (&Method::GET, "/v1/models") => {
             super::routes::openai_compat_server::handle_get_models(bridge.clone()).await?
         }
         (&Method::POST, "/v1/chat/completions") => {
-            super::routes::openai_compat_server::handle_post_chat_completions(req, bridge.clone())
+            super::routes::openai_compat_server::handle_post_chat_completions(req, bridge.clone(), db.clone())
                 .await?
         }
 
@@ -725,6 +842,15 @@ pub async fn dispatch(
     Ok(response)
 }
 
+/// Conversation id of `POST /api/conversation/{id}/compact`, or of its
+/// `/api/conversations/` alias.
+fn compact_conversation_id(path: &str) -> Option<&str> {
+    let rest = path
+        .strip_prefix("/api/conversation/")
+        .or_else(|| path.strip_prefix("/api/conversations/"))?;
+    rest.strip_suffix("/compact").filter(|id| !id.is_empty() && !id.contains('/'))
+}
+
 /// Run the HTTP API server on `addr`, reusing an existing worker pool + database.
 ///
 /// Used by the desktop app so the webview's `/api` fetches are served locally
@@ -757,3 +883,17 @@ pub async fn serve(
         .map_err(std::io::Error::other)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_routes_singular_and_plural_paths() {
+        assert_eq!(compact_conversation_id("/api/conversation/chat_1/compact"), Some("chat_1"));
+        assert_eq!(compact_conversation_id("/api/conversations/chat_1/compact"), Some("chat_1"));
+        assert_eq!(compact_conversation_id("/api/conversation//compact"), None);
+        assert_eq!(compact_conversation_id("/api/conversation/chat_1/truncate"), None);
+        assert_eq!(compact_conversation_id("/api/conversation/chat_1/events/compact"), None);
+    }
+}
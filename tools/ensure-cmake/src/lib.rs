@@ -57,6 +57,12 @@ pub fn ensure_cmake(cache_root: Option<&Path>) -> Result<CmakeResult, String> {
     })
 }
 
+/// Directory the cmake binary lives in once cached under `cache_root`
+/// (the same root passed to `ensure_cmake`).
+pub fn cached_bin_dir(cache_root: &Path) -> PathBuf {
+    cached_cmake_bin_dir(&cache_root.join("cmake"))
+}
+
 fn auto_cmake_cache_dir() -> PathBuf {
     if let Ok(exe) = env::current_exe() {
         let mut dir = exe.as_path();
@@ -180,7 +186,25 @@ fn download_file(url: &str, dest: &Path) -> Result<(), String> {
     Ok(())
 }
 
-#[cfg(windows)]
+/// Join an archive entry path onto `dest`, refusing anything that could land
+/// outside it (absolute paths, drive prefixes, `..` components).
+fn contained_path(dest: &Path, entry_path: &Path) -> Result<PathBuf, String> {
+    use std::path::Component;
+    for component in entry_path.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "Refusing to extract '{}': path escapes {}",
+                    entry_path.display(),
+                    dest.display()
+                ))
+            }
+        }
+    }
+    Ok(dest.join(entry_path))
+}
+
 fn extract_zip(archive: &Path, dest: &Path) -> Result<(), String> {
     let file = fs::File::open(archive)
         .map_err(|e| format!("Failed to open {}: {e}", archive.display()))?;
@@ -191,7 +215,16 @@ fn extract_zip(archive: &Path, dest: &Path) -> Result<(), String> {
             .by_index(i)
             .map_err(|e| format!("Failed to read zip entry {i}: {e}"))?;
 
-        let out_path = dest.join(entry.mangled_name());
+        // enclosed_name() is None for absolute or `..` names; mangled_name() would
+        // silently rewrite them, so reject explicitly instead
+        let entry_path = entry.enclosed_name().ok_or_else(|| {
+            format!(
+                "Refusing to extract '{}': path escapes {}",
+                entry.name(),
+                dest.display()
+            )
+        })?;
+        let out_path = contained_path(dest, &entry_path)?;
         if entry.is_dir() {
             fs::create_dir_all(&out_path).map_err(|e| format!("mkdir failed: {e}"))?;
         } else {
@@ -207,23 +240,127 @@ fn extract_zip(archive: &Path, dest: &Path) -> Result<(), String> {
     Ok(())
 }
 
-#[cfg(not(windows))]
-fn extract_zip(_archive: &Path, _dest: &Path) -> Result<(), String> {
-    Err("zip extraction not expected on this platform".to_string())
-}
-
-#[cfg(not(windows))]
 fn extract_tar_gz(archive: &Path, dest: &Path) -> Result<(), String> {
     let file = fs::File::open(archive)
         .map_err(|e| format!("Failed to open {}: {e}", archive.display()))?;
     let gz = flate2::read::GzDecoder::new(file);
     let mut tar = tar::Archive::new(gz);
-    tar.unpack(dest)
-        .map_err(|e| format!("Failed to extract tar.gz: {e}"))?;
+
+    let entries = tar
+        .entries()
+        .map_err(|e| format!("Failed to read tar.gz: {e}"))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Failed to read tar entry: {e}"))?;
+        let entry_path = entry
+            .path()
+            .map_err(|e| format!("Invalid tar entry path: {e}"))?
+            .into_owned();
+        contained_path(dest, &entry_path)?;
+        // unpack_in also refuses escapes (e.g. via symlinked parents) and returns false
+        let unpacked = entry
+            .unpack_in(dest)
+            .map_err(|e| format!("Failed to extract {}: {e}", entry_path.display()))?;
+        if !unpacked {
+            return Err(format!(
+                "Refusing to extract '{}': path escapes {}",
+                entry_path.display(),
+                dest.display()
+            ));
+        }
+    }
     Ok(())
 }
 
-#[cfg(windows)]
-fn extract_tar_gz(_archive: &Path, _dest: &Path) -> Result<(), String> {
-    Err("tar.gz extraction not expected on this platform".to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("ensure-cmake-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("dest")).unwrap();
+        dir
+    }
+
+    /// Build a tar.gz whose single entry has the raw name `name` (bypassing the
+    /// tar builder's own path validation, as a malicious archive would).
+    fn write_tar_gz(path: &Path, name: &str) {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+
+        let gz = flate2::write::GzEncoder::new(
+            fs::File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(gz);
+        builder.append(&header, &b"evil"[..]).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn write_zip(path: &Path, name: &str) {
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file(name, options).unwrap();
+        zip.write_all(b"evil").unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_tar_with_parent_dir_entry_is_refused() {
+        let dir = temp_dir("tar-slip");
+        let archive = dir.join("evil.tar.gz");
+        write_tar_gz(&archive, "../evil");
+
+        let err = extract_tar_gz(&archive, &dir.join("dest")).unwrap_err();
+        assert!(err.contains("escapes"), "{err}");
+        assert!(!dir.join("evil").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_zip_with_parent_dir_entry_is_refused() {
+        let dir = temp_dir("zip-slip");
+        let archive = dir.join("evil.zip");
+        write_zip(&archive, "../evil");
+
+        let err = extract_zip(&archive, &dir.join("dest")).unwrap_err();
+        assert!(err.contains("escapes"), "{err}");
+        assert!(!dir.join("evil").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_well_formed_archives_extract() {
+        let dir = temp_dir("archive-ok");
+        let tar_path = dir.join("ok.tar.gz");
+        write_tar_gz(&tar_path, "cmake/bin/cmake");
+        extract_tar_gz(&tar_path, &dir.join("dest")).unwrap();
+        assert!(dir.join("dest/cmake/bin/cmake").exists());
+
+        let zip_path = dir.join("ok.zip");
+        write_zip(&zip_path, "cmake/bin/cmake.exe");
+        extract_zip(&zip_path, &dir.join("dest")).unwrap();
+        assert!(dir.join("dest/cmake/bin/cmake.exe").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_contained_path_rejects_absolute() {
+        let dest = Path::new("/tmp/dest");
+        assert!(contained_path(dest, Path::new("/etc/passwd")).is_err());
+        assert!(contained_path(dest, Path::new("a/../../b")).is_err());
+        assert_eq!(
+            contained_path(dest, Path::new("./a/b")).unwrap(),
+            dest.join("./a/b")
+        );
+    }
 }
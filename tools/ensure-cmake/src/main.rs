@@ -2,14 +2,20 @@
 //!
 //! Usage:
 //!   cargo run --manifest-path tools/ensure-cmake/Cargo.toml -- cargo build --features cuda
+//!
+//! With no arguments, prints the resolved cmake bin dir. All resolution (and its
+//! stderr progress messages) lives in the library; `ENSURE_CMAKE_CACHE_ROOT`
+//! overrides the cache location.
 
 use std::env;
+use std::path::PathBuf;
 use std::process::{Command, ExitCode};
 
 fn main() -> ExitCode {
     let args: Vec<String> = env::args().skip(1).collect();
 
-    let cmake = match ensure_cmake::ensure_cmake(None) {
+    let cache_root = env::var_os("ENSURE_CMAKE_CACHE_ROOT").map(PathBuf::from);
+    let cmake = match ensure_cmake::ensure_cmake(cache_root.as_deref()) {
         Ok(r) => r,
         Err(e) => {
             eprintln!("ERROR: {e}");
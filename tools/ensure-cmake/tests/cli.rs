@@ -0,0 +1,31 @@
+//! Binary-level tests: the CLI must resolve cmake through the library.
+
+#[cfg(unix)]
+#[test]
+fn no_args_prints_library_resolved_bin_dir() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+
+    let cache_root = std::env::temp_dir().join(format!("ensure-cmake-cli-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache_root);
+
+    // Fake a cached cmake where the library expects it, so no download happens
+    let bin_dir = ensure_cmake::cached_bin_dir(&cache_root);
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    let cmake = bin_dir.join("cmake");
+    std::fs::write(&cmake, "#!/bin/sh\necho 'cmake version 3.31.6'\n").unwrap();
+    std::fs::set_permissions(&cmake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ensure-cmake"))
+        .env("ENSURE_CMAKE_CACHE_ROOT", &cache_root)
+        .output()
+        .unwrap();
+    let _ = std::fs::remove_dir_all(&cache_root);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), bin_dir.display().to_string());
+    // Progress messaging comes from the library, on stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("CMake found in cache"), "{stderr}");
+}
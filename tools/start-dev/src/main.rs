@@ -4,6 +4,9 @@ use std::path::{Path, PathBuf};
 use std::process::{self, Child, Command};
 use sysinfo::System;
 
+const DEFAULT_BACKEND_PORT: u16 = 18080;
+const FRONTEND_PORT: u16 = 14000;
+
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
     let build = args.iter().any(|a| a == "--build" || a == "-b");
@@ -51,7 +54,7 @@ fn main() {
     // 1. Kill existing processes
     println!("\x1b[36m[1/3] Cleaning up old processes...\x1b[0m");
     kill_by_name("llama_chat_web");
-    kill_port_holders(14000);
+    kill_port_holders(FRONTEND_PORT);
     std::thread::sleep(std::time::Duration::from_secs(1));
 
     // 2. Optionally rebuild
@@ -113,13 +116,14 @@ fn main() {
             process::exit(1);
         });
 
-    // Wait for backend to be ready
-    wait_for_port(18080, 15);
+    // Wait for backend to be ready on its configured address
+    let backend_addr = backend_connect_addr();
+    wait_for_addr(&backend_addr, 15);
 
     let npx = if cfg!(windows) { "npx.cmd" } else { "npx" };
     let frontend = Command::new(npx)
         .current_dir(&project_root)
-        .args(["vite", "--host", "--port", "14000"])
+        .args(["vite", "--host", "--port", &FRONTEND_PORT.to_string()])
         .spawn()
         .unwrap_or_else(|e| {
             eprintln!("\x1b[31mFailed to start frontend: {e}\x1b[0m");
@@ -127,12 +131,12 @@ fn main() {
         });
 
     // Wait for frontend to be ready
-    wait_for_port(14000, 10);
+    wait_for_addr(&format!("127.0.0.1:{FRONTEND_PORT}"), 10);
 
     println!();
     println!("\x1b[32mReady!\x1b[0m");
-    println!("  Backend:  http://localhost:18080");
-    println!("  Frontend: http://localhost:14000");
+    println!("  Backend:  http://{backend_addr}");
+    println!("  Frontend: http://localhost:{FRONTEND_PORT}");
     println!();
     println!("\x1b[90mPress Ctrl+C to stop both.\x1b[0m");
 
@@ -248,17 +252,34 @@ fn find_pids_on_port(port: u16) -> Vec<u32> {
     pids
 }
 
-fn wait_for_port(port: u16, timeout_secs: u64) {
+/// Where to reach the backend, from the same `LLAMA_CHAT_BIND_ADDR` /
+/// `LLAMA_CHAT_PORT` the server binds with. A wildcard bind is reached via loopback.
+fn backend_connect_addr() -> String {
+    let port = env::var("LLAMA_CHAT_PORT")
+        .ok()
+        .and_then(|p| p.trim().parse::<u16>().ok())
+        .filter(|&p| p != 0)
+        .unwrap_or(DEFAULT_BACKEND_PORT);
+    let host = env::var("LLAMA_CHAT_BIND_ADDR").unwrap_or_default();
+    match host.trim().trim_matches(|c| c == '[' || c == ']') {
+        "" | "0.0.0.0" | "localhost" => format!("127.0.0.1:{port}"),
+        "::" => format!("[::1]:{port}"),
+        ip if ip.contains(':') => format!("[{ip}]:{port}"),
+        ip => format!("{ip}:{port}"),
+    }
+}
+
+fn wait_for_addr(addr: &str, timeout_secs: u64) {
     let start = std::time::Instant::now();
     let timeout = std::time::Duration::from_secs(timeout_secs);
 
     while start.elapsed() < timeout {
-        if TcpStream::connect(format!("127.0.0.1:{port}")).is_ok() {
+        if TcpStream::connect(addr).is_ok() {
             return;
         }
         std::thread::sleep(std::time::Duration::from_millis(500));
     }
-    eprintln!("\x1b[33mWarning: port {port} not ready after {timeout_secs}s\x1b[0m");
+    eprintln!("\x1b[33mWarning: {addr} not ready after {timeout_secs}s\x1b[0m");
 }
 
 fn wait_and_cleanup(mut backend: Child, mut frontend: Child) {
@@ -3,6 +3,9 @@ import react from '@vitejs/plugin-react';
 // import checker from 'vite-plugin-checker'; // temporarily disabled
 import path from 'path';
 
+// Backend port; matches the server's LLAMA_CHAT_PORT override
+const backendTarget = `http://localhost:${process.env.LLAMA_CHAT_PORT || '18080'}`;
+
 export default defineConfig({
   plugins: [
     react(),
@@ -48,11 +51,11 @@ export default defineConfig({
     },
     proxy: {
       '/api': {
-        target: 'http://localhost:18080',
+        target: backendTarget,
         changeOrigin: true,
       },
       '/ws': {
-        target: 'http://localhost:18080',
+        target: backendTarget,
         changeOrigin: true,
         ws: true, // Enable WebSocket proxying
         configure: (proxy) => {